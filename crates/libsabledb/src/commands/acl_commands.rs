@@ -0,0 +1,315 @@
+#[allow(unused_imports)]
+use crate::{
+    check_args_count, command_arg_at, command_arg_at_as_str,
+    commands::{commands_manager, HandleCommandResult, Strings, ValkeyCommandFlags},
+    server::{Acl, ClientState, ACL_CATEGORIES, DEFAULT_USER},
+    utils::RespBuilderV2,
+    BytesMutUtils, SableError, ValkeyCommand, ValkeyCommandName,
+};
+
+use bytes::BytesMut;
+use std::rc::Rc;
+use std::str::FromStr;
+use tokio::io::AsyncWriteExt;
+
+pub struct AclCommands {}
+
+impl AclCommands {
+    pub async fn handle_command(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        _tx: &mut (impl AsyncWriteExt + std::marker::Unpin),
+    ) -> Result<HandleCommandResult, SableError> {
+        let mut response_buffer = BytesMut::with_capacity(256);
+        match command.metadata().name() {
+            ValkeyCommandName::Auth => {
+                Self::auth(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Acl => {
+                Self::acl(client_state, command, &mut response_buffer).await?;
+            }
+            _ => {
+                return Err(SableError::InvalidArgument(format!(
+                    "Non ACL command {}",
+                    command.main_command()
+                )));
+            }
+        }
+        Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
+    }
+
+    /// `AUTH [username] password`
+    async fn auth(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let server_state = client_state.server_inner_state();
+        let acl = server_state.acl();
+
+        let (username, password) = match command.arg_count() {
+            2 => {
+                // `AUTH password` authenticates the default user
+                let nopass = acl
+                    .user(DEFAULT_USER)
+                    .map(|user| user.is_nopass())
+                    .unwrap_or_default();
+                if nopass {
+                    builder.error_string(
+                        response_buffer,
+                        "ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?",
+                    );
+                    return Ok(());
+                }
+                (DEFAULT_USER.to_string(), command_arg_at!(command, 1))
+            }
+            3 => (
+                BytesMutUtils::to_string(command_arg_at!(command, 1)),
+                command_arg_at!(command, 2),
+            ),
+            _ => {
+                builder.error_string(response_buffer, Strings::SYNTAX_ERROR);
+                return Ok(());
+            }
+        };
+
+        if acl.authenticate(&username, password).is_none() {
+            builder.error_string(response_buffer, Strings::WRONGPASS);
+            return Ok(());
+        }
+
+        client_state.set_user(&username);
+        builder.ok(response_buffer);
+        Ok(())
+    }
+
+    /// `ACL SETUSER username [rule [rule ...]]`
+    /// `ACL GETUSER username`
+    /// `ACL DELUSER username [username ...]`
+    /// `ACL LIST`
+    /// `ACL WHOAMI`
+    /// `ACL CAT [category]`
+    /// `ACL SAVE`
+    /// `ACL LOAD`
+    async fn acl(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let sub_command = command_arg_at_as_str!(command, 1);
+        let builder = RespBuilderV2::default();
+        let server_state = client_state.server_inner_state();
+        let acl = server_state.acl();
+
+        match sub_command.as_str() {
+            "setuser" => {
+                check_args_count!(command, 3, response_buffer);
+                let username = BytesMutUtils::to_string(command_arg_at!(command, 2));
+                let rules: Vec<String> = command
+                    .args_vec()
+                    .iter()
+                    .skip(3)
+                    .map(|arg| BytesMutUtils::to_string(arg))
+                    .collect();
+                match acl.set_user(&username, &rules) {
+                    Ok(()) => builder.ok(response_buffer),
+                    Err(msg) => builder.error_string(response_buffer, &msg),
+                }
+            }
+            "getuser" => {
+                check_args_count!(command, 3, response_buffer);
+                let username = BytesMutUtils::to_string(command_arg_at!(command, 2));
+                let Some(user) = acl.user(&username) else {
+                    builder.null_string(response_buffer);
+                    return Ok(());
+                };
+
+                builder.add_array_len(response_buffer, 12);
+                builder.add_bulk_string(response_buffer, b"flags");
+                builder.add_strings(response_buffer, &user.flags());
+                builder.add_bulk_string(response_buffer, b"passwords");
+                builder.add_array_len(response_buffer, user.password_hashes().len());
+                for hash in user.password_hashes() {
+                    builder.add_bulk_string(response_buffer, hash.as_bytes());
+                }
+                builder.add_bulk_string(response_buffer, b"commands");
+                builder.add_bulk_string(response_buffer, user.commands_description().as_bytes());
+                builder.add_bulk_string(response_buffer, b"keys");
+                builder.add_bulk_string(response_buffer, user.keys_description().as_bytes());
+                builder.add_bulk_string(response_buffer, b"channels");
+                builder.add_bulk_string(response_buffer, user.channels_description().as_bytes());
+                builder.add_bulk_string(response_buffer, b"selectors");
+                builder.add_empty_array(response_buffer);
+            }
+            "deluser" => {
+                check_args_count!(command, 3, response_buffer);
+                let usernames: Vec<String> = command
+                    .args_vec()
+                    .iter()
+                    .skip(2)
+                    .map(|arg| BytesMutUtils::to_string(arg))
+                    .collect();
+                if usernames.iter().any(|name| name == DEFAULT_USER) {
+                    builder
+                        .error_string(response_buffer, "ERR The 'default' user cannot be removed");
+                    return Ok(());
+                }
+
+                let deleted = usernames
+                    .iter()
+                    .filter(|name| acl.delete_user(name))
+                    .count();
+                builder.number_usize(response_buffer, deleted);
+            }
+            "list" => {
+                let users = acl.users();
+                builder.add_array_len(response_buffer, users.len());
+                for user in users {
+                    builder.add_bulk_string(response_buffer, user.description().as_bytes());
+                }
+            }
+            "whoami" => {
+                builder.bulk_string(response_buffer, client_state.user_name().as_bytes());
+            }
+            "cat" => match command.arg(2) {
+                None => builder.add_strings(response_buffer, &ACL_CATEGORIES),
+                Some(category) => {
+                    let category = BytesMutUtils::to_string(category).to_lowercase();
+                    let flag = ValkeyCommandFlags::from_str(&category)
+                        .ok()
+                        .filter(|_| ACL_CATEGORIES.contains(&category.as_str()));
+                    let Some(flag) = flag else {
+                        builder.error_string(
+                            response_buffer,
+                            &format!("ERR Unknown category '{}'", category),
+                        );
+                        return Ok(());
+                    };
+
+                    let mut names: Vec<&str> = commands_manager()
+                        .all_commands()
+                        .iter()
+                        .filter(|(_, metadata)| metadata.is_in_category(flag.clone()))
+                        .map(|(name, _)| *name)
+                        .collect();
+                    names.sort();
+                    builder.add_strings(response_buffer, &names);
+                }
+            },
+            "save" => {
+                let file_path = Acl::file_path(server_state.options());
+                match acl.save(&file_path) {
+                    Ok(()) => builder.ok(response_buffer),
+                    Err(e) => builder.error_string(
+                        response_buffer,
+                        &format!("ERR There was an error trying to save the ACLs. {}", e),
+                    ),
+                }
+            }
+            "load" => {
+                let file_path = Acl::file_path(server_state.options());
+                match acl.load(&file_path) {
+                    Ok(()) => builder.ok(response_buffer),
+                    Err(e) => builder.error_string(response_buffer, &format!("ERR {}", e)),
+                }
+            }
+            _ => {
+                builder.error_string(
+                    response_buffer,
+                    &format!(
+                        "ERR unknown subcommand '{}'. Try ACL HELP.",
+                        sub_command.as_str()
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::ClientNextAction, Client, ServerState};
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    #[test_case(vec![
+        (vec!["acl", "whoami"], "$7\r\ndefault\r\n"),
+        (vec!["acl", "setuser", "bob", "on", ">secret", "~cache:*", "+get", "+@read"], "+OK\r\n"),
+        (vec!["acl", "setuser", "bob", "+nosuchcommand"], "-ERR Error in ACL SETUSER modifier '+nosuchcommand': Unknown command\r\n"),
+        (vec!["acl", "getuser", "bob"], "*12\r\n$5\r\nflags\r\n*1\r\n$2\r\non\r\n$9\r\npasswords\r\n*1\r\n$64\r\n2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b\r\n$8\r\ncommands\r\n$17\r\n-@all +get +@read\r\n$4\r\nkeys\r\n$8\r\n~cache:*\r\n$8\r\nchannels\r\n$0\r\n\r\n$9\r\nselectors\r\n*0\r\n"),
+        (vec!["acl", "getuser", "nosuchuser"], "$-1\r\n"),
+        (vec!["acl", "list"], "*2\r\n$104\r\nuser bob on #2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b ~cache:* -@all +get +@read\r\n$34\r\nuser default on nopass ~* &* +@all\r\n"),
+        (vec!["set", "cache:1", "value"], "+OK\r\n"),
+        (vec!["auth", "bob", "wrong"], "-WRONGPASS invalid username-password pair or user is disabled.\r\n"),
+        (vec!["auth", "bob", "secret"], "+OK\r\n"),
+        (vec!["acl", "whoami"], "-NOPERM User bob has no permissions to run the 'acl' command\r\n"),
+        (vec!["get", "cache:1"], "$5\r\nvalue\r\n"),
+        (vec!["get", "session:1"], "-NOPERM No permissions to access a key\r\n"),
+        (vec!["set", "cache:1", "value"], "-NOPERM User bob has no permissions to run the 'set' command\r\n"),
+        (vec!["auth", "default", "anything"], "+OK\r\n"),
+        (vec!["acl", "deluser", "bob", "nosuchuser"], ":1\r\n"),
+        (vec!["acl", "deluser", "default"], "-ERR The 'default' user cannot be removed\r\n"),
+        ], "acl_setuser"; "acl_setuser")]
+    #[test_case(vec![
+        (vec!["auth", "secret"], "-ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?\r\n"),
+        (vec!["acl", "setuser", "default", ">secret"], "+OK\r\n"),
+        (vec!["get", "k1"], "-NOAUTH Authentication required.\r\n"),
+        (vec!["auth", "wrong"], "-WRONGPASS invalid username-password pair or user is disabled.\r\n"),
+        (vec!["auth", "secret"], "+OK\r\n"),
+        (vec!["get", "k1"], "$-1\r\n"),
+        ], "noauth"; "noauth")]
+    #[test_case(vec![
+        (vec!["acl", "setuser", "bob", "on", ">secret", "~cache:*", "~w_?", "+@all"], "+OK\r\n"),
+        (vec!["auth", "bob", "secret"], "+OK\r\n"),
+        (vec!["rpush", "cache:list", "1", "2"], ":2\r\n"),
+        (vec!["set", "w_1", "20"], "+OK\r\n"),
+        (vec!["set", "w_2", "10"], "+OK\r\n"),
+        (vec!["sort", "cache:list", "by", "w_*"], "*2\r\n$1\r\n2\r\n$1\r\n1\r\n"),
+        (vec!["rpush", "cache:list", "33"], ":3\r\n"),
+        (vec!["sort", "cache:list", "by", "w_*"], "-NOPERM No permissions to access a key\r\n"),
+        (vec!["sort", "cache:list", "get", "w_*"], "-NOPERM No permissions to access a key\r\n"),
+        ], "acl_sort_keys"; "acl_sort_keys")]
+    #[test_case(vec![
+        (vec!["acl", "cat"], "*6\r\n$4\r\nread\r\n$5\r\nwrite\r\n$5\r\nadmin\r\n$10\r\nconnection\r\n$8\r\nblocking\r\n$6\r\npubsub\r\n"),
+        (vec!["acl", "cat", "nosuchcategory"], "-ERR Unknown category 'nosuchcategory'\r\n"),
+        (vec!["acl", "cat", "admin"], "*6\r\n$3\r\nacl\r\n$6\r\nconfig\r\n$8\r\nflushall\r\n$7\r\nflushdb\r\n$9\r\nreplicaof\r\n$7\r\nslaveof\r\n"),
+        (vec!["acl", "nosuchcommand"], "-ERR unknown subcommand 'nosuchcommand'. Try ACL HELP.\r\n"),
+        ], "acl_cat"; "acl_cat")]
+    fn test_acl_commands(
+        args_vec: Vec<(Vec<&'static str>, &'static str)>,
+        test_name: &str,
+    ) -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            for (args, expected_value) in args_vec {
+                let mut sink = crate::tests::ResponseSink::with_name(test_name).await;
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                match Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                    .await
+                    .unwrap()
+                {
+                    ClientNextAction::NoAction => {
+                        assert_eq!(sink.read_all().await.as_str(), expected_value);
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,883 @@
+#[allow(unused_imports)]
+use crate::{
+    check_args_count, command_arg_at,
+    commands::{HandleCommandResult, Strings},
+    server::ClientState,
+    storage::{
+        BitmapBitResult, BitmapDb, BitmapLenResult, BitmapReadResult, BitmapResult, BITMAP_MAX_LEN,
+    },
+    utils::RespBuilderV2,
+    BytesMutUtils, LockManager, SableError, ValkeyCommand, ValkeyCommandName,
+};
+
+use bytes::BytesMut;
+use std::rc::Rc;
+use tokio::io::AsyncWriteExt;
+
+/// The largest bit offset (exclusive)
+const MAX_BIT_OFFSET: u64 = BITMAP_MAX_LEN * 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BitfieldOverflow {
+    Wrap,
+    Sat,
+    Fail,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BitfieldOpKind {
+    Get,
+    Set(i64),
+    IncrBy(i64),
+}
+
+/// A single `BITFIELD` operation
+#[derive(Clone, Copy, Debug)]
+struct BitfieldOp {
+    kind: BitfieldOpKind,
+    signed: bool,
+    bits: u32,
+    offset: u64,
+    overflow: BitfieldOverflow,
+}
+
+pub struct BitmapCommands {}
+
+impl BitmapCommands {
+    pub async fn handle_command(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        _tx: &mut (impl AsyncWriteExt + std::marker::Unpin),
+    ) -> Result<HandleCommandResult, SableError> {
+        let mut response_buffer = BytesMut::with_capacity(256);
+        match command.metadata().name() {
+            ValkeyCommandName::Setbit => {
+                Self::setbit(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Getbit => {
+                Self::getbit(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Bitcount => {
+                Self::bitcount(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Bitpos => {
+                Self::bitpos(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Bitop => {
+                Self::bitop(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Bitfield => {
+                Self::bitfield(client_state, command, &mut response_buffer).await?;
+            }
+            _ => {
+                return Err(SableError::InvalidArgument(format!(
+                    "Non bitmap command {}",
+                    command.main_command()
+                )));
+            }
+        }
+        Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
+    }
+
+    /// `SETBIT key offset value`
+    /// Sets or clears the bit at offset in the string value stored at key. The string is grown
+    /// to make sure it can hold a bit at offset. Returns the original bit value stored at offset
+    async fn setbit(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 4, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let Some(offset) = Self::parse_bit_offset(command_arg_at!(command, 2)) else {
+            builder.error_string(response_buffer, Strings::ERR_BIT_OFFSET);
+            return Ok(());
+        };
+
+        let value = match command_arg_at!(command, 3).as_ref() {
+            b"0" => false,
+            b"1" => true,
+            _ => {
+                builder.error_string(response_buffer, Strings::ERR_BIT_VALUE);
+                return Ok(());
+            }
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut bitmap_db =
+            BitmapDb::with_storage(client_state.database(), client_state.database_id());
+        match bitmap_db.set_bit(key, offset, value)? {
+            BitmapBitResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+            }
+            BitmapBitResult::Some(old_value) => {
+                bitmap_db.commit()?;
+                builder.number_usize(response_buffer, old_value as usize);
+            }
+        }
+        Ok(())
+    }
+
+    /// `GETBIT key offset`
+    /// Returns the bit value at offset in the string value stored at key
+    async fn getbit(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let Some(offset) = Self::parse_bit_offset(command_arg_at!(command, 2)) else {
+            builder.error_string(response_buffer, Strings::ERR_BIT_OFFSET);
+            return Ok(());
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let bitmap_db = BitmapDb::with_storage(client_state.database(), client_state.database_id());
+        match bitmap_db.get_bit(key, offset)? {
+            BitmapBitResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+            }
+            BitmapBitResult::Some(value) => {
+                builder.number_usize(response_buffer, value as usize);
+            }
+        }
+        Ok(())
+    }
+
+    /// `BITCOUNT key [start end [BYTE | BIT]]`
+    /// Count the number of set bits (population counting) in a string. By default all the bytes
+    /// contained in the string are examined. It is possible to specify the counting operation
+    /// only in an interval passing the additional arguments start and end
+    async fn bitcount(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let range = match command.arg_count() {
+            2 => None,
+            4 | 5 => {
+                let Some(range) = Self::parse_range(&command.args_vec()[2..]) else {
+                    builder
+                        .error_string(response_buffer, Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE);
+                    return Ok(());
+                };
+                let Some(range) = range else {
+                    builder.error_string(response_buffer, Strings::SYNTAX_ERROR);
+                    return Ok(());
+                };
+                Some(range)
+            }
+            _ => {
+                builder.error_string(response_buffer, Strings::SYNTAX_ERROR);
+                return Ok(());
+            }
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let bitmap_db = BitmapDb::with_storage(client_state.database(), client_state.database_id());
+        let len = match bitmap_db.len(key)? {
+            BitmapLenResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+                return Ok(());
+            }
+            BitmapLenResult::NotFound => 0,
+            BitmapLenResult::Some(len) => len,
+        };
+
+        // Convert the range into a range of bits
+        let (start, end, is_bit) = range.unwrap_or((0, -1, false));
+        let total = if is_bit { len * 8 } else { len };
+        let Some((first_bit, last_bit)) = Self::normalize_range(start, end, total, is_bit) else {
+            builder.number_usize(response_buffer, 0);
+            return Ok(());
+        };
+
+        let mut count = 0usize;
+        bitmap_db.scan(key, first_bit / 8, last_bit / 8 + 1, |offset, chunk| {
+            for (index, byte) in chunk.iter().enumerate() {
+                let byte_offset = offset + index as u64;
+                let mask = Self::range_mask(byte_offset, first_bit, last_bit);
+                count = count.saturating_add((byte & mask).count_ones() as usize);
+            }
+            true
+        })?;
+        builder.number_usize(response_buffer, count);
+        Ok(())
+    }
+
+    /// `BITPOS key bit [start [end [BYTE | BIT]]]`
+    /// Return the position of the first bit set to 1 or 0 in a string
+    async fn bitpos(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let bit = match command_arg_at!(command, 2).as_ref() {
+            b"0" => 0u8,
+            b"1" => 1u8,
+            _ => {
+                builder.error_string(response_buffer, Strings::ERR_BITPOS_BIT);
+                return Ok(());
+            }
+        };
+
+        let args = &command.args_vec()[3..];
+        if args.len() > 3 {
+            builder.error_string(response_buffer, Strings::SYNTAX_ERROR);
+            return Ok(());
+        }
+
+        let end_given = args.len() >= 2;
+        let mut padded_args = args.to_vec();
+        if padded_args.is_empty() {
+            padded_args.push(BytesMut::from("0"));
+        }
+        if padded_args.len() == 1 {
+            padded_args.push(BytesMut::from("-1"));
+        }
+        let Some(range) = Self::parse_range(&padded_args) else {
+            builder.error_string(response_buffer, Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE);
+            return Ok(());
+        };
+        let Some((start, end, is_bit)) = range else {
+            builder.error_string(response_buffer, Strings::SYNTAX_ERROR);
+            return Ok(());
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let bitmap_db = BitmapDb::with_storage(client_state.database(), client_state.database_id());
+        let len = match bitmap_db.len(key)? {
+            BitmapLenResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+                return Ok(());
+            }
+            BitmapLenResult::NotFound => {
+                // A missing key is an infinite string of zeros
+                let pos: i64 = if bit == 1 { -1 } else { 0 };
+                builder.add_number::<i64>(response_buffer, pos, false);
+                return Ok(());
+            }
+            BitmapLenResult::Some(len) => len,
+        };
+
+        let total = if is_bit { len * 8 } else { len };
+        let Some((first_bit, last_bit)) = Self::normalize_range(start, end, total, is_bit) else {
+            builder.add_number::<i64>(response_buffer, -1, false);
+            return Ok(());
+        };
+
+        let mut found: Option<u64> = None;
+        bitmap_db.scan(key, first_bit / 8, last_bit / 8 + 1, |offset, chunk| {
+            for (index, byte) in chunk.iter().enumerate() {
+                let byte_offset = offset + index as u64;
+                let mask = Self::range_mask(byte_offset, first_bit, last_bit);
+                // Bits outside of the range never match
+                let byte = if bit == 1 { byte & mask } else { byte | !mask };
+                let position = if bit == 1 {
+                    byte.leading_zeros()
+                } else {
+                    byte.leading_ones()
+                };
+                if position < 8 {
+                    found = Some(byte_offset * 8 + position as u64);
+                    return false;
+                }
+            }
+            true
+        })?;
+
+        let pos = match found {
+            Some(pos) => pos as i64,
+            // When looking for a clear bit and no end was given, the string is considered
+            // to be padded with zeros on the right
+            None if bit == 0 && !end_given => ((last_bit / 8 + 1) * 8) as i64,
+            None => -1,
+        };
+        builder.add_number::<i64>(response_buffer, pos, false);
+        Ok(())
+    }
+
+    /// `BITOP <AND | OR | XOR | NOT> destkey key [key ...]`
+    /// Perform a bitwise operation between multiple keys (containing string values) and store
+    /// the result in the destination key. Returns the size of the string stored in the
+    /// destination key
+    async fn bitop(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 4, response_buffer);
+        let builder = RespBuilderV2::default();
+        let operation = BytesMutUtils::to_string(command_arg_at!(command, 1)).to_lowercase();
+        let destination = command_arg_at!(command, 2);
+        let sources: Vec<&BytesMut> = command.args_vec().iter().skip(3).collect();
+
+        match operation.as_str() {
+            "and" | "or" | "xor" => {}
+            "not" if sources.len() == 1 => {}
+            "not" => {
+                builder.error_string(response_buffer, Strings::ERR_BITOP_NOT);
+                return Ok(());
+            }
+            _ => {
+                builder.error_string(response_buffer, Strings::SYNTAX_ERROR);
+                return Ok(());
+            }
+        }
+
+        let mut user_keys = sources.clone();
+        user_keys.push(destination);
+        let _unused =
+            LockManager::lock_multi(&user_keys, client_state.clone(), command.clone()).await?;
+        let mut bitmap_db =
+            BitmapDb::with_storage(client_state.database(), client_state.database_id());
+
+        let mut values = Vec::<BytesMut>::with_capacity(sources.len());
+        for source in &sources {
+            match bitmap_db.read(source, 0, u64::MAX)? {
+                BitmapReadResult::WrongType => {
+                    builder.error_string(response_buffer, Strings::WRONGTYPE);
+                    return Ok(());
+                }
+                BitmapReadResult::Some(value) => values.push(value),
+            }
+        }
+
+        let max_len = values
+            .iter()
+            .map(|value| value.len())
+            .max()
+            .unwrap_or_default();
+        let mut result = BytesMut::zeroed(max_len);
+        for (index, byte) in result.iter_mut().enumerate() {
+            let mut bytes = values
+                .iter()
+                .map(|value| value.get(index).copied().unwrap_or_default());
+            *byte = match operation.as_str() {
+                "and" => bytes.fold(0xFF, |acc, byte| acc & byte),
+                "or" => bytes.fold(0, |acc, byte| acc | byte),
+                "xor" => bytes.fold(0, |acc, byte| acc ^ byte),
+                _ => !bytes.next().unwrap_or_default(),
+            };
+        }
+
+        if result.is_empty() {
+            bitmap_db.delete(destination)?;
+        } else {
+            bitmap_db.put(destination, &result)?;
+        }
+        bitmap_db.commit()?;
+        builder.number_usize(response_buffer, result.len());
+        Ok(())
+    }
+
+    /// `BITFIELD key [GET encoding offset | [OVERFLOW <WRAP | SAT | FAIL>]
+    /// <SET encoding offset value | INCRBY encoding offset increment> [...]]`
+    /// The command treats a string as an array of bits, and is capable of addressing specific
+    /// integer fields of varying bit widths and arbitrary non (necessary) aligned offset
+    async fn bitfield(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let ops = match Self::parse_bitfield_ops(&command.args_vec()[2..]) {
+            Ok(ops) => ops,
+            Err(msg) => {
+                builder.error_string(response_buffer, msg);
+                return Ok(());
+            }
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut bitmap_db =
+            BitmapDb::with_storage(client_state.database(), client_state.database_id());
+        if let BitmapLenResult::WrongType = bitmap_db.len(key)? {
+            builder.error_string(response_buffer, Strings::WRONGTYPE);
+            return Ok(());
+        }
+
+        let mut results = Vec::<Option<i64>>::with_capacity(ops.len());
+        for op in &ops {
+            // Load the bytes that hold the field
+            let first_byte = op.offset / 8;
+            let last_byte = (op.offset + op.bits as u64 - 1) / 8;
+            let BitmapReadResult::Some(mut bytes) =
+                bitmap_db.read(key, first_byte, last_byte + 1)?
+            else {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+                return Ok(());
+            };
+            bytes.resize((last_byte - first_byte + 1) as usize, 0);
+
+            let bit_offset = op.offset % 8;
+            let current = Self::get_bits(&bytes, bit_offset, op.bits);
+            let current = if op.signed {
+                Self::sign_extend(current, op.bits)
+            } else {
+                current as i64
+            };
+
+            // Compute the new value and the value returned to the caller
+            let (new_value, reply) = match op.kind {
+                BitfieldOpKind::Get => {
+                    results.push(Some(current));
+                    continue;
+                }
+                BitfieldOpKind::Set(value) => {
+                    match Self::apply_overflow(value, 0, op.signed, op.bits, op.overflow) {
+                        Some(new_value) => (new_value, current),
+                        None => {
+                            results.push(None);
+                            continue;
+                        }
+                    }
+                }
+                BitfieldOpKind::IncrBy(increment) => {
+                    match Self::apply_overflow(current, increment, op.signed, op.bits, op.overflow)
+                    {
+                        Some(new_value) => (new_value, new_value),
+                        None => {
+                            results.push(None);
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            Self::set_bits(&mut bytes, bit_offset, op.bits, new_value as u64);
+            if let BitmapResult::WrongType = bitmap_db.write(key, first_byte, &bytes)? {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+                return Ok(());
+            }
+            results.push(Some(reply));
+        }
+        bitmap_db.commit()?;
+
+        builder.add_array_len(response_buffer, results.len());
+        for result in results {
+            match result {
+                Some(value) => builder.add_number::<i64>(response_buffer, value, false),
+                None => builder.add_null_string(response_buffer),
+            }
+        }
+        Ok(())
+    }
+
+    // Internal helpers
+
+    /// Parse a bit offset. The offset must be smaller than `MAX_BIT_OFFSET`
+    fn parse_bit_offset(value: &BytesMut) -> Option<u64> {
+        BytesMutUtils::parse::<u64>(value).filter(|offset| *offset < MAX_BIT_OFFSET)
+    }
+
+    /// Parse `start end [BYTE | BIT]`. Return `None` if start or end are not numbers
+    /// and `Some(None)` on syntax error
+    fn parse_range(args: &[BytesMut]) -> Option<Option<(i64, i64, bool)>> {
+        let start = BytesMutUtils::parse::<i64>(args.first()?)?;
+        let end = BytesMutUtils::parse::<i64>(args.get(1)?)?;
+        let is_bit = match args.get(2) {
+            None => false,
+            Some(unit) => match BytesMutUtils::to_string(unit).to_lowercase().as_str() {
+                "byte" => false,
+                "bit" => true,
+                _ => return Some(None),
+            },
+        };
+        Some(Some((start, end, is_bit)))
+    }
+
+    /// Translate a user range (negative indexes are counted from the end) into an inclusive
+    /// range of bits. `total` is the number of units (bits or bytes) in the value
+    fn normalize_range(start: i64, end: i64, total: u64, is_bit: bool) -> Option<(u64, u64)> {
+        let total = total as i64;
+        let start = if start < 0 { start + total } else { start }.max(0);
+        let end = if end < 0 { end + total } else { end }
+            .max(0)
+            .min(total - 1);
+        if total == 0 || start > end {
+            return None;
+        }
+
+        let (start, end) = (start as u64, end as u64);
+        if is_bit {
+            Some((start, end))
+        } else {
+            Some((start * 8, end * 8 + 7))
+        }
+    }
+
+    /// Return a mask of the bits of the byte at `byte_offset` that are within the bit range
+    /// `[first_bit, last_bit]`
+    fn range_mask(byte_offset: u64, first_bit: u64, last_bit: u64) -> u8 {
+        let mut mask = 0xFFu8;
+        if byte_offset == first_bit / 8 {
+            mask &= 0xFF >> (first_bit % 8);
+        }
+        if byte_offset == last_bit / 8 {
+            mask &= 0xFF << (7 - last_bit % 8);
+        }
+        mask
+    }
+
+    /// Parse the `BITFIELD` sub-commands
+    fn parse_bitfield_ops(args: &[BytesMut]) -> Result<Vec<BitfieldOp>, &'static str> {
+        let mut ops = Vec::<BitfieldOp>::new();
+        let mut overflow = BitfieldOverflow::Wrap;
+        let mut pos = 0usize;
+        while let Some(arg) = args.get(pos) {
+            let arg_lowercase = BytesMutUtils::to_string(arg).to_lowercase();
+            let expected_args = match arg_lowercase.as_str() {
+                "get" => 3,
+                "set" | "incrby" => 4,
+                "overflow" => 2,
+                _ => return Err(Strings::SYNTAX_ERROR),
+            };
+            if pos + expected_args > args.len() {
+                return Err(Strings::SYNTAX_ERROR);
+            }
+
+            if arg_lowercase == "overflow" {
+                overflow = match BytesMutUtils::to_string(&args[pos + 1])
+                    .to_lowercase()
+                    .as_str()
+                {
+                    "wrap" => BitfieldOverflow::Wrap,
+                    "sat" => BitfieldOverflow::Sat,
+                    "fail" => BitfieldOverflow::Fail,
+                    _ => return Err(Strings::ERR_BITFIELD_OVERFLOW),
+                };
+                pos += expected_args;
+                continue;
+            }
+
+            let (signed, bits) =
+                Self::parse_bitfield_type(&args[pos + 1]).ok_or(Strings::ERR_BITFIELD_TYPE)?;
+            let offset =
+                Self::parse_bitfield_offset(&args[pos + 2], bits).ok_or(Strings::ERR_BIT_OFFSET)?;
+
+            let kind = match arg_lowercase.as_str() {
+                "get" => BitfieldOpKind::Get,
+                _ => {
+                    let value = BytesMutUtils::parse::<i64>(&args[pos + 3])
+                        .ok_or(Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE)?;
+                    if arg_lowercase == "set" {
+                        BitfieldOpKind::Set(value)
+                    } else {
+                        BitfieldOpKind::IncrBy(value)
+                    }
+                }
+            };
+
+            ops.push(BitfieldOp {
+                kind,
+                signed,
+                bits,
+                offset,
+                overflow,
+            });
+            pos += expected_args;
+        }
+        Ok(ops)
+    }
+
+    /// Parse a bitfield type: `i<bits>` (1-64) or `u<bits>` (1-63)
+    fn parse_bitfield_type(value: &BytesMut) -> Option<(bool, u32)> {
+        let (signed, bits) = match value.first()? {
+            b'i' => (true, &value[1..]),
+            b'u' => (false, &value[1..]),
+            _ => return None,
+        };
+        let bits = BytesMutUtils::parse::<u32>(&BytesMut::from(bits))?;
+        let max_bits = if signed { 64 } else { 63 };
+        (1..=max_bits).contains(&bits).then_some((signed, bits))
+    }
+
+    /// Parse a bitfield offset. An offset prefixed with `#` is multiplied by the type width
+    fn parse_bitfield_offset(value: &BytesMut, bits: u32) -> Option<u64> {
+        let offset = match value.strip_prefix(b"#") {
+            Some(index) => {
+                BytesMutUtils::parse::<u64>(&BytesMut::from(index))?.checked_mul(bits as u64)?
+            }
+            None => BytesMutUtils::parse::<u64>(value)?,
+        };
+        (offset.checked_add(bits as u64)? <= MAX_BIT_OFFSET).then_some(offset)
+    }
+
+    /// Read `bits` bits starting at bit `offset` of `bytes` as an unsigned number
+    fn get_bits(bytes: &[u8], offset: u64, bits: u32) -> u64 {
+        let mut value = 0u64;
+        for position in offset..offset + bits as u64 {
+            let bit = (bytes[(position / 8) as usize] >> (7 - position % 8)) & 1;
+            value = (value << 1) | bit as u64;
+        }
+        value
+    }
+
+    /// Write the lower `bits` bits of `value` starting at bit `offset` of `bytes`
+    fn set_bits(bytes: &mut [u8], offset: u64, bits: u32, value: u64) {
+        for index in 0..bits as u64 {
+            let position = offset + index;
+            let bit = (value >> (bits as u64 - 1 - index)) & 1;
+            let mask = 0x80u8 >> (position % 8);
+            if bit == 1 {
+                bytes[(position / 8) as usize] |= mask;
+            } else {
+                bytes[(position / 8) as usize] &= !mask;
+            }
+        }
+    }
+
+    /// Interpret the lower `bits` bits of `value` as a signed number
+    fn sign_extend(value: u64, bits: u32) -> i64 {
+        if bits < 64 && value & (1 << (bits - 1)) != 0 {
+            (value | (u64::MAX << bits)) as i64
+        } else {
+            value as i64
+        }
+    }
+
+    /// Compute `value + increment` for a field of `bits` bits, handling overflows according to
+    /// `overflow`. Return `None` when the operation overflows and the overflow mode is `FAIL`
+    fn apply_overflow(
+        value: i64,
+        increment: i64,
+        signed: bool,
+        bits: u32,
+        overflow: BitfieldOverflow,
+    ) -> Option<i64> {
+        let (limit, overflowed) = if signed {
+            let max = if bits == 64 {
+                i64::MAX
+            } else {
+                (1i64 << (bits - 1)) - 1
+            };
+            let min = -max - 1;
+            let max_increment = (max as u64).wrapping_sub(value as u64) as i64;
+            let min_increment = min.wrapping_sub(value);
+
+            if value > max
+                || (bits != 64 && increment > max_increment)
+                || (value >= 0 && increment > 0 && increment > max_increment)
+            {
+                (max, true)
+            } else if value < min
+                || (bits != 64 && increment < min_increment)
+                || (value < 0 && increment < 0 && increment < min_increment)
+            {
+                (min, true)
+            } else {
+                (value.wrapping_add(increment), false)
+            }
+        } else {
+            let value = value as u64;
+            let max = (1u64 << bits) - 1;
+            let max_increment = max.wrapping_sub(value) as i64;
+            let min_increment = (value as i64).wrapping_neg();
+
+            if value > max || (increment > 0 && increment > max_increment) {
+                (max as i64, true)
+            } else if increment < 0 && increment < min_increment {
+                (0, true)
+            } else {
+                (value.wrapping_add(increment as u64) as i64, false)
+            }
+        };
+
+        if !overflowed {
+            return Some(limit);
+        }
+
+        match overflow {
+            BitfieldOverflow::Fail => None,
+            BitfieldOverflow::Sat => Some(limit),
+            BitfieldOverflow::Wrap => {
+                let wrapped = (value as u64).wrapping_add(increment as u64);
+                Some(if signed {
+                    Self::sign_extend(wrapped & Self::bits_mask(bits), bits)
+                } else {
+                    (wrapped & Self::bits_mask(bits)) as i64
+                })
+            }
+        }
+    }
+
+    /// Return a mask with the lower `bits` bits set
+    fn bits_mask(bits: u32) -> u64 {
+        if bits == 64 {
+            u64::MAX
+        } else {
+            (1u64 << bits) - 1
+        }
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::ClientNextAction, Client, ServerState};
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    #[test_case(vec![
+        (vec!["setbit", "mykey", "7", "1"], ":0\r\n"),
+        (vec!["setbit", "mykey", "7", "0"], ":1\r\n"),
+        (vec!["get", "mykey"], "$1\r\n\x00\r\n"),
+        (vec!["setbit", "mykey", "7", "2"], "-ERR bit is not an integer or out of range\r\n"),
+        (vec!["setbit", "mykey", "4294967296", "1"], "-ERR bit offset is not an integer or out of range\r\n"),
+        (vec!["setbit", "mykey", "-1", "1"], "-ERR bit offset is not an integer or out of range\r\n"),
+        (vec!["getbit", "mykey", "0"], ":0\r\n"),
+        (vec!["getbit", "mykey", "100000"], ":0\r\n"),
+        (vec!["getbit", "nosuchkey", "100"], ":0\r\n"),
+        (vec!["set", "str", "a"], "+OK\r\n"),
+        (vec!["getbit", "str", "1"], ":1\r\n"),
+        (vec!["setbit", "str", "6", "1"], ":0\r\n"),
+        (vec!["setbit", "str", "7", "0"], ":1\r\n"),
+        (vec!["get", "str"], "$1\r\nb\r\n"),
+        (vec!["setbit", "str", "15", "1"], ":0\r\n"),
+        (vec!["strlen", "str"], ":2\r\n"),
+        (vec!["append", "str", "c"], ":3\r\n"),
+        (vec!["getbit", "str", "15"], ":1\r\n"),
+        (vec!["lpush", "list", "a"], ":1\r\n"),
+        (vec!["setbit", "list", "1", "1"], "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"),
+        (vec!["getbit", "list", "1"], "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"),
+        ], "setbit_getbit"; "setbit_getbit")]
+    #[test_case(vec![
+        (vec!["set", "mykey", "foobar"], "+OK\r\n"),
+        (vec!["bitcount", "mykey"], ":26\r\n"),
+        (vec!["bitcount", "mykey", "0", "0"], ":4\r\n"),
+        (vec!["bitcount", "mykey", "1", "1"], ":6\r\n"),
+        (vec!["bitcount", "mykey", "1", "1", "byte"], ":6\r\n"),
+        (vec!["bitcount", "mykey", "5", "30", "bit"], ":17\r\n"),
+        (vec!["bitcount", "mykey", "-2", "-1"], ":7\r\n"),
+        (vec!["bitcount", "mykey", "3", "1"], ":0\r\n"),
+        (vec!["bitcount", "mykey", "1"], "-ERR syntax error\r\n"),
+        (vec!["bitcount", "mykey", "1", "a"], "-ERR value is not an integer or out of range\r\n"),
+        (vec!["bitcount", "mykey", "1", "2", "bits"], "-ERR syntax error\r\n"),
+        (vec!["bitcount", "nosuchkey"], ":0\r\n"),
+        (vec!["setbit", "bitmap", "100000", "1"], ":0\r\n"),
+        (vec!["setbit", "bitmap", "3", "1"], ":0\r\n"),
+        (vec!["bitcount", "bitmap"], ":2\r\n"),
+        (vec!["bitcount", "bitmap", "1", "-1"], ":1\r\n"),
+        ], "bitcount"; "bitcount")]
+    #[test_case(vec![
+        (vec!["setbit", "bits", "0", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "1", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "2", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "3", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "4", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "5", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "6", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "7", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "8", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "9", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "10", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "11", "1"], ":0\r\n"),
+        (vec!["setbit", "bits", "23", "0"], ":0\r\n"),
+        (vec!["bitpos", "bits", "0"], ":12\r\n"),
+        (vec!["bitpos", "bits", "1", "2"], ":-1\r\n"),
+        (vec!["bitpos", "bits", "0", "2", "-1", "byte"], ":16\r\n"),
+        (vec!["bitpos", "bits", "1", "7", "15", "bit"], ":7\r\n"),
+        (vec!["bitpos", "bits", "0", "0", "0"], ":-1\r\n"),
+        (vec!["bitpos", "bits", "0", "0"], ":12\r\n"),
+        (vec!["bitpos", "bits", "2"], "-ERR The bit argument must be 1 or 0.\r\n"),
+        (vec!["bitpos", "nosuchkey", "0"], ":0\r\n"),
+        (vec!["bitpos", "nosuchkey", "1"], ":-1\r\n"),
+        (vec!["set", "ones", "\u{7f}"], "+OK\r\n"),
+        (vec!["setbit", "ones", "0", "1"], ":0\r\n"),
+        (vec!["bitpos", "ones", "0"], ":8\r\n"),
+        (vec!["bitpos", "ones", "0", "0", "-1"], ":-1\r\n"),
+        ], "bitpos"; "bitpos")]
+    #[test_case(vec![
+        (vec!["set", "key1", "foobar"], "+OK\r\n"),
+        (vec!["set", "key2", "abcdef"], "+OK\r\n"),
+        (vec!["bitop", "and", "dest", "key1", "key2"], ":6\r\n"),
+        (vec!["get", "dest"], "$6\r\n`bc`ab\r\n"),
+        (vec!["bitop", "or", "dest", "key1", "key2"], ":6\r\n"),
+        (vec!["get", "dest"], "$6\r\ngoofev\r\n"),
+        (vec!["bitop", "xor", "dest", "key1", "nosuchkey"], ":6\r\n"),
+        (vec!["get", "dest"], "$6\r\nfoobar\r\n"),
+        (vec!["bitop", "and", "dest", "key1", "nosuchkey"], ":6\r\n"),
+        (vec!["bitcount", "dest"], ":0\r\n"),
+        (vec!["setbit", "bits", "0", "1"], ":0\r\n"),
+        (vec!["bitop", "not", "dest", "bits"], ":1\r\n"),
+        (vec!["get", "dest"], "$1\r\n\x7f\r\n"),
+        (vec!["bitop", "not", "dest", "key1", "key2"], "-ERR BITOP NOT must be called with a single source key.\r\n"),
+        (vec!["bitop", "nand", "dest", "key1", "key2"], "-ERR syntax error\r\n"),
+        (vec!["bitop", "or", "dest", "nosuchkey"], ":0\r\n"),
+        (vec!["exists", "dest"], ":0\r\n"),
+        (vec!["lpush", "list", "a"], ":1\r\n"),
+        (vec!["bitop", "or", "dest", "key1", "list"], "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"),
+        ], "bitop"; "bitop")]
+    #[test_case(vec![
+        (vec!["bitfield", "mykey", "incrby", "i5", "100", "1", "get", "u4", "0"], "*2\r\n:1\r\n:0\r\n"),
+        (vec!["bitfield", "mykey", "set", "u8", "0", "255", "get", "u8", "0"], "*2\r\n:0\r\n:255\r\n"),
+        (vec!["bitfield", "mykey", "incrby", "u8", "0", "10"], "*1\r\n:9\r\n"),
+        (vec!["bitfield", "mykey", "overflow", "sat", "incrby", "u8", "0", "300"], "*1\r\n:255\r\n"),
+        (vec!["bitfield", "mykey", "overflow", "fail", "incrby", "u8", "0", "1"], "*1\r\n$-1\r\n"),
+        (vec!["bitfield", "mykey", "overflow", "wrap", "incrby", "u8", "0", "2"], "*1\r\n:1\r\n"),
+        (vec!["bitfield", "counters", "incrby", "u2", "100", "1", "overflow", "sat", "incrby", "u2", "102", "1"], "*2\r\n:1\r\n:1\r\n"),
+        (vec!["bitfield", "counters", "incrby", "u2", "100", "1", "overflow", "sat", "incrby", "u2", "102", "1"], "*2\r\n:2\r\n:2\r\n"),
+        (vec!["bitfield", "counters", "incrby", "u2", "100", "1", "overflow", "sat", "incrby", "u2", "102", "1"], "*2\r\n:3\r\n:3\r\n"),
+        (vec!["bitfield", "counters", "incrby", "u2", "100", "1", "overflow", "sat", "incrby", "u2", "102", "1"], "*2\r\n:0\r\n:3\r\n"),
+        (vec!["bitfield", "signed", "set", "i8", "#1", "-100", "get", "i8", "8", "get", "u8", "#1"], "*3\r\n:0\r\n:-100\r\n:156\r\n"),
+        (vec!["bitfield", "signed", "overflow", "sat", "incrby", "i8", "8", "-100"], "*1\r\n:-128\r\n"),
+        (vec!["bitfield", "signed", "overflow", "wrap", "incrby", "i8", "8", "-1"], "*1\r\n:127\r\n"),
+        (vec!["bitfield", "signed", "set", "i64", "0", "-1", "get", "i64", "0"], "*2\r\n:0\r\n:-1\r\n"),
+        (vec!["bitfield", "mykey", "get", "u64", "0"], "-ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.\r\n"),
+        (vec!["bitfield", "mykey", "get", "i8", "-1"], "-ERR bit offset is not an integer or out of range\r\n"),
+        (vec!["bitfield", "mykey", "overflow", "none"], "-ERR Invalid OVERFLOW type specified\r\n"),
+        (vec!["bitfield", "mykey", "set", "i8", "0"], "-ERR syntax error\r\n"),
+        (vec!["bitfield", "mykey", "set", "i8", "0", "a"], "-ERR value is not an integer or out of range\r\n"),
+        (vec!["lpush", "list", "a"], ":1\r\n"),
+        (vec!["bitfield", "list", "get", "i8", "0"], "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"),
+        ], "bitfield"; "bitfield")]
+    fn test_bitmap_commands(
+        args_vec: Vec<(Vec<&'static str>, &'static str)>,
+        test_name: &str,
+    ) -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            for (args, expected_value) in args_vec {
+                let mut sink = crate::tests::ResponseSink::with_name(test_name).await;
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                match Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                    .await
+                    .unwrap()
+                {
+                    ClientNextAction::NoAction => {
+                        assert_eq!(sink.read_all().await.as_str(), expected_value);
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::commands::{CommandMetadata, CommandsManager};
+use crate::commands::{CommandMetadata, CommandsManager, ValkeyCommandName};
 use crate::{BytesMutUtils, SableError};
 use bytes::BytesMut;
 use std::str::FromStr;
@@ -98,6 +98,139 @@ impl ValkeyCommand {
         &self.args
     }
 
+    /// Return the keys accessed by this command, as described by the command metadata
+    /// (first key, last key and step)
+    pub fn keys(&self) -> Vec<&BytesMut> {
+        let metadata = self.metadata();
+        if metadata.first_key() <= 0 {
+            return Vec::default();
+        }
+
+        let first_key = metadata.first_key() as usize;
+        // A negative last key is relative to the end of the command, e.g. `-1` is the last argument
+        let last_key = if metadata.last_key() < 0 {
+            self.arg_count() as i64 + metadata.last_key() as i64
+        } else {
+            metadata.last_key() as i64
+        };
+
+        if last_key < first_key as i64 {
+            return Vec::default();
+        }
+
+        self.args
+            .iter()
+            .take(last_key as usize + 1)
+            .skip(first_key)
+            .step_by(metadata.step().max(1) as usize)
+            .collect()
+    }
+
+    /// Return all the keys accessed by this command. For commands with movable keys, the keys are
+    /// located by parsing the arguments (`numkeys`, `STREAMS`, `KEYS`, the `SORT` options etc). The
+    /// `SORT` `BY` and `GET` patterns are returned as is. Return `None` if the keys can not be
+    /// located, e.g. the command is malformed
+    pub fn all_keys(&self) -> Option<Vec<&BytesMut>> {
+        if !self.metadata().has_movable_keys() {
+            return Some(self.keys());
+        }
+
+        match self.metadata().name() {
+            ValkeyCommandName::Lmpop
+            | ValkeyCommandName::Zmpop
+            | ValkeyCommandName::Zdiff
+            | ValkeyCommandName::Zinter
+            | ValkeyCommandName::Zunion
+            | ValkeyCommandName::Zintercard
+            | ValkeyCommandName::Sintercard => self.numkeys_keys(1),
+            ValkeyCommandName::Blmpop
+            | ValkeyCommandName::Bzmpop
+            | ValkeyCommandName::Eval
+            | ValkeyCommandName::Evalsha
+            | ValkeyCommandName::EvalRo
+            | ValkeyCommandName::EvalshaRo
+            | ValkeyCommandName::Fcall
+            | ValkeyCommandName::FcallRo => self.numkeys_keys(2),
+            ValkeyCommandName::Zdiffstore
+            | ValkeyCommandName::Zinterstore
+            | ValkeyCommandName::Zunionstore => {
+                let mut keys = vec![self.args.get(1)?];
+                keys.extend(self.numkeys_keys(2)?);
+                Some(keys)
+            }
+            ValkeyCommandName::Xread | ValkeyCommandName::Xreadgroup => self.streams_keys(),
+            ValkeyCommandName::Sort | ValkeyCommandName::SortRo => self.sort_keys(),
+            ValkeyCommandName::Migrate => self.migrate_keys(),
+            _ => None,
+        }
+    }
+
+    /// The keys that follow the `numkeys` argument at position `pos`
+    fn numkeys_keys(&self, pos: usize) -> Option<Vec<&BytesMut>> {
+        let numkeys = self.arg_as_number::<usize>(pos)?;
+        let first_key = pos.checked_add(1)?;
+        let keys = self.args.get(first_key..first_key.checked_add(numkeys)?)?;
+        Some(keys.iter().collect())
+    }
+
+    /// `XREAD` / `XREADGROUP`: the first half of the arguments that follow `STREAMS`
+    fn streams_keys(&self) -> Option<Vec<&BytesMut>> {
+        let mut pos = 1usize;
+        loop {
+            match self.arg_as_lowercase_string(pos)?.as_str() {
+                "streams" => break,
+                "noack" => pos += 1,
+                "count" | "block" => pos += 2,
+                "group" => pos += 3,
+                _ => return None,
+            }
+        }
+
+        let streams = self.args.get(pos + 1..)?;
+        if streams.is_empty() || streams.len() % 2 == 1 {
+            return None;
+        }
+        Some(streams.iter().take(streams.len() / 2).collect())
+    }
+
+    /// `SORT key [BY pattern] [LIMIT offset count] [GET pattern ...] [ASC|DESC] [ALPHA]
+    /// [STORE destination]`
+    fn sort_keys(&self) -> Option<Vec<&BytesMut>> {
+        let mut keys = vec![self.args.get(1)?];
+        let mut pos = 2usize;
+        while let Some(arg) = self.arg_as_lowercase_string(pos) {
+            match arg.as_str() {
+                "by" | "get" | "store" => {
+                    let value = self.args.get(pos + 1)?;
+                    // `GET #` returns the element itself
+                    if !(arg == "get" && value.as_ref() == b"#") {
+                        keys.push(value);
+                    }
+                    pos += 2;
+                }
+                "limit" => pos += 3,
+                _ => pos += 1,
+            }
+        }
+        Some(keys)
+    }
+
+    /// `MIGRATE host port key|"" db timeout [COPY] [REPLACE] [AUTH password]
+    /// [AUTH2 username password] [KEYS key [key ...]]`
+    fn migrate_keys(&self) -> Option<Vec<&BytesMut>> {
+        let key = self.args.get(3)?;
+        let mut pos = 6usize;
+        while let Some(arg) = self.arg_as_lowercase_string(pos) {
+            match arg.as_str() {
+                "keys" => return Some(self.args.iter().skip(pos + 1).collect()),
+                "auth" => pos += 2,
+                "auth2" => pos += 3,
+                _ => pos += 1,
+            }
+        }
+        Some(vec![key])
+    }
+
     /// Return argument at position `pos` as `T`
     /// In any case of error, return `None`
     pub fn arg_as_number<T: FromStr>(&self, pos: usize) -> Option<T> {
@@ -28,6 +28,13 @@ pub enum ValkeyCommandFlags {
     /// This command operates on multiple keys
     #[strum(serialize = "multikey")]
     MultiKey = 1 << 6,
+    /// Pub/Sub related command
+    #[strum(serialize = "pubsub")]
+    PubSub = 1 << 7,
+    /// The command keys can not be located using the first key, last key and step: the command
+    /// arguments must be parsed (see `ValkeyCommand::all_keys`)
+    #[strum(serialize = "movablekeys")]
+    MovableKeys = 1 << 8,
 }
 
 #[derive(Clone, Debug, Default, EnumString, PartialEq, Eq)]
@@ -94,13 +101,37 @@ pub enum ValkeyCommandName {
     FlushDb,
     DbSize,
     Slot,
+    Wait,
+    WaitAof,
     // Generic commands
     Ttl,
     Del,
     Exists,
     Expire,
+    Pexpire,
+    Expireat,
+    Pexpireat,
+    Pttl,
+    Expiretime,
+    Pexpiretime,
     Keys,
     Scan,
+    Rename,
+    Renamenx,
+    Copy,
+    Move,
+    Type,
+    Persist,
+    Touch,
+    Unlink,
+    Randomkey,
+    Dump,
+    Restore,
+    Migrate,
+    Sort,
+    SortRo,
+    Object,
+    Memory,
     // Hash commands
     Hset,
     Hget,
@@ -118,6 +149,17 @@ pub enum ValkeyCommandName {
     Hscan,
     Hsetnx,
     Hstrlen,
+    Hexpire,
+    Hpexpire,
+    Hexpireat,
+    Hpexpireat,
+    Httl,
+    Hpttl,
+    Hexpiretime,
+    Hpexpiretime,
+    Hpersist,
+    Hgetex,
+    Hsetex,
     // Transaction
     Multi,
     Exec,
@@ -183,6 +225,59 @@ pub enum ValkeyCommandName {
     Unlock,
     // Cluster commands
     Cluster,
+    // Pub/Sub commands
+    Subscribe,
+    Psubscribe,
+    Unsubscribe,
+    Punsubscribe,
+    Publish,
+    Pubsub,
+    Ssubscribe,
+    Sunsubscribe,
+    Spublish,
+    // Stream commands
+    Xadd,
+    Xrange,
+    Xread,
+    Xtrim,
+    Xdel,
+    Xlen,
+    Xgroup,
+    Xreadgroup,
+    Xack,
+    Xpending,
+    Xclaim,
+    Xautoclaim,
+    // Scripting commands
+    Eval,
+    Evalsha,
+    EvalRo,
+    EvalshaRo,
+    Script,
+    Function,
+    Fcall,
+    FcallRo,
+    // ACL commands
+    Auth,
+    Acl,
+    // Geo commands
+    Geoadd,
+    Geodist,
+    Geopos,
+    Geohash,
+    Geosearch,
+    Geosearchstore,
+    // Bitmap commands
+    Setbit,
+    Getbit,
+    Bitcount,
+    Bitpos,
+    Bitop,
+    Bitfield,
+    // HyperLogLog commands
+    Pfadd,
+    Pfcount,
+    Pfmerge,
     NotSupported(String),
 }
 
@@ -335,6 +430,19 @@ impl CommandMetadata {
         self
     }
 
+    /// This command falls under the @pubsub category
+    pub fn pubsub(mut self) -> Self {
+        self.set_flag(ValkeyCommandFlags::PubSub);
+        self
+    }
+
+    /// Some (or all) of the command keys are not described by the first key, last key and step,
+    /// e.g. commands with a `numkeys` argument
+    pub fn movable_keys(mut self) -> Self {
+        self.set_flag(ValkeyCommandFlags::MovableKeys);
+        self
+    }
+
     pub fn name(&self) -> &ValkeyCommandName {
         &self.cmd_name
     }
@@ -361,6 +469,50 @@ impl CommandMetadata {
         self.has_flag(ValkeyCommandFlags::MultiKey)
     }
 
+    pub fn is_pubsub(&self) -> bool {
+        self.has_flag(ValkeyCommandFlags::PubSub)
+    }
+
+    pub fn has_movable_keys(&self) -> bool {
+        self.has_flag(ValkeyCommandFlags::MovableKeys)
+    }
+
+    /// Does this command count as an access to its keys (see `OBJECT IDLETIME`)? Commands that
+    /// only inspect the key properties do not, `RESTORE` and `TOUCH` set the access time by
+    /// themselves
+    pub fn touches_keys(&self) -> bool {
+        !matches!(
+            self.cmd_name,
+            ValkeyCommandName::Object
+                | ValkeyCommandName::Memory
+                | ValkeyCommandName::Type
+                | ValkeyCommandName::Exists
+                | ValkeyCommandName::Ttl
+                | ValkeyCommandName::Pttl
+                | ValkeyCommandName::Expiretime
+                | ValkeyCommandName::Pexpiretime
+                | ValkeyCommandName::Restore
+                | ValkeyCommandName::Touch
+        )
+    }
+
+    /// Does this command belong to the ACL category `flag` (e.g. `@read`)?
+    pub fn is_in_category(&self, flag: ValkeyCommandFlags) -> bool {
+        self.has_flag(flag)
+    }
+
+    pub fn first_key(&self) -> i16 {
+        self.first_key
+    }
+
+    pub fn last_key(&self) -> i16 {
+        self.last_key
+    }
+
+    pub fn step(&self) -> u16 {
+        self.step
+    }
+
     pub fn to_resp_v2(&self) -> BytesMut {
         let builder = crate::RespBuilderV2::default();
         let mut buffer = BytesMut::with_capacity(64);
@@ -386,6 +538,14 @@ impl CommandMetadata {
             flags.push("notransaction");
         }
 
+        if self.has_flag(ValkeyCommandFlags::PubSub) {
+            flags.push("pubsub");
+        }
+
+        if self.has_flag(ValkeyCommandFlags::MovableKeys) {
+            flags.push("movablekeys");
+        }
+
         let cmdname = BytesMut::from(format!("{:?}", self.cmd_name).to_lowercase().as_str());
 
         // convert this object into RESP
@@ -419,7 +579,7 @@ impl Default for CommandsManager {
             (
                 "config",
                 CommandMetadata::new(ValkeyCommandName::Config)
-                    .read_only()
+                    .admin()
                     .with_arity(-2)
                     .with_first_key(0)
                     .with_last_key(0)
@@ -686,7 +846,8 @@ impl Default for CommandsManager {
                     .with_first_key(0)
                     .with_last_key(0)
                     .with_step(0)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "brpoplpush",
@@ -724,7 +885,8 @@ impl Default for CommandsManager {
                     .with_first_key(0)
                     .with_last_key(0)
                     .with_step(0)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "brpop",
@@ -740,6 +902,9 @@ impl Default for CommandsManager {
                 "client",
                 CommandMetadata::new(ValkeyCommandName::Client)
                     .connection()
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
                     .no_transaction(),
             ),
             (
@@ -828,6 +993,42 @@ impl Default for CommandsManager {
                     .write()
                     .with_arity(-3),
             ),
+            (
+                "pexpire",
+                CommandMetadata::new(ValkeyCommandName::Pexpire)
+                    .write()
+                    .with_arity(-3),
+            ),
+            (
+                "expireat",
+                CommandMetadata::new(ValkeyCommandName::Expireat)
+                    .write()
+                    .with_arity(-3),
+            ),
+            (
+                "pexpireat",
+                CommandMetadata::new(ValkeyCommandName::Pexpireat)
+                    .write()
+                    .with_arity(-3),
+            ),
+            (
+                "pttl",
+                CommandMetadata::new(ValkeyCommandName::Pttl)
+                    .read_only()
+                    .with_arity(2),
+            ),
+            (
+                "expiretime",
+                CommandMetadata::new(ValkeyCommandName::Expiretime)
+                    .read_only()
+                    .with_arity(2),
+            ),
+            (
+                "pexpiretime",
+                CommandMetadata::new(ValkeyCommandName::Pexpiretime)
+                    .read_only()
+                    .with_arity(2),
+            ),
             (
                 "keys",
                 CommandMetadata::new(ValkeyCommandName::Keys)
@@ -837,6 +1038,124 @@ impl Default for CommandsManager {
                     .with_first_key(0)
                     .with_step(0),
             ),
+            (
+                "rename",
+                CommandMetadata::new(ValkeyCommandName::Rename)
+                    .write()
+                    .with_arity(3)
+                    .with_last_key(2)
+                    .multi_key(),
+            ),
+            (
+                "renamenx",
+                CommandMetadata::new(ValkeyCommandName::Renamenx)
+                    .write()
+                    .with_arity(3)
+                    .with_last_key(2)
+                    .multi_key(),
+            ),
+            (
+                "copy",
+                CommandMetadata::new(ValkeyCommandName::Copy)
+                    .write()
+                    .with_arity(-3)
+                    .with_last_key(2)
+                    .multi_key(),
+            ),
+            (
+                "move",
+                CommandMetadata::new(ValkeyCommandName::Move)
+                    .write()
+                    .with_arity(3),
+            ),
+            (
+                "type",
+                CommandMetadata::new(ValkeyCommandName::Type)
+                    .read_only()
+                    .with_arity(2),
+            ),
+            (
+                "persist",
+                CommandMetadata::new(ValkeyCommandName::Persist)
+                    .write()
+                    .with_arity(2),
+            ),
+            (
+                "touch",
+                CommandMetadata::new(ValkeyCommandName::Touch)
+                    .read_only()
+                    .with_arity(-2)
+                    .with_last_key(-1)
+                    .multi_key(),
+            ),
+            (
+                "unlink",
+                CommandMetadata::new(ValkeyCommandName::Unlink)
+                    .write()
+                    .with_arity(-2)
+                    .with_last_key(-1)
+                    .multi_key(),
+            ),
+            (
+                "randomkey",
+                CommandMetadata::new(ValkeyCommandName::Randomkey)
+                    .read_only()
+                    .with_arity(1)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0),
+            ),
+            (
+                "dump",
+                CommandMetadata::new(ValkeyCommandName::Dump)
+                    .read_only()
+                    .with_arity(2),
+            ),
+            (
+                "restore",
+                CommandMetadata::new(ValkeyCommandName::Restore)
+                    .write()
+                    .with_arity(-4),
+            ),
+            (
+                "migrate",
+                CommandMetadata::new(ValkeyCommandName::Migrate)
+                    .write()
+                    .with_arity(-6)
+                    .with_first_key(3)
+                    .with_last_key(3)
+                    .movable_keys(),
+            ),
+            (
+                "sort",
+                CommandMetadata::new(ValkeyCommandName::Sort)
+                    .write()
+                    .with_arity(-2)
+                    .movable_keys(),
+            ),
+            (
+                "sort_ro",
+                CommandMetadata::new(ValkeyCommandName::SortRo)
+                    .read_only()
+                    .with_arity(-2)
+                    .movable_keys(),
+            ),
+            (
+                "object",
+                CommandMetadata::new(ValkeyCommandName::Object)
+                    .read_only()
+                    .with_arity(-2)
+                    .with_first_key(2)
+                    .with_last_key(2),
+            ),
+            (
+                "memory",
+                CommandMetadata::new(ValkeyCommandName::Memory)
+                    .read_only()
+                    .with_arity(-2)
+                    .with_first_key(2)
+                    .with_last_key(2),
+            ),
             // Hash commands
             (
                 "hset",
@@ -934,6 +1253,72 @@ impl Default for CommandsManager {
                     .read_only()
                     .with_arity(3),
             ),
+            (
+                "hexpire",
+                CommandMetadata::new(ValkeyCommandName::Hexpire)
+                    .write()
+                    .with_arity(-6),
+            ),
+            (
+                "hpexpire",
+                CommandMetadata::new(ValkeyCommandName::Hpexpire)
+                    .write()
+                    .with_arity(-6),
+            ),
+            (
+                "hexpireat",
+                CommandMetadata::new(ValkeyCommandName::Hexpireat)
+                    .write()
+                    .with_arity(-6),
+            ),
+            (
+                "hpexpireat",
+                CommandMetadata::new(ValkeyCommandName::Hpexpireat)
+                    .write()
+                    .with_arity(-6),
+            ),
+            (
+                "httl",
+                CommandMetadata::new(ValkeyCommandName::Httl)
+                    .read_only()
+                    .with_arity(-5),
+            ),
+            (
+                "hpttl",
+                CommandMetadata::new(ValkeyCommandName::Hpttl)
+                    .read_only()
+                    .with_arity(-5),
+            ),
+            (
+                "hexpiretime",
+                CommandMetadata::new(ValkeyCommandName::Hexpiretime)
+                    .read_only()
+                    .with_arity(-5),
+            ),
+            (
+                "hpexpiretime",
+                CommandMetadata::new(ValkeyCommandName::Hpexpiretime)
+                    .read_only()
+                    .with_arity(-5),
+            ),
+            (
+                "hpersist",
+                CommandMetadata::new(ValkeyCommandName::Hpersist)
+                    .write()
+                    .with_arity(-5),
+            ),
+            (
+                "hgetex",
+                CommandMetadata::new(ValkeyCommandName::Hgetex)
+                    .write()
+                    .with_arity(-5),
+            ),
+            (
+                "hsetex",
+                CommandMetadata::new(ValkeyCommandName::Hsetex)
+                    .write()
+                    .with_arity(-6),
+            ),
             (
                 "multi",
                 CommandMetadata::new(ValkeyCommandName::Multi)
@@ -1038,7 +1423,8 @@ impl Default for CommandsManager {
                     .with_first_key(0)
                     .with_last_key(0)
                     .with_step(0)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "zdiffstore",
@@ -1048,7 +1434,8 @@ impl Default for CommandsManager {
                     .with_first_key(1)
                     .with_last_key(1)
                     .with_step(1)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "zinter",
@@ -1058,7 +1445,8 @@ impl Default for CommandsManager {
                     .with_first_key(0)
                     .with_last_key(0)
                     .with_step(0)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "zintercard",
@@ -1068,14 +1456,16 @@ impl Default for CommandsManager {
                     .with_first_key(0)
                     .with_last_key(0)
                     .with_step(0)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "zinterstore",
                 CommandMetadata::new(ValkeyCommandName::Zinterstore)
                     .write()
                     .with_arity(-4)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "zlexcount",
@@ -1091,7 +1481,8 @@ impl Default for CommandsManager {
                     .with_first_key(0)
                     .with_last_key(0)
                     .with_step(0)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "bzmpop",
@@ -1102,7 +1493,8 @@ impl Default for CommandsManager {
                     .with_first_key(0)
                     .with_last_key(0)
                     .with_step(0)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "zmscore",
@@ -1215,14 +1607,16 @@ impl Default for CommandsManager {
                     .with_first_key(0)
                     .with_last_key(0)
                     .with_step(0)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "zunionstore",
                 CommandMetadata::new(ValkeyCommandName::Zunionstore)
                     .write()
                     .with_arity(-4)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "zscore",
@@ -1240,14 +1634,22 @@ impl Default for CommandsManager {
                 "flushall",
                 CommandMetadata::new(ValkeyCommandName::FlushAll)
                     .write()
+                    .admin()
                     .with_arity(-1)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
                     .no_transaction(),
             ),
             (
                 "flushdb",
                 CommandMetadata::new(ValkeyCommandName::FlushDb)
                     .write()
+                    .admin()
                     .with_arity(-1)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
                     .no_transaction(),
             ),
             (
@@ -1256,6 +1658,24 @@ impl Default for CommandsManager {
                     .read_only()
                     .with_arity(1),
             ),
+            (
+                "wait",
+                CommandMetadata::new(ValkeyCommandName::Wait)
+                    .blocking()
+                    .with_arity(3)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0),
+            ),
+            (
+                "waitaof",
+                CommandMetadata::new(ValkeyCommandName::WaitAof)
+                    .blocking()
+                    .with_arity(4)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0),
+            ),
             (
                 "sadd",
                 CommandMetadata::new(ValkeyCommandName::Sadd)
@@ -1306,7 +1726,8 @@ impl Default for CommandsManager {
                     .with_first_key(1)
                     .with_last_key(-1)
                     .with_step(1)
-                    .multi_key(),
+                    .multi_key()
+                    .movable_keys(),
             ),
             (
                 "sinterstore",
@@ -1413,14 +1834,413 @@ impl Default for CommandsManager {
                 "slot",
                 CommandMetadata::new(ValkeyCommandName::Slot)
                     .read_only()
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
                     .no_transaction(),
             ),
             (
                 "cluster",
                 CommandMetadata::new(ValkeyCommandName::Cluster)
                     .read_only()
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
                     .no_transaction(),
             ),
+            (
+                "subscribe",
+                CommandMetadata::new(ValkeyCommandName::Subscribe)
+                    .pubsub()
+                    .with_arity(-2)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .no_transaction(),
+            ),
+            (
+                "psubscribe",
+                CommandMetadata::new(ValkeyCommandName::Psubscribe)
+                    .pubsub()
+                    .with_arity(-2)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .no_transaction(),
+            ),
+            (
+                "unsubscribe",
+                CommandMetadata::new(ValkeyCommandName::Unsubscribe)
+                    .pubsub()
+                    .with_arity(-1)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .no_transaction(),
+            ),
+            (
+                "punsubscribe",
+                CommandMetadata::new(ValkeyCommandName::Punsubscribe)
+                    .pubsub()
+                    .with_arity(-1)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .no_transaction(),
+            ),
+            (
+                "publish",
+                CommandMetadata::new(ValkeyCommandName::Publish)
+                    .pubsub()
+                    .with_arity(3)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .no_transaction(),
+            ),
+            (
+                "pubsub",
+                CommandMetadata::new(ValkeyCommandName::Pubsub)
+                    .pubsub()
+                    .with_arity(-2)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .no_transaction(),
+            ),
+            (
+                "ssubscribe",
+                CommandMetadata::new(ValkeyCommandName::Ssubscribe)
+                    .pubsub()
+                    .with_arity(-2)
+                    .with_first_key(1)
+                    .with_last_key(-1)
+                    .with_step(1)
+                    .no_transaction(),
+            ),
+            (
+                "sunsubscribe",
+                CommandMetadata::new(ValkeyCommandName::Sunsubscribe)
+                    .pubsub()
+                    .with_arity(-1)
+                    .with_first_key(1)
+                    .with_last_key(-1)
+                    .with_step(1)
+                    .no_transaction(),
+            ),
+            (
+                "spublish",
+                CommandMetadata::new(ValkeyCommandName::Spublish)
+                    .write()
+                    .pubsub()
+                    .with_arity(3)
+                    .with_first_key(1)
+                    .with_last_key(1)
+                    .with_step(1)
+                    .no_transaction(),
+            ),
+            // Stream commands
+            (
+                "xadd",
+                CommandMetadata::new(ValkeyCommandName::Xadd)
+                    .write()
+                    .with_arity(-5),
+            ),
+            (
+                "xrange",
+                CommandMetadata::new(ValkeyCommandName::Xrange)
+                    .read_only()
+                    .with_arity(-4),
+            ),
+            (
+                "xread",
+                CommandMetadata::new(ValkeyCommandName::Xread)
+                    .read_only()
+                    .blocking()
+                    .with_arity(-4)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .multi_key()
+                    .movable_keys(),
+            ),
+            (
+                "xtrim",
+                CommandMetadata::new(ValkeyCommandName::Xtrim)
+                    .write()
+                    .with_arity(-4),
+            ),
+            (
+                "xdel",
+                CommandMetadata::new(ValkeyCommandName::Xdel)
+                    .write()
+                    .with_arity(-3),
+            ),
+            (
+                "xlen",
+                CommandMetadata::new(ValkeyCommandName::Xlen)
+                    .read_only()
+                    .with_arity(2),
+            ),
+            (
+                "xgroup",
+                CommandMetadata::new(ValkeyCommandName::Xgroup)
+                    .write()
+                    .with_arity(-2)
+                    .with_first_key(2)
+                    .with_last_key(2),
+            ),
+            (
+                "xreadgroup",
+                CommandMetadata::new(ValkeyCommandName::Xreadgroup)
+                    .write()
+                    .blocking()
+                    .with_arity(-7)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .multi_key()
+                    .movable_keys(),
+            ),
+            (
+                "xack",
+                CommandMetadata::new(ValkeyCommandName::Xack)
+                    .write()
+                    .with_arity(-4),
+            ),
+            (
+                "xpending",
+                CommandMetadata::new(ValkeyCommandName::Xpending)
+                    .read_only()
+                    .with_arity(-3),
+            ),
+            (
+                "xclaim",
+                CommandMetadata::new(ValkeyCommandName::Xclaim)
+                    .write()
+                    .with_arity(-6),
+            ),
+            (
+                "xautoclaim",
+                CommandMetadata::new(ValkeyCommandName::Xautoclaim)
+                    .write()
+                    .with_arity(-6),
+            ),
+            // Scripting commands
+            (
+                "eval",
+                CommandMetadata::new(ValkeyCommandName::Eval)
+                    .write()
+                    .with_arity(-3)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .multi_key()
+                    .no_transaction()
+                    .movable_keys(),
+            ),
+            (
+                "evalsha",
+                CommandMetadata::new(ValkeyCommandName::Evalsha)
+                    .write()
+                    .with_arity(-3)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .multi_key()
+                    .no_transaction()
+                    .movable_keys(),
+            ),
+            (
+                "eval_ro",
+                CommandMetadata::new(ValkeyCommandName::EvalRo)
+                    .read_only()
+                    .with_arity(-3)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .multi_key()
+                    .no_transaction()
+                    .movable_keys(),
+            ),
+            (
+                "evalsha_ro",
+                CommandMetadata::new(ValkeyCommandName::EvalshaRo)
+                    .read_only()
+                    .with_arity(-3)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .multi_key()
+                    .no_transaction()
+                    .movable_keys(),
+            ),
+            (
+                "script",
+                CommandMetadata::new(ValkeyCommandName::Script)
+                    .read_only()
+                    .with_arity(-2)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0),
+            ),
+            (
+                "function",
+                CommandMetadata::new(ValkeyCommandName::Function)
+                    .read_only()
+                    .with_arity(-2)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .no_transaction(),
+            ),
+            (
+                "fcall",
+                CommandMetadata::new(ValkeyCommandName::Fcall)
+                    .write()
+                    .with_arity(-3)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .multi_key()
+                    .no_transaction()
+                    .movable_keys(),
+            ),
+            (
+                "fcall_ro",
+                CommandMetadata::new(ValkeyCommandName::FcallRo)
+                    .read_only()
+                    .with_arity(-3)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .multi_key()
+                    .no_transaction()
+                    .movable_keys(),
+            ),
+            // ACL commands
+            (
+                "auth",
+                CommandMetadata::new(ValkeyCommandName::Auth)
+                    .connection()
+                    .with_arity(-2)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .no_transaction(),
+            ),
+            (
+                "acl",
+                CommandMetadata::new(ValkeyCommandName::Acl)
+                    .admin()
+                    .with_arity(-2)
+                    .with_first_key(0)
+                    .with_last_key(0)
+                    .with_step(0)
+                    .no_transaction(),
+            ),
+            // Geo commands
+            (
+                "geoadd",
+                CommandMetadata::new(ValkeyCommandName::Geoadd)
+                    .write()
+                    .with_arity(-5),
+            ),
+            (
+                "geodist",
+                CommandMetadata::new(ValkeyCommandName::Geodist)
+                    .read_only()
+                    .with_arity(-4),
+            ),
+            (
+                "geopos",
+                CommandMetadata::new(ValkeyCommandName::Geopos)
+                    .read_only()
+                    .with_arity(-2),
+            ),
+            (
+                "geohash",
+                CommandMetadata::new(ValkeyCommandName::Geohash)
+                    .read_only()
+                    .with_arity(-2),
+            ),
+            (
+                "geosearch",
+                CommandMetadata::new(ValkeyCommandName::Geosearch)
+                    .read_only()
+                    .with_arity(-7),
+            ),
+            (
+                "geosearchstore",
+                CommandMetadata::new(ValkeyCommandName::Geosearchstore)
+                    .write()
+                    .with_arity(-8)
+                    .with_last_key(2)
+                    .multi_key(),
+            ),
+            // Bitmap commands
+            (
+                "setbit",
+                CommandMetadata::new(ValkeyCommandName::Setbit)
+                    .write()
+                    .with_arity(4),
+            ),
+            (
+                "getbit",
+                CommandMetadata::new(ValkeyCommandName::Getbit)
+                    .read_only()
+                    .with_arity(3),
+            ),
+            (
+                "bitcount",
+                CommandMetadata::new(ValkeyCommandName::Bitcount)
+                    .read_only()
+                    .with_arity(-2),
+            ),
+            (
+                "bitpos",
+                CommandMetadata::new(ValkeyCommandName::Bitpos)
+                    .read_only()
+                    .with_arity(-3),
+            ),
+            (
+                "bitop",
+                CommandMetadata::new(ValkeyCommandName::Bitop)
+                    .write()
+                    .with_arity(-4)
+                    .with_first_key(2)
+                    .with_last_key(-1)
+                    .multi_key(),
+            ),
+            (
+                "bitfield",
+                CommandMetadata::new(ValkeyCommandName::Bitfield)
+                    .write()
+                    .with_arity(-2),
+            ),
+            // HyperLogLog commands
+            (
+                "pfadd",
+                CommandMetadata::new(ValkeyCommandName::Pfadd)
+                    .write()
+                    .with_arity(-2),
+            ),
+            (
+                "pfcount",
+                CommandMetadata::new(ValkeyCommandName::Pfcount)
+                    .read_only()
+                    .with_arity(-2)
+                    .with_last_key(-1)
+                    .multi_key(),
+            ),
+            (
+                "pfmerge",
+                CommandMetadata::new(ValkeyCommandName::Pfmerge)
+                    .write()
+                    .with_arity(-2)
+                    .with_last_key(-1)
+                    .multi_key(),
+            ),
         ]);
 
         let cmds: HashMap<&str, Arc<CommandMetadata>> = cmds
@@ -0,0 +1,628 @@
+#[allow(unused_imports)]
+use crate::{
+    check_args_count, command_arg_at, command_arg_at_as_str,
+    commands::{HandleCommandResult, ScopedScriptExecution, ScriptCommands, Strings},
+    metadata::FunctionLibrary,
+    server::{ClientState, LuaEngine},
+    storage::{FunctionDb, FunctionLoadResult},
+    utils::{PatternMatcher, RespBuilderV2},
+    BytesMutUtils, LockManager, SableError, U8ArrayBuilder, U8ArrayReader, ValkeyCommand,
+    ValkeyCommandName,
+};
+
+use bytes::BytesMut;
+use std::rc::Rc;
+use std::sync::Mutex;
+use tokio::io::AsyncWriteExt;
+
+/// Version of the `FUNCTION DUMP` payload
+const FUNCTION_DUMP_VERSION: u8 = 1;
+
+/// Serialises the commands that modify the libraries (`LOAD`, `DELETE`, `FLUSH` and `RESTORE`).
+/// These commands first check for conflicts and then write, so they must not interleave
+static FUNCTIONS_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct FunctionCommands {}
+
+impl FunctionCommands {
+    pub async fn handle_command(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        _tx: &mut (impl AsyncWriteExt + std::marker::Unpin),
+    ) -> Result<HandleCommandResult, SableError> {
+        let mut response_buffer = BytesMut::with_capacity(256);
+        match command.metadata().name() {
+            ValkeyCommandName::Function => {
+                Self::function(client_state, command, &mut response_buffer).await?;
+            }
+            _ => {
+                return Err(SableError::InvalidArgument(format!(
+                    "Non function command {}",
+                    command.main_command()
+                )));
+            }
+        }
+        Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
+    }
+
+    /// Handle `FCALL` and `FCALL_RO`.
+    ///
+    /// Same as `EVAL`, these commands are dispatched directly from `Client::handle_command`
+    pub async fn handle_fcall(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        _tx: &mut (impl AsyncWriteExt + std::marker::Unpin),
+    ) -> Result<HandleCommandResult, SableError> {
+        let mut response_buffer = BytesMut::with_capacity(256);
+        match command.metadata().name() {
+            ValkeyCommandName::Fcall | ValkeyCommandName::FcallRo => {
+                Self::fcall(client_state, command, &mut response_buffer).await?;
+            }
+            _ => {
+                return Err(SableError::InvalidArgument(format!(
+                    "Non function command {}",
+                    command.main_command()
+                )));
+            }
+        }
+        Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
+    }
+
+    /// `FCALL function numkeys [key [key ...]] [arg [arg ...]]`
+    /// `FCALL_RO function numkeys [key [key ...]] [arg [arg ...]]`
+    ///
+    /// Same as `EVAL`, the declared keys are locked for the duration of the call and all the
+    /// writes are committed as a single batch. `FCALL_RO` can only call functions that were
+    /// registered with the `no-writes` flag
+    async fn fcall(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let builder = RespBuilderV2::default();
+        let function = command_arg_at!(command, 1);
+        let Some(numkeys) = ScriptCommands::parse_numkeys(&command, response_buffer)? else {
+            return Ok(());
+        };
+
+        let keys = &command.args_vec()[3..3 + numkeys];
+        let args = &command.args_vec()[3 + numkeys..];
+
+        let user_keys: Vec<&BytesMut> = keys.iter().collect();
+        let _unused =
+            LockManager::lock_multi(&user_keys, client_state.clone(), command.clone()).await?;
+
+        let library = FunctionDb::with_storage(client_state.database()).find_function(function)?;
+        let Some(library) = library else {
+            builder.error_string(response_buffer, Strings::ERR_FUNCTION_NOT_FOUND);
+            return Ok(());
+        };
+
+        let read_only = library
+            .function(function)
+            .map(|info| info.is_read_only())
+            .unwrap_or_default();
+        if matches!(command.metadata().name(), ValkeyCommandName::FcallRo) && !read_only {
+            builder.error_string(response_buffer, Strings::ERR_FCALL_RO_WRITE_FUNCTION);
+            return Ok(());
+        }
+
+        // A replica runs all the functions in read-only mode
+        let read_only = read_only
+            || client_state
+                .server_inner_state()
+                .persistent_state()
+                .is_replica();
+        let _exec_guard = ScopedScriptExecution::new(client_state.clone());
+        let reply = LuaEngine::fcall(
+            client_state.clone(),
+            &library,
+            function,
+            keys,
+            args,
+            read_only,
+        )
+        .await;
+
+        client_state.database().commit()?;
+        builder.add_resp_string(response_buffer, &reply?);
+        Ok(())
+    }
+
+    /// `FUNCTION LOAD [REPLACE] function-code`
+    /// `FUNCTION LIST [LIBRARYNAME library-name-pattern] [WITHCODE]`
+    /// `FUNCTION DELETE library-name`
+    /// `FUNCTION FLUSH [ASYNC | SYNC]`
+    /// `FUNCTION DUMP`
+    /// `FUNCTION RESTORE serialized-value [FLUSH | APPEND | REPLACE]`
+    async fn function(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let sub_command = command_arg_at_as_str!(command, 1);
+        let builder = RespBuilderV2::default();
+
+        // `FUNCTION` is marked as read-only so `LIST` and `DUMP` can be used on a replica. The
+        // sub commands that modify the libraries are rejected here
+        let is_replica = client_state
+            .server_inner_state()
+            .persistent_state()
+            .is_replica();
+        if is_replica
+            && matches!(
+                sub_command.as_str(),
+                "load" | "delete" | "flush" | "restore"
+            )
+        {
+            builder.error_string(response_buffer, Strings::WRITE_CMD_AGAINST_REPLICA);
+            return Ok(());
+        }
+
+        match sub_command.as_str() {
+            "load" => Self::function_load(client_state, command, response_buffer).await,
+            "list" => Self::function_list(client_state, command, response_buffer).await,
+            "delete" => Self::function_delete(client_state, command, response_buffer).await,
+            "flush" => Self::function_flush(client_state, command, response_buffer).await,
+            "dump" => Self::function_dump(client_state, command, response_buffer).await,
+            "restore" => Self::function_restore(client_state, command, response_buffer).await,
+            _ => {
+                builder.error_string(
+                    response_buffer,
+                    &format!(
+                        "ERR unknown subcommand '{}'. Try FUNCTION HELP.",
+                        sub_command.as_str()
+                    ),
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// `FUNCTION LOAD [REPLACE] function-code`
+    async fn function_load(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        let builder = RespBuilderV2::default();
+        let (replace, code) = match command.arg_count() {
+            3 => (false, command_arg_at!(command, 2)),
+            4 if command_arg_at_as_str!(command, 2) == "replace" => {
+                (true, command_arg_at!(command, 3))
+            }
+            4 => {
+                builder.error_string(
+                    response_buffer,
+                    &format!(
+                        "ERR Unknown option given: {}",
+                        BytesMutUtils::to_string(command_arg_at!(command, 2))
+                    ),
+                );
+                return Ok(());
+            }
+            _ => {
+                builder.error_string(
+                    response_buffer,
+                    "ERR wrong number of arguments for 'function|load' command",
+                );
+                return Ok(());
+            }
+        };
+
+        let library = match LuaEngine::parse_library(code) {
+            Ok(library) => library,
+            Err(msg) => {
+                builder.error_string(response_buffer, &msg);
+                return Ok(());
+            }
+        };
+
+        let _guard = FUNCTIONS_LOCK.lock().expect(Strings::POISONED_MUTEX);
+        let mut function_db = FunctionDb::with_storage(client_state.database());
+        if let Some(msg) = Self::load_error(function_db.load(&library, replace)?, &library) {
+            builder.error_string(response_buffer, &msg);
+            return Ok(());
+        }
+        function_db.commit()?;
+        builder.bulk_string(response_buffer, &library.name);
+        Ok(())
+    }
+
+    /// `FUNCTION LIST [LIBRARYNAME library-name-pattern] [WITHCODE]`
+    async fn function_list(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        let builder = RespBuilderV2::default();
+        let mut with_code = false;
+        let mut pattern: Option<&BytesMut> = None;
+        let mut iter = command.args_vec().iter().skip(2);
+        while let Some(arg) = iter.next() {
+            let arg_lowercase = BytesMutUtils::to_string(arg).to_lowercase();
+            match arg_lowercase.as_str() {
+                "withcode" => with_code = true,
+                "libraryname" => {
+                    let Some(value) = iter.next() else {
+                        builder.error_string(
+                            response_buffer,
+                            "ERR library name argument was not given",
+                        );
+                        return Ok(());
+                    };
+                    pattern = Some(value);
+                }
+                _ => {
+                    builder.error_string(
+                        response_buffer,
+                        &format!("ERR Unknown argument {}", BytesMutUtils::to_string(arg)),
+                    );
+                    return Ok(());
+                }
+            }
+        }
+
+        let matcher = if let Some(pattern) = pattern {
+            PatternMatcher::builder().wildcard(pattern).build()
+        } else {
+            PatternMatcher::builder().pass_through().build()
+        };
+
+        let libraries: Vec<FunctionLibrary> = FunctionDb::with_storage(client_state.database())
+            .libraries()?
+            .into_iter()
+            .filter(|library| matcher.matches(&library.name))
+            .collect();
+
+        builder.add_array_len(response_buffer, libraries.len());
+        for library in &libraries {
+            builder.add_array_len(response_buffer, if with_code { 8 } else { 6 });
+            builder.add_bulk_string(response_buffer, b"library_name");
+            builder.add_bulk_string(response_buffer, &library.name);
+            builder.add_bulk_string(response_buffer, b"engine");
+            builder.add_bulk_string(response_buffer, b"LUA");
+            builder.add_bulk_string(response_buffer, b"functions");
+            builder.add_array_len(response_buffer, library.functions.len());
+            for function in &library.functions {
+                builder.add_array_len(response_buffer, 6);
+                builder.add_bulk_string(response_buffer, b"name");
+                builder.add_bulk_string(response_buffer, &function.name);
+                builder.add_bulk_string(response_buffer, b"description");
+                builder.add_null_string(response_buffer);
+                builder.add_bulk_string(response_buffer, b"flags");
+                builder.add_array_len(response_buffer, function.flags.len());
+                for flag in &function.flags {
+                    builder.add_bulk_string(response_buffer, flag);
+                }
+            }
+            if with_code {
+                builder.add_bulk_string(response_buffer, b"library_code");
+                builder.add_bulk_string(response_buffer, &library.code);
+            }
+        }
+        Ok(())
+    }
+
+    /// `FUNCTION DELETE library-name`
+    async fn function_delete(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        let builder = RespBuilderV2::default();
+        if command.arg_count() != 3 {
+            builder.error_string(
+                response_buffer,
+                "ERR wrong number of arguments for 'function|delete' command",
+            );
+            return Ok(());
+        }
+
+        let _guard = FUNCTIONS_LOCK.lock().expect(Strings::POISONED_MUTEX);
+        let mut function_db = FunctionDb::with_storage(client_state.database());
+        if !function_db.delete(command_arg_at!(command, 2))? {
+            builder.error_string(response_buffer, Strings::ERR_LIBRARY_NOT_FOUND);
+            return Ok(());
+        }
+        function_db.commit()?;
+        builder.ok(response_buffer);
+        Ok(())
+    }
+
+    /// `FUNCTION FLUSH [ASYNC | SYNC]`
+    async fn function_flush(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        let builder = RespBuilderV2::default();
+        if command.arg_count() > 3 {
+            builder.error_string(
+                response_buffer,
+                "ERR wrong number of arguments for 'function|flush' command",
+            );
+            return Ok(());
+        }
+
+        if let Some(mode) = command.arg_as_lowercase_string(2) {
+            if mode != "async" && mode != "sync" {
+                builder.error_string(
+                    response_buffer,
+                    "ERR FUNCTION FLUSH only supports SYNC|ASYNC option",
+                );
+                return Ok(());
+            }
+        }
+
+        let _guard = FUNCTIONS_LOCK.lock().expect(Strings::POISONED_MUTEX);
+        let mut function_db = FunctionDb::with_storage(client_state.database());
+        function_db.delete_all()?;
+        function_db.commit()?;
+        builder.ok(response_buffer);
+        Ok(())
+    }
+
+    /// `FUNCTION DUMP`
+    ///
+    /// Return a binary payload with the code of all the libraries. The payload can be restored
+    /// with `FUNCTION RESTORE`
+    async fn function_dump(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        let builder = RespBuilderV2::default();
+        if command.arg_count() != 2 {
+            builder.error_string(
+                response_buffer,
+                "ERR wrong number of arguments for 'function|dump' command",
+            );
+            return Ok(());
+        }
+
+        let libraries = FunctionDb::with_storage(client_state.database()).libraries()?;
+        builder.bulk_string(response_buffer, &Self::dump_payload(&libraries));
+        Ok(())
+    }
+
+    /// `FUNCTION RESTORE serialized-value [FLUSH | APPEND | REPLACE]`
+    ///
+    /// Restore the libraries from a payload created by `FUNCTION DUMP`. The libraries are
+    /// restored atomically: if one of them can not be restored, none is
+    async fn function_restore(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        let builder = RespBuilderV2::default();
+        if command.arg_count() != 3 && command.arg_count() != 4 {
+            builder.error_string(
+                response_buffer,
+                "ERR wrong number of arguments for 'function|restore' command",
+            );
+            return Ok(());
+        }
+
+        let policy = command
+            .arg_as_lowercase_string(3)
+            .unwrap_or_else(|| "append".to_string());
+        if !matches!(policy.as_str(), "flush" | "append" | "replace") {
+            builder.error_string(
+                response_buffer,
+                "ERR Wrong restore policy given, value should be either FLUSH, APPEND or REPLACE.",
+            );
+            return Ok(());
+        }
+
+        let Some(codes) = Self::parse_payload(command_arg_at!(command, 2)) else {
+            builder.error_string(response_buffer, Strings::ERR_FUNCTION_PAYLOAD);
+            return Ok(());
+        };
+
+        let mut libraries = Vec::<FunctionLibrary>::with_capacity(codes.len());
+        for code in &codes {
+            match LuaEngine::parse_library(code) {
+                Ok(library) => libraries.push(library),
+                Err(msg) => {
+                    builder.error_string(response_buffer, &msg);
+                    return Ok(());
+                }
+            }
+        }
+
+        let _guard = FUNCTIONS_LOCK.lock().expect(Strings::POISONED_MUTEX);
+        let mut function_db = FunctionDb::with_storage(client_state.database());
+        if policy == "flush" {
+            function_db.delete_all()?;
+        }
+
+        for library in &libraries {
+            let result = function_db.load(library, policy == "replace")?;
+            if let Some(msg) = Self::load_error(result, library) {
+                // Nothing was written yet, dropping `function_db` discards the changes
+                builder.error_string(response_buffer, &msg);
+                return Ok(());
+            }
+        }
+        function_db.commit()?;
+        builder.ok(response_buffer);
+        Ok(())
+    }
+
+    /// Convert a failed `FunctionDb::load` into an error message
+    fn load_error(result: FunctionLoadResult, library: &FunctionLibrary) -> Option<String> {
+        match result {
+            FunctionLoadResult::Ok => None,
+            FunctionLoadResult::LibraryExists => Some(format!(
+                "ERR Library '{}' already exists",
+                BytesMutUtils::to_string(&library.name)
+            )),
+            FunctionLoadResult::FunctionExists(name) => Some(format!(
+                "ERR Function {} already exists",
+                BytesMutUtils::to_string(&name)
+            )),
+        }
+    }
+
+    /// Build the `FUNCTION DUMP` payload:
+    ///
+    /// ```no_compile
+    /// [version (u8) | count (usize) | code (message) ... | CRC16 (u16)]
+    /// ```
+    fn dump_payload(libraries: &[FunctionLibrary]) -> BytesMut {
+        let mut payload = BytesMut::default();
+        let mut builder = U8ArrayBuilder::with_buffer(&mut payload);
+        builder.write_u8(FUNCTION_DUMP_VERSION);
+        builder.write_usize(libraries.len());
+        for library in libraries {
+            builder.write_message(&library.code);
+        }
+        let checksum = crc16::State::<crc16::XMODEM>::calculate(&payload);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut payload);
+        builder.write_u16(checksum);
+        payload
+    }
+
+    /// Parse a `FUNCTION DUMP` payload and return the libraries code. Return `None` if the
+    /// payload is corrupted or was created by an unsupported version
+    fn parse_payload(payload: &[u8]) -> Option<Vec<BytesMut>> {
+        let crc_pos = payload.len().checked_sub(std::mem::size_of::<u16>())?;
+        let (content, checksum) = payload.split_at(crc_pos);
+        let checksum = U8ArrayReader::with_buffer(checksum).read_u16()?;
+        if checksum != crc16::State::<crc16::XMODEM>::calculate(content) {
+            return None;
+        }
+
+        let mut reader = U8ArrayReader::with_buffer(content);
+        if reader.read_u8()? != FUNCTION_DUMP_VERSION {
+            return None;
+        }
+
+        let count = reader.read_usize()?;
+        let mut codes = Vec::<BytesMut>::new();
+        for _ in 0..count {
+            codes.push(reader.read_message()?);
+        }
+        Some(codes)
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::ClientNextAction, Client, ServerState};
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    #[test_case(vec![
+        (vec!["function", "load", "#!lua name=mylib\nredis.register_function('myfunc', function(keys, args) return args[1] end)"], "$5\r\nmylib\r\n"),
+        (vec!["function", "load", "#!lua name=mylib\nredis.register_function('myfunc', function(keys, args) return args[1] end)"], "-ERR Library 'mylib' already exists\r\n"),
+        (vec!["fcall", "myfunc", "0", "hello"], "$5\r\nhello\r\n"),
+        (vec!["fcall", "nosuchfunc", "0"], "-ERR Function not found\r\n"),
+        (vec!["fcall", "myfunc", "1"], "-ERR Number of keys can't be greater than number of args\r\n"),
+        (vec!["function", "load", "return 1"], "-ERR Missing library metadata\r\n"),
+        (vec!["function", "load", "#!js name=lib2"], "-ERR Engine 'js' not found\r\n"),
+        (vec!["function", "load", "#!lua lib2"], "-ERR Invalid metadata value given: lib2\r\n"),
+        (vec!["function", "load", "#!lua\nreturn 1"], "-ERR Library name was not given\r\n"),
+        (vec!["function", "load", "#!lua name=lib2\nreturn 1"], "-ERR No functions registered\r\n"),
+        (vec!["function", "load", "#!lua name=lib2\nredis.register_function('my-func', function() end)"],
+            "-ERR Function names can only contain letters, numbers, or underscores(_) and must be at least one character long\r\n"),
+        (vec!["function", "load", "#!lua name=lib2\nredis.register_function{function_name='f2', callback=function() end, flags={'no-such-flag'}}"],
+            "-ERR unknown flag given\r\n"),
+        (vec!["function", "load", "#!lua name=lib2\nredis.register_function('myfunc', function() return 1 end)"], "-ERR Function myfunc already exists\r\n"),
+        (vec!["function", "load", "replace", "#!lua name=mylib\nredis.register_function{function_name='myfunc', callback=function(keys, args) return redis.call('set', keys[1], args[1]) end}"], "$5\r\nmylib\r\n"),
+        (vec!["fcall", "myfunc", "1", "k1", "v1"], "+OK\r\n"),
+        (vec!["get", "k1"], "$2\r\nv1\r\n"),
+        (vec!["fcall_ro", "myfunc", "1", "k1", "v1"], "-ERR Can not execute a script with write flag using *_ro command.\r\n"),
+        (vec!["function", "load", "#!lua name=rolib\nredis.register_function{function_name='getter', callback=function(keys) return redis.call('get', keys[1]) end, flags={'no-writes'}}\nredis.register_function{function_name='setter', callback=function(keys) return redis.call('set', keys[1], 'x') end, flags={'no-writes'}}"], "$5\r\nrolib\r\n"),
+        (vec!["fcall_ro", "getter", "1", "k1"], "$2\r\nv1\r\n"),
+        (vec!["fcall", "setter", "1", "k1"], "-ERR Write commands are not allowed from read-only scripts.\r\n"),
+        (vec!["function", "nosuchcommand"], "-ERR unknown subcommand 'nosuchcommand'. Try FUNCTION HELP.\r\n"),
+        ], "function_load"; "function_load")]
+    #[test_case(vec![
+        (vec!["function", "load", "#!lua name=lib1\nredis.register_function{function_name='f1', callback=function() return 1 end, flags={'no-writes'}}"], "$4\r\nlib1\r\n"),
+        (vec!["function", "list"], "*1\r\n*6\r\n$12\r\nlibrary_name\r\n$4\r\nlib1\r\n$6\r\nengine\r\n$3\r\nLUA\r\n$9\r\nfunctions\r\n*1\r\n*6\r\n$4\r\nname\r\n$2\r\nf1\r\n$11\r\ndescription\r\n$-1\r\n$5\r\nflags\r\n*1\r\n$9\r\nno-writes\r\n"),
+        (vec!["function", "list", "libraryname", "nomatch*"], "*0\r\n"),
+        (vec!["function", "list", "libraryname"], "-ERR library name argument was not given\r\n"),
+        (vec!["function", "load", "#!lua name=lib2\nredis.register_function('f2', function() end)"], "$4\r\nlib2\r\n"),
+        (vec!["function", "list", "withcode", "libraryname", "*2"], "*1\r\n*8\r\n$12\r\nlibrary_name\r\n$4\r\nlib2\r\n$6\r\nengine\r\n$3\r\nLUA\r\n$9\r\nfunctions\r\n*1\r\n*6\r\n$4\r\nname\r\n$2\r\nf2\r\n$11\r\ndescription\r\n$-1\r\n$5\r\nflags\r\n*0\r\n$12\r\nlibrary_code\r\n$61\r\n#!lua name=lib2\nredis.register_function('f2', function() end)\r\n"),
+        (vec!["function", "delete", "lib1"], "+OK\r\n"),
+        (vec!["function", "delete", "lib1"], "-ERR Library not found\r\n"),
+        (vec!["fcall", "f1", "0"], "-ERR Function not found\r\n"),
+        (vec!["function", "restore", "corrupted"], "-ERR payload version or checksum are wrong\r\n"),
+        (vec!["function", "flush"], "+OK\r\n"),
+        (vec!["function", "list"], "*0\r\n"),
+        ], "function_list"; "function_list")]
+    #[test_case(vec![
+        (vec!["multi"], "+OK\r\n"),
+        (vec!["fcall", "myfunc", "0"], "-ERR command fcall can not be used in a MULTI / EXEC block\r\n"),
+        (vec!["discard"], "+OK\r\n"),
+        ], "fcall_in_multi"; "fcall_in_multi")]
+    fn test_function_commands(
+        args_vec: Vec<(Vec<&'static str>, &'static str)>,
+        test_name: &str,
+    ) -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            for (args, expected_value) in args_vec {
+                let mut sink = crate::tests::ResponseSink::with_name(test_name).await;
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                match Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                    .await
+                    .unwrap()
+                {
+                    ClientNextAction::NoAction => {
+                        assert_eq!(sink.read_all().await.as_str(), expected_value);
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_function_dump_payload() {
+        let libraries = vec![
+            FunctionLibrary {
+                name: BytesMut::from("lib1"),
+                code: BytesMut::from(
+                    "#!lua name=lib1\nredis.register_function('f1', function() end)",
+                ),
+                functions: Vec::default(),
+            },
+            FunctionLibrary {
+                name: BytesMut::from("lib2"),
+                code: BytesMut::from(
+                    "#!lua name=lib2\nredis.register_function('f2', function() end)",
+                ),
+                functions: Vec::default(),
+            },
+        ];
+
+        let mut payload = FunctionCommands::dump_payload(&libraries);
+        let codes = FunctionCommands::parse_payload(&payload).unwrap();
+        assert_eq!(
+            codes,
+            vec![libraries[0].code.clone(), libraries[1].code.clone()]
+        );
+
+        // Corrupt the payload
+        payload[1] ^= 0xFF;
+        assert!(FunctionCommands::parse_payload(&payload).is_none());
+        assert!(FunctionCommands::parse_payload(&[]).is_none());
+    }
+}
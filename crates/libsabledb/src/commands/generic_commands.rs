@@ -3,20 +3,33 @@ use crate::{
     commands::HandleCommandResult,
     metadata::CommonValueMetadata,
     metadata::{KeyType, ValueType},
-    server::ClientState,
-    storage::{GenericDb, ScanCursor},
-    utils::{PatternMatcher, RespBuilderV2},
-    BytesMutUtils, LockManager, PrimaryKeyMetadata, SableError, U8ArrayBuilder, ValkeyCommand,
-    ValkeyCommandName,
+    replication::{MigrateKeysResult, MigratedKey, NodeTalkClient},
+    server::{ClientState, Telemetry},
+    storage::{
+        DumpValueResult, GenericDb, HashDb, HashGetResult, ScanCursor, StringGetResult, StringsDb,
+    },
+    utils::{calculate_slot, PatternMatcher, RdbError, RdbValue, RespBuilderV2},
+    BytesMutUtils, LockManager, PrimaryKeyMetadata, SableError, TimeUtils, U8ArrayBuilder,
+    ValkeyCommand, ValkeyCommandName,
 };
 
 use bytes::BytesMut;
+use std::collections::BTreeSet;
 use std::rc::Rc;
 use tokio::io::AsyncWriteExt;
 
 pub struct GenericCommands {}
 
+/// The value used for ordering an element by `SORT`
+enum SortKey {
+    Score(f64),
+    Alpha(Option<BytesMut>),
+}
+
 impl GenericCommands {
+    /// Strings up to this length are reported as `embstr` by `OBJECT ENCODING`
+    const EMBSTR_SIZE_LIMIT: usize = 44;
+
     pub async fn handle_command(
         client_state: Rc<ClientState>,
         command: Rc<ValkeyCommand>,
@@ -24,16 +37,22 @@ impl GenericCommands {
     ) -> Result<HandleCommandResult, SableError> {
         let mut response_buffer = BytesMut::with_capacity(256);
         match command.metadata().name() {
-            ValkeyCommandName::Ttl => {
+            ValkeyCommandName::Ttl
+            | ValkeyCommandName::Pttl
+            | ValkeyCommandName::Expiretime
+            | ValkeyCommandName::Pexpiretime => {
                 Self::ttl(client_state, command, &mut response_buffer).await?;
             }
-            ValkeyCommandName::Del => {
+            ValkeyCommandName::Del | ValkeyCommandName::Unlink => {
                 Self::del(client_state, command, &mut response_buffer).await?;
             }
             ValkeyCommandName::Exists => {
                 Self::exists(client_state, command, &mut response_buffer).await?;
             }
-            ValkeyCommandName::Expire => {
+            ValkeyCommandName::Expire
+            | ValkeyCommandName::Pexpire
+            | ValkeyCommandName::Expireat
+            | ValkeyCommandName::Pexpireat => {
                 Self::expire(client_state, command, &mut response_buffer).await?;
             }
             ValkeyCommandName::Keys => {
@@ -42,6 +61,45 @@ impl GenericCommands {
             ValkeyCommandName::Scan => {
                 Self::scan(client_state, command, &mut response_buffer).await?;
             }
+            ValkeyCommandName::Rename | ValkeyCommandName::Renamenx => {
+                Self::rename(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Copy => {
+                Self::copy(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Move => {
+                Self::move_key(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Type => {
+                Self::key_type(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Persist => {
+                Self::persist(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Touch => {
+                Self::touch(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Randomkey => {
+                Self::randomkey(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Dump => {
+                Self::dump(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Restore => {
+                Self::restore(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Migrate => {
+                Self::migrate(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Sort | ValkeyCommandName::SortRo => {
+                Self::sort(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Object => {
+                Self::object(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Memory => {
+                Self::memory(client_state, command, &mut response_buffer).await?;
+            }
             _ => {
                 return Err(SableError::InvalidArgument(format!(
                     "Non generic command {}",
@@ -52,6 +110,7 @@ impl GenericCommands {
         Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
     }
 
+    /// `DEL` and `UNLINK`:
     /// O(N) where N is the number of keys that will be removed. When a key to remove holds a value other than a string,
     /// the individual complexity remains O(1) and the deletion of the element keys is done in a background thread
     async fn del(
@@ -105,7 +164,8 @@ impl GenericCommands {
         Ok(())
     }
 
-    /// Returns the remaining time to live of a key that has a timeout.
+    /// `TTL key` (and `PTTL`, `EXPIRETIME`, `PEXPIRETIME`)
+    /// Returns the remaining time to live of a key that has a timeout (or its expiration time).
     /// This introspection capability allows a Valkey client to check how
     /// many seconds a given key will continue to be part of the dataset.
     async fn ttl(
@@ -120,20 +180,26 @@ impl GenericCommands {
         let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
         let mut generic_db =
             GenericDb::with_storage(client_state.database(), client_state.database_id());
-        if let Some((_, value_metadata)) = generic_db.get(key)? {
-            if !value_metadata.expiration().has_ttl() {
-                // No timeout
-                builder.number_i64(response_buffer, -1);
-            } else {
-                builder.number_u64(
-                    response_buffer,
-                    value_metadata.expiration().ttl_in_seconds()?,
-                );
-            }
-        } else {
+        let Some((_, value_metadata)) = generic_db.get(key)? else {
             // The command returns -2 if the key does not exist.
             builder.number_i64(response_buffer, -2);
-        }
+            return Ok(());
+        };
+
+        let expiration = value_metadata.expiration();
+        let Some(expire_timestamp_ms) = expiration.expire_timestamp_millis() else {
+            // No timeout
+            builder.number_i64(response_buffer, -1);
+            return Ok(());
+        };
+
+        let value = match command.metadata().name() {
+            ValkeyCommandName::Pttl => expiration.ttl_in_millis()?,
+            ValkeyCommandName::Expiretime => expire_timestamp_ms.div_ceil(1000),
+            ValkeyCommandName::Pexpiretime => expire_timestamp_ms,
+            _ => expiration.ttl_in_seconds()?,
+        };
+        builder.number_u64(response_buffer, value);
         Ok(())
     }
 
@@ -165,6 +231,10 @@ impl GenericCommands {
         Ok(())
     }
 
+    /// `EXPIRE key seconds [NX | XX | GT | LT]`
+    /// (and `PEXPIRE`, `EXPIREAT`, `PEXPIREAT`)
+    /// Set a timeout on key. A timeout in the past deletes the key. Return `1` if the timeout was
+    /// set, `0` if the key does not exist or the condition was not met
     async fn expire(
         client_state: Rc<ClientState>,
         command: Rc<ValkeyCommand>,
@@ -174,16 +244,35 @@ impl GenericCommands {
         check_args_count!(command, 3, response_buffer);
         let builder = RespBuilderV2::default();
 
-        // EXPIRE key seconds [NX | XX | GT | LT]
         let key = command_arg_at!(command, 1);
-        let seconds = command_arg_at!(command, 2);
+        let time = command_arg_at!(command, 2);
 
-        // Convert into seconds
-        let Some(seconds) = BytesMutUtils::parse::<u64>(seconds) else {
+        let Some(time) = BytesMutUtils::parse::<i64>(time) else {
             builder.error_string(response_buffer, Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE);
             return Ok(());
         };
 
+        // Convert into an expiration timestamp in milliseconds. Timestamps before UNIX_EPOCH are
+        // in the past, just like UNIX_EPOCH
+        let now = TimeUtils::epoch_ms()? as i64;
+        let expire_timestamp_ms = match command.metadata().name() {
+            ValkeyCommandName::Expire => time.checked_mul(1000).and_then(|ms| ms.checked_add(now)),
+            ValkeyCommandName::Pexpire => time.checked_add(now),
+            ValkeyCommandName::Expireat => time.checked_mul(1000),
+            _ => Some(time),
+        };
+        let Some(expire_timestamp_ms) = expire_timestamp_ms else {
+            builder.error_string(
+                response_buffer,
+                &format!(
+                    "ERR invalid expire time in '{}' command",
+                    command.main_command()
+                ),
+            );
+            return Ok(());
+        };
+        let expire_timestamp_ms = expire_timestamp_ms.max(0) as u64;
+
         let db_id = client_state.database_id();
         let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
         let mut generic_db = GenericDb::with_storage(client_state.database(), db_id);
@@ -194,63 +283,39 @@ impl GenericCommands {
             return Ok(());
         };
 
-        // If no other param was provided, set the ttl in seconds and leave
-        let Some(arg) = command.arg(3) else {
-            expiration.set_ttl_seconds(seconds)?;
-            generic_db.put_expiration(key, &expiration, true)?;
-            builder.number_usize(response_buffer, 1);
-            return Ok(());
-        };
-
-        let arg = BytesMutUtils::to_string(arg).to_lowercase();
-        match arg.as_str() {
-            "nx" => {
-                // NX -- Set expiry only when the key has no expiry
-                if !expiration.has_ttl() {
-                    expiration.set_ttl_seconds(seconds)?;
-                    generic_db.put_expiration(key, &expiration, true)?;
-                    builder.number_usize(response_buffer, 1);
-                } else {
-                    builder.number_usize(response_buffer, 0);
-                }
-            }
-            "xx" => {
-                // XX -- Set expiry only when the key has an existing expiry
-                if expiration.has_ttl() {
-                    expiration.set_ttl_seconds(seconds)?;
-                    generic_db.put_expiration(key, &expiration, true)?;
-                    builder.number_usize(response_buffer, 1);
-                } else {
-                    builder.number_usize(response_buffer, 0);
-                }
-            }
-            "gt" => {
-                // GT -- Set expiry only when the new expiry is greater than current one
-                if seconds > expiration.ttl_in_seconds()? {
-                    expiration.set_ttl_seconds(seconds)?;
-                    generic_db.put_expiration(key, &expiration, true)?;
-                    builder.number_usize(response_buffer, 1);
-                } else {
-                    builder.number_usize(response_buffer, 0);
-                }
-            }
-            "lt" => {
-                // LT -- Set expiry only when the new expiry is less than current one
-                if seconds < expiration.ttl_in_seconds()? {
-                    expiration.set_ttl_seconds(seconds)?;
-                    generic_db.put_expiration(key, &expiration, true)?;
-                    builder.number_usize(response_buffer, 1);
-                } else {
-                    builder.number_usize(response_buffer, 0);
-                }
-            }
-            option => {
+        // A key without a timeout never expires
+        let current = expiration.expire_timestamp_millis();
+        let condition_met = match command.arg_as_lowercase_string(3).as_deref() {
+            None => true,
+            // NX -- Set expiry only when the key has no expiry
+            Some("nx") => current.is_none(),
+            // XX -- Set expiry only when the key has an existing expiry
+            Some("xx") => current.is_some(),
+            // GT -- Set expiry only when the new expiry is greater than current one
+            Some("gt") => current.is_some_and(|current| expire_timestamp_ms > current),
+            // LT -- Set expiry only when the new expiry is less than current one
+            Some("lt") => !current.is_some_and(|current| expire_timestamp_ms >= current),
+            Some(option) => {
                 builder.error_string(
                     response_buffer,
                     format!("ERR Unsupported option {}", option).as_str(),
                 );
+                return Ok(());
             }
+        };
+
+        if !condition_met {
+            builder.number_usize(response_buffer, 0);
+            return Ok(());
+        }
+
+        if expire_timestamp_ms <= now as u64 {
+            generic_db.delete(key, true)?;
+        } else {
+            expiration.set_expire_timestamp_millis(expire_timestamp_ms)?;
+            generic_db.put_expiration(key, &expiration, true)?;
         }
+        builder.number_usize(response_buffer, 1);
         Ok(())
     }
 
@@ -449,140 +514,1490 @@ impl GenericCommands {
         Ok(())
     }
 
-    /// Scan helper function: return true if the `encoded_key` / `encoded_value` pair are candidates for the scan command
-    fn should_collect(
-        encoded_key: &[u8],
-        encoded_value: &[u8],
-        matcher: &PatternMatcher,
-        requested_obj_type: &Option<ValueType>,
-    ) -> Result<Option<BytesMut>, SableError> {
-        let user_key = PrimaryKeyMetadata::from_raw(encoded_key)?
-            .user_key()
-            .clone();
+    /// `RENAME key newkey` / `RENAMENX key newkey`
+    /// Rename `key` to `newkey`. `RENAME` overwrites `newkey` if it exists, `RENAMENX` returns `0`
+    /// in this case
+    async fn rename(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+        let new_key = command_arg_at!(command, 2);
+        let only_if_not_exists = command.metadata().name() == &ValkeyCommandName::Renamenx;
 
-        // Filter by object type if needed
-        if let Some(requested_obj_type) = requested_obj_type {
-            let cmd = CommonValueMetadata::try_from(encoded_value)?;
-            if requested_obj_type.ne(&cmd.value_type()) {
-                return Ok(None);
+        let _unused =
+            LockManager::lock_multi(&[key, new_key], client_state.clone(), command.clone()).await?;
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+
+        if generic_db.get(key)?.is_none() {
+            builder.error_string(response_buffer, Strings::NO_SUCH_KEY);
+            return Ok(());
+        }
+
+        if key == new_key {
+            if only_if_not_exists {
+                builder.number_usize(response_buffer, 0);
+            } else {
+                builder.ok(response_buffer);
             }
+            return Ok(());
         }
 
-        Ok(if matcher.matches(&user_key) {
-            Some(user_key)
+        if only_if_not_exists && generic_db.get(new_key)?.is_some() {
+            builder.number_usize(response_buffer, 0);
+            return Ok(());
+        }
+
+        generic_db.rename(key, new_key)?;
+        if only_if_not_exists {
+            builder.number_usize(response_buffer, 1);
         } else {
-            None
-        })
+            builder.ok(response_buffer);
+        }
+        Ok(())
     }
-}
-
-//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
-// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
-// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
-// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
-// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
-//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
-//
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::{commands::ClientNextAction, Client, ServerState};
-    use std::rc::Rc;
-    use std::sync::Arc;
-    use test_case::test_case;
 
-    #[test_case(vec![
-        ("set mystr myvalue", "+OK\r\n"),
-        ("set mystr2 myvalue2", "+OK\r\n"),
-        ("lpush mylist_1 a b c", ":3\r\n"),
-        ("lpush mylist_2 a b c", ":3\r\n"),
-        ("del mystr mystr2 mylist_1 mylist_2", ":4\r\n"),
-        ("get mystr", "$-1\r\n"),
-        ("get mystr2", "$-1\r\n"),
-        ("llen mylist_1", ":0\r\n"),
-        ("llen mylist_2", ":0\r\n"),
-        ("del mylist_2", ":0\r\n"),
-    ]; "test_del")]
-    #[test_case(vec![
-        ("set mykey1 myvalue", "+OK\r\n"),
-        ("set mykey2 myvalue1", "+OK\r\n"),
-        ("exists mykey1 mykey2", ":2\r\n"),
-        ("exists mykey1 mykey2 mykey1", ":3\r\n"),
-        ("exists no_such_key mykey2 mykey1", ":2\r\n"),
-    ]; "test_exists")]
-    #[test_case(vec![
-        ("set mykey1 myvalue", "+OK\r\n"),
-        ("expire mykey1 100", ":1\r\n"),
-        ("get mykey1", "$7\r\nmyvalue\r\n"),
-        ("set mykey2 myvalue EX 100", "+OK\r\n"),
-        ("expire mykey2 90 GT", ":0\r\n"),
-        ("expire mykey2 120 GT", ":1\r\n"),
-        ("get mykey2", "$7\r\nmyvalue\r\n"),
-        ("set mykey3 myvalue EX 100", "+OK\r\n"),
-        ("expire mykey3 123 LT", ":0\r\n"),
-        ("expire mykey3 90 LT", ":1\r\n"),
-        ("get mykey3", "$7\r\nmyvalue\r\n"),
-        ("set mykey4 myvalue EX 100", "+OK\r\n"),
-        ("expire mykey4 120 NX", ":0\r\n"),
-        ("expire mykey4 120 XX", ":1\r\n"),
-        ("set mykey5 myvalue", "+OK\r\n"),
-        ("expire mykey5 120 XX", ":0\r\n"),
-        ("expire mykey5 120 NX", ":1\r\n"),
-    ]; "test_expire")]
-    #[test_case(vec![
-        ("select 0", "+OK\r\n"),
-        ("set k1 b", "+OK\r\n"),
-        ("set k2 d", "+OK\r\n"),
-        ("set k3 f", "+OK\r\n"),
-        ("hset myhash 1 2 3 4 5 6", ":3\r\n"),
-        ("keys *", "*4\r\n$2\r\nk2\r\n$2\r\nk3\r\n$6\r\nmyhash\r\n$2\r\nk1\r\n"),
-        ("select 1", "+OK\r\n"),
-        ("keys *", "*0\r\n"),
-        ("keys", "-ERR wrong number of arguments for 'keys' command\r\n"),
-        ("select 0", "+OK\r\n"),
-        ("keys k*", "*3\r\n$2\r\nk2\r\n$2\r\nk3\r\n$2\r\nk1\r\n"),
-        ("keys ??", "*3\r\n$2\r\nk2\r\n$2\r\nk3\r\n$2\r\nk1\r\n"),
-        ("keys myhash", "*1\r\n$6\r\nmyhash\r\n"),
-    ]; "test_keys")]
-    #[test_case(vec![
-        ("set k1 b", "+OK\r\n"),
-        ("set k2 d", "+OK\r\n"),
-        ("set k3 f", "+OK\r\n"),
-        ("set k4 f", "+OK\r\n"),
-        ("hset myhash 1 2 3 4 5 6", ":3\r\n"),
-        // fetch all keys
-        ("scan 0 COUNT 10", "*2\r\n:0\r\n*5\r\n$2\r\nk2\r\n$2\r\nk3\r\n$2\r\nk4\r\n$6\r\nmyhash\r\n$2\r\nk1\r\n"),
-        // fetch all string keys ("myhash" is excluded)
-        ("scan 0 COUNT 10 TYPE string", "*2\r\n:0\r\n*4\r\n$2\r\nk2\r\n$2\r\nk3\r\n$2\r\nk4\r\n$2\r\nk1\r\n"),
-        // fetch all hash keys (only "myhash")
-        ("scan 0 COUNT 10 TYPE hash", "*2\r\n:0\r\n*1\r\n$6\r\nmyhash\r\n"),
-        // All keys starting with k regardless of their type
-        ("scan 0 COUNT 10 MATCH k*", "*2\r\n:0\r\n*4\r\n$2\r\nk2\r\n$2\r\nk3\r\n$2\r\nk4\r\n$2\r\nk1\r\n"),
-        ("scan 0 COUNT 10 MATCH *ha*", "*2\r\n:0\r\n*1\r\n$6\r\nmyhash\r\n")
-    ]; "test_scan")]
-    fn test_generic_commands(args: Vec<(&'static str, &'static str)>) -> Result<(), SableError> {
-        let rt = tokio::runtime::Runtime::new().unwrap();
-        rt.block_on(async move {
-            let (_guard, store) = crate::tests::open_store();
-            let client = Client::new(Arc::<ServerState>::default(), store, None);
+    /// `COPY source destination [DB destination-db] [REPLACE]`
+    /// Copy the value stored at `source` to `destination`. Return `1` if the value was copied, `0`
+    /// if `source` does not exist or `destination` exists and `REPLACE` was not specified
+    async fn copy(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let builder = RespBuilderV2::default();
+        let source = command_arg_at!(command, 1);
+        let destination = command_arg_at!(command, 2);
 
-            for (args, expected_value) in args {
-                let mut sink = crate::io::FileResponseSink::new().await.unwrap();
-                let args = args.split(' ').collect();
-                let cmd = Rc::new(ValkeyCommand::for_test(args));
-                match Client::handle_command(client.inner(), cmd, &mut sink.fp)
-                    .await
-                    .unwrap()
-                {
-                    ClientNextAction::NoAction => {
-                        assert_eq!(
-                            sink.read_all_as_string().await.unwrap().as_str(),
-                            expected_value
-                        );
-                    }
-                    _ => {}
+        let mut target_db_id = client_state.database_id();
+        let mut replace = false;
+        let mut pos = 3usize;
+        while let Some(arg) = command.arg_as_lowercase_string(pos) {
+            match arg.as_str() {
+                "replace" => {
+                    replace = true;
+                    pos = pos.saturating_add(1);
+                }
+                "db" => {
+                    let Some(db_id) = command.arg(pos + 1).and_then(BytesMutUtils::parse::<u16>)
+                    else {
+                        builder_return_value_not_int!(builder, response_buffer);
+                    };
+                    target_db_id = db_id;
+                    pos = pos.saturating_add(2);
+                }
+                _ => {
+                    builder_return_syntax_error!(builder, response_buffer);
                 }
             }
+        }
+
+        if source == destination && target_db_id == client_state.database_id() {
+            builder.error_string(response_buffer, Strings::ERR_SAME_OBJECT);
+            return Ok(());
+        }
+
+        let _unused = LockManager::lock_multi(
+            &[source, destination],
+            client_state.clone(),
+            command.clone(),
+        )
+        .await?;
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+
+        if !replace
+            && GenericDb::with_storage(client_state.database(), target_db_id)
+                .get(destination)?
+                .is_some()
+        {
+            builder.number_usize(response_buffer, 0);
+            return Ok(());
+        }
+
+        if generic_db.copy(source, target_db_id, destination)? {
+            builder.number_usize(response_buffer, 1);
+        } else {
+            builder.number_usize(response_buffer, 0);
+        }
+        Ok(())
+    }
+
+    /// `MOVE key db`
+    /// Move `key` to the database `db`. Return `1` if the key was moved, `0` if the key does not
+    /// exist or if it already exists in the target database
+    async fn move_key(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+        let db_id = command_arg_at!(command, 2);
+
+        let Some(target_db_id) = BytesMutUtils::parse::<u16>(db_id) else {
+            builder_return_value_not_int!(builder, response_buffer);
+        };
+
+        if target_db_id == client_state.database_id() {
+            builder.error_string(response_buffer, Strings::ERR_SAME_OBJECT);
+            return Ok(());
+        }
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+
+        if GenericDb::with_storage(client_state.database(), target_db_id)
+            .get(key)?
+            .is_some()
+        {
+            builder.number_usize(response_buffer, 0);
+            return Ok(());
+        }
+
+        if generic_db.move_to_db(key, target_db_id)? {
+            builder.number_usize(response_buffer, 1);
+        } else {
+            builder.number_usize(response_buffer, 0);
+        }
+        Ok(())
+    }
+
+    /// `TYPE key`
+    /// Returns the string representation of the type of the value stored at key. The different
+    /// types that can be returned are: string, list, set, zset, hash and stream
+    async fn key_type(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+        match generic_db.get(key)? {
+            Some((_, md)) => builder.status_string(response_buffer, md.value_type().type_name()),
+            None => builder.status_string(response_buffer, "none"),
+        }
+        Ok(())
+    }
+
+    /// `PERSIST key`
+    /// Remove the existing timeout on key. Return `1` if the timeout was removed, `0` if the key
+    /// does not exist or does not have an associated timeout
+    async fn persist(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+
+        let Some(mut expiration) = generic_db.get_expiration(key)? else {
+            builder.number_usize(response_buffer, 0);
+            return Ok(());
+        };
+
+        if !expiration.has_ttl() {
+            builder.number_usize(response_buffer, 0);
+            return Ok(());
+        }
+
+        expiration.set_no_expiration()?;
+        generic_db.put_expiration(key, &expiration, true)?;
+        builder.number_usize(response_buffer, 1);
+        Ok(())
+    }
+
+    /// `TOUCH key [key ...]`
+    /// Alters the last access time of a key(s) (see `OBJECT IDLETIME`). Returns the number of
+    /// keys that were touched
+    async fn touch(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+
+        let mut iter = command.args_vec().iter();
+        let _ = iter.next(); // skip the first param which is the command name
+
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+        let mut touched_keys = Vec::<&BytesMut>::with_capacity(command.arg_count());
+        for user_key in iter {
+            if generic_db.get(user_key)?.is_some() {
+                touched_keys.push(user_key);
+            }
+        }
+
+        // Replicas receive the access time from their primary
+        if !client_state
+            .server_inner_state()
+            .persistent_state()
+            .is_replica()
+        {
+            Telemetry::record_key_access(
+                client_state.database_id(),
+                &touched_keys,
+                TimeUtils::epoch_ms()?,
+            );
+        }
+
+        let builder = RespBuilderV2::default();
+        builder.number_usize(response_buffer, touched_keys.len());
+        Ok(())
+    }
+
+    /// `RANDOMKEY`
+    /// Return a random key from the currently selected database, or a null reply if the database
+    /// is empty
+    async fn randomkey(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 1, response_buffer);
+        let builder = RespBuilderV2::default();
+
+        let generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+        match generic_db.random_key()? {
+            Some(user_key) => builder.bulk_string(response_buffer, &user_key),
+            None => builder.null_string(response_buffer),
+        }
+        Ok(())
+    }
+
+    /// `DUMP key`
+    /// Serialize the value stored at `key` and return it to the user. The payload can be
+    /// restored using the `RESTORE` command
+    async fn dump(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+        match generic_db.dump_value(key)? {
+            DumpValueResult::NotFound => builder.null_string(response_buffer),
+            DumpValueResult::Unsupported(value_type) => builder.error_string(
+                response_buffer,
+                &format!(
+                    "ERR DUMP is not supported for values of type '{}'",
+                    value_type.type_name()
+                ),
+            ),
+            DumpValueResult::Some(value) => {
+                builder.bulk_string(response_buffer, &value.to_dump_payload())
+            }
+        }
+        Ok(())
+    }
+
+    /// `RESTORE key ttl serialized-value [REPLACE] [ABSTTL] [IDLETIME seconds] [FREQ frequency]`
+    /// Create a key from a payload generated by `DUMP`. `IDLETIME` sets the key last access time.
+    /// `FREQ` is validated but otherwise ignored, the access frequency is estimated from sampling
+    async fn restore(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 4, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+        let ttl = command_arg_at!(command, 2);
+        let payload = command_arg_at!(command, 3);
+
+        let Some(ttl) = BytesMutUtils::parse::<i64>(ttl) else {
+            builder_return_value_not_int!(builder, response_buffer);
+        };
+        if ttl < 0 {
+            builder.error_string(response_buffer, Strings::ERR_INVALID_TTL);
+            return Ok(());
+        }
+
+        let mut replace = false;
+        let mut absttl = false;
+        let mut idletime_or_freq = false;
+        let mut idletime: Option<u64> = None;
+        let mut pos = 4usize;
+        while let Some(arg) = command.arg_as_lowercase_string(pos) {
+            match arg.as_str() {
+                "replace" => replace = true,
+                "absttl" => absttl = true,
+                "idletime" | "freq" if !idletime_or_freq => {
+                    pos = pos.saturating_add(1);
+                    let Some(value) = command.arg(pos).and_then(BytesMutUtils::parse::<i64>) else {
+                        builder_return_value_not_int!(builder, response_buffer);
+                    };
+                    if arg == "idletime" && value < 0 {
+                        builder.error_string(response_buffer, Strings::ERR_INVALID_IDLETIME);
+                        return Ok(());
+                    }
+                    if arg == "freq" && !(0..=255).contains(&value) {
+                        builder.error_string(response_buffer, Strings::ERR_INVALID_FREQ);
+                        return Ok(());
+                    }
+                    if arg == "idletime" {
+                        idletime = Some(value as u64);
+                    }
+                    idletime_or_freq = true;
+                }
+                _ => {
+                    builder_return_syntax_error!(builder, response_buffer);
+                }
+            }
+            pos = pos.saturating_add(1);
+        }
+
+        let value = match RdbValue::from_dump_payload(payload) {
+            Ok(value) => value,
+            Err(RdbError::VersionOrChecksum) => {
+                builder.error_string(response_buffer, Strings::ERR_DUMP_PAYLOAD);
+                return Ok(());
+            }
+            Err(RdbError::BadFormat) => {
+                builder.error_string(response_buffer, Strings::ERR_BAD_DATA_FORMAT);
+                return Ok(());
+            }
+            Err(RdbError::UnsupportedType(object_type)) => {
+                builder.error_string(
+                    response_buffer,
+                    &format!("ERR RDB object type {} is not supported", object_type),
+                );
+                return Ok(());
+            }
+        };
+
+        // A TTL of 0 means that the key does not expire
+        let now = TimeUtils::epoch_ms()?;
+        let expire_timestamp_ms = match ttl as u64 {
+            0 => None,
+            ttl if absttl => Some(ttl),
+            ttl => Some(now.saturating_add(ttl)),
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+        let exists = generic_db.contains(key)?;
+        if exists && !replace {
+            builder.error_string(response_buffer, Strings::BUSYKEY);
+            return Ok(());
+        }
+
+        // An already expired key is deleted (if replaced) but not created
+        if expire_timestamp_ms.is_some_and(|timestamp| timestamp <= now) {
+            if exists {
+                generic_db.delete(key, true)?;
+            }
+        } else {
+            generic_db.restore_value(key, value, expire_timestamp_ms)?;
+            Telemetry::forget_key_access(client_state.database_id(), key);
+            if let Some(idletime) = idletime {
+                generic_db
+                    .set_last_accessed(key, now.saturating_sub(idletime.saturating_mul(1000)))?;
+            }
+        }
+        builder.ok(response_buffer);
+        Ok(())
+    }
+
+    /// `MIGRATE host port key|"" destination-db timeout [COPY] [REPLACE] [AUTH password]
+    /// [AUTH2 username password] [KEYS key [key ...]]`
+    /// Transfer keys to the node listening on the private address `host:port`. Once the target
+    /// node restored the keys, they are deleted from this node (unless `COPY` is given). `AUTH`
+    /// and `AUTH2` are rejected: the keys are sent over the unauthenticated node talk channel.
+    /// The migrated keys stay locked while waiting for the target node (up to twice `timeout`)
+    async fn migrate(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 6, response_buffer);
+        let builder = RespBuilderV2::default();
+        let host = command_arg_at!(command, 1);
+        let port = command_arg_at!(command, 2);
+        let key = command_arg_at!(command, 3);
+        let db_id = command_arg_at!(command, 4);
+        let timeout_ms = command_arg_at!(command, 5);
+
+        let (Some(port), Some(target_db_id), Some(timeout_ms)) = (
+            BytesMutUtils::parse::<u16>(port),
+            BytesMutUtils::parse::<u16>(db_id),
+            BytesMutUtils::parse::<i64>(timeout_ms),
+        ) else {
+            builder_return_value_not_int!(builder, response_buffer);
+        };
+        // Same as Valkey: a timeout that is not positive means 1 second
+        let timeout = std::time::Duration::from_millis(if timeout_ms <= 0 {
+            1000
+        } else {
+            timeout_ms as u64
+        });
+
+        let mut copy = false;
+        let mut replace = false;
+        let mut keys = Vec::<&BytesMut>::new();
+        let mut pos = 6usize;
+        while let Some(arg) = command.arg_as_lowercase_string(pos) {
+            match arg.as_str() {
+                "copy" => copy = true,
+                "replace" => replace = true,
+                "auth" | "auth2" => {
+                    pos = pos.saturating_add(if arg == "auth" { 1 } else { 2 });
+                    if command.arg(pos).is_none() {
+                        builder_return_syntax_error!(builder, response_buffer);
+                    }
+                    builder.error_string(response_buffer, Strings::ERR_MIGRATE_AUTH);
+                    return Ok(());
+                }
+                "keys" => {
+                    if !key.is_empty() {
+                        builder.error_string(response_buffer, Strings::ERR_MIGRATE_KEYS);
+                        return Ok(());
+                    }
+                    keys = command.args_vec().iter().skip(pos + 1).collect();
+                    break;
+                }
+                _ => {
+                    builder_return_syntax_error!(builder, response_buffer);
+                }
+            }
+            pos = pos.saturating_add(1);
+        }
+        if keys.is_empty() {
+            keys.push(key);
+        }
+
+        let _unused = LockManager::lock_multi(&keys, client_state.clone(), command.clone()).await?;
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+        let mut migrated_keys = Vec::<MigratedKey>::with_capacity(keys.len());
+        for key in &keys {
+            let value = match generic_db.dump_value(key)? {
+                DumpValueResult::NotFound => continue,
+                DumpValueResult::Unsupported(value_type) => {
+                    builder.error_string(
+                        response_buffer,
+                        &format!(
+                            "ERR MIGRATE is not supported for values of type '{}'",
+                            value_type.type_name()
+                        ),
+                    );
+                    return Ok(());
+                }
+                DumpValueResult::Some(value) => value,
+            };
+            migrated_keys.push(MigratedKey {
+                key: key.to_vec(),
+                payload: value.to_dump_payload().to_vec(),
+                expire_timestamp_ms: generic_db
+                    .get_expiration(key)?
+                    .and_then(|expiration| expiration.expire_timestamp_millis()),
+            });
+        }
+
+        if migrated_keys.is_empty() {
+            builder.status_string(response_buffer, "NOKEY");
+            return Ok(());
+        }
+        let migrated_user_keys: Vec<BytesMut> = migrated_keys
+            .iter()
+            .map(|migrated_key| BytesMut::from(migrated_key.key.as_slice()))
+            .collect();
+
+        // The node talk client is blocking: talk to the target node from the worker's (bounded)
+        // blocking thread pool. The keys remain locked until the target node replies: up to
+        // `timeout` to connect plus `timeout` for the reply
+        let host = String::from_utf8_lossy(host);
+        let remote_address = if host.contains(':') {
+            format!("[{}]:{}", host, port)
+        } else {
+            format!("{}:{}", host, port)
+        };
+        let result = tokio::task::spawn_blocking(move || {
+            let mut node_talk_client = NodeTalkClient::default();
+            node_talk_client
+                .connect(&remote_address, timeout)
+                .and_then(|_| {
+                    node_talk_client.migrate_keys(target_db_id, replace, migrated_keys, timeout)
+                })
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .ok();
+
+        match result {
+            Some(Ok(MigrateKeysResult::Ok)) => {
+                if !copy {
+                    for user_key in &migrated_user_keys {
+                        generic_db.delete(user_key, false)?;
+                    }
+                    generic_db.commit()?;
+                }
+                builder.ok(response_buffer);
+            }
+            Some(Ok(MigrateKeysResult::Err(errmsg))) => {
+                builder.error_string(
+                    response_buffer,
+                    &format!("ERR Target instance replied with error: {}", errmsg),
+                );
+            }
+            Some(Err(errmsg)) => {
+                builder.error_string(
+                    response_buffer,
+                    &format!("IOERR error or timeout migrating keys to target instance. {errmsg}"),
+                );
+            }
+            None => {
+                builder.error_string(
+                    response_buffer,
+                    "IOERR error or timeout migrating keys to target instance",
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// `SORT key [BY pattern] [LIMIT offset count] [GET pattern [GET pattern ...]] [ASC | DESC]
+    /// [ALPHA] [STORE destination]` and `SORT_RO` (which does not accept `STORE`)
+    /// Return (or store) the elements of the list, set or sorted set at `key`, sorted. The keys
+    /// formed by the `BY` and `GET` patterns are locked and must be accessible by the user. In
+    /// cluster mode the patterns must point to keys in the slot of `key`
+    async fn sort(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+        let read_only = *command.metadata().name() == ValkeyCommandName::SortRo;
+
+        let mut by = None::<&BytesMut>;
+        let mut get_patterns = Vec::<&BytesMut>::new();
+        let mut limit = None::<(i64, i64)>;
+        let mut desc = false;
+        let mut alpha = false;
+        let mut destination = None::<&BytesMut>;
+        let mut pos = 2usize;
+        while let Some(arg) = command.arg_as_lowercase_string(pos) {
+            match (arg.as_str(), command.arg(pos + 1)) {
+                ("asc", _) => desc = false,
+                ("desc", _) => desc = true,
+                ("alpha", _) => alpha = true,
+                ("by", Some(pattern)) => {
+                    by = Some(pattern);
+                    pos = pos.saturating_add(1);
+                }
+                ("get", Some(pattern)) => {
+                    get_patterns.push(pattern);
+                    pos = pos.saturating_add(1);
+                }
+                ("store", Some(store_key)) if !read_only => {
+                    destination = Some(store_key);
+                    pos = pos.saturating_add(1);
+                }
+                ("limit", Some(offset)) => {
+                    let (Some(offset), Some(count)) = (
+                        BytesMutUtils::parse::<i64>(offset),
+                        command.arg(pos + 2).and_then(BytesMutUtils::parse::<i64>),
+                    ) else {
+                        builder_return_value_not_int!(builder, response_buffer);
+                    };
+                    limit = Some((offset, count));
+                    pos = pos.saturating_add(2);
+                }
+                _ => {
+                    builder_return_syntax_error!(builder, response_buffer);
+                }
+            }
+            pos = pos.saturating_add(1);
+        }
+
+        // A `BY` pattern without `*` means "do not sort"
+        let dont_sort = by.is_some_and(|pattern| !pattern.contains(&b'*'));
+        let is_cluster = client_state
+            .server_inner_state()
+            .options()
+            .read()
+            .expect("read error")
+            .general_settings
+            .cluster_address
+            .is_some();
+        if is_cluster {
+            let slot = calculate_slot(key);
+            if !dont_sort && by.is_some_and(|pattern| Self::pattern_slot(pattern) != Some(slot)) {
+                builder.error_string(response_buffer, Strings::ERR_SORT_BY_CROSSSLOT);
+                return Ok(());
+            }
+            if get_patterns
+                .iter()
+                .any(|pattern| *pattern != "#" && Self::pattern_slot(pattern) != Some(slot))
+            {
+                builder.error_string(response_buffer, Strings::ERR_SORT_GET_CROSSSLOT);
+                return Ok(());
+            }
+        }
+
+        // The keys formed by the `BY` and `GET` patterns are known only once the elements are
+        // read: lock them along with `key` and read the elements again, until no new key shows up
+        let mut lookup_patterns = get_patterns.clone();
+        if let (Some(by), false) = (by, dont_sort) {
+            lookup_patterns.push(by);
+        }
+        let mut locked_lookup_keys = BTreeSet::<BytesMut>::new();
+        let (_unused, mut elements, is_zset, lookup_keys) = loop {
+            let mut locked_keys = vec![key];
+            if let Some(destination) = destination {
+                locked_keys.push(destination);
+            }
+            locked_keys.extend(locked_lookup_keys.iter());
+            let guard =
+                LockManager::lock_multi(&locked_keys, client_state.clone(), command.clone())
+                    .await?;
+
+            let mut generic_db =
+                GenericDb::with_storage(client_state.database(), client_state.database_id());
+            let (elements, is_zset) = match generic_db.dump_value(key)? {
+                DumpValueResult::NotFound => (Vec::new(), false),
+                DumpValueResult::Some(RdbValue::List(items))
+                | DumpValueResult::Some(RdbValue::Set(items)) => (items, false),
+                DumpValueResult::Some(RdbValue::Zset(members)) => (
+                    members.into_iter().map(|(member, _)| member).collect(),
+                    true,
+                ),
+                _ => {
+                    builder.error_string(response_buffer, Strings::WRONGTYPE);
+                    return Ok(());
+                }
+            };
+
+            let lookup_keys: BTreeSet<BytesMut> = lookup_patterns
+                .iter()
+                .flat_map(|pattern| {
+                    elements
+                        .iter()
+                        .filter_map(move |element| Self::sort_lookup_key(pattern, element))
+                })
+                .map(|(user_key, _)| user_key)
+                .collect();
+            if lookup_keys.is_subset(&locked_lookup_keys) {
+                break (guard, elements, is_zset, lookup_keys);
+            }
+            locked_lookup_keys = lookup_keys;
+        };
+
+        let can_access_keys = client_state.acl_user().is_some_and(|user| {
+            lookup_keys
+                .iter()
+                .all(|lookup_key| user.can_access_key(lookup_key))
+        });
+        if !can_access_keys {
+            builder.error_string(response_buffer, Strings::NOPERM_KEY);
+            return Ok(());
+        }
+
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+        if dont_sort {
+            // A sorted set is already sorted, by score
+            if is_zset && desc {
+                elements.reverse();
+            }
+        } else {
+            let mut sort_keys = Vec::<SortKey>::with_capacity(elements.len());
+            for element in &elements {
+                let value = match by {
+                    Some(pattern) => Self::sort_lookup(&client_state, pattern, element)?,
+                    None => Some(element.clone()),
+                };
+                if alpha {
+                    sort_keys.push(SortKey::Alpha(value));
+                    continue;
+                }
+                // A missing value counts as 0
+                let score = match value {
+                    None => 0.0,
+                    Some(value) if value.is_empty() => 0.0,
+                    Some(value) => match BytesMutUtils::parse::<f64>(&value) {
+                        Some(score) if !score.is_nan() => score,
+                        _ => {
+                            builder.error_string(response_buffer, Strings::ERR_SORT_NOT_A_DOUBLE);
+                            return Ok(());
+                        }
+                    },
+                };
+                sort_keys.push(SortKey::Score(score));
+            }
+
+            let mut items: Vec<(BytesMut, SortKey)> = elements.into_iter().zip(sort_keys).collect();
+            items.sort_by(|(element1, key1), (element2, key2)| {
+                let ordering = match (key1, key2) {
+                    // Same score: order by the elements, to keep the output deterministic
+                    (SortKey::Score(score1), SortKey::Score(score2)) => score1
+                        .partial_cmp(score2)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                        .then_with(|| element1.cmp(element2)),
+                    // A missing value is placed first
+                    (SortKey::Alpha(value1), SortKey::Alpha(value2)) => value1.cmp(value2),
+                    _ => std::cmp::Ordering::Equal,
+                };
+                if desc {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+            elements = items.into_iter().map(|(element, _)| element).collect();
+        }
+
+        let (start, end) = match limit {
+            None => (0, elements.len()),
+            Some((offset, count)) => {
+                let start = usize::try_from(offset.max(0))
+                    .unwrap_or(usize::MAX)
+                    .min(elements.len());
+                let end = match usize::try_from(count) {
+                    Ok(count) => start.saturating_add(count).min(elements.len()),
+                    Err(_) => elements.len(),
+                };
+                (start, end)
+            }
+        };
+
+        let mut rows = Vec::<Option<BytesMut>>::with_capacity(
+            (end - start).saturating_mul(get_patterns.len().max(1)),
+        );
+        for element in elements.drain(start..end) {
+            if get_patterns.is_empty() {
+                rows.push(Some(element));
+                continue;
+            }
+            for pattern in &get_patterns {
+                rows.push(Self::sort_lookup(&client_state, pattern, &element)?);
+            }
+        }
+
+        if let Some(destination) = destination {
+            let stored_count = rows.len();
+            if rows.is_empty() {
+                if generic_db.contains(destination)? {
+                    generic_db.delete(destination, true)?;
+                }
+            } else {
+                // missing values are stored as empty strings
+                let items = rows.into_iter().map(Option::unwrap_or_default).collect();
+                generic_db.restore_value(destination, RdbValue::List(items), None)?;
+            }
+            builder.number_usize(response_buffer, stored_count);
+            return Ok(());
+        }
+
+        builder.add_array_len(response_buffer, rows.len());
+        for row in rows {
+            match row {
+                Some(value) => builder.add_bulk_string(response_buffer, &value),
+                None => builder.add_null_string(response_buffer),
+            }
+        }
+        Ok(())
+    }
+
+    /// `SORT` helper: return the value of the key formed by replacing the first `*` in `pattern`
+    /// with `element`. A pattern ending with `->field` reads `field` from a hash. The pattern `#`
+    /// returns the element itself
+    fn sort_lookup(
+        client_state: &ClientState,
+        pattern: &[u8],
+        element: &BytesMut,
+    ) -> Result<Option<BytesMut>, SableError> {
+        if pattern == b"#" {
+            return Ok(Some(element.clone()));
+        }
+        let Some((user_key, field)) = Self::sort_lookup_key(pattern, element) else {
+            return Ok(None);
+        };
+
+        let store = client_state.database();
+        let db_id = client_state.database_id();
+        Ok(match field {
+            Some(field) => match HashDb::with_storage(store, db_id).get(&user_key, &field)? {
+                HashGetResult::Some(value) => Some(value),
+                _ => None,
+            },
+            None => match StringsDb::with_storage(store, db_id).get(&user_key)? {
+                StringGetResult::Some((value, _)) => Some(value),
+                _ => None,
+            },
+        })
+    }
+
+    /// `SORT` helper: return the key (and the hash field, if any) that `sort_lookup` reads for
+    /// `pattern` and `element`, or `None` if `pattern` does not form a key
+    fn sort_lookup_key(pattern: &[u8], element: &BytesMut) -> Option<(BytesMut, Option<BytesMut>)> {
+        let star_pos = pattern.iter().position(|c| *c == b'*')?;
+
+        // The field name (after `->`) must not be empty
+        let field_pos = pattern[star_pos + 1..]
+            .windows(2)
+            .position(|chars| chars == b"->")
+            .map(|pos| pos + star_pos + 1)
+            .filter(|pos| pos + 2 < pattern.len());
+        let key_end = field_pos.unwrap_or(pattern.len());
+
+        let mut user_key = BytesMut::with_capacity(key_end + element.len());
+        user_key.extend_from_slice(&pattern[..star_pos]);
+        user_key.extend_from_slice(element);
+        user_key.extend_from_slice(&pattern[star_pos + 1..key_end]);
+
+        let field = field_pos.map(|field_pos| BytesMut::from(&pattern[field_pos + 2..]));
+        Some((user_key, field))
+    }
+
+    /// `SORT` helper: return the slot of the keys formed by `pattern`, or `None` if these keys
+    /// may be in different slots (i.e. the pattern does not have a hash tag without wildcards)
+    fn pattern_slot(pattern: &[u8]) -> Option<u16> {
+        let mut pos = 0usize;
+        while pos < pattern.len() {
+            match pattern[pos] {
+                b'*' | b'?' | b'[' => return None,
+                b'\\' => pos = pos.saturating_add(1),
+                b'{' => {
+                    let tag_end = pattern[pos + 1..].iter().position(|c| *c == b'}')? + pos + 1;
+                    if pattern[pos + 1..tag_end]
+                        .iter()
+                        .any(|c| matches!(c, b'*' | b'?' | b'['))
+                    {
+                        return None;
+                    }
+                    return Some(calculate_slot(&pattern[pos..=tag_end]));
+                }
+                _ => {}
+            }
+            pos = pos.saturating_add(1);
+        }
+        Some(calculate_slot(pattern))
+    }
+
+    /// `OBJECT <ENCODING | IDLETIME | FREQ | REFCOUNT> key`
+    /// Inspect the value stored at `key`. The idle time is the time elapsed since the key was last
+    /// accessed. The frequency is the estimated access count (capped at 255), based on the sampled
+    /// key accesses
+    async fn object(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let sub_command = command_arg_at_as_str!(command, 1);
+        if !matches!(
+            sub_command.as_str(),
+            "encoding" | "idletime" | "freq" | "refcount"
+        ) {
+            builder.error_string(
+                response_buffer,
+                &format!(
+                    "ERR unknown subcommand '{}'. Try OBJECT HELP.",
+                    sub_command.as_str()
+                ),
+            );
+            return Ok(());
+        }
+        if command.arg_count() != 3 {
+            builder_return_wrong_args_count!(
+                builder,
+                response_buffer,
+                format!("object|{}", sub_command)
+            );
+        }
+        let key = command_arg_at!(command, 2);
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+        let Some((value, mut md)) = generic_db.get(key)? else {
+            builder.null_string(response_buffer);
+            return Ok(());
+        };
+
+        match sub_command.as_str() {
+            "encoding" => {
+                let encoding = match md.value_type() {
+                    ValueType::Str if BytesMutUtils::parse::<i64>(&value).is_some() => "int",
+                    ValueType::Str if value.len() <= Self::EMBSTR_SIZE_LIMIT => "embstr",
+                    ValueType::Str | ValueType::Bitmap | ValueType::Lock => "raw",
+                    ValueType::List => "quicklist",
+                    ValueType::Hash | ValueType::Set => "hashtable",
+                    ValueType::Zset => "skiplist",
+                    ValueType::Stream => "stream",
+                };
+                builder.bulk_string(response_buffer, &BytesMut::from(encoding));
+            }
+            "idletime" => {
+                // The latest accesses are kept in memory until the cron thread persists them
+                if let Some(last_accessed) =
+                    Telemetry::key_last_accessed(client_state.database_id(), key)
+                {
+                    md.set_last_accessed(last_accessed.max(md.last_accessed()));
+                }
+                builder.number_u64(response_buffer, md.idle_time_ms()? / 1000)
+            }
+            "freq" => builder.number_u64(
+                response_buffer,
+                Telemetry::key_access_frequency(client_state.database_id(), key).min(255),
+            ),
+            _ => builder.number_usize(response_buffer, 1),
+        }
+        Ok(())
+    }
+
+    /// `MEMORY USAGE key [SAMPLES count]`
+    /// Return the number of bytes used on disk by `key`, including all of its sub items. `SAMPLES`
+    /// is accepted but ignored: the sub items are always counted
+    async fn memory(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let sub_command = command_arg_at_as_str!(command, 1);
+        if sub_command != "usage" {
+            builder.error_string(
+                response_buffer,
+                &format!(
+                    "ERR unknown subcommand '{}'. Try MEMORY HELP.",
+                    sub_command.as_str()
+                ),
+            );
+            return Ok(());
+        }
+        check_args_count!(command, 3, response_buffer);
+        let key = command_arg_at!(command, 2);
+
+        let mut pos = 3usize;
+        while let Some(arg) = command.arg_as_lowercase_string(pos) {
+            match (arg.as_str(), command.arg(pos + 1)) {
+                ("samples", Some(count)) => {
+                    if BytesMutUtils::parse::<i64>(count).is_none() {
+                        builder_return_value_not_int!(builder, response_buffer);
+                    }
+                    pos = pos.saturating_add(2);
+                }
+                _ => {
+                    builder_return_syntax_error!(builder, response_buffer);
+                }
+            }
+        }
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut generic_db =
+            GenericDb::with_storage(client_state.database(), client_state.database_id());
+        match generic_db.memory_usage(key)? {
+            Some(usage) => builder.number_usize(response_buffer, usage),
+            None => builder.null_string(response_buffer),
+        }
+        Ok(())
+    }
+
+    /// Scan helper function: return true if the `encoded_key` / `encoded_value` pair are candidates for the scan command
+    fn should_collect(
+        encoded_key: &[u8],
+        encoded_value: &[u8],
+        matcher: &PatternMatcher,
+        requested_obj_type: &Option<ValueType>,
+    ) -> Result<Option<BytesMut>, SableError> {
+        let user_key = PrimaryKeyMetadata::from_raw(encoded_key)?
+            .user_key()
+            .clone();
+
+        // Filter by object type if needed
+        if let Some(requested_obj_type) = requested_obj_type {
+            let cmd = CommonValueMetadata::try_from(encoded_value)?;
+            if requested_obj_type.ne(&cmd.value_type()) {
+                return Ok(None);
+            }
+        }
+
+        Ok(if matcher.matches(&user_key) {
+            Some(user_key)
+        } else {
+            None
+        })
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{commands::ClientNextAction, Client, ServerState};
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    #[test_case(vec![
+        ("set mystr myvalue", "+OK\r\n"),
+        ("set mystr2 myvalue2", "+OK\r\n"),
+        ("lpush mylist_1 a b c", ":3\r\n"),
+        ("lpush mylist_2 a b c", ":3\r\n"),
+        ("del mystr mystr2 mylist_1 mylist_2", ":4\r\n"),
+        ("get mystr", "$-1\r\n"),
+        ("get mystr2", "$-1\r\n"),
+        ("llen mylist_1", ":0\r\n"),
+        ("llen mylist_2", ":0\r\n"),
+        ("del mylist_2", ":0\r\n"),
+    ]; "test_del")]
+    #[test_case(vec![
+        ("set mykey1 myvalue", "+OK\r\n"),
+        ("set mykey2 myvalue1", "+OK\r\n"),
+        ("exists mykey1 mykey2", ":2\r\n"),
+        ("exists mykey1 mykey2 mykey1", ":3\r\n"),
+        ("exists no_such_key mykey2 mykey1", ":2\r\n"),
+    ]; "test_exists")]
+    #[test_case(vec![
+        ("set mykey1 myvalue", "+OK\r\n"),
+        ("expire mykey1 100", ":1\r\n"),
+        ("get mykey1", "$7\r\nmyvalue\r\n"),
+        ("set mykey2 myvalue EX 100", "+OK\r\n"),
+        ("expire mykey2 90 GT", ":0\r\n"),
+        ("expire mykey2 120 GT", ":1\r\n"),
+        ("get mykey2", "$7\r\nmyvalue\r\n"),
+        ("set mykey3 myvalue EX 100", "+OK\r\n"),
+        ("expire mykey3 123 LT", ":0\r\n"),
+        ("expire mykey3 90 LT", ":1\r\n"),
+        ("get mykey3", "$7\r\nmyvalue\r\n"),
+        ("set mykey4 myvalue EX 100", "+OK\r\n"),
+        ("expire mykey4 120 NX", ":0\r\n"),
+        ("expire mykey4 120 XX", ":1\r\n"),
+        ("set mykey5 myvalue", "+OK\r\n"),
+        ("expire mykey5 120 XX", ":0\r\n"),
+        ("expire mykey5 120 NX", ":1\r\n"),
+    ]; "test_expire")]
+    #[test_case(vec![
+        ("set a 1", "+OK\r\n"),
+        ("pexpire a 100000", ":1\r\n"),
+        ("ttl a", ":100\r\n"),
+        ("expireat a 4102444800", ":1\r\n"),
+        ("expiretime a", ":4102444800\r\n"),
+        ("pexpiretime a", ":4102444800000\r\n"),
+        ("pexpireat a 4102444800500 GT", ":1\r\n"),
+        ("expiretime a", ":4102444801\r\n"),
+        ("pexpireat a 4102444800000 GT", ":0\r\n"),
+        ("pexpireat a 4102444800000 LT", ":1\r\n"),
+        ("pexpireat a 4102444800000 NX", ":0\r\n"),
+        ("persist a", ":1\r\n"),
+        ("pexpiretime a", ":-1\r\n"),
+        ("pttl a", ":-1\r\n"),
+        ("pexpire a 100 LT", ":1\r\n"),
+        ("expiretime nosuchkey", ":-2\r\n"),
+        ("pttl nosuchkey", ":-2\r\n"),
+        // a timeout in the past deletes the key
+        ("expireat a 1", ":1\r\n"),
+        ("exists a", ":0\r\n"),
+        ("expire a 10", ":0\r\n"),
+        ("set b 1", "+OK\r\n"),
+        ("pexpire b -1", ":1\r\n"),
+        ("get b", "$-1\r\n"),
+        ("set b 1", "+OK\r\n"),
+        ("expire b 9223372036854775807", "-ERR invalid expire time in 'expire' command\r\n"),
+        ("pexpire b abc", "-ERR value is not an integer or out of range\r\n"),
+        ("hset myhash f v", ":1\r\n"),
+        ("pexpireat myhash 4102444800000", ":1\r\n"),
+        ("pexpiretime myhash", ":4102444800000\r\n"),
+        ("hget myhash f", "$1\r\nv\r\n"),
+    ]; "test_expire_family")]
+    #[test_case(vec![
+        ("select 0", "+OK\r\n"),
+        ("set k1 b", "+OK\r\n"),
+        ("set k2 d", "+OK\r\n"),
+        ("set k3 f", "+OK\r\n"),
+        ("hset myhash 1 2 3 4 5 6", ":3\r\n"),
+        ("keys *", "*4\r\n$2\r\nk2\r\n$2\r\nk3\r\n$6\r\nmyhash\r\n$2\r\nk1\r\n"),
+        ("select 1", "+OK\r\n"),
+        ("keys *", "*0\r\n"),
+        ("keys", "-ERR wrong number of arguments for 'keys' command\r\n"),
+        ("select 0", "+OK\r\n"),
+        ("keys k*", "*3\r\n$2\r\nk2\r\n$2\r\nk3\r\n$2\r\nk1\r\n"),
+        ("keys ??", "*3\r\n$2\r\nk2\r\n$2\r\nk3\r\n$2\r\nk1\r\n"),
+        ("keys myhash", "*1\r\n$6\r\nmyhash\r\n"),
+    ]; "test_keys")]
+    #[test_case(vec![
+        ("set k1 b", "+OK\r\n"),
+        ("set k2 d", "+OK\r\n"),
+        ("set k3 f", "+OK\r\n"),
+        ("set k4 f", "+OK\r\n"),
+        ("hset myhash 1 2 3 4 5 6", ":3\r\n"),
+        // fetch all keys
+        ("scan 0 COUNT 10", "*2\r\n:0\r\n*5\r\n$2\r\nk2\r\n$2\r\nk3\r\n$2\r\nk4\r\n$6\r\nmyhash\r\n$2\r\nk1\r\n"),
+        // fetch all string keys ("myhash" is excluded)
+        ("scan 0 COUNT 10 TYPE string", "*2\r\n:0\r\n*4\r\n$2\r\nk2\r\n$2\r\nk3\r\n$2\r\nk4\r\n$2\r\nk1\r\n"),
+        // fetch all hash keys (only "myhash")
+        ("scan 0 COUNT 10 TYPE hash", "*2\r\n:0\r\n*1\r\n$6\r\nmyhash\r\n"),
+        // All keys starting with k regardless of their type
+        ("scan 0 COUNT 10 MATCH k*", "*2\r\n:0\r\n*4\r\n$2\r\nk2\r\n$2\r\nk3\r\n$2\r\nk4\r\n$2\r\nk1\r\n"),
+        ("scan 0 COUNT 10 MATCH *ha*", "*2\r\n:0\r\n*1\r\n$6\r\nmyhash\r\n")
+    ]; "test_scan")]
+    #[test_case(vec![
+        ("set a 1", "+OK\r\n"),
+        ("rename a b", "+OK\r\n"),
+        ("get a", "$-1\r\n"),
+        ("get b", "$1\r\n1\r\n"),
+        ("rename nosuchkey c", "-ERR no such key\r\n"),
+        ("rename b b", "+OK\r\n"),
+        ("set c 3", "+OK\r\n"),
+        ("renamenx b c", ":0\r\n"),
+        ("renamenx b d", ":1\r\n"),
+        ("get d", "$1\r\n1\r\n"),
+        // a different slot: the hash fields are moved
+        ("hset myhash f1 v1 f2 v2", ":2\r\n"),
+        ("rename myhash newhash", "+OK\r\n"),
+        ("hlen myhash", ":0\r\n"),
+        ("hgetall newhash", "*4\r\n$2\r\nf1\r\n$2\r\nv1\r\n$2\r\nf2\r\n$2\r\nv2\r\n"),
+        // the same slot: the hash fields are kept in place
+        ("rename newhash {newhash}2", "+OK\r\n"),
+        ("hget {newhash}2 f2", "$2\r\nv2\r\n"),
+        ("lpush mylist a b c", ":3\r\n"),
+        ("rename mylist {newhash}2", "+OK\r\n"),
+        ("type {newhash}2", "+list\r\n"),
+        ("lrange {newhash}2 0 -1", "*3\r\n$1\r\nc\r\n$1\r\nb\r\n$1\r\na\r\n"),
+    ]; "test_rename")]
+    #[test_case(vec![
+        ("hset myhash f1 v1", ":1\r\n"),
+        ("copy myhash myhash2", ":1\r\n"),
+        ("hset myhash2 f2 v2", ":1\r\n"),
+        ("hlen myhash", ":1\r\n"),
+        ("hlen myhash2", ":2\r\n"),
+        ("copy myhash myhash2", ":0\r\n"),
+        ("copy myhash myhash2 REPLACE", ":1\r\n"),
+        ("hlen myhash2", ":1\r\n"),
+        ("copy myhash myhash", "-ERR source and destination objects are the same\r\n"),
+        ("copy nosuchkey myhash3", ":0\r\n"),
+        ("copy myhash myhash DB 1", ":1\r\n"),
+        ("select 1", "+OK\r\n"),
+        ("hget myhash f1", "$2\r\nv1\r\n"),
+    ]; "test_copy")]
+    #[test_case(vec![
+        ("set a 1", "+OK\r\n"),
+        ("sadd myset x y", ":2\r\n"),
+        ("move a 1", ":1\r\n"),
+        ("move myset 1", ":1\r\n"),
+        ("get a", "$-1\r\n"),
+        ("move a 1", ":0\r\n"),
+        ("move a 0", "-ERR source and destination objects are the same\r\n"),
+        ("select 1", "+OK\r\n"),
+        ("get a", "$1\r\n1\r\n"),
+        ("scard myset", ":2\r\n"),
+        ("set b 2", "+OK\r\n"),
+        ("select 0", "+OK\r\n"),
+        ("set b 3", "+OK\r\n"),
+        ("move b 1", ":0\r\n"),
+    ]; "test_move")]
+    #[test_case(vec![
+        ("set mystr a", "+OK\r\n"),
+        ("lpush mylist a", ":1\r\n"),
+        ("hset myhash f v", ":1\r\n"),
+        ("sadd myset a", ":1\r\n"),
+        ("zadd myzset 1 a", ":1\r\n"),
+        ("type mystr", "+string\r\n"),
+        ("type mylist", "+list\r\n"),
+        ("type myhash", "+hash\r\n"),
+        ("type myset", "+set\r\n"),
+        ("type myzset", "+zset\r\n"),
+        ("type nosuchkey", "+none\r\n"),
+    ]; "test_type")]
+    #[test_case(vec![
+        ("set a 1 EX 100", "+OK\r\n"),
+        ("persist a", ":1\r\n"),
+        ("ttl a", ":-1\r\n"),
+        ("persist a", ":0\r\n"),
+        ("persist nosuchkey", ":0\r\n"),
+    ]; "test_persist")]
+    #[test_case(vec![
+        ("set a 1", "+OK\r\n"),
+        ("set b 2", "+OK\r\n"),
+        ("touch a b nosuchkey", ":2\r\n"),
+        ("unlink a b nosuchkey", ":2\r\n"),
+        ("exists a b", ":0\r\n"),
+    ]; "test_touch_unlink")]
+    #[test_case(vec![
+        ("randomkey", "$-1\r\n"),
+        ("set a 1", "+OK\r\n"),
+        ("randomkey", "$1\r\na\r\n"),
+        ("select 1", "+OK\r\n"),
+        ("randomkey", "$-1\r\n"),
+    ]; "test_randomkey")]
+    #[test_case(vec![
+        ("dump nosuchkey", "$-1\r\n"),
+        ("restore a 0 corrupted", "-ERR DUMP payload version or checksum are wrong\r\n"),
+        ("restore a -1 corrupted", "-ERR Invalid TTL value, must be >= 0\r\n"),
+        ("restore a 0 corrupted idletime -1", "-ERR Invalid IDLE time, must be >= 0\r\n"),
+        ("restore a 0 corrupted freq 256", "-ERR Invalid FREQ value, must be >= 0 and <= 255\r\n"),
+        ("restore a 0 corrupted idletime 1 freq 1", "-ERR syntax error\r\n"),
+    ]; "test_dump_restore_errors")]
+    #[test_case(vec![
+        ("migrate 127.0.0.1 port a 0 1000", "-ERR value is not an integer or out of range\r\n"),
+        ("migrate 127.0.0.1 7000 a 0 1000 keys b", "-ERR When using MIGRATE KEYS option, the key argument must be set to the empty string\r\n"),
+        ("migrate 127.0.0.1 7000 a 0 1000 auth", "-ERR syntax error\r\n"),
+        ("migrate 127.0.0.1 7000 a 0 1000 auth2 user", "-ERR syntax error\r\n"),
+        ("migrate 127.0.0.1 7000 a 0 1000 auth2 user pass", "-ERR MIGRATE AUTH is not supported, nodes do not authenticate each other\r\n"),
+        ("migrate 127.0.0.1 7000 a 0 1000 nosuchoption", "-ERR syntax error\r\n"),
+        ("migrate 127.0.0.1 7000 nosuchkey 0 1000 copy replace", "+NOKEY\r\n"),
+    ]; "test_migrate")]
+    #[test_case(vec![
+        ("rpush mylist 3 1 2", ":3\r\n"),
+        ("sort mylist", "*3\r\n$1\r\n1\r\n$1\r\n2\r\n$1\r\n3\r\n"),
+        ("sort mylist desc", "*3\r\n$1\r\n3\r\n$1\r\n2\r\n$1\r\n1\r\n"),
+        ("sort mylist limit 1 1", "*1\r\n$1\r\n2\r\n"),
+        ("sort mylist limit 1 -1", "*2\r\n$1\r\n2\r\n$1\r\n3\r\n"),
+        ("sort_ro mylist limit 5 1", "*0\r\n"),
+        ("sort nosuchkey", "*0\r\n"),
+        ("rpush words b c a", ":3\r\n"),
+        ("sort words", "-ERR One or more scores can't be converted into double\r\n"),
+        ("sort words alpha", "*3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n"),
+        ("sort words alpha desc", "*3\r\n$1\r\nc\r\n$1\r\nb\r\n$1\r\na\r\n"),
+        ("sort words by nosort", "*3\r\n$1\r\nb\r\n$1\r\nc\r\n$1\r\na\r\n"),
+        ("sort mylist limit a 1", "-ERR value is not an integer or out of range\r\n"),
+        ("sort mylist nosuchoption", "-ERR syntax error\r\n"),
+        ("sort_ro mylist store dest", "-ERR syntax error\r\n"),
+        ("set mystr value", "+OK\r\n"),
+        ("sort mystr", "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"),
+    ]; "test_sort")]
+    #[test_case(vec![
+        ("sadd users 1 2 3", ":3\r\n"),
+        ("set weight_1 30", "+OK\r\n"),
+        ("set weight_2 10", "+OK\r\n"),
+        ("set weight_3 20", "+OK\r\n"),
+        ("hset user_1 name alice", ":1\r\n"),
+        ("hset user_2 name bob", ":1\r\n"),
+        ("sort users by weight_*", "*3\r\n$1\r\n2\r\n$1\r\n3\r\n$1\r\n1\r\n"),
+        ("sort users by weight_* get # get user_*->name", "*6\r\n$1\r\n2\r\n$3\r\nbob\r\n$1\r\n3\r\n$-1\r\n$1\r\n1\r\n$5\r\nalice\r\n"),
+        ("sort users by weight_* desc get weight_*", "*3\r\n$2\r\n30\r\n$2\r\n20\r\n$2\r\n10\r\n"),
+        ("sort users by weight_* store dest", ":3\r\n"),
+        ("lrange dest 0 -1", "*3\r\n$1\r\n2\r\n$1\r\n3\r\n$1\r\n1\r\n"),
+        ("sort users by weight_* get user_*->name store dest", ":3\r\n"),
+        ("lrange dest 0 -1", "*3\r\n$3\r\nbob\r\n$0\r\n\r\n$5\r\nalice\r\n"),
+        ("sort nosuchkey store dest", ":0\r\n"),
+        ("exists dest", ":0\r\n"),
+    ]; "test_sort_by_get_store")]
+    #[test_case(vec![
+        ("object encoding nosuchkey", "$-1\r\n"),
+        ("set num 12", "+OK\r\n"),
+        ("object encoding num", "$3\r\nint\r\n"),
+        ("set str hello", "+OK\r\n"),
+        ("object encoding str", "$6\r\nembstr\r\n"),
+        ("set longstr 0123456789012345678901234567890123456789012345", "+OK\r\n"),
+        ("object encoding longstr", "$3\r\nraw\r\n"),
+        ("rpush mylist a", ":1\r\n"),
+        ("object encoding mylist", "$9\r\nquicklist\r\n"),
+        ("hset myhash f v", ":1\r\n"),
+        ("object encoding myhash", "$9\r\nhashtable\r\n"),
+        ("zadd myzset 1 a", ":1\r\n"),
+        ("object encoding myzset", "$8\r\nskiplist\r\n"),
+        ("object idletime num", ":0\r\n"),
+        ("object refcount num", ":1\r\n"),
+        ("object freq num", ":0\r\n"),
+        ("object encoding num extra", "-ERR wrong number of arguments for 'object|encoding' command\r\n"),
+        ("object nosuchsubcommand num", "-ERR unknown subcommand 'nosuchsubcommand'. Try OBJECT HELP.\r\n"),
+    ]; "test_object")]
+    #[test_case(vec![
+        ("memory usage nosuchkey", "$-1\r\n"),
+        ("set num 12", "+OK\r\n"),
+        ("memory usage num", ":43\r\n"),
+        ("memory usage num samples 5", ":43\r\n"),
+        ("memory usage num samples a", "-ERR value is not an integer or out of range\r\n"),
+        ("memory usage num samples", "-ERR syntax error\r\n"),
+        ("memory doctor", "-ERR unknown subcommand 'doctor'. Try MEMORY HELP.\r\n"),
+    ]; "test_memory_usage")]
+    fn test_generic_commands(args: Vec<(&'static str, &'static str)>) -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            for (args, expected_value) in args {
+                let mut sink = crate::io::FileResponseSink::new().await.unwrap();
+                let args = args.split(' ').collect();
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                match Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                    .await
+                    .unwrap()
+                {
+                    ClientNextAction::NoAction => {
+                        assert_eq!(
+                            sink.read_all_as_string().await.unwrap().as_str(),
+                            expected_value
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn execute(client: &Client, args: Vec<BytesMut>) -> BytesMut {
+        let mut sink = crate::io::FileResponseSink::new().await.unwrap();
+        let cmd = Rc::new(ValkeyCommand::new(args).unwrap());
+        Client::handle_command(client.inner(), cmd, &mut sink.fp)
+            .await
+            .unwrap();
+        sink.read_all().await.unwrap()
+    }
+
+    fn split_args(args: &str) -> Vec<BytesMut> {
+        args.split(' ').map(BytesMut::from).collect()
+    }
+
+    /// Run `DUMP key` and return the payload
+    async fn dump_payload(client: &Client, key: &str) -> BytesMut {
+        let mut response = execute(client, split_args(&format!("dump {}", key))).await;
+        // strip the bulk string header and the trailing CRLF
+        let header_len = response.iter().position(|c| *c == b'\n').unwrap() + 1;
+        let _ = response.split_to(header_len);
+        response.truncate(response.len() - 2);
+        response
+    }
+
+    /// Run `RESTORE <args> <payload> <options>`
+    async fn restore(client: &Client, args: &str, payload: &BytesMut, options: &str) -> BytesMut {
+        let mut args = split_args(&format!("restore {}", args));
+        args.push(payload.clone());
+        if !options.is_empty() {
+            args.extend(split_args(options));
+        }
+        execute(client, args).await
+    }
+
+    #[test]
+    fn test_dump_restore() -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            let values = vec![
+                ("set key value", "get"),
+                ("rpush key a b a", "lrange 0 -1"),
+                ("hset key f1 v1 f2 v2", "hgetall"),
+                ("sadd key m1 m2", "smembers"),
+                ("zadd key 1 m1 2.5 m2", "zrange 0 -1 withscores"),
+            ];
+            for (create, read) in values {
+                execute(&client, split_args("del key copy")).await;
+                execute(&client, split_args(create)).await;
+
+                let payload = dump_payload(&client, "key").await;
+                assert_eq!(
+                    restore(&client, "key 0", &payload, "").await,
+                    "-BUSYKEY Target key name already exists.\r\n"
+                );
+                assert_eq!(restore(&client, "copy 0", &payload, "").await, "+OK\r\n");
+                assert_eq!(
+                    restore(&client, "key 0", &payload, "replace").await,
+                    "+OK\r\n"
+                );
+
+                let (command, args) = read.split_once(' ').unwrap_or((read, ""));
+                let read_key = format!("{} key {}", command, args);
+                let read_copy = format!("{} copy {}", command, args);
+                assert_eq!(
+                    execute(&client, split_args(read_key.trim_end())).await,
+                    execute(&client, split_args(read_copy.trim_end())).await
+                );
+            }
+
+            // Relative TTL
+            let payload = dump_payload(&client, "key").await;
+            assert_eq!(restore(&client, "a 100000", &payload, "").await, "+OK\r\n");
+            let ttl = execute(&client, split_args("ttl a")).await;
+            assert!(ttl == ":100\r\n" || ttl == ":99\r\n");
+
+            // An absolute TTL in the past deletes the replaced key
+            assert_eq!(
+                restore(&client, "a 1", &payload, "replace absttl").await,
+                "+OK\r\n"
+            );
+            assert_eq!(execute(&client, split_args("exists a")).await, ":0\r\n");
+
+            // IDLETIME sets the key last access time
+            assert_eq!(
+                restore(&client, "idle 0", &payload, "idletime 100").await,
+                "+OK\r\n"
+            );
+            let idletime = execute(&client, split_args("object idletime idle")).await;
+            assert!(idletime == ":100\r\n" || idletime == ":101\r\n");
+
+            // TOUCH resets the idle time
+            assert_eq!(
+                execute(&client, split_args("touch idle nosuchkey")).await,
+                ":1\r\n"
+            );
+            assert_eq!(
+                execute(&client, split_args("object idletime idle")).await,
+                ":0\r\n"
+            );
+
+            // Hash field expiration times are kept
+            for command in ["del key copy", "hset key f1 v1 f2 v2"] {
+                execute(&client, split_args(command)).await;
+            }
+            execute(&client, split_args("hexpireat key 4102444800 fields 1 f1")).await;
+            let payload = dump_payload(&client, "key").await;
+            assert_eq!(restore(&client, "copy 0", &payload, "").await, "+OK\r\n");
+            assert_eq!(
+                execute(&client, split_args("hexpiretime copy fields 2 f1 f2")).await,
+                "*2\r\n:4102444800\r\n:-1\r\n"
+            );
+
+            // Streams are restored with their consumer groups
+            for command in [
+                "del key copy",
+                "xadd key 1-1 f v",
+                "xadd key 2-1 f v2 g w",
+                "xgroup create key g1 0",
+                "xreadgroup group g1 alice count 1 streams key >",
+            ] {
+                execute(&client, split_args(command)).await;
+            }
+            let payload = dump_payload(&client, "key").await;
+            assert_eq!(restore(&client, "copy 0", &payload, "").await, "+OK\r\n");
+            for read in ["xrange {} - +", "xpending {} g1", "xlen {}"] {
+                assert_eq!(
+                    execute(&client, split_args(&read.replace("{}", "key"))).await,
+                    execute(&client, split_args(&read.replace("{}", "copy"))).await
+                );
+            }
         });
         Ok(())
     }
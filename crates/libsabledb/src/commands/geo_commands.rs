@@ -0,0 +1,841 @@
+#[allow(unused_imports)]
+use crate::{
+    check_args_count, command_arg_at,
+    commands::{HandleCommandResult, Strings},
+    server::ClientState,
+    storage::{
+        FindZSetResult, SortedSet, ZSetAddMemberResult, ZSetDb, ZSetGetScoreResult, ZWriteFlags,
+    },
+    utils::{GeoHash, GeoShape, RespBuilderV2},
+    BytesMutUtils, LockManager, SableError, ValkeyCommand, ValkeyCommandName,
+};
+
+use bytes::BytesMut;
+use std::rc::Rc;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SortOrder {
+    None,
+    Asc,
+    Desc,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum SearchOrigin {
+    Member(BytesMut),
+    LonLat(f64, f64),
+}
+
+/// The parsed arguments of `GEOSEARCH` / `GEOSEARCHSTORE`
+#[derive(Clone, Debug)]
+struct GeoSearchArgs {
+    origin: SearchOrigin,
+    shape: GeoShape,
+    /// The unit conversion factor (the number of meters in a single unit)
+    unit: f64,
+    sort: SortOrder,
+    count: Option<usize>,
+    any: bool,
+    with_coord: bool,
+    with_dist: bool,
+    with_hash: bool,
+    store_dist: bool,
+}
+
+/// A single point found by `GEOSEARCH`
+struct GeoSearchItem {
+    member: BytesMut,
+    score: f64,
+    /// Distance from the search center, in meters
+    distance: f64,
+    longitude: f64,
+    latitude: f64,
+}
+
+pub struct GeoCommands {}
+
+impl GeoCommands {
+    pub async fn handle_command(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        _tx: &mut (impl AsyncWriteExt + std::marker::Unpin),
+    ) -> Result<HandleCommandResult, SableError> {
+        let mut response_buffer = BytesMut::with_capacity(256);
+        match command.metadata().name() {
+            ValkeyCommandName::Geoadd => {
+                Self::geoadd(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Geodist => {
+                Self::geodist(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Geopos => {
+                Self::geopos(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Geohash => {
+                Self::geohash(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Geosearch => {
+                Self::geosearch(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Geosearchstore => {
+                Self::geosearchstore(client_state, command, &mut response_buffer).await?;
+            }
+            _ => {
+                return Err(SableError::InvalidArgument(format!(
+                    "Non geo command {}",
+                    command.main_command()
+                )));
+            }
+        }
+        Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
+    }
+
+    /// `GEOADD key [NX | XX] [CH] longitude latitude member [longitude latitude member ...]`
+    /// Adds the specified geospatial items (longitude, latitude, name) to the specified key.
+    /// Data is stored into the key as a sorted set, where the score is the 52 bit geohash
+    /// of the item
+    async fn geoadd(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 5, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let mut iter = command.args_vec().iter().skip(2).peekable();
+        let mut flags = ZWriteFlags::None;
+        while let Some(opt) = iter.peek() {
+            let opt_lowercase = BytesMutUtils::to_string(opt).to_lowercase();
+            match opt_lowercase.as_str() {
+                "nx" => flags.set(ZWriteFlags::Nx, true),
+                "xx" => flags.set(ZWriteFlags::Xx, true),
+                "ch" => flags.set(ZWriteFlags::Ch, true),
+                _ => break,
+            }
+            iter.next();
+        }
+
+        if flags.contains(ZWriteFlags::Nx | ZWriteFlags::Xx) {
+            builder.error_string(
+                response_buffer,
+                "ERR XX and NX options at the same time are not compatible",
+            );
+            return Ok(());
+        }
+
+        let items: Vec<&BytesMut> = iter.collect();
+        if items.is_empty() || items.len() % 3 != 0 {
+            builder.error_string(
+                response_buffer,
+                "ERR syntax error. Try GEOADD key [x1] [y1] [name1] [x2] [y2] [name2] ... ",
+            );
+            return Ok(());
+        }
+
+        // collect the score/member pairs
+        let mut pairs = Vec::<(f64, &BytesMut)>::with_capacity(items.len() / 3);
+        for item in items.chunks(3) {
+            let (Some(longitude), Some(latitude)) = (
+                BytesMutUtils::parse::<f64>(item[0]),
+                BytesMutUtils::parse::<f64>(item[1]),
+            ) else {
+                builder.error_string(response_buffer, Strings::VALUE_NOT_VALID_FLOAT);
+                return Ok(());
+            };
+
+            let Some(score) = GeoHash::encode_score(longitude, latitude) else {
+                builder.error_string(
+                    response_buffer,
+                    format!(
+                        "ERR invalid longitude,latitude pair {:.6},{:.6}",
+                        longitude, latitude
+                    )
+                    .as_str(),
+                );
+                return Ok(());
+            };
+            pairs.push((score, item[2]));
+        }
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut zset_db = ZSetDb::with_storage(client_state.database(), client_state.database_id());
+
+        let mut items_added = 0usize;
+        for (score, member) in &pairs {
+            match zset_db.add(key, member, *score, &flags, false)? {
+                ZSetAddMemberResult::Some(incr) => items_added = items_added.saturating_add(incr),
+                ZSetAddMemberResult::WrongType => {
+                    builder.error_string(response_buffer, Strings::WRONGTYPE);
+                    return Ok(());
+                }
+            }
+        }
+        zset_db.commit()?;
+
+        // Wakeup clients pending on this key
+        client_state
+            .server_inner_state()
+            .wakeup_clients(key, pairs.len())
+            .await;
+        builder.number_usize(response_buffer, items_added);
+        Ok(())
+    }
+
+    /// `GEODIST key member1 member2 [M | KM | FT | MI]`
+    /// Return the distance between two members in the geospatial index represented by the
+    /// sorted set. If one or both the members are missing, the command returns NULL
+    async fn geodist(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 4, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+        let member1 = command_arg_at!(command, 2);
+        let member2 = command_arg_at!(command, 3);
+
+        let unit = match command.args_vec().len() {
+            4 => 1.0,
+            5 => {
+                let Some(unit) = Self::parse_unit(command_arg_at!(command, 4)) else {
+                    builder.error_string(response_buffer, Self::UNSUPPORTED_UNIT);
+                    return Ok(());
+                };
+                unit
+            }
+            _ => {
+                builder.error_string(response_buffer, Strings::SYNTAX_ERROR);
+                return Ok(());
+            }
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let zset_db = ZSetDb::with_storage(client_state.database(), client_state.database_id());
+
+        let mut points = Vec::<(f64, f64)>::with_capacity(2);
+        for member in [member1, member2] {
+            match zset_db.get_score(key, member)? {
+                ZSetGetScoreResult::WrongType => {
+                    builder.error_string(response_buffer, Strings::WRONGTYPE);
+                    return Ok(());
+                }
+                ZSetGetScoreResult::NotFound => {
+                    builder.null_string(response_buffer);
+                    return Ok(());
+                }
+                ZSetGetScoreResult::Score(score) => points.push(GeoHash::decode_score(score)),
+            }
+        }
+
+        let distance = GeoHash::distance(points[0].0, points[0].1, points[1].0, points[1].1);
+        builder.bulk_string(
+            response_buffer,
+            Self::format_distance(distance, unit).as_bytes(),
+        );
+        Ok(())
+    }
+
+    /// `GEOPOS key [member [member ...]]`
+    /// Return the positions (longitude,latitude) of all the specified members of the
+    /// geospatial index represented by the sorted set at key. Non existing members are
+    /// reported as NULL elements of the array
+    async fn geopos(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let zset_db = ZSetDb::with_storage(client_state.database(), client_state.database_id());
+
+        let Some(scores) = Self::members_scores(&zset_db, key, &command.args_vec()[2..])? else {
+            builder.error_string(response_buffer, Strings::WRONGTYPE);
+            return Ok(());
+        };
+
+        builder.add_array_len(response_buffer, scores.len());
+        for score in scores {
+            match score {
+                Some(score) => {
+                    let (longitude, latitude) = GeoHash::decode_score(score);
+                    builder.add_array_len(response_buffer, 2);
+                    builder.add_bulk_string(
+                        response_buffer,
+                        Self::format_coordinate(longitude).as_bytes(),
+                    );
+                    builder.add_bulk_string(
+                        response_buffer,
+                        Self::format_coordinate(latitude).as_bytes(),
+                    );
+                }
+                None => builder.add_null_array(response_buffer),
+            }
+        }
+        Ok(())
+    }
+
+    /// `GEOHASH key [member [member ...]]`
+    /// Return valid 11 characters Geohash strings representing the position of one or more
+    /// elements in a sorted set value representing a geospatial index
+    async fn geohash(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let zset_db = ZSetDb::with_storage(client_state.database(), client_state.database_id());
+
+        let Some(scores) = Self::members_scores(&zset_db, key, &command.args_vec()[2..])? else {
+            builder.error_string(response_buffer, Strings::WRONGTYPE);
+            return Ok(());
+        };
+
+        builder.add_array_len(response_buffer, scores.len());
+        for score in scores {
+            match score {
+                Some(score) => builder.add_bulk_string(
+                    response_buffer,
+                    GeoHash::to_geohash_string(score).as_bytes(),
+                ),
+                None => builder.add_null_string(response_buffer),
+            }
+        }
+        Ok(())
+    }
+
+    /// `GEOSEARCH key <FROMMEMBER member | FROMLONLAT longitude latitude>
+    /// <BYRADIUS radius <M | KM | FT | MI> | BYBOX width height <M | KM | FT | MI>>
+    /// [ASC | DESC] [COUNT count [ANY]] [WITHCOORD] [WITHDIST] [WITHHASH]`
+    /// Return the members of a sorted set populated with geospatial information using
+    /// `GEOADD`, which are within the borders of the area specified by a given shape
+    async fn geosearch(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 7, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let args = match Self::parse_search_args(&command.args_vec()[2..], false) {
+            Ok(args) => args,
+            Err(msg) => {
+                builder.error_string(response_buffer, msg.as_str());
+                return Ok(());
+            }
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let zset_db = ZSetDb::with_storage(client_state.database(), client_state.database_id());
+
+        let set = match zset_db.find_set(key)? {
+            FindZSetResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+                return Ok(());
+            }
+            FindZSetResult::NotFound => {
+                builder.empty_array(response_buffer);
+                return Ok(());
+            }
+            FindZSetResult::Some(set) => set,
+        };
+
+        let items = match Self::search(&zset_db, key, &set, &args)? {
+            Ok(items) => items,
+            Err(msg) => {
+                builder.error_string(response_buffer, msg);
+                return Ok(());
+            }
+        };
+
+        let extra_fields = [args.with_dist, args.with_hash, args.with_coord]
+            .iter()
+            .filter(|flag| **flag)
+            .count();
+
+        builder.add_array_len(response_buffer, items.len());
+        for item in &items {
+            if extra_fields == 0 {
+                builder.add_bulk_string(response_buffer, &item.member);
+                continue;
+            }
+
+            builder.add_array_len(response_buffer, extra_fields.saturating_add(1));
+            builder.add_bulk_string(response_buffer, &item.member);
+            if args.with_dist {
+                builder.add_bulk_string(
+                    response_buffer,
+                    Self::format_distance(item.distance, args.unit).as_bytes(),
+                );
+            }
+            if args.with_hash {
+                builder.add_number::<u64>(response_buffer, item.score as u64, false);
+            }
+            if args.with_coord {
+                builder.add_array_len(response_buffer, 2);
+                builder.add_bulk_string(
+                    response_buffer,
+                    Self::format_coordinate(item.longitude).as_bytes(),
+                );
+                builder.add_bulk_string(
+                    response_buffer,
+                    Self::format_coordinate(item.latitude).as_bytes(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// `GEOSEARCHSTORE destination source <FROMMEMBER member | FROMLONLAT longitude latitude>
+    /// <BYRADIUS radius <M | KM | FT | MI> | BYBOX width height <M | KM | FT | MI>>
+    /// [ASC | DESC] [COUNT count [ANY]] [STOREDIST]`
+    /// This command is like `GEOSEARCH`, but stores the result in destination key. By default,
+    /// it stores the results in the destination sorted set with their geospatial information.
+    /// When using the `STOREDIST` option, the members are stored with their distance from the
+    /// center as their score
+    async fn geosearchstore(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 8, response_buffer);
+        let builder = RespBuilderV2::default();
+        let destination = command_arg_at!(command, 1);
+        let key = command_arg_at!(command, 2);
+
+        let args = match Self::parse_search_args(&command.args_vec()[3..], true) {
+            Ok(args) => args,
+            Err(msg) => {
+                builder.error_string(response_buffer, msg.as_str());
+                return Ok(());
+            }
+        };
+
+        let user_keys = vec![destination, key];
+        let _unused =
+            LockManager::lock_multi(&user_keys, client_state.clone(), command.clone()).await?;
+        let mut zset_db = ZSetDb::with_storage(client_state.database(), client_state.database_id());
+
+        let items = match zset_db.find_set(key)? {
+            FindZSetResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+                return Ok(());
+            }
+            FindZSetResult::NotFound => Vec::<GeoSearchItem>::new(),
+            FindZSetResult::Some(set) => match Self::search(&zset_db, key, &set, &args)? {
+                Ok(items) => items,
+                Err(msg) => {
+                    builder.error_string(response_buffer, msg);
+                    return Ok(());
+                }
+            },
+        };
+
+        // Delete any previous entry we had there and create a new set
+        zset_db.delete(destination, false)?;
+        let mut count: usize = 0;
+        for item in &items {
+            let score = if args.store_dist {
+                item.distance / args.unit
+            } else {
+                item.score
+            };
+            zset_db.add(destination, &item.member, score, &ZWriteFlags::None, false)?;
+            if count >= 1000 {
+                // commit every 1000 adds (to avoid too much memory used)
+                zset_db.commit()?;
+                count = 0;
+            }
+            count = count.saturating_add(1);
+        }
+        zset_db.commit()?;
+
+        // Wakeup clients pending on this key
+        client_state
+            .server_inner_state()
+            .wakeup_clients(destination, items.len())
+            .await;
+        builder.number_usize(response_buffer, items.len());
+        Ok(())
+    }
+
+    // Internal helpers
+
+    const UNSUPPORTED_UNIT: &'static str =
+        "ERR unsupported unit provided. please use M, KM, FT, MI";
+
+    /// Return the scores of `members`. Missing members are returned as `None`.
+    /// Return `None` if `key` exists but is not a sorted set
+    fn members_scores(
+        zset_db: &ZSetDb,
+        key: &BytesMut,
+        members: &[BytesMut],
+    ) -> Result<Option<Vec<Option<f64>>>, SableError> {
+        let mut scores = Vec::<Option<f64>>::with_capacity(members.len());
+        for member in members {
+            match zset_db.get_score(key, member)? {
+                ZSetGetScoreResult::WrongType => return Ok(None),
+                ZSetGetScoreResult::NotFound => scores.push(None),
+                ZSetGetScoreResult::Score(score) => scores.push(Some(score)),
+            }
+        }
+        Ok(Some(scores))
+    }
+
+    /// Search `set` for all the points that are within the shape described by `args`.
+    /// The inner `Err` contains an error message to return to the caller
+    fn search(
+        zset_db: &ZSetDb,
+        key: &BytesMut,
+        set: &SortedSet,
+        args: &GeoSearchArgs,
+    ) -> Result<Result<Vec<GeoSearchItem>, &'static str>, SableError> {
+        let (center_lon, center_lat) = match &args.origin {
+            SearchOrigin::LonLat(longitude, latitude) => (*longitude, *latitude),
+            SearchOrigin::Member(member) => match zset_db.get_score(key, member)? {
+                ZSetGetScoreResult::Score(score) => GeoHash::decode_score(score),
+                _ => return Ok(Err("ERR could not decode requested zset member")),
+            },
+        };
+
+        // With `ANY` we can stop as soon as we found enough matches
+        let limit = match (args.any, args.count) {
+            (true, Some(count)) => count,
+            _ => usize::MAX,
+        };
+
+        let mut items = Vec::<GeoSearchItem>::new();
+        'areas: for area in GeoHash::search_areas(&args.shape, center_lon, center_lat) {
+            let (min, max) = area.score_range();
+            for (member, score) in zset_db.members_by_score_range(set, min as f64, max as f64)? {
+                let (longitude, latitude) = GeoHash::decode_score(score);
+                let Some(distance) = GeoHash::distance_if_in_shape(
+                    &args.shape,
+                    center_lon,
+                    center_lat,
+                    longitude,
+                    latitude,
+                ) else {
+                    continue;
+                };
+                items.push(GeoSearchItem {
+                    member,
+                    score,
+                    distance,
+                    longitude,
+                    latitude,
+                });
+                if items.len() >= limit {
+                    break 'areas;
+                }
+            }
+        }
+
+        // When COUNT is used without ANY, the closest items are returned
+        let sort = match (args.sort, args.count, args.any) {
+            (SortOrder::None, Some(_), false) => SortOrder::Asc,
+            (sort, _, _) => sort,
+        };
+
+        match sort {
+            SortOrder::Asc => items.sort_by(|a, b| a.distance.total_cmp(&b.distance)),
+            SortOrder::Desc => items.sort_by(|a, b| b.distance.total_cmp(&a.distance)),
+            SortOrder::None => {}
+        }
+
+        if let Some(count) = args.count {
+            items.truncate(count);
+        }
+        Ok(Ok(items))
+    }
+
+    /// Parse the `GEOSEARCH` / `GEOSEARCHSTORE` arguments (starting after the key(s))
+    fn parse_search_args(args: &[BytesMut], is_store: bool) -> Result<GeoSearchArgs, String> {
+        let command_name = if is_store {
+            "GEOSEARCHSTORE"
+        } else {
+            "GEOSEARCH"
+        };
+
+        let mut origin: Option<SearchOrigin> = None;
+        let mut shape: Option<(GeoShape, f64)> = None;
+        let mut sort = SortOrder::None;
+        let mut count: Option<usize> = None;
+        let mut any = false;
+        let mut with_coord = false;
+        let mut with_dist = false;
+        let mut with_hash = false;
+        let mut store_dist = false;
+
+        let float_at = |pos: usize| -> Result<f64, String> {
+            args.get(pos)
+                .and_then(BytesMutUtils::parse::<f64>)
+                .ok_or_else(|| Strings::VALUE_NOT_VALID_FLOAT.to_string())
+        };
+
+        let unit_at = |pos: usize| -> Result<f64, String> {
+            args.get(pos)
+                .and_then(Self::parse_unit)
+                .ok_or_else(|| Self::UNSUPPORTED_UNIT.to_string())
+        };
+
+        let mut pos = 0usize;
+        while let Some(arg) = args.get(pos) {
+            let arg_lowercase = BytesMutUtils::to_string(arg).to_lowercase();
+            match arg_lowercase.as_str() {
+                "frommember" if pos + 1 < args.len() => {
+                    if origin.is_some() {
+                        return Err(format!(
+                            "ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for {}",
+                            command_name
+                        ));
+                    }
+                    origin = Some(SearchOrigin::Member(args[pos + 1].clone()));
+                    pos += 2;
+                }
+                "fromlonlat" if pos + 2 < args.len() => {
+                    if origin.is_some() {
+                        return Err(format!(
+                            "ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for {}",
+                            command_name
+                        ));
+                    }
+                    let longitude = float_at(pos + 1)?;
+                    let latitude = float_at(pos + 2)?;
+                    if !GeoHash::is_valid(longitude, latitude) {
+                        return Err(format!(
+                            "ERR invalid longitude,latitude pair {:.6},{:.6}",
+                            longitude, latitude
+                        ));
+                    }
+                    origin = Some(SearchOrigin::LonLat(longitude, latitude));
+                    pos += 3;
+                }
+                "byradius" if pos + 2 < args.len() => {
+                    if shape.is_some() {
+                        return Err(format!(
+                            "ERR exactly one of BYRADIUS and BYBOX can be specified for {}",
+                            command_name
+                        ));
+                    }
+                    let radius = float_at(pos + 1)?;
+                    if radius < 0.0 {
+                        return Err("ERR radius cannot be negative".to_string());
+                    }
+                    let unit = unit_at(pos + 2)?;
+                    shape = Some((GeoShape::Radius(radius * unit), unit));
+                    pos += 3;
+                }
+                "bybox" if pos + 3 < args.len() => {
+                    if shape.is_some() {
+                        return Err(format!(
+                            "ERR exactly one of BYRADIUS and BYBOX can be specified for {}",
+                            command_name
+                        ));
+                    }
+                    let width = float_at(pos + 1)?;
+                    let height = float_at(pos + 2)?;
+                    if width < 0.0 || height < 0.0 {
+                        return Err("ERR height or width cannot be negative".to_string());
+                    }
+                    let unit = unit_at(pos + 3)?;
+                    shape = Some((
+                        GeoShape::Box {
+                            width: width * unit,
+                            height: height * unit,
+                        },
+                        unit,
+                    ));
+                    pos += 4;
+                }
+                "asc" => {
+                    sort = SortOrder::Asc;
+                    pos += 1;
+                }
+                "desc" => {
+                    sort = SortOrder::Desc;
+                    pos += 1;
+                }
+                "count" if pos + 1 < args.len() => {
+                    let value = BytesMutUtils::parse::<i64>(&args[pos + 1])
+                        .ok_or_else(|| Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE.to_string())?;
+                    if value <= 0 {
+                        return Err("ERR COUNT must be > 0".to_string());
+                    }
+                    count = Some(value as usize);
+                    pos += 2;
+                }
+                "any" => {
+                    any = true;
+                    pos += 1;
+                }
+                "withcoord" if !is_store => {
+                    with_coord = true;
+                    pos += 1;
+                }
+                "withdist" if !is_store => {
+                    with_dist = true;
+                    pos += 1;
+                }
+                "withhash" if !is_store => {
+                    with_hash = true;
+                    pos += 1;
+                }
+                "storedist" if is_store => {
+                    store_dist = true;
+                    pos += 1;
+                }
+                _ => return Err(Strings::SYNTAX_ERROR.to_string()),
+            }
+        }
+
+        let Some(origin) = origin else {
+            return Err(format!(
+                "ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for {}",
+                command_name
+            ));
+        };
+
+        let Some((shape, unit)) = shape else {
+            return Err(format!(
+                "ERR exactly one of BYRADIUS and BYBOX can be specified for {}",
+                command_name
+            ));
+        };
+
+        if any && count.is_none() {
+            return Err("ERR the ANY argument requires COUNT argument".to_string());
+        }
+
+        Ok(GeoSearchArgs {
+            origin,
+            shape,
+            unit,
+            sort,
+            count,
+            any,
+            with_coord,
+            with_dist,
+            with_hash,
+            store_dist,
+        })
+    }
+
+    /// Return the number of meters in a single `unit`
+    fn parse_unit(unit: &BytesMut) -> Option<f64> {
+        match BytesMutUtils::to_string(unit).to_lowercase().as_str() {
+            "m" => Some(1.0),
+            "km" => Some(1000.0),
+            "ft" => Some(0.3048),
+            "mi" => Some(1609.34),
+            _ => None,
+        }
+    }
+
+    /// Format a distance (given in meters) in the requested unit
+    fn format_distance(distance: f64, unit: f64) -> String {
+        format!("{:.4}", distance / unit)
+    }
+
+    /// Format a coordinate with 17 digits precision, removing any trailing zeros
+    fn format_coordinate(value: f64) -> String {
+        let formatted = format!("{:.17}", value);
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::ClientNextAction, Client, ServerState};
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    #[test_case(vec![
+        (vec!["geoadd", "Sicily", "13.361389", "38.115556", "Palermo", "15.087269", "37.502669", "Catania"], ":2\r\n"),
+        (vec!["geoadd", "Sicily", "nx", "xx", "13.361389", "38.115556", "Palermo"], "-ERR XX and NX options at the same time are not compatible\r\n"),
+        (vec!["geoadd", "Sicily", "13.361389", "38.115556"], "-ERR wrong number of arguments for 'geoadd' command\r\n"),
+        (vec!["geoadd", "Sicily", "13.361389", "88.0", "North"], "-ERR invalid longitude,latitude pair 13.361389,88.000000\r\n"),
+        (vec!["geoadd", "Sicily", "ch", "13.361389", "38.115556", "Palermo"], ":0\r\n"),
+        (vec!["zscore", "Sicily", "Palermo"], "$16\r\n3479099956230698\r\n"),
+        (vec!["set", "string_key", "value"], "+OK\r\n"),
+        (vec!["geoadd", "string_key", "13.361389", "38.115556", "Palermo"], "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"),
+        ], "geoadd"; "geoadd")]
+    #[test_case(vec![
+        (vec!["geoadd", "Sicily", "13.361389", "38.115556", "Palermo", "15.087269", "37.502669", "Catania"], ":2\r\n"),
+        (vec!["geodist", "Sicily", "Palermo", "Catania"], "$11\r\n166274.1516\r\n"),
+        (vec!["geodist", "Sicily", "Palermo", "Catania", "km"], "$8\r\n166.2742\r\n"),
+        (vec!["geodist", "Sicily", "Palermo", "Catania", "mi"], "$7\r\n103.3182\r\n"),
+        (vec!["geodist", "Sicily", "Palermo", "Catania", "yards"], "-ERR unsupported unit provided. please use M, KM, FT, MI\r\n"),
+        (vec!["geodist", "Sicily", "Palermo", "Rome"], "$-1\r\n"),
+        (vec!["geopos", "Sicily", "Palermo", "Rome"], "*2\r\n*2\r\n$19\r\n13.36138933897018433\r\n$19\r\n38.11555639549629859\r\n*-1\r\n"),
+        (vec!["geohash", "Sicily", "Palermo", "Catania", "Rome"], "*3\r\n$11\r\nsqc8b49rny0\r\n$11\r\nsqdtr74hyu0\r\n$-1\r\n"),
+        ], "geodist_geopos_geohash"; "geodist_geopos_geohash")]
+    #[test_case(vec![
+        (vec!["geoadd", "Sicily", "13.361389", "38.115556", "Palermo", "15.087269", "37.502669", "Catania"], ":2\r\n"),
+        (vec!["geosearch", "Sicily", "fromlonlat", "15", "37", "byradius", "200", "km", "asc"], "*2\r\n$7\r\nCatania\r\n$7\r\nPalermo\r\n"),
+        (vec!["geosearch", "Sicily", "fromlonlat", "15", "37", "byradius", "100", "km"], "*1\r\n$7\r\nCatania\r\n"),
+        (vec!["geosearch", "Sicily", "fromlonlat", "15", "37", "byradius", "200", "km", "desc", "withdist"], "*2\r\n*2\r\n$7\r\nPalermo\r\n$8\r\n190.4424\r\n*2\r\n$7\r\nCatania\r\n$7\r\n56.4413\r\n"),
+        (vec!["geosearch", "Sicily", "fromlonlat", "15", "37", "byradius", "200", "km", "count", "1"], "*1\r\n$7\r\nCatania\r\n"),
+        (vec!["geosearch", "Sicily", "frommember", "Palermo", "bybox", "400", "400", "km", "asc", "withhash"], "*2\r\n*2\r\n$7\r\nPalermo\r\n:3479099956230698\r\n*2\r\n$7\r\nCatania\r\n:3479447370796909\r\n"),
+        (vec!["geosearch", "Sicily", "frommember", "Rome", "byradius", "200", "km"], "-ERR could not decode requested zset member\r\n"),
+        (vec!["geosearch", "Sicily", "fromlonlat", "15", "37", "frommember", "Palermo", "byradius", "200", "km"], "-ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH\r\n"),
+        (vec!["geosearch", "Sicily", "fromlonlat", "15", "37", "asc", "withdist"], "-ERR exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH\r\n"),
+        (vec!["geosearch", "Sicily", "fromlonlat", "15", "37", "byradius", "200", "km", "any"], "-ERR the ANY argument requires COUNT argument\r\n"),
+        (vec!["geosearch", "Sicily", "fromlonlat", "15", "37", "byradius", "200", "km", "count", "0"], "-ERR COUNT must be > 0\r\n"),
+        (vec!["geosearch", "nosuchkey", "fromlonlat", "15", "37", "byradius", "200", "km"], "*0\r\n"),
+        ], "geosearch"; "geosearch")]
+    #[test_case(vec![
+        (vec!["geoadd", "Sicily", "13.361389", "38.115556", "Palermo", "15.087269", "37.502669", "Catania"], ":2\r\n"),
+        (vec!["geosearchstore", "dest", "Sicily", "fromlonlat", "15", "37", "byradius", "200", "km"], ":2\r\n"),
+        (vec!["zrange", "dest", "0", "-1", "withscores"], "*4\r\n$7\r\nPalermo\r\n$16\r\n3479099956230698\r\n$7\r\nCatania\r\n$16\r\n3479447370796909\r\n"),
+        (vec!["geosearchstore", "dest", "Sicily", "fromlonlat", "15", "37", "byradius", "200", "km", "count", "1", "storedist"], ":1\r\n"),
+        (vec!["zcard", "dest"], ":1\r\n"),
+        (vec!["geosearchstore", "dest", "Sicily", "fromlonlat", "15", "37", "byradius", "200", "km", "withdist"], "-ERR syntax error\r\n"),
+        ], "geosearchstore"; "geosearchstore")]
+    fn test_geo_commands(
+        args_vec: Vec<(Vec<&'static str>, &'static str)>,
+        test_name: &str,
+    ) -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            for (args, expected_value) in args_vec {
+                let mut sink = crate::tests::ResponseSink::with_name(test_name).await;
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                match Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                    .await
+                    .unwrap()
+                {
+                    ClientNextAction::NoAction => {
+                        assert_eq!(sink.read_all().await.as_str(), expected_value);
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+}
@@ -6,8 +6,9 @@ use crate::{
     parse_string_to_number,
     server::ClientState,
     storage::{
-        FindHashResult, HashDb, HashDeleteResult, HashExistsResult, HashGetMultiResult,
-        HashGetResult, HashLenResult, HashPutResult, ScanCursor,
+        FindHashResult, HashDb, HashDeleteResult, HashExistsResult, HashExpireResult,
+        HashFieldExpiration, HashFieldTtl, HashFieldsTtlResult, HashGetMultiResult, HashGetResult,
+        HashLenResult, HashPutResult, ScanCursor,
     },
     utils::{PatternMatcher, RespBuilderV2},
     BytesMutUtils, Expiration, LockManager, PrimaryKeyMetadata, SableError, StorageAdapter,
@@ -29,6 +30,20 @@ enum HGetAllOutput {
     Both,
 }
 
+/// `HEXPIRE` family condition
+#[derive(Eq, PartialEq, Clone, Copy)]
+enum HExpireCondition {
+    Always,
+    /// Set the expiration only when the field has no expiration
+    Nx,
+    /// Set the expiration only when the field has an existing expiration
+    Xx,
+    /// Set the expiration only when the new expiration is greater than the current one
+    Gt,
+    /// Set the expiration only when the new expiration is less than the current one
+    Lt,
+}
+
 impl HashCommands {
     pub async fn handle_command(
         client_state: Rc<ClientState>,
@@ -105,6 +120,27 @@ impl HashCommands {
                 // simple response, write to response_buffer
                 Self::hstrlen(client_state, command, &mut response_buffer).await?;
             }
+            ValkeyCommandName::Hexpire
+            | ValkeyCommandName::Hpexpire
+            | ValkeyCommandName::Hexpireat
+            | ValkeyCommandName::Hpexpireat => {
+                Self::hexpire(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Httl
+            | ValkeyCommandName::Hpttl
+            | ValkeyCommandName::Hexpiretime
+            | ValkeyCommandName::Hpexpiretime => {
+                Self::httl(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Hpersist => {
+                Self::hpersist(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Hgetex => {
+                Self::hgetex(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Hsetex => {
+                Self::hsetex(client_state, command, &mut response_buffer).await?;
+            }
             _ => {
                 return Err(SableError::InvalidArgument(format!(
                     "Non hash command {}",
@@ -414,7 +450,9 @@ impl HashCommands {
         };
 
         // empty hash? empty array
-        if hash.is_empty() {
+        let now = TimeUtils::epoch_ms()?;
+        let hash_len = hash_db.live_len(&hash, now)?;
+        if hash_len == 0 {
             writer.empty_array().await?;
             writer.flush().await?;
             return Ok(());
@@ -423,7 +461,7 @@ impl HashCommands {
         // Write the length
         writer
             .add_array_len(
-                hash.len()
+                hash_len
                     .saturating_mul(if output_type == HGetAllOutput::Both {
                         2
                     } else {
@@ -451,16 +489,22 @@ impl HashCommands {
                 panic!("failed to construct hashfieldkey!");
             };
 
+            let field_value = hash.value.decode_field_value(value)?;
+            if field_value.is_expired(now) {
+                db_iter.next();
+                continue;
+            }
+
             match output_type {
                 HGetAllOutput::Keys => {
                     writer.add_bulk_string(hash_field_key.user_key()).await?;
                 }
                 HGetAllOutput::Values => {
-                    writer.add_bulk_string(value).await?;
+                    writer.add_bulk_string(field_value.value()).await?;
                 }
                 HGetAllOutput::Both => {
                     writer.add_bulk_string(hash_field_key.user_key()).await?;
-                    writer.add_bulk_string(value).await?;
+                    writer.add_bulk_string(field_value.value()).await?;
                 }
             }
             db_iter.next();
@@ -512,9 +556,13 @@ impl HashCommands {
         let new_value = prev_value + increment;
         builder.number::<i64>(response_buffer, new_value, false);
 
-        // store the new value
+        // store the new value, the field keeps its expiration time
         let new_value = BytesMutUtils::from::<i64>(&new_value);
-        let _ = hash_db.put_multi(key, &[(field, &new_value)])?;
+        let _ = hash_db.put_multi_with_expiration(
+            key,
+            &[(field, &new_value)],
+            HashFieldExpiration::Keep,
+        )?;
         Ok(())
     }
 
@@ -560,9 +608,13 @@ impl HashCommands {
         let new_value = prev_value + increment;
         builder.number::<f64>(response_buffer, new_value, true);
 
-        // store the new value
+        // store the new value, the field keeps its expiration time
         let new_value = BytesMutUtils::from::<f64>(&new_value);
-        let _ = hash_db.put_multi(key, &[(field, &new_value)])?;
+        let _ = hash_db.put_multi_with_expiration(
+            key,
+            &[(field, &new_value)],
+            HashFieldExpiration::Keep,
+        )?;
         Ok(())
     }
 
@@ -683,10 +735,14 @@ impl HashCommands {
         };
 
         // Adjust the "count"
-        let count = if allow_dups {
+        let now = TimeUtils::epoch_ms()?;
+        let hash_len = hash_db.live_len(&hash, now)?;
+        let count = if hash_len == 0 {
+            0
+        } else if allow_dups {
             count
         } else {
-            std::cmp::min(count, hash.len() as i64)
+            std::cmp::min(count, hash_len as i64)
         };
 
         // fast bail out
@@ -696,7 +752,7 @@ impl HashCommands {
             return Ok(());
         }
 
-        let possible_indexes = (0..hash.len() as usize).collect::<Vec<usize>>();
+        let possible_indexes = (0..hash_len as usize).collect::<Vec<usize>>();
 
         // select the indices we want to pick
         let mut indices =
@@ -744,11 +800,18 @@ impl HashCommands {
             // extract the key from the row data
             let hash_field_key = HashFieldKey::from_bytes(key)?;
 
+            // expired fields are not counted
+            let field_value = hash.value.decode_field_value(value)?;
+            if field_value.is_expired(now) {
+                db_iter.next();
+                continue;
+            }
+
             while let Some(wanted_index) = indices.front() {
                 if curidx.eq(wanted_index) {
                     builder.add_bulk_string(&mut response_buffer, hash_field_key.user_key());
                     if with_values {
-                        builder.add_bulk_string(&mut response_buffer, value);
+                        builder.add_bulk_string(&mut response_buffer, field_value.value());
                     }
                     // pop the first element
                     indices.pop_front();
@@ -889,6 +952,7 @@ impl HashCommands {
         };
 
         let hash_prefix = hash.item_prefix();
+        let now = TimeUtils::epoch_ms()?;
 
         let mut results = Vec::<(BytesMut, BytesMut)>::with_capacity(count);
         let mut db_iter = client_state.database().create_iterator(&iter_start_pos)?;
@@ -906,14 +970,11 @@ impl HashCommands {
             // extract the key from the row data
             let hash_field_key = HashFieldKey::from_bytes(key)?;
             let item_user_key = hash_field_key.user_key();
-            let item_user_value = value;
-
-            // Collect matches
-            if matcher.matches(item_user_key) {
-                results.push((
-                    BytesMut::from(item_user_key),
-                    BytesMut::from(item_user_value),
-                ));
+            let field_value = hash.value.decode_field_value(value)?;
+
+            // Collect matches, skipping expired fields
+            if !field_value.is_expired(now) && matcher.matches(item_user_key) {
+                results.push((BytesMut::from(item_user_key), field_value.into_value()));
                 count = count.saturating_sub(1);
             }
             db_iter.next();
@@ -959,6 +1020,441 @@ impl HashCommands {
 
         Ok(())
     }
+
+    /// `HEXPIRE key seconds [NX | XX | GT | LT] FIELDS numfields field [field ...]`
+    /// (and `HPEXPIRE`, `HEXPIREAT`, `HPEXPIREAT`)
+    /// Set an expiration time on hash fields. For every field, return `-2` if the field does not
+    /// exist, `0` if the condition was not met, `1` if the expiration time was set and `2` if the
+    /// field was deleted because the expiration time is in the past
+    async fn hexpire(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 6, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+        let time = command_arg_at!(command, 2);
+
+        let now = TimeUtils::epoch_ms()?;
+        let expire_timestamp_ms = BytesMutUtils::parse::<i64>(time)
+            .and_then(|time| u64::try_from(time).ok())
+            .and_then(|time| match command.metadata().name() {
+                ValkeyCommandName::Hexpire => time.checked_mul(1000)?.checked_add(now),
+                ValkeyCommandName::Hpexpire => time.checked_add(now),
+                ValkeyCommandName::Hexpireat => time.checked_mul(1000),
+                _ => Some(time),
+            });
+        let Some(expire_timestamp_ms) = expire_timestamp_ms else {
+            builder.error_string(
+                response_buffer,
+                &format!(
+                    "ERR invalid expire time in '{}' command",
+                    command.main_command()
+                ),
+            );
+            return Ok(());
+        };
+
+        let (condition, fields_pos) = match command.arg_as_lowercase_string(3).as_deref() {
+            Some("nx") => (HExpireCondition::Nx, 4),
+            Some("xx") => (HExpireCondition::Xx, 4),
+            Some("gt") => (HExpireCondition::Gt, 4),
+            Some("lt") => (HExpireCondition::Lt, 4),
+            _ => (HExpireCondition::Always, 3),
+        };
+
+        let fields = match Self::parse_fields(&command, fields_pos, 1) {
+            Ok(fields) => fields,
+            Err(msg) => {
+                builder.error_string(response_buffer, msg);
+                return Ok(());
+            }
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut hash_db = HashDb::with_storage(client_state.database(), client_state.database_id());
+
+        let ttls = match hash_db.fields_ttl(key, &fields)? {
+            HashFieldsTtlResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+                return Ok(());
+            }
+            HashFieldsTtlResult::Some(ttls) => ttls,
+        };
+
+        let mut codes = Vec::<i64>::with_capacity(fields.len());
+        let mut fields_to_update = Vec::<&BytesMut>::with_capacity(fields.len());
+        for (field, ttl) in fields.iter().zip(ttls) {
+            let current = match ttl {
+                HashFieldTtl::NoSuchField => {
+                    codes.push(-2);
+                    continue;
+                }
+                HashFieldTtl::Persistent => None,
+                HashFieldTtl::ExpireAt(timestamp) => Some(timestamp),
+            };
+
+            let condition_met = match condition {
+                HExpireCondition::Always => true,
+                HExpireCondition::Nx => current.is_none(),
+                HExpireCondition::Xx => current.is_some(),
+                // a field without expiration time never expires
+                HExpireCondition::Gt => current.is_some_and(|c| expire_timestamp_ms > c),
+                HExpireCondition::Lt => !current.is_some_and(|c| expire_timestamp_ms >= c),
+            };
+            if !condition_met {
+                codes.push(0);
+                continue;
+            }
+
+            codes.push(if expire_timestamp_ms <= now { 2 } else { 1 });
+            fields_to_update.push(*field);
+        }
+
+        if !fields_to_update.is_empty() {
+            hash_db.set_fields_expiration(
+                key,
+                &fields_to_update,
+                HashFieldExpiration::At(expire_timestamp_ms),
+            )?;
+        }
+
+        builder.add_array_len(response_buffer, codes.len());
+        for code in codes {
+            builder.add_number::<i64>(response_buffer, code, false);
+        }
+        Ok(())
+    }
+
+    /// `HTTL key FIELDS numfields field [field ...]` (and `HPTTL`, `HEXPIRETIME`, `HPEXPIRETIME`)
+    /// Return the remaining time to live (or the expiration timestamp) of hash fields. For every
+    /// field, return `-2` if the field does not exist and `-1` if the field has no expiration time
+    async fn httl(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 5, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let fields = match Self::parse_fields(&command, 2, 1) {
+            Ok(fields) => fields,
+            Err(msg) => {
+                builder.error_string(response_buffer, msg);
+                return Ok(());
+            }
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let hash_db = HashDb::with_storage(client_state.database(), client_state.database_id());
+
+        let ttls = match hash_db.fields_ttl(key, &fields)? {
+            HashFieldsTtlResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+                return Ok(());
+            }
+            HashFieldsTtlResult::Some(ttls) => ttls,
+        };
+
+        let now = TimeUtils::epoch_ms()?;
+        builder.add_array_len(response_buffer, ttls.len());
+        for ttl in ttls {
+            let value = match ttl {
+                HashFieldTtl::NoSuchField => -2i64,
+                HashFieldTtl::Persistent => -1i64,
+                HashFieldTtl::ExpireAt(timestamp) => {
+                    let value = match command.metadata().name() {
+                        ValkeyCommandName::Httl => timestamp.saturating_sub(now).div_ceil(1000),
+                        ValkeyCommandName::Hpttl => timestamp.saturating_sub(now),
+                        ValkeyCommandName::Hexpiretime => timestamp.div_ceil(1000),
+                        _ => timestamp,
+                    };
+                    i64::try_from(value).unwrap_or(i64::MAX)
+                }
+            };
+            builder.add_number::<i64>(response_buffer, value, false);
+        }
+        Ok(())
+    }
+
+    /// `HPERSIST key FIELDS numfields field [field ...]`
+    /// Remove the expiration time of hash fields. For every field, return `-2` if the field does
+    /// not exist, `-1` if the field has no expiration time and `1` if the expiration was removed
+    async fn hpersist(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 5, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let fields = match Self::parse_fields(&command, 2, 1) {
+            Ok(fields) => fields,
+            Err(msg) => {
+                builder.error_string(response_buffer, msg);
+                return Ok(());
+            }
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut hash_db = HashDb::with_storage(client_state.database(), client_state.database_id());
+
+        let ttls = match hash_db.fields_ttl(key, &fields)? {
+            HashFieldsTtlResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+                return Ok(());
+            }
+            HashFieldsTtlResult::Some(ttls) => ttls,
+        };
+
+        let mut codes = Vec::<i64>::with_capacity(fields.len());
+        let mut fields_to_update = Vec::<&BytesMut>::with_capacity(fields.len());
+        for (field, ttl) in fields.iter().zip(ttls) {
+            match ttl {
+                HashFieldTtl::NoSuchField => codes.push(-2),
+                HashFieldTtl::Persistent => codes.push(-1),
+                HashFieldTtl::ExpireAt(_) => {
+                    codes.push(1);
+                    fields_to_update.push(*field);
+                }
+            }
+        }
+
+        if !fields_to_update.is_empty() {
+            hash_db.set_fields_expiration(key, &fields_to_update, HashFieldExpiration::Persist)?;
+        }
+
+        builder.add_array_len(response_buffer, codes.len());
+        for code in codes {
+            builder.add_number::<i64>(response_buffer, code, false);
+        }
+        Ok(())
+    }
+
+    /// `HGETEX key [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+    /// PXAT unix-time-milliseconds | PERSIST] FIELDS numfields field [field ...]`
+    /// Return the values of hash fields and optionally set (or remove) their expiration time
+    async fn hgetex(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 5, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let now = TimeUtils::epoch_ms()?;
+        let mut expiration: Option<HashFieldExpiration> = None;
+        let mut pos = 2usize;
+        while let Some(arg) = command.arg_as_lowercase_string(pos) {
+            match arg.as_str() {
+                "fields" => break,
+                "persist" if expiration.is_none() => {
+                    expiration = Some(HashFieldExpiration::Persist);
+                    pos = pos.saturating_add(1);
+                }
+                "ex" | "px" | "exat" | "pxat" if expiration.is_none() => {
+                    let Some(timestamp) =
+                        Self::parse_expire_timestamp(&arg, command.arg(pos + 1), now)
+                    else {
+                        builder.error_string(
+                            response_buffer,
+                            "ERR invalid expire time in 'hgetex' command",
+                        );
+                        return Ok(());
+                    };
+                    expiration = Some(HashFieldExpiration::At(timestamp));
+                    pos = pos.saturating_add(2);
+                }
+                _ => {
+                    builder.error_string(response_buffer, Strings::SYNTAX_ERROR);
+                    return Ok(());
+                }
+            }
+        }
+
+        let fields = match Self::parse_fields(&command, pos, 1) {
+            Ok(fields) => fields,
+            Err(msg) => {
+                builder.error_string(response_buffer, msg);
+                return Ok(());
+            }
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut hash_db = HashDb::with_storage(client_state.database(), client_state.database_id());
+
+        let values = match hash_db.get_multi(key, &fields)? {
+            HashGetMultiResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+                return Ok(());
+            }
+            HashGetMultiResult::None => vec![None; fields.len()],
+            HashGetMultiResult::Some(values) => values,
+        };
+
+        if let Some(expiration) = expiration {
+            let existing_fields: Vec<&BytesMut> = fields
+                .iter()
+                .zip(&values)
+                .filter_map(|(field, value)| value.as_ref().map(|_| *field))
+                .collect();
+            if !existing_fields.is_empty() {
+                hash_db.set_fields_expiration(key, &existing_fields, expiration)?;
+            }
+        }
+
+        builder.add_array_len(response_buffer, values.len());
+        for value in values {
+            match value {
+                Some(value) => builder.add_bulk_string(response_buffer, &value),
+                None => builder.add_null_string(response_buffer),
+            }
+        }
+        Ok(())
+    }
+
+    /// `HSETEX key [FNX | FXX] [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+    /// PXAT unix-time-milliseconds | KEEPTTL] FIELDS numfields field value [field value ...]`
+    /// Set the values of hash fields and optionally their expiration time. Without `KEEPTTL`, or
+    /// an expiration time, the fields expiration time is removed. Return `1` if the fields were
+    /// set, `0` if the `FNX` / `FXX` condition was not met
+    async fn hsetex(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 6, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let now = TimeUtils::epoch_ms()?;
+        let mut expiration: Option<HashFieldExpiration> = None;
+        // `Some(true)` for FNX, `Some(false)` for FXX
+        let mut only_new_fields: Option<bool> = None;
+        let mut pos = 2usize;
+        while let Some(arg) = command.arg_as_lowercase_string(pos) {
+            match arg.as_str() {
+                "fields" => break,
+                "fnx" | "fxx" if only_new_fields.is_none() => {
+                    only_new_fields = Some(arg == "fnx");
+                    pos = pos.saturating_add(1);
+                }
+                "keepttl" if expiration.is_none() => {
+                    expiration = Some(HashFieldExpiration::Keep);
+                    pos = pos.saturating_add(1);
+                }
+                "ex" | "px" | "exat" | "pxat" if expiration.is_none() => {
+                    let Some(timestamp) =
+                        Self::parse_expire_timestamp(&arg, command.arg(pos + 1), now)
+                    else {
+                        builder.error_string(
+                            response_buffer,
+                            "ERR invalid expire time in 'hsetex' command",
+                        );
+                        return Ok(());
+                    };
+                    expiration = Some(HashFieldExpiration::At(timestamp));
+                    pos = pos.saturating_add(2);
+                }
+                _ => {
+                    builder.error_string(response_buffer, Strings::SYNTAX_ERROR);
+                    return Ok(());
+                }
+            }
+        }
+
+        let args = match Self::parse_fields(&command, pos, 2) {
+            Ok(args) => args,
+            Err(msg) => {
+                builder.error_string(response_buffer, msg);
+                return Ok(());
+            }
+        };
+        let field_values: Vec<(&BytesMut, &BytesMut)> =
+            args.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut hash_db = HashDb::with_storage(client_state.database(), client_state.database_id());
+
+        if let Some(only_new_fields) = only_new_fields {
+            let fields: Vec<&BytesMut> = field_values.iter().map(|(field, _)| *field).collect();
+            let ttls = match hash_db.fields_ttl(key, &fields)? {
+                HashFieldsTtlResult::WrongType => {
+                    builder.error_string(response_buffer, Strings::WRONGTYPE);
+                    return Ok(());
+                }
+                HashFieldsTtlResult::Some(ttls) => ttls,
+            };
+            let condition_met = ttls
+                .iter()
+                .all(|ttl| (*ttl == HashFieldTtl::NoSuchField) == only_new_fields);
+            if !condition_met {
+                builder.number_usize(response_buffer, 0);
+                return Ok(());
+            }
+        }
+
+        match hash_db.put_multi_with_expiration(
+            key,
+            &field_values,
+            expiration.unwrap_or(HashFieldExpiration::Persist),
+        )? {
+            HashPutResult::WrongType => {
+                builder.error_string(response_buffer, Strings::WRONGTYPE);
+            }
+            HashPutResult::Some(_) => {
+                builder.number_usize(response_buffer, 1);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse the `FIELDS numfields arg [arg ...]` block that starts at position `pos`. Each field
+    /// is made of `args_per_field` arguments (e.g. `field value` for `HSETEX`). Return the
+    /// arguments that follow `numfields`, or an error message
+    fn parse_fields(
+        command: &ValkeyCommand,
+        pos: usize,
+        args_per_field: usize,
+    ) -> Result<Vec<&BytesMut>, &'static str> {
+        if command.arg_as_lowercase_string(pos).as_deref() != Some("fields") {
+            return Err(Strings::ERR_FIELDS_MISSING);
+        }
+
+        let numfields = command
+            .arg(pos + 1)
+            .and_then(BytesMutUtils::parse::<i64>)
+            .ok_or(Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE)?;
+        if numfields <= 0 {
+            return Err(Strings::ERR_NUMFIELDS_ZERO);
+        }
+
+        let args: Vec<&BytesMut> = command.args_vec().iter().skip(pos + 2).collect();
+        if (numfields as usize).checked_mul(args_per_field) != Some(args.len()) {
+            return Err(Strings::ERR_NUMFIELDS_MISMATCH);
+        }
+        Ok(args)
+    }
+
+    /// Convert an `EX`, `PX`, `EXAT` or `PXAT` argument (`unit` is the lowercase token) into an
+    /// expiration timestamp in milliseconds. Return `None` if the value is not a positive number
+    fn parse_expire_timestamp(unit: &str, value: Option<&BytesMut>, now: u64) -> Option<u64> {
+        let value = BytesMutUtils::parse::<i64>(value?)?;
+        if value <= 0 {
+            return None;
+        }
+        let value = value as u64;
+        match unit {
+            "ex" => value.checked_mul(1000)?.checked_add(now),
+            "px" => value.checked_add(now),
+            "exat" => value.checked_mul(1000),
+            _ => Some(value),
+        }
+    }
 }
 
 //  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
@@ -1139,6 +1635,56 @@ mod test {
         (vec!["hset", "myhash", "field", "value"], ":1\r\n"),
         (vec!["hstrlen", "myhash", "field"], ":5\r\n"),
     ], "test_hstrlen"; "test_hstrlen")]
+    #[test_case(vec![
+        (vec!["hset", "myhash", "f1", "v1", "f2", "v2", "f3", "v3"], ":3\r\n"),
+        (vec!["hexpire", "myhash", "100", "FIELDS", "2", "f1", "nosuchfield"], "*2\r\n:1\r\n:-2\r\n"),
+        (vec!["httl", "myhash", "FIELDS", "3", "f1", "f2", "nosuchfield"], "*3\r\n:100\r\n:-1\r\n:-2\r\n"),
+        (vec!["hexpire", "myhash", "100", "NX", "FIELDS", "1", "f1"], "*1\r\n:0\r\n"),
+        (vec!["hexpire", "myhash", "200", "GT", "FIELDS", "2", "f1", "f2"], "*2\r\n:1\r\n:0\r\n"),
+        (vec!["hexpire", "myhash", "50", "LT", "FIELDS", "1", "f2"], "*1\r\n:1\r\n"),
+        (vec!["hexpire", "myhash", "100", "XX", "FIELDS", "2", "f2", "f3"], "*2\r\n:1\r\n:0\r\n"),
+        (vec!["httl", "myhash", "FIELDS", "2", "f1", "f2"], "*2\r\n:200\r\n:100\r\n"),
+        (vec!["hpersist", "myhash", "FIELDS", "3", "f1", "f3", "nosuchfield"], "*3\r\n:1\r\n:-1\r\n:-2\r\n"),
+        (vec!["hpttl", "myhash", "FIELDS", "1", "f1"], "*1\r\n:-1\r\n"),
+        // HSET removes the expiration time
+        (vec!["hset", "myhash", "f2", "v2"], ":0\r\n"),
+        (vec!["hexpiretime", "myhash", "FIELDS", "1", "f2"], "*1\r\n:-1\r\n"),
+        (vec!["hpexpireat", "myhash", "1", "FIELDS", "1", "f3"], "*1\r\n:2\r\n"),
+        (vec!["hget", "myhash", "f3"], "$-1\r\n"),
+        (vec!["hlen", "myhash"], ":2\r\n"),
+        (vec!["hexpire", "myhash", "0", "FIELDS", "2", "f1", "f2"], "*2\r\n:2\r\n:2\r\n"),
+        (vec!["exists", "myhash"], ":0\r\n"),
+        (vec!["httl", "myhash", "FIELDS", "1", "f1"], "*1\r\n:-2\r\n"),
+        (vec!["hexpire", "myhash", "100", "FIELDS", "2", "f1"], "-ERR The `numfields` parameter must match the number of arguments\r\n"),
+        (vec!["hexpire", "myhash", "100", "FIELDS", "0", "f1"], "-ERR Parameter `numFields` should be greater than 0\r\n"),
+        (vec!["hexpire", "myhash", "100", "FILEDS", "1", "f1"], "-ERR Mandatory argument FIELDS is missing or not at the right position\r\n"),
+        (vec!["hexpire", "myhash", "-1", "FIELDS", "1", "f1"], "-ERR invalid expire time in 'hexpire' command\r\n"),
+        (vec!["set", "str", "value"], "+OK\r\n"),
+        (vec!["httl", "str", "FIELDS", "1", "f1"], "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"),
+    ], "test_hexpire"; "test_hexpire")]
+    #[test_case(vec![
+        (vec!["hsetex", "myhash", "EX", "100", "FIELDS", "2", "a", "1", "b", "2"], ":1\r\n"),
+        (vec!["httl", "myhash", "FIELDS", "2", "a", "b"], "*2\r\n:100\r\n:100\r\n"),
+        (vec!["hsetex", "myhash", "FNX", "FIELDS", "2", "a", "3", "c", "3"], ":0\r\n"),
+        (vec!["hsetex", "myhash", "FXX", "KEEPTTL", "FIELDS", "1", "a", "3"], ":1\r\n"),
+        (vec!["httl", "myhash", "FIELDS", "1", "a"], "*1\r\n:100\r\n"),
+        (vec!["hsetex", "myhash", "FIELDS", "1", "a", "4"], ":1\r\n"),
+        (vec!["httl", "myhash", "FIELDS", "1", "a"], "*1\r\n:-1\r\n"),
+        (vec!["hgetex", "myhash", "PERSIST", "FIELDS", "2", "b", "nosuchfield"], "*2\r\n$1\r\n2\r\n$-1\r\n"),
+        (vec!["httl", "myhash", "FIELDS", "1", "b"], "*1\r\n:-1\r\n"),
+        (vec!["hgetex", "myhash", "EX", "10", "FIELDS", "1", "a"], "*1\r\n$1\r\n4\r\n"),
+        // HINCRBY keeps the expiration time
+        (vec!["hincrby", "myhash", "a", "1"], ":5\r\n"),
+        (vec!["httl", "myhash", "FIELDS", "1", "a"], "*1\r\n:10\r\n"),
+        (vec!["hgetex", "myhash", "PXAT", "1", "FIELDS", "1", "a"], "*1\r\n$1\r\n5\r\n"),
+        (vec!["hexists", "myhash", "a"], ":0\r\n"),
+        (vec!["hgetall", "myhash"], "*2\r\n$1\r\nb\r\n$1\r\n2\r\n"),
+        (vec!["hgetex", "myhash", "EX", "0", "FIELDS", "1", "b"], "-ERR invalid expire time in 'hgetex' command\r\n"),
+        (vec!["hgetex", "myhash", "EX", "10", "PERSIST", "FIELDS", "1", "b"], "-ERR syntax error\r\n"),
+        (vec!["hsetex", "myhash", "FIELDS", "2", "a", "1", "b"], "-ERR The `numfields` parameter must match the number of arguments\r\n"),
+        (vec!["hsetex", "myhash", "FXX", "FIELDS", "1", "nosuchfield", "1"], ":0\r\n"),
+        (vec!["hlen", "myhash"], ":1\r\n"),
+    ], "test_hgetex_hsetex"; "test_hgetex_hsetex")]
     fn test_hash_commands(
         args: Vec<(Vec<&'static str>, &'static str)>,
         test_name: &str,
@@ -1354,7 +1900,7 @@ mod test {
         let arr = response.array().unwrap();
         assert_eq!(arr.len(), 2);
 
-        let next_cursor_id = arr[0].integer()?;
+        let next_cursor_id = arr[0].integer()? as u64;
         let items = arr[1].array()?;
 
         let mut values = Vec::<String>::with_capacity(items.len() * 2);
@@ -0,0 +1,270 @@
+#[allow(unused_imports)]
+use crate::{
+    check_args_count, command_arg_at,
+    commands::{HandleCommandResult, Strings},
+    server::ClientState,
+    storage::{PutFlags, StringGetResult, StringsDb},
+    utils::{HyperLogLog, HyperLogLogError, RespBuilderV2},
+    LockManager, SableError, StringValueMetadata, ValkeyCommand, ValkeyCommandName,
+};
+
+use bytes::BytesMut;
+use std::rc::Rc;
+use tokio::io::AsyncWriteExt;
+
+enum LoadHyperLogLogResult {
+    /// The key holds a value that is not a HyperLogLog. Contains the error message
+    Invalid(&'static str),
+    NotFound,
+    Some(HyperLogLog, StringValueMetadata),
+}
+
+pub struct HyperLogLogCommands {}
+
+impl HyperLogLogCommands {
+    pub async fn handle_command(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        _tx: &mut (impl AsyncWriteExt + std::marker::Unpin),
+    ) -> Result<HandleCommandResult, SableError> {
+        let mut response_buffer = BytesMut::with_capacity(256);
+        match command.metadata().name() {
+            ValkeyCommandName::Pfadd => {
+                Self::pfadd(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Pfcount => {
+                Self::pfcount(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Pfmerge => {
+                Self::pfmerge(client_state, command, &mut response_buffer).await?;
+            }
+            _ => {
+                return Err(SableError::InvalidArgument(format!(
+                    "Non HyperLogLog command {}",
+                    command.main_command()
+                )));
+            }
+        }
+        Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
+    }
+
+    /// `PFADD key [element [element ...]]`
+    /// Adds all the element arguments to the HyperLogLog data structure stored at the variable
+    /// name specified as first argument. Returns 1 if at least 1 HyperLogLog internal register
+    /// was altered (or the key was created), 0 otherwise
+    async fn pfadd(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let key = command_arg_at!(command, 1);
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut strings_db =
+            StringsDb::with_storage(client_state.database(), client_state.database_id());
+
+        let (mut hll, md, mut updated) = match Self::load(&mut strings_db, key)? {
+            LoadHyperLogLogResult::Invalid(msg) => {
+                builder.error_string(response_buffer, msg);
+                return Ok(());
+            }
+            LoadHyperLogLogResult::NotFound => {
+                (HyperLogLog::default(), StringValueMetadata::new(), true)
+            }
+            LoadHyperLogLogResult::Some(hll, md) => (hll, md, false),
+        };
+
+        for element in command.args_vec().iter().skip(2) {
+            updated |= hll.add(element);
+        }
+
+        if updated {
+            strings_db.put(key, &hll.to_bytes(), &md, PutFlags::Override)?;
+        }
+        builder.number_usize(response_buffer, updated as usize);
+        Ok(())
+    }
+
+    /// `PFCOUNT key [key ...]`
+    /// When called with a single key, returns the approximated cardinality computed by the
+    /// HyperLogLog data structure stored at the specified variable, which is 0 if the variable
+    /// does not exist. When called with multiple keys, returns the approximated cardinality of
+    /// the union of the HyperLogLogs passed
+    async fn pfcount(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let user_keys: Vec<&BytesMut> = command.args_vec().iter().skip(1).collect();
+
+        let _unused =
+            LockManager::lock_multi(&user_keys, client_state.clone(), command.clone()).await?;
+        let mut strings_db =
+            StringsDb::with_storage(client_state.database(), client_state.database_id());
+
+        let mut union: Option<HyperLogLog> = None;
+        for key in &user_keys {
+            match Self::load(&mut strings_db, key)? {
+                LoadHyperLogLogResult::Invalid(msg) => {
+                    builder.error_string(response_buffer, msg);
+                    return Ok(());
+                }
+                LoadHyperLogLogResult::NotFound => {}
+                LoadHyperLogLogResult::Some(hll, _) => match union.as_mut() {
+                    // Merging invalidates the cached cardinality, so a single key can use it
+                    Some(union) => union.merge(&hll),
+                    None => union = Some(hll),
+                },
+            }
+        }
+
+        let count = union.map(|hll| hll.count()).unwrap_or_default();
+        builder.number_usize(response_buffer, count as usize);
+        Ok(())
+    }
+
+    /// `PFMERGE destkey [sourcekey [sourcekey ...]]`
+    /// Merge multiple HyperLogLog values into a unique value that will approximate the
+    /// cardinality of the union of the observed sets of the source HyperLogLog structures.
+    /// If the destination variable exists, it is treated as one of the source sets
+    async fn pfmerge(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let builder = RespBuilderV2::default();
+        let destination = command_arg_at!(command, 1);
+        let user_keys: Vec<&BytesMut> = command.args_vec().iter().skip(1).collect();
+
+        let _unused =
+            LockManager::lock_multi(&user_keys, client_state.clone(), command.clone()).await?;
+        let mut strings_db =
+            StringsDb::with_storage(client_state.database(), client_state.database_id());
+
+        let mut union = HyperLogLog::default();
+        let mut destination_md = StringValueMetadata::new();
+        // The destination is the first key in the list
+        for (index, key) in user_keys.iter().enumerate() {
+            match Self::load(&mut strings_db, key)? {
+                LoadHyperLogLogResult::Invalid(msg) => {
+                    builder.error_string(response_buffer, msg);
+                    return Ok(());
+                }
+                LoadHyperLogLogResult::NotFound => {}
+                LoadHyperLogLogResult::Some(hll, md) => {
+                    if index == 0 {
+                        destination_md = md;
+                    }
+                    union.merge(&hll);
+                }
+            }
+        }
+
+        strings_db.put(
+            destination,
+            &union.to_bytes(),
+            &destination_md,
+            PutFlags::Override,
+        )?;
+        builder.ok(response_buffer);
+        Ok(())
+    }
+
+    /// Load and decode the HyperLogLog stored at `user_key`
+    fn load(
+        strings_db: &mut StringsDb,
+        user_key: &BytesMut,
+    ) -> Result<LoadHyperLogLogResult, SableError> {
+        match strings_db.get(user_key)? {
+            StringGetResult::WrongType => Ok(LoadHyperLogLogResult::Invalid(Strings::WRONGTYPE)),
+            StringGetResult::None => Ok(LoadHyperLogLogResult::NotFound),
+            StringGetResult::Some((value, md)) => match HyperLogLog::from_bytes(&value) {
+                Ok(hll) => Ok(LoadHyperLogLogResult::Some(hll, md)),
+                Err(HyperLogLogError::NotHyperLogLog) => {
+                    Ok(LoadHyperLogLogResult::Invalid(Strings::HLL_WRONGTYPE))
+                }
+                Err(HyperLogLogError::Corrupted) => {
+                    Ok(LoadHyperLogLogResult::Invalid(Strings::HLL_CORRUPTED))
+                }
+            },
+        }
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::ClientNextAction, Client, ServerState};
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    #[test_case(vec![
+        (vec!["pfadd", "hll", "a", "b", "c", "d", "e", "f", "g"], ":1\r\n"),
+        (vec!["pfadd", "hll", "a", "b"], ":0\r\n"),
+        (vec!["pfcount", "hll"], ":7\r\n"),
+        (vec!["pfadd", "empty"], ":1\r\n"),
+        (vec!["pfadd", "empty"], ":0\r\n"),
+        (vec!["pfcount", "empty"], ":0\r\n"),
+        (vec!["strlen", "empty"], ":18\r\n"),
+        (vec!["pfcount", "nosuchkey"], ":0\r\n"),
+        (vec!["set", "str", "hello"], "+OK\r\n"),
+        (vec!["pfadd", "str", "a"], "-WRONGTYPE Key is not a valid HyperLogLog string value.\r\n"),
+        (vec!["pfcount", "str"], "-WRONGTYPE Key is not a valid HyperLogLog string value.\r\n"),
+        (vec!["set", "corrupted", "HYLL\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01"], "+OK\r\n"),
+        (vec!["pfcount", "corrupted"], "-INVALIDOBJ Corrupted HLL object detected\r\n"),
+        (vec!["lpush", "list", "a"], ":1\r\n"),
+        (vec!["pfadd", "list", "a"], "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"),
+        ], "pfadd_pfcount"; "pfadd_pfcount")]
+    #[test_case(vec![
+        (vec!["pfadd", "hll1", "foo", "bar", "zap", "a"], ":1\r\n"),
+        (vec!["pfadd", "hll2", "a", "b", "c", "foo"], ":1\r\n"),
+        (vec!["pfcount", "hll1", "hll2"], ":6\r\n"),
+        (vec!["pfcount", "hll1", "hll2", "nosuchkey"], ":6\r\n"),
+        (vec!["pfmerge", "hll3", "hll1", "hll2"], "+OK\r\n"),
+        (vec!["pfcount", "hll3"], ":6\r\n"),
+        (vec!["pfmerge", "hll1", "hll2"], "+OK\r\n"),
+        (vec!["pfcount", "hll1"], ":6\r\n"),
+        (vec!["pfmerge", "newkey"], "+OK\r\n"),
+        (vec!["pfcount", "newkey"], ":0\r\n"),
+        (vec!["set", "str", "hello"], "+OK\r\n"),
+        (vec!["pfmerge", "hll3", "str"], "-WRONGTYPE Key is not a valid HyperLogLog string value.\r\n"),
+        (vec!["pfcount", "hll1", "str"], "-WRONGTYPE Key is not a valid HyperLogLog string value.\r\n"),
+        ], "pfmerge"; "pfmerge")]
+    fn test_hyperloglog_commands(
+        args_vec: Vec<(Vec<&'static str>, &'static str)>,
+        test_name: &str,
+    ) -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            for (args, expected_value) in args_vec {
+                let mut sink = crate::tests::ResponseSink::with_name(test_name).await;
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                match Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                    .await
+                    .unwrap()
+                {
+                    ClientNextAction::NoAction => {
+                        assert_eq!(sink.read_all().await.as_str(), expected_value);
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+}
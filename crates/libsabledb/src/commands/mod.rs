@@ -575,6 +575,11 @@ pub enum TimeoutResponse {
     Number(i64),
     Ok,
     Err(String),
+    /// The number of replicas that acknowledged the sequence number (`WAIT`)
+    ReplicasAcked(u64),
+    /// The number of local WAL flushes followed by the number of replicas that acknowledged
+    /// the sequence number (`WAITAOF`)
+    LocalAndReplicasAcked(i64, u64),
 }
 
 use bytes::BytesMut;
@@ -590,6 +595,9 @@ pub enum TryAgainResponse {
     ClientAcquiredLock(BytesMut),
     /// Re-run the command
     RunCommandAgain,
+    /// Run the provided command instead of the original one (e.g. the original command
+    /// with its arguments resolved at the time the client was blocked)
+    RunCommand(Rc<ValkeyCommand>),
 }
 
 /// Possible return value for a "process_command" function
@@ -611,37 +619,55 @@ pub enum ClientNextAction {
     NoAction,
 }
 
+mod acl_commands;
 mod base_commands;
+mod bitmap_commands;
 mod client_commands;
 mod cluster_commands;
 mod command;
 mod commander;
+mod function_commands;
 mod generic_commands;
+mod geo_commands;
 mod hash_commands;
+mod hyperloglog_commands;
 mod list_commands;
 mod lock_commands;
+mod pubsub_commands;
+mod script_commands;
 mod server_commands;
 mod set_commands;
+mod stream_commands;
 mod string_commands;
 mod strings;
 mod transaction_commands;
 mod zset_commands;
 
 pub use crate::commands::strings::Strings;
+pub use acl_commands::AclCommands;
 pub use base_commands::BaseCommands;
+pub use bitmap_commands::BitmapCommands;
 pub use client_commands::ClientCommands;
 pub use cluster_commands::ClusterCommands;
 pub use command::commands_manager;
 pub use command::ValkeyCommand;
-pub use commander::{CommandMetadata, CommandsManager, ValkeyCommandName};
+pub use commander::{CommandMetadata, CommandsManager, ValkeyCommandFlags, ValkeyCommandName};
+pub use function_commands::FunctionCommands;
 pub use generic_commands::GenericCommands;
+pub use geo_commands::GeoCommands;
 pub use hash_commands::HashCommands;
+pub use hyperloglog_commands::HyperLogLogCommands;
 pub use list_commands::ListCommands;
 pub use lock_commands::LockCommands;
+pub use pubsub_commands::PubSubCommands;
+pub(crate) use script_commands::ScopedScriptExecution;
+pub use script_commands::ScriptCommands;
 pub use server_commands::ServerCommands;
 pub use set_commands::SetCommands;
+pub use stream_commands::StreamCommands;
 pub use string_commands::StringCommands;
 pub use transaction_commands::TransactionCommands;
 pub use zset_commands::ZSetCommands;
 
+use std::rc::Rc;
 use tokio::{sync::mpsc::Receiver, time::Duration};
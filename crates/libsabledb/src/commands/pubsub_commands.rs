@@ -0,0 +1,683 @@
+#[allow(unused_imports)]
+use crate::{
+    check_args_count, command_arg_at, command_arg_at_as_str,
+    commands::{HandleCommandResult, Strings},
+    metadata::ShardMessage,
+    server::ClientState,
+    storage::BatchUpdate,
+    utils::RespBuilderV2,
+    SableError, ValkeyCommand, ValkeyCommandName,
+};
+
+use bytes::BytesMut;
+use std::rc::Rc;
+use tokio::io::AsyncWriteExt;
+
+pub struct PubSubCommands {}
+
+impl PubSubCommands {
+    pub async fn handle_command(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        _tx: &mut (impl AsyncWriteExt + std::marker::Unpin),
+    ) -> Result<HandleCommandResult, SableError> {
+        let mut response_buffer = BytesMut::with_capacity(256);
+        match command.metadata().name() {
+            ValkeyCommandName::Subscribe => {
+                Self::subscribe(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Psubscribe => {
+                Self::psubscribe(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Unsubscribe => {
+                Self::unsubscribe(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Punsubscribe => {
+                Self::punsubscribe(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Publish => {
+                Self::publish(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Pubsub => {
+                Self::pubsub(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Ssubscribe => {
+                Self::ssubscribe(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Sunsubscribe => {
+                Self::sunsubscribe(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Spublish => {
+                Self::spublish(client_state, command, &mut response_buffer).await?;
+            }
+            _ => {
+                return Err(SableError::InvalidArgument(format!(
+                    "Non pubsub command {}",
+                    command.main_command()
+                )));
+            }
+        }
+        Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
+    }
+
+    /// Subscribes the client to the specified channels.
+    ///
+    /// Once the client enters the subscribed state it is not supposed to issue any other commands,
+    /// except for additional `SUBSCRIBE`, `PSUBSCRIBE`, `UNSUBSCRIBE`, `PUNSUBSCRIBE` and `PING` commands
+    async fn subscribe(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let server_state = client_state.server_inner_state();
+        for channel in command.args_vec().iter().skip(1) {
+            if client_state.channel_subscribe(channel) {
+                server_state.pubsub().subscribe(
+                    channel,
+                    client_state.id(),
+                    client_state.pubsub_sender(),
+                );
+            }
+            Self::add_subscription_reply(
+                response_buffer,
+                "subscribe",
+                Some(channel),
+                client_state.subscriptions_count(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Subscribes the client to the given patterns. Supported glob-style patterns: `?` and `*`
+    async fn psubscribe(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let server_state = client_state.server_inner_state();
+        for pattern in command.args_vec().iter().skip(1) {
+            if client_state.pattern_subscribe(pattern) {
+                server_state.pubsub().psubscribe(
+                    pattern,
+                    client_state.id(),
+                    client_state.pubsub_sender(),
+                );
+            }
+            Self::add_subscription_reply(
+                response_buffer,
+                "psubscribe",
+                Some(pattern),
+                client_state.subscriptions_count(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Unsubscribes the client from the given channels, or from all of them if none is given
+    async fn unsubscribe(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        let channels: Vec<BytesMut> = if command.arg_count() > 1 {
+            command.args_vec().iter().skip(1).cloned().collect()
+        } else {
+            client_state.subscribed_channels()
+        };
+
+        if channels.is_empty() {
+            Self::add_subscription_reply(
+                response_buffer,
+                "unsubscribe",
+                None,
+                client_state.subscriptions_count(),
+            );
+            return Ok(());
+        }
+
+        let server_state = client_state.server_inner_state();
+        for channel in &channels {
+            if client_state.channel_unsubscribe(channel) {
+                server_state
+                    .pubsub()
+                    .unsubscribe(channel, client_state.id());
+            }
+            Self::add_subscription_reply(
+                response_buffer,
+                "unsubscribe",
+                Some(channel),
+                client_state.subscriptions_count(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Unsubscribes the client from the given patterns, or from all of them if none is given
+    async fn punsubscribe(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        let patterns: Vec<BytesMut> = if command.arg_count() > 1 {
+            command.args_vec().iter().skip(1).cloned().collect()
+        } else {
+            client_state.subscribed_patterns()
+        };
+
+        if patterns.is_empty() {
+            Self::add_subscription_reply(
+                response_buffer,
+                "punsubscribe",
+                None,
+                client_state.subscriptions_count(),
+            );
+            return Ok(());
+        }
+
+        let server_state = client_state.server_inner_state();
+        for pattern in &patterns {
+            if client_state.pattern_unsubscribe(pattern) {
+                server_state
+                    .pubsub()
+                    .punsubscribe(pattern, client_state.id());
+            }
+            Self::add_subscription_reply(
+                response_buffer,
+                "punsubscribe",
+                Some(pattern),
+                client_state.subscriptions_count(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Posts a message to the given channel. Returns the number of clients that received the message.
+    /// Subscribers that can't keep up with the rate of published messages are disconnected
+    async fn publish(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let channel = command_arg_at!(command, 1);
+        let message = command_arg_at!(command, 2);
+
+        let server_state = client_state.server_inner_state();
+        let result = server_state.pubsub().publish(channel, message);
+        for client_id in result.slow_clients {
+            client_state.warn(&format!(
+                "Subscriber {} can't keep up with the published messages. Closing its connection",
+                client_id
+            ));
+            server_state.terminate_client(client_id).await?;
+        }
+
+        let builder = RespBuilderV2::default();
+        builder.number_usize(response_buffer, result.receivers);
+        Ok(())
+    }
+
+    /// Subscribes the client to the specified shard channels. All the channels must hash to the
+    /// same slot, and the slot must be owned by this node
+    async fn ssubscribe(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let channels: Vec<BytesMut> = command.args_vec().iter().skip(1).cloned().collect();
+        if !Self::check_shard_channels_slot(&client_state, &channels, response_buffer)? {
+            return Ok(());
+        }
+
+        let server_state = client_state.server_inner_state();
+        for channel in &channels {
+            if client_state.shard_channel_subscribe(channel) {
+                server_state.pubsub().ssubscribe(
+                    channel,
+                    client_state.id(),
+                    client_state.pubsub_sender(),
+                );
+            }
+            Self::add_subscription_reply(
+                response_buffer,
+                "ssubscribe",
+                Some(channel),
+                client_state.shard_subscriptions_count(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Unsubscribes the client from the given shard channels, or from all of them if none is given
+    async fn sunsubscribe(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        let channels: Vec<BytesMut> = if command.arg_count() > 1 {
+            let channels: Vec<BytesMut> = command.args_vec().iter().skip(1).cloned().collect();
+            if !Self::check_shard_channels_slot(&client_state, &channels, response_buffer)? {
+                return Ok(());
+            }
+            channels
+        } else {
+            client_state.subscribed_shard_channels()
+        };
+
+        if channels.is_empty() {
+            Self::add_subscription_reply(
+                response_buffer,
+                "sunsubscribe",
+                None,
+                client_state.shard_subscriptions_count(),
+            );
+            return Ok(());
+        }
+
+        let server_state = client_state.server_inner_state();
+        for channel in &channels {
+            if client_state.shard_channel_unsubscribe(channel) {
+                server_state
+                    .pubsub()
+                    .sunsubscribe(channel, client_state.id());
+            }
+            Self::add_subscription_reply(
+                response_buffer,
+                "sunsubscribe",
+                Some(channel),
+                client_state.shard_subscriptions_count(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Posts a message to the given shard channel. The channel is hashed into a slot exactly like
+    /// a key, so the command must be sent to the node that owns the slot. The message is also
+    /// written to the WAL (as a put & delete marker record) so it is delivered to the subscribers
+    /// connected to the replicas of this shard
+    async fn spublish(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let channel = command_arg_at!(command, 1);
+        let message = command_arg_at!(command, 2);
+
+        let slot = crate::utils::calculate_slot(channel);
+        if !client_state.server_inner_state().slots().is_set(slot)? {
+            return Err(SableError::NotOwner(vec![slot]));
+        }
+
+        // Place the marker record so the replication picks up the message. This is not free: every
+        // message costs a WAL write of its size plus a tombstone in the memtable, i.e. publishing
+        // at a high rate has the write amplification of the same rate of `SET` + `DEL`. Both the
+        // record and its tombstone are dropped by the compaction, so nothing is left behind on
+        // disk (see `test_spublish_leaves_no_records`)
+        let shard_message = ShardMessage::new(channel.clone(), message.clone());
+        let mut batch = BatchUpdate::with_capacity(2);
+        batch.put(shard_message.key(), message.clone());
+        batch.delete(shard_message.key());
+        client_state.database().apply_batch(&batch)?;
+
+        let server_state = client_state.server_inner_state();
+        let result = server_state.pubsub().spublish(channel, message);
+        for client_id in result.slow_clients {
+            client_state.warn(&format!(
+                "Subscriber {} can't keep up with the published messages. Closing its connection",
+                client_id
+            ));
+            server_state.terminate_client(client_id).await?;
+        }
+
+        let builder = RespBuilderV2::default();
+        builder.number_usize(response_buffer, result.receivers);
+        Ok(())
+    }
+
+    /// Pub/Sub introspection commands:
+    ///
+    /// ```text
+    /// PUBSUB CHANNELS [pattern]
+    /// PUBSUB NUMSUB [channel [channel ...]]
+    /// PUBSUB NUMPAT
+    /// PUBSUB SHARDCHANNELS [pattern]
+    /// PUBSUB SHARDNUMSUB [channel [channel ...]]
+    /// ```
+    async fn pubsub(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let sub_command = command_arg_at_as_str!(command, 1);
+        let server_state = client_state.server_inner_state();
+        let builder = RespBuilderV2::default();
+        match sub_command.as_str() {
+            "channels" => {
+                if command.arg_count() > 3 {
+                    builder.error_string(
+                        response_buffer,
+                        "ERR wrong number of arguments for 'pubsub|channels' command",
+                    );
+                    return Ok(());
+                }
+                let channels = server_state.pubsub().channels(command.arg(2));
+                builder.add_array_len(response_buffer, channels.len());
+                for channel in &channels {
+                    builder.add_bulk_string(response_buffer, channel);
+                }
+            }
+            "numsub" => {
+                let channels: Vec<&BytesMut> = command.args_vec().iter().skip(2).collect();
+                builder.add_array_len(response_buffer, channels.len() * 2);
+                for channel in channels {
+                    builder.add_bulk_string(response_buffer, channel);
+                    builder.add_number::<usize>(
+                        response_buffer,
+                        server_state.pubsub().numsub(channel),
+                        false,
+                    );
+                }
+            }
+            "shardchannels" => {
+                if command.arg_count() > 3 {
+                    builder.error_string(
+                        response_buffer,
+                        "ERR wrong number of arguments for 'pubsub|shardchannels' command",
+                    );
+                    return Ok(());
+                }
+                let channels = server_state.pubsub().shard_channels(command.arg(2));
+                builder.add_array_len(response_buffer, channels.len());
+                for channel in &channels {
+                    builder.add_bulk_string(response_buffer, channel);
+                }
+            }
+            "shardnumsub" => {
+                let channels: Vec<&BytesMut> = command.args_vec().iter().skip(2).collect();
+                builder.add_array_len(response_buffer, channels.len() * 2);
+                for channel in channels {
+                    builder.add_bulk_string(response_buffer, channel);
+                    builder.add_number::<usize>(
+                        response_buffer,
+                        server_state.pubsub().shard_numsub(channel),
+                        false,
+                    );
+                }
+            }
+            "numpat" => {
+                if command.arg_count() != 2 {
+                    builder.error_string(
+                        response_buffer,
+                        "ERR wrong number of arguments for 'pubsub|numpat' command",
+                    );
+                    return Ok(());
+                }
+                builder.number_usize(response_buffer, server_state.pubsub().numpat());
+            }
+            _ => {
+                builder.error_string(
+                    response_buffer,
+                    &format!(
+                        "ERR unknown subcommand '{}'. Try PUBSUB HELP.",
+                        sub_command.as_str()
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Shard channels are routed by their slot. Return `Ok(false)` (with an error written into
+    /// `response_buffer`) if the channels do not hash to the same slot, or `NotOwner` if the
+    /// slot is not owned by this node
+    fn check_shard_channels_slot(
+        client_state: &Rc<ClientState>,
+        channels: &[BytesMut],
+        response_buffer: &mut BytesMut,
+    ) -> Result<bool, SableError> {
+        let Some(first) = channels.first() else {
+            return Ok(true);
+        };
+
+        let slot = crate::utils::calculate_slot(first);
+        if channels
+            .iter()
+            .any(|channel| crate::utils::calculate_slot(channel) != slot)
+        {
+            let builder = RespBuilderV2::default();
+            builder.error_string(
+                response_buffer,
+                "CROSSSLOT Keys in request don't hash to the same slot",
+            );
+            return Ok(false);
+        }
+
+        if !client_state.server_inner_state().slots().is_set(slot)? {
+            return Err(SableError::NotOwner(vec![slot]));
+        }
+        Ok(true)
+    }
+
+    /// Append a (un)subscribe confirmation message into `response_buffer`
+    fn add_subscription_reply(
+        response_buffer: &mut BytesMut,
+        kind: &str,
+        name: Option<&BytesMut>,
+        subscriptions_count: usize,
+    ) {
+        let builder = RespBuilderV2::default();
+        builder.add_array_len(response_buffer, 3);
+        builder.add_bulk_string(response_buffer, kind.as_bytes());
+        match name {
+            Some(name) => builder.add_bulk_string(response_buffer, name),
+            None => builder.add_null_string(response_buffer),
+        }
+        builder.add_number::<usize>(response_buffer, subscriptions_count, false);
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commands::ClientNextAction, Client, KeyType, ServerState, ToU8Writer, U8ArrayBuilder,
+    };
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    #[test_case(vec![
+        (vec!["subscribe"], "-ERR wrong number of arguments for 'subscribe' command\r\n"),
+        (vec!["subscribe", "ch1", "ch2"], "*3\r\n$9\r\nsubscribe\r\n$3\r\nch1\r\n:1\r\n*3\r\n$9\r\nsubscribe\r\n$3\r\nch2\r\n:2\r\n"),
+        (vec!["subscribe", "ch1"], "*3\r\n$9\r\nsubscribe\r\n$3\r\nch1\r\n:2\r\n"),
+        (vec!["get", "key"], "-ERR Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING are allowed in this context\r\n"),
+        (vec!["ping"], "*2\r\n$4\r\npong\r\n$0\r\n\r\n"),
+        (vec!["psubscribe", "ch*"], "*3\r\n$10\r\npsubscribe\r\n$3\r\nch*\r\n:3\r\n"),
+        (vec!["unsubscribe", "ch1"], "*3\r\n$11\r\nunsubscribe\r\n$3\r\nch1\r\n:2\r\n"),
+        (vec!["unsubscribe", "no_such_channel"], "*3\r\n$11\r\nunsubscribe\r\n$15\r\nno_such_channel\r\n:2\r\n"),
+        (vec!["unsubscribe"], "*3\r\n$11\r\nunsubscribe\r\n$3\r\nch2\r\n:1\r\n"),
+        (vec!["unsubscribe"], "*3\r\n$11\r\nunsubscribe\r\n$-1\r\n:1\r\n"),
+        (vec!["punsubscribe"], "*3\r\n$12\r\npunsubscribe\r\n$3\r\nch*\r\n:0\r\n"),
+        (vec!["punsubscribe"], "*3\r\n$12\r\npunsubscribe\r\n$-1\r\n:0\r\n"),
+        (vec!["get", "key"], "$-1\r\n"),
+        (vec!["ping"], "+PONG\r\n"),
+        ], "subscribe"; "subscribe")]
+    #[test_case(vec![
+        (vec!["publish", "ch1"], "-ERR wrong number of arguments for 'publish' command\r\n"),
+        (vec!["publish", "ch1", "hello"], ":0\r\n"),
+        (vec!["pubsub", "channels"], "*0\r\n"),
+        (vec!["pubsub", "numsub", "ch1", "ch2"], "*4\r\n$3\r\nch1\r\n:0\r\n$3\r\nch2\r\n:0\r\n"),
+        (vec!["pubsub", "numpat"], ":0\r\n"),
+        (vec!["pubsub", "nosuchcommand"], "-ERR unknown subcommand 'nosuchcommand'. Try PUBSUB HELP.\r\n"),
+        ], "publish"; "publish")]
+    #[test_case(vec![
+        (vec!["ssubscribe"], "-ERR wrong number of arguments for 'ssubscribe' command\r\n"),
+        (vec!["ssubscribe", "{orders}.eu", "{orders}.us"], "*3\r\n$10\r\nssubscribe\r\n$11\r\n{orders}.eu\r\n:1\r\n*3\r\n$10\r\nssubscribe\r\n$11\r\n{orders}.us\r\n:2\r\n"),
+        (vec!["ssubscribe", "orders.eu", "orders.us"], "-CROSSSLOT Keys in request don't hash to the same slot\r\n"),
+        (vec!["subscribe", "news"], "*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n"),
+        (vec!["get", "key"], "-ERR Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING are allowed in this context\r\n"),
+        (vec!["pubsub", "shardchannels", "*.eu"], "*1\r\n$11\r\n{orders}.eu\r\n"),
+        (vec!["pubsub", "shardnumsub", "{orders}.eu", "news"], "*4\r\n$11\r\n{orders}.eu\r\n:1\r\n$4\r\nnews\r\n:0\r\n"),
+        (vec!["sunsubscribe", "{orders}.eu"], "*3\r\n$12\r\nsunsubscribe\r\n$11\r\n{orders}.eu\r\n:1\r\n"),
+        (vec!["sunsubscribe"], "*3\r\n$12\r\nsunsubscribe\r\n$11\r\n{orders}.us\r\n:0\r\n"),
+        (vec!["sunsubscribe"], "*3\r\n$12\r\nsunsubscribe\r\n$-1\r\n:0\r\n"),
+        (vec!["unsubscribe"], "*3\r\n$11\r\nunsubscribe\r\n$4\r\nnews\r\n:0\r\n"),
+        (vec!["spublish", "{orders}.eu", "hello"], ":0\r\n"),
+        (vec!["pubsub", "shardchannels"], "*0\r\n"),
+        ], "ssubscribe"; "ssubscribe")]
+    fn test_pubsub_commands(
+        args_vec: Vec<(Vec<&'static str>, &'static str)>,
+        test_name: &str,
+    ) -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            for (args, expected_value) in args_vec {
+                let mut sink = crate::tests::ResponseSink::with_name(test_name).await;
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                match Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                    .await
+                    .unwrap()
+                {
+                    ClientNextAction::NoAction => {
+                        assert_eq!(sink.read_all().await.as_str(), expected_value);
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_publish_across_clients() -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let server_state = Arc::<ServerState>::default();
+            let subscriber = Client::new(server_state.clone(), store.clone(), None);
+            let publisher = Client::new(server_state, store, None);
+            let mut subscriber_rx = subscriber.inner().take_pubsub_receiver().unwrap();
+
+            for args in [vec!["subscribe", "news.tech"], vec!["psubscribe", "news.*"]] {
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                crate::tests::execute_command(subscriber.inner(), cmd).await;
+            }
+
+            let cmd = Rc::new(ValkeyCommand::for_test(vec![
+                "publish",
+                "news.tech",
+                "hello",
+            ]));
+            let output = crate::tests::execute_command(publisher.inner(), cmd).await;
+            assert_eq!(output, BytesMut::from(":2\r\n"));
+
+            assert_eq!(
+                subscriber_rx.try_recv().unwrap(),
+                BytesMut::from("*3\r\n$7\r\nmessage\r\n$9\r\nnews.tech\r\n$5\r\nhello\r\n")
+            );
+            assert_eq!(
+                subscriber_rx.try_recv().unwrap(),
+                BytesMut::from(
+                    "*4\r\n$8\r\npmessage\r\n$6\r\nnews.*\r\n$9\r\nnews.tech\r\n$5\r\nhello\r\n"
+                )
+            );
+
+            let cmd = Rc::new(ValkeyCommand::for_test(vec![
+                "pubsub",
+                "numsub",
+                "news.tech",
+            ]));
+            let output = crate::tests::execute_command(publisher.inner(), cmd).await;
+            assert_eq!(output, BytesMut::from("*2\r\n$9\r\nnews.tech\r\n:1\r\n"));
+
+            let cmd = Rc::new(ValkeyCommand::for_test(vec!["pubsub", "numpat"]));
+            let output = crate::tests::execute_command(publisher.inner(), cmd).await;
+            assert_eq!(output, BytesMut::from(":1\r\n"));
+
+            // Dropping the subscriber removes its subscriptions
+            drop(subscriber);
+            let cmd = Rc::new(ValkeyCommand::for_test(vec!["pubsub", "channels"]));
+            let output = crate::tests::execute_command(publisher.inner(), cmd).await;
+            assert_eq!(output, BytesMut::from("*0\r\n"));
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_spublish_across_clients() -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let server_state = Arc::<ServerState>::default();
+            let subscriber = Client::new(server_state.clone(), store.clone(), None);
+            let publisher = Client::new(server_state, store.clone(), None);
+            let mut subscriber_rx = subscriber.inner().take_pubsub_receiver().unwrap();
+
+            let cmd = Rc::new(ValkeyCommand::for_test(vec!["ssubscribe", "orders"]));
+            crate::tests::execute_command(subscriber.inner(), cmd).await;
+
+            // regular publish does not reach shard channel subscribers
+            let cmd = Rc::new(ValkeyCommand::for_test(vec!["publish", "orders", "hello"]));
+            let output = crate::tests::execute_command(publisher.inner(), cmd).await;
+            assert_eq!(output, BytesMut::from(":0\r\n"));
+
+            let cmd = Rc::new(ValkeyCommand::for_test(vec!["spublish", "orders", "hello"]));
+            let output = crate::tests::execute_command(publisher.inner(), cmd).await;
+            assert_eq!(output, BytesMut::from(":1\r\n"));
+            assert_eq!(
+                subscriber_rx.try_recv().unwrap(),
+                BytesMut::from("*3\r\n$8\r\nsmessage\r\n$6\r\norders\r\n$5\r\nhello\r\n")
+            );
+            assert!(subscriber_rx.try_recv().is_err());
+
+            // the replication marker record is not kept in the database
+            let shard_message =
+                ShardMessage::new(BytesMut::from("orders"), BytesMut::from("hello"));
+            assert!(!store.contains(&shard_message.key()).unwrap());
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_spublish_leaves_no_records() -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store.clone(), None);
+            for i in 0..100 {
+                let channel = format!("orders{}", i % 10);
+                let cmd = Rc::new(ValkeyCommand::for_test(vec![
+                    "spublish",
+                    channel.as_str(),
+                    "hello",
+                ]));
+                crate::tests::execute_command(client.inner(), cmd).await;
+            }
+
+            // flush the memtable and compact: the marker records and their tombstones are gone
+            store.flush().unwrap();
+            store.vacuum().unwrap();
+
+            let mut prefix = BytesMut::new();
+            let mut builder = U8ArrayBuilder::with_buffer(&mut prefix);
+            KeyType::ShardMessage.to_writer(&mut builder);
+            let db_iter = store.create_iterator(&prefix).unwrap();
+            assert!(!db_iter.valid() || !db_iter.key().unwrap().starts_with(&prefix));
+        });
+        Ok(())
+    }
+}
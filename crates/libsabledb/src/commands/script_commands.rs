@@ -0,0 +1,363 @@
+#[allow(unused_imports)]
+use crate::{
+    check_args_count, command_arg_at, command_arg_at_as_str,
+    commands::{HandleCommandResult, Strings},
+    server::{ClientState, LuaEngine},
+    utils::RespBuilderV2,
+    BytesMutUtils, LockManager, SableError, ValkeyCommand, ValkeyCommandName,
+};
+
+use bytes::BytesMut;
+use std::rc::Rc;
+use tokio::io::AsyncWriteExt;
+
+/// Puts the client in the "exec" state for the lifetime of the script and restores it
+/// when leaving the scope
+pub(crate) struct ScopedScriptExecution {
+    client_state: Rc<ClientState>,
+}
+
+impl ScopedScriptExecution {
+    pub(crate) fn new(client_state: Rc<ClientState>) -> Self {
+        client_state.set_txn_state_exec(true);
+        ScopedScriptExecution { client_state }
+    }
+}
+
+impl Drop for ScopedScriptExecution {
+    fn drop(&mut self) {
+        self.client_state.set_txn_state_exec(false);
+    }
+}
+
+pub struct ScriptCommands {}
+
+impl ScriptCommands {
+    pub async fn handle_command(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        _tx: &mut (impl AsyncWriteExt + std::marker::Unpin),
+    ) -> Result<HandleCommandResult, SableError> {
+        let mut response_buffer = BytesMut::with_capacity(256);
+        match command.metadata().name() {
+            ValkeyCommandName::Script => {
+                Self::script(client_state, command, &mut response_buffer).await?;
+            }
+            _ => {
+                return Err(SableError::InvalidArgument(format!(
+                    "Non script command {}",
+                    command.main_command()
+                )));
+            }
+        }
+        Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
+    }
+
+    /// Handle `EVAL`, `EVALSHA` and their read-only variants.
+    ///
+    /// Like `EXEC`, these commands are dispatched directly from `Client::handle_command`: the
+    /// script calls back into `Client::handle_non_exec_command` and Rust async does not allow us
+    /// to recurse into it
+    pub async fn handle_eval(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        _tx: &mut (impl AsyncWriteExt + std::marker::Unpin),
+    ) -> Result<HandleCommandResult, SableError> {
+        let mut response_buffer = BytesMut::with_capacity(256);
+        match command.metadata().name() {
+            ValkeyCommandName::Eval
+            | ValkeyCommandName::Evalsha
+            | ValkeyCommandName::EvalRo
+            | ValkeyCommandName::EvalshaRo => {
+                Self::eval(client_state, command, &mut response_buffer).await?;
+            }
+            _ => {
+                return Err(SableError::InvalidArgument(format!(
+                    "Non script command {}",
+                    command.main_command()
+                )));
+            }
+        }
+        Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
+    }
+
+    /// `EVAL script numkeys [key [key ...]] [arg [arg ...]]`
+    /// `EVALSHA sha1 numkeys [key [key ...]] [arg [arg ...]]`
+    /// `EVAL_RO script numkeys [key [key ...]] [arg [arg ...]]`
+    /// `EVALSHA_RO sha1 numkeys [key [key ...]] [arg [arg ...]]`
+    ///
+    /// The script runs atomically: the declared keys are locked for the duration of the
+    /// script and all the writes performed by the script are committed as a single batch.
+    /// Keys that are accessed by the script but not declared are not locked. The `_RO` variants,
+    /// and all the scripts executed by a replica, can not write
+    async fn eval(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let builder = RespBuilderV2::default();
+        let server_state = client_state.server_inner_state();
+        let scripts = server_state.scripts();
+        let body = if matches!(
+            command.metadata().name(),
+            ValkeyCommandName::Evalsha | ValkeyCommandName::EvalshaRo
+        ) {
+            let sha = BytesMutUtils::to_string(command_arg_at!(command, 1));
+            let Some(body) = scripts.get(&sha) else {
+                builder.error_string(response_buffer, Strings::ERR_NOSCRIPT);
+                return Ok(());
+            };
+            body
+        } else {
+            let body = command_arg_at!(command, 1).clone();
+            scripts.load(&body);
+            body
+        };
+
+        let Some(numkeys) = Self::parse_numkeys(&command, response_buffer)? else {
+            return Ok(());
+        };
+
+        let keys = &command.args_vec()[3..3 + numkeys];
+        let args = &command.args_vec()[3 + numkeys..];
+
+        // Lock the declared keys, same as `EXEC` locks the slots of the queued commands
+        let user_keys: Vec<&BytesMut> = keys.iter().collect();
+        let _unused =
+            LockManager::lock_multi(&user_keys, client_state.clone(), command.clone()).await?;
+
+        // While in the "exec" state, the commands invoked by the script do not lock (we already
+        // own the locks) and their writes are accumulated in the client's transaction
+        let read_only =
+            !command.metadata().is_write_command() || server_state.persistent_state().is_replica();
+        let _exec_guard = ScopedScriptExecution::new(client_state.clone());
+        let reply = LuaEngine::eval(client_state.clone(), &body, keys, args, read_only).await;
+
+        // Scripts are not rolled back: whatever was written before an error is committed
+        client_state.database().commit()?;
+        builder.add_resp_string(response_buffer, &reply?);
+        Ok(())
+    }
+
+    /// Parse the `numkeys` argument of `EVAL` / `FCALL` (the third argument) and validate it
+    /// against the number of arguments. On error, the error is written into `response_buffer`
+    /// and `None` is returned
+    pub(crate) fn parse_numkeys(
+        command: &ValkeyCommand,
+        response_buffer: &mut BytesMut,
+    ) -> Result<Option<usize>, SableError> {
+        let builder = RespBuilderV2::default();
+        let numkeys = command_arg_at!(command, 2);
+        let numkeys = to_number!(numkeys, i64, response_buffer, Ok(None));
+        if numkeys < 0 {
+            builder.error_string(response_buffer, Strings::ERR_NUMKEYS_NEGATIVE);
+            return Ok(None);
+        }
+
+        let numkeys = numkeys as usize;
+        if numkeys > command.arg_count().saturating_sub(3) {
+            builder.error_string(response_buffer, Strings::ERR_NUMKEYS);
+            return Ok(None);
+        }
+        Ok(Some(numkeys))
+    }
+
+    /// `SCRIPT LOAD script`
+    /// `SCRIPT EXISTS sha1 [sha1 ...]`
+    /// `SCRIPT FLUSH [ASYNC | SYNC]`
+    /// `SCRIPT KILL`
+    async fn script(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let sub_command = command_arg_at_as_str!(command, 1);
+        let server_state = client_state.server_inner_state();
+        let scripts = server_state.scripts();
+        let builder = RespBuilderV2::default();
+        match sub_command.as_str() {
+            "load" => {
+                if command.arg_count() != 3 {
+                    builder.error_string(
+                        response_buffer,
+                        "ERR wrong number of arguments for 'script|load' command",
+                    );
+                    return Ok(());
+                }
+                let sha = scripts.load(command_arg_at!(command, 2));
+                builder.bulk_string(response_buffer, sha.as_bytes());
+            }
+            "exists" => {
+                check_args_count!(command, 3, response_buffer);
+                let shas: Vec<&BytesMut> = command.args_vec().iter().skip(2).collect();
+                builder.add_array_len(response_buffer, shas.len());
+                for sha in shas {
+                    let exists = scripts.contains(&BytesMutUtils::to_string(sha));
+                    builder.add_number::<usize>(response_buffer, usize::from(exists), false);
+                }
+            }
+            "flush" => {
+                if command.arg_count() > 3 {
+                    builder.error_string(
+                        response_buffer,
+                        "ERR wrong number of arguments for 'script|flush' command",
+                    );
+                    return Ok(());
+                }
+                if let Some(mode) = command.arg_as_lowercase_string(2) {
+                    if mode != "async" && mode != "sync" {
+                        builder.error_string(
+                            response_buffer,
+                            "ERR SCRIPT FLUSH only support SYNC|ASYNC option",
+                        );
+                        return Ok(());
+                    }
+                }
+                scripts.flush();
+                builder.ok(response_buffer);
+            }
+            "kill" => {
+                if command.arg_count() != 2 {
+                    builder.error_string(
+                        response_buffer,
+                        "ERR wrong number of arguments for 'script|kill' command",
+                    );
+                    return Ok(());
+                }
+                match server_state.running_scripts().kill() {
+                    Ok(()) => builder.ok(response_buffer),
+                    Err(msg) => builder.error_string(response_buffer, msg),
+                }
+            }
+            _ => {
+                builder.error_string(
+                    response_buffer,
+                    &format!(
+                        "ERR unknown subcommand '{}'. Try SCRIPT HELP.",
+                        sub_command.as_str()
+                    ),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::ClientNextAction, Client, ServerState};
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    #[test_case(vec![
+        (vec!["eval", "return 1"], "-ERR wrong number of arguments for 'eval' command\r\n"),
+        (vec!["eval", "return 1", "-1"], "-ERR Number of keys can't be negative\r\n"),
+        (vec!["eval", "return 1", "2", "k1"], "-ERR Number of keys can't be greater than number of args\r\n"),
+        (vec!["eval", "return 1", "0"], ":1\r\n"),
+        (vec!["eval", "return 3.99", "0"], ":3\r\n"),
+        (vec!["eval", "return {1, 'two', false, 3}", "0"], "*4\r\n:1\r\n$3\r\ntwo\r\n$-1\r\n:3\r\n"),
+        (vec!["eval", "return {KEYS[1], ARGV[1], ARGV[2]}", "1", "a", "b", "c"], "*3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n"),
+        (vec!["eval", "return redis.call('set', KEYS[1], ARGV[1])", "1", "k1", "v1"], "+OK\r\n"),
+        (vec!["eval", "return redis.call('get', KEYS[1])", "1", "k1"], "$2\r\nv1\r\n"),
+        (vec!["get", "k1"], "$2\r\nv1\r\n"),
+        (vec!["eval", "return redis.call('get', KEYS[1])", "1", "nosuchkey"], "$-1\r\n"),
+        (vec!["eval", "return redis.call('incr', KEYS[1])", "1", "k1"], "-ERR value is not an integer or out of range\r\n"),
+        (vec!["eval", "return type(redis.pcall('incr', KEYS[1]))", "1", "k1"], "$5\r\ntable\r\n"),
+        (vec!["eval", "return redis.call('eval', 'return 1', '0')", "0"], "-ERR This Redis command is not allowed from script\r\n"),
+        (vec!["eval", "return redis.status_reply('DONE')", "0"], "+DONE\r\n"),
+        (vec!["eval", "return redis.error_reply('MY error')", "0"], "-MY error\r\n"),
+        (vec!["eval", "return nosuchfunc()", "0"], "-ERR Error running script: user_script:1: attempt to call a nil value (global 'nosuchfunc')\r\n"),
+        (vec!["eval_ro", "return redis.call('set', KEYS[1], 'v2')", "1", "k1"], "-ERR Write commands are not allowed from read-only scripts.\r\n"),
+        (vec!["eval_ro", "return redis.call('get', KEYS[1])", "1", "k1"], "$2\r\nv1\r\n"),
+        (vec!["evalsha_ro", "ffffffffffffffffffffffffffffffffffffffff", "0"], "-NOSCRIPT No matching script. Please use EVAL.\r\n"),
+        ], "eval"; "eval")]
+    #[test_case(vec![
+        (vec!["script"], "-ERR wrong number of arguments for 'script' command\r\n"),
+        (vec!["script", "load", "return 'hello'"], "$40\r\n1b936e3fe509bcbc9cd0664897bbe8fd0cac101b\r\n"),
+        (vec!["script", "exists", "1b936e3fe509bcbc9cd0664897bbe8fd0cac101b", "ffffffffffffffffffffffffffffffffffffffff"], "*2\r\n:1\r\n:0\r\n"),
+        (vec!["evalsha", "1B936E3FE509BCBC9CD0664897BBE8FD0CAC101B", "0"], "$5\r\nhello\r\n"),
+        (vec!["script", "flush", "lazy"], "-ERR SCRIPT FLUSH only support SYNC|ASYNC option\r\n"),
+        (vec!["script", "flush"], "+OK\r\n"),
+        (vec!["evalsha", "1b936e3fe509bcbc9cd0664897bbe8fd0cac101b", "0"], "-NOSCRIPT No matching script. Please use EVAL.\r\n"),
+        (vec!["eval", "return 'hello'", "0"], "$5\r\nhello\r\n"),
+        (vec!["evalsha", "1b936e3fe509bcbc9cd0664897bbe8fd0cac101b", "0"], "$5\r\nhello\r\n"),
+        (vec!["script", "kill"], "-NOTBUSY No scripts in execution right now.\r\n"),
+        (vec!["script", "nosuchcommand"], "-ERR unknown subcommand 'nosuchcommand'. Try SCRIPT HELP.\r\n"),
+        ], "script"; "script")]
+    #[test_case(vec![
+        (vec!["multi"], "+OK\r\n"),
+        (vec!["eval", "return 1", "0"], "-ERR command eval can not be used in a MULTI / EXEC block\r\n"),
+        (vec!["discard"], "+OK\r\n"),
+        ], "eval_in_multi"; "eval_in_multi")]
+    fn test_script_commands(
+        args_vec: Vec<(Vec<&'static str>, &'static str)>,
+        test_name: &str,
+    ) -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            for (args, expected_value) in args_vec {
+                let mut sink = crate::tests::ResponseSink::with_name(test_name).await;
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                match Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                    .await
+                    .unwrap()
+                {
+                    ClientNextAction::NoAction => {
+                        assert_eq!(sink.read_all().await.as_str(), expected_value);
+                    }
+                    _ => {}
+                }
+            }
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_script_kill() -> Result<(), SableError> {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let server_state = Arc::<ServerState>::default();
+            server_state
+                .options()
+                .write()
+                .unwrap()
+                .general_settings
+                .lua_time_limit_ms = 10;
+            let client = Client::new(server_state.clone(), store, None);
+
+            // Once the script exceeds its time limit, kill it from another thread
+            let killer_state = server_state.clone();
+            let killer = std::thread::spawn(move || {
+                while !killer_state.running_scripts().is_busy() {
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                }
+                killer_state.running_scripts().kill()
+            });
+
+            let mut sink = crate::tests::ResponseSink::with_name("script_kill").await;
+            let cmd = Rc::new(ValkeyCommand::for_test(vec![
+                "eval",
+                "while true do end",
+                "0",
+            ]));
+            Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                .await
+                .unwrap();
+            assert_eq!(
+                sink.read_all().await.as_str(),
+                "-ERR Script killed by user with SCRIPT KILL...\r\n"
+            );
+            assert_eq!(killer.join().unwrap(), Ok(()));
+            assert!(!server_state.running_scripts().is_busy());
+            assert_eq!(server_state.running_scripts().kill(), Err(Strings::NOTBUSY));
+        });
+        Ok(())
+    }
+}
@@ -2,23 +2,26 @@
 use crate::{
     check_args_count, check_value_type, command_arg_at,
     commands::Strings,
-    commands::{HandleCommandResult, StringCommands},
+    commands::{HandleCommandResult, StringCommands, TimeoutResponse, TryAgainResponse},
+    expect_args_count,
     metadata::{CommonValueMetadata, KeyType},
     parse_string_to_number,
     replication::{ClusterManager, NodeBuilder, NodeTalkClient},
-    server::ClientState,
     server::SlotFileExporter,
-    storage::StringsDb,
+    server::{ClientState, ReplicaAckWait, ReplicationTelemetry, REPLICA_ACK_KEY},
+    storage::{GetChangesLimits, StringsDb},
     utils::SLOT_SIZE,
-    BytesMutUtils, Expiration, LockManager, PrimaryKeyMetadata, RespBuilderV2, SableError, Server,
-    Slot, StorageAdapter, StringUtils, Telemetry, TimeUtils, U8ArrayBuilder, ValkeyCommand,
+    BlockClientResult, BytesMutUtils, Expiration, LockManager, PrimaryKeyMetadata, RespBuilderV2,
+    SableError, Server, Slot, StringUtils, Telemetry, TimeUtils, U8ArrayBuilder, ValkeyCommand,
     ValkeyCommandName,
 };
 use bytes::BytesMut;
 use futures_intrusive::sync::ManualResetEvent;
 use std::rc::Rc;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::Receiver as TokioReceiver;
 
 const POISONED_MUTEX: &str = "Poisoned Mutex";
 
@@ -82,6 +85,12 @@ impl ServerCommands {
             ValkeyCommandName::Slot => {
                 Self::slot(client_state, command, &mut response_buffer).await?;
             }
+            ValkeyCommandName::Wait => {
+                return Self::wait(client_state, command, response_buffer).await;
+            }
+            ValkeyCommandName::WaitAof => {
+                return Self::waitaof(client_state, command, response_buffer).await;
+            }
             _ => {
                 return Err(SableError::InvalidArgument(format!(
                     "Non server command {}",
@@ -206,6 +215,225 @@ impl ServerCommands {
         Ok(())
     }
 
+    /// `WAIT numreplicas timeout`
+    /// Block the client until `numreplicas` replicas acknowledged all the writes this client
+    /// performed so far, or until `timeout` milliseconds passed (`0` means block forever). Return the number of
+    /// replicas that acknowledged the writes
+    async fn wait(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        mut response_buffer: BytesMut,
+    ) -> Result<HandleCommandResult, SableError> {
+        expect_args_count!(
+            command,
+            3,
+            &mut response_buffer,
+            HandleCommandResult::ResponseBufferUpdated(response_buffer)
+        );
+
+        let builder = RespBuilderV2::default();
+        let (Some(numreplicas), Some(timeout_ms)) = (
+            command.arg_as_number::<usize>(1),
+            command.arg_as_number::<u64>(2),
+        ) else {
+            builder.error_string(
+                &mut response_buffer,
+                Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE,
+            );
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        };
+
+        if client_state
+            .server_inner_state()
+            .persistent_state()
+            .is_replica()
+        {
+            builder.error_string(
+                &mut response_buffer,
+                "ERR WAIT cannot be used with replica instances",
+            );
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        }
+
+        let Some((rx, duration, sequence_number)) =
+            Self::block_for_replicas(client_state.clone(), numreplicas, timeout_ms).await?
+        else {
+            // Enough replicas acknowledged the writes (or the client can't be blocked)
+            let wait = client_state.take_replica_ack_wait();
+            let sequence_number = wait.map(|wait| wait.sequence_number).unwrap_or_default();
+            builder.number_usize(
+                &mut response_buffer,
+                ReplicationTelemetry::replicas_acked(sequence_number),
+            );
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        };
+
+        Ok(HandleCommandResult::Blocked((
+            rx,
+            duration,
+            TimeoutResponse::ReplicasAcked(sequence_number),
+            TryAgainResponse::RunCommandAgain,
+        )))
+    }
+
+    /// `WAITAOF numlocal numreplicas timeout`
+    /// Same as `WAIT`, but also reports the local persistence. `SableDB` writes every change
+    /// to the RocksDB WAL, so the local count is `1` unless the WAL is disabled. Return an array
+    /// with the local count and the number of replicas that acknowledged the writes
+    async fn waitaof(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        mut response_buffer: BytesMut,
+    ) -> Result<HandleCommandResult, SableError> {
+        expect_args_count!(
+            command,
+            4,
+            &mut response_buffer,
+            HandleCommandResult::ResponseBufferUpdated(response_buffer)
+        );
+
+        let builder = RespBuilderV2::default();
+        let (Some(numlocal), Some(numreplicas), Some(timeout_ms)) = (
+            command.arg_as_number::<usize>(1),
+            command.arg_as_number::<usize>(2),
+            command.arg_as_number::<u64>(3),
+        ) else {
+            builder.error_string(
+                &mut response_buffer,
+                Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE,
+            );
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        };
+
+        if client_state
+            .server_inner_state()
+            .persistent_state()
+            .is_replica()
+        {
+            builder.error_string(
+                &mut response_buffer,
+                "ERR WAITAOF cannot be used with replica instances",
+            );
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        }
+
+        let wal_disabled = client_state.database().open_params().rocksdb.disable_wal;
+        if numlocal > 0 && wal_disabled {
+            builder.error_string(
+                &mut response_buffer,
+                "ERR WAITAOF cannot be used when numlocal is set but the WAL is disabled",
+            );
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        }
+        let local: i64 = if wal_disabled { 0 } else { 1 };
+
+        let Some((rx, duration, sequence_number)) =
+            Self::block_for_replicas(client_state.clone(), numreplicas, timeout_ms).await?
+        else {
+            let wait = client_state.take_replica_ack_wait();
+            let sequence_number = wait.map(|wait| wait.sequence_number).unwrap_or_default();
+            builder.add_array_len(&mut response_buffer, 2);
+            builder.add_number::<i64>(&mut response_buffer, local, false);
+            builder.add_number::<usize>(
+                &mut response_buffer,
+                ReplicationTelemetry::replicas_acked(sequence_number),
+                false,
+            );
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        };
+
+        Ok(HandleCommandResult::Blocked((
+            rx,
+            duration,
+            TimeoutResponse::LocalAndReplicasAcked(local, sequence_number),
+            TryAgainResponse::RunCommandAgain,
+        )))
+    }
+
+    /// Block the client until `numreplicas` replicas acknowledge its last write. The sequence
+    /// number to acknowledge and the deadline are kept in the client state, so they survive the
+    /// command being re-run after a wakeup. Return `None` if the client should not be blocked
+    /// (the replicas already acknowledged, the timeout expired or a transaction is active)
+    async fn block_for_replicas(
+        client_state: Rc<ClientState>,
+        numreplicas: usize,
+        timeout_ms: u64,
+    ) -> Result<Option<(TokioReceiver<u8>, Duration, u64)>, SableError> {
+        let wait = match client_state.take_replica_ack_wait() {
+            Some(wait) => wait,
+            None => ReplicaAckWait {
+                sequence_number: Self::last_write_sequence_number(&client_state)?,
+                deadline: if timeout_ms == 0 {
+                    None
+                } else {
+                    Some(Instant::now() + Duration::from_millis(timeout_ms))
+                },
+            },
+        };
+
+        // Keep the wait in the client state, the caller takes it when it builds the response
+        client_state.set_replica_ack_wait(wait);
+        if ReplicationTelemetry::replicas_acked(wait.sequence_number) >= numreplicas
+            || client_state.is_txn_state_exec()
+        {
+            return Ok(None);
+        }
+
+        let duration = match wait.deadline {
+            None => Duration::MAX,
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Ok(None);
+                }
+                remaining
+            }
+        };
+
+        let rx = match client_state
+            .server_inner_state()
+            .block_client(
+                client_state.id(),
+                &[BytesMut::from(REPLICA_ACK_KEY)],
+                client_state.clone(),
+            )
+            .await
+        {
+            BlockClientResult::Blocked(rx) => rx,
+            BlockClientResult::TxnActive => return Ok(None),
+        };
+
+        // A replica might have acknowledged before we were registered as blocked
+        if ReplicationTelemetry::replicas_acked(wait.sequence_number) >= numreplicas {
+            client_state
+                .server_inner_state()
+                .remove_blocked_client(&client_state.id())
+                .await;
+            return Ok(None);
+        }
+        Ok(Some((rx, duration, wait.sequence_number)))
+    }
+
+    /// Return the sequence number a replica reports (in its `GetUpdatesSince` request) once it
+    /// applied the last write batch of the client. This is the first sequence number of that
+    /// batch
+    fn last_write_sequence_number(client_state: &ClientState) -> Result<u64, SableError> {
+        let latest_sequence_number = client_state.last_write_sequence_number();
+        if latest_sequence_number == 0 {
+            return Ok(0);
+        }
+        let store = client_state.database();
+        let limits = Rc::new(
+            GetChangesLimits::builder()
+                .with_max_changes_count(1)
+                .build(),
+        );
+        match store.storage_updates_since(latest_sequence_number, limits) {
+            Ok(storage_updates) => Ok(storage_updates.end_seq_number),
+            Err(_) => Ok(latest_sequence_number),
+        }
+    }
+
     async fn flushdb(
         client_state: Rc<ClientState>,
         command: Rc<ValkeyCommand>,
@@ -644,6 +872,15 @@ mod test {
         ("slot COUNT 16383", ":0\r\n"),
         ("slot COUNT 16384", "-ERR value is not an integer or out of range\r\n"),
     ]; "test_slot")]
+    #[test_case(vec![
+        ("wait 0 0", ":0\r\n"),
+        ("set key1 v", "+OK\r\n"),
+        ("wait 0 100", ":0\r\n"),
+        ("wait abc 100", "-ERR value is not an integer or out of range\r\n"),
+        ("wait 1", "-ERR wrong number of arguments for 'wait' command\r\n"),
+        ("waitaof 0 0 0", "*2\r\n:0\r\n:0\r\n"),
+        ("waitaof 1 0 0", "-ERR WAITAOF cannot be used when numlocal is set but the WAL is disabled\r\n"),
+    ]; "test_wait")]
     fn test_server_commands(args: Vec<(&'static str, &'static str)>) -> Result<(), SableError> {
         let rt = tokio::runtime::Runtime::new().unwrap();
         rt.block_on(async move {
@@ -670,4 +907,56 @@ mod test {
         });
         Ok(())
     }
+
+    #[test]
+    fn test_wait_uses_client_last_write() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let server_state = Arc::<ServerState>::default();
+            let writer = Client::new(server_state.clone(), store.clone(), None);
+            let reader = Client::new(server_state, store, None);
+
+            for (client, args) in [
+                (&writer, vec!["set", "key", "value"]),
+                (&reader, vec!["get", "key"]),
+                (&reader, vec!["multi"]),
+                (&reader, vec!["set", "key", "value"]),
+            ] {
+                let mut sink = crate::io::FileResponseSink::new().await.unwrap();
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                    .await
+                    .unwrap();
+            }
+
+            // Only the writes of the client itself count, a queued command did not write yet
+            assert!(writer.inner().last_write_sequence_number() > 0);
+            assert_eq!(reader.inner().last_write_sequence_number(), 0);
+        });
+    }
+
+    #[test]
+    fn test_wait_blocks_until_timeout() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            // No replica is connected, so the client is blocked until the timeout expires
+            let cmd = Rc::new(ValkeyCommand::for_test(["wait", "1", "100"].to_vec()));
+            let (_rx, duration, timeout_response) =
+                crate::tests::deferred_command(client.inner(), cmd).await;
+            assert!(duration <= std::time::Duration::from_millis(100));
+            assert!(matches!(
+                timeout_response,
+                crate::commands::TimeoutResponse::ReplicasAcked(_)
+            ));
+
+            // And the response built on timeout reports the replicas that acknowledged
+            let cmd = Rc::new(ValkeyCommand::for_test(["wait", "1", "100"].to_vec()));
+            let response = Client::handle_timeout(client.inner(), cmd, timeout_response).unwrap();
+            assert_eq!(BytesMutUtils::to_string(&response).as_str(), ":0\r\n");
+        });
+    }
 }
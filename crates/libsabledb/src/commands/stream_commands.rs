@@ -0,0 +1,1624 @@
+use crate::{
+    check_args_count, command_arg_at,
+    commands::{HandleCommandResult, Strings, TimeoutResponse, TryAgainResponse},
+    metadata::StreamId,
+    server::ClientState,
+    storage::{
+        StreamAddResult, StreamClaimOptions, StreamCountResult, StreamDb, StreamEntry,
+        StreamGroupResult, StreamIdSpec, StreamLastIdResult, StreamRangeResult, StreamTrim,
+        StreamTrimStrategy,
+    },
+    utils::{current_time, CurrentTimeResolution, RespBuilderV2},
+    BlockClientResult, BytesMutUtils, LockManager, SableError, ValkeyCommand, ValkeyCommandName,
+};
+
+use bytes::BytesMut;
+use std::rc::Rc;
+use tokio::io::AsyncWriteExt;
+use tokio::time::Duration;
+
+/// When trimming with `~` and no `LIMIT` is given, evict at most this number of entries
+const STREAM_DEFAULT_TRIM_LIMIT: u64 = 10_000;
+
+/// The default `COUNT` of `XAUTOCLAIM`
+const STREAM_AUTOCLAIM_DEFAULT_COUNT: usize = 100;
+
+pub struct StreamCommands {}
+
+impl StreamCommands {
+    pub async fn handle_command(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        _tx: &mut (impl AsyncWriteExt + std::marker::Unpin),
+    ) -> Result<HandleCommandResult, SableError> {
+        let mut response_buffer = BytesMut::with_capacity(256);
+        match command.metadata().name() {
+            ValkeyCommandName::Xadd => {
+                Self::xadd(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Xrange => {
+                Self::xrange(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Xread => {
+                return Self::xread(client_state, command, response_buffer).await;
+            }
+            ValkeyCommandName::Xtrim => {
+                Self::xtrim(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Xdel => {
+                Self::xdel(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Xlen => {
+                Self::xlen(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Xgroup => {
+                Self::xgroup(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Xreadgroup => {
+                return Self::xreadgroup(client_state, command, response_buffer).await;
+            }
+            ValkeyCommandName::Xack => {
+                Self::xack(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Xpending => {
+                Self::xpending(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Xclaim => {
+                Self::xclaim(client_state, command, &mut response_buffer).await?;
+            }
+            ValkeyCommandName::Xautoclaim => {
+                Self::xautoclaim(client_state, command, &mut response_buffer).await?;
+            }
+            _ => {
+                return Err(SableError::InvalidArgument(format!(
+                    "Stream command '{}' is not supported",
+                    command.main_command()
+                )));
+            }
+        }
+        Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer))
+    }
+
+    /// Appends the specified stream entry to the stream at the specified key.
+    /// If the key does not exist, as a side effect of running this command the key is created
+    /// with a stream value
+    /// `XADD key [NOMKSTREAM] [<MAXLEN | MINID> [= | ~] threshold [LIMIT count]] <* | id> field value [field value ...]`
+    async fn xadd(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 5, response_buffer);
+        let key = command_arg_at!(command, 1);
+
+        let builder = RespBuilderV2::default();
+        let mut nomkstream = false;
+        let mut trim: Option<StreamTrim> = None;
+        let mut pos = 2usize;
+        while let Some(arg) = command.arg_as_lowercase_string(pos) {
+            match arg.as_str() {
+                "nomkstream" => {
+                    nomkstream = true;
+                    pos = pos.saturating_add(1);
+                }
+                "maxlen" | "minid" => match Self::parse_trim(&command, pos) {
+                    Ok((stream_trim, next_pos)) => {
+                        trim = Some(stream_trim);
+                        pos = next_pos;
+                    }
+                    Err(errmsg) => {
+                        builder.error_string(response_buffer, errmsg);
+                        return Ok(());
+                    }
+                },
+                _ => break,
+            }
+        }
+
+        // What's left: the ID followed by field-value pairs
+        let remaining = command.arg_count().saturating_sub(pos);
+        if remaining < 3 || remaining.saturating_sub(1).rem_euclid(2) != 0 {
+            builder_return_wrong_args_count!(builder, response_buffer, command.main_command());
+        }
+
+        let Some(id_spec) = Self::parse_id_spec(command_arg_at!(command, pos)) else {
+            builder.error_string(response_buffer, Strings::ERR_INVALID_STREAM_ID);
+            return Ok(());
+        };
+
+        let fields: Vec<(BytesMut, BytesMut)> = command.args_vec()[pos.saturating_add(1)..]
+            .chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect();
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut stream_db =
+            StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        match stream_db.add(key, id_spec, fields, nomkstream, trim)? {
+            StreamAddResult::WrongType => {
+                builder_return_wrong_type!(builder, response_buffer);
+            }
+            StreamAddResult::NotFound => {
+                builder_return_null_string!(builder, response_buffer);
+            }
+            StreamAddResult::IdTooSmall => {
+                builder.error_string(response_buffer, Strings::ERR_XADD_ID_TOO_SMALL);
+            }
+            StreamAddResult::IdIsZero => {
+                builder.error_string(response_buffer, Strings::ERR_XADD_ID_ZERO);
+            }
+            StreamAddResult::IdsExhausted => {
+                builder.error_string(response_buffer, Strings::ERR_STREAM_IDS_EXHAUSTED);
+            }
+            StreamAddResult::Some(entry_id) => {
+                stream_db.commit()?;
+                builder.bulk_string(response_buffer, &entry_id.to_bytes());
+
+                // Wakeup all clients blocked on this stream
+                client_state
+                    .server_inner_state()
+                    .wakeup_clients(key, usize::MAX)
+                    .await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the stream entries matching a given range of IDs
+    /// `XRANGE key start end [COUNT count]`
+    async fn xrange(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 4, response_buffer);
+        let key = command_arg_at!(command, 1);
+        let start = command_arg_at!(command, 2);
+        let end = command_arg_at!(command, 3);
+
+        let builder = RespBuilderV2::default();
+        let count = match command.arg_count() {
+            4 => None,
+            6 => {
+                let keyword = command_arg_at_as_str!(command, 4);
+                if keyword != "count" {
+                    builder_return_syntax_error!(builder, response_buffer);
+                }
+                let count = to_number!(command_arg_at!(command, 5), i64, response_buffer, Ok(()));
+                Some(count.max(0) as usize)
+            }
+            _ => {
+                builder_return_syntax_error!(builder, response_buffer);
+            }
+        };
+
+        let Some(start) = Self::parse_range_id(start, true) else {
+            builder.error_string(response_buffer, Strings::ERR_INVALID_STREAM_ID);
+            return Ok(());
+        };
+
+        let Some(end) = Self::parse_range_id(end, false) else {
+            builder.error_string(response_buffer, Strings::ERR_INVALID_STREAM_ID);
+            return Ok(());
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let stream_db = StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        let (Some(start), Some(end)) = (start, end) else {
+            // an exclusive range that can not contain any ID
+            builder_return_empty_array!(builder, response_buffer);
+        };
+
+        match stream_db.range(key, start, end, count)? {
+            StreamRangeResult::WrongType => {
+                builder_return_wrong_type!(builder, response_buffer);
+            }
+            StreamRangeResult::Some(entries) => {
+                Self::add_entries(&builder, response_buffer, &entries);
+            }
+        }
+        Ok(())
+    }
+
+    /// Read data from one or multiple streams, only returning entries with an ID greater than the
+    /// last received ID reported by the caller
+    /// `XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]`
+    async fn xread(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        mut response_buffer: BytesMut,
+    ) -> Result<HandleCommandResult, SableError> {
+        expect_args_count!(
+            command,
+            4,
+            &mut response_buffer,
+            HandleCommandResult::ResponseBufferUpdated(response_buffer)
+        );
+
+        let builder = RespBuilderV2::default();
+        let mut count: Option<usize> = None;
+        let mut block: Option<u64> = None;
+        let mut pos = 1usize;
+        loop {
+            let Some(arg) = command.arg_as_lowercase_string(pos) else {
+                builder.error_string(&mut response_buffer, Strings::SYNTAX_ERROR);
+                return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+            };
+            let value = command.arg(pos.saturating_add(1));
+            match (arg.as_str(), value) {
+                ("streams", _) => {
+                    pos = pos.saturating_add(1);
+                    break;
+                }
+                ("count", Some(value)) => {
+                    let Some(value) = BytesMutUtils::parse::<i64>(value) else {
+                        builder.error_string(
+                            &mut response_buffer,
+                            Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE,
+                        );
+                        return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                    };
+                    // Non positive count means "no limit"
+                    count = if value > 0 {
+                        Some(value as usize)
+                    } else {
+                        None
+                    };
+                }
+                ("block", Some(value)) => {
+                    let Some(value) = BytesMutUtils::parse::<i64>(value) else {
+                        builder.error_string(
+                            &mut response_buffer,
+                            Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE,
+                        );
+                        return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                    };
+                    if value < 0 {
+                        builder.error_string(&mut response_buffer, "ERR timeout is negative");
+                        return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                    }
+                    block = Some(value as u64);
+                }
+                (_, _) => {
+                    builder.error_string(&mut response_buffer, Strings::SYNTAX_ERROR);
+                    return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                }
+            }
+            pos = pos.saturating_add(2);
+        }
+
+        // The remaining arguments are: the keys followed by the same number of IDs
+        let remaining = &command.args_vec()[pos..];
+        if remaining.is_empty() || remaining.len().rem_euclid(2) != 0 {
+            builder.error_string(&mut response_buffer, Strings::ERR_XREAD_UNBALANCED);
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        }
+        let (keys, ids) = remaining.split_at(remaining.len() / 2);
+        let keys: Vec<&BytesMut> = keys.iter().collect();
+
+        let _unused = LockManager::lock_multi(&keys, client_state.clone(), command.clone()).await?;
+        let stream_db = StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        // Resolve the IDs. `$` is replaced with the last ID of the stream
+        let mut last_ids = Vec::<StreamId>::with_capacity(ids.len());
+        let mut has_dollar = false;
+        for (key, id) in keys.iter().zip(ids) {
+            let last_id = if id.eq(&BytesMut::from("$")) {
+                has_dollar = true;
+                match stream_db.last_id(key)? {
+                    StreamLastIdResult::WrongType => {
+                        builder.error_string(&mut response_buffer, Strings::WRONGTYPE);
+                        return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                    }
+                    StreamLastIdResult::NotFound => StreamId::MIN,
+                    StreamLastIdResult::Some(last_id) => last_id,
+                }
+            } else {
+                let Some(last_id) = StreamId::parse(id, 0) else {
+                    builder.error_string(&mut response_buffer, Strings::ERR_INVALID_STREAM_ID);
+                    return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                };
+                last_id
+            };
+            last_ids.push(last_id);
+        }
+
+        // Collect the new entries from all the streams
+        let mut results = Vec::<(&BytesMut, Vec<StreamEntry>)>::new();
+        for (key, last_id) in keys.iter().zip(&last_ids) {
+            let Some(start) = last_id.next() else {
+                continue;
+            };
+            match stream_db.range(key, start, StreamId::MAX, count)? {
+                StreamRangeResult::WrongType => {
+                    builder.error_string(&mut response_buffer, Strings::WRONGTYPE);
+                    return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                }
+                StreamRangeResult::Some(entries) => {
+                    if !entries.is_empty() {
+                        results.push((*key, entries));
+                    }
+                }
+            }
+        }
+
+        if !results.is_empty() {
+            builder.add_array_len(&mut response_buffer, results.len());
+            for (key, entries) in &results {
+                builder.add_array_len(&mut response_buffer, 2);
+                builder.add_bulk_string(&mut response_buffer, key);
+                Self::add_entries(&builder, &mut response_buffer, entries);
+            }
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        }
+
+        let Some(block) = block else {
+            builder.null_array(&mut response_buffer);
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        };
+
+        // No data is available, block the client
+        let interesting_keys: Vec<BytesMut> = keys.iter().map(|key| (*key).clone()).collect();
+        let rx = block_client_for_keys_return_null_array!(
+            client_state,
+            interesting_keys,
+            response_buffer
+        );
+
+        // When woken up, we must only return entries added after this call. So replace any `$`
+        // with the ID we resolved now
+        let try_again_response = if has_dollar {
+            let mut resolved_command = (*command).clone();
+            for (idx, last_id) in last_ids.iter().enumerate() {
+                resolved_command.set_arg_at(
+                    pos.saturating_add(keys.len()).saturating_add(idx),
+                    &last_id.to_bytes(),
+                );
+            }
+            TryAgainResponse::RunCommand(Rc::new(resolved_command))
+        } else {
+            TryAgainResponse::RunCommandAgain
+        };
+
+        let duration = if block == 0 {
+            Duration::MAX
+        } else {
+            Duration::from_millis(block)
+        };
+
+        Ok(HandleCommandResult::Blocked((
+            rx,
+            duration,
+            TimeoutResponse::NullArrray,
+            try_again_response,
+        )))
+    }
+
+    /// Trims the stream by evicting older entries
+    /// `XTRIM key <MAXLEN | MINID> [= | ~] threshold [LIMIT count]`
+    async fn xtrim(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 4, response_buffer);
+        let key = command_arg_at!(command, 1);
+
+        let builder = RespBuilderV2::default();
+        let trim = match Self::parse_trim(&command, 2) {
+            Ok((trim, next_pos)) if next_pos == command.arg_count() => trim,
+            Ok(_) => {
+                builder_return_syntax_error!(builder, response_buffer);
+            }
+            Err(errmsg) => {
+                builder.error_string(response_buffer, errmsg);
+                return Ok(());
+            }
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut stream_db =
+            StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        match stream_db.trim(key, &trim)? {
+            StreamCountResult::WrongType => {
+                builder_return_wrong_type!(builder, response_buffer);
+            }
+            StreamCountResult::Some(count) => {
+                stream_db.commit()?;
+                builder.number_usize(response_buffer, count);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the specified entries from a stream, and returns the number of entries deleted
+    /// `XDEL key id [id ...]`
+    async fn xdel(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let key = command_arg_at!(command, 1);
+
+        let builder = RespBuilderV2::default();
+        let Some(ids) = Self::parse_ids(&command.args_vec()[2..]) else {
+            builder.error_string(response_buffer, Strings::ERR_INVALID_STREAM_ID);
+            return Ok(());
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut stream_db =
+            StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        match stream_db.delete(key, &ids)? {
+            StreamCountResult::WrongType => {
+                builder_return_wrong_type!(builder, response_buffer);
+            }
+            StreamCountResult::Some(count) => {
+                stream_db.commit()?;
+                builder.number_usize(response_buffer, count);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of entries inside a stream
+    /// `XLEN key`
+    async fn xlen(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let key = command_arg_at!(command, 1);
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let stream_db = StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        let builder = RespBuilderV2::default();
+        match stream_db.len(key)? {
+            StreamCountResult::WrongType => {
+                builder_return_wrong_type!(builder, response_buffer);
+            }
+            StreamCountResult::Some(len) => builder.number_usize(response_buffer, len),
+        }
+        Ok(())
+    }
+
+    /// Create and manage the consumer groups of a stream
+    /// `XGROUP CREATE key group <id | $> [MKSTREAM] [ENTRIESREAD entries-read]`
+    /// `XGROUP CREATECONSUMER key group consumer`
+    /// `XGROUP DELCONSUMER key group consumer`
+    /// `XGROUP DESTROY key group`
+    /// `XGROUP SETID key group <id | $> [ENTRIESREAD entries-read]`
+    async fn xgroup(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 2, response_buffer);
+        let sub_command = command_arg_at_as_str!(command, 1);
+
+        let builder = RespBuilderV2::default();
+        let args_count = command.arg_count();
+        let valid_args_count = match sub_command.as_str() {
+            "create" => (5..=8).contains(&args_count),
+            "setid" => (5..=7).contains(&args_count),
+            "createconsumer" | "delconsumer" => args_count == 5,
+            "destroy" => args_count == 4,
+            _ => {
+                builder.error_string(
+                    response_buffer,
+                    &format!(
+                        "ERR unknown subcommand '{}'. Try XGROUP HELP.",
+                        sub_command.as_str()
+                    ),
+                );
+                return Ok(());
+            }
+        };
+
+        if !valid_args_count {
+            builder_return_wrong_args_count!(
+                builder,
+                response_buffer,
+                format!("xgroup|{}", sub_command)
+            );
+        }
+
+        let key = command_arg_at!(command, 2);
+        let group = command_arg_at!(command, 3);
+
+        // `CREATE` and `SETID` arguments. A `None` ID stands for `$`
+        let mut last_id: Option<StreamId> = None;
+        let mut mkstream = false;
+        let mut entries_read: Option<u64> = None;
+        if sub_command == "create" || sub_command == "setid" {
+            let id = command_arg_at!(command, 4);
+            if id.as_ref() != b"$" {
+                let Some(id) = StreamId::parse(id, 0) else {
+                    builder.error_string(response_buffer, Strings::ERR_INVALID_STREAM_ID);
+                    return Ok(());
+                };
+                last_id = Some(id);
+            }
+
+            let mut pos = 5usize;
+            while let Some(arg) = command.arg_as_lowercase_string(pos) {
+                let value = command.arg(pos.saturating_add(1));
+                match (arg.as_str(), value) {
+                    ("mkstream", _) if sub_command == "create" => {
+                        mkstream = true;
+                        pos = pos.saturating_add(1);
+                    }
+                    ("entriesread", Some(value)) => {
+                        let Some(value) = BytesMutUtils::parse::<u64>(value) else {
+                            builder.error_string(
+                                response_buffer,
+                                Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE,
+                            );
+                            return Ok(());
+                        };
+                        entries_read = Some(value);
+                        pos = pos.saturating_add(2);
+                    }
+                    (_, _) => {
+                        builder_return_syntax_error!(builder, response_buffer);
+                    }
+                }
+            }
+        }
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut stream_db =
+            StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        let no_group_error = format!(
+            "NOGROUP No such consumer group '{}' for key name '{}'",
+            BytesMutUtils::to_string(group),
+            BytesMutUtils::to_string(key)
+        );
+
+        match sub_command.as_str() {
+            "create" => {
+                match stream_db.create_group(key, group, last_id, mkstream, entries_read)? {
+                    StreamGroupResult::WrongType => {
+                        builder_return_wrong_type!(builder, response_buffer);
+                    }
+                    StreamGroupResult::NoStream | StreamGroupResult::NoGroup => {
+                        builder.error_string(response_buffer, Strings::ERR_XGROUP_KEY_MUST_EXIST);
+                    }
+                    StreamGroupResult::Some(false) => {
+                        builder.error_string(response_buffer, Strings::ERR_BUSYGROUP);
+                    }
+                    StreamGroupResult::Some(true) => {
+                        stream_db.commit()?;
+                        builder.ok(response_buffer);
+                    }
+                }
+            }
+            "setid" => match stream_db.set_group_id(key, group, last_id, entries_read)? {
+                StreamGroupResult::WrongType => {
+                    builder_return_wrong_type!(builder, response_buffer);
+                }
+                StreamGroupResult::NoStream => {
+                    builder.error_string(response_buffer, Strings::ERR_XGROUP_KEY_MUST_EXIST);
+                }
+                StreamGroupResult::NoGroup => {
+                    builder.error_string(response_buffer, &no_group_error);
+                }
+                StreamGroupResult::Some(()) => {
+                    stream_db.commit()?;
+                    builder.ok(response_buffer);
+                }
+            },
+            "createconsumer" | "delconsumer" => {
+                let consumer = command_arg_at!(command, 4);
+                let result = if sub_command == "createconsumer" {
+                    match stream_db.create_consumer(key, group, consumer)? {
+                        StreamGroupResult::WrongType => StreamGroupResult::WrongType,
+                        StreamGroupResult::NoStream => StreamGroupResult::NoStream,
+                        StreamGroupResult::NoGroup => StreamGroupResult::NoGroup,
+                        StreamGroupResult::Some(created) => {
+                            StreamGroupResult::Some(usize::from(created))
+                        }
+                    }
+                } else {
+                    stream_db.delete_consumer(key, group, consumer)?
+                };
+
+                match result {
+                    StreamGroupResult::WrongType => {
+                        builder_return_wrong_type!(builder, response_buffer);
+                    }
+                    StreamGroupResult::NoStream => {
+                        builder.error_string(response_buffer, Strings::ERR_XGROUP_KEY_MUST_EXIST);
+                    }
+                    StreamGroupResult::NoGroup => {
+                        builder.error_string(response_buffer, &no_group_error);
+                    }
+                    StreamGroupResult::Some(count) => {
+                        stream_db.commit()?;
+                        builder.number_usize(response_buffer, count);
+                    }
+                }
+            }
+            _ => match stream_db.destroy_group(key, group)? {
+                StreamGroupResult::WrongType => {
+                    builder_return_wrong_type!(builder, response_buffer);
+                }
+                StreamGroupResult::NoStream | StreamGroupResult::NoGroup => {
+                    builder.error_string(response_buffer, Strings::ERR_XGROUP_KEY_MUST_EXIST);
+                }
+                StreamGroupResult::Some(destroyed) => {
+                    stream_db.commit()?;
+                    builder.number_usize(response_buffer, usize::from(destroyed));
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Read data from one or multiple streams on behalf of a consumer of a consumer group
+    /// `XREADGROUP GROUP group consumer [COUNT count] [BLOCK milliseconds] [NOACK] STREAMS key [key ...] id [id ...]`
+    async fn xreadgroup(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        mut response_buffer: BytesMut,
+    ) -> Result<HandleCommandResult, SableError> {
+        expect_args_count!(
+            command,
+            7,
+            &mut response_buffer,
+            HandleCommandResult::ResponseBufferUpdated(response_buffer)
+        );
+
+        let builder = RespBuilderV2::default();
+        if !command
+            .arg_as_lowercase_string(1)
+            .is_some_and(|arg| arg == "group")
+        {
+            builder.error_string(&mut response_buffer, Strings::SYNTAX_ERROR);
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        }
+        let group = command_arg_at!(command, 2);
+        let consumer = command_arg_at!(command, 3);
+
+        let mut count: Option<usize> = None;
+        let mut block: Option<u64> = None;
+        let mut noack = false;
+        let mut pos = 4usize;
+        loop {
+            let Some(arg) = command.arg_as_lowercase_string(pos) else {
+                builder.error_string(&mut response_buffer, Strings::SYNTAX_ERROR);
+                return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+            };
+            let value = command.arg(pos.saturating_add(1));
+            match (arg.as_str(), value) {
+                ("streams", _) => {
+                    pos = pos.saturating_add(1);
+                    break;
+                }
+                ("noack", _) => {
+                    noack = true;
+                    pos = pos.saturating_add(1);
+                    continue;
+                }
+                ("count", Some(value)) => {
+                    let Some(value) = BytesMutUtils::parse::<i64>(value) else {
+                        builder.error_string(
+                            &mut response_buffer,
+                            Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE,
+                        );
+                        return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                    };
+                    // Non positive count means "no limit"
+                    count = if value > 0 {
+                        Some(value as usize)
+                    } else {
+                        None
+                    };
+                }
+                ("block", Some(value)) => {
+                    let Some(value) = BytesMutUtils::parse::<i64>(value) else {
+                        builder.error_string(
+                            &mut response_buffer,
+                            Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE,
+                        );
+                        return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                    };
+                    if value < 0 {
+                        builder.error_string(&mut response_buffer, "ERR timeout is negative");
+                        return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                    }
+                    block = Some(value as u64);
+                }
+                (_, _) => {
+                    builder.error_string(&mut response_buffer, Strings::SYNTAX_ERROR);
+                    return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                }
+            }
+            pos = pos.saturating_add(2);
+        }
+
+        // The remaining arguments are: the keys followed by the same number of IDs
+        let remaining = &command.args_vec()[pos..];
+        if remaining.is_empty() || remaining.len().rem_euclid(2) != 0 {
+            builder.error_string(&mut response_buffer, Strings::ERR_XREADGROUP_UNBALANCED);
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        }
+        let (keys, ids) = remaining.split_at(remaining.len() / 2);
+        let keys: Vec<&BytesMut> = keys.iter().collect();
+
+        // `>` (represented by `None`) means: entries that were never delivered to the group
+        let mut start_ids = Vec::<Option<StreamId>>::with_capacity(ids.len());
+        for id in ids {
+            if id.as_ref() == b">" {
+                start_ids.push(None);
+            } else {
+                let Some(id) = StreamId::parse(id, 0) else {
+                    builder.error_string(&mut response_buffer, Strings::ERR_INVALID_STREAM_ID);
+                    return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                };
+                start_ids.push(Some(id));
+            }
+        }
+
+        let _unused = LockManager::lock_multi(&keys, client_state.clone(), command.clone()).await?;
+        let mut stream_db =
+            StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        type GroupEntries = Vec<(StreamId, Option<StreamEntry>)>;
+        let mut results = Vec::<(&BytesMut, GroupEntries)>::new();
+        for (key, start_id) in keys.iter().zip(&start_ids) {
+            match stream_db.read_group(key, group, consumer, *start_id, count, noack)? {
+                StreamGroupResult::WrongType => {
+                    builder.error_string(&mut response_buffer, Strings::WRONGTYPE);
+                    return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                }
+                StreamGroupResult::NoStream | StreamGroupResult::NoGroup => {
+                    builder.error_string(
+                        &mut response_buffer,
+                        &format!(
+                            "{} in XREADGROUP with GROUP option",
+                            Self::no_group_error(key, group)
+                        ),
+                    );
+                    return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+                }
+                StreamGroupResult::Some(entries) => {
+                    // History reads are always reported, even when empty
+                    if start_id.is_some() || !entries.is_empty() {
+                        results.push((*key, entries));
+                    }
+                }
+            }
+        }
+        stream_db.commit()?;
+
+        if !results.is_empty() {
+            builder.add_array_len(&mut response_buffer, results.len());
+            for (key, entries) in &results {
+                builder.add_array_len(&mut response_buffer, 2);
+                builder.add_bulk_string(&mut response_buffer, key);
+                builder.add_array_len(&mut response_buffer, entries.len());
+                for (id, entry) in entries {
+                    match entry {
+                        Some(entry) => Self::add_entry(&builder, &mut response_buffer, entry),
+                        None => {
+                            // The entry was deleted from the stream, but it is still pending
+                            builder.add_array_len(&mut response_buffer, 2);
+                            builder.add_bulk_string(&mut response_buffer, &id.to_bytes());
+                            builder.add_null_array(&mut response_buffer);
+                        }
+                    }
+                }
+            }
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        }
+
+        let Some(block) = block else {
+            builder.null_array(&mut response_buffer);
+            return Ok(HandleCommandResult::ResponseBufferUpdated(response_buffer));
+        };
+
+        // No new entries are available, block the client
+        let interesting_keys: Vec<BytesMut> = keys.iter().map(|key| (*key).clone()).collect();
+        let rx = block_client_for_keys_return_null_array!(
+            client_state,
+            interesting_keys,
+            response_buffer
+        );
+
+        let duration = if block == 0 {
+            Duration::MAX
+        } else {
+            Duration::from_millis(block)
+        };
+
+        Ok(HandleCommandResult::Blocked((
+            rx,
+            duration,
+            TimeoutResponse::NullArrray,
+            TryAgainResponse::RunCommandAgain,
+        )))
+    }
+
+    /// Removes one or more entries from the Pending Entries List (PEL) of a consumer group
+    /// `XACK key group id [id ...]`
+    async fn xack(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 4, response_buffer);
+        let key = command_arg_at!(command, 1);
+        let group = command_arg_at!(command, 2);
+
+        let builder = RespBuilderV2::default();
+        let Some(ids) = Self::parse_ids(&command.args_vec()[3..]) else {
+            builder.error_string(response_buffer, Strings::ERR_INVALID_STREAM_ID);
+            return Ok(());
+        };
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut stream_db =
+            StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        match stream_db.ack(key, group, &ids)? {
+            StreamGroupResult::WrongType => {
+                builder_return_wrong_type!(builder, response_buffer);
+            }
+            StreamGroupResult::NoStream | StreamGroupResult::NoGroup => {
+                builder.number_usize(response_buffer, 0);
+            }
+            StreamGroupResult::Some(count) => {
+                stream_db.commit()?;
+                builder.number_usize(response_buffer, count);
+            }
+        }
+        Ok(())
+    }
+
+    /// Inspect the list of pending entries of a consumer group
+    /// `XPENDING key group [[IDLE min-idle-time] start end count [consumer]]`
+    async fn xpending(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 3, response_buffer);
+        let key = command_arg_at!(command, 1);
+        let group = command_arg_at!(command, 2);
+
+        let builder = RespBuilderV2::default();
+        if command.arg_count() == 3 {
+            let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+            let stream_db =
+                StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+            match stream_db.pending_summary(key, group)? {
+                StreamGroupResult::WrongType => {
+                    builder_return_wrong_type!(builder, response_buffer);
+                }
+                StreamGroupResult::NoStream | StreamGroupResult::NoGroup => {
+                    builder.error_string(response_buffer, &Self::no_group_error(key, group));
+                }
+                StreamGroupResult::Some(summary) => {
+                    builder.add_array_len(response_buffer, 4);
+                    builder.add_number::<usize>(response_buffer, summary.count, false);
+                    match summary.range {
+                        Some((min_id, max_id)) => {
+                            builder.add_bulk_string(response_buffer, &min_id.to_bytes());
+                            builder.add_bulk_string(response_buffer, &max_id.to_bytes());
+                        }
+                        None => {
+                            builder.add_null_string(response_buffer);
+                            builder.add_null_string(response_buffer);
+                        }
+                    }
+
+                    if summary.consumers.is_empty() {
+                        builder.add_null_array(response_buffer);
+                    } else {
+                        builder.add_array_len(response_buffer, summary.consumers.len());
+                        for (consumer, count) in &summary.consumers {
+                            builder.add_array_len(response_buffer, 2);
+                            builder.add_bulk_string(response_buffer, consumer);
+                            builder.add_bulk_string(response_buffer, count.to_string().as_bytes());
+                        }
+                    }
+                }
+            }
+            return Ok(());
+        }
+
+        // The extended form
+        let mut pos = 3usize;
+        let mut min_idle: Option<u64> = None;
+        if command
+            .arg_as_lowercase_string(pos)
+            .is_some_and(|arg| arg == "idle")
+        {
+            let Some(value) = command.arg(pos.saturating_add(1)) else {
+                builder_return_syntax_error!(builder, response_buffer);
+            };
+            min_idle = Some(to_number!(value, u64, response_buffer, Ok(())));
+            pos = pos.saturating_add(2);
+        }
+
+        let remaining = command.arg_count().saturating_sub(pos);
+        if remaining != 3 && remaining != 4 {
+            builder_return_syntax_error!(builder, response_buffer);
+        }
+
+        let Some(start) = Self::parse_range_id(command_arg_at!(command, pos), true) else {
+            builder.error_string(response_buffer, Strings::ERR_INVALID_STREAM_ID);
+            return Ok(());
+        };
+        let Some(end) = Self::parse_range_id(command_arg_at!(command, pos + 1), false) else {
+            builder.error_string(response_buffer, Strings::ERR_INVALID_STREAM_ID);
+            return Ok(());
+        };
+        let count = to_number!(
+            command_arg_at!(command, pos + 2),
+            i64,
+            response_buffer,
+            Ok(())
+        );
+        let consumer = command.arg(pos + 3);
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let stream_db = StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        let (Some(start), Some(end)) = (start, end) else {
+            // an exclusive range that can not contain any ID
+            builder_return_empty_array!(builder, response_buffer);
+        };
+
+        let count = count.max(0) as usize;
+        match stream_db.pending_range(key, group, start, end, count, min_idle, consumer)? {
+            StreamGroupResult::WrongType => {
+                builder_return_wrong_type!(builder, response_buffer);
+            }
+            StreamGroupResult::NoStream | StreamGroupResult::NoGroup => {
+                builder.error_string(response_buffer, &Self::no_group_error(key, group));
+            }
+            StreamGroupResult::Some(items) => {
+                let now = current_time(CurrentTimeResolution::Milliseconds);
+                builder.add_array_len(response_buffer, items.len());
+                for (id, pending) in &items {
+                    builder.add_array_len(response_buffer, 4);
+                    builder.add_bulk_string(response_buffer, &id.to_bytes());
+                    builder.add_bulk_string(response_buffer, pending.consumer());
+                    builder.add_number::<u64>(response_buffer, pending.idle(now), false);
+                    builder.add_number::<u64>(response_buffer, pending.delivery_count(), false);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes the ownership of pending entries, so the new owner is the consumer specified
+    /// `XCLAIM key group consumer min-idle-time id [id ...] [IDLE ms] [TIME unix-time-milliseconds]
+    ///     [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID lastid]`
+    async fn xclaim(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 6, response_buffer);
+        let key = command_arg_at!(command, 1);
+        let group = command_arg_at!(command, 2);
+        let consumer = command_arg_at!(command, 3);
+
+        let builder = RespBuilderV2::default();
+        let min_idle = to_number!(command_arg_at!(command, 4), i64, response_buffer, Ok(()));
+
+        // The IDs list ends with the first argument that is not an ID
+        let mut pos = 5usize;
+        let mut ids = Vec::<StreamId>::new();
+        while let Some(id) = command.arg(pos).and_then(|arg| StreamId::parse(arg, 0)) {
+            ids.push(id);
+            pos = pos.saturating_add(1);
+        }
+
+        let mut options = StreamClaimOptions::default();
+        while let Some(arg) = command.arg_as_lowercase_string(pos) {
+            let value = command.arg(pos.saturating_add(1));
+            match (arg.as_str(), value) {
+                ("force", _) => {
+                    options.force = true;
+                    pos = pos.saturating_add(1);
+                    continue;
+                }
+                ("justid", _) => {
+                    options.justid = true;
+                    pos = pos.saturating_add(1);
+                    continue;
+                }
+                ("lastid", Some(value)) => {
+                    let Some(last_id) = StreamId::parse(value, 0) else {
+                        builder.error_string(response_buffer, Strings::ERR_INVALID_STREAM_ID);
+                        return Ok(());
+                    };
+                    options.last_id = Some(last_id);
+                }
+                ("idle" | "time" | "retrycount", Some(value)) => {
+                    let Some(value) = BytesMutUtils::parse::<u64>(value) else {
+                        builder.error_string(
+                            response_buffer,
+                            Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE,
+                        );
+                        return Ok(());
+                    };
+                    match arg.as_str() {
+                        "idle" => options.idle = Some(value),
+                        "time" => options.time = Some(value),
+                        _ => options.retry_count = Some(value),
+                    }
+                }
+                (_, _) => {
+                    builder_return_syntax_error!(builder, response_buffer);
+                }
+            }
+            pos = pos.saturating_add(2);
+        }
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut stream_db =
+            StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        match stream_db.claim(key, group, consumer, min_idle.max(0) as u64, &ids, &options)? {
+            StreamGroupResult::WrongType => {
+                builder_return_wrong_type!(builder, response_buffer);
+            }
+            StreamGroupResult::NoStream | StreamGroupResult::NoGroup => {
+                builder.error_string(response_buffer, &Self::no_group_error(key, group));
+            }
+            StreamGroupResult::Some(claimed) => {
+                stream_db.commit()?;
+                if options.justid {
+                    Self::add_entry_ids(&builder, response_buffer, &claimed);
+                } else {
+                    Self::add_entries(&builder, response_buffer, &claimed);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Transfers ownership of pending entries that match the specified criteria
+    /// `XAUTOCLAIM key group consumer min-idle-time start [COUNT count] [JUSTID]`
+    async fn xautoclaim(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+        response_buffer: &mut BytesMut,
+    ) -> Result<(), SableError> {
+        check_args_count!(command, 6, response_buffer);
+        let key = command_arg_at!(command, 1);
+        let group = command_arg_at!(command, 2);
+        let consumer = command_arg_at!(command, 3);
+
+        let builder = RespBuilderV2::default();
+        let min_idle = to_number!(command_arg_at!(command, 4), i64, response_buffer, Ok(()));
+        let Some(Some(start)) = Self::parse_range_id(command_arg_at!(command, 5), true) else {
+            builder.error_string(response_buffer, Strings::ERR_INVALID_STREAM_ID);
+            return Ok(());
+        };
+
+        let mut count = STREAM_AUTOCLAIM_DEFAULT_COUNT;
+        let mut justid = false;
+        let mut pos = 6usize;
+        while let Some(arg) = command.arg_as_lowercase_string(pos) {
+            let value = command.arg(pos.saturating_add(1));
+            match (arg.as_str(), value) {
+                ("justid", _) => {
+                    justid = true;
+                    pos = pos.saturating_add(1);
+                }
+                ("count", Some(value)) => {
+                    match BytesMutUtils::parse::<i64>(value) {
+                        Some(value) if value > 0 => count = value as usize,
+                        _ => {
+                            builder.error_string(response_buffer, "ERR COUNT must be > 0");
+                            return Ok(());
+                        }
+                    }
+                    pos = pos.saturating_add(2);
+                }
+                (_, _) => {
+                    builder_return_syntax_error!(builder, response_buffer);
+                }
+            }
+        }
+
+        let _unused = LockManager::lock(key, client_state.clone(), command.clone()).await?;
+        let mut stream_db =
+            StreamDb::with_storage(client_state.database(), client_state.database_id());
+
+        match stream_db.autoclaim(
+            key,
+            group,
+            consumer,
+            min_idle.max(0) as u64,
+            start,
+            count,
+            justid,
+        )? {
+            StreamGroupResult::WrongType => {
+                builder_return_wrong_type!(builder, response_buffer);
+            }
+            StreamGroupResult::NoStream | StreamGroupResult::NoGroup => {
+                builder.error_string(response_buffer, &Self::no_group_error(key, group));
+            }
+            StreamGroupResult::Some(result) => {
+                stream_db.commit()?;
+                builder.add_array_len(response_buffer, 3);
+                builder.add_bulk_string(response_buffer, &result.next_id.to_bytes());
+                if justid {
+                    Self::add_entry_ids(&builder, response_buffer, &result.claimed);
+                } else {
+                    Self::add_entries(&builder, response_buffer, &result.claimed);
+                }
+                builder.add_array_len(response_buffer, result.deleted.len());
+                for id in &result.deleted {
+                    builder.add_bulk_string(response_buffer, &id.to_bytes());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //=======================================================
+    // Internal API for this class
+    //=======================================================
+
+    /// Write `entries` as an array of `[id, [field, value, ...]]`
+    fn add_entries(
+        builder: &RespBuilderV2,
+        response_buffer: &mut BytesMut,
+        entries: &[StreamEntry],
+    ) {
+        builder.add_array_len(response_buffer, entries.len());
+        for entry in entries {
+            Self::add_entry(builder, response_buffer, entry);
+        }
+    }
+
+    /// Write a single entry as `[id, [field, value, ...]]`
+    fn add_entry(builder: &RespBuilderV2, response_buffer: &mut BytesMut, entry: &StreamEntry) {
+        builder.add_array_len(response_buffer, 2);
+        builder.add_bulk_string(response_buffer, &entry.id.to_bytes());
+        builder.add_array_len(response_buffer, entry.fields.len().saturating_mul(2));
+        for (field, value) in &entry.fields {
+            builder.add_bulk_string(response_buffer, field);
+            builder.add_bulk_string(response_buffer, value);
+        }
+    }
+
+    /// Write the IDs of `entries` as an array (used by the `JUSTID` option)
+    fn add_entry_ids(
+        builder: &RespBuilderV2,
+        response_buffer: &mut BytesMut,
+        entries: &[StreamEntry],
+    ) {
+        builder.add_array_len(response_buffer, entries.len());
+        for entry in entries {
+            builder.add_bulk_string(response_buffer, &entry.id.to_bytes());
+        }
+    }
+
+    /// The error returned when the stream or the consumer group do not exist
+    fn no_group_error(key: &BytesMut, group: &BytesMut) -> String {
+        format!(
+            "NOGROUP No such key '{}' or consumer group '{}'",
+            BytesMutUtils::to_string(key),
+            BytesMutUtils::to_string(group)
+        )
+    }
+
+    /// Parse a list of stream IDs. Return `None` if any of the IDs is invalid
+    fn parse_ids(values: &[BytesMut]) -> Option<Vec<StreamId>> {
+        values.iter().map(|id| StreamId::parse(id, 0)).collect()
+    }
+
+    /// Parse the ID argument of `XADD`: `*`, `<ms>-*` or `<ms>-<seq>`
+    fn parse_id_spec(value: &BytesMut) -> Option<StreamIdSpec> {
+        if value.eq(&BytesMut::from("*")) {
+            return Some(StreamIdSpec::Auto);
+        }
+
+        if let Some(ms) = value.strip_suffix(b"-*") {
+            let ms = BytesMutUtils::parse::<u64>(&BytesMut::from(ms))?;
+            return Some(StreamIdSpec::AutoSeq(ms));
+        }
+        Some(StreamIdSpec::Explicit(StreamId::parse(value, 0)?))
+    }
+
+    /// Parse an `XRANGE` boundary. `-` and `+` are the smallest and the largest possible IDs,
+    /// a `(` prefix makes the boundary exclusive. Returns `None` for an invalid ID and `Some(None)`
+    /// for an exclusive boundary that leaves no room for any ID
+    fn parse_range_id(value: &BytesMut, is_start: bool) -> Option<Option<StreamId>> {
+        match value.as_ref() {
+            b"-" => return Some(Some(StreamId::MIN)),
+            b"+" => return Some(Some(StreamId::MAX)),
+            _ => {}
+        }
+
+        let missing_seq = if is_start { 0 } else { u64::MAX };
+        match value.strip_prefix(b"(") {
+            Some(exclusive) => {
+                let id = StreamId::parse(exclusive, missing_seq)?;
+                Some(if is_start { id.next() } else { id.prev() })
+            }
+            None => Some(Some(StreamId::parse(value, missing_seq)?)),
+        }
+    }
+
+    /// Parse `<MAXLEN | MINID> [= | ~] threshold [LIMIT count]` starting at `pos`.
+    /// On success, return the trimming options and the position of the next argument
+    fn parse_trim(
+        command: &ValkeyCommand,
+        mut pos: usize,
+    ) -> Result<(StreamTrim, usize), &'static str> {
+        let Some(strategy) = command.arg_as_lowercase_string(pos) else {
+            return Err(Strings::SYNTAX_ERROR);
+        };
+        pos = pos.saturating_add(1);
+
+        let mut approx = false;
+        match command.arg(pos).map(|arg| arg.as_ref()) {
+            Some(b"~") => {
+                approx = true;
+                pos = pos.saturating_add(1);
+            }
+            Some(b"=") => {
+                pos = pos.saturating_add(1);
+            }
+            _ => {}
+        }
+
+        let Some(threshold) = command.arg(pos) else {
+            return Err(Strings::SYNTAX_ERROR);
+        };
+        pos = pos.saturating_add(1);
+
+        let strategy = match strategy.as_str() {
+            "maxlen" => {
+                let Some(maxlen) = BytesMutUtils::parse::<i64>(threshold) else {
+                    return Err(Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE);
+                };
+                if maxlen < 0 {
+                    return Err("ERR The MAXLEN argument must be >= 0.");
+                }
+                StreamTrimStrategy::MaxLen(maxlen as u64)
+            }
+            "minid" => {
+                let Some(minid) = StreamId::parse(threshold, 0) else {
+                    return Err(Strings::ERR_INVALID_STREAM_ID);
+                };
+                StreamTrimStrategy::MinId(minid)
+            }
+            _ => return Err(Strings::SYNTAX_ERROR),
+        };
+
+        let mut limit = if approx {
+            Some(STREAM_DEFAULT_TRIM_LIMIT)
+        } else {
+            None
+        };
+
+        if command
+            .arg_as_lowercase_string(pos)
+            .is_some_and(|arg| arg == "limit")
+        {
+            let Some(count) = command.arg(pos.saturating_add(1)) else {
+                return Err(Strings::SYNTAX_ERROR);
+            };
+            let Some(count) = BytesMutUtils::parse::<i64>(count) else {
+                return Err(Strings::VALUE_NOT_AN_INT_OR_OUT_OF_RANGE);
+            };
+            if count < 0 {
+                return Err("ERR The LIMIT argument must be >= 0.");
+            }
+            if !approx {
+                return Err(Strings::ERR_STREAM_LIMIT_WITHOUT_TILDE);
+            }
+            // LIMIT 0 means "no limit"
+            limit = if count == 0 { None } else { Some(count as u64) };
+            pos = pos.saturating_add(2);
+        }
+
+        Ok((StreamTrim { strategy, limit }, pos))
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands::ClientNextAction, Client, ServerState};
+    use std::sync::Arc;
+    use test_case::test_case;
+
+    #[test_case(vec![
+        (vec!["xadd", "xadd_stream", "1-1", "name", "sable"], "$3\r\n1-1\r\n"),
+        (vec!["xadd", "xadd_stream", "1-1", "name", "sable"], "-ERR The ID specified in XADD is equal or smaller than the target stream top item\r\n"),
+        (vec!["xadd", "xadd_stream", "1-*", "name", "sable"], "$3\r\n1-2\r\n"),
+        (vec!["xadd", "xadd_stream", "0-0", "name", "sable"], "-ERR The ID specified in XADD must be greater than 0-0\r\n"),
+        (vec!["xadd", "xadd_stream", "abc", "name", "sable"], "-ERR Invalid stream ID specified as stream command argument\r\n"),
+        (vec!["xadd", "xadd_stream", "5", "name"], "-ERR wrong number of arguments for 'xadd' command\r\n"),
+        (vec!["xadd", "xadd_stream", "5", "name", "sable"], "$3\r\n5-0\r\n"),
+        (vec!["xadd", "xadd_no_stream", "nomkstream", "*", "name", "sable"], "$-1\r\n"),
+        (vec!["xlen", "xadd_stream"], ":3\r\n"),
+        (vec!["xlen", "xadd_no_stream"], ":0\r\n"),
+        (vec!["set", "xadd_string", "value"], "+OK\r\n"),
+        (vec!["xadd", "xadd_string", "*", "name", "sable"], "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"),
+        (vec!["xlen", "xadd_string"], "-WRONGTYPE Operation against a key holding the wrong kind of value\r\n"),
+        ], "xadd"; "xadd")]
+    #[test_case(vec![
+        (vec!["xadd", "xadd_trim", "1", "a", "1"], "$3\r\n1-0\r\n"),
+        (vec!["xadd", "xadd_trim", "2", "a", "2"], "$3\r\n2-0\r\n"),
+        (vec!["xadd", "xadd_trim", "maxlen", "2", "3", "a", "3"], "$3\r\n3-0\r\n"),
+        (vec!["xrange", "xadd_trim", "-", "+"], "*2\r\n*2\r\n$3\r\n2-0\r\n*2\r\n$1\r\na\r\n$1\r\n2\r\n*2\r\n$3\r\n3-0\r\n*2\r\n$1\r\na\r\n$1\r\n3\r\n"),
+        (vec!["xadd", "xadd_trim", "minid", "=", "4", "4", "a", "4"], "$3\r\n4-0\r\n"),
+        (vec!["xlen", "xadd_trim"], ":1\r\n"),
+        (vec!["xadd", "xadd_trim", "maxlen", "2", "limit", "10", "5", "a", "5"], "-ERR syntax error, LIMIT cannot be used without the special ~ option\r\n"),
+        (vec!["xadd", "xadd_trim", "maxlen", "-1", "5", "a", "5"], "-ERR The MAXLEN argument must be >= 0.\r\n"),
+        ], "xadd_trim"; "xadd_trim")]
+    #[test_case(vec![
+        (vec!["xadd", "xrange_stream", "1-1", "a", "1"], "$3\r\n1-1\r\n"),
+        (vec!["xadd", "xrange_stream", "1-2", "b", "2"], "$3\r\n1-2\r\n"),
+        (vec!["xadd", "xrange_stream", "2-1", "c", "3"], "$3\r\n2-1\r\n"),
+        (vec!["xrange", "xrange_stream", "-", "+", "count", "1"], "*1\r\n*2\r\n$3\r\n1-1\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n"),
+        (vec!["xrange", "xrange_stream", "(1-1", "1"], "*1\r\n*2\r\n$3\r\n1-2\r\n*2\r\n$1\r\nb\r\n$1\r\n2\r\n"),
+        (vec!["xrange", "xrange_stream", "2", "+"], "*1\r\n*2\r\n$3\r\n2-1\r\n*2\r\n$1\r\nc\r\n$1\r\n3\r\n"),
+        (vec!["xrange", "xrange_stream", "+", "-"], "*0\r\n"),
+        (vec!["xrange", "xrange_stream", "x", "+"], "-ERR Invalid stream ID specified as stream command argument\r\n"),
+        (vec!["xrange", "xrange_stream", "-", "+", "count"], "-ERR syntax error\r\n"),
+        (vec!["xrange", "xrange_no_stream", "-", "+"], "*0\r\n"),
+        ], "xrange"; "xrange")]
+    #[test_case(vec![
+        (vec!["xadd", "xdel_stream", "1", "a", "1"], "$3\r\n1-0\r\n"),
+        (vec!["xadd", "xdel_stream", "2", "a", "2"], "$3\r\n2-0\r\n"),
+        (vec!["xadd", "xdel_stream", "3", "a", "3"], "$3\r\n3-0\r\n"),
+        (vec!["xdel", "xdel_stream", "2", "7"], ":1\r\n"),
+        (vec!["xdel", "xdel_stream", "2"], ":0\r\n"),
+        (vec!["xlen", "xdel_stream"], ":2\r\n"),
+        (vec!["xtrim", "xdel_stream", "maxlen", "1"], ":1\r\n"),
+        (vec!["xtrim", "xdel_stream", "maxlen", "~", "0", "limit", "0"], ":1\r\n"),
+        (vec!["xlen", "xdel_stream"], ":0\r\n"),
+        (vec!["xtrim", "xdel_stream", "minid"], "-ERR wrong number of arguments for 'xtrim' command\r\n"),
+        (vec!["xtrim", "xdel_stream", "maxlen", "1", "extra"], "-ERR syntax error\r\n"),
+        ], "xdel_xtrim"; "xdel_xtrim")]
+    #[test_case(vec![
+        (vec!["xadd", "xread_stream1", "1", "a", "1"], "$3\r\n1-0\r\n"),
+        (vec!["xadd", "xread_stream2", "2", "b", "2"], "$3\r\n2-0\r\n"),
+        (vec!["xread", "streams", "xread_stream1", "xread_stream2", "0", "0"], "*2\r\n*2\r\n$13\r\nxread_stream1\r\n*1\r\n*2\r\n$3\r\n1-0\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n*2\r\n$13\r\nxread_stream2\r\n*1\r\n*2\r\n$3\r\n2-0\r\n*2\r\n$1\r\nb\r\n$1\r\n2\r\n"),
+        (vec!["xread", "count", "1", "streams", "xread_stream1", "xread_stream2", "1", "0"], "*1\r\n*2\r\n$13\r\nxread_stream2\r\n*1\r\n*2\r\n$3\r\n2-0\r\n*2\r\n$1\r\nb\r\n$1\r\n2\r\n"),
+        (vec!["xread", "streams", "xread_stream1", "$"], "*-1\r\n"),
+        (vec!["xread", "block", "10", "streams", "xread_stream1", "$"], "*-1\r\n"),
+        (vec!["xread", "streams", "xread_stream1", "xread_stream2", "0"], "-ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.\r\n"),
+        (vec!["xread", "block", "-1", "streams", "xread_stream1", "0"], "-ERR timeout is negative\r\n"),
+        ], "xread"; "xread")]
+    #[test_case(vec![
+        (vec!["xgroup", "create", "xgroup_stream", "g1", "$"], "-ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.\r\n"),
+        (vec!["xgroup", "create", "xgroup_stream", "g1", "$", "mkstream"], "+OK\r\n"),
+        (vec!["xgroup", "create", "xgroup_stream", "g1", "$"], "-BUSYGROUP Consumer Group name already exists\r\n"),
+        (vec!["xadd", "xgroup_stream", "1", "a", "1"], "$3\r\n1-0\r\n"),
+        (vec!["xadd", "xgroup_stream", "2", "b", "2"], "$3\r\n2-0\r\n"),
+        (vec!["xreadgroup", "group", "g1", "alice", "count", "1", "streams", "xgroup_stream", ">"], "*1\r\n*2\r\n$13\r\nxgroup_stream\r\n*1\r\n*2\r\n$3\r\n1-0\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n"),
+        (vec!["xreadgroup", "group", "g1", "bob", "streams", "xgroup_stream", ">"], "*1\r\n*2\r\n$13\r\nxgroup_stream\r\n*1\r\n*2\r\n$3\r\n2-0\r\n*2\r\n$1\r\nb\r\n$1\r\n2\r\n"),
+        (vec!["xreadgroup", "group", "g1", "bob", "streams", "xgroup_stream", ">"], "*-1\r\n"),
+        (vec!["xpending", "xgroup_stream", "g1"], "*4\r\n:2\r\n$3\r\n1-0\r\n$3\r\n2-0\r\n*2\r\n*2\r\n$5\r\nalice\r\n$1\r\n1\r\n*2\r\n$3\r\nbob\r\n$1\r\n1\r\n"),
+        (vec!["xreadgroup", "group", "g1", "alice", "streams", "xgroup_stream", "0"], "*1\r\n*2\r\n$13\r\nxgroup_stream\r\n*1\r\n*2\r\n$3\r\n1-0\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n"),
+        (vec!["xack", "xgroup_stream", "g1", "1-0", "5-0"], ":1\r\n"),
+        (vec!["xreadgroup", "group", "g1", "alice", "streams", "xgroup_stream", "0"], "*1\r\n*2\r\n$13\r\nxgroup_stream\r\n*0\r\n"),
+        (vec!["xclaim", "xgroup_stream", "g1", "alice", "0", "2-0", "justid"], "*1\r\n$3\r\n2-0\r\n"),
+        (vec!["xautoclaim", "xgroup_stream", "g1", "bob", "0", "0", "count", "1", "justid"], "*3\r\n$3\r\n0-0\r\n*1\r\n$3\r\n2-0\r\n*0\r\n"),
+        (vec!["xgroup", "createconsumer", "xgroup_stream", "g1", "carol"], ":1\r\n"),
+        (vec!["xgroup", "createconsumer", "xgroup_stream", "g1", "carol"], ":0\r\n"),
+        (vec!["xgroup", "delconsumer", "xgroup_stream", "g1", "bob"], ":1\r\n"),
+        (vec!["xpending", "xgroup_stream", "g1"], "*4\r\n:0\r\n$-1\r\n$-1\r\n*-1\r\n"),
+        (vec!["xpending", "xgroup_stream", "g1", "-", "+", "10"], "*0\r\n"),
+        (vec!["xreadgroup", "group", "nogroup", "alice", "streams", "xgroup_stream", ">"], "-NOGROUP No such key 'xgroup_stream' or consumer group 'nogroup' in XREADGROUP with GROUP option\r\n"),
+        (vec!["xgroup", "setid", "xgroup_stream", "g1", "0"], "+OK\r\n"),
+        (vec!["xreadgroup", "group", "g1", "alice", "streams", "xgroup_stream", ">"], "*1\r\n*2\r\n$13\r\nxgroup_stream\r\n*2\r\n*2\r\n$3\r\n1-0\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n*2\r\n$3\r\n2-0\r\n*2\r\n$1\r\nb\r\n$1\r\n2\r\n"),
+        (vec!["xgroup", "destroy", "xgroup_stream", "g1"], ":1\r\n"),
+        (vec!["xgroup", "destroy", "xgroup_stream", "g1"], ":0\r\n"),
+        (vec!["xgroup", "foo", "xgroup_stream"], "-ERR unknown subcommand 'foo'. Try XGROUP HELP.\r\n"),
+        (vec!["xack", "xgroup_no_stream", "g1", "1"], ":0\r\n"),
+        ], "xgroup"; "xgroup")]
+    fn test_stream_commands(args_vec: Vec<(Vec<&'static str>, &'static str)>, test_name: &str) {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let client = Client::new(Arc::<ServerState>::default(), store, None);
+
+            for (args, expected_value) in args_vec {
+                let mut sink = crate::tests::ResponseSink::with_name(test_name).await;
+                let cmd = Rc::new(ValkeyCommand::for_test(args));
+                match Client::handle_command(client.inner(), cmd, &mut sink.fp)
+                    .await
+                    .unwrap()
+                {
+                    ClientNextAction::SendResponse(response_buffer) => {
+                        assert_eq!(
+                            BytesMutUtils::to_string(&response_buffer).as_str(),
+                            expected_value
+                        );
+                    }
+                    ClientNextAction::NoAction => {
+                        assert_eq!(&sink.read_all().await, expected_value);
+                    }
+                    ClientNextAction::Wait((rx, duration, _, _)) => {
+                        // we only expect a time-out here
+                        match Client::wait_for(rx, duration).await {
+                            crate::server::WaitResult::Timeout => {
+                                assert_eq!("*-1\r\n", expected_value);
+                            }
+                            crate::server::WaitResult::TryAgain => {
+                                panic!("expected a timeout");
+                            }
+                        }
+                    }
+                    ClientNextAction::TerminateConnection => {
+                        panic!("unexpected connection termination");
+                    }
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_blocking_xreadgroup() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+
+            let server = Arc::<ServerState>::default();
+            let reader = Client::new(server.clone(), store.clone(), None);
+            let writer = Client::new(server, store, None);
+
+            let create_cmd = Rc::new(ValkeyCommand::for_test(vec![
+                "xgroup",
+                "create",
+                "blocking_xreadgroup",
+                "g1",
+                "$",
+                "mkstream",
+            ]));
+            let response = crate::tests::execute_command(writer.inner(), create_cmd).await;
+            assert_eq!("+OK\r\n", BytesMutUtils::to_string(&response).as_str());
+
+            let read_cmd = Rc::new(ValkeyCommand::for_test(vec![
+                "xreadgroup",
+                "group",
+                "g1",
+                "alice",
+                "block",
+                "5000",
+                "streams",
+                "blocking_xreadgroup",
+                ">",
+            ]));
+
+            let mut sink = crate::io::FileResponseSink::new().await.unwrap();
+            let ClientNextAction::Wait((rx, duration, _, try_again_response)) =
+                Client::handle_command(reader.inner(), read_cmd.clone(), &mut sink.fp)
+                    .await
+                    .unwrap()
+            else {
+                panic!("expected the client to be blocked");
+            };
+            assert!(matches!(
+                try_again_response,
+                TryAgainResponse::RunCommandAgain
+            ));
+
+            let add_cmd = Rc::new(ValkeyCommand::for_test(vec![
+                "xadd",
+                "blocking_xreadgroup",
+                "1",
+                "a",
+                "1",
+            ]));
+            let response = crate::tests::execute_command(writer.inner(), add_cmd).await;
+            assert_eq!("$3\r\n1-0\r\n", BytesMutUtils::to_string(&response).as_str());
+
+            match Client::wait_for(rx, duration).await {
+                crate::server::WaitResult::TryAgain => {
+                    let response = crate::tests::execute_command(reader.inner(), read_cmd).await;
+                    assert_eq!(
+                        "*1\r\n*2\r\n$19\r\nblocking_xreadgroup\r\n*1\r\n*2\r\n$3\r\n1-0\r\n*2\r\n$1\r\na\r\n$1\r\n1\r\n",
+                        BytesMutUtils::to_string(&response).as_str()
+                    );
+                }
+                crate::server::WaitResult::Timeout => {
+                    panic!("expected `TryAgain` not a `Timeout`");
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_blocking_xread() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+
+            let server = Arc::<ServerState>::default();
+            let reader = Client::new(server.clone(), store.clone(), None);
+            let writer = Client::new(server, store, None);
+
+            let add_cmd = Rc::new(ValkeyCommand::for_test(vec![
+                "xadd",
+                "blocking_xread",
+                "1",
+                "a",
+                "1",
+            ]));
+            let response = crate::tests::execute_command(writer.inner(), add_cmd).await;
+            assert_eq!("$3\r\n1-0\r\n", BytesMutUtils::to_string(&response).as_str());
+
+            // `$` means: only entries added from now on
+            let read_cmd = Rc::new(ValkeyCommand::for_test(vec![
+                "xread",
+                "block",
+                "5000",
+                "streams",
+                "blocking_xread",
+                "$",
+            ]));
+
+            let mut sink = crate::io::FileResponseSink::new().await.unwrap();
+            let ClientNextAction::Wait((rx, duration, _, try_again_response)) =
+                Client::handle_command(reader.inner(), read_cmd, &mut sink.fp)
+                    .await
+                    .unwrap()
+            else {
+                panic!("expected the client to be blocked");
+            };
+
+            // The command to run after wakeup must have `$` resolved into the last ID
+            let TryAgainResponse::RunCommand(next_cmd) = try_again_response else {
+                panic!("expected a resolved command");
+            };
+            assert_eq!(next_cmd.arg(5).unwrap(), &BytesMut::from("1-0"));
+
+            let add_cmd = Rc::new(ValkeyCommand::for_test(vec![
+                "xadd",
+                "blocking_xread",
+                "2",
+                "b",
+                "2",
+            ]));
+            let response = crate::tests::execute_command(writer.inner(), add_cmd).await;
+            assert_eq!("$3\r\n2-0\r\n", BytesMutUtils::to_string(&response).as_str());
+
+            match Client::wait_for(rx, duration).await {
+                crate::server::WaitResult::TryAgain => {
+                    let response = crate::tests::execute_command(reader.inner(), next_cmd).await;
+                    assert_eq!(
+                        "*1\r\n*2\r\n$14\r\nblocking_xread\r\n*1\r\n*2\r\n$3\r\n2-0\r\n*2\r\n$1\r\nb\r\n$1\r\n2\r\n",
+                        BytesMutUtils::to_string(&response).as_str()
+                    );
+                }
+                crate::server::WaitResult::Timeout => {
+                    panic!("expected `TryAgain` not a `Timeout`");
+                }
+            }
+        });
+    }
+}
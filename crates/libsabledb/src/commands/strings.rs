@@ -39,6 +39,76 @@ impl Strings {
     pub const ERR_EAGAIN: &'static str = "EAGAIN resource is not available, try again later";
     pub const ERR_DEADLOCK: &'static str = "DEADLOCK lock is already owned by the calling client";
     pub const ERR_NOT_OWNER: &'static str = "ERR resource is not owned by the calling client";
+    pub const ERR_INVALID_STREAM_ID: &'static str =
+        "ERR Invalid stream ID specified as stream command argument";
+    pub const ERR_XADD_ID_TOO_SMALL: &'static str =
+        "ERR The ID specified in XADD is equal or smaller than the target stream top item";
+    pub const ERR_XADD_ID_ZERO: &'static str =
+        "ERR The ID specified in XADD must be greater than 0-0";
+    pub const ERR_STREAM_IDS_EXHAUSTED: &'static str =
+        "ERR The stream has exhausted the last possible ID, unable to add more items";
+    pub const ERR_STREAM_LIMIT_WITHOUT_TILDE: &'static str =
+        "ERR syntax error, LIMIT cannot be used without the special ~ option";
+    pub const ERR_XREAD_UNBALANCED: &'static str = "ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.";
+    pub const ERR_XREADGROUP_UNBALANCED: &'static str = "ERR Unbalanced 'xreadgroup' list of streams: for each stream key an ID or '>' must be specified.";
+    pub const ERR_BUSYGROUP: &'static str = "BUSYGROUP Consumer Group name already exists";
+    pub const ERR_XGROUP_KEY_MUST_EXIST: &'static str = "ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.";
+    pub const ERR_NOSCRIPT: &'static str = "NOSCRIPT No matching script. Please use EVAL.";
+    pub const ERR_NUMKEYS_NEGATIVE: &'static str = "ERR Number of keys can't be negative";
+    pub const ERR_SCRIPT_NO_ARGS: &'static str =
+        "ERR Please specify at least one argument for this redis lib call";
+    pub const ERR_SCRIPT_ARGS: &'static str =
+        "ERR Lua redis lib command arguments must be strings or integers";
+    pub const ERR_NOT_ALLOWED_FROM_SCRIPT: &'static str =
+        "ERR This Redis command is not allowed from script";
+    pub const ERR_WRITE_FROM_READONLY_SCRIPT: &'static str =
+        "ERR Write commands are not allowed from read-only scripts.";
+    pub const ERR_SCRIPT_KILLED: &'static str = "ERR Script killed by user with SCRIPT KILL...";
+    pub const BUSY_SCRIPT: &'static str =
+        "BUSY SableDB is busy running a script. You can only call SCRIPT KILL.";
+    pub const NOTBUSY: &'static str = "NOTBUSY No scripts in execution right now.";
+    pub const UNKILLABLE: &'static str = "UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or restart the server.";
+    pub const ERR_FUNCTION_NOT_FOUND: &'static str = "ERR Function not found";
+    pub const ERR_LIBRARY_NOT_FOUND: &'static str = "ERR Library not found";
+    pub const ERR_FCALL_RO_WRITE_FUNCTION: &'static str =
+        "ERR Can not execute a script with write flag using *_ro command.";
+    pub const ERR_FUNCTION_PAYLOAD: &'static str = "ERR payload version or checksum are wrong";
+    pub const NOAUTH: &'static str = "NOAUTH Authentication required.";
+    pub const WRONGPASS: &'static str =
+        "WRONGPASS invalid username-password pair or user is disabled.";
+    pub const NOPERM_KEY: &'static str = "NOPERM No permissions to access a key";
+    pub const NOPERM_CHANNEL: &'static str = "NOPERM No permissions to access a channel";
+    pub const ERR_BIT_OFFSET: &'static str = "ERR bit offset is not an integer or out of range";
+    pub const ERR_BIT_VALUE: &'static str = "ERR bit is not an integer or out of range";
+    pub const ERR_BITPOS_BIT: &'static str = "ERR The bit argument must be 1 or 0.";
+    pub const ERR_BITOP_NOT: &'static str =
+        "ERR BITOP NOT must be called with a single source key.";
+    pub const ERR_BITFIELD_TYPE: &'static str = "ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.";
+    pub const ERR_BITFIELD_OVERFLOW: &'static str = "ERR Invalid OVERFLOW type specified";
+    pub const HLL_WRONGTYPE: &'static str =
+        "WRONGTYPE Key is not a valid HyperLogLog string value.";
+    pub const HLL_CORRUPTED: &'static str = "INVALIDOBJ Corrupted HLL object detected";
+    pub const ERR_FIELDS_MISSING: &'static str =
+        "ERR Mandatory argument FIELDS is missing or not at the right position";
+    pub const ERR_NUMFIELDS_ZERO: &'static str =
+        "ERR Parameter `numFields` should be greater than 0";
+    pub const ERR_NUMFIELDS_MISMATCH: &'static str =
+        "ERR The `numfields` parameter must match the number of arguments";
+    pub const ERR_SAME_OBJECT: &'static str = "ERR source and destination objects are the same";
+    pub const ERR_DUMP_PAYLOAD: &'static str = "ERR DUMP payload version or checksum are wrong";
+    pub const ERR_BAD_DATA_FORMAT: &'static str = "ERR Bad data format";
+    pub const BUSYKEY: &'static str = "BUSYKEY Target key name already exists.";
+    pub const ERR_INVALID_TTL: &'static str = "ERR Invalid TTL value, must be >= 0";
+    pub const ERR_INVALID_IDLETIME: &'static str = "ERR Invalid IDLE time, must be >= 0";
+    pub const ERR_MIGRATE_KEYS: &'static str =
+        "ERR When using MIGRATE KEYS option, the key argument must be set to the empty string";
+    pub const ERR_MIGRATE_AUTH: &'static str =
+        "ERR MIGRATE AUTH is not supported, nodes do not authenticate each other";
+    pub const ERR_SORT_NOT_A_DOUBLE: &'static str =
+        "ERR One or more scores can't be converted into double";
+    pub const ERR_SORT_BY_CROSSSLOT: &'static str = "ERR BY option of SORT denied in Cluster mode when keys formed by the pattern may be in different slots.";
+    pub const ERR_SORT_GET_CROSSSLOT: &'static str = "ERR GET option of SORT denied in Cluster mode when keys formed by the pattern may be in different slots.";
+    pub const ERR_INVALID_FREQ: &'static str = "ERR Invalid FREQ value, must be >= 0 and <= 255";
 
     // General strings
     pub const POISONED_MUTEX: &'static str = "poisoned mutex";
@@ -10,7 +10,8 @@ const MAX_BUFFER_SIZE: usize = 10 << 20; // 10MB
 
 use crate::{utils::BytesMutUtils, SableError};
 use bytes::BytesMut;
-use std::io::{Read, Write};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 #[cfg(not(test))]
@@ -126,12 +127,26 @@ where
 
 /// Write `fp` content into `writer`
 pub fn send_file<W>(writer: &mut W, filepath: &std::path::Path) -> Result<u64, SableError>
+where
+    W: ?Sized + Write,
+{
+    send_file_from(writer, filepath, 0)
+}
+
+/// Write `fp` content, starting at `offset`, into `writer`. Return the number of bytes sent
+pub fn send_file_from<W>(
+    writer: &mut W,
+    filepath: &std::path::Path,
+    offset: u64,
+) -> Result<u64, SableError>
 where
     W: ?Sized + Write,
 {
     // Send the file size first
     let mut fp = std::fs::File::options().read(true).open(filepath)?;
-    let file_len = fp.metadata()?.len() as usize;
+    let offset = offset.min(fp.metadata()?.len());
+    fp.seek(SeekFrom::Start(offset))?;
+    let file_len = fp.metadata()?.len().saturating_sub(offset) as usize;
     let mut bytes_left: usize = file_len;
 
     // Send the file length first
@@ -165,8 +180,31 @@ where
     R: ?Sized + Read,
 {
     // Prepare the local file
-    let mut fp = std::fs::File::create(filepath)?;
+    let fp = std::fs::File::create(filepath)?;
+    recv_file_into(fp, filepath, stream)
+}
+
+/// Read file content from `stream` and append it to `filepath` (the file is created if it does
+/// not exist). Return the file size
+pub fn recv_file_append<R>(filepath: &std::path::Path, stream: &mut R) -> Result<u64, SableError>
+where
+    R: ?Sized + Read,
+{
+    let fp = std::fs::File::options()
+        .create(true)
+        .append(true)
+        .open(filepath)?;
+    recv_file_into(fp, filepath, stream)
+}
 
+fn recv_file_into<R>(
+    mut fp: std::fs::File,
+    filepath: &std::path::Path,
+    stream: &mut R,
+) -> Result<u64, SableError>
+where
+    R: ?Sized + Read,
+{
     // Read the file's length
     const BYTES_TO_READ: usize = std::mem::size_of::<usize>();
     let mut file_len = Vec::<u8>::with_capacity(BYTES_TO_READ);
@@ -190,6 +228,21 @@ where
     Ok(fp.metadata()?.len())
 }
 
+/// Return the SHA256 checksum (hex encoded) of the file content
+pub fn file_checksum(filepath: &std::path::Path) -> Result<String, SableError> {
+    let mut fp = std::fs::File::open(filepath)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; MAX_BUFFER_SIZE];
+    loop {
+        let count = fp.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+        hasher.update(&buffer[..count]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 #[derive(Default)]
 pub struct Archive {}
 
@@ -269,4 +322,27 @@ mod tests {
             drop(_guard);
         }
     }
+
+    #[test]
+    fn test_send_file_from_offset() {
+        const FILE_CONTENT: &str = "hello world";
+        let tempfile = crate::io::TempFile::with_name("test_send_file_from_offset");
+        std::fs::write(&tempfile.fullpath(), FILE_CONTENT).unwrap();
+        let source = std::path::PathBuf::from(tempfile.fullpath().as_str());
+
+        // Resume a partial copy: the target already contains the first 6 bytes
+        let outfile = crate::io::TempFile::with_name("test_send_file_from_offset_out");
+        let target = std::path::PathBuf::from(outfile.fullpath().as_str());
+        std::fs::write(&target, &FILE_CONTENT[..6]).unwrap();
+
+        let mut buffer = Vec::<u8>::new();
+        assert_eq!(send_file_from(&mut buffer, &source, 6).unwrap(), 5);
+        let file_size = recv_file_append(&target, &mut buffer.as_slice()).unwrap();
+        assert_eq!(file_size, FILE_CONTENT.len() as u64);
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), FILE_CONTENT);
+        assert_eq!(
+            file_checksum(&target).unwrap(),
+            file_checksum(&source).unwrap()
+        );
+    }
 }
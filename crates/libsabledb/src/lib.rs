@@ -9,9 +9,10 @@ pub mod storage;
 pub mod utils;
 
 pub use commands::{
-    ClientCommands, ClusterCommands, GenericCommands, HashCommands, ListCommands, LockCommands,
-    ServerCommands, SetCommands, StringCommands, TransactionCommands, ValkeyCommand,
-    ValkeyCommandName, ZSetCommands,
+    AclCommands, BitmapCommands, ClientCommands, ClusterCommands, FunctionCommands,
+    GenericCommands, GeoCommands, HashCommands, HyperLogLogCommands, ListCommands, LockCommands,
+    PubSubCommands, ScriptCommands, ServerCommands, SetCommands, StreamCommands, StringCommands,
+    TransactionCommands, ValkeyCommand, ValkeyCommandName, ZSetCommands,
 };
 pub use metadata::{CommonValueMetadata, Expiration, PrimaryKeyMetadata, StringValueMetadata};
 pub use net::Transport;
@@ -0,0 +1,182 @@
+use crate::{
+    metadata::CommonValueMetadata,
+    metadata::{KeyPrefix, KeyType},
+    Expiration, FromU8Reader, SableError, ToU8Writer, U8ArrayBuilder, U8ArrayReader,
+};
+use bytes::BytesMut;
+
+/// Contains information about the bitmap. The bitmap content is split into fixed size chunks
+/// (see `BitmapChunkKey`) so updating a single bit does not rewrite the entire value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitmapValueMetadata {
+    common: CommonValueMetadata,
+    /// The bitmap length, in bytes
+    bitmap_len: u64,
+}
+
+#[allow(dead_code)]
+impl BitmapValueMetadata {
+    pub const SIZE: usize = std::mem::size_of::<u64>() + CommonValueMetadata::SIZE;
+
+    pub fn with_id(bitmap_id: u64) -> Self {
+        BitmapValueMetadata {
+            common: CommonValueMetadata::default()
+                .set_bitmap()
+                .with_uid(bitmap_id),
+            bitmap_len: 0,
+        }
+    }
+
+    pub fn expiration(&self) -> &Expiration {
+        self.common.expiration()
+    }
+
+    pub fn expiration_mut(&mut self) -> &mut Expiration {
+        self.common.expiration_mut()
+    }
+
+    /// Return the bitmap length in bytes
+    pub fn len(&self) -> u64 {
+        self.bitmap_len
+    }
+
+    /// Equivalent to `len() == 0`
+    pub fn is_empty(&self) -> bool {
+        self.bitmap_len.eq(&0u64)
+    }
+
+    /// Grow the bitmap so it is at least `len` bytes long
+    pub fn extend_to(&mut self, len: u64) {
+        self.bitmap_len = self.bitmap_len.max(len);
+    }
+
+    /// Return the bitmap unique ID
+    pub fn id(&self) -> u64 {
+        self.common.uid()
+    }
+
+    /// Serialise the bitmap value metadata into bytes
+    pub fn to_bytes(&self, builder: &mut U8ArrayBuilder) {
+        self.common.to_bytes(builder);
+        builder.write_u64(self.bitmap_len);
+    }
+
+    pub fn from_bytes(reader: &mut U8ArrayReader) -> Result<Self, SableError> {
+        let common = CommonValueMetadata::from_bytes(reader)?;
+        let bitmap_len = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        Ok(BitmapValueMetadata { common, bitmap_len })
+    }
+}
+
+/// The key of a single bitmap chunk: `<prefix><bitmap UID><chunk index>`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BitmapChunkKey {
+    prefix: KeyPrefix,
+    bitmap_id: u64,
+    chunk_index: u64,
+}
+
+impl BitmapChunkKey {
+    pub const SIZE: usize = KeyPrefix::SIZE + 2 * std::mem::size_of::<u64>();
+
+    pub fn new(bitmap_id: u64, db_id: u16, slot: u16, chunk_index: u64) -> Self {
+        BitmapChunkKey {
+            prefix: KeyPrefix::new(KeyType::BitmapChunk, db_id, slot),
+            bitmap_id,
+            chunk_index,
+        }
+    }
+
+    /// Serialise this object into `BytesMut`
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(Self::SIZE);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        self.prefix.to_writer(&mut builder);
+        self.bitmap_id.to_writer(&mut builder);
+        self.chunk_index.to_writer(&mut builder);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let prefix = KeyPrefix::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        let bitmap_id = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let chunk_index = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        Ok(BitmapChunkKey {
+            prefix,
+            bitmap_id,
+            chunk_index,
+        })
+    }
+
+    pub fn bitmap_id(&self) -> u64 {
+        self.bitmap_id
+    }
+
+    pub fn chunk_index(&self) -> u64 {
+        self.chunk_index
+    }
+
+    pub fn key_type(&self) -> &KeyType {
+        self.prefix.key_type()
+    }
+
+    /// Return prefix for iterating over all the bitmap chunks
+    pub fn prefix(bitmap_id: u64, db_id: u16, slot: u16) -> BytesMut {
+        let prefix = KeyPrefix::new(KeyType::BitmapChunk, db_id, slot);
+        let mut buffer = BytesMut::with_capacity(KeyPrefix::SIZE + std::mem::size_of::<u64>());
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        prefix.to_writer(&mut builder);
+        bitmap_id.to_writer(&mut builder);
+        buffer
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitmap_chunk_key_serialization() -> Result<(), SableError> {
+        let chunk_key = BitmapChunkKey::new(42, 5, 10, 7);
+        let buffer = chunk_key.to_bytes();
+        assert_eq!(buffer.len(), BitmapChunkKey::SIZE);
+        assert!(buffer.starts_with(&BitmapChunkKey::prefix(42, 5, 10)));
+
+        let deserialised = BitmapChunkKey::from_bytes(&buffer)?;
+        assert_eq!(deserialised, chunk_key);
+        assert_eq!(deserialised.key_type(), &KeyType::BitmapChunk);
+        assert_eq!(deserialised.bitmap_id(), 42);
+        assert_eq!(deserialised.chunk_index(), 7);
+
+        // chunks are sorted by their index
+        assert!(BitmapChunkKey::new(42, 5, 10, 256).to_bytes() > buffer);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitmap_metadata_serialization() -> Result<(), SableError> {
+        let mut md = BitmapValueMetadata::with_id(42);
+        md.extend_to(100);
+        md.extend_to(10);
+        assert_eq!(md.len(), 100);
+
+        let mut buffer = BytesMut::with_capacity(BitmapValueMetadata::SIZE);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        md.to_bytes(&mut builder);
+        assert_eq!(buffer.len(), BitmapValueMetadata::SIZE);
+
+        let mut reader = U8ArrayReader::with_buffer(&buffer);
+        let deserialised = BitmapValueMetadata::from_bytes(&mut reader)?;
+        assert_eq!(deserialised, md);
+        assert_eq!(deserialised.id(), 42);
+        Ok(())
+    }
+}
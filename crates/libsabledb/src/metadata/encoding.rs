@@ -16,6 +16,8 @@ pub enum ValueType {
     Zset = 3,
     Set = 4,
     Lock = 5,
+    Stream = 6,
+    Bitmap = 7,
 }
 
 impl Default for ValueType {
@@ -24,6 +26,40 @@ impl Default for ValueType {
     }
 }
 
+impl ValueType {
+    /// The type name as reported by the `TYPE` command. A bitmap is a string
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Str | Self::Bitmap => "string",
+            Self::List => "list",
+            Self::Hash => "hash",
+            Self::Zset => "zset",
+            Self::Set => "set",
+            Self::Lock => "lock",
+            Self::Stream => "stream",
+        }
+    }
+
+    /// The key types of the sub items owned by a value of this type. Sub items are keyed by
+    /// `<key type><db id><slot><value uid>`. Values without sub items return an empty slice
+    pub fn sub_item_types(&self) -> &'static [KeyType] {
+        match self {
+            Self::Str | Self::Lock => &[],
+            Self::List => &[KeyType::ListItem],
+            Self::Hash => &[KeyType::HashItem],
+            Self::Zset => &[KeyType::ZsetMemberItem, KeyType::ZsetScoreItem],
+            Self::Set => &[KeyType::SetItem],
+            Self::Stream => &[
+                KeyType::StreamItem,
+                KeyType::StreamGroup,
+                KeyType::StreamPending,
+                KeyType::StreamConsumer,
+            ],
+            Self::Bitmap => &[KeyType::BitmapChunk],
+        }
+    }
+}
+
 impl crate::FromU8Reader for ValueType {
     type Item = ValueType;
     fn from_reader(reader: &mut crate::U8ArrayReader) -> Option<Self::Item> {
@@ -47,6 +83,8 @@ impl FromRaw for ValueType {
             3 => Some(Self::Zset),
             4 => Some(Self::Set),
             5 => Some(Self::Lock),
+            6 => Some(Self::Stream),
+            7 => Some(Self::Bitmap),
             _ => None,
         }
     }
@@ -62,6 +100,8 @@ impl FromStr for ValueType {
             "set" => Ok(Self::Set),
             "zset" => Ok(Self::Zset),
             "lock" => Ok(Self::Lock),
+            "stream" => Ok(Self::Stream),
+            "bitmap" => Ok(Self::Bitmap),
             _ => Err(crate::SableError::InvalidArgument(format!(
                 "Could not convert '{}' into ValueType",
                 s
@@ -93,6 +133,19 @@ pub enum KeyType {
     Lock = 8,
     /// Custom delete_range marker
     DeleteRange = 9,
+    /// Sharded pub/sub message marker
+    ShardMessage = 10,
+    StreamItem = 11,
+    StreamGroup = 12,
+    StreamPending = 13,
+    StreamConsumer = 14,
+    /// Function libraries (`FUNCTION LOAD`). These records are not bound to a database or a slot
+    Function = 15,
+    /// A fixed size chunk of a bitmap value
+    BitmapChunk = 16,
+    /// Expiration index entries, ordered by the expiration timestamp. These records are not bound
+    /// to a database or a slot
+    TtlIndex = 17,
 }
 
 impl Default for KeyType {
@@ -128,6 +181,14 @@ impl FromRaw for KeyType {
             7 => Some(Self::Metadata),
             8 => Some(Self::Lock),
             9 => Some(Self::DeleteRange),
+            10 => Some(Self::ShardMessage),
+            11 => Some(Self::StreamItem),
+            12 => Some(Self::StreamGroup),
+            13 => Some(Self::StreamPending),
+            14 => Some(Self::StreamConsumer),
+            15 => Some(Self::Function),
+            16 => Some(Self::BitmapChunk),
+            17 => Some(Self::TtlIndex),
             _ => None,
         }
     }
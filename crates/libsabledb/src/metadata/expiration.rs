@@ -78,6 +78,15 @@ impl Expiration {
         self.ttl_ms != u64::MAX
     }
 
+    /// Returns the expiration timestamp (millis since UNIX_EPOCH) of a key that has a timeout
+    /// If key has no timeout, return `None`
+    pub fn expire_timestamp_millis(&self) -> Option<u64> {
+        if !self.has_ttl() {
+            return None;
+        }
+        Some(self.last_updated.saturating_add(self.ttl_ms))
+    }
+
     /// Remove the expiration date for this key
     pub fn set_no_expiration(&mut self) -> Result<(), SableError> {
         self.ttl_ms = u64::MAX;
@@ -0,0 +1,204 @@
+use crate::{FromU8Reader, KeyType, SableError, ToU8Writer, U8ArrayBuilder, U8ArrayReader};
+use bytes::BytesMut;
+
+/// The kind of a function record
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FunctionRecordKind {
+    /// `<name>` is a library name and the value is the serialised `FunctionLibrary`
+    Library = 0,
+    /// `<name>` is a function name and the value is the name of the library that owns it
+    Function = 1,
+}
+
+/// The key of a function record: `<KeyType::Function><kind><name>`.
+///
+/// Function records are global: they are not bound to a database or to a slot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionKey {
+    kind: FunctionRecordKind,
+    name: BytesMut,
+}
+
+impl FunctionKey {
+    pub fn library(name: &[u8]) -> Self {
+        FunctionKey {
+            kind: FunctionRecordKind::Library,
+            name: BytesMut::from(name),
+        }
+    }
+
+    pub fn function(name: &[u8]) -> Self {
+        FunctionKey {
+            kind: FunctionRecordKind::Function,
+            name: BytesMut::from(name),
+        }
+    }
+
+    pub fn name(&self) -> &BytesMut {
+        &self.name
+    }
+
+    pub fn kind(&self) -> FunctionRecordKind {
+        self.kind
+    }
+
+    /// Serialise this object into `BytesMut`
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = Self::prefix(self.kind);
+        buffer.extend_from_slice(&self.name);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let key_type = KeyType::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        if key_type != KeyType::Function {
+            return Err(SableError::SerialisationError);
+        }
+        let kind = match reader.read_u8().ok_or(SableError::SerialisationError)? {
+            0 => FunctionRecordKind::Library,
+            1 => FunctionRecordKind::Function,
+            _ => return Err(SableError::SerialisationError),
+        };
+        let name = reader.remaining().ok_or(SableError::SerialisationError)?;
+        Ok(FunctionKey { kind, name })
+    }
+
+    /// Return prefix for iterating over all the records of a given kind
+    pub fn prefix(kind: FunctionRecordKind) -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(2 * std::mem::size_of::<u8>());
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        KeyType::Function.to_writer(&mut builder);
+        builder.write_u8(kind as u8);
+        buffer
+    }
+}
+
+/// A function registered by a library
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionInfo {
+    pub name: BytesMut,
+    /// The flags passed to `redis.register_function` (e.g. `no-writes`)
+    pub flags: Vec<BytesMut>,
+}
+
+impl FunctionInfo {
+    pub const FLAG_NO_WRITES: &'static str = "no-writes";
+
+    /// Return `true` if the function declared that it does not write
+    pub fn is_read_only(&self) -> bool {
+        self.flags
+            .iter()
+            .any(|flag| flag.as_ref() == Self::FLAG_NO_WRITES.as_bytes())
+    }
+}
+
+/// A library loaded with `FUNCTION LOAD`: its code and the functions it registered
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionLibrary {
+    pub name: BytesMut,
+    pub code: BytesMut,
+    pub functions: Vec<FunctionInfo>,
+}
+
+impl FunctionLibrary {
+    /// Return the function `name` owned by this library
+    pub fn function(&self, name: &[u8]) -> Option<&FunctionInfo> {
+        self.functions.iter().find(|f| f.name.as_ref() == name)
+    }
+
+    /// Serialise the library into bytes
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(self.code.len() + 64);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        builder.write_message(&self.name);
+        builder.write_message(&self.code);
+        builder.write_u16(self.functions.len() as u16);
+        for function in &self.functions {
+            builder.write_message(&function.name);
+            builder.write_u16(function.flags.len() as u16);
+            for flag in &function.flags {
+                builder.write_message(flag);
+            }
+        }
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let name = reader
+            .read_message()
+            .ok_or(SableError::SerialisationError)?;
+        let code = reader
+            .read_message()
+            .ok_or(SableError::SerialisationError)?;
+        let count = reader.read_u16().ok_or(SableError::SerialisationError)?;
+        let mut functions = Vec::<FunctionInfo>::with_capacity(count as usize);
+        for _ in 0..count {
+            let name = reader
+                .read_message()
+                .ok_or(SableError::SerialisationError)?;
+            let flags_count = reader.read_u16().ok_or(SableError::SerialisationError)?;
+            let mut flags = Vec::<BytesMut>::with_capacity(flags_count as usize);
+            for _ in 0..flags_count {
+                flags.push(
+                    reader
+                        .read_message()
+                        .ok_or(SableError::SerialisationError)?,
+                );
+            }
+            functions.push(FunctionInfo { name, flags });
+        }
+        Ok(FunctionLibrary {
+            name,
+            code,
+            functions,
+        })
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_function_library_serialization() -> Result<(), SableError> {
+        let library = FunctionLibrary {
+            name: BytesMut::from("mylib"),
+            code: BytesMut::from("#!lua name=mylib\nredis.register_function('f', function() end)"),
+            functions: vec![
+                FunctionInfo {
+                    name: BytesMut::from("f"),
+                    flags: vec![],
+                },
+                FunctionInfo {
+                    name: BytesMut::from("g"),
+                    flags: vec![BytesMut::from("no-writes"), BytesMut::from("allow-oom")],
+                },
+            ],
+        };
+
+        let deserialised = FunctionLibrary::from_bytes(&library.to_bytes())?;
+        assert_eq!(deserialised, library);
+        assert!(!deserialised.function(b"f").unwrap().is_read_only());
+        assert!(deserialised.function(b"g").unwrap().is_read_only());
+        assert!(deserialised.function(b"h").is_none());
+
+        let key = FunctionKey::function(b"g");
+        let deserialised = FunctionKey::from_bytes(&key.to_bytes())?;
+        assert_eq!(deserialised, key);
+        assert_eq!(deserialised.kind(), FunctionRecordKind::Function);
+        assert!(key
+            .to_bytes()
+            .starts_with(&FunctionKey::prefix(FunctionRecordKind::Function)));
+        Ok(())
+    }
+}
@@ -11,16 +11,29 @@ use bytes::BytesMut;
 pub struct HashValueMetadata {
     common: CommonValueMetadata,
     hash_size: u64,
+    /// Hash flags (see `HashValueMetadata::FIELD_METADATA`)
+    flags: u8,
+    /// The number of fields with an expiration time
+    volatile_size: u64,
 }
 
 #[allow(dead_code)]
 impl HashValueMetadata {
-    pub const SIZE: usize = 2 * std::mem::size_of::<u64>() + CommonValueMetadata::SIZE;
+    pub const SIZE: usize =
+        2 * std::mem::size_of::<u64>() + std::mem::size_of::<u8>() + CommonValueMetadata::SIZE;
+
+    /// The field values of this hash are encoded as `HashFieldValue`. Hashes are created storing
+    /// the raw value of their fields (as did hashes written before field expiration was
+    /// supported), so hashes that never use field expiration pay nothing for it. They are
+    /// converted when an expiration time is set on one of their fields
+    pub const FIELD_METADATA: u8 = 1 << 0;
 
     pub fn with_id(hash_id: u64) -> Self {
         HashValueMetadata {
             common: CommonValueMetadata::default().set_hash().with_uid(hash_id),
             hash_size: 0,
+            flags: 0,
+            volatile_size: 0,
         }
     }
 
@@ -56,6 +69,46 @@ impl HashValueMetadata {
         self.hash_size = self.hash_size.saturating_sub(diff);
     }
 
+    /// Return the number of fields with an expiration time
+    pub fn volatile_len(&self) -> u64 {
+        self.volatile_size
+    }
+
+    pub fn incr_volatile_len_by(&mut self, diff: u64) {
+        self.volatile_size = self.volatile_size.saturating_add(diff);
+    }
+
+    pub fn decr_volatile_len_by(&mut self, diff: u64) {
+        self.volatile_size = self.volatile_size.saturating_sub(diff);
+    }
+
+    /// Return true if the field values of this hash are encoded as `HashFieldValue`
+    pub fn has_field_metadata(&self) -> bool {
+        self.flags & Self::FIELD_METADATA != 0
+    }
+
+    pub fn set_field_metadata(&mut self) {
+        self.flags |= Self::FIELD_METADATA;
+    }
+
+    /// Encode `field_value` into the format used by this hash
+    pub fn encode_field_value(&self, field_value: &HashFieldValue) -> BytesMut {
+        if self.has_field_metadata() {
+            field_value.to_bytes()
+        } else {
+            field_value.value().clone()
+        }
+    }
+
+    /// Decode a field value that was read from the database
+    pub fn decode_field_value(&self, buff: &[u8]) -> Result<HashFieldValue, SableError> {
+        if self.has_field_metadata() {
+            HashFieldValue::from_bytes(buff)
+        } else {
+            Ok(HashFieldValue::new(BytesMut::from(buff)))
+        }
+    }
+
     /// Set the hash ID
     pub fn set_id(&mut self, hash_id: u64) {
         self.common.set_uid(hash_id);
@@ -65,13 +118,29 @@ impl HashValueMetadata {
     pub fn to_bytes(&self, builder: &mut U8ArrayBuilder) {
         self.common.to_bytes(builder);
         builder.write_u64(self.hash_size);
+        builder.write_u8(self.flags);
+        builder.write_u64(self.volatile_size);
     }
 
     pub fn from_bytes(reader: &mut U8ArrayReader) -> Result<Self, SableError> {
         let common = CommonValueMetadata::from_bytes(reader)?;
         let hash_size = reader.read_u64().ok_or(SableError::SerialisationError)?;
 
-        Ok(HashValueMetadata { common, hash_size })
+        // Hashes created before field expiration was supported do not have the flags
+        let (flags, volatile_size) = match reader.read_u8() {
+            Some(flags) => (
+                flags,
+                reader.read_u64().ok_or(SableError::SerialisationError)?,
+            ),
+            None => (0, 0),
+        };
+
+        Ok(HashValueMetadata {
+            common,
+            hash_size,
+            flags,
+            volatile_size,
+        })
     }
 
     /// Create a prefix for iterating all items belonged to this hash
@@ -84,6 +153,79 @@ impl HashValueMetadata {
     }
 }
 
+/// The value of a hash field: `<expire timestamp><value>`
+///
+/// The expiration timestamp is in milliseconds since UNIX_EPOCH, `u64::MAX` means that the field
+/// does not expire
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashFieldValue {
+    expire_timestamp_ms: u64,
+    value: BytesMut,
+}
+
+impl HashFieldValue {
+    /// A field value without an expiration time
+    pub fn new(value: BytesMut) -> Self {
+        HashFieldValue {
+            expire_timestamp_ms: u64::MAX,
+            value,
+        }
+    }
+
+    /// Set the field expiration timestamp (`None` removes it)
+    pub fn with_expire_timestamp_ms(mut self, expire_timestamp_ms: Option<u64>) -> Self {
+        self.expire_timestamp_ms = expire_timestamp_ms.unwrap_or(u64::MAX);
+        self
+    }
+
+    /// Return the field expiration timestamp (millis since UNIX_EPOCH), `None` if the field does
+    /// not expire
+    pub fn expire_timestamp_ms(&self) -> Option<u64> {
+        if self.expire_timestamp_ms == u64::MAX {
+            None
+        } else {
+            Some(self.expire_timestamp_ms)
+        }
+    }
+
+    /// Does this field has an expiration time?
+    pub fn has_ttl(&self) -> bool {
+        self.expire_timestamp_ms != u64::MAX
+    }
+
+    /// Return true if the field expired at `now_ms`
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        self.expire_timestamp_ms <= now_ms
+    }
+
+    pub fn value(&self) -> &BytesMut {
+        &self.value
+    }
+
+    pub fn into_value(self) -> BytesMut {
+        self.value
+    }
+
+    /// Serialise this object into `BytesMut`
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(std::mem::size_of::<u64>() + self.value.len());
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        builder.write_u64(self.expire_timestamp_ms);
+        builder.write_bytes(&self.value);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let expire_timestamp_ms = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let (_, value) = buff.split_at(reader.consumed());
+        Ok(HashFieldValue {
+            expire_timestamp_ms,
+            value: BytesMut::from(value),
+        })
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct HashFieldKey<'a> {
     prefix: KeyPrefix,
@@ -122,6 +264,10 @@ impl<'a> HashFieldKey<'a> {
         })
     }
 
+    pub fn prefix(&self) -> &KeyPrefix {
+        &self.prefix
+    }
+
     pub fn set_hash_id(&mut self, hash_id: u64) {
         self.hash_id = hash_id;
     }
@@ -166,4 +312,47 @@ mod tests {
         assert_eq!(deserialised, hash_item_key);
         Ok(())
     }
+
+    #[test]
+    pub fn test_hash_field_value_serialization() -> Result<(), SableError> {
+        let field_value =
+            HashFieldValue::new(BytesMut::from("value")).with_expire_timestamp_ms(Some(1500));
+        assert!(field_value.has_ttl());
+        assert!(!field_value.is_expired(1499));
+        assert!(field_value.is_expired(1500));
+
+        let mut md = HashValueMetadata::with_id(42);
+        assert!(!md.has_field_metadata());
+        assert_eq!(md.encode_field_value(&field_value), BytesMut::from("value"));
+
+        md.set_field_metadata();
+        let buffer = md.encode_field_value(&field_value);
+        assert_eq!(md.decode_field_value(&buffer)?, field_value);
+
+        let persistent = field_value.with_expire_timestamp_ms(None);
+        assert_eq!(persistent.expire_timestamp_ms(), None);
+        assert!(!persistent.is_expired(u64::MAX - 1));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_legacy_hash_metadata() -> Result<(), SableError> {
+        let md = HashValueMetadata::with_id(42);
+        let mut buffer = BytesMut::new();
+        md.to_bytes(&mut U8ArrayBuilder::with_buffer(&mut buffer));
+        assert_eq!(buffer.len(), HashValueMetadata::SIZE);
+
+        // hashes written before field expiration was added: no flags, raw field values
+        let legacy_len =
+            HashValueMetadata::SIZE - std::mem::size_of::<u8>() - std::mem::size_of::<u64>();
+        let legacy =
+            HashValueMetadata::from_bytes(&mut U8ArrayReader::with_buffer(&buffer[..legacy_len]))?;
+        assert!(!legacy.has_field_metadata());
+        assert_eq!(legacy.id(), 42);
+        assert_eq!(
+            legacy.decode_field_value(b"value")?,
+            HashFieldValue::new(BytesMut::from("value"))
+        );
+        Ok(())
+    }
 }
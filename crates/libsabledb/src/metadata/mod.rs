@@ -1,23 +1,31 @@
+mod bitmap_metadata;
 mod bookkeeping;
 mod delete_range;
 mod encoding;
 mod expiration;
+mod function_metadata;
 mod hash_metadata;
 mod keyprefix;
 mod list_metadata;
 mod lock_metadata;
 mod primary_key_metadata;
 mod set_metadata;
+mod shard_message;
+mod stream_group_metadata;
+mod stream_metadata;
 mod string_value_metadata;
+mod ttl_index;
 mod value_metadata;
 mod zset_metadata;
 
+pub use bitmap_metadata::*;
 pub use bookkeeping::*;
 pub use encoding::*;
 pub use expiration::Expiration;
+pub use function_metadata::*;
 pub use lock_metadata::*;
 
-pub use hash_metadata::{HashFieldKey, HashValueMetadata};
+pub use hash_metadata::{HashFieldKey, HashFieldValue, HashValueMetadata};
 pub use set_metadata::*;
 pub use zset_metadata::*;
 
@@ -26,5 +34,9 @@ pub use keyprefix::*;
 #[allow(unused_imports)]
 pub use list_metadata::ListValueMetadata;
 pub use primary_key_metadata::*;
+pub use shard_message::*;
+pub use stream_group_metadata::*;
+pub use stream_metadata::*;
 pub use string_value_metadata::StringValueMetadata;
+pub use ttl_index::TtlIndexKey;
 pub use value_metadata::CommonValueMetadata;
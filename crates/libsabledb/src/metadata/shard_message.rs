@@ -0,0 +1,89 @@
+use crate::{FromU8Reader, KeyType, SableError, ToU8Writer, U8ArrayBuilder, U8ArrayReader};
+use bytes::BytesMut;
+
+/// Sharded pub/sub messages are not stored in the database, but they must reach the
+/// replicas of the shard. Similar to `DeleteRange`, we place a special record in the
+/// database (put & delete) so the message is written to the WAL and picked up by the replication
+#[derive(Clone, PartialEq, Debug)]
+pub struct ShardMessage {
+    struct_type: KeyType,
+    channel: BytesMut,
+    message: BytesMut,
+}
+
+impl ShardMessage {
+    pub fn new(channel: BytesMut, message: BytesMut) -> Self {
+        ShardMessage {
+            struct_type: KeyType::ShardMessage,
+            channel,
+            message,
+        }
+    }
+
+    pub fn channel(&self) -> &BytesMut {
+        &self.channel
+    }
+
+    pub fn message(&self) -> &BytesMut {
+        &self.message
+    }
+
+    pub fn is_shard_message(buffer: &[u8]) -> bool {
+        if buffer.is_empty() {
+            return false;
+        }
+
+        let mut reader = U8ArrayReader::with_buffer(buffer);
+        let struct_type = KeyType::from_reader(&mut reader).unwrap_or_default();
+        struct_type == KeyType::ShardMessage
+    }
+
+    /// The record key: the struct type followed by the channel
+    pub fn key(&self) -> BytesMut {
+        let mut buffer = BytesMut::default();
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        self.struct_type.to_writer(&mut builder);
+        builder.write_message(&self.channel);
+        buffer
+    }
+
+    /// Construct `ShardMessage` from the record's key and value
+    pub fn from_bytes(key: &[u8], value: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(key);
+        let struct_type =
+            KeyType::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        let channel = reader
+            .read_message()
+            .ok_or(SableError::SerialisationError)?;
+        Ok(ShardMessage {
+            struct_type,
+            channel,
+            message: BytesMut::from(value),
+        })
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_message_serialization() {
+        let msg = ShardMessage::new(BytesMut::from("orders"), BytesMut::from("hello"));
+        let key = msg.key();
+        assert!(ShardMessage::is_shard_message(&key));
+        assert!(!ShardMessage::is_shard_message(b""));
+
+        let decoded = ShardMessage::from_bytes(&key, b"hello").unwrap();
+        assert_eq!(decoded, msg);
+        assert_eq!(decoded.channel(), &BytesMut::from("orders"));
+        assert_eq!(decoded.message(), &BytesMut::from("hello"));
+    }
+}
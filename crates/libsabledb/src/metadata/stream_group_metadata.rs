@@ -0,0 +1,442 @@
+use crate::{
+    metadata::{KeyPrefix, KeyType, StreamId},
+    FromU8Reader, SableError, ToU8Writer, U8ArrayBuilder, U8ArrayReader,
+};
+use bytes::BytesMut;
+
+/// The key of a consumer group: `<prefix><stream UID><group name>`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamGroupKey {
+    prefix: KeyPrefix,
+    stream_id: u64,
+    group_name: BytesMut,
+}
+
+impl StreamGroupKey {
+    pub fn new(stream_id: u64, db_id: u16, slot: u16, group_name: &[u8]) -> Self {
+        StreamGroupKey {
+            prefix: KeyPrefix::new(KeyType::StreamGroup, db_id, slot),
+            stream_id,
+            group_name: BytesMut::from(group_name),
+        }
+    }
+
+    /// Serialise this object into `BytesMut`
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = Self::prefix(self.stream_id, self.prefix.db_id(), self.prefix.key_slot());
+        buffer.extend_from_slice(&self.group_name);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let prefix = KeyPrefix::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        let stream_id = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let group_name = reader.remaining().ok_or(SableError::SerialisationError)?;
+        Ok(StreamGroupKey {
+            prefix,
+            stream_id,
+            group_name,
+        })
+    }
+
+    pub fn group_name(&self) -> &BytesMut {
+        &self.group_name
+    }
+
+    pub fn slot(&self) -> u16 {
+        self.prefix.key_slot()
+    }
+
+    /// Return prefix for iterating over all the groups of a stream
+    pub fn prefix(stream_id: u64, db_id: u16, slot: u16) -> BytesMut {
+        let prefix = KeyPrefix::new(KeyType::StreamGroup, db_id, slot);
+        let mut buffer = BytesMut::with_capacity(KeyPrefix::SIZE + std::mem::size_of::<u64>());
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        prefix.to_writer(&mut builder);
+        stream_id.to_writer(&mut builder);
+        buffer
+    }
+}
+
+/// Contains information about a consumer group
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamGroupMetadata {
+    /// The group unique ID. Used to build the keys of the group PEL and consumers
+    group_id: u64,
+    /// The ID of the last entry delivered to the group consumers
+    last_delivered_id: StreamId,
+    /// Number of entries read by the group
+    entries_read: u64,
+}
+
+impl StreamGroupMetadata {
+    pub const SIZE: usize = 2 * std::mem::size_of::<u64>() + StreamId::SIZE;
+
+    pub fn new(group_id: u64, last_delivered_id: StreamId, entries_read: u64) -> Self {
+        StreamGroupMetadata {
+            group_id,
+            last_delivered_id,
+            entries_read,
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.group_id
+    }
+
+    pub fn last_delivered_id(&self) -> &StreamId {
+        &self.last_delivered_id
+    }
+
+    pub fn set_last_delivered_id(&mut self, last_delivered_id: StreamId) {
+        self.last_delivered_id = last_delivered_id;
+    }
+
+    pub fn entries_read(&self) -> u64 {
+        self.entries_read
+    }
+
+    pub fn set_entries_read(&mut self, entries_read: u64) {
+        self.entries_read = entries_read;
+    }
+
+    pub fn incr_entries_read_by(&mut self, diff: u64) {
+        self.entries_read = self.entries_read.saturating_add(diff);
+    }
+
+    /// Serialise the group metadata into bytes
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(Self::SIZE);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        builder.write_u64(self.group_id);
+        self.last_delivered_id.to_writer(&mut builder);
+        builder.write_u64(self.entries_read);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let group_id = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let last_delivered_id =
+            StreamId::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        let entries_read = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        Ok(StreamGroupMetadata {
+            group_id,
+            last_delivered_id,
+            entries_read,
+        })
+    }
+}
+
+/// The key of a pending entry (PEL): `<prefix><stream UID><group UID><entry ID>`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamPendingKey {
+    prefix: KeyPrefix,
+    stream_id: u64,
+    group_id: u64,
+    entry_id: StreamId,
+}
+
+impl StreamPendingKey {
+    pub const SIZE: usize = KeyPrefix::SIZE + 2 * std::mem::size_of::<u64>() + StreamId::SIZE;
+
+    pub fn new(stream_id: u64, group_id: u64, db_id: u16, slot: u16, entry_id: StreamId) -> Self {
+        StreamPendingKey {
+            prefix: KeyPrefix::new(KeyType::StreamPending, db_id, slot),
+            stream_id,
+            group_id,
+            entry_id,
+        }
+    }
+
+    /// Serialise this object into `BytesMut`
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(Self::SIZE);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        self.prefix.to_writer(&mut builder);
+        self.stream_id.to_writer(&mut builder);
+        self.group_id.to_writer(&mut builder);
+        self.entry_id.to_writer(&mut builder);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let prefix = KeyPrefix::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        let stream_id = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let group_id = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let entry_id = StreamId::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        Ok(StreamPendingKey {
+            prefix,
+            stream_id,
+            group_id,
+            entry_id,
+        })
+    }
+
+    pub fn entry_id(&self) -> &StreamId {
+        &self.entry_id
+    }
+
+    /// Return prefix for iterating over the PEL of a group
+    pub fn prefix(stream_id: u64, group_id: u64, db_id: u16, slot: u16) -> BytesMut {
+        group_child_prefix(KeyType::StreamPending, stream_id, group_id, db_id, slot)
+    }
+}
+
+/// A pending entry: an entry that was delivered to a consumer but not acknowledged yet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamPendingEntry {
+    /// The consumer that owns the entry
+    consumer: BytesMut,
+    /// The last time (milliseconds since UNIX EPOCH) the entry was delivered
+    delivery_time: u64,
+    /// Number of times the entry was delivered
+    delivery_count: u64,
+}
+
+impl StreamPendingEntry {
+    pub fn new(consumer: &[u8], delivery_time: u64, delivery_count: u64) -> Self {
+        StreamPendingEntry {
+            consumer: BytesMut::from(consumer),
+            delivery_time,
+            delivery_count,
+        }
+    }
+
+    pub fn consumer(&self) -> &BytesMut {
+        &self.consumer
+    }
+
+    pub fn set_consumer(&mut self, consumer: &[u8]) {
+        self.consumer = BytesMut::from(consumer);
+    }
+
+    pub fn delivery_time(&self) -> u64 {
+        self.delivery_time
+    }
+
+    pub fn set_delivery_time(&mut self, delivery_time: u64) {
+        self.delivery_time = delivery_time;
+    }
+
+    /// Return the number of milliseconds passed since the entry was last delivered
+    pub fn idle(&self, now: u64) -> u64 {
+        now.saturating_sub(self.delivery_time)
+    }
+
+    pub fn delivery_count(&self) -> u64 {
+        self.delivery_count
+    }
+
+    pub fn set_delivery_count(&mut self, delivery_count: u64) {
+        self.delivery_count = delivery_count;
+    }
+
+    pub fn incr_delivery_count(&mut self) {
+        self.delivery_count = self.delivery_count.saturating_add(1);
+    }
+
+    /// Serialise the pending entry into bytes
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = BytesMut::default();
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        builder.write_u64(self.delivery_time);
+        builder.write_u64(self.delivery_count);
+        builder.write_message(&self.consumer);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let delivery_time = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let delivery_count = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let consumer = reader
+            .read_message()
+            .ok_or(SableError::SerialisationError)?;
+        Ok(StreamPendingEntry {
+            consumer,
+            delivery_time,
+            delivery_count,
+        })
+    }
+}
+
+/// The key of a group consumer: `<prefix><stream UID><group UID><consumer name>`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamConsumerKey {
+    prefix: KeyPrefix,
+    stream_id: u64,
+    group_id: u64,
+    consumer_name: BytesMut,
+}
+
+impl StreamConsumerKey {
+    pub fn new(stream_id: u64, group_id: u64, db_id: u16, slot: u16, consumer_name: &[u8]) -> Self {
+        StreamConsumerKey {
+            prefix: KeyPrefix::new(KeyType::StreamConsumer, db_id, slot),
+            stream_id,
+            group_id,
+            consumer_name: BytesMut::from(consumer_name),
+        }
+    }
+
+    /// Serialise this object into `BytesMut`
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = Self::prefix(
+            self.stream_id,
+            self.group_id,
+            self.prefix.db_id(),
+            self.prefix.key_slot(),
+        );
+        buffer.extend_from_slice(&self.consumer_name);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let prefix = KeyPrefix::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        let stream_id = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let group_id = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let consumer_name = reader.remaining().ok_or(SableError::SerialisationError)?;
+        Ok(StreamConsumerKey {
+            prefix,
+            stream_id,
+            group_id,
+            consumer_name,
+        })
+    }
+
+    pub fn consumer_name(&self) -> &BytesMut {
+        &self.consumer_name
+    }
+
+    /// Return prefix for iterating over the consumers of a group
+    pub fn prefix(stream_id: u64, group_id: u64, db_id: u16, slot: u16) -> BytesMut {
+        group_child_prefix(KeyType::StreamConsumer, stream_id, group_id, db_id, slot)
+    }
+}
+
+/// Contains information about a group consumer
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StreamConsumerMetadata {
+    /// The last time (milliseconds since UNIX EPOCH) the consumer interacted with the group
+    seen_time: u64,
+    /// The last time (milliseconds since UNIX EPOCH) the consumer read or claimed entries
+    active_time: u64,
+}
+
+impl StreamConsumerMetadata {
+    pub const SIZE: usize = 2 * std::mem::size_of::<u64>();
+
+    pub fn new(seen_time: u64, active_time: u64) -> Self {
+        StreamConsumerMetadata {
+            seen_time,
+            active_time,
+        }
+    }
+
+    pub fn seen_time(&self) -> u64 {
+        self.seen_time
+    }
+
+    pub fn active_time(&self) -> u64 {
+        self.active_time
+    }
+
+    /// Serialise the consumer metadata into bytes
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(Self::SIZE);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        builder.write_u64(self.seen_time);
+        builder.write_u64(self.active_time);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let seen_time = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let active_time = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        Ok(StreamConsumerMetadata {
+            seen_time,
+            active_time,
+        })
+    }
+}
+
+/// Sub-items of a group are encoded with: the prefix, the stream UID and the group UID
+fn group_child_prefix(
+    key_type: KeyType,
+    stream_id: u64,
+    group_id: u64,
+    db_id: u16,
+    slot: u16,
+) -> BytesMut {
+    let prefix = KeyPrefix::new(key_type, db_id, slot);
+    let mut buffer = BytesMut::with_capacity(KeyPrefix::SIZE + 2 * std::mem::size_of::<u64>());
+    let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+    prefix.to_writer(&mut builder);
+    stream_id.to_writer(&mut builder);
+    group_id.to_writer(&mut builder);
+    buffer
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_group_keys() -> Result<(), SableError> {
+        let group_key = StreamGroupKey::new(42, 1, 100, b"mygroup");
+        let deserialised = StreamGroupKey::from_bytes(&group_key.to_bytes())?;
+        assert_eq!(deserialised, group_key);
+        assert_eq!(deserialised.group_name(), &BytesMut::from("mygroup"));
+        assert!(group_key
+            .to_bytes()
+            .starts_with(&StreamGroupKey::prefix(42, 1, 100)));
+
+        let pending_key = StreamPendingKey::new(42, 7, 1, 100, StreamId::new(5, 1));
+        let deserialised = StreamPendingKey::from_bytes(&pending_key.to_bytes())?;
+        assert_eq!(deserialised, pending_key);
+        assert!(pending_key
+            .to_bytes()
+            .starts_with(&StreamPendingKey::prefix(42, 7, 1, 100)));
+
+        let consumer_key = StreamConsumerKey::new(42, 7, 1, 100, b"alice");
+        let deserialised = StreamConsumerKey::from_bytes(&consumer_key.to_bytes())?;
+        assert_eq!(deserialised, consumer_key);
+        assert_eq!(deserialised.consumer_name(), &BytesMut::from("alice"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_group_values() -> Result<(), SableError> {
+        let group_md = StreamGroupMetadata::new(7, StreamId::new(5, 1), 3);
+        assert_eq!(
+            StreamGroupMetadata::from_bytes(&group_md.to_bytes())?,
+            group_md
+        );
+
+        let pending = StreamPendingEntry::new(b"alice", 1000, 2);
+        assert_eq!(
+            StreamPendingEntry::from_bytes(&pending.to_bytes())?,
+            pending
+        );
+        assert_eq!(pending.idle(1500), 500);
+
+        let consumer_md = StreamConsumerMetadata::new(1000, 900);
+        assert_eq!(
+            StreamConsumerMetadata::from_bytes(&consumer_md.to_bytes())?,
+            consumer_md
+        );
+        Ok(())
+    }
+}
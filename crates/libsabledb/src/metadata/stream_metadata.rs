@@ -0,0 +1,347 @@
+use crate::{
+    metadata::CommonValueMetadata,
+    metadata::{KeyPrefix, KeyType},
+    BytesMutUtils, Expiration, FromU8Reader, SableError, ToU8Writer, U8ArrayBuilder, U8ArrayReader,
+};
+use bytes::BytesMut;
+
+/// A stream entry ID: `<milliseconds>-<sequence>`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    ms: u64,
+    seq: u64,
+}
+
+impl StreamId {
+    pub const SIZE: usize = 2 * std::mem::size_of::<u64>();
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    pub fn ms(&self) -> u64 {
+        self.ms
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Return the smallest ID that is greater than this ID, or `None` if this is the largest ID
+    pub fn next(&self) -> Option<StreamId> {
+        if self.seq < u64::MAX {
+            Some(StreamId::new(self.ms, self.seq + 1))
+        } else if self.ms < u64::MAX {
+            Some(StreamId::new(self.ms + 1, 0))
+        } else {
+            None
+        }
+    }
+
+    /// Return the largest ID that is smaller than this ID, or `None` if this is the smallest ID
+    pub fn prev(&self) -> Option<StreamId> {
+        if self.seq > 0 {
+            Some(StreamId::new(self.ms, self.seq - 1))
+        } else if self.ms > 0 {
+            Some(StreamId::new(self.ms - 1, u64::MAX))
+        } else {
+            None
+        }
+    }
+
+    /// Parse a stream ID from user input. The sequence part is optional, when it is omitted
+    /// `missing_seq` is used (e.g. `0` for the start of a range and `u64::MAX` for its end)
+    pub fn parse(value: &[u8], missing_seq: u64) -> Option<StreamId> {
+        let value = BytesMutUtils::to_string(value);
+        match value.split_once('-') {
+            Some((ms, seq)) => Some(StreamId::new(ms.parse().ok()?, seq.parse().ok()?)),
+            None => Some(StreamId::new(value.parse().ok()?, missing_seq)),
+        }
+    }
+
+    /// Return the ID formatted as `<ms>-<seq>`
+    pub fn to_bytes(&self) -> BytesMut {
+        BytesMut::from(self.to_string().as_bytes())
+    }
+}
+
+impl std::fmt::Display for StreamId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+impl ToU8Writer for StreamId {
+    fn to_writer(&self, builder: &mut U8ArrayBuilder) {
+        // IDs are written in big endian, so the database order is the same as the IDs order
+        builder.write_u64(self.ms);
+        builder.write_u64(self.seq);
+    }
+}
+
+impl FromU8Reader for StreamId {
+    type Item = StreamId;
+    fn from_reader(reader: &mut U8ArrayReader) -> Option<Self::Item> {
+        Some(StreamId {
+            ms: reader.read_u64()?,
+            seq: reader.read_u64()?,
+        })
+    }
+}
+
+/// Contains information about the stream
+#[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamValueMetadata {
+    common: CommonValueMetadata,
+    /// Number of entries in the stream
+    stream_size: u64,
+    /// The ID of the last entry added to the stream
+    last_id: StreamId,
+    /// The largest ID that was removed from the stream
+    max_deleted_id: StreamId,
+    /// Number of entries ever added to the stream
+    entries_added: u64,
+}
+
+#[allow(dead_code)]
+impl StreamValueMetadata {
+    pub const SIZE: usize =
+        2 * std::mem::size_of::<u64>() + 2 * StreamId::SIZE + CommonValueMetadata::SIZE;
+
+    pub fn with_id(stream_id: u64) -> Self {
+        StreamValueMetadata {
+            common: CommonValueMetadata::default()
+                .set_stream()
+                .with_uid(stream_id),
+            stream_size: 0,
+            last_id: StreamId::MIN,
+            max_deleted_id: StreamId::MIN,
+            entries_added: 0,
+        }
+    }
+
+    pub fn expiration(&self) -> &Expiration {
+        self.common.expiration()
+    }
+
+    pub fn expiration_mut(&mut self) -> &mut Expiration {
+        self.common.expiration_mut()
+    }
+
+    /// Return the number of entries in this stream
+    pub fn len(&self) -> u64 {
+        self.stream_size
+    }
+
+    /// Equivalent to `len() == 0`
+    pub fn is_empty(&self) -> bool {
+        self.stream_size.eq(&0u64)
+    }
+
+    /// Return the stream unique ID
+    pub fn id(&self) -> u64 {
+        self.common.uid()
+    }
+
+    pub fn incr_len_by(&mut self, diff: u64) {
+        self.stream_size = self.stream_size.saturating_add(diff);
+    }
+
+    pub fn decr_len_by(&mut self, diff: u64) {
+        self.stream_size = self.stream_size.saturating_sub(diff);
+    }
+
+    pub fn last_id(&self) -> &StreamId {
+        &self.last_id
+    }
+
+    pub fn set_last_id(&mut self, last_id: StreamId) {
+        self.last_id = last_id;
+    }
+
+    pub fn max_deleted_id(&self) -> &StreamId {
+        &self.max_deleted_id
+    }
+
+    /// Record that `deleted_id` was removed from the stream
+    pub fn update_max_deleted_id(&mut self, deleted_id: StreamId) {
+        if deleted_id > self.max_deleted_id {
+            self.max_deleted_id = deleted_id;
+        }
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn incr_entries_added(&mut self) {
+        self.entries_added = self.entries_added.saturating_add(1);
+    }
+
+    pub fn set_entries_added(&mut self, entries_added: u64) {
+        self.entries_added = entries_added;
+    }
+
+    /// Serialise the stream value metadata into bytes
+    pub fn to_bytes(&self, builder: &mut U8ArrayBuilder) {
+        self.common.to_bytes(builder);
+        builder.write_u64(self.stream_size);
+        self.last_id.to_writer(builder);
+        self.max_deleted_id.to_writer(builder);
+        builder.write_u64(self.entries_added);
+    }
+
+    pub fn from_bytes(reader: &mut U8ArrayReader) -> Result<Self, SableError> {
+        let common = CommonValueMetadata::from_bytes(reader)?;
+        let stream_size = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let last_id = StreamId::from_reader(reader).ok_or(SableError::SerialisationError)?;
+        let max_deleted_id = StreamId::from_reader(reader).ok_or(SableError::SerialisationError)?;
+        let entries_added = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        Ok(StreamValueMetadata {
+            common,
+            stream_size,
+            last_id,
+            max_deleted_id,
+            entries_added,
+        })
+    }
+}
+
+/// The key of a single stream entry: `<prefix><stream UID><entry ID>`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamEntryKey {
+    prefix: KeyPrefix,
+    stream_id: u64,
+    entry_id: StreamId,
+}
+
+impl StreamEntryKey {
+    pub const SIZE: usize = KeyPrefix::SIZE + std::mem::size_of::<u64>() + StreamId::SIZE;
+
+    pub fn new(stream_id: u64, db_id: u16, slot: u16, entry_id: StreamId) -> Self {
+        StreamEntryKey {
+            prefix: KeyPrefix::new(KeyType::StreamItem, db_id, slot),
+            stream_id,
+            entry_id,
+        }
+    }
+
+    /// Serialise this object into `BytesMut`
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(Self::SIZE);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        self.prefix.to_writer(&mut builder);
+        self.stream_id.to_writer(&mut builder);
+        self.entry_id.to_writer(&mut builder);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let prefix = KeyPrefix::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        let stream_id = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let entry_id = StreamId::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        Ok(StreamEntryKey {
+            prefix,
+            stream_id,
+            entry_id,
+        })
+    }
+
+    pub fn stream_id(&self) -> u64 {
+        self.stream_id
+    }
+
+    pub fn entry_id(&self) -> &StreamId {
+        &self.entry_id
+    }
+
+    pub fn key_type(&self) -> &KeyType {
+        self.prefix.key_type()
+    }
+
+    /// Return prefix for iterating over all the stream entries
+    pub fn prefix(stream_id: u64, db_id: u16, slot: u16) -> BytesMut {
+        let prefix = KeyPrefix::new(KeyType::StreamItem, db_id, slot);
+        let mut buffer = BytesMut::with_capacity(KeyPrefix::SIZE + std::mem::size_of::<u64>());
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        prefix.to_writer(&mut builder);
+        stream_id.to_writer(&mut builder);
+        buffer
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("1526919030474-55", 0, Some(StreamId::new(1526919030474, 55)); "full id")]
+    #[test_case("1526919030474", 0, Some(StreamId::new(1526919030474, 0)); "ms only start")]
+    #[test_case("1526919030474", u64::MAX, Some(StreamId::new(1526919030474, u64::MAX)); "ms only end")]
+    #[test_case("abc", 0, None; "not a number")]
+    #[test_case("1-abc", 0, None; "invalid sequence")]
+    #[test_case("-1", 0, None; "negative")]
+    fn test_parse_stream_id(input: &str, missing_seq: u64, expected: Option<StreamId>) {
+        assert_eq!(StreamId::parse(input.as_bytes(), missing_seq), expected);
+    }
+
+    #[test]
+    fn test_stream_id_ordering() {
+        let id = StreamId::new(5, u64::MAX);
+        assert_eq!(id.next(), Some(StreamId::new(6, 0)));
+        assert_eq!(StreamId::new(6, 0).prev(), Some(id));
+        assert_eq!(StreamId::MAX.next(), None);
+        assert_eq!(StreamId::MIN.prev(), None);
+        assert_eq!(id.to_bytes(), BytesMut::from("5-18446744073709551615"));
+
+        // the serialised keys must keep the same order as the IDs
+        let key1 = StreamEntryKey::new(1, 0, 0, StreamId::new(1, 300)).to_bytes();
+        let key2 = StreamEntryKey::new(1, 0, 0, StreamId::new(2, 1)).to_bytes();
+        assert!(key1 < key2);
+    }
+
+    #[test]
+    fn test_stream_entry_key_serialization() -> Result<(), SableError> {
+        let key = StreamEntryKey::new(42, 3, 1000, StreamId::new(1526919030474, 55));
+        let deserialised = StreamEntryKey::from_bytes(&key.to_bytes())?;
+        assert_eq!(deserialised, key);
+        assert_eq!(deserialised.key_type(), &KeyType::StreamItem);
+        assert_eq!(deserialised.stream_id(), 42);
+        assert!(key
+            .to_bytes()
+            .starts_with(&StreamEntryKey::prefix(42, 3, 1000)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_metadata_serialization() -> Result<(), SableError> {
+        let mut md = StreamValueMetadata::with_id(7);
+        md.incr_len_by(3);
+        md.set_last_id(StreamId::new(10, 2));
+        md.update_max_deleted_id(StreamId::new(10, 1));
+        md.incr_entries_added();
+
+        let mut buffer = BytesMut::with_capacity(StreamValueMetadata::SIZE);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        md.to_bytes(&mut builder);
+        assert_eq!(buffer.len(), StreamValueMetadata::SIZE);
+
+        let mut reader = U8ArrayReader::with_buffer(&buffer);
+        assert_eq!(StreamValueMetadata::from_bytes(&mut reader)?, md);
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{
+    CommonValueMetadata, FromU8Reader, KeyType, SableError, ToU8Writer, U8ArrayBuilder,
+    U8ArrayReader,
+};
+use bytes::BytesMut;
+
+/// An entry in the expiration index: `<KeyType::TtlIndex><expire timestamp><record key>`.
+/// The value is empty. The record key is an encoded primary key or an encoded hash field key
+/// (`HashFieldKey`), for hash fields with an expiration time.
+///
+/// The timestamp (milliseconds since UNIX_EPOCH) is written in big endian, so the entries are
+/// ordered by their expiration time. Index records are global: they are not bound to a database or
+/// to a slot (the record key contains both).
+///
+/// Entries are added whenever a record with a TTL is written, but they are never updated: when the
+/// TTL of a record changes (or the record is deleted) the old entry becomes stale. Stale entries
+/// are removed by the expiration sweeper once they are due, after it verifies that the record
+/// does not expire at the entry's timestamp
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TtlIndexKey {
+    expire_timestamp_ms: u64,
+    record_key: BytesMut,
+}
+
+impl TtlIndexKey {
+    pub fn new(expire_timestamp_ms: u64, record_key: &[u8]) -> Self {
+        TtlIndexKey {
+            expire_timestamp_ms,
+            record_key: BytesMut::from(record_key),
+        }
+    }
+
+    /// Given a record (key and value) that is about to be written, return the index entry for it.
+    /// Return `None` if the record is not a primary key or if it has no TTL
+    pub fn for_record(key: &[u8], value: &[u8]) -> Option<Self> {
+        if key.first() != Some(&(KeyType::PrimaryKey as u8)) {
+            return None;
+        }
+        let mut reader = U8ArrayReader::with_buffer(value);
+        let md = CommonValueMetadata::from_bytes(&mut reader).ok()?;
+        let expire_timestamp_ms = md.expiration().expire_timestamp_millis()?;
+        Some(Self::new(expire_timestamp_ms, key))
+    }
+
+    /// The key expiration time, in milliseconds since UNIX_EPOCH
+    pub fn expire_timestamp_ms(&self) -> u64 {
+        self.expire_timestamp_ms
+    }
+
+    /// The encoded key of the record that expires (see `PrimaryKeyMetadata` and `HashFieldKey`)
+    pub fn record_key(&self) -> &BytesMut {
+        &self.record_key
+    }
+
+    /// Serialise this object into `BytesMut`
+    pub fn to_bytes(&self) -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(
+            std::mem::size_of::<u8>() + std::mem::size_of::<u64>() + self.record_key.len(),
+        );
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        KeyType::TtlIndex.to_writer(&mut builder);
+        builder.write_u64(self.expire_timestamp_ms);
+        builder.write_bytes(&self.record_key);
+        buffer
+    }
+
+    pub fn from_bytes(buff: &[u8]) -> Result<Self, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(buff);
+        let key_type = KeyType::from_reader(&mut reader).ok_or(SableError::SerialisationError)?;
+        if key_type != KeyType::TtlIndex {
+            return Err(SableError::SerialisationError);
+        }
+        let expire_timestamp_ms = reader.read_u64().ok_or(SableError::SerialisationError)?;
+        let record_key = reader.remaining().ok_or(SableError::SerialisationError)?;
+        Ok(TtlIndexKey {
+            expire_timestamp_ms,
+            record_key,
+        })
+    }
+
+    /// Return prefix for iterating over the index, from the earliest expiration time
+    pub fn prefix() -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(std::mem::size_of::<u8>());
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        KeyType::TtlIndex.to_writer(&mut builder);
+        buffer
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PrimaryKeyMetadata, StringValueMetadata};
+
+    #[test]
+    fn test_ttl_index_key() -> Result<(), SableError> {
+        let primary_key = PrimaryKeyMetadata::new_primary_key(&BytesMut::from("mykey"), 3);
+        let mut md = StringValueMetadata::new();
+
+        // no TTL, no index entry
+        let mut value = BytesMut::new();
+        md.to_bytes(&mut U8ArrayBuilder::with_buffer(&mut value));
+        assert!(TtlIndexKey::for_record(&primary_key, &value).is_none());
+
+        md.expiration_mut().last_updated = 1000;
+        md.expiration_mut().ttl_ms = 500;
+        let mut value = BytesMut::new();
+        md.to_bytes(&mut U8ArrayBuilder::with_buffer(&mut value));
+        let entry = TtlIndexKey::for_record(&primary_key, &value).unwrap();
+        assert_eq!(entry.expire_timestamp_ms(), 1500);
+        assert_eq!(entry.record_key(), &primary_key);
+
+        // hash fields are indexed by `HashDb`, their values do not hold a `CommonValueMetadata`
+        let mut other_key = primary_key.clone();
+        other_key[0] = KeyType::HashItem as u8;
+        assert!(TtlIndexKey::for_record(&other_key, &value).is_none());
+
+        let buffer = entry.to_bytes();
+        assert!(buffer.starts_with(&TtlIndexKey::prefix()));
+        assert_eq!(TtlIndexKey::from_bytes(&buffer)?, entry);
+
+        // entries are ordered by their expiration time
+        let later = TtlIndexKey::new(1501, &BytesMut::from("a"));
+        assert!(later.to_bytes() > buffer);
+        Ok(())
+    }
+}
@@ -1,11 +1,11 @@
 use crate::{
     metadata::encoding::{FromRaw, ValueType},
-    Expiration, SableError, U8ArrayBuilder, U8ArrayReader,
+    Expiration, SableError, TimeUtils, U8ArrayBuilder, U8ArrayReader,
 };
 
-/// Contains information regarding the value, such as: the value type (Str, Hash, Set etc), the item unique ID and its
-/// expiration information
-#[derive(Clone, Debug, PartialEq, Eq, Default)]
+/// Contains information regarding the value, such as: the value type (Str, Hash, Set etc), the item unique ID, its
+/// expiration information and its last access time
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CommonValueMetadata {
     // u8
     value_encoding: ValueType,
@@ -13,6 +13,20 @@ pub struct CommonValueMetadata {
     unique_id: u64,
     /// Value ttl information
     expiration: Expiration,
+    /// Last access time, ms since UNIX_EPOCH. `0` for values written before the access time
+    /// was tracked
+    last_accessed: u64,
+}
+
+impl Default for CommonValueMetadata {
+    fn default() -> Self {
+        CommonValueMetadata {
+            value_encoding: ValueType::default(),
+            unique_id: 0,
+            expiration: Expiration::default(),
+            last_accessed: TimeUtils::epoch_ms().unwrap_or_default(),
+        }
+    }
 }
 
 impl TryFrom<&[u8]> for CommonValueMetadata {
@@ -25,14 +39,23 @@ impl TryFrom<&[u8]> for CommonValueMetadata {
 
 #[allow(dead_code)]
 impl CommonValueMetadata {
-    pub const SIZE: usize =
-        std::mem::size_of::<ValueType>() + std::mem::size_of::<u64>() + Expiration::SIZE;
+    /// The serialised size of the metadata, as written by `to_bytes`
+    pub const SIZE: usize = std::mem::size_of::<ValueType>()
+        + std::mem::size_of::<u64>()
+        + Expiration::SIZE
+        + std::mem::size_of::<u64>();
+
+    /// Set on the value type byte when the last access time follows the expiration. Values
+    /// written before the access time was tracked do not have it, so the size of the
+    /// serialised metadata must be taken from the reader (`U8ArrayReader::consumed`)
+    const ACCESS_TIME_FLAG: u8 = 1 << 7;
 
     /// Serialise this object into `BytesMut`
     pub fn to_bytes(&self, builder: &mut U8ArrayBuilder) {
-        builder.write_u8(self.value_encoding as u8);
+        builder.write_u8(self.value_encoding as u8 | Self::ACCESS_TIME_FLAG);
         builder.write_u64(self.unique_id);
         self.expiration.to_bytes(builder);
+        builder.write_u64(self.last_accessed);
     }
 
     pub fn from_bytes(reader: &mut U8ArrayReader) -> Result<Self, SableError> {
@@ -40,15 +63,39 @@ impl CommonValueMetadata {
         let unique_id = reader.read_u64().ok_or(SableError::SerialisationError)?;
 
         let expiration = Expiration::from_bytes(reader)?;
-        let value_encoding =
-            ValueType::from_u8(value_type).ok_or(SableError::SerialisationError)?;
+        let last_accessed = if value_type & Self::ACCESS_TIME_FLAG != 0 {
+            reader.read_u64().ok_or(SableError::SerialisationError)?
+        } else {
+            0
+        };
+        let value_encoding = ValueType::from_u8(value_type & !Self::ACCESS_TIME_FLAG)
+            .ok_or(SableError::SerialisationError)?;
         Ok(CommonValueMetadata {
             value_encoding,
             unique_id,
             expiration,
+            last_accessed,
         })
     }
 
+    /// Return the last access time (ms since UNIX_EPOCH), `0` if unknown
+    pub fn last_accessed(&self) -> u64 {
+        self.last_accessed
+    }
+
+    pub fn set_last_accessed(&mut self, last_accessed: u64) {
+        self.last_accessed = last_accessed;
+    }
+
+    /// Return the number of milliseconds elapsed since the last access. Values with an
+    /// unknown access time are reported as accessed now
+    pub fn idle_time_ms(&self) -> Result<u64, SableError> {
+        if self.last_accessed == 0 {
+            return Ok(0);
+        }
+        Ok(TimeUtils::epoch_ms()?.saturating_sub(self.last_accessed))
+    }
+
     pub fn expiration(&self) -> &Expiration {
         &self.expiration
     }
@@ -81,6 +128,14 @@ impl CommonValueMetadata {
         self.value_encoding == ValueType::Zset
     }
 
+    pub fn is_stream(&self) -> bool {
+        self.value_encoding == ValueType::Stream
+    }
+
+    pub fn is_bitmap(&self) -> bool {
+        self.value_encoding == ValueType::Bitmap
+    }
+
     pub fn value_type(&self) -> ValueType {
         self.value_encoding
     }
@@ -115,6 +170,16 @@ impl CommonValueMetadata {
         self
     }
 
+    pub fn set_stream(mut self) -> Self {
+        self.value_encoding = ValueType::Stream;
+        self
+    }
+
+    pub fn set_bitmap(mut self) -> Self {
+        self.value_encoding = ValueType::Bitmap;
+        self
+    }
+
     pub fn with_uid(mut self, id: u64) -> Self {
         self.unique_id = id;
         self
@@ -173,6 +238,7 @@ mod test {
             deserialized_md,
         );
         assert_eq!(deserialized_md.uid(), 1234);
+        assert_eq!(deserialized_md.last_accessed(), md.last_accessed());
         assert!(deserialized_md.expiration().is_expired()? == false);
         assert_eq!(&arr[..], &[5, 5]);
         Ok(())
@@ -200,4 +266,49 @@ mod test {
         assert!(md.expiration().ttl_in_millis()? == 42);
         Ok(())
     }
+
+    #[test]
+    fn test_last_accessed() -> Result<(), SableError> {
+        let mut md = CommonValueMetadata::default();
+        let created = md.last_accessed();
+        assert!(created > 0);
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert!(md.idle_time_ms()? >= 5);
+
+        md.set_last_accessed(TimeUtils::epoch_ms()?);
+        assert!(md.last_accessed() >= created + 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_without_access_time() -> Result<(), SableError> {
+        // a string record, as written before the access time was tracked:
+        // `[value type | uid | expiration | value]`
+        let mut expiration = Expiration::default();
+        expiration.set_ttl_millis(5000)?;
+        let mut arr = bytes::BytesMut::new();
+        let mut builder = U8ArrayBuilder::with_buffer(&mut arr);
+        builder.write_u8(ValueType::Str as u8);
+        builder.write_u64(0);
+        expiration.to_bytes(&mut builder);
+        builder.write_bytes(&bytes::BytesMut::from("value"));
+
+        let mut reader = U8ArrayReader::with_buffer(&arr);
+        let md = CommonValueMetadata::from_bytes(&mut reader)?;
+        assert!(md.is_string());
+        assert_eq!(md.last_accessed(), 0);
+        assert_eq!(md.idle_time_ms()?, 0);
+        assert!(md.expiration().has_ttl());
+        assert_eq!(&arr[reader.consumed()..], b"value");
+
+        // writing it back adds the access time
+        let mut rewritten = bytes::BytesMut::new();
+        let mut builder = U8ArrayBuilder::with_buffer(&mut rewritten);
+        md.to_bytes(&mut builder);
+        assert_eq!(rewritten.len(), CommonValueMetadata::SIZE);
+        let mut reader = U8ArrayReader::with_buffer(&rewritten);
+        assert_eq!(CommonValueMetadata::from_bytes(&mut reader)?, md);
+        Ok(())
+    }
 }
@@ -11,6 +11,7 @@ use std::{println as info, println as debug, println as error};
 
 use crate::{
     io::Archive,
+    metadata::ShardMessage,
     replication::node_talk_client::JoinShardResult,
     replication::{
         node_talk_client::NodeTalkClient, ClusterManager, NodeBuilder, StorageUpdates,
@@ -20,8 +21,8 @@ use crate::{
 };
 use crate::{
     replication::{
-        socket_set_timeout, BytesReader, BytesWriter, NodeResponse, NodeTalkRequest, RequestCommon,
-        ResponseReason, TcpStreamBytesReader, TcpStreamBytesWriter,
+        socket_set_timeout, BytesReader, BytesWriter, CheckpointFile, NodeResponse,
+        NodeTalkRequest, RequestCommon, ResponseReason, TcpStreamBytesReader, TcpStreamBytesWriter,
     },
     storage::SEQUENCES_FILE,
     ReplicationTelemetry,
@@ -34,6 +35,14 @@ use tokio::sync::mpsc::{channel as tokio_channel, error::TryRecvError, Sender as
 
 const OPTIONS_LOCK_ERR: &str = "Failed to obtain read lock on ServerOptions";
 
+/// Incremental fullsync: suffix for checkpoint files that were not completely fetched
+const PARTIAL_FILE_SUFFIX: &str = ".partial";
+
+/// Primaries that predate streaming replication and incremental fullsync close the connection when
+/// they receive these requests. Fall back to the older protocol after the primary closed the
+/// connection this many times in a row, right after receiving such a request
+const MAX_CLOSED_AFTER_REQUEST: usize = 3;
+
 #[allow(dead_code)]
 pub enum NodeTalkCommand {
     Shutdown,
@@ -57,6 +66,23 @@ enum RequestChangesResult {
     FullSync,
     /// Server replied that there are no changes available. Returns the last sequence number
     NoChanges(u64),
+    /// The primary does not support streaming replication, fall back to polling
+    StreamingNotSupported,
+    /// The primary closed the connection right after receiving the subscription request. This is
+    /// how primaries that predate streaming replication reject it, but also what a primary restart
+    /// looks like
+    SubscriptionClosed,
+}
+
+#[derive(Debug, PartialEq)]
+enum IncrementalFullSyncResult {
+    /// The database was restored from the primary checkpoint
+    Done,
+    /// The primary rejected the `CheckpointManifest` request
+    NotSupported,
+    /// The primary closed the connection right after receiving the `CheckpointManifest` request
+    /// (see `RequestChangesResult::SubscriptionClosed`)
+    ManifestClosed,
 }
 
 /// A client side replication loop.
@@ -86,6 +112,10 @@ impl ClientReplicationLoop {
                 let primary_address = Server::state().persistent_state().primary_address();
                 tracing::info!("Connecting to primary at: {}", primary_address);
                 let cm = ClusterManager::with_options(options.clone());
+                let mut streaming = options.read().expect(OPTIONS_LOCK_ERR).replication_limits.streaming;
+                let mut incremental_fullsync = true;
+                let mut subscription_closed = 0usize;
+                let mut manifest_closed = 0usize;
                 'client_loop: loop {
                     let mut client = NodeTalkClient::default();
                     // Loop until we manage to connect
@@ -114,18 +144,27 @@ impl ClientReplicationLoop {
                     }
                     
                     // The first thing a replication does is sending a "join shard"
-                    // request to the primary. On success, the primary assigns this replica with
-                    // a node-id (a unique number to the shard!)
+                    // request to the upstream node. The upstream node is either the shard primary
+                    // or another replica (cascading replication). On success, it replies with its
+                    // own node-id and the shard primary node-id
                     match client.join_shard() {
-                        Ok(JoinShardResult::Ok{ cluster_name, shard_name, node_id }) => {
-                            // Store the primary's node ID (this also changes the role to ServerRole::Replica)
+                        Ok(JoinShardResult::Ok{ cluster_name, shard_name, node_id, primary_node_id }) => {
                             info!(
-                                "Successfully joined cluster: '{}', shard: '{}'",
-                                cluster_name, shard_name
+                                "Successfully joined cluster: '{}', shard: '{}'. Upstream: {}, primary: {}",
+                                cluster_name, shard_name, node_id, primary_node_id
                             );
+                            // An upstream replica that did not join its own upstream yet does not know
+                            // who the primary is. Use the upstream node ID until the next join
+                            let primary_node_id = if primary_node_id.is_empty() {
+                                node_id.clone()
+                            } else {
+                                primary_node_id
+                            };
                             let pstate = Server::state();
                             let pstate = pstate.persistent_state();
-                            pstate.set_primary_node_id(Some(node_id));
+                            // Store the primary's node ID (this also changes the role to ServerRole::Replica)
+                            pstate.set_primary_node_id(Some(primary_node_id));
+                            pstate.set_upstream_node_id(node_id);
                             pstate.set_shard_name(shard_name);
                             pstate.set_cluster_name(cluster_name);
                             pstate.save();
@@ -146,7 +185,8 @@ impl ClientReplicationLoop {
                     
                     // This is the replication main loop:
                     // We continuously calling `request_changes` from the primary
-                    // and store them in our database
+                    // and store them in our database. In streaming mode, we subscribe
+                    // to the changes and the primary pushes them as they are committed
                     let mut request_id = 0u64;
                     loop {
                         if let Err(e) = cm.fail_over_if_needed(&store).await {
@@ -155,8 +195,14 @@ impl ClientReplicationLoop {
 
                         let mut reader = TcpStreamBytesReader::new(stream);
                         let mut writer = TcpStreamBytesWriter::new(stream);
-                        let result =
-                            Self::request_changes(&store, &cm, options.clone(), &mut reader, &mut writer, &mut rx, &mut request_id);
+                        let result = if streaming {
+                            Self::subscribe_changes(&store, &cm, options.clone(), &mut reader, &mut writer, &mut rx, &mut request_id)
+                        } else {
+                            Self::request_changes(&store, &cm, options.clone(), &mut reader, &mut writer, &mut rx, &mut request_id)
+                        };
+                        if !matches!(result, RequestChangesResult::SubscriptionClosed) {
+                            subscription_closed = 0;
+                        }
                         match result {
                             RequestChangesResult::Success(sequence_number)
                             | RequestChangesResult::NoChanges(sequence_number) => {
@@ -169,6 +215,24 @@ impl ClientReplicationLoop {
                                     tracing::warn!("(run) error while updating cluster manager. {:?}", e);
                                 }
                             }
+                            RequestChangesResult::StreamingNotSupported => {
+                                tracing::warn!("Primary does not support streaming replication. Falling back to polling");
+                                streaming = false;
+                                let _ = stream.shutdown(std::net::Shutdown::Both);
+                                break;
+                            }
+                            RequestChangesResult::SubscriptionClosed => {
+                                subscription_closed = subscription_closed.saturating_add(1);
+                                if subscription_closed >= MAX_CLOSED_AFTER_REQUEST {
+                                    tracing::warn!(
+                                        "Primary closed the connection {} times after a subscription request. Falling back to polling",
+                                        subscription_closed
+                                    );
+                                    streaming = false;
+                                }
+                                let _ = stream.shutdown(std::net::Shutdown::Both);
+                                break;
+                            }
                             RequestChangesResult::Reconnect => {
                                 info!("Closing connection with primary: {:?}", stream);
                                 let _ = stream.shutdown(std::net::Shutdown::Both);
@@ -180,6 +244,41 @@ impl ClientReplicationLoop {
                                 let _ = stream.shutdown(std::net::Shutdown::Both);
                                 break 'client_loop;
                             }
+                            RequestChangesResult::FullSync if incremental_fullsync => {
+                                // Try to do an incremental fullsync. On error, the files
+                                // received so far are kept and the next attempt continues from there
+                                match Self::incremental_fullsync(&store, &cm, options.clone(), stream, &mut request_id).await {
+                                    Ok(IncrementalFullSyncResult::Done) => {
+                                        manifest_closed = 0;
+                                    }
+                                    Ok(IncrementalFullSyncResult::NotSupported) => {
+                                        tracing::warn!("Primary does not support incremental fullsync. Falling back to a full checkpoint transfer");
+                                        incremental_fullsync = false;
+                                        let _ = stream.shutdown(std::net::Shutdown::Both);
+                                        break;
+                                    }
+                                    Ok(IncrementalFullSyncResult::ManifestClosed) => {
+                                        manifest_closed = manifest_closed.saturating_add(1);
+                                        if manifest_closed >= MAX_CLOSED_AFTER_REQUEST {
+                                            tracing::warn!(
+                                                "Primary closed the connection {} times after a checkpoint manifest request. Falling back to a full checkpoint transfer",
+                                                manifest_closed
+                                            );
+                                            incremental_fullsync = false;
+                                        }
+                                        let _ = stream.shutdown(std::net::Shutdown::Both);
+                                        break;
+                                    }
+                                    Err(e) => {
+                                        manifest_closed = 0;
+                                        error!("Incremental fullsync error. {:?}", e);
+                                        let _ = stream.shutdown(std::net::Shutdown::Both);
+                                        // Sleep a bit before retrying
+                                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+                                        break;
+                                    }
+                                }
+                            }
                             RequestChangesResult::FullSync => {
                                 // Try to do a fullsync
                                 if let Err(e)
@@ -289,6 +388,266 @@ impl ClientReplicationLoop {
         Ok(())
     }
 
+    /// Perform an incremental fullsync with the primary:
+    ///
+    /// 1. Ask the primary to create a checkpoint and send back the list of its files
+    /// 2. Fetch the files into the staging folder. Files that were already fetched by a previous
+    ///    (interrupted) fullsync are skipped and partially fetched files are resumed
+    /// 3. Restore the database from a copy of the staging folder. The staging folder is kept, so the
+    ///    next fullsync only fetches the files that changed since
+    async fn incremental_fullsync(
+        store: &StorageAdapter,
+        cm: &ClusterManager,
+        options: Arc<StdRwLock<ServerOptions>>,
+        stream: &mut std::net::TcpStream,
+        request_id: &mut u64,
+    ) -> Result<IncrementalFullSyncResult, SableError> {
+        socket_set_timeout(stream)?;
+
+        let request =
+            NodeTalkRequest::CheckpointManifest(RequestCommon::new().with_request_id(request_id));
+        info!("Sending {} message to primary", request);
+        Self::write_request(stream, &request)?;
+
+        let files = match Self::read_replication_message(&mut TcpStreamBytesReader::new(stream)) {
+            Ok(NodeResponse::CheckpointManifest { common: _, files }) => files,
+            Ok(NodeResponse::NotOk(common))
+                if *common.reason() == ResponseReason::UnknownRequest =>
+            {
+                info!(
+                    "Primary does not know the CheckpointManifest request. {}",
+                    common
+                );
+                return Ok(IncrementalFullSyncResult::NotSupported);
+            }
+            Ok(response) => {
+                return Err(SableError::ProtocolError(format!(
+                    "Expected ReplicationResponse::CheckpointManifest. Got: {}",
+                    response
+                )));
+            }
+            Err(SableError::BincodeSerializationErr(e)) => {
+                error!("Could not decode the checkpoint manifest. {:?}", e);
+                return Ok(IncrementalFullSyncResult::NotSupported);
+            }
+            Err(SableError::ConnectionClosed) => {
+                info!("Primary closed the connection after the checkpoint manifest request");
+                return Ok(IncrementalFullSyncResult::ManifestClosed);
+            }
+            Err(e) => return Err(e),
+        };
+
+        let (staging_path, restore_path, sequence_file) = {
+            let opts = options.read().expect(OPTIONS_LOCK_ERR);
+            let staging_path =
+                PathBuf::from(format!("{}.fullsync", opts.open_params.db_path.display()));
+            let restore_path = PathBuf::from(format!(
+                "{}.fullsync.restore",
+                opts.open_params.db_path.display()
+            ));
+            let sequence_file = opts.open_params.db_path.join(SEQUENCES_FILE);
+            (staging_path, restore_path, sequence_file)
+        };
+
+        std::fs::create_dir_all(&staging_path)?;
+        Self::remove_stale_checkpoint_files(&staging_path, &files)?;
+
+        let total_bytes: u64 = files.iter().map(|file| file.size).sum();
+        info!(
+            "Fetching {} checkpoint files ({} bytes) into {}",
+            files.len(),
+            total_bytes.to_formatted_string(&Locale::en),
+            staging_path.display()
+        );
+        for file in &files {
+            Self::fetch_checkpoint_file(stream, &staging_path, file, request_id)?;
+        }
+
+        // Let the primary know that it can delete the checkpoint
+        let request =
+            NodeTalkRequest::CheckpointDone(RequestCommon::new().with_request_id(request_id));
+        Self::write_request(stream, &request)?;
+        let NodeResponse::Ok(_) =
+            Self::read_replication_message(&mut TcpStreamBytesReader::new(stream))?
+        else {
+            return Err(SableError::ProtocolError(
+                "Expected ReplicationResponse::Ok".to_string(),
+            ));
+        };
+
+        // Opening the checkpoint modifies its folder, restore from a copy
+        Self::copy_checkpoint_files(&staging_path, &restore_path, &files)?;
+
+        let _unused = crate::LockManager::lock_all_keys_shared().await?;
+        info!("Database is now locked (read-only mode)");
+        info!("Loading database from checkpoint...");
+        let result = store.restore_from_checkpoint(&restore_path, true);
+        let _ = std::fs::remove_dir_all(&restore_path);
+        result?;
+        info!("Database successfully restored from checkpoint");
+
+        let mut last_txn_id = 0u64;
+        if let Some(seq) = Self::read_next_sequence(sequence_file) {
+            ReplicationTelemetry::set_last_change(seq);
+            last_txn_id = seq;
+        }
+
+        // Update the cluster manager with the current info
+        if let Err(e) = cm.put_node(NodeBuilder::default().with_last_txn_id(last_txn_id).build()) {
+            tracing::warn!(
+                "(incremental_fullsync) error while updating cluster manager. {:?}",
+                e
+            );
+        }
+
+        Ok(IncrementalFullSyncResult::Done)
+    }
+
+    /// Copy the checkpoint `files` from `staging_path` into a fresh `restore_path` folder. SST files
+    /// are never modified, so they are hard-linked when possible
+    fn copy_checkpoint_files(
+        staging_path: &std::path::Path,
+        restore_path: &std::path::Path,
+        files: &[CheckpointFile],
+    ) -> Result<(), SableError> {
+        let _ = std::fs::remove_dir_all(restore_path);
+        std::fs::create_dir_all(restore_path)?;
+        for file in files {
+            let source = staging_path.join(&file.name);
+            let target = restore_path.join(&file.name);
+            if file.name.ends_with(".sst") && std::fs::hard_link(&source, &target).is_ok() {
+                continue;
+            }
+            std::fs::copy(&source, &target)?;
+        }
+        Ok(())
+    }
+
+    /// Fetch the checkpoint file `file` from the primary into `staging_path`. If the file was
+    /// already fetched, do nothing. If it was partially fetched, fetch the remainder
+    fn fetch_checkpoint_file(
+        stream: &mut std::net::TcpStream,
+        staging_path: &std::path::Path,
+        file: &CheckpointFile,
+        request_id: &mut u64,
+    ) -> Result<(), SableError> {
+        if std::path::Path::new(&file.name)
+            .file_name()
+            .is_none_or(|file_name| file_name != file.name.as_str())
+        {
+            return Err(SableError::ProtocolError(format!(
+                "Invalid checkpoint file name: {}",
+                file.name
+            )));
+        }
+
+        let target = staging_path.join(&file.name);
+        if target.is_file() {
+            if crate::io::file_checksum(&target)? == file.checksum {
+                debug!("Checkpoint file {} already exists, skipping it", file.name);
+                return Ok(());
+            }
+            std::fs::remove_file(&target)?;
+        }
+
+        let partial = staging_path.join(format!("{}{}", file.name, PARTIAL_FILE_SUFFIX));
+        let offset = match std::fs::metadata(&partial) {
+            Ok(metadata) if metadata.len() <= file.size => metadata.len(),
+            Ok(_) => {
+                std::fs::remove_file(&partial)?;
+                0
+            }
+            Err(_) => 0,
+        };
+
+        if offset > 0 {
+            info!(
+                "Resuming checkpoint file {} from offset {}",
+                file.name, offset
+            );
+        }
+
+        Self::fetch_checkpoint_file_from(stream, &partial, file, offset, request_id)?;
+        if crate::io::file_checksum(&partial)? != file.checksum {
+            // The content we had does not match the primary's file, start from scratch
+            std::fs::remove_file(&partial)?;
+            if offset == 0 {
+                return Err(SableError::Corrupted(format!(
+                    "Checksum mismatch for checkpoint file {}",
+                    file.name
+                )));
+            }
+            tracing::warn!(
+                "Checksum mismatch for checkpoint file {}. Fetching it again",
+                file.name
+            );
+            return Self::fetch_checkpoint_file(stream, staging_path, file, request_id);
+        }
+
+        std::fs::rename(&partial, &target)?;
+        Ok(())
+    }
+
+    /// Request the checkpoint file `file`, starting at `offset`, and append it to `filepath`
+    fn fetch_checkpoint_file_from(
+        stream: &mut std::net::TcpStream,
+        filepath: &std::path::Path,
+        file: &CheckpointFile,
+        offset: u64,
+        request_id: &mut u64,
+    ) -> Result<u64, SableError> {
+        let request = NodeTalkRequest::GetCheckpointFile {
+            common: RequestCommon::new().with_request_id(request_id),
+            name: file.name.clone(),
+            offset,
+        };
+        debug!("Sending {} message to primary", request);
+        Self::write_request(stream, &request)?;
+
+        match Self::read_replication_message(&mut TcpStreamBytesReader::new(stream))? {
+            NodeResponse::Ok(_) => {}
+            response => {
+                return Err(SableError::ProtocolError(format!(
+                    "Failed to fetch checkpoint file {}. {}",
+                    file.name, response
+                )));
+            }
+        }
+        crate::io::recv_file_append(filepath, stream)
+    }
+
+    fn write_request(
+        stream: &std::net::TcpStream,
+        request: &NodeTalkRequest,
+    ) -> Result<(), SableError> {
+        let mut buffer = bincode_to_bytesmut!(request);
+        TcpStreamBytesWriter::new(stream).write_message(&mut buffer)
+    }
+
+    /// Remove files (and partially fetched files) from the staging folder that are not part
+    /// of the checkpoint
+    fn remove_stale_checkpoint_files(
+        staging_path: &std::path::Path,
+        files: &[CheckpointFile],
+    ) -> Result<(), SableError> {
+        let names: std::collections::HashSet<&str> =
+            files.iter().map(|file| file.name.as_str()).collect();
+        for entry in std::fs::read_dir(staging_path)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let base_name = name.strip_suffix(PARTIAL_FILE_SUFFIX).unwrap_or(&name);
+            if !entry.file_type()?.is_file() || !names.contains(base_name) {
+                debug!("Removing stale checkpoint file {}", name);
+                if entry.file_type()?.is_dir() {
+                    std::fs::remove_dir_all(entry.path())?;
+                } else {
+                    std::fs::remove_file(entry.path())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn read_replication_message(reader: &mut dyn BytesReader) -> Result<NodeResponse, SableError> {
         // Read the request (this is a fixed size request)
         loop {
@@ -430,7 +789,162 @@ impl ClientReplicationLoop {
         };
 
         info!("Received changes updates: {}", storage_updates);
+        Self::apply_changes(store, cm, &storage_updates, common.node_id(), sequence_file)
+    }
 
+    /// Subscribe to the changes committed on the primary (streaming replication):
+    ///
+    /// 1. Read the next sequence number to fetch from the primary
+    /// 2. Send a `SubscribeUpdatesSince` to the primary
+    /// 3. From now on, the primary pushes the changes as they are committed. We store each
+    ///    change set in our storage, update the `changes.seq` file and acknowledge it
+    ///
+    /// This function returns only on error, when a fullsync is required or when requested
+    /// to shutdown
+    fn subscribe_changes(
+        store: &StorageAdapter,
+        cm: &ClusterManager,
+        options: Arc<StdRwLock<ServerOptions>>,
+        reader: &mut impl BytesReader,
+        writer: &mut impl BytesWriter,
+        rx: &mut tokio::sync::mpsc::Receiver<NodeTalkCommand>,
+        request_id: &mut u64,
+    ) -> RequestChangesResult {
+        let sequence_file = options
+            .read()
+            .expect(OPTIONS_LOCK_ERR)
+            .open_params
+            .db_path
+            .join(SEQUENCES_FILE);
+        let Some(from_sequence) = Self::read_next_sequence(sequence_file.clone()) else {
+            return RequestChangesResult::ExitThread;
+        };
+
+        let request = NodeTalkRequest::SubscribeUpdatesSince {
+            common: RequestCommon::new().with_request_id(request_id),
+            from_sequence,
+        };
+
+        let mut buffer = bincode_to_bytesmut_or!(request, RequestChangesResult::ExitThread);
+        if let Err(e) = writer.write_message(&mut buffer) {
+            error!("Failed to send replication request. {:?}", e);
+            return RequestChangesResult::ExitThread;
+        };
+
+        info!("Successfully sent request: {} to primary", request);
+
+        let common = match Self::read_replication_message(reader) {
+            Err(SableError::BincodeSerializationErr(e)) => {
+                error!(
+                    "subscribe_changes: could not decode the primary reply. {:?}",
+                    e
+                );
+                return RequestChangesResult::StreamingNotSupported;
+            }
+            Err(SableError::ConnectionClosed) => {
+                info!("subscribe_changes: primary closed the connection");
+                return RequestChangesResult::SubscriptionClosed;
+            }
+            Err(e) => {
+                error!(
+                    "subscribe_changes: error reading replication message. {:?}",
+                    e
+                );
+                return RequestChangesResult::Reconnect;
+            }
+            Ok(NodeResponse::NotOk(common))
+                if *common.reason() == ResponseReason::UnknownRequest =>
+            {
+                info!(
+                    "Primary does not know the SubscribeUpdatesSince request. {}",
+                    common
+                );
+                return RequestChangesResult::StreamingNotSupported;
+            }
+            Ok(NodeResponse::Ok(common)) => {
+                debug!("Got response: {}", common);
+                common
+            }
+            Ok(NodeResponse::NotOk(common)) => {
+                // the requested sequence was is not acceptable by the server
+                // do a full sync
+                info!(
+                    "Failed to subscribe to changes. Requesting fullsync. {}",
+                    common
+                );
+                return RequestChangesResult::FullSync;
+            }
+            Ok(e) => {
+                info!(
+                    "Received an unexpected response to SubscribeUpdatesSince command. {:?}",
+                    e
+                );
+                return RequestChangesResult::ExitThread;
+            }
+        };
+
+        loop {
+            let buffer = match reader.read_message() {
+                Ok(None) => {
+                    // Timeout occurred, this is a good time to check the channel for commands
+                    match Self::check_command_channel(rx) {
+                        CheckShutdownResult::Terminate => {
+                            return RequestChangesResult::ExitThread;
+                        }
+                        CheckShutdownResult::Timeout => {
+                            continue;
+                        }
+                        CheckShutdownResult::Err(e) => {
+                            error!("Error occurred while reading from channel. {:?}", e);
+                            return RequestChangesResult::ExitThread;
+                        }
+                    }
+                }
+                Ok(Some(buffer)) => buffer,
+                Err(e) => {
+                    error!("Error reading replication stream. {:?}", e);
+                    return RequestChangesResult::Reconnect;
+                }
+            };
+
+            let Some(storage_updates) = StorageUpdates::from_bytes(&buffer) else {
+                error!("Failed to deserialise `StorageUpdats` from bytes");
+                return RequestChangesResult::ExitThread;
+            };
+
+            debug!("Received changes updates: {}", storage_updates);
+            let sequence_number = match Self::apply_changes(
+                store,
+                cm,
+                &storage_updates,
+                common.node_id(),
+                sequence_file.clone(),
+            ) {
+                RequestChangesResult::Success(sequence_number) => sequence_number,
+                other => return other,
+            };
+
+            // Let the primary know that the changes were applied
+            let ack = NodeTalkRequest::UpdatesAck {
+                common: RequestCommon::new().with_request_id(request_id),
+                sequence_number,
+            };
+            let mut buffer = bincode_to_bytesmut_or!(ack, RequestChangesResult::ExitThread);
+            if let Err(e) = writer.write_message(&mut buffer) {
+                error!("Failed to acknowledge changes. {:?}", e);
+                return RequestChangesResult::Reconnect;
+            };
+        }
+    }
+
+    /// Store the changes received from the primary and update the `changes.seq` file
+    fn apply_changes(
+        store: &StorageAdapter,
+        cm: &ClusterManager,
+        storage_updates: &StorageUpdates,
+        primary_node_id: &str,
+        sequence_file: PathBuf,
+    ) -> RequestChangesResult {
         // Keep the "next sequence number" to fetch from the primary
         let sequence_number = storage_updates.end_seq_number;
         let changes_count = storage_updates.changes_count;
@@ -440,6 +954,15 @@ impl ClientReplicationLoop {
         let mut reader = U8ArrayReader::with_buffer(&storage_updates.serialised_data);
         while let Some(change) = StorageUpdates::next(&mut reader) {
             match change {
+                StorageUpdatesRecord::Put { key, value }
+                    if ShardMessage::is_shard_message(&key) =>
+                {
+                    // Sharded pub/sub message, deliver it to the local subscribers
+                    Self::publish_shard_message(&key, &value);
+                }
+                StorageUpdatesRecord::Del { key } if ShardMessage::is_shard_message(&key) => {
+                    // The marker record is never stored, nothing to delete
+                }
                 StorageUpdatesRecord::Put { key, value } => {
                     batch_update.put(key, value);
                 }
@@ -474,7 +997,7 @@ impl ClientReplicationLoop {
         // Make sure that the global state is configured with the correct primary node ID
         Server::state()
             .persistent_state()
-            .set_primary_node_id(Some(common.node_id().to_string()));
+            .set_primary_node_id(Some(primary_node_id.to_string()));
 
         if let Err(e) = cm.put_node(
             NodeBuilder::default()
@@ -482,13 +1005,46 @@ impl ClientReplicationLoop {
                 .build(),
         ) {
             tracing::warn!(
-                "(request_changes) error while updating cluster manager. {:?}",
+                "(apply_changes) error while updating cluster manager. {:?}",
                 e
             );
         }
         Self::write_next_sequence(sequence_file, sequence_number)
     }
 
+    /// Deliver a sharded pub/sub message, replicated from the primary, to the subscribers
+    /// connected to this replica
+    fn publish_shard_message(key: &[u8], value: &[u8]) {
+        let shard_message = match ShardMessage::from_bytes(key, value) {
+            Ok(shard_message) => shard_message,
+            Err(e) => {
+                error!("Failed to decode shard message. {:?}", e);
+                return;
+            }
+        };
+
+        let server_state = Server::state();
+        let result = server_state
+            .pubsub()
+            .spublish(shard_message.channel(), shard_message.message());
+        if result.slow_clients.is_empty() {
+            return;
+        }
+
+        // The clients are owned by the workers, ask them to close the slow subscribers
+        tokio::spawn(async move {
+            for client_id in result.slow_clients {
+                tracing::warn!(
+                    "Subscriber {} can't keep up with the published messages. Closing its connection",
+                    client_id
+                );
+                if let Err(e) = server_state.terminate_client(client_id).await {
+                    tracing::warn!("Failed to terminate client {}. {:?}", client_id, e);
+                }
+            }
+        });
+    }
+
     /// Read the next sequence to get from the primary from the file system.
     /// If the file does not exist, return `Some(0)`. Else return the parsed value
     /// or `None` in case of any other error
@@ -665,6 +1221,135 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_replication_streaming_flow() -> Result<(), SableError> {
+        use crate::storage::GetChangesLimits;
+        use std::rc::Rc;
+
+        // Subscribe to the primary changes: the primary accepts the subscription and pushes
+        // a single change set. The replica applies it and acknowledges it
+        let primary_db = create_database("replication_primary.3", true)?;
+        let replica_db = create_database("replication_replica.3", false)?;
+
+        let mut writer = SimpleBytesWriter::default();
+        let mut reader = StorageUpdatesBytesReader::default();
+
+        let mut request_id = 1u64;
+        let req = RequestCommon::new().with_request_id(&mut request_id);
+        reader.add_response(bincode_to_bytesmut!(NodeResponse::Ok(
+            ResponseCommon::new(&req).with_reason(ResponseReason::Invalid)
+        )));
+
+        let limits = Rc::new(GetChangesLimits::builder().build());
+        let storage_updates = primary_db.storage_updates_since(0, limits)?;
+        reader.add_response(storage_updates.to_bytes());
+
+        // Once there are no more changes, the replica checks the channel and exits
+        let (tx, mut rx) = tokio_channel::<NodeTalkCommand>(100);
+        tx.try_send(NodeTalkCommand::Shutdown).unwrap();
+
+        let server_options = Arc::new(StdRwLock::new(ServerOptions::default()));
+        server_options.write().unwrap().open_params = replica_db.open_params().clone();
+
+        let cm = ClusterManager::with_options(server_options.clone());
+        let mut request_id = 1u64;
+        let res = ClientReplicationLoop::subscribe_changes(
+            &replica_db,
+            &cm,
+            server_options,
+            &mut reader,
+            &mut writer,
+            &mut rx,
+            &mut request_id,
+        );
+        assert_eq!(res, RequestChangesResult::ExitThread);
+        verify_all_records_exist(&replica_db)?;
+
+        // The replica sent the subscription followed by an acknowledgement
+        assert_eq!(writer.buffers.len(), 2);
+        let ack = bincode::deserialize::<NodeTalkRequest>(&writer.buffers[1]).unwrap();
+        let NodeTalkRequest::UpdatesAck {
+            common: _,
+            sequence_number,
+        } = ack
+        else {
+            panic!("expected UpdatesAck");
+        };
+        assert_eq!(sequence_number, storage_updates.end_seq_number);
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_stale_checkpoint_files() -> Result<(), SableError> {
+        let staging_path = PathBuf::from("tests/test_remove_stale_checkpoint_files.fullsync");
+        let _ = std::fs::remove_dir_all(&staging_path);
+        std::fs::create_dir_all(&staging_path)?;
+
+        for name in [
+            "000010.sst",
+            "000011.sst.partial",
+            "000009.sst",
+            "000008.sst.partial",
+        ] {
+            std::fs::write(staging_path.join(name), "content")?;
+        }
+
+        let files: Vec<CheckpointFile> = ["000010.sst", "000011.sst", "000012.sst"]
+            .iter()
+            .map(|name| CheckpointFile {
+                name: name.to_string(),
+                size: 7,
+                checksum: String::default(),
+            })
+            .collect();
+        ClientReplicationLoop::remove_stale_checkpoint_files(&staging_path, &files)?;
+
+        // Files (and partially fetched files) that are part of the checkpoint are kept
+        let mut names: Vec<String> = std::fs::read_dir(&staging_path)?
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["000010.sst", "000011.sst.partial"]);
+        let _ = std::fs::remove_dir_all(&staging_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_checkpoint_files() -> Result<(), SableError> {
+        let staging_path = PathBuf::from("tests/test_copy_checkpoint_files.fullsync");
+        let restore_path = PathBuf::from("tests/test_copy_checkpoint_files.fullsync.restore");
+        let _ = std::fs::remove_dir_all(&staging_path);
+        std::fs::create_dir_all(&staging_path)?;
+
+        let files: Vec<CheckpointFile> = ["000010.sst", "MANIFEST-000005", "CURRENT"]
+            .iter()
+            .map(|name| CheckpointFile {
+                name: name.to_string(),
+                size: 7,
+                checksum: String::default(),
+            })
+            .collect();
+        for file in &files {
+            std::fs::write(staging_path.join(&file.name), "content")?;
+        }
+        std::fs::create_dir_all(&restore_path)?;
+        std::fs::write(restore_path.join("stale"), "content")?;
+
+        ClientReplicationLoop::copy_checkpoint_files(&staging_path, &restore_path, &files)?;
+
+        // The restore folder holds exactly the checkpoint files, the staging folder is untouched
+        for path in [&staging_path, &restore_path] {
+            let mut names: Vec<String> = std::fs::read_dir(path)?
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            assert_eq!(names, vec!["000010.sst", "CURRENT", "MANIFEST-000005"]);
+        }
+        let _ = std::fs::remove_dir_all(&staging_path);
+        let _ = std::fs::remove_dir_all(&restore_path);
+        Ok(())
+    }
+
     #[derive(Default)]
     struct StorageUpdatesBytesReader {
         buffers: std::collections::VecDeque<BytesMut>,
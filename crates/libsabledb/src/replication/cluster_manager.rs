@@ -217,6 +217,16 @@ impl ClusterManager {
             return Ok(());
         };
 
+        // A cascading replica monitors the replica it replicates from, the shard primary
+        // is monitored by the replicas that are directly connected to it
+        let shard_nodes = db.shard_nodes(&shard)?;
+        if let Some(upstream) = Self::cascading_upstream(&shard_nodes) {
+            if self.is_node_alive(&upstream)? {
+                return Ok(());
+            }
+            return self.reattach_to_new_upstream(&db, &shard_nodes, &upstream, &old_primary);
+        }
+
         if self.is_node_alive(&old_primary)? {
             return Ok(());
         }
@@ -226,23 +236,30 @@ impl ClusterManager {
 
         // This instance will be the orchestrator of the failover
         tracing::info!("Listing replicas...");
-        let mut all_replicas = db.shard_nodes(&shard)?;
+        let mut all_replicas = shard_nodes;
 
         // Keep only replicas
         all_replicas.retain(|node| node.is_replica());
         tracing::info!("Found {:#?}", all_replicas);
 
-        // Choose the best replica to use
+        // Choose the best replica to use. Prefer replicas that are directly connected to the
+        // primary, cascading replicas are only promoted if no other replica is available
         let Some(mut new_primary) = all_replicas
             .iter()
+            .filter(|node| !node.is_cascading_replica())
             .max_by_key(|node| node.last_txn_id())
+            .or_else(|| all_replicas.iter().max_by_key(|node| node.last_txn_id()))
             .cloned()
         else {
             return Err(SableError::AutoFailOverError("No replicas found".into()));
         };
 
-        // Keep all nodes that their node ID is not equal to the new primary ID
-        all_replicas.retain(|node| node.node_id().ne(new_primary.node_id()));
+        // Keep all nodes that their node ID is not equal to the new primary ID. Cascading
+        // replicas keep replicating from their upstream replica, so the chain is not broken
+        all_replicas.retain(|node| {
+            node.node_id().ne(new_primary.node_id())
+                && !Self::keeps_upstream_on_failover(node, new_primary.node_id())
+        });
 
         // Convert the vector of replicas into HashMap
         let mut all_replicas: HashMap<String, Node> = all_replicas
@@ -258,7 +275,11 @@ impl ClusterManager {
 
         // switch the roles
         new_primary.set_role(ServerRole::Primary);
+        new_primary.set_primary_node_id(String::default());
+        new_primary.set_upstream_node_id(String::default());
         old_primary.set_role(ServerRole::Replica);
+        old_primary.set_primary_node_id(new_primary.node_id().to_string());
+        old_primary.set_upstream_node_id(new_primary.node_id().to_string());
 
         // reflect the role change in the database
         db.put_node(&old_primary)?;
@@ -395,6 +416,13 @@ impl ClusterManager {
             node.set_primary_node_id(primary_node.node_id().to_string());
         }
 
+        if node.is_primary() {
+            node.set_upstream_node_id(String::default());
+        } else if node.upstream_node_id().is_empty() {
+            // Nodes that do not report their upstream are replicating directly from the primary
+            node.set_upstream_node_id(node.primary_node_id().to_string());
+        }
+
         db.put_shard(&shard)?;
         db.put_node(&node)?;
         drop(lk);
@@ -457,6 +485,69 @@ impl ClusterManager {
         Ok(())
     }
 
+    /// If the current node is a cascading replica, return the replica it replicates from
+    fn cascading_upstream(shard_nodes: &[Node]) -> Option<Node> {
+        let pstate = Server::state();
+        let pstate = pstate.persistent_state();
+        let upstream_node_id = pstate.upstream_node_id();
+        if upstream_node_id.is_empty() || upstream_node_id.eq(&pstate.primary_node_id()) {
+            return None;
+        }
+
+        // If the upstream was promoted to primary, it is monitored like any other primary
+        shard_nodes
+            .iter()
+            .find(|node| node.node_id().eq(&upstream_node_id) && node.is_replica())
+            .cloned()
+    }
+
+    /// Return true if `node` should keep replicating from its current upstream when the shard
+    /// primary is replaced by `new_primary_id`
+    fn keeps_upstream_on_failover(node: &Node, new_primary_id: &str) -> bool {
+        node.is_cascading_replica() && node.upstream_node_id().ne(new_primary_id)
+    }
+
+    /// The replica that this node replicates from is down. Re-attach this node to the
+    /// upstream's upstream (or to the shard primary, if it is not known)
+    fn reattach_to_new_upstream(
+        &self,
+        db: &Persistence,
+        shard_nodes: &[Node],
+        upstream: &Node,
+        primary: &Node,
+    ) -> Result<(), SableError> {
+        let current_node_id = Server::state().persistent_state().id();
+        let new_upstream = shard_nodes
+            .iter()
+            .find(|node| {
+                node.node_id().eq(upstream.upstream_node_id())
+                    && node.node_id().ne(&current_node_id)
+            })
+            .unwrap_or(primary);
+
+        tracing::info!(
+            "Upstream node {} is down. Re-attaching to node {}",
+            upstream.node_id(),
+            new_upstream.node_id()
+        );
+
+        let command = format!(
+            "REPLICAOF {}",
+            new_upstream.private_address().replace(':', " ")
+        );
+        let node = NodeBuilder::default()
+            .with_node_id(current_node_id)
+            .with_shard_name(Server::state().persistent_state().shard_name())
+            .build();
+        db.queue_push_command(&node, &command)?;
+        tracing::info!(
+            "Sent command: '{}' on queue: {}",
+            &command,
+            &node.queue_name()
+        );
+        Ok(())
+    }
+
     /// Return whether the current node's command queue is empty
     async fn is_commands_queue_empty(&self, db: &Persistence) -> Result<bool, SableError> {
         let current_node_id = Server::state().persistent_state().id();
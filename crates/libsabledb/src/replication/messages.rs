@@ -120,6 +120,63 @@ pub enum NodeTalkRequest {
         common: RequestCommon,
         slot: u16,
     },
+    /// Client sends this message to restore keys on the receiver (`MIGRATE`). The receiver
+    /// replies with `NodeResponse::Ok` once all the keys were restored
+    MigrateKeys {
+        common: RequestCommon,
+        db_id: u16,
+        replace: bool,
+        keys: Vec<MigratedKey>,
+    },
+    /// Streaming replication: the replica subscribes to the changes done since `from_sequence`.
+    /// The primary replies with `NodeResponse::Ok` and from then on, pushes `StorageUpdates`
+    /// messages as soon as they are committed
+    SubscribeUpdatesSince {
+        common: RequestCommon,
+        from_sequence: u64,
+    },
+    /// Streaming replication: sent by the replica after it applied a `StorageUpdates` message.
+    /// `sequence_number` is the next sequence number the replica expects
+    UpdatesAck {
+        common: RequestCommon,
+        sequence_number: u64,
+    },
+    /// Incremental fullsync: the primary creates a checkpoint and replies with
+    /// `NodeResponse::CheckpointManifest` listing the checkpoint files
+    CheckpointManifest(RequestCommon),
+    /// Incremental fullsync: request the content of the checkpoint file `name`, starting at
+    /// `offset`. The primary replies with `NodeResponse::Ok` followed by the file content
+    GetCheckpointFile {
+        common: RequestCommon,
+        name: String,
+        offset: u64,
+    },
+    /// Incremental fullsync: the replica received all the checkpoint files. The primary
+    /// deletes the checkpoint
+    CheckpointDone(RequestCommon),
+    /// Like `JoinShard`, but the receiver replies with `NodeResponse::JoinShardOkV2`, which also
+    /// carries the shard primary node ID (the receiver can be a replica)
+    JoinShardV2(RequestCommon),
+}
+
+/// A file that belongs to a checkpoint, sent during an incremental fullsync
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CheckpointFile {
+    /// The file name (relative to the checkpoint folder)
+    pub name: String,
+    pub size: u64,
+    /// SHA256 of the file content
+    pub checksum: String,
+}
+
+/// A key sent by `MIGRATE`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigratedKey {
+    pub key: Vec<u8>,
+    /// The value, serialized in the `DUMP` format
+    pub payload: Vec<u8>,
+    /// Expiration timestamp (millis since UNIX_EPOCH), if the key has a timeout
+    pub expire_timestamp_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -134,6 +191,20 @@ pub enum NodeResponse {
         shard_name: String,
         cluster_name: String,
     },
+    /// An OK response to "CheckpointManifest" request
+    CheckpointManifest {
+        common: ResponseCommon,
+        files: Vec<CheckpointFile>,
+    },
+    /// An OK response to "JoinShardV2" request
+    JoinShardOkV2 {
+        common: ResponseCommon,
+        shard_name: String,
+        cluster_name: String,
+        /// The shard primary node ID. When the responding node is itself a replica,
+        /// this is different from the responding node ID (`common.node_id()`)
+        primary_node_id: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -145,6 +216,12 @@ pub enum ResponseReason {
     CreatingUpdatesSinceError,
     /// No changes available
     NoChangesAvailable,
+    /// Failed to restore the migrated keys. The response context contains the error message
+    MigrateKeysError,
+    /// Incremental fullsync: the requested checkpoint file does not exist
+    NoSuchCheckpointFile,
+    /// The request could not be decoded, e.g. it was sent by a node running a newer version
+    UnknownRequest,
 }
 
 impl Default for ResponseReason {
@@ -169,6 +246,45 @@ impl std::fmt::Display for NodeTalkRequest {
             Self::JoinShard(common) => {
                 write!(f, "JoinShard({})", common)
             }
+            Self::MigrateKeys {
+                common,
+                db_id,
+                replace,
+                keys,
+            } => {
+                write!(
+                    f,
+                    "MigrateKeys({}, db: {}, replace: {}, keys: {})",
+                    common,
+                    db_id,
+                    replace,
+                    keys.len()
+                )
+            }
+            Self::SubscribeUpdatesSince {
+                common,
+                from_sequence,
+            } => {
+                write!(f, "SubscribeUpdatesSince({}, {})", common, from_sequence)
+            }
+            Self::UpdatesAck {
+                common,
+                sequence_number,
+            } => {
+                write!(f, "UpdatesAck({}, {})", common, sequence_number)
+            }
+            Self::CheckpointManifest(common) => write!(f, "CheckpointManifest({})", common),
+            Self::GetCheckpointFile {
+                common,
+                name,
+                offset,
+            } => {
+                write!(f, "GetCheckpointFile({}, {}, {})", common, name, offset)
+            }
+            Self::CheckpointDone(common) => write!(f, "CheckpointDone({})", common),
+            Self::JoinShardV2(common) => {
+                write!(f, "JoinShardV2({})", common)
+            }
         }
     }
 }
@@ -187,6 +303,19 @@ impl std::fmt::Display for NodeResponse {
                 "JoinShardOk(shard_nane: {}, cluster_name: {})",
                 shard_name, cluster_name
             ),
+            Self::CheckpointManifest { common, files } => {
+                write!(f, "CheckpointManifest({}, files: {})", common, files.len())
+            }
+            Self::JoinShardOkV2 {
+                common: _,
+                shard_name,
+                cluster_name,
+                primary_node_id,
+            } => write!(
+                f,
+                "JoinShardOkV2(shard_name: {}, cluster_name: {}, primary_node_id: {})",
+                shard_name, cluster_name, primary_node_id
+            ),
         }
     }
 }
@@ -210,4 +339,30 @@ mod tests {
         assert_eq!(de_resp, resp);
         Ok(())
     }
+
+    #[test]
+    fn test_join_shard_ok_wire_format() -> Result<(), crate::SableError> {
+        let req = RequestCommon::new();
+        let legacy = NodeResponse::JoinShardOk {
+            common: ResponseCommon::new(&req),
+            shard_name: "shard".into(),
+            cluster_name: "cluster".into(),
+        };
+
+        // The legacy reply must keep its variant index and layout, so nodes running an older
+        // version can still decode it
+        let as_bytes = crate::bincode_to_bytesmut!(legacy);
+        assert_eq!(as_bytes[..4], 2u32.to_le_bytes());
+        assert_eq!(bincode::deserialize::<NodeResponse>(&as_bytes)?, legacy);
+
+        let v2 = NodeResponse::JoinShardOkV2 {
+            common: ResponseCommon::new(&req),
+            shard_name: "shard".into(),
+            cluster_name: "cluster".into(),
+            primary_node_id: "primary".into(),
+        };
+        let as_bytes = crate::bincode_to_bytesmut!(v2);
+        assert_eq!(bincode::deserialize::<NodeResponse>(&as_bytes)?, v2);
+        Ok(())
+    }
 }
@@ -5,10 +5,12 @@ mod messages;
 mod node_talk_client;
 mod node_talk_server;
 mod persistence;
+mod replication_backlog;
 mod replication_config;
 mod replication_traits;
 mod replicator;
 mod storage_updates;
+mod updates_notifier;
 
 pub use crate::SableError;
 pub use cluster_manager::*;
@@ -18,11 +20,16 @@ pub use client_replication_loop::*;
 pub use cluster_lock::{BlockingLock, Lock};
 
 pub use client_replication_loop::NodeTalkCommand;
-pub use messages::{NodeResponse, NodeTalkRequest, RequestCommon, ResponseCommon, ResponseReason};
+pub use messages::{
+    CheckpointFile, MigratedKey, NodeResponse, NodeTalkRequest, RequestCommon, ResponseCommon,
+    ResponseReason,
+};
 pub use node_talk_client::*;
 pub use node_talk_server::*;
+pub use replication_backlog::ReplicationBacklog;
 pub use replication_config::ServerRole;
 pub use storage_updates::{StorageUpdates, StorageUpdatesRecord};
+pub use updates_notifier::UpdatesNotifier;
 
 pub use replication_traits::{
     BytesReader, BytesWriter, TcpStreamBytesReader, TcpStreamBytesWriter,
@@ -6,7 +6,7 @@ use crate::{
 };
 use num_format::{Locale, ToFormattedString};
 use std::io::{Read, Write};
-use std::net::{SocketAddr, TcpStream};
+use std::net::{TcpStream, ToSocketAddrs};
 
 #[cfg(not(test))]
 use tracing::{error, info};
@@ -16,16 +16,26 @@ use std::{println as info, println as error};
 
 #[derive(Debug, PartialEq)]
 pub enum JoinShardResult {
-    /// Successfully joined the shard, returns the node-id
+    /// Successfully joined the shard. `node_id` is the node we are connected to (our upstream)
+    /// and `primary_node_id` is the shard primary. The two differ when joining via a replica
     Ok {
         cluster_name: String,
         shard_name: String,
         node_id: String,
+        primary_node_id: String,
     },
     /// Failed to join the shard
     Err,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum MigrateKeysResult {
+    /// The keys were restored by the remote node
+    Ok,
+    /// The remote node refused the keys, returns the error message
+    Err(String),
+}
+
 /// A client used for communicating with the NodeTalkServer
 #[derive(Default)]
 #[allow(dead_code)]
@@ -39,9 +49,23 @@ pub struct NodeTalkClient {
 impl NodeTalkClient {
     /// Connect to NodeServer at a given address - on success, make the socket non blocking
     pub fn connect_with_timeout(&mut self, remote_addr: &str) -> Result<(), SableError> {
-        let addr = remote_addr.parse::<SocketAddr>()?;
         // TODO: make the timeout configurable
-        let stream = TcpStream::connect_timeout(&addr, std::time::Duration::from_secs(5))?;
+        self.connect(remote_addr, std::time::Duration::from_secs(5))
+    }
+
+    /// Connect to NodeServer at a given address, waiting up to `timeout` for the connection to
+    /// be established
+    pub fn connect(
+        &mut self,
+        remote_addr: &str,
+        timeout: std::time::Duration,
+    ) -> Result<(), SableError> {
+        let Some(addr) = remote_addr.to_socket_addrs()?.next() else {
+            return Err(SableError::OtherError(format!(
+                "could not resolve address {remote_addr}"
+            )));
+        };
+        let stream = TcpStream::connect_timeout(&addr, timeout)?;
         crate::replication::socket_set_timeout(&stream)?;
         stream.set_nodelay(true)?;
         self.stream = Some(stream);
@@ -111,6 +135,50 @@ impl NodeTalkClient {
         }
     }
 
+    /// Send `keys` to the connected remote server and wait up to `timeout` for it to restore them
+    /// into the database `db_id`
+    pub fn migrate_keys(
+        &mut self,
+        db_id: u16,
+        replace: bool,
+        keys: Vec<MigratedKey>,
+        timeout: std::time::Duration,
+    ) -> Result<MigrateKeysResult, SableError> {
+        let request = NodeTalkRequest::MigrateKeys {
+            common: RequestCommon::new().with_request_id(&mut self.request_id),
+            db_id,
+            replace,
+            keys,
+        };
+
+        let mut buffer = bincode_to_bytesmut_or!(request, Err(SableError::SerialisationError));
+        let (mut writer, mut reader) = self.split_stream()?;
+        writer.write_message(&mut buffer)?;
+
+        // The socket read timeout is short, keep reading until `timeout` expires
+        let started_at = std::time::Instant::now();
+        let response = loop {
+            if let Some(bytes) = reader.read_message()? {
+                break bincode::deserialize::<NodeResponse>(&bytes)?;
+            }
+            if started_at.elapsed() >= timeout {
+                return Err(SableError::OtherError(format!(
+                    "timeout waiting for a response from {}",
+                    self.remote_addr
+                )));
+            }
+        };
+
+        match response {
+            NodeResponse::Ok(_) => Ok(MigrateKeysResult::Ok),
+            NodeResponse::NotOk(common) => Ok(MigrateKeysResult::Err(common.context().clone())),
+            e => Err(SableError::InternalError(format!(
+                "Received an unexpected response. {:?}",
+                e
+            ))),
+        }
+    }
+
     pub fn stream(&self) -> Option<&TcpStream> {
         self.stream.as_ref()
     }
@@ -119,51 +187,88 @@ impl NodeTalkClient {
         self.stream.as_mut()
     }
 
-    /// Request to join the shard
-    pub fn join_shard(&self) -> Result<JoinShardResult, SableError> {
-        let (mut writer, mut reader) = self.split_stream()?;
-        Ok(self.join_shard_internal(&mut reader, &mut writer))
+    /// Request to join the shard. A node that predates `JoinShardV2` can not decode the request
+    /// and closes the connection: reconnect and join it with the legacy `JoinShard` request
+    pub fn join_shard(&mut self) -> Result<JoinShardResult, SableError> {
+        let result = {
+            let (mut writer, mut reader) = self.split_stream()?;
+            self.join_shard_internal(&mut reader, &mut writer, true)
+        };
+        match result {
+            Err(SableError::ConnectionClosed) => {
+                info!(
+                    "Remote {} closed the connection, re-joining using the legacy JoinShard request",
+                    self.remote_addr
+                );
+                let remote_addr = self.remote_addr.clone();
+                self.connect_with_timeout(&remote_addr)?;
+                let (mut writer, mut reader) = self.split_stream()?;
+                Ok(self
+                    .join_shard_internal(&mut reader, &mut writer, false)
+                    .unwrap_or_else(|e| {
+                        error!("join_shard: error reading replication message. {:?}", e);
+                        JoinShardResult::Err
+                    }))
+            }
+            Err(e) => {
+                error!("join_shard: error reading replication message. {:?}", e);
+                Ok(JoinShardResult::Err)
+            }
+            Ok(result) => Ok(result),
+        }
     }
 
-    /// Request to join the shard, on success, return the node-id
+    /// Request to join the shard using `JoinShardV2` (or the legacy `JoinShard` when `v2` is
+    /// `false`), on success, return the node-id
     fn join_shard_internal(
         &self,
         reader: &mut impl BytesReader,
         writer: &mut impl BytesWriter,
-    ) -> JoinShardResult {
-        let join_request = NodeTalkRequest::JoinShard(RequestCommon::new());
-        match self.send_receive(reader, writer, join_request) {
-            Err(e) => {
-                error!("join_shard: error reading replication message. {:?}", e);
+        v2: bool,
+    ) -> Result<JoinShardResult, SableError> {
+        let join_request = if v2 {
+            NodeTalkRequest::JoinShardV2(RequestCommon::new())
+        } else {
+            NodeTalkRequest::JoinShard(RequestCommon::new())
+        };
+        let msg = self.send_receive(reader, writer, join_request)?;
+        Ok(match msg {
+            NodeResponse::NotOk(common) => {
+                // the requested sequence was is not acceptable by the server
+                // do a full sync
+                info!("Failed to join the shard! {}", common);
                 JoinShardResult::Err
             }
-            Ok(msg) => {
-                match msg {
-                    NodeResponse::NotOk(common) => {
-                        // the requested sequence was is not acceptable by the server
-                        // do a full sync
-                        info!("Failed to join the shard! {}", common);
-                        JoinShardResult::Err
-                    }
-                    NodeResponse::JoinShardOk {
-                        common,
-                        shard_name,
-                        cluster_name,
-                    } => JoinShardResult::Ok {
-                        cluster_name,
-                        shard_name,
-                        node_id: common.node_id().to_string(),
-                    },
-                    e => {
-                        error!(
-                            "Received an unexpected response to JoinsShard request. {:?}",
-                            e
-                        );
-                        JoinShardResult::Err
-                    }
-                }
+            NodeResponse::JoinShardOkV2 {
+                common,
+                shard_name,
+                cluster_name,
+                primary_node_id,
+            } => JoinShardResult::Ok {
+                cluster_name,
+                shard_name,
+                node_id: common.node_id().to_string(),
+                primary_node_id,
+            },
+            // The legacy reply: the node we joined is the shard primary
+            NodeResponse::JoinShardOk {
+                common,
+                shard_name,
+                cluster_name,
+            } => JoinShardResult::Ok {
+                cluster_name,
+                shard_name,
+                node_id: common.node_id().to_string(),
+                primary_node_id: common.node_id().to_string(),
+            },
+            e => {
+                error!(
+                    "Received an unexpected response to JoinsShard request. {:?}",
+                    e
+                );
+                JoinShardResult::Err
             }
-        }
+        })
     }
 
     fn read_response(&self, reader: &mut dyn BytesReader) -> Result<NodeResponse, SableError> {
@@ -1,33 +1,40 @@
 use crate::bincode_to_bytesmut_or;
 use crate::server::ServerOptions;
-use crate::server::{ReplicaTelemetry, ReplicationTelemetry};
+use crate::server::{ReplicaTelemetry, ReplicationTelemetry, REPLICA_ACK_KEY};
 use crate::storage::GetChangesLimits;
 use crate::{
     io::TempFile,
     replication::{
         node_talk_client::NodeTalkClient, socket_make_blocking, socket_set_timeout, ClusterManager,
+        ReplicationBacklog, StorageUpdates, UpdatesNotifier,
     },
     server::SlotFileImporter,
 };
 use futures::future;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::rc::Rc;
 
 use crate::utils;
 #[allow(unused_imports)]
 use crate::{
+    commands::Strings,
     io::Archive,
     replication::{
-        BytesReader, BytesWriter, NodeResponse, NodeTalkRequest, ResponseCommon, ResponseReason,
-        TcpStreamBytesReader, TcpStreamBytesWriter,
+        BytesReader, BytesWriter, CheckpointFile, MigratedKey, NodeResponse, NodeTalkRequest,
+        RequestCommon, ResponseCommon, ResponseReason, TcpStreamBytesReader, TcpStreamBytesWriter,
     },
-    SableError, Server, StorageAdapter,
+    storage::GenericDb,
+    utils::{calculate_slot, RdbError, RdbValue},
+    LockManager, SableError, Server, StorageAdapter, TimeUtils,
 };
+use bytes::BytesMut;
 
 use std::sync::{
     atomic::{AtomicBool, AtomicUsize, Ordering},
-    Arc, RwLock as StdRwLock,
+    Arc, Condvar, Mutex, RwLock as StdRwLock,
 };
+use std::time::Duration;
 use tokio::net::TcpListener;
 
 const OPTIONS_LOCK_ERR: &str = "Failed to obtain read lock on ServerOptions";
@@ -43,6 +50,62 @@ enum HandleRequestResult {
     OtherError(String),
 }
 
+/// Streaming replication: how long to wait for changes or acknowledgements before checking
+/// for shutdown
+const STREAMING_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Default)]
+struct StreamingAcksState {
+    /// Number of `StorageUpdates` messages acknowledged by the replica
+    acked: u64,
+    /// The connection is closed (or we are done streaming)
+    closed: bool,
+}
+
+/// Acknowledgements received from a replica subscribed to the changes
+#[derive(Default)]
+struct StreamingAcks {
+    state: Mutex<StreamingAcksState>,
+    condvar: Condvar,
+}
+
+impl StreamingAcks {
+    /// The replica acknowledged a message
+    fn ack(&self) {
+        let mut state = self.state.lock().expect("poisoned mutex");
+        state.acked = state.acked.saturating_add(1);
+        self.condvar.notify_all();
+    }
+
+    fn close(&self) {
+        let mut state = self.state.lock().expect("poisoned mutex");
+        state.closed = true;
+        self.condvar.notify_all();
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().expect("poisoned mutex").closed
+    }
+
+    /// Wait until less than `max_unacked` of the `sent` messages are waiting to be
+    /// acknowledged, or until `timeout` expires. Return `Some(true)` if another message can be
+    /// sent, `Some(false)` on timeout and `None` if closed
+    fn wait_for_window(&self, sent: u64, max_unacked: u64, timeout: Duration) -> Option<bool> {
+        let state = self.state.lock().expect("poisoned mutex");
+        let (state, _) = self
+            .condvar
+            .wait_timeout_while(state, timeout, |state| {
+                !state.closed && sent.saturating_sub(state.acked) >= max_unacked
+            })
+            .expect("poisoned mutex");
+        if state.closed {
+            None
+        } else {
+            Some(sent.saturating_sub(state.acked) < max_unacked)
+        }
+    }
+}
+
 #[cfg(not(test))]
 use tracing::{debug, error, info};
 
@@ -55,6 +118,10 @@ pub struct NodeTalkServer {}
 lazy_static::lazy_static! {
     static ref REPLICATION_THREADS: AtomicUsize = AtomicUsize::new(0);
     static ref STOP_FLAG: AtomicBool = AtomicBool::new(false);
+    /// Incremental fullsync: the manifest (sequence number and files) of each replica checkpoint,
+    /// keyed by the checkpoint folder
+    static ref CHECKPOINT_MANIFESTS: Mutex<HashMap<PathBuf, (u64, Vec<CheckpointFile>)>> =
+        Mutex::new(HashMap::new());
 }
 
 /// Notify the replication threads to stop and wait for them to terminate
@@ -151,6 +218,10 @@ impl NodeTalkServer {
                 .private_address
         );
 
+        if let Err(e) = ReplicationBacklog::start(options.clone(), store.clone()) {
+            error!("Failed to start the replication backlog. {:?}", e);
+        }
+
         let cm = ClusterManager::with_options(options.clone());
         loop {
             // Accept with timeout
@@ -227,6 +298,135 @@ impl NodeTalkServer {
         Ok(changes_count)
     }
 
+    /// Incremental fullsync: the folder that holds the checkpoint created for `replica_node_id`
+    fn replica_checkpoint_path(store: &StorageAdapter, replica_node_id: &str) -> PathBuf {
+        PathBuf::from(format!(
+            "{}.checkpoint.{}",
+            store.open_params().db_path.display(),
+            replica_node_id
+        ))
+    }
+
+    /// Incremental fullsync: return the sequence number and the list of files of the checkpoint
+    /// created for `replica_node_id`. A replica that reconnects in the middle of a fullsync resumes
+    /// from the checkpoint (and manifest) created for its previous attempt
+    fn replica_checkpoint_manifest(
+        store: &StorageAdapter,
+        replica_node_id: &str,
+    ) -> Result<(u64, Vec<CheckpointFile>), SableError> {
+        let checkpoint_path = Self::replica_checkpoint_path(store, replica_node_id);
+        let cached = CHECKPOINT_MANIFESTS
+            .lock()
+            .expect(Strings::POISONED_MUTEX)
+            .get(&checkpoint_path)
+            .cloned();
+        if let Some(manifest) = cached {
+            if checkpoint_path.is_dir() {
+                info!(
+                    "Re-using checkpoint {} for replica {}",
+                    checkpoint_path.display(),
+                    replica_node_id
+                );
+                return Ok(manifest);
+            }
+        }
+
+        let manifest = Self::create_replica_checkpoint(store, &checkpoint_path, replica_node_id)?;
+        CHECKPOINT_MANIFESTS
+            .lock()
+            .expect(Strings::POISONED_MUTEX)
+            .insert(checkpoint_path, manifest.clone());
+        Ok(manifest)
+    }
+
+    /// Incremental fullsync: the replica fetched the checkpoint, delete it
+    fn delete_replica_checkpoint(store: &StorageAdapter, replica_node_id: &str) {
+        let checkpoint_path = Self::replica_checkpoint_path(store, replica_node_id);
+        CHECKPOINT_MANIFESTS
+            .lock()
+            .expect(Strings::POISONED_MUTEX)
+            .remove(&checkpoint_path);
+        let _ = std::fs::remove_dir_all(&checkpoint_path);
+    }
+
+    /// Incremental fullsync: create a checkpoint for `replica_node_id` at `checkpoint_path` and
+    /// return its sequence number together with the list of the checkpoint files
+    fn create_replica_checkpoint(
+        store: &StorageAdapter,
+        checkpoint_path: &std::path::Path,
+        replica_node_id: &str,
+    ) -> Result<(u64, Vec<CheckpointFile>), SableError> {
+        // A left over from a previous run, start with a fresh checkpoint
+        let _ = std::fs::remove_dir_all(checkpoint_path);
+
+        info!("Preparing checkpoint for replica {}", replica_node_id);
+        let changes_count = store.create_checkpoint(checkpoint_path)?;
+
+        let mut files = Vec::<CheckpointFile>::new();
+        for entry in std::fs::read_dir(checkpoint_path)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+            files.push(CheckpointFile {
+                name: entry.file_name().to_string_lossy().to_string(),
+                size: entry.metadata()?.len(),
+                checksum: crate::io::file_checksum(&entry.path())?,
+            });
+        }
+
+        info!(
+            "Checkpoint {} created successfully with {} changes and {} files",
+            checkpoint_path.display(),
+            changes_count,
+            files.len()
+        );
+        Ok((changes_count, files))
+    }
+
+    /// Incremental fullsync: send the content of `filepath`, starting at `offset`
+    fn send_checkpoint_file(
+        stream: &mut std::net::TcpStream,
+        filepath: &std::path::Path,
+        offset: u64,
+    ) -> Result<u64, SableError> {
+        // async sockets are non-blocking. Make it blocking for sending the file
+        socket_make_blocking(stream)?;
+        let bytes_sent = crate::io::send_file_from(stream, filepath, offset)?;
+
+        // Restore the stream state
+        socket_set_timeout(stream)?;
+        Ok(bytes_sent)
+    }
+
+    /// Return the changes since `from_sequence`. If the WAL no longer holds them, look for them
+    /// in the replication backlog
+    fn storage_updates_since(
+        store: &StorageAdapter,
+        from_sequence: u64,
+        limits: Rc<GetChangesLimits>,
+    ) -> Result<StorageUpdates, SableError> {
+        let err = match store.storage_updates_since(from_sequence, limits) {
+            Ok(storage_updates) => return Ok(storage_updates),
+            Err(e) => e,
+        };
+
+        let Some(backlog) = ReplicationBacklog::get() else {
+            return Err(err);
+        };
+
+        match backlog.updates_since(from_sequence)? {
+            Some(storage_updates) => {
+                debug!(
+                    "Changes since {} were read from the replication backlog",
+                    from_sequence
+                );
+                Ok(storage_updates)
+            }
+            None => Err(err),
+        }
+    }
+
     /// Write `response` to `writer`. Return `true` on success, `false` otherwise
     fn write_response(writer: &mut impl BytesWriter, response: &NodeResponse) -> bool {
         let mut response_mut = bincode_to_bytesmut_or!(response, false);
@@ -253,6 +453,20 @@ impl NodeTalkServer {
             let mut reader = TcpStreamBytesReader::new(stream);
             let result = Self::read_request(&mut reader);
             match result {
+                Err(SableError::BincodeSerializationErr(e)) => {
+                    // The message was read completely, so the connection is still usable. Let the
+                    // requesting node know that we do not support this request
+                    info!("Received an unknown request. {:?}", e);
+                    let response_not_ok = NodeResponse::NotOk(
+                        ResponseCommon::new(&RequestCommon::default())
+                            .with_reason(ResponseReason::UnknownRequest),
+                    );
+                    let mut writer = TcpStreamBytesWriter::new(stream);
+                    if !Self::write_response(&mut writer, &response_not_ok) {
+                        return HandleRequestResult::NetError("Failed to write response".into());
+                    }
+                    continue;
+                }
                 Err(e) => {
                     return HandleRequestResult::NetError(format!(
                         "Failed to read request. {:?}",
@@ -275,15 +489,40 @@ impl NodeTalkServer {
         };
         debug!("Received remote request {:?}", req);
 
+        let join_shard_v2 = matches!(req, NodeTalkRequest::JoinShardV2(_));
         match req {
-            NodeTalkRequest::JoinShard(common) => {
-                info!("Received request: JoinShard({})", common);
-                let shard_name = Server::state().persistent_state().shard_name();
-                let cluster_name = Server::state().persistent_state().cluster_name();
-                let response_ok = NodeResponse::JoinShardOk {
-                    common: ResponseCommon::new(&common),
-                    cluster_name: cluster_name.clone(),
-                    shard_name: shard_name.clone(),
+            NodeTalkRequest::JoinShard(common) | NodeTalkRequest::JoinShardV2(common) => {
+                info!(
+                    "Received request: JoinShard({}), V2: {}",
+                    common, join_shard_v2
+                );
+                let pstate = Server::state();
+                let pstate = pstate.persistent_state();
+                let shard_name = pstate.shard_name();
+                let cluster_name = pstate.cluster_name();
+                let response_ok = if join_shard_v2 {
+                    // A replica can serve other replicas (cascading replication). In this case, we
+                    // report the shard primary so the joining node does not assume that we are
+                    // the primary
+                    let primary_node_id = if pstate.is_replica() {
+                        pstate.primary_node_id()
+                    } else {
+                        pstate.id()
+                    };
+                    NodeResponse::JoinShardOkV2 {
+                        common: ResponseCommon::new(&common),
+                        cluster_name: cluster_name.clone(),
+                        shard_name: shard_name.clone(),
+                        primary_node_id,
+                    }
+                } else {
+                    // Nodes that predate `JoinShardV2` consider the node they join to be the
+                    // shard primary
+                    NodeResponse::JoinShardOk {
+                        common: ResponseCommon::new(&common),
+                        cluster_name: cluster_name.clone(),
+                        shard_name: shard_name.clone(),
+                    }
                 };
                 let mut writer = TcpStreamBytesWriter::new(stream);
                 if !Self::write_response(&mut writer, &response_ok) {
@@ -335,6 +574,40 @@ impl NodeTalkServer {
                 // Leave the current connection
                 return HandleRequestResult::SuccessAndExit;
             }
+            NodeTalkRequest::MigrateKeys {
+                common,
+                db_id,
+                replace,
+                keys,
+            } => {
+                info!(
+                    "Received MigrateKeys: ({}, db: {}, keys: {})",
+                    common,
+                    db_id,
+                    keys.len()
+                );
+                let response = match Self::restore_migrated_keys(store, db_id, replace, keys) {
+                    Ok(None) => NodeResponse::Ok(ResponseCommon::new(&common)),
+                    Ok(Some(errmsg)) => NodeResponse::NotOk(
+                        ResponseCommon::new(&common)
+                            .with_reason(ResponseReason::MigrateKeysError)
+                            .with_context(errmsg),
+                    ),
+                    Err(e) => NodeResponse::NotOk(
+                        ResponseCommon::new(&common)
+                            .with_reason(ResponseReason::MigrateKeysError)
+                            .with_context(format!("ERR {e}")),
+                    ),
+                };
+
+                let mut writer = TcpStreamBytesWriter::new(stream);
+                if !Self::write_response(&mut writer, &response) {
+                    return HandleRequestResult::NetError("Failed to write response".into());
+                }
+
+                // Leave the current connection
+                return HandleRequestResult::SuccessAndExit;
+            }
             NodeTalkRequest::FullSync(common) => {
                 debug!("Received request {}", common);
                 let response_ok = NodeResponse::Ok(ResponseCommon::new(&common));
@@ -358,13 +631,165 @@ impl NodeTalkServer {
                 let replinfo = ReplicaTelemetry {
                     last_change_sequence_number: changes_count,
                     distance_from_primary: 0,
+                    ..Default::default()
+                };
+                ReplicationTelemetry::update_replica_info(replica_node_id.to_string(), replinfo);
+
+                // Update the current node info in the cluster manager database as primary
+                Self::update_primary_info(store, cm);
+            }
+
+            NodeTalkRequest::CheckpointManifest(common) => {
+                info!(
+                    "Replica {} requested an incremental fullsync",
+                    replica_node_id
+                );
+                let (changes_count, files) =
+                    match Self::replica_checkpoint_manifest(store, replica_node_id) {
+                        Ok(result) => result,
+                        Err(e) => {
+                            return HandleRequestResult::IoError(format!(
+                                "Failed to create checkpoint for replica {}. {:?}",
+                                replica_node_id, e
+                            ));
+                        }
+                    };
+
+                let replinfo = ReplicaTelemetry {
+                    last_change_sequence_number: changes_count,
+                    distance_from_primary: 0,
+                    ..Default::default()
                 };
                 ReplicationTelemetry::update_replica_info(replica_node_id.to_string(), replinfo);
 
+                let response = NodeResponse::CheckpointManifest {
+                    common: ResponseCommon::new(&common),
+                    files,
+                };
+                let mut writer = TcpStreamBytesWriter::new(stream);
+                if !Self::write_response(&mut writer, &response) {
+                    return HandleRequestResult::NetError("Failed to write response".into());
+                }
+            }
+            NodeTalkRequest::GetCheckpointFile {
+                common,
+                name,
+                offset,
+            } => {
+                debug!("Received request {}", common);
+                // Only files located directly under the checkpoint folder can be requested
+                let filepath = Self::replica_checkpoint_path(store, replica_node_id).join(&name);
+                let is_valid_name = std::path::Path::new(&name)
+                    .file_name()
+                    .is_some_and(|file_name| file_name == name.as_str());
+
+                let mut writer = TcpStreamBytesWriter::new(stream);
+                if !is_valid_name || !filepath.is_file() {
+                    let response_not_ok = NodeResponse::NotOk(
+                        ResponseCommon::new(&common)
+                            .with_reason(ResponseReason::NoSuchCheckpointFile),
+                    );
+                    if Self::write_response(&mut writer, &response_not_ok) {
+                        return HandleRequestResult::Success;
+                    } else {
+                        return HandleRequestResult::NetError("Failed to write response".into());
+                    }
+                }
+
+                let response_ok = NodeResponse::Ok(ResponseCommon::new(&common));
+                if !Self::write_response(&mut writer, &response_ok) {
+                    return HandleRequestResult::NetError("Failed to write response".into());
+                }
+
+                if let Err(e) = Self::send_checkpoint_file(stream, &filepath, offset) {
+                    return HandleRequestResult::NetError(format!(
+                        "Failed sending checkpoint file {} to replica {}. {:?}",
+                        filepath.display(),
+                        replica_node_id,
+                        e
+                    ));
+                }
+            }
+            NodeTalkRequest::CheckpointDone(common) => {
+                info!(
+                    "Replica {} completed an incremental fullsync",
+                    replica_node_id
+                );
+                Self::delete_replica_checkpoint(store, replica_node_id);
+
+                let response_ok = NodeResponse::Ok(ResponseCommon::new(&common));
+                let mut writer = TcpStreamBytesWriter::new(stream);
+                if !Self::write_response(&mut writer, &response_ok) {
+                    return HandleRequestResult::NetError("Failed to write response".into());
+                }
+
                 // Update the current node info in the cluster manager database as primary
                 Self::update_primary_info(store, cm);
             }
+            NodeTalkRequest::SubscribeUpdatesSince {
+                common,
+                from_sequence,
+            } => {
+                info!(
+                    "Replica {} subscribed to changes since: {}",
+                    replica_node_id, from_sequence
+                );
+
+                let reason = if common.request_id() == 0 {
+                    // This is the first request - force a fullsync
+                    Some(ResponseReason::NoFullSyncDone)
+                } else if let Err(e) = Self::storage_updates_since(
+                    store,
+                    from_sequence,
+                    Rc::new(
+                        GetChangesLimits::builder()
+                            .with_max_changes_count(1)
+                            .build(),
+                    ),
+                ) {
+                    // The requested changes are no longer available
+                    tracing::warn!("Failed to construct 'changes since' message. {:?}", e);
+                    Some(ResponseReason::CreatingUpdatesSinceError)
+                } else {
+                    None
+                };
 
+                let mut writer = TcpStreamBytesWriter::new(stream);
+                if let Some(reason) = reason {
+                    let response_not_ok =
+                        NodeResponse::NotOk(ResponseCommon::new(&common).with_reason(reason));
+                    if Self::write_response(&mut writer, &response_not_ok) {
+                        return HandleRequestResult::Success;
+                    } else {
+                        return HandleRequestResult::NetError("Failed to write response".into());
+                    }
+                }
+
+                // The replica applied all the changes up to `from_sequence`
+                Self::replica_acked(replica_node_id, from_sequence);
+
+                let response_ok = NodeResponse::Ok(ResponseCommon::new(&common));
+                if !Self::write_response(&mut writer, &response_ok) {
+                    return HandleRequestResult::NetError("Failed to write response".into());
+                }
+
+                // From now on, we push the changes to the replica as soon as they are committed
+                return Self::stream_updates(
+                    store,
+                    cm,
+                    options,
+                    stream,
+                    replica_node_id,
+                    from_sequence,
+                );
+            }
+            NodeTalkRequest::UpdatesAck {
+                common: _,
+                sequence_number,
+            } => {
+                // An acknowledgement that arrived after we stopped streaming
+                Self::replica_acked(replica_node_id, sequence_number);
+            }
             NodeTalkRequest::GetUpdatesSince {
                 common,
                 from_sequence,
@@ -393,6 +818,9 @@ impl NodeTalkServer {
                     }
                 }
 
+                // The replica applied all the changes up to `from_sequence`
+                Self::replica_acked(replica_node_id, from_sequence);
+
                 // Get the changes from the database. If no changes available
                 // hold the request until we have some changes to send over
                 let mut retries = 5usize;
@@ -417,7 +845,7 @@ impl NodeTalkServer {
 
                 let storage_updates = loop {
                     let storage_updates =
-                        match store.storage_updates_since(from_sequence, limits.clone()) {
+                        match Self::storage_updates_since(store, from_sequence, limits.clone()) {
                             Err(e) => {
                                 let msg =
                                     format!("Failed to construct 'changes since' message. {:?}", e);
@@ -503,6 +931,7 @@ impl NodeTalkServer {
                         let replinfo = ReplicaTelemetry {
                             last_change_sequence_number: storage_updates.end_seq_number,
                             distance_from_primary: 0,
+                            ..Default::default()
                         };
                         ReplicationTelemetry::update_replica_info(
                             replica_node_id.to_string(),
@@ -602,19 +1031,253 @@ impl NodeTalkServer {
                 }
                 Self::update_primary_info(&store_clone, &cm);
             }
+
+            // Delete the checkpoint left behind by an interrupted incremental fullsync
+            let _ =
+                std::fs::remove_dir_all(Self::replica_checkpoint_path(&store_clone, &replica_name));
         });
         Ok(())
     }
 
+    /// Streaming replication: push the changes committed since `from_sequence` to the replica
+    /// as soon as they are committed. The replica acknowledges every `StorageUpdates` message
+    /// it applies. The acknowledgements are read by a dedicated thread and once
+    /// `max_unacked_updates` messages are waiting for an acknowledgement, we stop pushing
+    fn stream_updates(
+        store: &StorageAdapter,
+        cm: &ClusterManager,
+        options: Arc<StdRwLock<ServerOptions>>,
+        stream: &mut std::net::TcpStream,
+        replica_node_id: &String,
+        from_sequence: u64,
+    ) -> HandleRequestResult {
+        let ack_stream = match stream.try_clone() {
+            Ok(ack_stream) => ack_stream,
+            Err(e) => {
+                return HandleRequestResult::NetError(format!("Failed to clone socket. {:?}", e));
+            }
+        };
+
+        let acks = Arc::new(StreamingAcks::default());
+        let acks_clone = acks.clone();
+        let replica_node_id_clone = replica_node_id.clone();
+        let acks_reader = std::thread::spawn(move || {
+            Self::read_updates_acks(ack_stream, acks_clone, replica_node_id_clone);
+        });
+
+        let result = Self::push_updates(
+            store,
+            cm,
+            options,
+            stream,
+            replica_node_id,
+            from_sequence,
+            &acks,
+        );
+
+        // Stop the acknowledgements reader and wait for it
+        acks.close();
+        let _ = acks_reader.join();
+        result
+    }
+
+    fn push_updates(
+        store: &StorageAdapter,
+        cm: &ClusterManager,
+        options: Arc<StdRwLock<ServerOptions>>,
+        stream: &std::net::TcpStream,
+        replica_node_id: &String,
+        from_sequence: u64,
+        acks: &StreamingAcks,
+    ) -> HandleRequestResult {
+        let (limits, max_unacked_updates) = {
+            let options = options.read().expect(OPTIONS_LOCK_ERR);
+            let limits = Rc::new(
+                GetChangesLimits::builder()
+                    .with_memory(options.replication_limits.single_update_buffer_size as u64)
+                    .with_max_changes_count(
+                        options.replication_limits.num_updates_per_message as u64,
+                    )
+                    .build(),
+            );
+            (
+                limits,
+                options.replication_limits.max_unacked_updates.max(1) as u64,
+            )
+        };
+
+        let mut writer = TcpStreamBytesWriter::new(stream);
+        let mut next_sequence = from_sequence;
+        let mut messages_sent = 0u64;
+        loop {
+            if replication_thread_is_going_down() {
+                return HandleRequestResult::Shutdown("Received request to shutdown - bye".into());
+            }
+
+            // Flow control: wait for the replica to acknowledge before we push more changes
+            match acks.wait_for_window(messages_sent, max_unacked_updates, STREAMING_POLL_INTERVAL)
+            {
+                None => {
+                    return HandleRequestResult::NetError(
+                        "Replica stopped acknowledging changes".into(),
+                    );
+                }
+                Some(false) => continue,
+                Some(true) => {}
+            }
+
+            // Read the generation before we look for changes, so changes committed in between
+            // are not missed
+            let generation = UpdatesNotifier::generation();
+            let storage_updates =
+                match Self::storage_updates_since(store, next_sequence, limits.clone()) {
+                    Ok(storage_updates) => storage_updates,
+                    Err(e) => {
+                        return HandleRequestResult::OtherError(format!(
+                            "Failed to construct 'changes since' message. {:?}",
+                            e
+                        ));
+                    }
+                };
+
+            if storage_updates.is_empty() {
+                // Nothing to send, sleep until a change is committed
+                if !UpdatesNotifier::wait_for_changes(generation, STREAMING_POLL_INTERVAL) {
+                    Self::update_primary_info(store, cm);
+                }
+                continue;
+            }
+
+            debug!("Pushing remote update: {}", storage_updates);
+            let mut buffer = storage_updates.to_bytes();
+            if let Err(e) = writer.write_message(&mut buffer) {
+                return HandleRequestResult::NetError(format!(
+                    "Failed to push changes to replica. {:?}",
+                    e
+                ));
+            }
+
+            messages_sent = messages_sent.saturating_add(1);
+            next_sequence = storage_updates.end_seq_number;
+            let replinfo = ReplicaTelemetry {
+                last_change_sequence_number: storage_updates.end_seq_number,
+                distance_from_primary: 0,
+                ..Default::default()
+            };
+            ReplicationTelemetry::update_replica_info(replica_node_id.to_string(), replinfo);
+        }
+    }
+
+    /// Read the acknowledgements sent by a replica subscribed to the changes, until `acks`
+    /// is closed or the connection is broken
+    fn read_updates_acks(
+        stream: std::net::TcpStream,
+        acks: Arc<StreamingAcks>,
+        replica_node_id: String,
+    ) {
+        let mut reader = TcpStreamBytesReader::new(&stream);
+        while !acks.is_closed() {
+            match Self::read_request(&mut reader) {
+                Ok(None) => {}
+                Ok(Some(NodeTalkRequest::UpdatesAck {
+                    common: _,
+                    sequence_number,
+                })) => {
+                    Self::replica_acked(&replica_node_id, sequence_number);
+                    acks.ack();
+                }
+                Ok(Some(req)) => {
+                    tracing::warn!("Unexpected request while streaming changes: {}", req);
+                    break;
+                }
+                Err(e) => {
+                    debug!("Failed to read acknowledgement. {:?}", e);
+                    break;
+                }
+            }
+        }
+        acks.close();
+    }
+
+    /// Restore the keys sent by `MIGRATE` into the database `db_id`. The keys are restored only
+    /// if all of them can be restored, otherwise, return the error message to send back
+    fn restore_migrated_keys(
+        store: &StorageAdapter,
+        db_id: u16,
+        replace: bool,
+        keys: Vec<MigratedKey>,
+    ) -> Result<Option<String>, SableError> {
+        let mut values = Vec::<(BytesMut, RdbValue, Option<u64>)>::with_capacity(keys.len());
+        for migrated_key in keys {
+            let value = match RdbValue::from_dump_payload(&migrated_key.payload) {
+                Ok(value) => value,
+                Err(RdbError::UnsupportedType(object_type)) => {
+                    return Ok(Some(format!(
+                        "ERR RDB object type {} is not supported",
+                        object_type
+                    )));
+                }
+                Err(_) => return Ok(Some(Strings::ERR_DUMP_PAYLOAD.to_string())),
+            };
+            values.push((
+                BytesMut::from(migrated_key.key.as_slice()),
+                value,
+                migrated_key.expire_timestamp_ms,
+            ));
+        }
+
+        // We are not running on a worker thread: block until we own the locks of the keys
+        let slots = values
+            .iter()
+            .map(|(key, _, _)| calculate_slot(key))
+            .collect();
+        let _unused = futures::executor::block_on(
+            LockManager::lock_multi_slots_exclusive_unconditionally(slots),
+        )?;
+
+        let mut generic_db = GenericDb::with_storage(store, db_id);
+        if !replace {
+            for (key, _, _) in &values {
+                if generic_db.contains(key)? {
+                    return Ok(Some(Strings::BUSYKEY.to_string()));
+                }
+            }
+        }
+
+        let now = TimeUtils::epoch_ms()?;
+        for (key, value, expire_timestamp_ms) in values {
+            if expire_timestamp_ms.is_some_and(|timestamp| timestamp <= now) {
+                // the key expired on its way here
+                generic_db.delete(&key, true)?;
+            } else {
+                generic_db.restore_value(&key, value, expire_timestamp_ms)?;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Record the replica's acknowledged sequence number and wake up the clients blocked by
+    /// `WAIT` / `WAITAOF`
+    fn replica_acked(replica_node_id: &str, sequence_number: u64) {
+        if !ReplicationTelemetry::replica_acked(replica_node_id, sequence_number) {
+            return;
+        }
+        // We are not running on a worker thread
+        futures::executor::block_on(
+            Server::state().wakeup_clients(&BytesMut::from(REPLICA_ACK_KEY), usize::MAX),
+        );
+    }
+
     fn update_primary_info(store: &StorageAdapter, cm: &ClusterManager) {
-        // Update the current node info in the cluster manager database as primary
+        // Update the current node info in the cluster manager database. Note that this node
+        // might be a replica serving cascading replicas, in which case the role is kept as is
         let node = crate::replication::NodeBuilder::default()
             .with_last_txn_id(store.latest_sequence_number().unwrap_or_default())
             .build();
         if let Err(e) = cm.put_node(node.clone()) {
             crate::warn_with_throttling!(
                 10,
-                "Error while updating self as Primary({:?}) in the cluster database. {:?}",
+                "Error while updating self({:?}) in the cluster database. {:?}",
                 node,
                 e
             );
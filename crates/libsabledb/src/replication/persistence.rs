@@ -134,6 +134,11 @@ pub struct Node {
     node_id: String,
     /// The node ID of the primary (will be empty for primary node)
     primary_node_id: String,
+    /// The node ID this node replicates from. This is the primary node ID for replicas
+    /// that are directly connected to the primary, or another replica's node ID for
+    /// cascading replicas (will be empty for primary node)
+    #[serde(default)]
+    upstream_node_id: String,
     /// The name of the shard this node belongs to
     shard_name: String,
     /// Last timestamp for this node. In microseconds since epoch
@@ -161,6 +166,7 @@ impl Default for Node {
             last_updated: TimeUtils::epoch_micros().unwrap_or(0),
             role: pstate.role(),
             primary_node_id: pstate.primary_node_id(),
+            upstream_node_id: pstate.upstream_node_id(),
             slots: pstate.slots().to_string(),
             private_address: server_state
                 .options()
@@ -216,6 +222,21 @@ impl Node {
         &self.primary_node_id
     }
 
+    pub fn set_upstream_node_id(&mut self, upstream_node_id: String) {
+        self.upstream_node_id = upstream_node_id;
+    }
+
+    pub fn upstream_node_id(&self) -> &String {
+        &self.upstream_node_id
+    }
+
+    /// Return true if this node is a replica that replicates from another replica
+    pub fn is_cascading_replica(&self) -> bool {
+        self.is_replica()
+            && !self.upstream_node_id.is_empty()
+            && self.upstream_node_id.ne(&self.primary_node_id)
+    }
+
     pub fn is_primary(&self) -> bool {
         self.role == ServerRole::Primary
     }
@@ -256,6 +277,7 @@ pub struct NodeBuilder {
     last_txn_id: u64,
     slots: String,
     primary_node_id: String,
+    upstream_node_id: String,
 }
 
 impl Default for NodeBuilder {
@@ -271,6 +293,7 @@ impl Default for NodeBuilder {
             last_txn_id: node.last_txn_id,
             slots: node.slots,
             primary_node_id: node.primary_node_id,
+            upstream_node_id: node.upstream_node_id,
         }
     }
 }
@@ -285,6 +308,7 @@ impl NodeBuilder {
     impl_builder_with_fn!(last_txn_id, u64);
     impl_builder_with_fn!(slots, String);
     impl_builder_with_fn!(primary_node_id, String);
+    impl_builder_with_fn!(upstream_node_id, String);
 
     pub fn build(self) -> Node {
         Node {
@@ -297,6 +321,7 @@ impl NodeBuilder {
             last_txn_id: self.last_txn_id,
             slots: self.slots,
             primary_node_id: self.primary_node_id,
+            upstream_node_id: self.upstream_node_id,
         }
     }
 }
@@ -454,6 +479,8 @@ pub enum ShardIsStableResult {
     MultipleSlotRange,
     /// A replica node has no primary node ID set
     ReplicaIsMissingPrimary,
+    /// A cascading replica replicates from a node that is not a member of the shard
+    ReplicaIsMissingUpstream,
 }
 
 impl PartialEq for ShardPrimaryResult {
@@ -783,6 +810,15 @@ impl Persistence {
                 if node.primary_node_id != primary_node.node_id {
                     return Ok(ShardIsStableResult::MultiplePrimaries);
                 }
+
+                // A cascading replica must replicate from another member of the shard
+                if node.is_cascading_replica()
+                    && !nodes
+                        .iter()
+                        .any(|other| other.node_id.eq(&node.upstream_node_id))
+                {
+                    return Ok(ShardIsStableResult::ReplicaIsMissingUpstream);
+                }
             }
         }
         Ok(ShardIsStableResult::Ok)
@@ -1089,4 +1125,56 @@ mod tests {
             Persistence::find_primary_node(&[&node1, &node2]).unwrap()
         );
     }
+
+    #[test]
+    fn test_cascading_replica_topology() {
+        let primary = NodeBuilder::default()
+            .with_node_id("1".to_string())
+            .with_slots("0-100".to_string())
+            .with_role(ServerRole::Primary)
+            .with_primary_node_id(String::default())
+            .with_upstream_node_id(String::default())
+            .build();
+
+        let replica = NodeBuilder::default()
+            .with_node_id("2".to_string())
+            .with_slots("0-100".to_string())
+            .with_role(ServerRole::Replica)
+            .with_primary_node_id("1".to_string())
+            .with_upstream_node_id("1".to_string())
+            .build();
+        assert!(!replica.is_cascading_replica());
+
+        let mut cascading = NodeBuilder::default()
+            .with_node_id("3".to_string())
+            .with_slots("0-100".to_string())
+            .with_role(ServerRole::Replica)
+            .with_primary_node_id("1".to_string())
+            .with_upstream_node_id("2".to_string())
+            .build();
+        assert!(cascading.is_cascading_replica());
+
+        let db = Persistence::with_options(Arc::new(StdRwLock::new(ServerOptions::default())));
+        assert_eq!(
+            ShardIsStableResult::Ok,
+            db.is_shard_stable(&[&primary, &replica, &cascading])
+                .unwrap()
+        );
+
+        // The upstream node is not part of the shard
+        assert_eq!(
+            ShardIsStableResult::ReplicaIsMissingUpstream,
+            db.is_shard_stable(&[&primary, &cascading]).unwrap()
+        );
+
+        // Nodes written by older versions do not have the upstream field
+        let mut as_json = serde_json::to_value(&cascading).unwrap();
+        as_json.as_object_mut().unwrap().remove("upstream_node_id");
+        let de_node: Node = serde_json::from_value(as_json).unwrap();
+        assert!(de_node.upstream_node_id().is_empty());
+        assert!(!de_node.is_cascading_replica());
+
+        cascading.set_upstream_node_id(String::default());
+        assert_eq!(cascading, de_node);
+    }
 }
@@ -0,0 +1,641 @@
+use crate::{
+    replication::{StorageUpdates, UpdatesNotifier},
+    server::{BacklogTelemetry, ReplicationTelemetry, ServerOptions},
+    storage::GetChangesLimits,
+    utils, SableError, StorageAdapter,
+};
+use bytes::BytesMut;
+use std::collections::VecDeque;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, RwLock as StdRwLock};
+use std::time::Duration;
+
+#[cfg(not(test))]
+use tracing::{debug, error, info};
+
+#[cfg(test)]
+use std::{println as info, println as debug, println as error};
+
+const OPTIONS_LOCK_ERR: &str = "Failed to obtain read lock on ServerOptions";
+const SEGMENT_FILE_EXT: &str = "log";
+/// Each record starts with a header: timestamp (u64), the payload length (u64) and the number of
+/// write batches in the payload (u64). The payload is followed by the write batches index: the
+/// sequence number (u64) and the payload data offset (u64) of each batch
+const RECORD_HEADER_SIZE: u64 = 24;
+const BATCH_INDEX_ENTRY_SIZE: u64 = 16;
+/// The backlog is split into `SEGMENTS_COUNT` files. Once the backlog exceeds its size, the
+/// oldest segment is deleted
+const SEGMENTS_COUNT: u64 = 8;
+const MIN_SEGMENT_SIZE: u64 = 1 << 20; // 1mb
+
+lazy_static::lazy_static! {
+    static ref BACKLOG: StdRwLock<Option<Arc<ReplicationBacklog>>> = StdRwLock::default();
+}
+
+/// The location of a `StorageUpdates` record in the backlog
+#[derive(Clone, Copy, Debug)]
+struct RecordLocation {
+    start_sequence: u64,
+    end_sequence: u64,
+    /// Offset of the record header in the segment file
+    offset: u64,
+    /// The payload length
+    len: u64,
+    /// The number of write batches in the payload
+    batches_count: u64,
+}
+
+struct Segment {
+    path: PathBuf,
+    size: u64,
+    /// The time (milliseconds since UNIX_EPOCH) in which the first record was written
+    first_timestamp_ms: u64,
+    records: Vec<RecordLocation>,
+}
+
+impl Segment {
+    fn first_sequence(&self) -> Option<u64> {
+        self.records.first().map(|record| record.start_sequence)
+    }
+
+    fn next_sequence(&self) -> Option<u64> {
+        self.records.last().map(|record| record.end_sequence)
+    }
+}
+
+#[derive(Default)]
+struct BacklogState {
+    segments: VecDeque<Segment>,
+    /// Total size of the segments, in bytes
+    size: u64,
+    /// The segment we are appending to
+    writer: Option<std::fs::File>,
+}
+
+/// A size bounded, on-disk, log of `StorageUpdates` messages. Replicas that fell behind the WAL
+/// retention (`wal_ttl_seconds`) can still catch up from it instead of doing a fullsync
+pub struct ReplicationBacklog {
+    folder: PathBuf,
+    max_size: u64,
+    segment_size: u64,
+    state: Mutex<BacklogState>,
+}
+
+impl ReplicationBacklog {
+    /// Open the backlog located at `folder`, create it if it does not exist
+    pub fn open(folder: &Path, max_size: u64) -> Result<Self, SableError> {
+        std::fs::create_dir_all(folder)?;
+
+        let mut paths = Vec::<PathBuf>::new();
+        for entry in std::fs::read_dir(folder)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == SEGMENT_FILE_EXT) {
+                paths.push(path);
+            }
+        }
+        // Segment names start with their first sequence number, zero padded
+        paths.sort();
+
+        let mut state = BacklogState::default();
+        for path in paths {
+            let segment = match Self::load_segment(&path) {
+                Ok(Some(segment)) => segment,
+                Ok(None) => {
+                    let _ = std::fs::remove_file(&path);
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to load backlog segment {}. {:?}", path.display(), e);
+                    let _ = std::fs::remove_file(&path);
+                    continue;
+                }
+            };
+
+            // The backlog must not have gaps: drop everything that comes before a gap
+            let last_sequence = state
+                .segments
+                .back()
+                .and_then(|segment| segment.next_sequence());
+            if last_sequence.is_some() && last_sequence != segment.first_sequence() {
+                tracing::warn!(
+                    "Gap found in the replication backlog before segment {}",
+                    path.display()
+                );
+                Self::remove_segments(&mut state);
+            }
+            state.size = state.size.saturating_add(segment.size);
+            state.segments.push_back(segment);
+        }
+
+        let backlog = ReplicationBacklog {
+            folder: folder.to_path_buf(),
+            max_size,
+            segment_size: (max_size / SEGMENTS_COUNT).max(MIN_SEGMENT_SIZE),
+            state: Mutex::new(state),
+        };
+        info!(
+            "Replication backlog {} opened. {}",
+            folder.display(),
+            backlog.telemetry()
+        );
+        Ok(backlog)
+    }
+
+    /// Return the replication backlog, `None` if it is disabled
+    pub fn get() -> Option<Arc<ReplicationBacklog>> {
+        BACKLOG.read().expect("poisoned mutex").clone()
+    }
+
+    /// Open the replication backlog (if enabled) and start a thread that appends the database
+    /// changes to it
+    pub fn start(
+        options: Arc<StdRwLock<ServerOptions>>,
+        store: StorageAdapter,
+    ) -> Result<(), SableError> {
+        let (max_size, folder, single_update_buffer_size, num_updates_per_message) = {
+            let options = options.read().expect(OPTIONS_LOCK_ERR);
+            (
+                options.replication_limits.backlog_size as u64,
+                PathBuf::from(format!("{}.backlog", options.open_params.db_path.display())),
+                options.replication_limits.single_update_buffer_size as u64,
+                options.replication_limits.num_updates_per_message as u64,
+            )
+        };
+
+        if max_size == 0 {
+            ReplicationTelemetry::set_backlog_info(None);
+            return Ok(());
+        }
+
+        let backlog = Arc::new(Self::open(&folder, max_size)?);
+
+        // The database was replaced since the backlog was written
+        let latest_sequence = store.latest_sequence_number()?;
+        if backlog
+            .next_sequence()
+            .is_some_and(|next_sequence| next_sequence > latest_sequence)
+        {
+            tracing::warn!("Replication backlog is ahead of the database, clearing it");
+            backlog.clear();
+        }
+
+        *BACKLOG.write().expect("poisoned mutex") = Some(backlog.clone());
+        ReplicationTelemetry::set_backlog_info(Some(backlog.telemetry()));
+
+        std::thread::Builder::new()
+            .name("ReplicationBacklog".to_string())
+            .spawn(move || {
+                let limits = Rc::new(
+                    GetChangesLimits::builder()
+                        .with_memory(single_update_buffer_size)
+                        .with_max_changes_count(num_updates_per_message)
+                        .build(),
+                );
+                backlog.writer_loop(store, limits);
+            })?;
+        Ok(())
+    }
+
+    /// Tail the database changes and append them to the backlog
+    fn writer_loop(&self, store: StorageAdapter, limits: Rc<GetChangesLimits>) {
+        info!("Replication backlog writer started");
+        loop {
+            // Read the generation before we look for changes, so changes committed in between
+            // are not missed
+            let generation = UpdatesNotifier::generation();
+            let from_sequence = match self.next_sequence() {
+                Some(next_sequence) => next_sequence,
+                None => match store.latest_sequence_number() {
+                    Ok(latest_sequence) => latest_sequence,
+                    Err(e) => {
+                        error!("Failed to read the latest sequence number. {:?}", e);
+                        std::thread::sleep(Duration::from_secs(1));
+                        continue;
+                    }
+                },
+            };
+
+            match store.storage_updates_since(from_sequence, limits.clone()) {
+                Ok(storage_updates) if storage_updates.end_seq_number <= from_sequence => {
+                    // No new changes
+                    UpdatesNotifier::wait_for_changes(generation, Duration::from_secs(1));
+                }
+                Ok(storage_updates) => {
+                    if let Err(e) = self.append(&storage_updates) {
+                        error!(
+                            "Failed to append changes to the replication backlog. {:?}",
+                            e
+                        );
+                        std::thread::sleep(Duration::from_secs(1));
+                    }
+                }
+                Err(e) => {
+                    // The WAL no longer holds the changes that follow the backlog
+                    tracing::warn!(
+                        "Failed to read changes since {}, clearing the replication backlog. {:?}",
+                        from_sequence,
+                        e
+                    );
+                    self.clear();
+                    std::thread::sleep(Duration::from_secs(1));
+                }
+            }
+        }
+    }
+
+    /// Append `storage_updates` to the backlog. Delete the oldest segments if the backlog
+    /// exceeds its size
+    pub fn append(&self, storage_updates: &StorageUpdates) -> Result<(), SableError> {
+        let mut state = self.state.lock().expect("poisoned mutex");
+        let timestamp_ms = utils::current_time(utils::CurrentTimeResolution::Milliseconds);
+
+        let start_new_segment = state.writer.is_none()
+            || state
+                .segments
+                .back()
+                .is_none_or(|segment| segment.size >= self.segment_size);
+        if start_new_segment {
+            let path = self.folder.join(format!(
+                "{:020}-{:020}.{}",
+                storage_updates.start_seq_number,
+                utils::current_time(utils::CurrentTimeResolution::Microseconds),
+                SEGMENT_FILE_EXT
+            ));
+            debug!("Starting backlog segment {}", path.display());
+            state.writer = Some(
+                std::fs::File::options()
+                    .create(true)
+                    .write(true)
+                    .truncate(true)
+                    .open(&path)?,
+            );
+            state.segments.push_back(Segment {
+                path,
+                size: 0,
+                first_timestamp_ms: timestamp_ms,
+                records: Vec::new(),
+            });
+        }
+
+        let payload = storage_updates.to_bytes();
+        let batches_count = storage_updates.batches.len() as u64;
+        let mut record = Vec::<u8>::with_capacity(
+            (RECORD_HEADER_SIZE + batches_count * BATCH_INDEX_ENTRY_SIZE) as usize + payload.len(),
+        );
+        record.extend_from_slice(&timestamp_ms.to_be_bytes());
+        record.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+        record.extend_from_slice(&batches_count.to_be_bytes());
+        record.extend_from_slice(&payload);
+        for (seq, offset) in &storage_updates.batches {
+            record.extend_from_slice(&seq.to_be_bytes());
+            record.extend_from_slice(&offset.to_be_bytes());
+        }
+
+        let Some(writer) = &mut state.writer else {
+            return Err(SableError::InternalError(
+                "Replication backlog has no active segment".into(),
+            ));
+        };
+        writer.write_all(&record)?;
+        writer.flush()?;
+
+        let Some(segment) = state.segments.back_mut() else {
+            return Err(SableError::InternalError(
+                "Replication backlog has no active segment".into(),
+            ));
+        };
+        segment.records.push(RecordLocation {
+            start_sequence: storage_updates.start_seq_number,
+            end_sequence: storage_updates.end_seq_number,
+            offset: segment.size,
+            len: payload.len() as u64,
+            batches_count,
+        });
+        segment.size = segment.size.saturating_add(record.len() as u64);
+        state.size = state.size.saturating_add(record.len() as u64);
+
+        // Delete the oldest segments (never the one we are writing to)
+        while state.size > self.max_size && state.segments.len() > 1 {
+            if let Some(segment) = state.segments.pop_front() {
+                debug!("Deleting backlog segment {}", segment.path.display());
+                let _ = std::fs::remove_file(&segment.path);
+                state.size = state.size.saturating_sub(segment.size);
+            }
+        }
+
+        ReplicationTelemetry::set_backlog_info(Some(Self::state_telemetry(&state)));
+        Ok(())
+    }
+
+    /// Return the changes since `from_sequence`. Return `None` if the backlog does not hold them
+    pub fn updates_since(&self, from_sequence: u64) -> Result<Option<StorageUpdates>, SableError> {
+        let state = self.state.lock().expect("poisoned mutex");
+
+        // Locate the last record that starts at, or before, `from_sequence`. The write batches of
+        // that record that come before `from_sequence` are not replayed
+        let Some((segment_index, segment)) =
+            state
+                .segments
+                .iter()
+                .enumerate()
+                .rev()
+                .find(|(_, segment)| {
+                    segment
+                        .first_sequence()
+                        .is_some_and(|first_sequence| first_sequence <= from_sequence)
+                })
+        else {
+            return Ok(None);
+        };
+
+        let record_index = segment
+            .records
+            .partition_point(|record| record.start_sequence <= from_sequence)
+            .saturating_sub(1);
+        let location = segment.records[record_index];
+
+        let is_last_record =
+            segment_index + 1 == state.segments.len() && record_index + 1 == segment.records.len();
+        if is_last_record && from_sequence >= location.end_sequence {
+            // The backlog has nothing newer
+            return Ok(None);
+        }
+
+        let mut fp = std::fs::File::open(&segment.path)?;
+        fp.seek(SeekFrom::Start(location.offset + RECORD_HEADER_SIZE))?;
+        let mut payload = vec![0u8; location.len as usize];
+        fp.read_exact(&mut payload)?;
+        let mut batches_index =
+            vec![0u8; (location.batches_count * BATCH_INDEX_ENTRY_SIZE) as usize];
+        fp.read_exact(&mut batches_index)?;
+
+        let Some(mut storage_updates) = StorageUpdates::from_bytes(&BytesMut::from(&payload[..]))
+        else {
+            return Err(SableError::Corrupted(format!(
+                "Failed to read record from backlog segment {}",
+                segment.path.display()
+            )));
+        };
+        storage_updates.batches = Self::decode_batches_index(&batches_index);
+        storage_updates.skip_batches_before(from_sequence);
+        Ok(Some(storage_updates))
+    }
+
+    /// The sequence number that follows the last record in the backlog
+    pub fn next_sequence(&self) -> Option<u64> {
+        let state = self.state.lock().expect("poisoned mutex");
+        state
+            .segments
+            .back()
+            .and_then(|segment| segment.next_sequence())
+    }
+
+    /// Delete all the records from the backlog
+    pub fn clear(&self) {
+        let mut state = self.state.lock().expect("poisoned mutex");
+        Self::remove_segments(&mut state);
+        ReplicationTelemetry::set_backlog_info(Some(Self::state_telemetry(&state)));
+    }
+
+    pub fn telemetry(&self) -> BacklogTelemetry {
+        let state = self.state.lock().expect("poisoned mutex");
+        Self::state_telemetry(&state)
+    }
+
+    fn state_telemetry(state: &BacklogState) -> BacklogTelemetry {
+        let Some(oldest_segment) = state.segments.front() else {
+            return BacklogTelemetry::default();
+        };
+        BacklogTelemetry {
+            size: state.size,
+            first_sequence_number: oldest_segment.first_sequence().unwrap_or_default(),
+            next_sequence_number: state
+                .segments
+                .back()
+                .and_then(|segment| segment.next_sequence())
+                .unwrap_or_default(),
+            oldest_entry_timestamp_ms: oldest_segment.first_timestamp_ms,
+        }
+    }
+
+    fn remove_segments(state: &mut BacklogState) {
+        state.writer = None;
+        for segment in state.segments.drain(..) {
+            let _ = std::fs::remove_file(&segment.path);
+        }
+        state.size = 0;
+    }
+
+    fn read_u64_at(buffer: &[u8], pos: usize) -> u64 {
+        let mut value = [0u8; 8];
+        value.copy_from_slice(&buffer[pos..pos + 8]);
+        u64::from_be_bytes(value)
+    }
+
+    /// Decode the write batches index of a record: (sequence number, payload data offset) pairs
+    fn decode_batches_index(buffer: &[u8]) -> Vec<(u64, u64)> {
+        buffer
+            .chunks_exact(BATCH_INDEX_ENTRY_SIZE as usize)
+            .map(|entry| (Self::read_u64_at(entry, 0), Self::read_u64_at(entry, 8)))
+            .collect()
+    }
+
+    /// Load the records index of the segment file `path`. A partially written record at the end
+    /// of the file is truncated. Return `None` if the segment has no records
+    fn load_segment(path: &Path) -> Result<Option<Segment>, SableError> {
+        let fp = std::fs::File::open(path)?;
+        let file_len = fp.metadata()?.len();
+        let mut reader = BufReader::new(fp);
+
+        let mut offset = 0u64;
+        let mut first_timestamp_ms = 0u64;
+        let mut records = Vec::<RecordLocation>::new();
+        while offset + RECORD_HEADER_SIZE <= file_len {
+            let mut header = [0u8; RECORD_HEADER_SIZE as usize];
+            reader.read_exact(&mut header)?;
+            let timestamp_ms = Self::read_u64_at(&header, 0);
+            let len = Self::read_u64_at(&header, 8);
+            let batches_count = Self::read_u64_at(&header, 16);
+            let record_len = RECORD_HEADER_SIZE
+                .saturating_add(len)
+                .saturating_add(batches_count.saturating_mul(BATCH_INDEX_ENTRY_SIZE));
+
+            if offset.saturating_add(record_len) > file_len {
+                break;
+            }
+
+            let mut payload = vec![0u8; len as usize];
+            reader.read_exact(&mut payload)?;
+            reader.seek_relative((batches_count * BATCH_INDEX_ENTRY_SIZE) as i64)?;
+            let Some(storage_updates) = StorageUpdates::from_bytes(&BytesMut::from(&payload[..]))
+            else {
+                break;
+            };
+
+            if records.is_empty() {
+                first_timestamp_ms = timestamp_ms;
+            }
+            records.push(RecordLocation {
+                start_sequence: storage_updates.start_seq_number,
+                end_sequence: storage_updates.end_seq_number,
+                offset,
+                len,
+                batches_count,
+            });
+            offset += record_len;
+        }
+
+        if offset < file_len {
+            tracing::warn!(
+                "Truncating partially written backlog segment {} to {} bytes",
+                path.display(),
+                offset
+            );
+            std::fs::File::options()
+                .write(true)
+                .open(path)?
+                .set_len(offset)?;
+        }
+
+        if records.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Segment {
+            path: path.to_path_buf(),
+            size: offset,
+            first_timestamp_ms,
+            records,
+        }))
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_updates(start_seq_number: u64, end_seq_number: u64) -> StorageUpdates {
+        let mut storage_updates = StorageUpdates::from_seq_number(start_seq_number);
+        storage_updates.end_seq_number = end_seq_number;
+        storage_updates.changes_count = 1;
+        storage_updates.add_put(
+            format!("key_{}", start_seq_number).as_bytes(),
+            &[b'x'; 1024],
+        );
+        storage_updates
+    }
+
+    #[test]
+    fn test_backlog_updates_since() -> Result<(), SableError> {
+        let folder = PathBuf::from("tests/test_backlog_updates_since.backlog");
+        let _ = std::fs::remove_dir_all(&folder);
+
+        let backlog = ReplicationBacklog::open(&folder, 1 << 30)?;
+        assert!(backlog.updates_since(0)?.is_none());
+
+        backlog.append(&make_updates(1, 10))?;
+        backlog.append(&make_updates(10, 20))?;
+        backlog.append(&make_updates(20, 30))?;
+        assert_eq!(backlog.next_sequence(), Some(30));
+
+        // Exact match
+        let storage_updates = backlog.updates_since(10)?.unwrap();
+        assert_eq!(storage_updates.start_seq_number, 10);
+        assert_eq!(storage_updates.end_seq_number, 20);
+
+        // A sequence in the middle of a record returns the record that holds it
+        let storage_updates = backlog.updates_since(25)?.unwrap();
+        assert_eq!(storage_updates.start_seq_number, 20);
+
+        // Not covered by the backlog
+        assert!(backlog.updates_since(0)?.is_none());
+        assert!(backlog.updates_since(30)?.is_none());
+
+        // The index is rebuilt when the backlog is re-opened
+        drop(backlog);
+        let backlog = ReplicationBacklog::open(&folder, 1 << 30)?;
+        assert_eq!(backlog.next_sequence(), Some(30));
+        assert_eq!(backlog.updates_since(1)?.unwrap().end_seq_number, 10);
+        assert_eq!(backlog.telemetry().first_sequence_number, 1);
+
+        backlog.clear();
+        assert!(backlog.next_sequence().is_none());
+        let _ = std::fs::remove_dir_all(&folder);
+        Ok(())
+    }
+
+    #[test]
+    fn test_backlog_skips_older_batches() -> Result<(), SableError> {
+        let folder = PathBuf::from("tests/test_backlog_skips_older_batches.backlog");
+        let _ = std::fs::remove_dir_all(&folder);
+
+        // A record made of 3 write batches
+        let mut storage_updates = StorageUpdates::from_seq_number(10);
+        for (seq, key) in [(10u64, "a"), (12, "b"), (15, "c")] {
+            storage_updates.start_batch(seq);
+            storage_updates.add_put(key.as_bytes(), b"value");
+            storage_updates.end_seq_number = seq;
+            storage_updates.changes_count += 1;
+        }
+
+        let mut expected = StorageUpdates::from_seq_number(12);
+        expected.add_put(b"b", b"value");
+        expected.add_put(b"c", b"value");
+
+        let backlog = ReplicationBacklog::open(&folder, 1 << 30)?;
+        backlog.append(&storage_updates)?;
+        for backlog in [
+            backlog,
+            // the batches index is kept in the segment file
+            ReplicationBacklog::open(&folder, 1 << 30)?,
+        ] {
+            assert_eq!(backlog.updates_since(10)?.unwrap().changes_count, 3);
+
+            let storage_updates = backlog.updates_since(12)?.unwrap();
+            assert_eq!(storage_updates.start_seq_number, 12);
+            assert_eq!(storage_updates.end_seq_number, 15);
+            assert_eq!(storage_updates.changes_count, 2);
+            assert_eq!(storage_updates.serialised_data, expected.serialised_data);
+
+            let storage_updates = backlog.updates_since(13)?.unwrap();
+            assert_eq!(storage_updates.start_seq_number, 15);
+            assert_eq!(storage_updates.changes_count, 1);
+        }
+        let _ = std::fs::remove_dir_all(&folder);
+        Ok(())
+    }
+
+    #[test]
+    fn test_backlog_size_is_bounded() -> Result<(), SableError> {
+        let folder = PathBuf::from("tests/test_backlog_size_is_bounded.backlog");
+        let _ = std::fs::remove_dir_all(&folder);
+
+        // Each segment holds ~1MB
+        let backlog = ReplicationBacklog::open(&folder, 4 << 20)?;
+        for i in 0..10_000u64 {
+            backlog.append(&make_updates(i, i + 1))?;
+        }
+
+        let telemetry = backlog.telemetry();
+        assert!(telemetry.size <= 4 << 20);
+        assert!(telemetry.first_sequence_number > 0);
+        assert_eq!(telemetry.next_sequence_number, 10_000);
+
+        // The oldest records were dropped
+        assert!(backlog.updates_since(0)?.is_none());
+        assert!(backlog
+            .updates_since(telemetry.first_sequence_number)?
+            .is_some());
+        let _ = std::fs::remove_dir_all(&folder);
+        Ok(())
+    }
+}
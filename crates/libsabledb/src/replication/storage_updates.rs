@@ -119,6 +119,9 @@ pub struct StorageUpdates {
     /// Binary representation of the changes that can be sent over the network
     /// or stored to disk. All serialised binary data is using big endian
     pub serialised_data: BytesMut,
+    /// The sequence number of each write batch and the offset of its first change in
+    /// `serialised_data`. This is not serialised (see `ReplicationBacklog`)
+    pub batches: Vec<(u64, u64)>,
 }
 
 impl std::fmt::Display for StorageUpdates {
@@ -155,6 +158,32 @@ impl StorageUpdates {
         StorageUpdatesRecord::serialise_del(&mut writer, key);
     }
 
+    /// Mark the start of a write batch with sequence number `seq`: the changes added from now on
+    /// belong to it
+    pub fn start_batch(&mut self, seq: u64) {
+        self.batches.push((seq, self.serialised_data.len() as u64));
+    }
+
+    /// Drop the write batches that come before sequence number `seq`. Does nothing if the
+    /// batches are not known
+    pub fn skip_batches_before(&mut self, seq: u64) {
+        let first_batch = self
+            .batches
+            .partition_point(|(batch_seq, _)| *batch_seq < seq);
+        if first_batch == 0 || first_batch == self.batches.len() {
+            return;
+        }
+
+        let (start_seq_number, offset) = self.batches[first_batch];
+        let _ = self.serialised_data.split_to(offset as usize);
+        self.batches.drain(..first_batch);
+        for (_, batch_offset) in self.batches.iter_mut() {
+            *batch_offset -= offset;
+        }
+        self.start_seq_number = start_seq_number;
+        self.changes_count = self.changes_count.saturating_sub(first_batch as u64);
+    }
+
     /// Return the size of the changes, in bytes
     pub fn len(&self) -> u64 {
         self.serialised_data.len() as u64
@@ -191,6 +220,7 @@ impl StorageUpdates {
             end_seq_number,
             changes_count,
             serialised_data,
+            batches: Vec::default(),
         })
     }
 
@@ -216,6 +246,7 @@ mod tests {
             end_seq_number: 57,
             changes_count: 3,
             serialised_data: BytesMut::from("hello world"),
+            batches: Vec::default(),
         };
 
         let mut buffer = updates.to_bytes();
@@ -0,0 +1,96 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+lazy_static::lazy_static! {
+    static ref UPDATES_LOCK: Mutex<()> = Mutex::default();
+    static ref UPDATES_CONDVAR: Condvar = Condvar::new();
+}
+
+/// Incremented every time a change is committed to the database
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Number of threads waiting for changes
+static WAITERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Wakes up the replication threads that stream changes to the replicas as soon as a change
+/// is committed to the database
+pub struct UpdatesNotifier {}
+
+impl UpdatesNotifier {
+    /// Notify the waiting threads that a change was committed
+    pub fn notify() {
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+        if WAITERS.load(Ordering::SeqCst) == 0 {
+            // fast path: nobody is waiting
+            return;
+        }
+        let _guard = UPDATES_LOCK.lock().expect("poisoned mutex");
+        UPDATES_CONDVAR.notify_all();
+    }
+
+    /// Return the current generation. Pass it to `wait_for_changes` so changes committed
+    /// after this call are not missed
+    pub fn generation() -> u64 {
+        GENERATION.load(Ordering::SeqCst)
+    }
+
+    /// Block the calling thread until a change is committed after `generation` was read
+    /// or until `timeout` expires. Return `true` if a change was committed
+    pub fn wait_for_changes(generation: u64, timeout: Duration) -> bool {
+        WAITERS.fetch_add(1, Ordering::SeqCst);
+        let deadline = Instant::now() + timeout;
+        let mut guard = UPDATES_LOCK.lock().expect("poisoned mutex");
+        let changed = loop {
+            if GENERATION.load(Ordering::SeqCst) != generation {
+                break true;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break false;
+            }
+            guard = match UPDATES_CONDVAR.wait_timeout(guard, remaining) {
+                Ok((guard, _)) => guard,
+                Err(_) => break false,
+            };
+        };
+        drop(guard);
+        WAITERS.fetch_sub(1, Ordering::SeqCst);
+        changed
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wait_for_changes() {
+        // A change committed by another thread wakes us up
+        let generation = UpdatesNotifier::generation();
+        let handle = std::thread::spawn(|| {
+            std::thread::sleep(Duration::from_millis(50));
+            UpdatesNotifier::notify();
+        });
+        assert!(UpdatesNotifier::wait_for_changes(
+            generation,
+            Duration::from_secs(10)
+        ));
+        handle.join().unwrap();
+
+        // A change committed before we started waiting is not missed
+        let generation = UpdatesNotifier::generation();
+        UpdatesNotifier::notify();
+        assert!(UpdatesNotifier::wait_for_changes(
+            generation,
+            Duration::from_millis(10)
+        ));
+    }
+}
@@ -0,0 +1,671 @@
+use crate::{
+    commands::{commands_manager, CommandMetadata, ValkeyCommandFlags},
+    server::ServerOptions,
+    utils::PatternMatcher,
+    SableError, ValkeyCommand, ValkeyCommandName,
+};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+/// The user used by clients that did not authenticate
+pub const DEFAULT_USER: &str = "default";
+
+/// The categories that can be used in `+@<category>` / `-@<category>` rules. Each category is
+/// mapped into a `ValkeyCommandFlags` flag
+pub const ACL_CATEGORIES: [&str; 6] =
+    ["read", "write", "admin", "connection", "blocking", "pubsub"];
+
+const POISONED_MUTEX: &str = "poisoned mutex";
+const OPTIONS_LOCK_ERR: &str = "Failed to obtain read lock on ServerOptions";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CommandSelector {
+    /// `@all`
+    All,
+    /// A category, e.g. `@read`
+    Category(String),
+    /// A single command, e.g. `get`
+    Command(String),
+}
+
+impl CommandSelector {
+    fn matches(&self, metadata: &CommandMetadata, command_name: &str) -> bool {
+        match self {
+            CommandSelector::All => true,
+            CommandSelector::Category(category) => ValkeyCommandFlags::from_str(category)
+                .map(|flag| metadata.is_in_category(flag))
+                .unwrap_or_default(),
+            CommandSelector::Command(name) => name == command_name,
+        }
+    }
+}
+
+impl std::fmt::Display for CommandSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CommandSelector::All => write!(f, "@all"),
+            CommandSelector::Category(category) => write!(f, "@{}", category),
+            CommandSelector::Command(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct CommandRule {
+    allow: bool,
+    selector: CommandSelector,
+}
+
+/// The result of checking a command against the user rules
+#[derive(Debug, PartialEq, Eq)]
+pub enum AclCheckResult {
+    Ok,
+    /// The user is not allowed to run the command
+    CommandDenied,
+    /// The command accesses a key that the user is not allowed to access
+    KeyDenied,
+    /// The command accesses a channel that the user is not allowed to access
+    ChannelDenied,
+}
+
+/// An ACL user: its passwords and the rules that control which commands, keys and channels
+/// it can access
+#[derive(Clone, Debug)]
+pub struct AclUser {
+    name: String,
+    enabled: bool,
+    nopass: bool,
+    /// SHA256 of the user passwords (hex)
+    passwords: BTreeSet<String>,
+    /// Command rules, evaluated in order. The last matching rule wins
+    commands: Vec<CommandRule>,
+    /// Key patterns the user can access
+    keys: Vec<String>,
+    /// Pub/Sub channel patterns the user can access
+    channels: Vec<String>,
+}
+
+impl AclUser {
+    /// Create a new user. A new user is disabled, has no passwords and can not run any command
+    pub fn new(name: &str) -> Self {
+        AclUser {
+            name: name.to_string(),
+            enabled: false,
+            nopass: false,
+            passwords: BTreeSet::default(),
+            commands: Vec::default(),
+            keys: Vec::default(),
+            channels: Vec::default(),
+        }
+    }
+
+    /// The `default` user: enabled, no password and full access
+    pub fn default_user() -> Self {
+        let mut user = AclUser::new(DEFAULT_USER);
+        for rule in ["on", "nopass", "allkeys", "allchannels", "allcommands"] {
+            // can't fail
+            let _ = user.apply_rule(rule);
+        }
+        user
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_nopass(&self) -> bool {
+        self.nopass
+    }
+
+    /// Return `true` if `password` is valid for this user
+    pub fn check_password(&self, password: &[u8]) -> bool {
+        self.enabled && (self.nopass || self.passwords.contains(&Self::hash_password(password)))
+    }
+
+    /// Apply a single `ACL SETUSER` rule. On error, return the reason
+    pub fn apply_rule(&mut self, rule: &str) -> Result<(), String> {
+        match rule.to_lowercase().as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allkeys" => self.keys = vec!["*".to_string()],
+            "resetkeys" => self.keys.clear(),
+            "allchannels" => self.channels = vec!["*".to_string()],
+            "resetchannels" => self.channels.clear(),
+            "allcommands" => self.add_command_rule(true, CommandSelector::All),
+            "nocommands" => self.add_command_rule(false, CommandSelector::All),
+            "reset" => *self = AclUser::new(&self.name),
+            _ => {
+                let Some(first) = rule.chars().next() else {
+                    return Err("Syntax error".to_string());
+                };
+                let value = &rule[first.len_utf8()..];
+                match first {
+                    '>' => {
+                        self.passwords.insert(Self::hash_password(value.as_bytes()));
+                        self.nopass = false;
+                    }
+                    '<' => {
+                        self.passwords
+                            .remove(&Self::hash_password(value.as_bytes()));
+                    }
+                    '#' => {
+                        if value.len() != 64 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+                            return Err("The password hash must be exactly 64 characters and contain only lowercase hexadecimal characters".to_string());
+                        }
+                        self.passwords.insert(value.to_lowercase());
+                        self.nopass = false;
+                    }
+                    '!' => {
+                        self.passwords.remove(&value.to_lowercase());
+                    }
+                    '~' => Self::add_pattern(&mut self.keys, value),
+                    '&' => Self::add_pattern(&mut self.channels, value),
+                    '+' | '-' => {
+                        let selector = Self::parse_command_selector(value)?;
+                        self.add_command_rule(first == '+', selector);
+                    }
+                    _ => return Err("Syntax error".to_string()),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether this user can run `command`
+    pub fn check_command(&self, command: &ValkeyCommand) -> AclCheckResult {
+        if !self.can_run(command.metadata(), command.main_command()) {
+            return AclCheckResult::CommandDenied;
+        }
+
+        // The sharded Pub/Sub commands declare their channels as keys (for slot routing), these
+        // are checked against the channel patterns below. When the keys of a command can not be
+        // located, only users that can access all the keys may run it
+        if !command.metadata().is_pubsub() {
+            let allowed = match command.all_keys() {
+                Some(keys) => keys.iter().all(|key| self.can_access_key(key)),
+                None => self.keys.iter().any(|pattern| pattern == "*"),
+            };
+            if !allowed {
+                return AclCheckResult::KeyDenied;
+            }
+        }
+
+        let (channels, is_pattern) = match command.metadata().name() {
+            ValkeyCommandName::Publish | ValkeyCommandName::Spublish => {
+                (command.args_vec().iter().skip(1).take(1), false)
+            }
+            ValkeyCommandName::Subscribe | ValkeyCommandName::Ssubscribe => {
+                (command.args_vec().iter().skip(1).take(usize::MAX), false)
+            }
+            ValkeyCommandName::Psubscribe => {
+                (command.args_vec().iter().skip(1).take(usize::MAX), true)
+            }
+            _ => return AclCheckResult::Ok,
+        };
+
+        for channel in channels {
+            let allowed = if is_pattern {
+                // A pattern must be explicitly allowed
+                self.channels
+                    .iter()
+                    .any(|p| p == "*" || p.as_bytes() == channel.as_ref())
+            } else {
+                Self::matches_any(&self.channels, channel)
+            };
+            if !allowed {
+                return AclCheckResult::ChannelDenied;
+            }
+        }
+        AclCheckResult::Ok
+    }
+
+    /// User flags, as reported by `ACL GETUSER`
+    pub fn flags(&self) -> Vec<&'static str> {
+        let mut flags = vec![if self.enabled { "on" } else { "off" }];
+        if self.nopass {
+            flags.push("nopass");
+        }
+        flags
+    }
+
+    /// The SHA256 of the user passwords
+    pub fn password_hashes(&self) -> Vec<&String> {
+        self.passwords.iter().collect()
+    }
+
+    /// The command rules, e.g. `-@all +get +@read`
+    pub fn commands_description(&self) -> String {
+        let mut rules = Vec::<String>::with_capacity(self.commands.len() + 1);
+        if !matches!(
+            self.commands.first(),
+            Some(CommandRule {
+                selector: CommandSelector::All,
+                ..
+            })
+        ) {
+            rules.push("-@all".to_string());
+        }
+        for rule in &self.commands {
+            rules.push(format!(
+                "{}{}",
+                if rule.allow { "+" } else { "-" },
+                rule.selector
+            ));
+        }
+        rules.join(" ")
+    }
+
+    /// The key patterns, e.g. `~cache:* ~session:*`
+    pub fn keys_description(&self) -> String {
+        Self::patterns_description('~', &self.keys)
+    }
+
+    /// The channel patterns, e.g. `&news.*`
+    pub fn channels_description(&self) -> String {
+        Self::patterns_description('&', &self.channels)
+    }
+
+    /// Describe the user as a list of rules, as reported by `ACL LIST` and stored in the ACL
+    /// file. Applying these rules to a new user re-creates this user
+    pub fn description(&self) -> String {
+        let mut parts = vec![format!("user {}", self.name)];
+        parts.extend(self.flags().iter().map(|flag| flag.to_string()));
+        parts.extend(self.passwords.iter().map(|hash| format!("#{}", hash)));
+        for description in [self.keys_description(), self.channels_description()] {
+            if !description.is_empty() {
+                parts.push(description);
+            }
+        }
+        parts.push(self.commands_description());
+        parts.join(" ")
+    }
+
+    fn can_run(&self, metadata: &CommandMetadata, command_name: &str) -> bool {
+        let mut allowed = false;
+        for rule in &self.commands {
+            if rule.selector.matches(metadata, command_name) {
+                allowed = rule.allow;
+            }
+        }
+        allowed
+    }
+
+    fn parse_command_selector(value: &str) -> Result<CommandSelector, String> {
+        let value = value.to_lowercase();
+        if let Some(category) = value.strip_prefix('@') {
+            if category == "all" {
+                Ok(CommandSelector::All)
+            } else if ACL_CATEGORIES.contains(&category) {
+                Ok(CommandSelector::Category(category.to_string()))
+            } else {
+                Err("Unknown command category".to_string())
+            }
+        } else if commands_manager()
+            .all_commands()
+            .contains_key(value.as_str())
+        {
+            Ok(CommandSelector::Command(value))
+        } else {
+            Err("Unknown command".to_string())
+        }
+    }
+
+    fn add_command_rule(&mut self, allow: bool, selector: CommandSelector) {
+        if selector == CommandSelector::All {
+            // `+@all` and `-@all` override all the previous rules
+            self.commands.clear();
+        } else {
+            self.commands.retain(|rule| rule.selector != selector);
+        }
+        self.commands.push(CommandRule { allow, selector });
+    }
+
+    fn add_pattern(patterns: &mut Vec<String>, pattern: &str) {
+        if pattern == "*" {
+            *patterns = vec![pattern.to_string()];
+        } else if !patterns.iter().any(|p| p == pattern || p == "*") {
+            patterns.push(pattern.to_string());
+        }
+    }
+
+    /// Can this user access `key` (e.g. a key formed by a `SORT` `BY` pattern)?
+    pub fn can_access_key(&self, key: &[u8]) -> bool {
+        Self::matches_any(&self.keys, key)
+    }
+
+    fn matches_any(patterns: &[String], value: &[u8]) -> bool {
+        patterns.iter().any(|pattern| {
+            pattern == "*"
+                || PatternMatcher::builder()
+                    .wildcard(pattern.as_bytes())
+                    .build()
+                    .matches(value)
+        })
+    }
+
+    fn patterns_description(prefix: char, patterns: &[String]) -> String {
+        patterns
+            .iter()
+            .map(|pattern| format!("{}{}", prefix, pattern))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    fn hash_password(password: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(password))
+    }
+}
+
+/// The server users, shared by all the workers
+pub struct Acl {
+    users: RwLock<HashMap<String, Arc<AclUser>>>,
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        let mut users = HashMap::<String, Arc<AclUser>>::new();
+        users.insert(DEFAULT_USER.to_string(), Arc::new(AclUser::default_user()));
+        Acl {
+            users: RwLock::new(users),
+        }
+    }
+}
+
+impl Acl {
+    const ACL_FILE: &'static str = "users.acl";
+
+    /// The ACL file is located in the configuration directory
+    pub fn file_path(options: Arc<RwLock<ServerOptions>>) -> PathBuf {
+        let options_ref = options.read().expect(OPTIONS_LOCK_ERR);
+        let configuration_dir = options_ref.general_settings.config_dir.as_deref();
+
+        let mut acl_file = match configuration_dir {
+            Some(p) => p.to_path_buf(),
+            None => match std::env::current_dir() {
+                Ok(wd) => wd,
+                Err(_) => Path::new(".").to_path_buf(),
+            },
+        };
+
+        acl_file.push(Self::ACL_FILE);
+        acl_file
+    }
+
+    /// Load the users from the ACL file, if it exists
+    pub fn initialise(&self, options: Arc<RwLock<ServerOptions>>) {
+        let file_path = Self::file_path(options);
+        if !file_path.exists() {
+            return;
+        }
+
+        if let Err(e) = self.load(&file_path) {
+            tracing::warn!("Failed to load ACL file {}. {}", file_path.display(), e);
+        } else {
+            tracing::info!("Successfully loaded ACL file {}", file_path.display());
+        }
+    }
+
+    pub fn user(&self, name: &str) -> Option<Arc<AclUser>> {
+        self.users.read().expect(POISONED_MUTEX).get(name).cloned()
+    }
+
+    /// Return all the users, sorted by name
+    pub fn users(&self) -> Vec<Arc<AclUser>> {
+        let mut users: Vec<Arc<AclUser>> = self
+            .users
+            .read()
+            .expect(POISONED_MUTEX)
+            .values()
+            .cloned()
+            .collect();
+        users.sort_by(|a, b| a.name().cmp(b.name()));
+        users
+    }
+
+    /// Create or modify the user `name`. The rules are applied atomically: if one of them is
+    /// invalid, the user is not modified. On error, return the error message
+    pub fn set_user(&self, name: &str, rules: &[String]) -> Result<(), String> {
+        let mut users = self.users.write().expect(POISONED_MUTEX);
+        let mut user = match users.get(name) {
+            Some(user) => user.as_ref().clone(),
+            None => AclUser::new(name),
+        };
+
+        for rule in rules {
+            user.apply_rule(rule).map_err(|reason| {
+                format!("ERR Error in ACL SETUSER modifier '{}': {}", rule, reason)
+            })?;
+        }
+        users.insert(name.to_string(), Arc::new(user));
+        Ok(())
+    }
+
+    /// Delete the user `name`. Return `false` if no such user exists
+    pub fn delete_user(&self, name: &str) -> bool {
+        self.users
+            .write()
+            .expect(POISONED_MUTEX)
+            .remove(name)
+            .is_some()
+    }
+
+    /// Return the user `username` if `password` is valid for it
+    pub fn authenticate(&self, username: &str, password: &[u8]) -> Option<Arc<AclUser>> {
+        self.user(username)
+            .filter(|user| user.check_password(password))
+    }
+
+    /// Write the users into `path`, one user per line
+    pub fn save(&self, path: &Path) -> Result<(), SableError> {
+        let content: Vec<String> = self.users().iter().map(|user| user.description()).collect();
+        std::fs::write(path, format!("{}\n", content.join("\n")))?;
+        Ok(())
+    }
+
+    /// Replace the users with the content of the file `path`. If the file contains an error,
+    /// the current users are kept
+    pub fn load(&self, path: &Path) -> Result<(), SableError> {
+        let content = std::fs::read_to_string(path)?;
+        let mut users = HashMap::<String, Arc<AclUser>>::new();
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |reason: &str| {
+                SableError::InvalidArgument(format!(
+                    "{}:{}: {}",
+                    path.display(),
+                    line_number + 1,
+                    reason
+                ))
+            };
+
+            let mut parts = line.split_whitespace();
+            if parts.next() != Some("user") {
+                return Err(error("line should start with user keyword"));
+            }
+
+            let Some(name) = parts.next() else {
+                return Err(error("missing user name"));
+            };
+
+            let mut user = AclUser::new(name);
+            for rule in parts {
+                user.apply_rule(rule)
+                    .map_err(|reason| error(&format!("'{}': {}", rule, reason)))?;
+            }
+
+            if users.insert(name.to_string(), Arc::new(user)).is_some() {
+                return Err(error(&format!("duplicate user '{}' found", name)));
+            }
+        }
+
+        users
+            .entry(DEFAULT_USER.to_string())
+            .or_insert_with(|| Arc::new(AclUser::default_user()));
+        *self.users.write().expect(POISONED_MUTEX) = users;
+        Ok(())
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn command(args: &str) -> ValkeyCommand {
+        ValkeyCommand::from_str(args).unwrap()
+    }
+
+    fn rules(rules: &str) -> Vec<String> {
+        rules.split(' ').map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_acl_user_rules() {
+        let mut user = AclUser::new("bob");
+        for rule in rules("on >secret ~cache:* &news.* +@read -ttl +set") {
+            user.apply_rule(&rule).unwrap();
+        }
+
+        assert!(user.check_password(b"secret"));
+        assert!(!user.check_password(b"wrong"));
+        assert_eq!(
+            user.check_command(&command("get cache:1")),
+            AclCheckResult::Ok
+        );
+        assert_eq!(
+            user.check_command(&command("get session:1")),
+            AclCheckResult::KeyDenied
+        );
+        assert_eq!(
+            user.check_command(&command("ttl cache:1")),
+            AclCheckResult::CommandDenied
+        );
+        assert_eq!(
+            user.check_command(&command("set cache:1 value")),
+            AclCheckResult::Ok
+        );
+        assert_eq!(
+            user.check_command(&command("del cache:1")),
+            AclCheckResult::CommandDenied
+        );
+        assert_eq!(
+            user.commands_description(),
+            "-@all +@read -ttl +set".to_string()
+        );
+
+        user.apply_rule("+@pubsub").unwrap();
+        assert_eq!(
+            user.check_command(&command("publish news.sport hello")),
+            AclCheckResult::Ok
+        );
+        assert_eq!(
+            user.check_command(&command("subscribe news.sport weather")),
+            AclCheckResult::ChannelDenied
+        );
+        assert_eq!(
+            user.check_command(&command("psubscribe news.*")),
+            AclCheckResult::Ok
+        );
+        assert_eq!(
+            user.check_command(&command("psubscribe news.s*")),
+            AclCheckResult::ChannelDenied
+        );
+
+        user.apply_rule("off").unwrap();
+        assert!(!user.check_password(b"secret"));
+
+        assert!(user.apply_rule("+nosuchcommand").is_err());
+        assert!(user.apply_rule("+@nosuchcategory").is_err());
+        assert!(user.apply_rule("bad").is_err());
+        assert!(user.apply_rule("#1234").is_err());
+    }
+
+    #[test_case("xread streams cache:1 0", AclCheckResult::Ok; "xread allowed")]
+    #[test_case("xread count 10 streams cache:1 other:1 0 0", AclCheckResult::KeyDenied; "xread denied")]
+    #[test_case("xreadgroup group g c streams other:1 >", AclCheckResult::KeyDenied; "xreadgroup denied")]
+    #[test_case("lmpop 2 cache:1 cache:2 left", AclCheckResult::Ok; "lmpop allowed")]
+    #[test_case("lmpop 1 other left", AclCheckResult::KeyDenied; "lmpop denied")]
+    #[test_case("blmpop 0 1 other left", AclCheckResult::KeyDenied; "blmpop denied")]
+    #[test_case("zmpop 1 other min", AclCheckResult::KeyDenied; "zmpop denied")]
+    #[test_case("bzmpop 0 1 other min", AclCheckResult::KeyDenied; "bzmpop denied")]
+    #[test_case("zunion 2 cache:1 other", AclCheckResult::KeyDenied; "zunion denied")]
+    #[test_case("zintercard 1 other", AclCheckResult::KeyDenied; "zintercard denied")]
+    #[test_case("zunionstore cache:d 2 cache:a other:b", AclCheckResult::KeyDenied; "zunionstore denied")]
+    #[test_case("zunionstore cache:d 2 cache:a cache:b", AclCheckResult::Ok; "zunionstore allowed")]
+    #[test_case("eval return 1 other:x", AclCheckResult::KeyDenied; "eval denied")]
+    #[test_case("eval return 1 cache:x", AclCheckResult::Ok; "eval allowed")]
+    #[test_case("evalsha abc 1 other:x", AclCheckResult::KeyDenied; "evalsha denied")]
+    #[test_case("fcall f 1 other", AclCheckResult::KeyDenied; "fcall denied")]
+    #[test_case("fcall_ro f 1 other", AclCheckResult::KeyDenied; "fcall_ro denied")]
+    #[test_case("sort cache:1 by cache:*->w get # get cache:*", AclCheckResult::Ok; "sort allowed")]
+    #[test_case("sort cache:1 by other:*", AclCheckResult::KeyDenied; "sort by denied")]
+    #[test_case("sort cache:1 limit 0 10 get other:*", AclCheckResult::KeyDenied; "sort get denied")]
+    #[test_case("sort cache:1 alpha store other", AclCheckResult::KeyDenied; "sort store denied")]
+    #[test_case("migrate h 1 cache:1 0 10", AclCheckResult::Ok; "migrate allowed")]
+    #[test_case("migrate h 1 other 0 10 copy", AclCheckResult::KeyDenied; "migrate denied")]
+    #[test_case("migrate h 1 x 0 10 replace keys cache:1 other", AclCheckResult::KeyDenied; "migrate keys denied")]
+    #[test_case("lmpop 5 cache:1 left", AclCheckResult::KeyDenied; "bad numkeys denied")]
+    #[test_case("xread streams cache:1", AclCheckResult::KeyDenied; "unbalanced streams denied")]
+    fn test_acl_movable_keys(args: &str, expected: AclCheckResult) {
+        let mut user = AclUser::new("bob");
+        for rule in rules("on nopass ~cache:* +@all") {
+            user.apply_rule(&rule).unwrap();
+        }
+        assert_eq!(user.check_command(&command(args)), expected);
+
+        // A user that can access all the keys may run malformed commands (they are rejected
+        // later, by the command handler)
+        user.apply_rule("allkeys").unwrap();
+        assert_eq!(user.check_command(&command(args)), AclCheckResult::Ok);
+    }
+
+    #[test]
+    fn test_acl_save_load() -> Result<(), SableError> {
+        let path = PathBuf::from(crate::io::TempFile::create_path("test_acl_save_load"));
+        let acl = Acl::default();
+        acl.set_user("bob", &rules("on >secret ~cache:* -@all +get"))
+            .unwrap();
+        assert!(acl.set_user("alice", &rules("on +nosuchcommand")).is_err());
+        assert!(acl.user("alice").is_none());
+        acl.save(&path)?;
+
+        let loaded = Acl::default();
+        loaded.delete_user(DEFAULT_USER);
+        loaded.load(&path)?;
+        let _ = std::fs::remove_file(&path);
+
+        let descriptions: Vec<String> = loaded.users().iter().map(|u| u.description()).collect();
+        let expected: Vec<String> = acl.users().iter().map(|u| u.description()).collect();
+        assert_eq!(descriptions, expected);
+        assert!(loaded.authenticate("bob", b"secret").is_some());
+        assert!(loaded.authenticate("bob", b"wrong").is_none());
+        assert!(loaded.authenticate(DEFAULT_USER, b"anything").is_some());
+        Ok(())
+    }
+}
@@ -1,12 +1,14 @@
 use crate::{
     commands::{ClientNextAction, HandleCommandResult, Strings, TimeoutResponse, TryAgainResponse},
     io::RespWriter,
-    server::{ClientState, Telemetry},
+    server::{AclCheckResult, ClientState, ReplicationTelemetry, Telemetry},
     utils::RequestParser,
     utils::RespBuilderV2,
-    ClientCommands, ClusterCommands, GenericCommands, HashCommands, ListCommands, LockCommands,
-    ParserError, SableError, ServerCommands, ServerState, SetCommands, StorageAdapter,
-    StringCommands, TransactionCommands, ValkeyCommand, ValkeyCommandName, ZSetCommands,
+    AclCommands, BitmapCommands, ClientCommands, ClusterCommands, FunctionCommands,
+    GenericCommands, GeoCommands, HashCommands, HyperLogLogCommands, ListCommands, LockCommands,
+    ParserError, PubSubCommands, SableError, ScriptCommands, ServerCommands, ServerState,
+    SetCommands, StorageAdapter, StreamCommands, StringCommands, TimeUtils, TransactionCommands,
+    ValkeyCommand, ValkeyCommandName, ZSetCommands,
 };
 
 use bytes::BytesMut;
@@ -18,6 +20,7 @@ use std::sync::Arc;
 use std::sync::Mutex;
 
 const PONG: &[u8] = b"+PONG\r\n";
+const SUBSCRIBED_PONG: &[u8] = b"*2\r\n$4\r\npong\r\n$0\r\n\r\n";
 const OPTIONS_LOCK_ERR: &str = "Failed to obtain read lock on ServerOptions";
 
 #[allow(unused_imports)]
@@ -57,6 +60,12 @@ enum PreHandleCommandResult {
     /// The current txn should be aborted. This can happen for multiple reasons
     /// e.g. a "No transaction" command was passed as part of the MULTI phase
     CmdIsNotValidForTxn,
+    /// The client is in the subscribed state and the command is not allowed in this context
+    NotAllowedInSubscribedMode,
+    /// The client's user is not allowed to run this command. Holds the error message
+    AclDenied(String),
+    /// A Lua script exceeded its time limit, only `SCRIPT KILL` is allowed
+    BusyScript,
 }
 
 /// Used by the `block_until` return code
@@ -251,9 +260,28 @@ impl Client {
         mut channel_rx: TokioReceiver<Rc<ValkeyCommand>>,
         client_state: Rc<ClientState>,
     ) -> Result<(), SableError> {
-        while let Some(command) = channel_rx.recv().await {
+        let Some(mut pubsub_rx) = client_state.take_pubsub_receiver() else {
+            return Err(SableError::ClientInvalidState);
+        };
+
+        loop {
+            let mut command = tokio::select! {
+                command = channel_rx.recv() => {
+                    let Some(command) = command else {
+                        break;
+                    };
+                    command
+                }
+                Some(message) = pubsub_rx.recv() => {
+                    // A message was published to a channel this client is subscribed to
+                    Self::send_response(&mut tx, &message, client_state.id()).await?;
+                    continue;
+                }
+            };
+
             // update telemetry and process the command
             Telemetry::inc_total_commands_processed();
+            Telemetry::sample_key_access(client_state.database_id(), &command.keys());
 
             // Use a loop here to handle timeouts & retries
             loop {
@@ -309,6 +337,19 @@ impl Client {
                                     // re-run the command
                                     continue;
                                 }
+                                (
+                                    WaitResult::TryAgain,
+                                    TryAgainResponse::RunCommand(next_command),
+                                ) => {
+                                    if !client_state.active() {
+                                        // Client is no longer active
+                                        tracing::debug!("Client terminated while waiting");
+                                        return Err(SableError::ConnectionClosed);
+                                    }
+                                    // run the provided command instead
+                                    command = next_command;
+                                    continue;
+                                }
                                 (WaitResult::TryAgain, TryAgainResponse::RespondWith(response)) => {
                                     Self::send_response(&mut tx, &response, client_state.id())
                                         .await?;
@@ -379,10 +420,32 @@ impl Client {
                     }
                 }
             }
+            Self::record_keys_access(&client_state, &command);
         }
         Ok(())
     }
 
+    /// Record the last access time of the keys used by `command` (see `OBJECT IDLETIME`). The
+    /// access time is kept in memory and persisted later by the cron thread, nothing is written
+    /// here. Replicas receive the access time from their primary, and commands queued by `MULTI`
+    /// are not executed yet
+    fn record_keys_access(client_state: &Rc<ClientState>, command: &Rc<ValkeyCommand>) {
+        if !command.metadata().touches_keys()
+            || client_state.is_txn_state_multi()
+            || client_state
+                .server_inner_state()
+                .persistent_state()
+                .is_replica()
+        {
+            return;
+        }
+
+        let Ok(now) = TimeUtils::epoch_ms() else {
+            return;
+        };
+        Telemetry::record_key_access(client_state.database_id(), &command.keys(), now);
+    }
+
     /// Suspend the client until a message arrives or a time-out occurs
     pub async fn wait_for(mut cont: TokioReceiver<u8>, duration: Duration) -> WaitResult {
         tokio::select! {
@@ -396,8 +459,8 @@ impl Client {
     }
 
     /// Handle time-out for command
-    fn handle_timeout(
-        _client_state: Rc<ClientState>,
+    pub(crate) fn handle_timeout(
+        client_state: Rc<ClientState>,
         _command: Rc<ValkeyCommand>,
         timeout_response: TimeoutResponse,
     ) -> Result<BytesMut, SableError> {
@@ -420,6 +483,18 @@ impl Client {
             TimeoutResponse::Err(msg) => {
                 builder.error_string(&mut response_buffer, &msg);
             }
+            TimeoutResponse::ReplicasAcked(sequence_number) => {
+                let _ = client_state.take_replica_ack_wait();
+                let acked = ReplicationTelemetry::replicas_acked(sequence_number);
+                builder.number_usize(&mut response_buffer, acked);
+            }
+            TimeoutResponse::LocalAndReplicasAcked(local, sequence_number) => {
+                let _ = client_state.take_replica_ack_wait();
+                let acked = ReplicationTelemetry::replicas_acked(sequence_number);
+                builder.add_array_len(&mut response_buffer, 2);
+                builder.add_number::<i64>(&mut response_buffer, local, false);
+                builder.add_number::<usize>(&mut response_buffer, acked, false);
+            }
         }
         Ok(response_buffer)
     }
@@ -433,13 +508,31 @@ impl Client {
     ) -> PreHandleCommandResult {
         if !client_state.active() {
             PreHandleCommandResult::ClientKilled
+        } else if client_state
+            .server_inner_state()
+            .running_scripts()
+            .is_busy()
+            && !Self::allowed_while_busy(&command)
+        {
+            PreHandleCommandResult::BusyScript
+        } else if let Err(msg) = Self::check_permissions(&client_state, &command) {
+            PreHandleCommandResult::AclDenied(msg)
         } else if command.metadata().is_write_command()
+            // On a replica, scripts run in read-only mode
+            && !matches!(
+                command.metadata().name(),
+                ValkeyCommandName::Eval | ValkeyCommandName::Evalsha | ValkeyCommandName::Fcall
+            )
             && client_state
                 .server_inner_state()
                 .persistent_state()
                 .is_replica()
         {
             PreHandleCommandResult::WriteInReadOnlyReplica
+        } else if client_state.is_subscribed()
+            && !Self::allowed_in_subscribed_mode(command.metadata().name())
+        {
+            PreHandleCommandResult::NotAllowedInSubscribedMode
         } else if client_state.is_txn_state_multi() {
             // All commands by "exec" and "discard" are queued
             match command.metadata().name() {
@@ -459,6 +552,54 @@ impl Client {
         }
     }
 
+    /// Check that the client's user is allowed to run `command` (the command itself, the keys
+    /// and the channels it accesses). On failure, return the error message
+    pub(crate) fn check_permissions(
+        client_state: &ClientState,
+        command: &ValkeyCommand,
+    ) -> Result<(), String> {
+        match command.metadata().name() {
+            // `AUTH` is always allowed and unknown commands are reported as such
+            ValkeyCommandName::Auth | ValkeyCommandName::NotSupported(_) => return Ok(()),
+            _ => {}
+        }
+
+        let Some(user) = client_state.acl_user() else {
+            return Err(Strings::NOAUTH.to_string());
+        };
+
+        match user.check_command(command) {
+            AclCheckResult::Ok => Ok(()),
+            AclCheckResult::CommandDenied => Err(format!(
+                "NOPERM User {} has no permissions to run the '{}' command",
+                user.name(),
+                command.main_command()
+            )),
+            AclCheckResult::KeyDenied => Err(Strings::NOPERM_KEY.to_string()),
+            AclCheckResult::ChannelDenied => Err(Strings::NOPERM_CHANNEL.to_string()),
+        }
+    }
+
+    /// While a Lua script exceeds its time limit, only `SCRIPT KILL` is allowed
+    fn allowed_while_busy(command: &ValkeyCommand) -> bool {
+        matches!(command.metadata().name(), ValkeyCommandName::Script)
+            && command.arg_as_lowercase_string(1).as_deref() == Some("kill")
+    }
+
+    /// While in the subscribed state, only a small set of commands is allowed
+    fn allowed_in_subscribed_mode(kind: &ValkeyCommandName) -> bool {
+        matches!(
+            kind,
+            ValkeyCommandName::Subscribe
+                | ValkeyCommandName::Psubscribe
+                | ValkeyCommandName::Unsubscribe
+                | ValkeyCommandName::Punsubscribe
+                | ValkeyCommandName::Ssubscribe
+                | ValkeyCommandName::Sunsubscribe
+                | ValkeyCommandName::Ping
+        )
+    }
+
     /// Accepts the parsed requests, execute the command and send back the response
     pub async fn handle_command(
         client_state: Rc<ClientState>,
@@ -493,6 +634,26 @@ impl Client {
                     resp_writer.flush().await?;
                     return Ok(ClientNextAction::NoAction);
                 }
+                PreHandleCommandResult::NotAllowedInSubscribedMode => {
+                    resp_writer
+                        .error_string(&format!(
+                            "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING are allowed in this context",
+                            command.main_command()
+                        ))
+                        .await?;
+                    resp_writer.flush().await?;
+                    return Ok(ClientNextAction::NoAction);
+                }
+                PreHandleCommandResult::AclDenied(msg) => {
+                    resp_writer.error_string(&msg).await?;
+                    resp_writer.flush().await?;
+                    return Ok(ClientNextAction::NoAction);
+                }
+                PreHandleCommandResult::BusyScript => {
+                    resp_writer.error_string(Strings::BUSY_SCRIPT).await?;
+                    resp_writer.flush().await?;
+                    return Ok(ClientNextAction::NoAction);
+                }
                 PreHandleCommandResult::QueueCommand => {
                     // queue the command and reply with "QUEUED"
                     client_state.add_txn_command(command);
@@ -506,13 +667,14 @@ impl Client {
             }
         }
 
-        // We break the match here into 2: EXEC and all other non EXEC commands
+        // We break the match here into 2: EXEC (and the scripting commands) and all other non EXEC commands
         // we do this in order to be able to process these commands while in
         // the `TransactionCommands::handle_command`. Rust async does not allow us to
         // recursively call `Client::handle_command()`from within `TransactionCommands::handle_command()`
         // as this will cause some compilation errors
         let kind = command.metadata().name();
-        match kind {
+        let may_write = Self::may_write(&command);
+        let next_action = match kind {
             ValkeyCommandName::Exec => {
                 match TransactionCommands::handle_exec(client_state.clone(), command, tx).await? {
                     HandleCommandResult::Blocked(_) => Err(SableError::ClientInvalidState),
@@ -523,8 +685,53 @@ impl Client {
                     }
                 }
             }
+            ValkeyCommandName::Eval
+            | ValkeyCommandName::Evalsha
+            | ValkeyCommandName::EvalRo
+            | ValkeyCommandName::EvalshaRo => {
+                match ScriptCommands::handle_eval(client_state.clone(), command, tx).await? {
+                    HandleCommandResult::Blocked(_) => Err(SableError::ClientInvalidState),
+                    HandleCommandResult::ResponseSent => Ok(ClientNextAction::NoAction),
+                    HandleCommandResult::ResponseBufferUpdated(buffer) => {
+                        Self::send_response(tx, &buffer, client_state.id()).await?;
+                        Ok(ClientNextAction::NoAction)
+                    }
+                }
+            }
+            ValkeyCommandName::Fcall | ValkeyCommandName::FcallRo => {
+                match FunctionCommands::handle_fcall(client_state.clone(), command, tx).await? {
+                    HandleCommandResult::Blocked(_) => Err(SableError::ClientInvalidState),
+                    HandleCommandResult::ResponseSent => Ok(ClientNextAction::NoAction),
+                    HandleCommandResult::ResponseBufferUpdated(buffer) => {
+                        Self::send_response(tx, &buffer, client_state.id()).await?;
+                        Ok(ClientNextAction::NoAction)
+                    }
+                }
+            }
             _ => Self::handle_non_exec_command(client_state.clone(), command, tx).await,
+        }?;
+
+        // `WAIT` waits for the replicas to acknowledge the writes of this client. The latest
+        // sequence number read here covers them (and possibly a few concurrent writes of other
+        // clients)
+        if may_write && !client_state.is_txn_state_multi() {
+            client_state
+                .set_last_write_sequence_number(client_state.database().latest_sequence_number()?);
         }
+        Ok(next_action)
+    }
+
+    /// Can `command` modify the database? Transactions and scripts are included, their commands
+    /// are not known in advance
+    fn may_write(command: &ValkeyCommand) -> bool {
+        command.metadata().is_write_command()
+            || matches!(
+                command.metadata().name(),
+                ValkeyCommandName::Exec
+                    | ValkeyCommandName::Eval
+                    | ValkeyCommandName::Evalsha
+                    | ValkeyCommandName::Fcall
+            )
     }
 
     /// Handle all commands, execpt for `Exec`
@@ -556,8 +763,14 @@ impl Client {
                 }
             }
             ValkeyCommandName::Ping => {
-                tx.write_all(PONG).await?;
-                Telemetry::inc_net_bytes_written(PONG.len() as u128);
+                // In the subscribed state, the reply is sent as a push message
+                let pong = if client_state.is_subscribed() {
+                    SUBSCRIBED_PONG
+                } else {
+                    PONG
+                };
+                tx.write_all(pong).await?;
+                Telemetry::inc_net_bytes_written(pong.len() as u128);
                 ClientNextAction::NoAction
             }
             ValkeyCommandName::Set
@@ -602,8 +815,30 @@ impl Client {
             | ValkeyCommandName::Del
             | ValkeyCommandName::Exists
             | ValkeyCommandName::Expire
+            | ValkeyCommandName::Pexpire
+            | ValkeyCommandName::Expireat
+            | ValkeyCommandName::Pexpireat
+            | ValkeyCommandName::Pttl
+            | ValkeyCommandName::Expiretime
+            | ValkeyCommandName::Pexpiretime
             | ValkeyCommandName::Keys
-            | ValkeyCommandName::Scan => {
+            | ValkeyCommandName::Scan
+            | ValkeyCommandName::Rename
+            | ValkeyCommandName::Renamenx
+            | ValkeyCommandName::Copy
+            | ValkeyCommandName::Move
+            | ValkeyCommandName::Type
+            | ValkeyCommandName::Persist
+            | ValkeyCommandName::Touch
+            | ValkeyCommandName::Unlink
+            | ValkeyCommandName::Randomkey
+            | ValkeyCommandName::Dump
+            | ValkeyCommandName::Restore
+            | ValkeyCommandName::Migrate
+            | ValkeyCommandName::Sort
+            | ValkeyCommandName::SortRo
+            | ValkeyCommandName::Object
+            | ValkeyCommandName::Memory => {
                 match GenericCommands::handle_command(client_state.clone(), command.clone(), tx)
                     .await?
                 {
@@ -632,12 +867,15 @@ impl Client {
                 );
 
                 // build the stats
-                let stats = client_state
-                    .server_inner_state()
-                    .shared_telemetry()
-                    .read()
-                    .expect("mutex")
-                    .to_string();
+                let stats = match command.arg_as_lowercase_string(1).as_deref() {
+                    Some("keyspace-analysis") => Telemetry::keyspace_analysis_info(),
+                    _ => client_state
+                        .server_inner_state()
+                        .shared_telemetry()
+                        .read()
+                        .expect("mutex")
+                        .to_string(),
+                };
 
                 builder.bulk_string(&mut buffer, &BytesMut::from(stats.as_bytes()));
                 Self::send_response(tx, &buffer, client_state.id()).await?;
@@ -700,15 +938,24 @@ impl Client {
             | ValkeyCommandName::FlushDb
             | ValkeyCommandName::FlushAll
             | ValkeyCommandName::DbSize
-            | ValkeyCommandName::Slot => {
+            | ValkeyCommandName::Slot
+            | ValkeyCommandName::Wait
+            | ValkeyCommandName::WaitAof => {
                 match ServerCommands::handle_command(client_state.clone(), command, tx).await? {
                     HandleCommandResult::ResponseBufferUpdated(buffer) => {
                         Self::send_response(tx, &buffer, client_state.id()).await?;
+                        ClientNextAction::NoAction
                     }
-                    HandleCommandResult::Blocked(_) => {}
-                    HandleCommandResult::ResponseSent => {}
+                    HandleCommandResult::Blocked((
+                        rx,
+                        duration,
+                        timeout_response,
+                        try_again_response,
+                    )) => {
+                        ClientNextAction::Wait((rx, duration, timeout_response, try_again_response))
+                    }
+                    HandleCommandResult::ResponseSent => ClientNextAction::NoAction,
                 }
-                ClientNextAction::NoAction
             }
             // Set commands
             ValkeyCommandName::Sadd
@@ -757,7 +1004,18 @@ impl Client {
             | ValkeyCommandName::Hrandfield
             | ValkeyCommandName::Hscan
             | ValkeyCommandName::Hsetnx
-            | ValkeyCommandName::Hstrlen => {
+            | ValkeyCommandName::Hstrlen
+            | ValkeyCommandName::Hexpire
+            | ValkeyCommandName::Hpexpire
+            | ValkeyCommandName::Hexpireat
+            | ValkeyCommandName::Hpexpireat
+            | ValkeyCommandName::Httl
+            | ValkeyCommandName::Hpttl
+            | ValkeyCommandName::Hexpiretime
+            | ValkeyCommandName::Hpexpiretime
+            | ValkeyCommandName::Hpersist
+            | ValkeyCommandName::Hgetex
+            | ValkeyCommandName::Hsetex => {
                 match HashCommands::handle_command(client_state.clone(), command, tx).await? {
                     HandleCommandResult::Blocked(_) => {
                         return Err(SableError::OtherError(
@@ -771,9 +1029,16 @@ impl Client {
                     }
                 }
             }
-            ValkeyCommandName::Exec => {
+            ValkeyCommandName::Exec
+            | ValkeyCommandName::Eval
+            | ValkeyCommandName::Evalsha
+            | ValkeyCommandName::EvalRo
+            | ValkeyCommandName::EvalshaRo
+            | ValkeyCommandName::Fcall
+            | ValkeyCommandName::FcallRo => {
                 // Well, this is unexpected. We shouldn't reach this pattern matching block
-                // with `Exec`... (it is handled earlier in the Client::handle_command)
+                // with `Exec` or the scripting commands... (they are handled earlier in the
+                // Client::handle_command)
                 return Err(SableError::ClientInvalidState);
             }
             ValkeyCommandName::Multi
@@ -862,6 +1127,158 @@ impl Client {
                     }
                 }
             }
+            // Pub/Sub commands
+            ValkeyCommandName::Subscribe
+            | ValkeyCommandName::Psubscribe
+            | ValkeyCommandName::Unsubscribe
+            | ValkeyCommandName::Punsubscribe
+            | ValkeyCommandName::Publish
+            | ValkeyCommandName::Pubsub
+            | ValkeyCommandName::Ssubscribe
+            | ValkeyCommandName::Sunsubscribe
+            | ValkeyCommandName::Spublish => {
+                match PubSubCommands::handle_command(client_state.clone(), command, tx).await? {
+                    HandleCommandResult::Blocked(_) => {
+                        return Err(SableError::OtherError(
+                            "Internal error: client is in invalid state".to_string(),
+                        ));
+                    }
+                    HandleCommandResult::ResponseSent => ClientNextAction::NoAction,
+                    HandleCommandResult::ResponseBufferUpdated(buffer) => {
+                        Self::send_response(tx, &buffer, client_state.id()).await?;
+                        ClientNextAction::NoAction
+                    }
+                }
+            }
+            // Stream commands
+            ValkeyCommandName::Xadd
+            | ValkeyCommandName::Xrange
+            | ValkeyCommandName::Xread
+            | ValkeyCommandName::Xtrim
+            | ValkeyCommandName::Xdel
+            | ValkeyCommandName::Xlen
+            | ValkeyCommandName::Xgroup
+            | ValkeyCommandName::Xreadgroup
+            | ValkeyCommandName::Xack
+            | ValkeyCommandName::Xpending
+            | ValkeyCommandName::Xclaim
+            | ValkeyCommandName::Xautoclaim => {
+                match StreamCommands::handle_command(client_state.clone(), command, tx).await? {
+                    HandleCommandResult::Blocked((
+                        rx,
+                        duration,
+                        timeout_response,
+                        try_again_response,
+                    )) => {
+                        ClientNextAction::Wait((rx, duration, timeout_response, try_again_response))
+                    }
+                    HandleCommandResult::ResponseSent => ClientNextAction::NoAction,
+                    HandleCommandResult::ResponseBufferUpdated(buffer) => {
+                        Self::send_response(tx, &buffer, client_state.id()).await?;
+                        ClientNextAction::NoAction
+                    }
+                }
+            }
+            // Scripting commands
+            ValkeyCommandName::Script => {
+                match ScriptCommands::handle_command(client_state.clone(), command, tx).await? {
+                    HandleCommandResult::Blocked(_) => {
+                        return Err(SableError::OtherError(
+                            "Internal error: client is in invalid state".to_string(),
+                        ));
+                    }
+                    HandleCommandResult::ResponseSent => ClientNextAction::NoAction,
+                    HandleCommandResult::ResponseBufferUpdated(buffer) => {
+                        Self::send_response(tx, &buffer, client_state.id()).await?;
+                        ClientNextAction::NoAction
+                    }
+                }
+            }
+            ValkeyCommandName::Function => {
+                match FunctionCommands::handle_command(client_state.clone(), command, tx).await? {
+                    HandleCommandResult::Blocked(_) => {
+                        return Err(SableError::OtherError(
+                            "Internal error: client is in invalid state".to_string(),
+                        ));
+                    }
+                    HandleCommandResult::ResponseSent => ClientNextAction::NoAction,
+                    HandleCommandResult::ResponseBufferUpdated(buffer) => {
+                        Self::send_response(tx, &buffer, client_state.id()).await?;
+                        ClientNextAction::NoAction
+                    }
+                }
+            }
+            // ACL commands
+            ValkeyCommandName::Auth | ValkeyCommandName::Acl => {
+                match AclCommands::handle_command(client_state.clone(), command, tx).await? {
+                    HandleCommandResult::Blocked(_) => {
+                        return Err(SableError::OtherError(
+                            "Internal error: client is in invalid state".to_string(),
+                        ));
+                    }
+                    HandleCommandResult::ResponseSent => ClientNextAction::NoAction,
+                    HandleCommandResult::ResponseBufferUpdated(buffer) => {
+                        Self::send_response(tx, &buffer, client_state.id()).await?;
+                        ClientNextAction::NoAction
+                    }
+                }
+            }
+            // Geo commands
+            ValkeyCommandName::Geoadd
+            | ValkeyCommandName::Geodist
+            | ValkeyCommandName::Geopos
+            | ValkeyCommandName::Geohash
+            | ValkeyCommandName::Geosearch
+            | ValkeyCommandName::Geosearchstore => {
+                match GeoCommands::handle_command(client_state.clone(), command, tx).await? {
+                    HandleCommandResult::Blocked(_) => {
+                        return Err(SableError::OtherError(
+                            "Internal error: client is in invalid state".to_string(),
+                        ));
+                    }
+                    HandleCommandResult::ResponseSent => ClientNextAction::NoAction,
+                    HandleCommandResult::ResponseBufferUpdated(buffer) => {
+                        Self::send_response(tx, &buffer, client_state.id()).await?;
+                        ClientNextAction::NoAction
+                    }
+                }
+            }
+            // Bitmap commands
+            ValkeyCommandName::Setbit
+            | ValkeyCommandName::Getbit
+            | ValkeyCommandName::Bitcount
+            | ValkeyCommandName::Bitpos
+            | ValkeyCommandName::Bitop
+            | ValkeyCommandName::Bitfield => {
+                match BitmapCommands::handle_command(client_state.clone(), command, tx).await? {
+                    HandleCommandResult::Blocked(_) => {
+                        return Err(SableError::OtherError(
+                            "Internal error: client is in invalid state".to_string(),
+                        ));
+                    }
+                    HandleCommandResult::ResponseSent => ClientNextAction::NoAction,
+                    HandleCommandResult::ResponseBufferUpdated(buffer) => {
+                        Self::send_response(tx, &buffer, client_state.id()).await?;
+                        ClientNextAction::NoAction
+                    }
+                }
+            }
+            // HyperLogLog commands
+            ValkeyCommandName::Pfadd | ValkeyCommandName::Pfcount | ValkeyCommandName::Pfmerge => {
+                match HyperLogLogCommands::handle_command(client_state.clone(), command, tx).await?
+                {
+                    HandleCommandResult::Blocked(_) => {
+                        return Err(SableError::OtherError(
+                            "Internal error: client is in invalid state".to_string(),
+                        ));
+                    }
+                    HandleCommandResult::ResponseSent => ClientNextAction::NoAction,
+                    HandleCommandResult::ResponseBufferUpdated(buffer) => {
+                        Self::send_response(tx, &buffer, client_state.id()).await?;
+                        ClientNextAction::NoAction
+                    }
+                }
+            }
             // Misc
             ValkeyCommandName::NotSupported(msg) => {
                 tracing::info!(msg);
@@ -912,6 +1329,16 @@ impl Drop for Client {
         // drop any transaction related info for this client
         self.state.discard_transaction();
 
+        // remove this client's subscriptions
+        if self.state.is_subscribed() {
+            self.state.server_inner_state().pubsub().remove_client(
+                self.state.id(),
+                &self.state.subscribed_channels(),
+                &self.state.subscribed_patterns(),
+                &self.state.subscribed_shard_channels(),
+            );
+        }
+
         let all_keys: Vec<BytesMut> = self.state.locked_key_get_all();
         if !all_keys.is_empty() {
             tracing::warn!(
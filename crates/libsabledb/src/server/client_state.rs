@@ -1,12 +1,15 @@
 use crate::{
     commands::ValkeyCommand,
-    server::{new_client_id, ServerState, WatchedKeys},
+    server::{
+        new_client_id, AclUser, PubSubReceiver, PubSubSender, ServerState, WatchedKeys,
+        DEFAULT_USER, PUBSUB_CLIENT_QUEUE_SIZE,
+    },
     storage::{ScanCursor, StorageAdapter},
 };
 
 use bytes::BytesMut;
 use dashmap::{DashMap, DashSet};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
 use std::sync::{
@@ -51,6 +54,15 @@ impl TransactionState {
     }
 }
 
+/// A `WAIT` / `WAITAOF` call that is blocked until enough replicas acknowledge `sequence_number`
+#[derive(Clone, Copy, Debug)]
+pub struct ReplicaAckWait {
+    /// The sequence number the replicas must acknowledge
+    pub sequence_number: u64,
+    /// When to stop waiting. `None` means wait forever
+    pub deadline: Option<std::time::Instant>,
+}
+
 pub struct ClientStateFlags {}
 
 impl ClientStateFlags {
@@ -79,6 +91,21 @@ pub struct ClientState {
     flags: AtomicU32,
     cursors: DashMap<u64, Rc<ScanCursor>>,
     keys_locked: DashSet<BytesMut>,
+    /// Channels this client is subscribed to
+    subscribed_channels: DashSet<BytesMut>,
+    /// Patterns this client is subscribed to
+    subscribed_patterns: DashSet<BytesMut>,
+    /// Shard channels this client is subscribed to
+    subscribed_shard_channels: DashSet<BytesMut>,
+    /// Pub/Sub messages are pushed to the client over this channel
+    pubsub_tx: PubSubSender,
+    pubsub_rx: RefCell<Option<PubSubReceiver>>,
+    /// The user this client authenticated as (using `AUTH`)
+    user: RefCell<Option<String>>,
+    /// The replicas acknowledgement this client is blocked on (`WAIT`)
+    replica_ack_wait: Cell<Option<ReplicaAckWait>>,
+    /// The database sequence number following the last write of this client (`WAIT`)
+    last_write_sequence_number: Cell<u64>,
 }
 
 impl ClientState {
@@ -87,6 +114,7 @@ impl ClientState {
         store: StorageAdapter,
         tls_acceptor: Option<Rc<tokio_rustls::TlsAcceptor>>,
     ) -> Self {
+        let (pubsub_tx, pubsub_rx) = tokio::sync::mpsc::channel(PUBSUB_CLIENT_QUEUE_SIZE);
         ClientState {
             server_state,
             store_with_cache: store.transaction(),
@@ -98,6 +126,14 @@ impl ClientState {
             flags: AtomicU32::new(0),
             cursors: DashMap::<u64, Rc<ScanCursor>>::default(),
             keys_locked: DashSet::<BytesMut>::default(),
+            subscribed_channels: DashSet::<BytesMut>::default(),
+            subscribed_patterns: DashSet::<BytesMut>::default(),
+            subscribed_shard_channels: DashSet::<BytesMut>::default(),
+            pubsub_tx,
+            pubsub_rx: RefCell::new(Some(pubsub_rx)),
+            user: RefCell::new(None),
+            replica_ack_wait: Cell::new(None),
+            last_write_sequence_number: Cell::new(0),
         }
     }
 
@@ -193,6 +229,53 @@ impl ClientState {
         self.db_id.store(id, std::sync::atomic::Ordering::Relaxed);
     }
 
+    /// Mark this client as authenticated as `user`
+    pub fn set_user(&self, user: &str) {
+        *self.user.borrow_mut() = Some(user.to_string());
+    }
+
+    /// Return the name of the user this client is authenticated as
+    pub fn user_name(&self) -> String {
+        self.user
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| DEFAULT_USER.to_string())
+    }
+
+    /// Return the ACL user of this client. A client that did not authenticate is using the
+    /// `default` user, as long as it is enabled and does not require a password. Return `None`
+    /// if the client must authenticate first
+    pub fn acl_user(&self) -> Option<Arc<AclUser>> {
+        let acl = self.server_state.acl();
+        match self.user.borrow().as_deref() {
+            Some(name) => acl.user(name),
+            None => acl
+                .user(DEFAULT_USER)
+                .filter(|user| user.is_enabled() && user.is_nopass()),
+        }
+    }
+
+    /// Keep the replicas acknowledgement this client is about to block on
+    pub fn set_replica_ack_wait(&self, wait: ReplicaAckWait) {
+        self.replica_ack_wait.set(Some(wait));
+    }
+
+    /// Take the replicas acknowledgement this client was blocked on, if any
+    pub fn take_replica_ack_wait(&self) -> Option<ReplicaAckWait> {
+        self.replica_ack_wait.take()
+    }
+
+    /// Keep the database sequence number read right after a write of this client committed
+    pub fn set_last_write_sequence_number(&self, sequence_number: u64) {
+        self.last_write_sequence_number.set(sequence_number);
+    }
+
+    /// The database sequence number read right after the last write of this client committed,
+    /// `0` if the client did not write anything
+    pub fn last_write_sequence_number(&self) -> u64 {
+        self.last_write_sequence_number.get()
+    }
+
     /// Is the client alive? (e.g. was it killed using `client kill` command?)
     pub fn active(&self) -> bool {
         !self.is_flag_enabled(ClientStateFlags::KILLED)
@@ -362,6 +445,95 @@ impl ClientState {
             .collect()
     }
 
+    /// Return the sending side of the client's pub/sub channel
+    pub fn pubsub_sender(&self) -> PubSubSender {
+        self.pubsub_tx.clone()
+    }
+
+    /// Take the receiving side of the client's pub/sub channel. This can only be done once,
+    /// by the task that writes to the client's connection
+    pub fn take_pubsub_receiver(&self) -> Option<PubSubReceiver> {
+        self.pubsub_rx.borrow_mut().take()
+    }
+
+    /// Add `channel` to the list of channels this client is subscribed to.
+    /// Return `true` if the client was not already subscribed to it
+    pub fn channel_subscribe(&self, channel: &BytesMut) -> bool {
+        self.subscribed_channels.insert(channel.clone())
+    }
+
+    /// Remove `channel` from the list of channels this client is subscribed to.
+    /// Return `true` if the client was subscribed to it
+    pub fn channel_unsubscribe(&self, channel: &BytesMut) -> bool {
+        self.subscribed_channels.remove(channel).is_some()
+    }
+
+    /// Add `pattern` to the list of patterns this client is subscribed to.
+    /// Return `true` if the client was not already subscribed to it
+    pub fn pattern_subscribe(&self, pattern: &BytesMut) -> bool {
+        self.subscribed_patterns.insert(pattern.clone())
+    }
+
+    /// Remove `pattern` from the list of patterns this client is subscribed to.
+    /// Return `true` if the client was subscribed to it
+    pub fn pattern_unsubscribe(&self, pattern: &BytesMut) -> bool {
+        self.subscribed_patterns.remove(pattern).is_some()
+    }
+
+    /// Add `channel` to the list of shard channels this client is subscribed to.
+    /// Return `true` if the client was not already subscribed to it
+    pub fn shard_channel_subscribe(&self, channel: &BytesMut) -> bool {
+        self.subscribed_shard_channels.insert(channel.clone())
+    }
+
+    /// Remove `channel` from the list of shard channels this client is subscribed to.
+    /// Return `true` if the client was subscribed to it
+    pub fn shard_channel_unsubscribe(&self, channel: &BytesMut) -> bool {
+        self.subscribed_shard_channels.remove(channel).is_some()
+    }
+
+    /// Return the channels this client is subscribed to
+    pub fn subscribed_channels(&self) -> Vec<BytesMut> {
+        self.subscribed_channels
+            .iter()
+            .map(|channel| channel.key().clone())
+            .collect()
+    }
+
+    /// Return the patterns this client is subscribed to
+    pub fn subscribed_patterns(&self) -> Vec<BytesMut> {
+        self.subscribed_patterns
+            .iter()
+            .map(|pattern| pattern.key().clone())
+            .collect()
+    }
+
+    /// Return the shard channels this client is subscribed to
+    pub fn subscribed_shard_channels(&self) -> Vec<BytesMut> {
+        self.subscribed_shard_channels
+            .iter()
+            .map(|channel| channel.key().clone())
+            .collect()
+    }
+
+    /// Return the total number of channels and patterns this client is subscribed to
+    pub fn subscriptions_count(&self) -> usize {
+        self.subscribed_channels
+            .len()
+            .saturating_add(self.subscribed_patterns.len())
+    }
+
+    /// Return the number of shard channels this client is subscribed to
+    pub fn shard_subscriptions_count(&self) -> usize {
+        self.subscribed_shard_channels.len()
+    }
+
+    /// A client with at least one subscription is in the "subscribed" state. While in this state
+    /// the connection is used for pushing messages and only a small set of commands is allowed
+    pub fn is_subscribed(&self) -> bool {
+        self.subscriptions_count() > 0 || self.shard_subscriptions_count() > 0
+    }
+
     // Helper methods
     fn enable_client_flag(&self, flag: u32, enabled: bool) {
         let mut flags = self.flags.load(std::sync::atomic::Ordering::Relaxed);
@@ -1,13 +1,17 @@
 use crate::{
-    metadata::{Bookkeeping, KeyPrefix, KeyType, ValueType},
+    metadata::{
+        BitmapValueMetadata, Bookkeeping, HashFieldKey, HashValueMetadata, KeyPrefix, KeyType,
+        ListValueMetadata, SetValueMetadata, StreamValueMetadata, ValueType, ZSetValueMetadata,
+    },
     replication::{ClusterManager, NodeBuilder},
     server::telemetry::Telemetry,
     server::NodeExt,
-    storage::{DbWriteCache, GenericDb, StorageMetadata},
+    storage::{DbWriteCache, GenericDb, HashDb, StorageMetadata},
     utils::ticker::{TickInterval, Ticker},
     utils::StopWatch,
-    LockManager, SableError, Server, ServerOptions, StorageAdapter, ToU8Writer, U8ArrayBuilder,
-    U8ArrayReader, WorkerHandle,
+    CommonValueMetadata, LockManager, PrimaryKeyMetadata, SableError, Server, ServerOptions,
+    StorageAdapter, TimeUtils, ToU8Writer, TtlIndexKey, U8ArrayBuilder, U8ArrayReader,
+    WorkerHandle,
 };
 use bytes::BytesMut;
 use num_format::{Locale, ToFormattedString};
@@ -162,6 +166,21 @@ impl Cron {
                 .scan_keys_secs as u64,
         ));
 
+        let mut persist_access_times_ticker = Ticker::new(TickInterval::Seconds(
+            self.server_options
+                .read()
+                .expect(OPTIONS_LOCK_ERR)
+                .cron
+                .persist_access_times_secs as u64,
+        ));
+
+        let keyspace_analysis_top_keys = self
+            .server_options
+            .read()
+            .expect(OPTIONS_LOCK_ERR)
+            .cron
+            .keyspace_analysis_top_keys;
+
         // The cron will wake-up every N milliseconds to execute the tasks
         let cron_interval_ms = self
             .server_options
@@ -177,6 +196,14 @@ impl Cron {
             .cron
             .compaction_after_eviction;
 
+        let (active_expire_enabled, active_expire_keys_per_cycle) = {
+            let options = self.server_options.read().expect(OPTIONS_LOCK_ERR);
+            (
+                options.cron.active_expire_enabled,
+                options.cron.active_expire_keys_per_cycle,
+            )
+        };
+
         // In a cluster configuration or as part of a replication group, the cron will update its status in the cluster
         // database every N milliseconds.
         let cluster_database_updates_interval_ms =
@@ -200,6 +227,7 @@ impl Cron {
                             Self::evict(&self.store, compaction_after_eviction).await?;
                         }
                         Some(CronMessage::Shutdown) => {
+                            Self::persist_access_times(&self.store).await?;
                             tracing::info!("Exiting");
                             break;
                         }
@@ -210,7 +238,13 @@ impl Cron {
                         time::Instant::now() + time::Duration::from_millis(cron_interval_ms)) => {
                     let cm = ClusterManager::with_options(self.server_options.clone());
                     evict_ticker.tick_if_needed(Self::evict(&self.store, compaction_after_eviction)).await?;
-                    scan_ticker.tick_if_needed(Self::scan(&self.store)).await?;
+                    scan_ticker.tick_if_needed(Self::scan(&self.store, keyspace_analysis_top_keys)).await?;
+
+                    // Replicas receive the deletions from their primary
+                    if active_expire_enabled && Server::state().persistent_state().is_primary() {
+                        Self::active_expire(&self.store, active_expire_keys_per_cycle).await?;
+                    }
+                    persist_access_times_ticker.tick_if_needed(Self::persist_access_times(&self.store)).await?;
                     if cluster_db_updater_ticker.try_tick()? {
                         let node_info =  NodeBuilder::default()
                                 .with_last_txn_id(self.store.latest_sequence_number()?)
@@ -284,15 +318,7 @@ impl Cron {
         store: &StorageAdapter,
         compaction_after_eviction: bool,
     ) -> Result<usize, SableError> {
-        let prefix_arr = vec![
-            (ValueType::Hash, vec![KeyType::HashItem]),
-            (ValueType::List, vec![KeyType::ListItem]),
-            (
-                ValueType::Zset,
-                vec![KeyType::ZsetMemberItem, KeyType::ZsetScoreItem],
-            ),
-            (ValueType::Set, vec![KeyType::SetItem]),
-        ];
+        let prefix_arr = Self::sub_item_types();
 
         let mut items_evicted = 0usize;
         let mut write_cache = DbWriteCache::with_storage(store);
@@ -325,7 +351,7 @@ impl Cron {
 
                     match Self::record_exists(store, &record, &user_key, primary_type)? {
                         RecordExistsResult::WrongType | RecordExistsResult::NotFound => {
-                            for key_type in sub_items {
+                            for key_type in sub_items.iter() {
                                 // purge sub-items
                                 let count = Self::purge_subitems(
                                     store,
@@ -382,6 +408,165 @@ impl Cron {
         Ok(items_evicted)
     }
 
+    /// Complex types and the key types used by their sub items
+    fn sub_item_types() -> Vec<(ValueType, &'static [KeyType])> {
+        [
+            ValueType::Hash,
+            ValueType::List,
+            ValueType::Zset,
+            ValueType::Set,
+            ValueType::Stream,
+            ValueType::Bitmap,
+        ]
+        .into_iter()
+        .map(|value_type| (value_type, value_type.sub_item_types()))
+        .collect()
+    }
+
+    /// Active expiration:
+    /// Expired keys are deleted lazily, when they are accessed. Keys that are never accessed again
+    /// would remain on the disk forever, so on every activation the cron visits the expiration
+    /// index entries that are due (up to `keys_per_cycle` of them) and deletes the expired keys,
+    /// including their sub items. Hash fields with an expiration time are indexed as well: expired
+    /// fields are removed from their hash. Return the number of keys deleted
+    async fn active_expire(
+        store: &StorageAdapter,
+        keys_per_cycle: usize,
+    ) -> Result<usize, SableError> {
+        let prefix = TtlIndexKey::prefix();
+        let now = TimeUtils::epoch_ms()?;
+
+        // Collect the due entries first, the deletion requires locking
+        let mut due_entries = Vec::<TtlIndexKey>::new();
+        {
+            let mut db_iter = store.create_iterator(&prefix)?;
+            while db_iter.valid() && due_entries.len() < keys_per_cycle {
+                let Some(key) = db_iter.key() else {
+                    break;
+                };
+
+                if !key.starts_with(&prefix) {
+                    break;
+                }
+
+                let entry = TtlIndexKey::from_bytes(key)?;
+                // The index is sorted by the expiration timestamp
+                if entry.expire_timestamp_ms() > now {
+                    break;
+                }
+                due_entries.push(entry);
+                db_iter.next();
+            }
+        }
+
+        let mut keys_deleted = 0usize;
+        for entry in &due_entries {
+            if entry.record_key().starts_with(&[KeyType::HashItem as u8]) {
+                Self::delete_expired_hash_field(store, entry).await?;
+            } else if Self::delete_expired_key(store, entry).await? {
+                keys_deleted = keys_deleted.saturating_add(1);
+            }
+        }
+
+        if keys_deleted > 0 {
+            tracing::debug!("Active expiration deleted {} keys", keys_deleted);
+            Telemetry::inc_expired_keys(keys_deleted as u64);
+        }
+        Ok(keys_deleted)
+    }
+
+    /// Write the key access times recorded in memory by the workers (see
+    /// `Telemetry::record_key_access`). Return the number of keys updated
+    async fn persist_access_times(store: &StorageAdapter) -> Result<usize, SableError> {
+        let mut updated = 0usize;
+        for (db_id, user_key, last_accessed) in Telemetry::pending_key_access_times() {
+            let _unused = LockManager::lock_user_key_exclusive_unconditionally(&user_key).await?;
+            let mut generic_db = GenericDb::with_storage(store, db_id);
+            if generic_db.persist_last_accessed(&user_key, last_accessed)? {
+                updated = updated.saturating_add(1);
+            }
+            Telemetry::key_access_persisted(db_id, &user_key, last_accessed);
+        }
+        if updated > 0 {
+            tracing::debug!("Persisted the access time of {} keys", updated);
+        }
+        Ok(updated)
+    }
+
+    /// Delete the primary key pointed by the index `entry` and its sub items, if it is still
+    /// expired. The index entry itself is always deleted: if the key was deleted or its TTL was
+    /// changed since the entry was written, the entry is stale
+    async fn delete_expired_key(
+        store: &StorageAdapter,
+        entry: &TtlIndexKey,
+    ) -> Result<bool, SableError> {
+        let key = entry.record_key();
+        let primary_key = PrimaryKeyMetadata::from_bytes(key)?;
+        let _unused =
+            LockManager::lock_user_key_exclusive_unconditionally(primary_key.user_key()).await?;
+
+        let mut write_cache = DbWriteCache::with_storage(store);
+        write_cache.delete(&entry.to_bytes())?;
+
+        // The key might have been updated or deleted since the entry was written
+        let md = match store.get(key)? {
+            Some(value) => {
+                let mut reader = U8ArrayReader::with_buffer(&value);
+                Some(CommonValueMetadata::from_bytes(&mut reader)?)
+            }
+            None => None,
+        };
+        let Some(md) = md.filter(|md| md.expiration().is_expired().unwrap_or(false)) else {
+            write_cache.flush()?;
+            return Ok(false);
+        };
+
+        write_cache.delete(key)?;
+
+        let value_type = md.value_type();
+        let sub_items = value_type.sub_item_types();
+        if !sub_items.is_empty() {
+            let record = Bookkeeping::new(primary_key.database_id(), primary_key.slot())
+                .with_uid(md.uid())
+                .with_value_type(value_type);
+            for key_type in sub_items {
+                Self::purge_subitems(store, &mut write_cache, &record, key_type)?;
+            }
+            write_cache.delete(&record.to_bytes())?;
+        }
+        write_cache.flush()?;
+        Ok(true)
+    }
+
+    /// Delete the hash field pointed by the index `entry`, if it is still expired. The hash itself
+    /// is deleted when its last field is deleted. The index entry is always deleted
+    async fn delete_expired_hash_field(
+        store: &StorageAdapter,
+        entry: &TtlIndexKey,
+    ) -> Result<(), SableError> {
+        let field_key = HashFieldKey::from_bytes(entry.record_key())?;
+        let db_id = field_key.prefix().db_id();
+
+        // Find the hash name using its bookkeeping record
+        let record = Bookkeeping::new(db_id, field_key.prefix().key_slot())
+            .with_uid(field_key.hash_id())
+            .with_value_type(ValueType::Hash);
+        let Some(user_key) = store.get(&record.to_bytes())? else {
+            // The hash no longer exists, the entry is stale
+            return store.delete(&entry.to_bytes());
+        };
+
+        let _unused = LockManager::lock_user_key_exclusive_unconditionally(&user_key).await?;
+        let mut hash_db = HashDb::with_storage(store, db_id);
+        hash_db.delete_expired_field(
+            &user_key,
+            field_key.hash_id(),
+            &BytesMut::from(field_key.user_key()),
+            &entry.to_bytes(),
+        )?;
+        Ok(())
+    }
+
     /// Purge sub items from the database belonged to a zombie
     /// key (hash that was overwritten, but its sub items are still there)
     fn purge_subitems(
@@ -475,16 +660,29 @@ impl Cron {
     }
 
     /// Scan the database count keys / databases
-    async fn scan(store: &StorageAdapter) -> Result<(), SableError> {
+    async fn scan(store: &StorageAdapter, top_keys_count: usize) -> Result<(), SableError> {
+        let storage_metadata = Self::collect_storage_metadata(store, top_keys_count).await?;
+        tracing::debug!("Scan output: {:?}", storage_metadata);
+        Telemetry::set_database_info(storage_metadata);
+        Ok(())
+    }
+
+    /// Count the keys of every database, keep the `top_keys_count` largest keys of every type and
+    /// the `top_keys_count` most accessed keys (from the key accesses sampled since the previous
+    /// scan)
+    async fn collect_storage_metadata(
+        store: &StorageAdapter,
+        top_keys_count: usize,
+    ) -> Result<StorageMetadata, SableError> {
         // Scan of all keys, regardless of their database association
         let mut prefix = BytesMut::new();
         let mut builder = U8ArrayBuilder::with_buffer(&mut prefix);
         builder.write_key_type(KeyType::PrimaryKey);
         let mut db_iter = store.create_iterator(&prefix)?;
-        let mut storage_metadata = StorageMetadata::default();
+        let mut storage_metadata = StorageMetadata::with_top_keys(top_keys_count);
         let mut counter = 0u64;
         while db_iter.valid() {
-            let Some(key) = db_iter.key() else {
+            let Some((key, value)) = db_iter.key_value() else {
                 break;
             };
 
@@ -492,16 +690,15 @@ impl Cron {
                 break;
             }
 
-            // Extract the database ID from the key
-            let mut reader = U8ArrayReader::with_buffer(key);
-
-            // Skip the primary key encoding
-            reader.advance(std::mem::size_of::<KeyType>())?;
-
-            // Read the database ID
-            let db_id = reader.read_u16().ok_or(SableError::SerialisationError)?;
-
+            let primary_key = PrimaryKeyMetadata::from_bytes(key)?;
+            let db_id = primary_key.database_id();
             storage_metadata.incr_keys(db_id);
+
+            if top_keys_count > 0 {
+                if let Some((type_name, size)) = Self::value_size(value) {
+                    storage_metadata.add_key_size(type_name, db_id, primary_key.user_key(), size);
+                }
+            }
             db_iter.next();
 
             counter = counter.saturating_add(1);
@@ -512,9 +709,35 @@ impl Cron {
             }
         }
 
-        tracing::debug!("Scan output: {:?}", storage_metadata);
-        Telemetry::set_database_info(storage_metadata);
-        Ok(())
+        for ((db_id, user_key), accesses) in Telemetry::take_key_accesses() {
+            storage_metadata.add_key_accesses(db_id, &user_key, accesses);
+        }
+        Ok(storage_metadata)
+    }
+
+    /// Return the type name and the size of a value: the number of its items (fields, members,
+    /// entries), or its length in bytes for strings and bitmaps. Return `None` for locks, expired
+    /// values and values that can not be decoded
+    fn value_size(value: &[u8]) -> Option<(&'static str, u64)> {
+        let mut reader = U8ArrayReader::with_buffer(value);
+        let md = CommonValueMetadata::from_bytes(&mut reader).ok()?;
+        if md.expiration().is_expired().unwrap_or(true) {
+            return None;
+        }
+        let metadata_len = reader.consumed();
+
+        let mut reader = U8ArrayReader::with_buffer(value);
+        let size = match md.value_type() {
+            ValueType::Str => value.len().saturating_sub(metadata_len) as u64,
+            ValueType::Bitmap => BitmapValueMetadata::from_bytes(&mut reader).ok()?.len(),
+            ValueType::List => ListValueMetadata::from_bytes(&mut reader).ok()?.len(),
+            ValueType::Hash => HashValueMetadata::from_bytes(&mut reader).ok()?.len(),
+            ValueType::Set => SetValueMetadata::from_bytes(&mut reader).ok()?.len(),
+            ValueType::Zset => ZSetValueMetadata::from_bytes(&mut reader).ok()?.len(),
+            ValueType::Stream => StreamValueMetadata::from_bytes(&mut reader).ok()?.len(),
+            ValueType::Lock => return None,
+        };
+        Some((md.value_type().type_name(), size))
     }
 }
 
@@ -528,9 +751,12 @@ impl Cron {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::metadata::StreamId;
     use crate::storage::{
-        PutFlags, SetDb, SetExistsResult, SetLenResult, StringsDb, ZSetAddMemberResult, ZSetDb,
-        ZSetLenResult, ZWriteFlags,
+        FindHashResult, HashDb, HashFieldExpiration, HashFieldTtl, HashFieldsTtlResult,
+        HashPutResult, PutFlags, SetDb, SetExistsResult, SetLenResult, StreamCountResult, StreamDb,
+        StreamIdSpec, StringGetResult, StringsDb, ZSetAddMemberResult, ZSetDb, ZSetLenResult,
+        ZWriteFlags,
     };
 
     #[test]
@@ -662,4 +888,283 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    fn test_eviction_of_stream_records() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_deleter, db) = crate::tests::open_store();
+            let mut stream_db = StreamDb::with_storage(&db, 0);
+
+            let stream_name = BytesMut::from("mystream");
+            for _ in 0..5 {
+                let fields = vec![(BytesMut::from("field"), BytesMut::from("value"))];
+                stream_db
+                    .add(&stream_name, StreamIdSpec::Auto, fields, false, None)
+                    .unwrap();
+                stream_db.commit().unwrap();
+            }
+            assert_eq!(
+                stream_db.len(&stream_name).unwrap(),
+                StreamCountResult::Some(5)
+            );
+
+            // a consumer group with a single consumer that has 5 pending entries
+            let group_name = BytesMut::from("mygroup");
+            let consumer_name = BytesMut::from("myconsumer");
+            stream_db
+                .create_group(&stream_name, &group_name, Some(StreamId::MIN), false, None)
+                .unwrap();
+            stream_db.commit().unwrap();
+            stream_db
+                .read_group(&stream_name, &group_name, &consumer_name, None, None, false)
+                .unwrap();
+            stream_db.commit().unwrap();
+
+            // override the stream creating zombie entries
+            let mut strings_db = StringsDb::with_storage(&db, 0);
+            let string_md = crate::StringValueMetadata::default();
+            let value = BytesMut::from("a string value");
+            strings_db
+                .put(&stream_name, &value, &string_md, PutFlags::Override)
+                .unwrap();
+
+            assert_eq!(
+                stream_db.len(&stream_name).unwrap(),
+                StreamCountResult::WrongType
+            );
+
+            // 5 entries, the group, 5 pending entries and the consumer
+            let items_evicted = Cron::evict(&db, false).await.unwrap();
+            assert_eq!(items_evicted, 12);
+        });
+    }
+
+    #[test]
+    fn test_active_expiration() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_deleter, db) = crate::tests::open_store();
+            let expired = crate::Expiration {
+                last_updated: 0,
+                ttl_ms: 1,
+            };
+
+            // 10 string keys, half of them expired
+            let mut strings_db = StringsDb::with_storage(&db, 0);
+            for i in 0..10 {
+                let mut string_md = crate::StringValueMetadata::default();
+                if i % 2 == 0 {
+                    *string_md.expiration_mut() = expired.clone();
+                }
+                let key = BytesMut::from(format!("key_{}", i).as_str());
+                let value = BytesMut::from("value");
+                strings_db
+                    .put(&key, &value, &string_md, PutFlags::Override)
+                    .unwrap();
+            }
+
+            // and an expired hash
+            let mut hash_db = HashDb::with_storage(&db, 0);
+            let hash_name = BytesMut::from("myhash");
+            let field = BytesMut::from("field");
+            let value = BytesMut::from("value");
+            assert_eq!(
+                hash_db.put_multi(&hash_name, &[(&field, &value)]).unwrap(),
+                HashPutResult::Some(1)
+            );
+            let mut generic_db = GenericDb::with_storage(&db, 0);
+            generic_db
+                .put_expiration(&hash_name, &expired, true)
+                .unwrap();
+
+            // remove the TTL of "key_0": its index entry is now stale
+            strings_db
+                .put(
+                    &BytesMut::from("key_0"),
+                    &BytesMut::from("value"),
+                    &crate::StringValueMetadata::default(),
+                    PutFlags::Override,
+                )
+                .unwrap();
+
+            // 6 index entries are due, visiting 3 entries per cycle requires 2 cycles
+            let mut keys_deleted = 0usize;
+            for _ in 0..3 {
+                keys_deleted += Cron::active_expire(&db, 3).await.unwrap();
+            }
+            assert_eq!(keys_deleted, 5);
+
+            // all the due index entries were removed
+            let db_iter = db.create_iterator(&TtlIndexKey::prefix()).unwrap();
+            assert!(
+                !db_iter.valid() || !db_iter.key().unwrap().starts_with(&TtlIndexKey::prefix())
+            );
+
+            // the hash field was deleted together with the hash
+            assert_eq!(Cron::evict(&db, false).await.unwrap(), 0);
+
+            // keys without TTL are not affected
+            for i in 0..10 {
+                let key = BytesMut::from(format!("key_{}", i).as_str());
+                let found = matches!(strings_db.get(&key).unwrap(), StringGetResult::Some(_));
+                assert_eq!(found, i == 0 || i % 2 != 0);
+            }
+            assert!(!db
+                .contains(&PrimaryKeyMetadata::new_primary_key(&hash_name, 0))
+                .unwrap());
+            assert!(Telemetry::expired_keys() >= 5);
+        });
+    }
+
+    #[test]
+    fn test_active_expiration_hash_fields() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_deleter, db) = crate::tests::open_store();
+            let mut hash_db = HashDb::with_storage(&db, 0);
+            let hash_name = BytesMut::from("myhash");
+            let field1 = BytesMut::from("field1");
+            let field2 = BytesMut::from("field2");
+            assert_eq!(
+                hash_db
+                    .put_multi(&hash_name, &[(&field1, &field1), (&field2, &field2)])
+                    .unwrap(),
+                HashPutResult::Some(2)
+            );
+
+            let expire_at = TimeUtils::epoch_ms().unwrap() + 20;
+            hash_db
+                .set_fields_expiration(&hash_name, &[&field1], HashFieldExpiration::At(expire_at))
+                .unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(50));
+
+            // hash fields are not counted as keys
+            assert_eq!(Cron::active_expire(&db, 10).await.unwrap(), 0);
+            let FindHashResult::Some(hash) = hash_db.find_hash(&hash_name).unwrap() else {
+                panic!("expected to find the hash");
+            };
+            assert_eq!(hash.len(), 1);
+            assert_eq!(hash.value.volatile_len(), 0);
+            assert_eq!(
+                hash_db.fields_ttl(&hash_name, &[&field1, &field2]).unwrap(),
+                HashFieldsTtlResult::Some(vec![
+                    HashFieldTtl::NoSuchField,
+                    HashFieldTtl::Persistent
+                ])
+            );
+
+            // expiring the last field deletes the hash
+            let expire_at = TimeUtils::epoch_ms().unwrap() + 20;
+            hash_db
+                .set_fields_expiration(&hash_name, &[&field2], HashFieldExpiration::At(expire_at))
+                .unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            assert_eq!(Cron::active_expire(&db, 10).await.unwrap(), 0);
+            assert!(!db
+                .contains(&PrimaryKeyMetadata::new_primary_key(&hash_name, 0))
+                .unwrap());
+            assert_eq!(Cron::evict(&db, false).await.unwrap(), 0);
+        });
+    }
+
+    #[test]
+    fn test_persist_access_times() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_deleter, db) = crate::tests::open_store();
+            let key = BytesMut::from("test_persist_access_times");
+            let mut strings_db = StringsDb::with_storage(&db, 3);
+            strings_db
+                .put(
+                    &key,
+                    &BytesMut::from("value"),
+                    &crate::StringValueMetadata::default(),
+                    PutFlags::Override,
+                )
+                .unwrap();
+
+            let mut generic_db = GenericDb::with_storage(&db, 3);
+            let now = TimeUtils::epoch_ms().unwrap();
+            assert!(generic_db.set_last_accessed(&key, now - 60_000).unwrap());
+
+            // recording an access does not write anything
+            Telemetry::record_key_access(3, &[&key], now);
+            assert_eq!(Telemetry::key_last_accessed(3, &key), Some(now));
+            assert_eq!(
+                generic_db.get(&key).unwrap().unwrap().1.last_accessed(),
+                now - 60_000
+            );
+
+            assert!(Cron::persist_access_times(&db).await.unwrap() >= 1);
+            assert_eq!(Telemetry::key_last_accessed(3, &key), None);
+            assert_eq!(
+                generic_db.get(&key).unwrap().unwrap().1.last_accessed(),
+                now
+            );
+        });
+    }
+
+    #[test]
+    fn test_keyspace_analysis() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_deleter, db) = crate::tests::open_store();
+            let mut strings_db = StringsDb::with_storage(&db, 0);
+            for (key, value) in [("small", "a"), ("big", "a bigger value"), ("medium", "abc")] {
+                strings_db
+                    .put(
+                        &BytesMut::from(key),
+                        &BytesMut::from(value),
+                        &crate::StringValueMetadata::default(),
+                        PutFlags::Override,
+                    )
+                    .unwrap();
+            }
+
+            let mut hash_db = HashDb::with_storage(&db, 1);
+            let hash_name = BytesMut::from("myhash");
+            let fields: Vec<BytesMut> = (0..3)
+                .map(|i| BytesMut::from(format!("field_{}", i).as_str()))
+                .collect();
+            let fields: Vec<(&BytesMut, &BytesMut)> =
+                fields.iter().map(|field| (field, field)).collect();
+            assert_eq!(
+                hash_db.put_multi(&hash_name, &fields).unwrap(),
+                HashPutResult::Some(3)
+            );
+
+            // sample a key access
+            let hot_key = BytesMut::from("test_keyspace_analysis_hot_key");
+            std::thread::spawn(move || {
+                for _ in 0..crate::server::telemetry::KEY_ACCESS_SAMPLE_RATE {
+                    Telemetry::sample_key_access(0, &[&hot_key]);
+                }
+            })
+            .join()
+            .unwrap();
+
+            let storage_metadata = Cron::collect_storage_metadata(&db, 2).await.unwrap();
+            assert_eq!(storage_metadata.total_key_count(), 4);
+
+            let big_keys = storage_metadata.big_keys();
+            let big_strings: Vec<(&[u8], u64)> = big_keys["string"]
+                .keys()
+                .iter()
+                .map(|key| (&key.user_key[..], key.value))
+                .collect();
+            assert_eq!(big_strings, vec![(&b"big"[..], 14), (&b"medium"[..], 3)]);
+
+            let big_hashes = big_keys["hash"].keys();
+            assert_eq!(big_hashes.len(), 1);
+            assert_eq!(big_hashes[0].db_id, 1);
+            assert_eq!(big_hashes[0].user_key, hash_name);
+            assert_eq!(big_hashes[0].value, 3);
+
+            assert!(storage_metadata.hot_keys().iter().any(|key| {
+                key.user_key == "test_keyspace_analysis_hot_key"
+                    && key.value == crate::server::telemetry::KEY_ACCESS_SAMPLE_RATE
+            }));
+        });
+    }
 }
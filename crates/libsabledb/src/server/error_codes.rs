@@ -83,6 +83,8 @@ pub enum SableError {
     Backoff,
     #[error("SerializationErr. {0}")]
     BincodeSerializationErr(#[from] bincode::Error),
+    #[error("Lua error. {0}")]
+    LuaError(#[from] mlua::Error),
 }
 
 #[allow(dead_code)]
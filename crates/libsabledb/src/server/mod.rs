@@ -1,8 +1,11 @@
+mod acl;
 mod client;
 mod client_state;
 mod cron_thread;
 mod error_codes;
 mod node_state;
+mod pubsub;
+mod scripting;
 #[allow(clippy::module_inception)]
 mod server;
 mod server_options;
@@ -14,11 +17,14 @@ mod worker_manager;
 
 pub type WorkerHandle = tokio::runtime::Handle;
 
+pub use acl::*;
 pub use client::*;
 pub use client_state::*;
 pub use cron_thread::*;
 pub use error_codes::*;
 pub use node_state::*;
+pub use pubsub::*;
+pub use scripting::*;
 pub use server::*;
 pub use server_options::*;
 pub use slots::*;
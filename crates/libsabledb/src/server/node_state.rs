@@ -79,6 +79,7 @@ impl From<&Node> for NodeExt {
 struct ServerPersistentStateInner {
     node_id: String,
     primary_node_id: String,
+    upstream_node_id: String,
     role: u8,
     private_primary_address: String,
     config_file: String,
@@ -184,6 +185,7 @@ impl ServerPersistentState {
             inner: Arc::new(RwLock::new(ServerPersistentStateInner {
                 node_id: utils::create_uuid(),
                 primary_node_id: String::default(),
+                upstream_node_id: String::default(),
                 role: ROLE_PRIMARY,
                 private_primary_address: String::default(),
                 config_file: String::default(),
@@ -223,6 +225,7 @@ impl ServerPersistentState {
             // Clear replica related values
             inner.private_primary_address.clear();
             inner.primary_node_id.clear();
+            inner.upstream_node_id.clear();
         }
     }
 
@@ -311,6 +314,24 @@ impl ServerPersistentState {
             .clone()
     }
 
+    /// Set the node ID this replica is replicating from. For a replica that is directly
+    /// connected to the shard primary, this is the same as the primary node ID. For a
+    /// cascading replica, this is the ID of the replica it is connected to
+    #[inline]
+    pub fn set_upstream_node_id(&self, upstream_node_id: String) {
+        self.inner.write().expect(POISONED_MUTEX).upstream_node_id = upstream_node_id;
+    }
+
+    /// Return the node ID this replica is replicating from
+    #[inline]
+    pub fn upstream_node_id(&self) -> String {
+        self.inner
+            .read()
+            .expect(POISONED_MUTEX)
+            .upstream_node_id
+            .clone()
+    }
+
     #[inline]
     pub fn set_shard_name(&self, shard_name: String) {
         self.inner.write().expect(POISONED_MUTEX).shard_name = shard_name;
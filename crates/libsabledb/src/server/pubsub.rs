@@ -0,0 +1,357 @@
+use crate::{utils::PatternMatcher, RespBuilderV2};
+use bytes::BytesMut;
+use dashmap::DashMap;
+use std::collections::HashMap;
+use tokio::sync::mpsc::error::TrySendError;
+
+pub type PubSubSender = tokio::sync::mpsc::Sender<BytesMut>;
+pub type PubSubReceiver = tokio::sync::mpsc::Receiver<BytesMut>;
+
+/// The maximum number of messages that can be queued for a single subscriber.
+/// A subscriber that can't keep up with this rate is disconnected
+pub const PUBSUB_CLIENT_QUEUE_SIZE: usize = 10_000;
+
+/// Maps between a channel (or a pattern) and the clients subscribed to it
+type Subscribers = DashMap<BytesMut, HashMap<u128, PubSubSender>>;
+
+/// The outcome of a `PubSubRegistry::publish` call
+#[derive(Default, Debug)]
+pub struct PublishResult {
+    /// Number of clients that received the message
+    pub receivers: usize,
+    /// Clients with a full message queue. These clients should be disconnected
+    pub slow_clients: Vec<u128>,
+}
+
+/// Process wide table of pub/sub subscriptions.
+///
+/// The registry is shared by all the workers (it is owned by the `ServerState`). Each subscriber
+/// registers the sending side of its own push channel, so a message published from any worker is
+/// delivered directly to the writer task of the subscribed client
+#[derive(Default)]
+pub struct PubSubRegistry {
+    channels: Subscribers,
+    patterns: Subscribers,
+    shard_channels: Subscribers,
+}
+
+impl PubSubRegistry {
+    /// Subscribe `client_id` to `channel`
+    pub fn subscribe(&self, channel: &BytesMut, client_id: u128, tx: PubSubSender) {
+        Self::add_subscriber(&self.channels, channel, client_id, tx);
+    }
+
+    /// Remove `client_id` from the subscribers of `channel`
+    pub fn unsubscribe(&self, channel: &BytesMut, client_id: u128) {
+        Self::remove_subscriber(&self.channels, channel, client_id);
+    }
+
+    /// Subscribe `client_id` to all channels matching `pattern`
+    pub fn psubscribe(&self, pattern: &BytesMut, client_id: u128, tx: PubSubSender) {
+        Self::add_subscriber(&self.patterns, pattern, client_id, tx);
+    }
+
+    /// Remove `client_id` from the subscribers of `pattern`
+    pub fn punsubscribe(&self, pattern: &BytesMut, client_id: u128) {
+        Self::remove_subscriber(&self.patterns, pattern, client_id);
+    }
+
+    /// Subscribe `client_id` to the shard channel `channel`
+    pub fn ssubscribe(&self, channel: &BytesMut, client_id: u128, tx: PubSubSender) {
+        Self::add_subscriber(&self.shard_channels, channel, client_id, tx);
+    }
+
+    /// Remove `client_id` from the subscribers of the shard channel `channel`
+    pub fn sunsubscribe(&self, channel: &BytesMut, client_id: u128) {
+        Self::remove_subscriber(&self.shard_channels, channel, client_id);
+    }
+
+    /// Remove all the subscriptions of `client_id`
+    pub fn remove_client(
+        &self,
+        client_id: u128,
+        channels: &[BytesMut],
+        patterns: &[BytesMut],
+        shard_channels: &[BytesMut],
+    ) {
+        for channel in channels {
+            self.unsubscribe(channel, client_id);
+        }
+        for pattern in patterns {
+            self.punsubscribe(pattern, client_id);
+        }
+        for channel in shard_channels {
+            self.sunsubscribe(channel, client_id);
+        }
+    }
+
+    /// Post `message` to all the clients subscribed to `channel` and to all the clients subscribed
+    /// to a pattern that matches `channel`
+    pub fn publish(&self, channel: &BytesMut, message: &BytesMut) -> PublishResult {
+        let builder = RespBuilderV2::default();
+        let mut result = PublishResult::default();
+
+        if let Some(subscribers) = self.channels.get(channel) {
+            let mut buffer = BytesMut::with_capacity(32 + channel.len() + message.len());
+            builder.add_array_len(&mut buffer, 3);
+            builder.add_bulk_string(&mut buffer, b"message");
+            builder.add_bulk_string(&mut buffer, channel);
+            builder.add_bulk_string(&mut buffer, message);
+            for (client_id, tx) in subscribers.value() {
+                Self::deliver(*client_id, tx, &buffer, &mut result);
+            }
+        }
+
+        for entry in self.patterns.iter() {
+            let matcher = PatternMatcher::builder().wildcard(entry.key()).build();
+            if !matcher.matches(channel) {
+                continue;
+            }
+
+            let mut buffer =
+                BytesMut::with_capacity(48 + entry.key().len() + channel.len() + message.len());
+            builder.add_array_len(&mut buffer, 4);
+            builder.add_bulk_string(&mut buffer, b"pmessage");
+            builder.add_bulk_string(&mut buffer, entry.key());
+            builder.add_bulk_string(&mut buffer, channel);
+            builder.add_bulk_string(&mut buffer, message);
+            for (client_id, tx) in entry.value() {
+                Self::deliver(*client_id, tx, &buffer, &mut result);
+            }
+        }
+        result
+    }
+
+    /// Post `message` to all the clients subscribed to the shard channel `channel`.
+    /// Shard channels are not matched against patterns
+    pub fn spublish(&self, channel: &BytesMut, message: &BytesMut) -> PublishResult {
+        let mut result = PublishResult::default();
+        let Some(subscribers) = self.shard_channels.get(channel) else {
+            return result;
+        };
+
+        let builder = RespBuilderV2::default();
+        let mut buffer = BytesMut::with_capacity(32 + channel.len() + message.len());
+        builder.add_array_len(&mut buffer, 3);
+        builder.add_bulk_string(&mut buffer, b"smessage");
+        builder.add_bulk_string(&mut buffer, channel);
+        builder.add_bulk_string(&mut buffer, message);
+        for (client_id, tx) in subscribers.value() {
+            Self::deliver(*client_id, tx, &buffer, &mut result);
+        }
+        result
+    }
+
+    /// Return the list of active channels (channels with at least one subscriber).
+    /// If `pattern` is provided, only channels matching it are returned
+    pub fn channels(&self, pattern: Option<&BytesMut>) -> Vec<BytesMut> {
+        Self::active_channels(&self.channels, pattern)
+    }
+
+    /// Return the list of active shard channels. If `pattern` is provided, only shard channels
+    /// matching it are returned
+    pub fn shard_channels(&self, pattern: Option<&BytesMut>) -> Vec<BytesMut> {
+        Self::active_channels(&self.shard_channels, pattern)
+    }
+
+    /// Return the number of subscribers of `channel` (pattern subscribers are not counted)
+    pub fn numsub(&self, channel: &BytesMut) -> usize {
+        Self::subscribers_count(&self.channels, channel)
+    }
+
+    /// Return the number of subscribers of the shard channel `channel`
+    pub fn shard_numsub(&self, channel: &BytesMut) -> usize {
+        Self::subscribers_count(&self.shard_channels, channel)
+    }
+
+    /// Return the number of unique patterns that clients are subscribed to
+    pub fn numpat(&self) -> usize {
+        self.patterns.len()
+    }
+
+    fn active_channels(subscribers: &Subscribers, pattern: Option<&BytesMut>) -> Vec<BytesMut> {
+        let matcher = match pattern {
+            Some(pattern) => PatternMatcher::builder().wildcard(pattern).build(),
+            None => PatternMatcher::builder().pass_through().build(),
+        };
+
+        subscribers
+            .iter()
+            .filter(|entry| matcher.matches(entry.key()))
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
+    fn subscribers_count(subscribers: &Subscribers, name: &BytesMut) -> usize {
+        subscribers
+            .get(name)
+            .map(|clients| clients.len())
+            .unwrap_or(0)
+    }
+
+    fn add_subscriber(
+        subscribers: &Subscribers,
+        name: &BytesMut,
+        client_id: u128,
+        tx: PubSubSender,
+    ) {
+        subscribers
+            .entry(name.clone())
+            .or_default()
+            .insert(client_id, tx);
+    }
+
+    fn remove_subscriber(subscribers: &Subscribers, name: &BytesMut, client_id: u128) {
+        // The entry reference must be released before we attempt to remove the entry
+        let is_empty = match subscribers.get_mut(name) {
+            Some(mut entry) => {
+                entry.remove(&client_id);
+                entry.is_empty()
+            }
+            None => false,
+        };
+
+        if is_empty {
+            subscribers.remove_if(name, |_, clients| clients.is_empty());
+        }
+    }
+
+    fn deliver(client_id: u128, tx: &PubSubSender, buffer: &BytesMut, result: &mut PublishResult) {
+        match tx.try_send(buffer.clone()) {
+            Ok(()) => {
+                result.receivers = result.receivers.saturating_add(1);
+            }
+            Err(TrySendError::Full(_)) => {
+                result.slow_clients.push(client_id);
+            }
+            Err(TrySendError::Closed(_)) => {
+                // The client is gone, its subscriptions are removed when the connection is dropped
+            }
+        }
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_to_channel_and_pattern() {
+        let registry = PubSubRegistry::default();
+        let (tx1, mut rx1) = tokio::sync::mpsc::channel::<BytesMut>(10);
+        let (tx2, mut rx2) = tokio::sync::mpsc::channel::<BytesMut>(10);
+
+        registry.subscribe(&BytesMut::from("news"), 1, tx1);
+        registry.psubscribe(&BytesMut::from("n*"), 2, tx2);
+
+        let result = registry.publish(&BytesMut::from("news"), &BytesMut::from("hello"));
+        assert_eq!(result.receivers, 2);
+        assert!(result.slow_clients.is_empty());
+
+        assert_eq!(
+            rx1.try_recv().unwrap(),
+            BytesMut::from("*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n")
+        );
+        assert_eq!(
+            rx2.try_recv().unwrap(),
+            BytesMut::from("*4\r\n$8\r\npmessage\r\n$2\r\nn*\r\n$4\r\nnews\r\n$5\r\nhello\r\n")
+        );
+
+        // No subscribers for this channel
+        let result = registry.publish(&BytesMut::from("sport"), &BytesMut::from("hello"));
+        assert_eq!(result.receivers, 0);
+    }
+
+    #[test]
+    fn test_introspection() {
+        let registry = PubSubRegistry::default();
+        let (tx, _rx) = tokio::sync::mpsc::channel::<BytesMut>(10);
+
+        registry.subscribe(&BytesMut::from("news.tech"), 1, tx.clone());
+        registry.subscribe(&BytesMut::from("news.tech"), 2, tx.clone());
+        registry.subscribe(&BytesMut::from("sport"), 1, tx.clone());
+        registry.psubscribe(&BytesMut::from("news.*"), 1, tx.clone());
+        registry.psubscribe(&BytesMut::from("news.*"), 2, tx);
+
+        assert_eq!(registry.channels(None).len(), 2);
+        assert_eq!(
+            registry.channels(Some(&BytesMut::from("news.*"))),
+            vec![BytesMut::from("news.tech")]
+        );
+        assert_eq!(registry.numsub(&BytesMut::from("news.tech")), 2);
+        assert_eq!(registry.numsub(&BytesMut::from("no_such_channel")), 0);
+        assert_eq!(registry.numpat(), 1);
+
+        registry.remove_client(
+            1,
+            &[BytesMut::from("news.tech"), BytesMut::from("sport")],
+            &[BytesMut::from("news.*")],
+            &[],
+        );
+        assert_eq!(registry.channels(None), vec![BytesMut::from("news.tech")]);
+        assert_eq!(registry.numsub(&BytesMut::from("news.tech")), 1);
+        assert_eq!(registry.numpat(), 1);
+
+        registry.unsubscribe(&BytesMut::from("news.tech"), 2);
+        registry.punsubscribe(&BytesMut::from("news.*"), 2);
+        assert!(registry.channels(None).is_empty());
+        assert_eq!(registry.numpat(), 0);
+    }
+
+    #[test]
+    fn test_shard_channels() {
+        let registry = PubSubRegistry::default();
+        let (tx1, mut rx1) = tokio::sync::mpsc::channel::<BytesMut>(10);
+        let (tx2, mut rx2) = tokio::sync::mpsc::channel::<BytesMut>(10);
+
+        registry.ssubscribe(&BytesMut::from("orders"), 1, tx1);
+        // shard channels are not visible to pattern or regular subscribers
+        registry.psubscribe(&BytesMut::from("*"), 2, tx2.clone());
+        registry.subscribe(&BytesMut::from("orders"), 2, tx2);
+
+        let result = registry.spublish(&BytesMut::from("orders"), &BytesMut::from("hello"));
+        assert_eq!(result.receivers, 1);
+        assert_eq!(
+            rx1.try_recv().unwrap(),
+            BytesMut::from("*3\r\n$8\r\nsmessage\r\n$6\r\norders\r\n$5\r\nhello\r\n")
+        );
+        assert!(rx2.try_recv().is_err());
+
+        assert_eq!(
+            registry.shard_channels(None),
+            vec![BytesMut::from("orders")]
+        );
+        assert_eq!(
+            registry.shard_channels(Some(&BytesMut::from("news*"))),
+            Vec::<BytesMut>::new()
+        );
+        assert_eq!(registry.shard_numsub(&BytesMut::from("orders")), 1);
+        assert_eq!(registry.numsub(&BytesMut::from("orders")), 1);
+
+        registry.remove_client(1, &[], &[], &[BytesMut::from("orders")]);
+        assert!(registry.shard_channels(None).is_empty());
+        assert_eq!(registry.shard_numsub(&BytesMut::from("orders")), 0);
+    }
+
+    #[test]
+    fn test_slow_subscriber() {
+        let registry = PubSubRegistry::default();
+        let (tx, _rx) = tokio::sync::mpsc::channel::<BytesMut>(1);
+        registry.subscribe(&BytesMut::from("news"), 7, tx);
+
+        let result = registry.publish(&BytesMut::from("news"), &BytesMut::from("first"));
+        assert_eq!(result.receivers, 1);
+
+        // the queue is full now
+        let result = registry.publish(&BytesMut::from("news"), &BytesMut::from("second"));
+        assert_eq!(result.receivers, 0);
+        assert_eq!(result.slow_clients, vec![7u128]);
+    }
+}
@@ -0,0 +1,824 @@
+use crate::{
+    commands::{ClientNextAction, Strings},
+    metadata::{FunctionInfo, FunctionLibrary},
+    server::{Client, ClientState},
+    RespBuilderV2, RespResponseParserV2, ResponseParseResult, SableError, ServerState,
+    ValkeyCommand, ValkeyCommandName, ValkeyObject,
+};
+use bytes::BytesMut;
+use dashmap::DashMap;
+use mlua::{Function, HookTriggers, IntoLuaMulti, Lua, LuaOptions, StdLib, Table, Value, Variadic};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Lua code that completes the `redis` library. `redis.call` is built on top of `redis.pcall`: it
+/// raises the error table instead of returning it
+const REDIS_LIB: &str = r#"
+unpack = table.unpack
+redis.call = function(...)
+    local reply = redis.pcall(...)
+    if type(reply) == 'table' and reply.err ~= nil then
+        error(reply, 0)
+    end
+    return reply
+end
+redis.error_reply = function(msg)
+    return { err = msg }
+end
+redis.status_reply = function(msg)
+    return { ok = msg }
+end
+"#;
+
+/// Runs the user script in protected mode. Errors raised by the script are converted into an
+/// error reply
+const SCRIPT_RUNNER: &str = r#"
+local ok, reply = pcall(...)
+if ok or (type(reply) == 'table' and reply.err ~= nil) then
+    return reply
+end
+return { err = 'ERR Error running script: ' .. tostring(reply) }
+"#;
+
+/// Registry table that collects the functions registered by a library while it is loaded
+const REGISTERED_FUNCTIONS: &str = "__registered_functions";
+
+/// The flags accepted by `redis.register_function`
+const FUNCTION_FLAGS: [&str; 5] = [
+    FunctionInfo::FLAG_NO_WRITES,
+    "allow-oom",
+    "allow-stale",
+    "no-cluster",
+    "allow-cross-slot-keys",
+];
+
+/// Number of Lua instructions executed between two checks of the script state (killed, busy)
+const HOOK_INSTRUCTIONS: u32 = 10_000;
+
+const ERR_INVALID_LIBRARY_NAME: &str = "ERR Library names can only contain letters, numbers, or underscores(_) and must be at least one character long";
+const ERR_INVALID_FUNCTION_NAME: &str = "ERR Function names can only contain letters, numbers, or underscores(_) and must be at least one character long";
+
+/// Process wide cache of Lua scripts, keyed by their SHA1 digest.
+///
+/// The cache is shared by all the workers (it is owned by the `ServerState`). Scripts are added
+/// by `SCRIPT LOAD` and `EVAL` and are executed by `EVALSHA`
+#[derive(Default)]
+pub struct ScriptCache {
+    scripts: DashMap<String, BytesMut>,
+}
+
+impl ScriptCache {
+    /// Return the SHA1 digest of `body` as a lowercase hex string
+    pub fn digest(body: &[u8]) -> String {
+        sha1_smol::Sha1::from(body).digest().to_string()
+    }
+
+    /// Add `body` to the cache and return its SHA1 digest
+    pub fn load(&self, body: &BytesMut) -> String {
+        let sha = Self::digest(body);
+        self.scripts
+            .entry(sha.clone())
+            .or_insert_with(|| body.clone());
+        sha
+    }
+
+    /// Return the script identified by `sha`
+    pub fn get(&self, sha: &str) -> Option<BytesMut> {
+        self.scripts
+            .get(&sha.to_lowercase())
+            .map(|script| script.value().clone())
+    }
+
+    /// Return `true` if a script identified by `sha` exists in the cache
+    pub fn contains(&self, sha: &str) -> bool {
+        self.scripts.contains_key(&sha.to_lowercase())
+    }
+
+    /// Remove all the scripts from the cache
+    pub fn flush(&self) {
+        self.scripts.clear();
+    }
+}
+
+/// A script executed by `EVAL` or `FCALL` (see `RunningScripts`)
+pub struct RunningScript {
+    started: Instant,
+    time_limit: Duration,
+    /// One of `RUNNING`, `WROTE` or `KILLED`
+    state: AtomicU8,
+    /// Set once the script runs for longer than `time_limit`
+    busy: AtomicBool,
+}
+
+impl RunningScript {
+    const RUNNING: u8 = 0;
+    /// The script ran a write command, it can no longer be killed
+    const WROTE: u8 = 1;
+    /// Stopped by `SCRIPT KILL`
+    const KILLED: u8 = 2;
+
+    fn new(time_limit: Duration) -> Self {
+        RunningScript {
+            started: Instant::now(),
+            time_limit,
+            state: AtomicU8::new(Self::RUNNING),
+            busy: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_killed(&self) -> bool {
+        self.state.load(Ordering::Acquire) == Self::KILLED
+    }
+
+    /// Called before the script runs a write command. Return `false` if the script was killed
+    fn start_write(&self) -> bool {
+        match self.state.compare_exchange(
+            Self::RUNNING,
+            Self::WROTE,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => true,
+            Err(state) => state == Self::WROTE,
+        }
+    }
+
+    /// Called by `SCRIPT KILL`. Return `false` if the script already wrote to the database
+    fn kill(&self) -> bool {
+        match self.state.compare_exchange(
+            Self::RUNNING,
+            Self::KILLED,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => true,
+            Err(state) => state == Self::KILLED,
+        }
+    }
+}
+
+/// The scripts executed by the workers, keyed by the client ID. Shared by all the workers (it is
+/// owned by the `ServerState`).
+///
+/// A script runs on its worker until it completes. Once a script runs for longer than
+/// `lua_time_limit_ms`, the other clients are answered with `BUSY` until the script completes or
+/// is stopped by `SCRIPT KILL`
+#[derive(Default)]
+pub struct RunningScripts {
+    scripts: DashMap<u128, Arc<RunningScript>>,
+    /// Number of running scripts that exceeded their time limit
+    busy_scripts: AtomicUsize,
+}
+
+impl RunningScripts {
+    fn start(&self, client_id: u128, time_limit: Duration) -> Arc<RunningScript> {
+        let script = Arc::new(RunningScript::new(time_limit));
+        self.scripts.insert(client_id, script.clone());
+        script
+    }
+
+    fn finish(&self, client_id: u128) {
+        if let Some((_, script)) = self.scripts.remove(&client_id) {
+            if script.busy.load(Ordering::Acquire) {
+                self.busy_scripts.fetch_sub(1, Ordering::AcqRel);
+            }
+        }
+    }
+
+    /// Return `true` while a script runs for longer than its time limit
+    pub fn is_busy(&self) -> bool {
+        self.busy_scripts.load(Ordering::Acquire) > 0
+    }
+
+    /// `SCRIPT KILL`: stop the running scripts that did not write to the database. On error,
+    /// return the error to send back to the client
+    pub fn kill(&self) -> Result<(), &'static str> {
+        if self.scripts.is_empty() {
+            return Err(Strings::NOTBUSY);
+        }
+
+        let mut killed = false;
+        for script in self.scripts.iter() {
+            killed |= script.value().kill();
+        }
+        if killed {
+            Ok(())
+        } else {
+            Err(Strings::UNKILLABLE)
+        }
+    }
+
+    /// Called by the Lua instruction hook: abort a killed script and flag a script that exceeded
+    /// its time limit as busy
+    fn check(&self, script: &RunningScript) -> mlua::Result<()> {
+        if script.is_killed() {
+            return Err(mlua::Error::RuntimeError(
+                Strings::ERR_SCRIPT_KILLED.to_string(),
+            ));
+        }
+
+        if !script.busy.load(Ordering::Acquire) && script.started.elapsed() >= script.time_limit {
+            script.busy.store(true, Ordering::Release);
+            self.busy_scripts.fetch_add(1, Ordering::AcqRel);
+            tracing::warn!(
+                "Lua script is running for more than {} ms, replying with BUSY to the other clients",
+                script.time_limit.as_millis()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Registers the script executed by a client in the `RunningScripts` and removes it when
+/// leaving the scope
+struct ScopedRunningScript {
+    server_state: Arc<ServerState>,
+    client_id: u128,
+    script: Arc<RunningScript>,
+}
+
+impl ScopedRunningScript {
+    fn new(client_state: &ClientState) -> Self {
+        let server_state = client_state.server_inner_state();
+        let time_limit_ms = server_state
+            .options()
+            .read()
+            .expect("poisoned mutex")
+            .general_settings
+            .lua_time_limit_ms;
+        let script = server_state.running_scripts().start(
+            client_state.id(),
+            Duration::from_millis(time_limit_ms as u64),
+        );
+        ScopedRunningScript {
+            server_state,
+            client_id: client_state.id(),
+            script,
+        }
+    }
+}
+
+impl Drop for ScopedRunningScript {
+    fn drop(&mut self) {
+        self.server_state.running_scripts().finish(self.client_id);
+    }
+}
+
+/// Embedded Lua interpreter.
+///
+/// Each invocation gets its own Lua state, so scripts executed by different clients on the same
+/// worker can never observe each other's globals. `redis.call` and `redis.pcall` are dispatched
+/// into `Client::handle_non_exec_command`, i.e. the same handlers used for commands arriving over
+/// the network. The engine does not lock anything: the caller is responsible for making the
+/// execution atomic
+pub struct LuaEngine {}
+
+impl LuaEngine {
+    /// Execute the script `body` with `keys` and `args` exposed as `KEYS` and `ARGV`. When
+    /// `read_only` is `true`, write commands are rejected. On success, return the script reply
+    /// encoded as RESP
+    pub async fn eval(
+        client_state: Rc<ClientState>,
+        body: &[u8],
+        keys: &[BytesMut],
+        args: &[BytesMut],
+        read_only: bool,
+    ) -> Result<BytesMut, SableError> {
+        let running = ScopedRunningScript::new(&client_state);
+        let lua = Self::create_state()?;
+        Self::bind_client(&lua, client_state, running.script.clone(), read_only)?;
+
+        let globals = lua.globals();
+        globals.set("KEYS", Self::strings_table(&lua, keys)?)?;
+        globals.set("ARGV", Self::strings_table(&lua, args)?)?;
+
+        let builder = RespBuilderV2::default();
+        let mut response = BytesMut::with_capacity(256);
+        let script = match lua.load(body).set_name("@user_script").into_function() {
+            Ok(script) => script,
+            Err(e) => {
+                builder.error_string(
+                    &mut response,
+                    &format!(
+                        "ERR Error compiling script: {}",
+                        Self::single_line(&e.to_string())
+                    ),
+                );
+                return Ok(response);
+            }
+        };
+
+        let reply = Self::run_script(&lua, &running, script).await;
+        if running.script.is_killed() {
+            builder.error_string(&mut response, Strings::ERR_SCRIPT_KILLED);
+            return Ok(response);
+        }
+        Self::add_reply(&builder, &mut response, &reply?)?;
+        Ok(response)
+    }
+
+    /// Parse and validate the library `code` as passed to `FUNCTION LOAD`. The code must start
+    /// with a `#!lua name=<library name>` line and register at least one function. On error,
+    /// return the error message to send back to the client
+    pub fn parse_library(code: &BytesMut) -> Result<FunctionLibrary, String> {
+        let (name, _) = Self::parse_library_metadata(code)?;
+        let lua = Self::create_state().map_err(|e| format!("ERR {}", e))?;
+        let registered = Self::load_library(&lua, code)?;
+        let mut functions = Vec::<FunctionInfo>::with_capacity(registered.len());
+        for (function_name, entry) in registered {
+            let flags = entry
+                .get::<_, Table>("flags")
+                .and_then(|flags| {
+                    flags
+                        .sequence_values::<mlua::String>()
+                        .map(|flag| flag.map(|flag| BytesMut::from(flag.as_bytes())))
+                        .collect::<mlua::Result<Vec<BytesMut>>>()
+                })
+                .map_err(|e| format!("ERR {}", Self::error_message(&e)))?;
+            functions.push(FunctionInfo {
+                name: BytesMut::from(function_name.as_bytes()),
+                flags,
+            });
+        }
+        Ok(FunctionLibrary {
+            name,
+            code: code.clone(),
+            functions,
+        })
+    }
+
+    /// Call the function `function` of `library`. The function receives the keys and the
+    /// arguments as two tables. When `read_only` is `true`, write commands are rejected
+    pub async fn fcall(
+        client_state: Rc<ClientState>,
+        library: &FunctionLibrary,
+        function: &[u8],
+        keys: &[BytesMut],
+        args: &[BytesMut],
+        read_only: bool,
+    ) -> Result<BytesMut, SableError> {
+        let running = ScopedRunningScript::new(&client_state);
+        let lua = Self::create_state()?;
+        Self::bind_client(&lua, client_state, running.script.clone(), read_only)?;
+
+        let builder = RespBuilderV2::default();
+        let mut response = BytesMut::with_capacity(256);
+        let registered = match Self::load_library(&lua, &library.code) {
+            Ok(registered) => registered,
+            Err(msg) => {
+                builder.error_string(&mut response, &msg);
+                return Ok(response);
+            }
+        };
+
+        let Some((_, entry)) = registered
+            .into_iter()
+            .find(|(name, _)| name.as_bytes() == function)
+        else {
+            builder.error_string(&mut response, Strings::ERR_FUNCTION_NOT_FOUND);
+            return Ok(response);
+        };
+
+        let callback: Function = entry.get("callback")?;
+        let reply = Self::run_script(
+            &lua,
+            &running,
+            (
+                callback,
+                Self::strings_table(&lua, keys)?,
+                Self::strings_table(&lua, args)?,
+            ),
+        )
+        .await;
+        if running.script.is_killed() {
+            builder.error_string(&mut response, Strings::ERR_SCRIPT_KILLED);
+            return Ok(response);
+        }
+        Self::add_reply(&builder, &mut response, &reply?)?;
+        Ok(response)
+    }
+
+    /// Run `SCRIPT_RUNNER` with `args` (the user function followed by its arguments) in a new Lua
+    /// thread. Every `HOOK_INSTRUCTIONS` instructions, a hook stops the script if it was killed
+    /// and flags it as busy once it exceeds its time limit
+    async fn run_script<'lua>(
+        lua: &'lua Lua,
+        running: &ScopedRunningScript,
+        args: impl IntoLuaMulti<'lua>,
+    ) -> mlua::Result<Value<'lua>> {
+        let runner = lua
+            .load(SCRIPT_RUNNER)
+            .set_name("@script_runner")
+            .into_function()?;
+        let thread = lua.create_thread(runner)?;
+        let server_state = running.server_state.clone();
+        let script = running.script.clone();
+        thread.set_hook(
+            HookTriggers::new().every_nth_instruction(HOOK_INSTRUCTIONS),
+            move |_, _| server_state.running_scripts().check(&script),
+        );
+        thread.into_async(args).await
+    }
+
+    /// Create a new Lua state with the `redis` library. The state is not bound to any client:
+    /// `redis.call` and `redis.pcall` are available only after calling `bind_client`
+    fn create_state() -> Result<Lua, SableError> {
+        let lua = Lua::new_with(
+            StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+            LuaOptions::default(),
+        )?;
+        let redis = lua.create_table()?;
+        let sha1hex =
+            lua.create_function(|_, body: mlua::String| Ok(ScriptCache::digest(body.as_bytes())))?;
+        redis.set("sha1hex", sha1hex)?;
+        redis.set(
+            "register_function",
+            lua.create_function(Self::register_function)?,
+        )?;
+        lua.globals().set("redis", redis)?;
+
+        lua.load(REDIS_LIB).set_name("@redis_lib").exec()?;
+        Ok(lua)
+    }
+
+    /// Route `redis.pcall` (and `redis.call`) to `client_state`
+    fn bind_client(
+        lua: &Lua,
+        client_state: Rc<ClientState>,
+        script: Arc<RunningScript>,
+        read_only: bool,
+    ) -> Result<(), SableError> {
+        let pcall = lua.create_async_function(move |lua, args: Variadic<Value>| {
+            let client_state = client_state.clone();
+            let script = script.clone();
+            async move { Self::pcall(lua, client_state, &script, args, read_only).await }
+        })?;
+        let redis: Table = lua.globals().get("redis")?;
+        redis.set("pcall", pcall)?;
+        Ok(())
+    }
+
+    /// Run the library `code` and return the functions it registered, sorted by name
+    fn load_library<'lua>(
+        lua: &'lua Lua,
+        code: &[u8],
+    ) -> Result<Vec<(String, Table<'lua>)>, String> {
+        let (_, body) = Self::parse_library_metadata(code)?;
+        let internal_error = |e: mlua::Error| format!("ERR {}", Self::error_message(&e));
+        lua.set_named_registry_value(
+            REGISTERED_FUNCTIONS,
+            lua.create_table().map_err(internal_error)?,
+        )
+        .map_err(internal_error)?;
+
+        let library = lua
+            .load(body)
+            .set_name("@user_function")
+            .into_function()
+            .map_err(|e| format!("ERR Error compiling function: {}", Self::error_message(&e)))?;
+        library.call::<_, ()>(()).map_err(|e| {
+            let msg = Self::error_message(&e);
+            if msg.starts_with("ERR ") {
+                msg
+            } else {
+                format!("ERR Error registering functions: {}", msg)
+            }
+        })?;
+
+        let functions: Table = lua
+            .named_registry_value(REGISTERED_FUNCTIONS)
+            .map_err(internal_error)?;
+        let mut registered = functions
+            .pairs::<String, Table>()
+            .collect::<mlua::Result<Vec<(String, Table)>>>()
+            .map_err(internal_error)?;
+        if registered.is_empty() {
+            return Err("ERR No functions registered".to_string());
+        }
+        registered.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(registered)
+    }
+
+    /// Parse the first line of a library: `#!<engine> name=<library name>`. Return the library
+    /// name and the library body. The body keeps the first line (empty) so the line numbers
+    /// reported by Lua match the user code
+    fn parse_library_metadata(code: &[u8]) -> Result<(BytesMut, &[u8]), String> {
+        let Some(code) = code.strip_prefix(b"#!") else {
+            return Err("ERR Missing library metadata".to_string());
+        };
+
+        let (first_line, body) = match code.iter().position(|c| *c == b'\n') {
+            Some(pos) => (&code[..pos], &code[pos..]),
+            None => (code, &code[code.len()..]),
+        };
+
+        let first_line = String::from_utf8_lossy(first_line);
+        let mut parts = first_line.split_whitespace();
+        let engine = parts.next().unwrap_or_default();
+        if !engine.eq_ignore_ascii_case("lua") {
+            return Err(format!("ERR Engine '{}' not found", engine));
+        }
+
+        let mut name: Option<&str> = None;
+        for part in parts {
+            match part.strip_prefix("name=") {
+                Some(value) => name = Some(value),
+                None => return Err(format!("ERR Invalid metadata value given: {}", part)),
+            }
+        }
+
+        let Some(name) = name else {
+            return Err("ERR Library name was not given".to_string());
+        };
+
+        if !Self::is_valid_name(name.as_bytes()) {
+            return Err(ERR_INVALID_LIBRARY_NAME.to_string());
+        }
+        Ok((BytesMut::from(name), body))
+    }
+
+    /// `redis.register_function` implementation. Accepts either `(name, callback)` or a table
+    /// with the fields `function_name`, `callback`, `flags` and `description`
+    fn register_function<'lua>(lua: &'lua Lua, args: Variadic<Value<'lua>>) -> mlua::Result<()> {
+        let (name, callback, flags) = match args.as_slice() {
+            [Value::String(name), Value::Function(callback)] => {
+                (name.clone(), callback.clone(), None)
+            }
+            [Value::Table(options)] => {
+                for pair in options.clone().pairs::<String, Value>() {
+                    let (key, _) = pair?;
+                    if !matches!(
+                        key.as_str(),
+                        "function_name" | "callback" | "flags" | "description"
+                    ) {
+                        return Err(mlua::Error::RuntimeError(format!(
+                            "ERR unknown argument given to redis.register_function: {}",
+                            key
+                        )));
+                    }
+                }
+                let Some(name) = options.get::<_, Option<mlua::String>>("function_name")? else {
+                    return Err(mlua::Error::RuntimeError(
+                        "ERR redis.register_function must get a function name argument".into(),
+                    ));
+                };
+                let Some(callback) = options.get::<_, Option<Function>>("callback")? else {
+                    return Err(mlua::Error::RuntimeError(
+                        "ERR redis.register_function must get a callback argument".into(),
+                    ));
+                };
+                (name, callback, options.get::<_, Option<Table>>("flags")?)
+            }
+            _ => {
+                return Err(mlua::Error::RuntimeError(
+                    "ERR wrong number of arguments to redis.register_function".into(),
+                ))
+            }
+        };
+
+        if !Self::is_valid_name(name.as_bytes()) {
+            return Err(mlua::Error::RuntimeError(
+                ERR_INVALID_FUNCTION_NAME.to_string(),
+            ));
+        }
+
+        let validated_flags = lua.create_table()?;
+        if let Some(flags) = flags {
+            for (idx, flag) in flags.sequence_values::<mlua::String>().enumerate() {
+                let flag = flag?;
+                if !FUNCTION_FLAGS
+                    .iter()
+                    .any(|f| f.as_bytes() == flag.as_bytes())
+                {
+                    return Err(mlua::Error::RuntimeError("ERR unknown flag given".into()));
+                }
+                validated_flags.raw_set(idx + 1, flag)?;
+            }
+        }
+
+        let functions: Table = lua.named_registry_value(REGISTERED_FUNCTIONS)?;
+        if functions.contains_key(name.clone())? {
+            return Err(mlua::Error::RuntimeError(
+                "ERR Function already exists in the library".into(),
+            ));
+        }
+
+        let entry = lua.create_table()?;
+        entry.set("callback", callback)?;
+        entry.set("flags", validated_flags)?;
+        functions.set(name, entry)
+    }
+
+    /// Library and function names may contain only letters, numbers and underscores
+    fn is_valid_name(name: &[u8]) -> bool {
+        !name.is_empty() && name.iter().all(|c| c.is_ascii_alphanumeric() || *c == b'_')
+    }
+
+    /// Extract the message of a Lua error, without the callback traceback
+    fn error_message(e: &mlua::Error) -> String {
+        match e {
+            mlua::Error::CallbackError { cause, .. } => Self::error_message(cause),
+            mlua::Error::RuntimeError(msg) => Self::single_line(msg),
+            mlua::Error::SyntaxError { message, .. } => Self::single_line(message),
+            other => Self::single_line(&other.to_string()),
+        }
+    }
+
+    /// `redis.pcall` implementation: run the command and return its reply. Errors are returned
+    /// as a table with a single `err` field
+    async fn pcall<'lua>(
+        lua: &'lua Lua,
+        client_state: Rc<ClientState>,
+        script: &RunningScript,
+        args: Variadic<Value<'lua>>,
+        read_only: bool,
+    ) -> mlua::Result<Value<'lua>> {
+        let mut cmd_args = Vec::<BytesMut>::with_capacity(args.len());
+        for arg in args.iter() {
+            match arg {
+                Value::String(s) => cmd_args.push(BytesMut::from(s.as_bytes())),
+                Value::Integer(num) => cmd_args.push(BytesMut::from(num.to_string().as_str())),
+                Value::Number(num) => cmd_args.push(BytesMut::from(num.to_string().as_str())),
+                _ => return Self::error_table(lua, Strings::ERR_SCRIPT_ARGS),
+            }
+        }
+
+        let Ok(command) = ValkeyCommand::new(cmd_args) else {
+            return Self::error_table(lua, Strings::ERR_SCRIPT_NO_ARGS);
+        };
+
+        if !Self::allowed_in_script(command.metadata().name()) {
+            return Self::error_table(lua, Strings::ERR_NOT_ALLOWED_FROM_SCRIPT);
+        }
+
+        let is_write = command.metadata().is_write_command();
+        if read_only && is_write {
+            let is_replica = client_state
+                .server_inner_state()
+                .persistent_state()
+                .is_replica();
+            return Self::error_table(
+                lua,
+                if is_replica {
+                    Strings::WRITE_CMD_AGAINST_REPLICA
+                } else {
+                    Strings::ERR_WRITE_FROM_READONLY_SCRIPT
+                },
+            );
+        }
+
+        if let Err(msg) = Client::check_permissions(&client_state, &command) {
+            return Self::error_table(lua, &msg);
+        }
+
+        // Once the script writes, `SCRIPT KILL` can no longer stop it
+        if is_write && !script.start_write() {
+            return Self::error_table(lua, Strings::ERR_SCRIPT_KILLED);
+        }
+
+        match Self::run_command(client_state, Rc::new(command)).await {
+            Ok(reply) => Self::to_lua_value(lua, &reply),
+            Err(e) => Self::error_table(lua, &format!("ERR {}", e)),
+        }
+    }
+
+    /// Run `command` and return its parsed reply
+    async fn run_command(
+        client_state: Rc<ClientState>,
+        command: Rc<ValkeyCommand>,
+    ) -> Result<ValkeyObject, SableError> {
+        let mut sink = Vec::<u8>::new();
+        let output =
+            match Client::handle_non_exec_command(client_state.clone(), command.clone(), &mut sink)
+                .await?
+            {
+                ClientNextAction::NoAction => BytesMut::from(sink.as_slice()),
+                ClientNextAction::SendResponse(buffer) => buffer,
+                ClientNextAction::Wait((_, _, timeout_response, _)) => {
+                    // A script can not block: reply as if the command timed out
+                    Client::handle_timeout(client_state, command, timeout_response)?
+                }
+                ClientNextAction::TerminateConnection => return Err(SableError::ConnectionClosed),
+            };
+
+        match RespResponseParserV2::parse_response(&output)? {
+            ResponseParseResult::Ok((_, reply)) => Ok(reply),
+            ResponseParseResult::NeedMoreData => Err(SableError::ParseError(format!(
+                "incomplete reply for command '{}'",
+                command.main_command()
+            ))),
+        }
+    }
+
+    /// Commands that can not be called from within a script
+    fn allowed_in_script(kind: &ValkeyCommandName) -> bool {
+        !matches!(
+            kind,
+            ValkeyCommandName::Eval
+                | ValkeyCommandName::Evalsha
+                | ValkeyCommandName::EvalRo
+                | ValkeyCommandName::EvalshaRo
+                | ValkeyCommandName::Script
+                | ValkeyCommandName::Function
+                | ValkeyCommandName::Fcall
+                | ValkeyCommandName::FcallRo
+                | ValkeyCommandName::Multi
+                | ValkeyCommandName::Exec
+                | ValkeyCommandName::Discard
+                | ValkeyCommandName::Watch
+                | ValkeyCommandName::Unwatch
+                | ValkeyCommandName::Subscribe
+                | ValkeyCommandName::Psubscribe
+                | ValkeyCommandName::Unsubscribe
+                | ValkeyCommandName::Punsubscribe
+                | ValkeyCommandName::Ssubscribe
+                | ValkeyCommandName::Sunsubscribe
+                | ValkeyCommandName::Auth
+        )
+    }
+
+    /// Convert a command reply into a Lua value
+    fn to_lua_value<'lua>(lua: &'lua Lua, reply: &ValkeyObject) -> mlua::Result<Value<'lua>> {
+        let value = match reply {
+            ValkeyObject::Str(s) => Value::String(lua.create_string(s)?),
+            ValkeyObject::Integer(num) => Value::Integer(*num),
+            ValkeyObject::NullString | ValkeyObject::NullArray => Value::Boolean(false),
+            ValkeyObject::Status(s) => {
+                let table = lua.create_table()?;
+                table.set("ok", lua.create_string(s)?)?;
+                Value::Table(table)
+            }
+            ValkeyObject::Error(s) => {
+                let table = lua.create_table()?;
+                table.set("err", lua.create_string(s)?)?;
+                Value::Table(table)
+            }
+            ValkeyObject::Array(items) => {
+                let table = lua.create_table_with_capacity(items.len(), 0)?;
+                for (idx, item) in items.iter().enumerate() {
+                    table.raw_set(idx + 1, Self::to_lua_value(lua, item)?)?;
+                }
+                Value::Table(table)
+            }
+        };
+        Ok(value)
+    }
+
+    /// Convert the Lua value returned by the script into RESP and append it to `buffer`
+    fn add_reply(
+        builder: &RespBuilderV2,
+        buffer: &mut BytesMut,
+        value: &Value,
+    ) -> mlua::Result<()> {
+        match value {
+            Value::Boolean(true) => builder.add_number::<i64>(buffer, 1, false),
+            Value::Integer(num) => builder.add_number(buffer, *num, false),
+            // Lua numbers are truncated into integers
+            Value::Number(num) => builder.add_number(buffer, *num as i64, false),
+            Value::String(s) => builder.add_bulk_string(buffer, s.as_bytes()),
+            Value::Table(table) => {
+                if let Some(err) = table.raw_get::<_, Option<mlua::String>>("err")? {
+                    let err = Self::single_line(&err.to_string_lossy());
+                    builder.add_resp_string(buffer, format!("-{}\r\n", err).as_bytes());
+                } else if let Some(status) = table.raw_get::<_, Option<mlua::String>>("ok")? {
+                    let status = Self::single_line(&status.to_string_lossy());
+                    builder.add_resp_string(buffer, format!("+{}\r\n", status).as_bytes());
+                } else {
+                    // Array: stop at the first `nil`
+                    let items = table
+                        .clone()
+                        .sequence_values::<Value>()
+                        .collect::<mlua::Result<Vec<Value>>>()?;
+                    builder.add_array_len(buffer, items.len());
+                    for item in &items {
+                        Self::add_reply(builder, buffer, item)?;
+                    }
+                }
+            }
+            // `nil`, `false` and anything that has no RESP representation
+            _ => builder.add_null_string(buffer),
+        }
+        Ok(())
+    }
+
+    fn error_table<'lua>(lua: &'lua Lua, msg: &str) -> mlua::Result<Value<'lua>> {
+        let table = lua.create_table()?;
+        table.set("err", msg)?;
+        Ok(Value::Table(table))
+    }
+
+    fn strings_table<'lua>(lua: &'lua Lua, items: &[BytesMut]) -> mlua::Result<Table<'lua>> {
+        let table = lua.create_table_with_capacity(items.len(), 0)?;
+        for (idx, item) in items.iter().enumerate() {
+            table.raw_set(idx + 1, lua.create_string(item)?)?;
+        }
+        Ok(table)
+    }
+
+    /// Error and status replies can not span multiple lines
+    fn single_line(msg: &str) -> String {
+        msg.replace(['\r', '\n'], " ")
+    }
+}
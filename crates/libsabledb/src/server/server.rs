@@ -1,6 +1,7 @@
 use crate::server::{
-    BroadcastMessageType, Client, ClientState, SableError, ServerOptions, SlotBitmap, Telemetry,
-    WorkerContext, WorkerManager, WorkerMessage, WorkerSender,
+    Acl, BroadcastMessageType, Client, ClientState, PubSubRegistry, RunningScripts, SableError,
+    ScriptCache, ServerOptions, SlotBitmap, Telemetry, WorkerContext, WorkerManager, WorkerMessage,
+    WorkerSender,
 };
 use crate::{
     commands::ClientNextAction,
@@ -22,6 +23,10 @@ lazy_static::lazy_static! {
     static ref SERVER_STATE: StdRwLock<Arc<ServerState>> = StdRwLock::new(Arc::new(ServerState::default()));
 }
 
+/// Clients blocked by `WAIT` / `WAITAOF` are waiting on this key. They are woken up whenever a
+/// replica acknowledges a new sequence number
+pub const REPLICA_ACK_KEY: &str = "__sabledb_replica_ack__";
+
 #[derive(Default)]
 struct BlockedClients {
     /// Maps between client id and the keys it is blocking on
@@ -103,6 +108,14 @@ pub struct ServerState {
     /// This state is persisted to the disk
     persistent_state: ServerPersistentState,
     locks: LockDb,
+    /// Pub/Sub subscriptions, shared by all the workers
+    pubsub: PubSubRegistry,
+    /// Lua scripts loaded by `SCRIPT LOAD` / `EVAL`, shared by all the workers
+    scripts: ScriptCache,
+    /// The Lua scripts being executed, shared by all the workers
+    running_scripts: RunningScripts,
+    /// ACL users, shared by all the workers
+    acl: Acl,
 }
 
 pub struct Server {
@@ -126,6 +139,10 @@ impl ServerState {
             worker_tx_channels: DashMap::<std::thread::ThreadId, WorkerSender>::new(),
             persistent_state: ServerPersistentState::new(),
             locks: LockDb::default(),
+            pubsub: PubSubRegistry::default(),
+            scripts: ScriptCache::default(),
+            running_scripts: RunningScripts::default(),
+            acl: Acl::default(),
         }
     }
 
@@ -371,6 +388,26 @@ impl ServerState {
         self.persistent_state.slots()
     }
 
+    /// Return the pub/sub subscriptions registry
+    pub fn pubsub(&self) -> &PubSubRegistry {
+        &self.pubsub
+    }
+
+    /// Return the Lua scripts cache
+    pub fn scripts(&self) -> &ScriptCache {
+        &self.scripts
+    }
+
+    /// Return the Lua scripts being executed
+    pub fn running_scripts(&self) -> &RunningScripts {
+        &self.running_scripts
+    }
+
+    /// Return the ACL users
+    pub fn acl(&self) -> &Acl {
+        &self.acl
+    }
+
     /// Clear all locks owned by `client_id`. If there are pending clients for these locks
     /// they will be waken up
     pub fn clear_locks(&self, keys: &[&BytesMut], client_id: u128) -> Result<(), SableError> {
@@ -30,6 +30,9 @@ pub struct GeneralSettings {
     /// Log directory. If set to `None`, logs are written into `stdout`
     /// SableDB uses an hourly rotating logs
     pub logdir: Option<PathBuf>,
+    /// A Lua script running for longer than this is considered busy: the other clients are
+    /// answered with `BUSY` and the script can be stopped with `SCRIPT KILL`
+    pub lua_time_limit_ms: usize,
 }
 
 impl Default for GeneralSettings {
@@ -44,6 +47,7 @@ impl Default for GeneralSettings {
             private_address: "127.0.0.1:7379".to_string(),
             logdir: None,
             cluster_address: None,
+            lua_time_limit_ms: 5000,
         }
     }
 }
@@ -59,6 +63,15 @@ pub struct ReplicationLimits {
     /// However, when there are no changes to send to the replica, the replication task
     /// suspend itself for `check_for_updates_interval_ms` milliseconds.
     pub check_for_updates_interval_ms: usize,
+    /// When enabled, the replica subscribes to the primary's changes and the primary pushes them
+    /// as soon as they are committed, instead of the replica polling for them
+    pub streaming: bool,
+    /// Streaming replication: the primary stops pushing changes once `max_unacked_updates`
+    /// messages are waiting to be acknowledged by the replica
+    pub max_unacked_updates: usize,
+    /// Size, in bytes, of the on-disk replication backlog. Replicas that fell behind the WAL
+    /// retention catch up from the backlog instead of doing a fullsync. 0 disables the backlog
+    pub backlog_size: usize,
 }
 
 impl Default for ReplicationLimits {
@@ -67,6 +80,9 @@ impl Default for ReplicationLimits {
             single_update_buffer_size: 50 << 20, // 50mb
             num_updates_per_message: 10_000,
             check_for_updates_interval_ms: 50,
+            streaming: true,
+            max_unacked_updates: 4,
+            backlog_size: 0,
         }
     }
 }
@@ -97,11 +113,20 @@ pub struct CronSettings {
     pub instant_delete: bool,
     /// Scan the database and collect statistics every `scan_keys_secs` seconds
     pub scan_keys_secs: usize,
+    /// Number of largest keys (per type) and most accessed keys reported by the keyspace analysis
+    pub keyspace_analysis_top_keys: usize,
     /// In a cluster configuration or as part of a replication group, this node will update its status in the cluster
     /// database every N milliseconds.
     pub cluster_database_updates_interval_ms: usize,
     /// The cron job is set to activate every N milliseconds to complete its tasks.
     pub cron_interval_ms: usize,
+    /// Actively delete keys with an expired TTL, instead of waiting for them to be accessed
+    pub active_expire_enabled: bool,
+    /// Maximum number of expiration index entries processed on every cron activation
+    pub active_expire_keys_per_cycle: usize,
+    /// The key access times (see `OBJECT IDLETIME`) are kept in memory and persisted every N
+    /// seconds
+    pub persist_access_times_secs: usize,
 }
 
 impl Default for CronSettings {
@@ -110,9 +135,13 @@ impl Default for CronSettings {
             evict_orphan_records_secs: 60, // 1 minute
             instant_delete: true,
             scan_keys_secs: 30,
+            keyspace_analysis_top_keys: 10,
             cluster_database_updates_interval_ms: 500,
             cron_interval_ms: 100,
             compaction_after_eviction: true,
+            active_expire_enabled: true,
+            active_expire_keys_per_cycle: 1000,
+            persist_access_times_secs: 10,
         }
     }
 }
@@ -523,6 +552,13 @@ impl ServerOptions {
             &mut options.general_settings.key,
         )?;
 
+        Self::read_usize_with_unit(
+            &ini_file,
+            "general",
+            "lua_time_limit_ms",
+            &mut options.general_settings.lua_time_limit_ms,
+        )?;
+
         // [replication_limits]
         Self::read_usize_with_unit(
             &ini_file,
@@ -545,6 +581,27 @@ impl ServerOptions {
             &mut options.replication_limits.check_for_updates_interval_ms,
         )?;
 
+        Self::read_bool(
+            &ini_file,
+            "replication_limits",
+            "streaming",
+            &mut options.replication_limits.streaming,
+        )?;
+
+        Self::read_usize_with_unit(
+            &ini_file,
+            "replication_limits",
+            "max_unacked_updates",
+            &mut options.replication_limits.max_unacked_updates,
+        )?;
+
+        Self::read_usize_with_unit(
+            &ini_file,
+            "replication_limits",
+            "backlog_size",
+            &mut options.replication_limits.backlog_size,
+        )?;
+
         // [client_limits]
         Self::read_usize_with_unit(
             &ini_file,
@@ -582,6 +639,13 @@ impl ServerOptions {
             &mut options.cron.scan_keys_secs,
         )?;
 
+        Self::read_usize(
+            &ini_file,
+            "cron",
+            "keyspace_analysis_top_keys",
+            &mut options.cron.keyspace_analysis_top_keys,
+        )?;
+
         Self::read_usize_with_unit(
             &ini_file,
             "cron",
@@ -595,6 +659,27 @@ impl ServerOptions {
             "cron_interval_ms",
             &mut options.cron.cron_interval_ms,
         )?;
+
+        Self::read_bool(
+            &ini_file,
+            "cron",
+            "active_expire_enabled",
+            &mut options.cron.active_expire_enabled,
+        )?;
+
+        Self::read_usize_with_unit(
+            &ini_file,
+            "cron",
+            "active_expire_keys_per_cycle",
+            &mut options.cron.active_expire_keys_per_cycle,
+        )?;
+
+        Self::read_usize_with_unit(
+            &ini_file,
+            "cron",
+            "persist_access_times_secs",
+            &mut options.cron.persist_access_times_secs,
+        )?;
         Ok(options)
     }
 
@@ -2,15 +2,18 @@ use crate::utils::SLOT_SIZE;
 #[allow(unused_imports)]
 use crate::{
     io::TempFile,
+    metadata::{HashFieldKey, HashValueMetadata},
     replication::StorageUpdatesRecord,
     storage::BatchUpdate,
     storage::GenericDb,
     utils::{U8ArrayBuilder, U8ArrayReader},
-    ClientState, KeyType, SableError, Server, StorageAdapter, ToU8Writer,
+    ClientState, CommonValueMetadata, KeyType, SableError, Server, StorageAdapter, ToU8Writer,
+    TtlIndexKey,
 };
 
 use bytes::{Buf, BytesMut};
 use enum_iterator::next;
+use std::collections::HashMap;
 use std::fs::File as StdFile;
 use std::io::Read;
 use std::ops::Range;
@@ -455,9 +458,16 @@ impl<'a> SlotFileImporter<'a> {
         let file_iter = SlotFileIterator::new(&self.filepath)?;
         let mut del_ops = 0usize;
         let mut put_ops = 0usize;
+        let mut volatile_hashes = HashMap::<u64, HashValueMetadata>::new();
         for record in file_iter {
             match record {
                 StorageUpdatesRecord::Put { key, value } => {
+                    // The expiration index is global, so it is not part of the slot file
+                    if let Some(index_key) =
+                        Self::ttl_index_entry(&key, &value, &mut volatile_hashes)
+                    {
+                        batch_update.put(index_key.to_bytes(), BytesMut::new());
+                    }
                     batch_update.put(key, value);
                     put_ops = put_ops.saturating_add(1);
                 }
@@ -466,7 +476,7 @@ impl<'a> SlotFileImporter<'a> {
                     del_ops = del_ops.saturating_add(1);
                 }
             }
-            if batch_update.len().is_multiple_of(MAX_BATCH_SIZE) {
+            if batch_update.len() >= MAX_BATCH_SIZE {
                 self.db.apply_batch(&batch_update)?;
                 batch_update.clear();
             }
@@ -485,6 +495,43 @@ impl<'a> SlotFileImporter<'a> {
         );
         Ok(())
     }
+
+    /// Return the expiration index entry of an imported record, if the record has an expiration
+    /// time. Hash fields can only be decoded using their hash metadata: the metadata of the hashes
+    /// that have fields with an expiration time is kept in `volatile_hashes`. The exporter writes
+    /// the primary keys before the hash fields
+    fn ttl_index_entry(
+        key: &[u8],
+        value: &[u8],
+        volatile_hashes: &mut HashMap<u64, HashValueMetadata>,
+    ) -> Option<TtlIndexKey> {
+        match key.first() {
+            Some(key_type) if *key_type == KeyType::PrimaryKey as u8 => {
+                let mut reader = U8ArrayReader::with_buffer(value);
+                let is_hash = CommonValueMetadata::from_bytes(&mut reader)
+                    .is_ok_and(|common_md| common_md.is_hash());
+                if is_hash {
+                    reader.rewind();
+                    if let Ok(hash_md) = HashValueMetadata::from_bytes(&mut reader) {
+                        if hash_md.volatile_len() > 0 {
+                            volatile_hashes.insert(hash_md.id(), hash_md);
+                        }
+                    }
+                }
+                TtlIndexKey::for_record(key, value)
+            }
+            Some(key_type) if *key_type == KeyType::HashItem as u8 => {
+                let field_key = HashFieldKey::from_bytes(key).ok()?;
+                let hash_md = volatile_hashes.get(&field_key.hash_id())?;
+                let expire_timestamp_ms = hash_md
+                    .decode_field_value(value)
+                    .ok()?
+                    .expire_timestamp_ms()?;
+                Some(TtlIndexKey::new(expire_timestamp_ms, key))
+            }
+            _ => None,
+        }
+    }
 }
 
 // Slot operations
@@ -580,6 +627,17 @@ impl Slot {
             }
             prefix_arr.push(Self::create_prefix_for_key_type(key_type, db_id, self.slot));
         }
+
+        // Data types added after the internal records
+        for key_type in [
+            KeyType::StreamItem,
+            KeyType::StreamGroup,
+            KeyType::StreamPending,
+            KeyType::StreamConsumer,
+            KeyType::BitmapChunk,
+        ] {
+            prefix_arr.push(Self::create_prefix_for_key_type(key_type, db_id, self.slot));
+        }
         Ok(prefix_arr)
     }
 
@@ -612,6 +670,11 @@ mod tests {
             Slot::create_prefix_for_key_type(KeyType::ZsetMemberItem, 0, 10),
             Slot::create_prefix_for_key_type(KeyType::ZsetScoreItem, 0, 10),
             Slot::create_prefix_for_key_type(KeyType::SetItem, 0, 10),
+            Slot::create_prefix_for_key_type(KeyType::StreamItem, 0, 10),
+            Slot::create_prefix_for_key_type(KeyType::StreamGroup, 0, 10),
+            Slot::create_prefix_for_key_type(KeyType::StreamPending, 0, 10),
+            Slot::create_prefix_for_key_type(KeyType::StreamConsumer, 0, 10),
+            Slot::create_prefix_for_key_type(KeyType::BitmapChunk, 0, 10),
         ];
 
         let slot = Slot::with_slot(10);
@@ -767,4 +830,56 @@ mod tests {
             let _ = std::fs::remove_file(&filepath);
         });
     }
+
+    #[test]
+    fn test_slot_import_indexes_hash_fields() {
+        use crate::{
+            storage::{HashDb, HashFieldExpiration},
+            utils,
+        };
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(async move {
+            let (_guard, store) = crate::tests::open_store();
+            let (_target_guard, target) = crate::tests::open_store();
+            let key = BytesMut::from("{1}hash");
+            let mut hash_db = HashDb::with_storage(&store, 0);
+            hash_db
+                .put_multi(&key, &[(&BytesMut::from("f1"), &BytesMut::from("v1"))])
+                .unwrap();
+            hash_db
+                .put_multi_with_expiration(
+                    &key,
+                    &[(&BytesMut::from("f2"), &BytesMut::from("v2"))],
+                    HashFieldExpiration::At(4_102_444_800_000),
+                )
+                .unwrap();
+
+            let slot_number = utils::calculate_slot(&key);
+            let mut slot_file = SlotFileExporter::new(&store, 0, slot_number, 1 << 20).unwrap();
+            let filepath = slot_file.export().await.unwrap().unwrap();
+            SlotFileImporter::new(&target, filepath.clone())
+                .import()
+                .unwrap();
+
+            // Only the field with an expiration time is indexed
+            let prefix = TtlIndexKey::prefix();
+            let mut entries = Vec::<TtlIndexKey>::new();
+            let mut db_iter = target.create_iterator(&prefix).unwrap();
+            while db_iter.valid() {
+                let Some(key) = db_iter.key() else {
+                    break;
+                };
+                if !key.starts_with(&prefix) {
+                    break;
+                }
+                entries.push(TtlIndexKey::from_bytes(key).unwrap());
+                db_iter.next();
+            }
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].expire_timestamp_ms(), 4_102_444_800_000);
+            let field_key = HashFieldKey::from_bytes(entries[0].record_key()).unwrap();
+            assert_eq!(field_key.user_key(), b"f2");
+            let _ = std::fs::remove_file(&filepath);
+        });
+    }
 }
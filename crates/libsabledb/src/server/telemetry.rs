@@ -1,38 +1,85 @@
 use crate::{commands::Strings, replication::ServerRole, storage::StorageMetadata, Server};
 
-use std::cell::RefCell;
+use bytes::BytesMut;
+use dashmap::DashMap;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
-use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+
+/// The keys of one in every `KEY_ACCESS_SAMPLE_RATE` commands are sampled for the keyspace analysis
+pub const KEY_ACCESS_SAMPLE_RATE: u64 = 100;
+
+/// Maximum number of distinct keys sampled between two keyspace scans
+const MAX_SAMPLED_KEYS: usize = 10_000;
+
+/// Maximum number of keys whose access time is kept in memory until the cron thread persists it
+const MAX_TRACKED_ACCESS_TIMES: usize = 100_000;
 
 thread_local! {
     pub static WORKER_TELEMETRY: RefCell<Telemetry> = RefCell::new(Telemetry::default());
+    static COMMANDS_SINCE_KEY_SAMPLE: Cell<u64> = Cell::new(0);
 }
 
 lazy_static::lazy_static! {
     /// Replication info goes into a separate data structure
     static ref REPLICATION_INFO: RwLock<ReplicationTelemetry> = RwLock::<ReplicationTelemetry>::default();
     static ref STORAGE_MD: RwLock<StorageMetadata> = RwLock::<StorageMetadata>::default();
+    /// Sampled key accesses (database ID + user key -> number of samples)
+    static ref KEY_ACCESS_SAMPLES: Mutex<HashMap<(u16, BytesMut), u64>> = Mutex::default();
+    /// Key access times not persisted yet (database ID + user key -> ms since UNIX_EPOCH)
+    static ref KEY_ACCESS_TIMES: DashMap<(u16, BytesMut), u64> = DashMap::default();
 }
 
+/// Number of keys deleted by the active expiration (updated by the cron thread)
+static EXPIRED_KEYS: AtomicU64 = AtomicU64::new(0);
+
 #[derive(Clone, Default, Debug)]
 pub struct ReplicationTelemetry {
     pub last_change_sequence_number: u64,
     pub replica_telemetry: ReplicaTelemetry,
     pub primary_telemetry: PrimaryTelemetry,
+    /// The replication backlog, `None` if disabled
+    pub backlog: Option<BacklogTelemetry>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct BacklogTelemetry {
+    /// The backlog size, in bytes
+    pub size: u64,
+    pub first_sequence_number: u64,
+    pub next_sequence_number: u64,
+    /// The time (milliseconds since UNIX_EPOCH) in which the oldest record was written
+    pub oldest_entry_timestamp_ms: u64,
+}
+
+impl std::fmt::Display for BacklogTelemetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "size:{},first_sequence_number:{},next_sequence_number:{}",
+            self.size, self.first_sequence_number, self.next_sequence_number
+        )
+    }
 }
 
 #[derive(Clone, Default, Debug)]
 pub struct ReplicaTelemetry {
     pub distance_from_primary: u64,
     pub last_change_sequence_number: u64,
+    /// The sequence number the replica confirmed it applied (its next `GetUpdatesSince` request).
+    /// `None` until the replica acknowledges for the first time
+    pub acked_sequence_number: Option<u64>,
 }
 
 impl std::fmt::Display for ReplicaTelemetry {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "distance_from_primary:{},last_change_sequence_number:{}",
-            self.distance_from_primary, self.last_change_sequence_number
+            "distance_from_primary:{},last_change_sequence_number:{},acked_sequence_number:{}",
+            self.distance_from_primary,
+            self.last_change_sequence_number,
+            self.acked_sequence_number.unwrap_or_default()
         )
     }
 }
@@ -60,6 +107,8 @@ impl ReplicationTelemetry {
         }
     }
 
+    /// Update the replica info. The acknowledged sequence number is kept, use `replica_acked`
+    /// to update it
     pub fn update_replica_info(replica_id: String, info: ReplicaTelemetry) {
         let mut replication_telemetry = REPLICATION_INFO.write().expect(Strings::POISONED_MUTEX);
         if let Some(replica_data) = replication_telemetry
@@ -67,7 +116,9 @@ impl ReplicationTelemetry {
             .replicas
             .get_mut(&replica_id)
         {
+            let acked_sequence_number = replica_data.acked_sequence_number;
             *replica_data = info.clone();
+            replica_data.acked_sequence_number = acked_sequence_number;
         } else {
             replication_telemetry
                 .primary_telemetry
@@ -84,6 +135,52 @@ impl ReplicationTelemetry {
             .remove(replica_id);
     }
 
+    /// Record that `replica_id` applied all the changes before `sequence_number`.
+    /// Return `true` if the replica's acknowledged sequence number moved forward
+    pub fn replica_acked(replica_id: &str, sequence_number: u64) -> bool {
+        let mut replication_telemetry = REPLICATION_INFO.write().expect(Strings::POISONED_MUTEX);
+        let Some(replica_data) = replication_telemetry
+            .primary_telemetry
+            .replicas
+            .get_mut(replica_id)
+        else {
+            return false;
+        };
+
+        if replica_data
+            .acked_sequence_number
+            .is_some_and(|acked| sequence_number <= acked)
+        {
+            return false;
+        }
+        replica_data.acked_sequence_number = Some(sequence_number);
+        true
+    }
+
+    /// Return the number of replicas that acknowledged `sequence_number`. Replicas that never
+    /// acknowledged anything are not counted
+    pub fn replicas_acked(sequence_number: u64) -> usize {
+        REPLICATION_INFO
+            .read()
+            .expect(Strings::POISONED_MUTEX)
+            .primary_telemetry
+            .replicas
+            .values()
+            .filter(|info| {
+                info.acked_sequence_number
+                    .is_some_and(|acked| acked >= sequence_number)
+            })
+            .count()
+    }
+
+    /// Update the replication backlog info
+    pub fn set_backlog_info(info: Option<BacklogTelemetry>) {
+        REPLICATION_INFO
+            .write()
+            .expect(Strings::POISONED_MUTEX)
+            .backlog = info;
+    }
+
     /// Return the number of replicas connected to this instance
     pub fn connected_replicas() -> usize {
         REPLICATION_INFO
@@ -132,23 +229,49 @@ impl std::fmt::Display for ReplicationTelemetry {
             self.last_change_sequence_number
         ));
 
-        match node_role {
-            ServerRole::Primary => {
-                lines.push(format!(
-                    "connected_replicas:{}",
-                    self.primary_telemetry.replicas.len()
-                ));
+        if let Some(backlog) = &self.backlog {
+            // The retention window: the range of changes (and their age) kept in the backlog
+            let window_seconds = if backlog.oldest_entry_timestamp_ms == 0 {
+                0
+            } else {
+                crate::utils::current_time(crate::utils::CurrentTimeResolution::Milliseconds)
+                    .saturating_sub(backlog.oldest_entry_timestamp_ms)
+                    / 1000
+            };
+            lines.push("backlog_active:1".to_string());
+            lines.push(format!("backlog_size:{}", backlog.size));
+            lines.push(format!(
+                "backlog_first_sequence_number:{}",
+                backlog.first_sequence_number
+            ));
+            lines.push(format!(
+                "backlog_next_sequence_number:{}",
+                backlog.next_sequence_number
+            ));
+            lines.push(format!("backlog_window_seconds:{}", window_seconds));
+        } else {
+            lines.push("backlog_active:0".to_string());
+        }
 
-                for (replica_id, info) in &self.primary_telemetry.replicas {
-                    lines.push(format!("replica:{},{}", replica_id, info));
-                }
-            }
-            ServerRole::Replica => {
-                lines.push(format!(
-                    "primary_node_id:{}",
-                    Server::state().persistent_state().primary_node_id()
-                ));
-            }
+        if node_role == ServerRole::Replica {
+            lines.push(format!(
+                "primary_node_id:{}",
+                Server::state().persistent_state().primary_node_id()
+            ));
+            lines.push(format!(
+                "upstream_node_id:{}",
+                Server::state().persistent_state().upstream_node_id()
+            ));
+        }
+
+        // Replicas can serve other replicas (cascading replication)
+        lines.push(format!(
+            "connected_replicas:{}",
+            self.primary_telemetry.replicas.len()
+        ));
+
+        for (replica_id, info) in &self.primary_telemetry.replicas {
+            lines.push(format!("replica:{},{}", replica_id, info));
         }
         let as_str = lines.join("\n");
         write!(f, "{}", as_str)
@@ -292,6 +415,148 @@ impl Telemetry {
         data.db_keys(db_id)
     }
 
+    /// Sample the keys accessed by a command. Only one in every `KEY_ACCESS_SAMPLE_RATE` calls
+    /// (per worker) is recorded
+    pub fn sample_key_access(db_id: u16, user_keys: &[&BytesMut]) {
+        if user_keys.is_empty() {
+            return;
+        }
+
+        let sample = COMMANDS_SINCE_KEY_SAMPLE.with(|counter| {
+            let count = counter.get().saturating_add(1);
+            counter.set(count % KEY_ACCESS_SAMPLE_RATE);
+            count == KEY_ACCESS_SAMPLE_RATE
+        });
+        if !sample {
+            return;
+        }
+
+        let mut samples = KEY_ACCESS_SAMPLES.lock().expect(Strings::POISONED_MUTEX);
+        for user_key in user_keys {
+            let sample_key = (db_id, (*user_key).clone());
+            if let Some(count) = samples.get_mut(&sample_key) {
+                *count = count.saturating_add(1);
+            } else if samples.len() < MAX_SAMPLED_KEYS {
+                samples.insert(sample_key, 1);
+            }
+        }
+    }
+
+    /// Record the access time of the keys accessed by a command. The access times are kept in
+    /// memory: the cron thread persists them periodically (see `pending_key_access_times`). When
+    /// too many keys are pending, accesses to new keys are dropped until the next persist
+    pub fn record_key_access(db_id: u16, user_keys: &[&BytesMut], now: u64) {
+        for user_key in user_keys {
+            let access_key = (db_id, (*user_key).clone());
+            if let Some(mut last_accessed) = KEY_ACCESS_TIMES.get_mut(&access_key) {
+                *last_accessed = now.max(*last_accessed);
+            } else if KEY_ACCESS_TIMES.len() < MAX_TRACKED_ACCESS_TIMES {
+                KEY_ACCESS_TIMES.insert(access_key, now);
+            }
+        }
+    }
+
+    /// Return the access time of `user_key` recorded since the last persist, if any
+    pub fn key_last_accessed(db_id: u16, user_key: &BytesMut) -> Option<u64> {
+        KEY_ACCESS_TIMES
+            .get(&(db_id, user_key.clone()))
+            .map(|last_accessed| *last_accessed)
+    }
+
+    /// Drop the pending access time of `user_key`, e.g. after its access time was set explicitly
+    pub fn forget_key_access(db_id: u16, user_key: &BytesMut) {
+        KEY_ACCESS_TIMES.remove(&(db_id, user_key.clone()));
+    }
+
+    /// Return the access times that were not persisted yet
+    pub fn pending_key_access_times() -> Vec<(u16, BytesMut, u64)> {
+        KEY_ACCESS_TIMES
+            .iter()
+            .map(|entry| (entry.key().0, entry.key().1.clone(), *entry.value()))
+            .collect()
+    }
+
+    /// The access time of `user_key` was persisted. It is dropped from memory, unless the key was
+    /// accessed again since
+    pub fn key_access_persisted(db_id: u16, user_key: &BytesMut, last_accessed: u64) {
+        KEY_ACCESS_TIMES.remove_if(&(db_id, user_key.clone()), |_, pending| {
+            *pending == last_accessed
+        });
+    }
+
+    /// Return the key accesses sampled since the previous call, as estimated access counts
+    pub fn take_key_accesses() -> HashMap<(u16, BytesMut), u64> {
+        let mut samples = KEY_ACCESS_SAMPLES.lock().expect(Strings::POISONED_MUTEX);
+        let mut accesses = std::mem::take(&mut *samples);
+        for count in accesses.values_mut() {
+            *count = count.saturating_mul(KEY_ACCESS_SAMPLE_RATE);
+        }
+        accesses
+    }
+
+    /// Return the estimated access count of a key: the accesses counted by the last keyspace scan
+    /// (for the hottest keys) plus the accesses sampled since then
+    pub fn key_access_frequency(db_id: u16, user_key: &BytesMut) -> u64 {
+        let pending = KEY_ACCESS_SAMPLES
+            .lock()
+            .expect(Strings::POISONED_MUTEX)
+            .get(&(db_id, user_key.clone()))
+            .copied()
+            .unwrap_or_default()
+            .saturating_mul(KEY_ACCESS_SAMPLE_RATE);
+        let data = STORAGE_MD.read().expect("read lock error");
+        let scanned = data
+            .hot_keys()
+            .iter()
+            .find(|key| key.db_id == db_id && &key.user_key == user_key)
+            .map(|key| key.value)
+            .unwrap_or_default();
+        pending.saturating_add(scanned)
+    }
+
+    /// Build the `keyspace-analysis` section of the `INFO` command from the last keyspace scan
+    pub fn keyspace_analysis_info() -> String {
+        let data = STORAGE_MD.read().expect("read lock error");
+        let mut lines = Vec::<String>::new();
+        lines.push("# Keyspace-analysis".to_string());
+        lines.push(format!("keys:{}", data.total_key_count()));
+        lines.push(format!("key_access_sample_rate:{}", KEY_ACCESS_SAMPLE_RATE));
+
+        // the user key is placed last, as it may contain commas
+        for (type_name, top_keys) in data.big_keys() {
+            for (rank, key) in top_keys.keys().iter().enumerate() {
+                lines.push(format!(
+                    "bigkey_{}_{}:db={},size={},key={}",
+                    type_name,
+                    rank.saturating_add(1),
+                    key.db_id,
+                    key.value,
+                    key.user_key.escape_ascii()
+                ));
+            }
+        }
+        for (rank, key) in data.hot_keys().iter().enumerate() {
+            lines.push(format!(
+                "hotkey_{}:db={},freq={},key={}",
+                rank.saturating_add(1),
+                key.db_id,
+                key.value,
+                key.user_key.escape_ascii()
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// Increase the number of keys deleted because their TTL expired by `count`
+    pub fn inc_expired_keys(count: u64) {
+        EXPIRED_KEYS.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Return the number of keys deleted because their TTL expired
+    pub fn expired_keys() -> u64 {
+        EXPIRED_KEYS.load(Ordering::Relaxed)
+    }
+
     /// Clear the telemetry object
     pub fn clear() {
         WORKER_TELEMETRY.with(|telemetry| {
@@ -372,6 +637,7 @@ impl std::fmt::Display for Telemetry {
         lines.push("\n# Statistics".to_string());
         lines.push(format!("db_miss:{}", self.db_miss));
         lines.push(format!("db_hit:{}", self.db_hit));
+        lines.push(format!("expired_keys:{}", Self::expired_keys()));
 
         lines.push("\n# Keyspace".to_string());
         lines.push(format!(
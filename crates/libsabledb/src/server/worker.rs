@@ -11,6 +11,8 @@ use tracing::log::{log_enabled, Level};
 use tracing::{debug, error, info, trace};
 
 const OPTIONS_LOCK_ERR: &str = "Failed to obtain read lock on ServerOptions";
+/// The maximum number of threads a worker uses for blocking calls (e.g. `MIGRATE`)
+const MAX_BLOCKING_THREADS: usize = 16;
 
 #[derive(Debug)]
 pub enum WorkerMessage {
@@ -93,6 +95,7 @@ impl Worker {
             .spawn(move || {
                 let rt = tokio::runtime::Builder::new_current_thread()
                     .enable_all()
+                    .max_blocking_threads(MAX_BLOCKING_THREADS)
                     .thread_name("Worker")
                     .build()
                     .unwrap_or_else(|e| {
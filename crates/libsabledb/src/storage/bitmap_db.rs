@@ -0,0 +1,553 @@
+use crate::{
+    metadata::{BitmapChunkKey, BitmapValueMetadata, Bookkeeping, ValueType},
+    storage::DbWriteCache,
+    CommonValueMetadata, PrimaryKeyMetadata, SableError, StorageAdapter, StringValueMetadata,
+    U8ArrayBuilder, U8ArrayReader,
+};
+use bytes::{Buf, BytesMut};
+
+/// Bitmaps are stored in chunks of this size (in bytes)
+pub const BITMAP_CHUNK_SIZE: u64 = 4096;
+
+/// The maximum bitmap length, in bytes (same as the maximum length of a string)
+pub const BITMAP_MAX_LEN: u64 = 512 * 1024 * 1024;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Bitmap {
+    pub key: PrimaryKeyMetadata,
+    pub metadata: BitmapValueMetadata,
+}
+
+impl Bitmap {
+    pub fn slot(&self) -> u16 {
+        self.key.slot()
+    }
+
+    pub fn database_id(&self) -> u16 {
+        self.key.database_id()
+    }
+
+    pub fn id(&self) -> u64 {
+        self.metadata.id()
+    }
+
+    /// Return the bitmap length, in bytes
+    pub fn len(&self) -> u64 {
+        self.metadata.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_empty()
+    }
+
+    /// Return a prefix for iterating all the chunks owned by this bitmap
+    pub fn prefix(&self) -> BytesMut {
+        BitmapChunkKey::prefix(self.id(), self.database_id(), self.slot())
+    }
+
+    /// Encode the database key of the chunk `chunk_index`
+    pub fn chunk_key(&self, chunk_index: u64) -> BytesMut {
+        BitmapChunkKey::new(self.id(), self.database_id(), self.slot(), chunk_index).to_bytes()
+    }
+}
+
+/// `BitmapDb::len` result
+#[derive(PartialEq, Eq, Debug)]
+pub enum BitmapLenResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// No entry exist
+    NotFound,
+    /// The value length, in bytes
+    Some(u64),
+}
+
+/// `BitmapDb::get_bit` and `BitmapDb::set_bit` result
+#[derive(PartialEq, Eq, Debug)]
+pub enum BitmapBitResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// The bit value (for `set_bit`, this is the value before the update)
+    Some(u8),
+}
+
+/// `BitmapDb::read` result
+#[derive(PartialEq, Eq, Debug)]
+pub enum BitmapReadResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// The requested bytes
+    Some(BytesMut),
+}
+
+/// `BitmapDb::write` and `BitmapDb::scan` result
+#[derive(PartialEq, Eq, Debug)]
+pub enum BitmapResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    Ok,
+}
+
+/// The bitmap commands work on strings as well as on bitmaps
+enum FindBitmapValueResult {
+    WrongType,
+    NotFound,
+    String(BytesMut, StringValueMetadata),
+    Bitmap(Bitmap),
+}
+
+/// Bitmap DB wrapper. This class is specialized in reading/writing bitmaps
+/// (commands from the `SETBIT`, `BITCOUNT` etc family).
+///
+/// A bitmap can be created from a string value (e.g. `SET` followed by `SETBIT`). When this
+/// happens, the string is converted into a bitmap, so following updates only touch the chunk
+/// that holds the modified bits. Reading a bitmap with the string commands (e.g. `GET`)
+/// returns its content.
+///
+/// Locking strategy: this class does not lock anything and relies on the caller
+/// to obtain the locks if needed
+pub struct BitmapDb<'a> {
+    store: &'a StorageAdapter,
+    db_id: u16,
+    cache: Box<DbWriteCache<'a>>,
+}
+
+#[allow(dead_code)]
+impl<'a> BitmapDb<'a> {
+    pub fn with_storage(store: &'a StorageAdapter, db_id: u16) -> Self {
+        let cache = Box::new(DbWriteCache::with_storage(store));
+        BitmapDb {
+            store,
+            db_id,
+            cache,
+        }
+    }
+
+    /// Return the length of the value stored at `user_key`, in bytes
+    pub fn len(&self, user_key: &BytesMut) -> Result<BitmapLenResult, SableError> {
+        match self.find_value(user_key)? {
+            FindBitmapValueResult::WrongType => Ok(BitmapLenResult::WrongType),
+            FindBitmapValueResult::NotFound => Ok(BitmapLenResult::NotFound),
+            FindBitmapValueResult::String(value, _) => {
+                Ok(BitmapLenResult::Some(value.len() as u64))
+            }
+            FindBitmapValueResult::Bitmap(bitmap) => Ok(BitmapLenResult::Some(bitmap.len())),
+        }
+    }
+
+    /// Return the bit value at `offset`. Bits outside of the value are `0`
+    pub fn get_bit(&self, user_key: &BytesMut, offset: u64) -> Result<BitmapBitResult, SableError> {
+        let byte = match self.read(user_key, offset / 8, offset / 8 + 1)? {
+            BitmapReadResult::WrongType => return Ok(BitmapBitResult::WrongType),
+            BitmapReadResult::Some(bytes) => bytes.first().copied().unwrap_or_default(),
+        };
+        Ok(BitmapBitResult::Some(Self::bit_of(byte, offset)))
+    }
+
+    /// Set or clear the bit at `offset`. The value is grown as needed. Return the previous
+    /// value of the bit
+    pub fn set_bit(
+        &mut self,
+        user_key: &BytesMut,
+        offset: u64,
+        value: bool,
+    ) -> Result<BitmapBitResult, SableError> {
+        let Some(mut bitmap) = self.load_for_write(user_key)? else {
+            return Ok(BitmapBitResult::WrongType);
+        };
+
+        let byte_offset = offset / 8;
+        let byte = self
+            .read_bitmap(&bitmap, byte_offset, byte_offset + 1)?
+            .first()
+            .copied()
+            .unwrap_or_default();
+
+        let mask = 0x80u8 >> (offset % 8);
+        let new_byte = if value { byte | mask } else { byte & !mask };
+        self.write_bitmap(&mut bitmap, byte_offset, &[new_byte])?;
+        self.put_bitmap_metadata(user_key, &bitmap.metadata)?;
+        Ok(BitmapBitResult::Some(Self::bit_of(byte, offset)))
+    }
+
+    /// Return the bytes in the range `[start, end)`. The range is clipped to the value length
+    pub fn read(
+        &self,
+        user_key: &BytesMut,
+        start: u64,
+        end: u64,
+    ) -> Result<BitmapReadResult, SableError> {
+        let mut bytes = BytesMut::new();
+        match self.scan(user_key, start, end, |_, chunk| {
+            bytes.extend_from_slice(chunk);
+            true
+        })? {
+            BitmapResult::WrongType => Ok(BitmapReadResult::WrongType),
+            BitmapResult::Ok => Ok(BitmapReadResult::Some(bytes)),
+        }
+    }
+
+    /// Visit the bytes in the range `[start, end)` one chunk at a time. The range is clipped to
+    /// the value length. `callback` is called with the offset of the first byte of the chunk and
+    /// the chunk content. Return `false` from the callback to stop the scan
+    pub fn scan<F>(
+        &self,
+        user_key: &BytesMut,
+        start: u64,
+        end: u64,
+        mut callback: F,
+    ) -> Result<BitmapResult, SableError>
+    where
+        F: FnMut(u64, &[u8]) -> bool,
+    {
+        match self.find_value(user_key)? {
+            FindBitmapValueResult::WrongType => return Ok(BitmapResult::WrongType),
+            FindBitmapValueResult::NotFound => {}
+            FindBitmapValueResult::String(value, _) => {
+                let end = end.min(value.len() as u64);
+                if start < end {
+                    callback(start, &value[start as usize..end as usize]);
+                }
+            }
+            FindBitmapValueResult::Bitmap(bitmap) => {
+                let end = end.min(bitmap.len());
+                let mut offset = start;
+                while offset < end {
+                    let chunk_index = offset / BITMAP_CHUNK_SIZE;
+                    let chunk_start = chunk_index * BITMAP_CHUNK_SIZE;
+                    let chunk_end = (chunk_start + BITMAP_CHUNK_SIZE).min(end);
+                    let chunk = self.read_chunk(&bitmap, chunk_index, chunk_end - chunk_start)?;
+                    if !callback(offset, &chunk[(offset - chunk_start) as usize..]) {
+                        break;
+                    }
+                    offset = chunk_end;
+                }
+            }
+        }
+        Ok(BitmapResult::Ok)
+    }
+
+    /// Write `bytes` starting at `offset`. The value is grown as needed
+    pub fn write(
+        &mut self,
+        user_key: &BytesMut,
+        offset: u64,
+        bytes: &[u8],
+    ) -> Result<BitmapResult, SableError> {
+        let Some(mut bitmap) = self.load_for_write(user_key)? else {
+            return Ok(BitmapResult::WrongType);
+        };
+        self.write_bitmap(&mut bitmap, offset, bytes)?;
+        self.put_bitmap_metadata(user_key, &bitmap.metadata)?;
+        Ok(BitmapResult::Ok)
+    }
+
+    /// Replace the value stored at `user_key` (of any type) with a bitmap holding `value`
+    pub fn put(&mut self, user_key: &BytesMut, value: &[u8]) -> Result<(), SableError> {
+        self.delete(user_key)?;
+        let mut bitmap = self.create_bitmap(user_key)?;
+        for (chunk_index, chunk) in value.chunks(BITMAP_CHUNK_SIZE as usize).enumerate() {
+            // Chunks that are all zeros are not stored
+            if chunk.iter().any(|byte| *byte != 0) {
+                self.cache
+                    .put(&bitmap.chunk_key(chunk_index as u64), BytesMut::from(chunk))?;
+            }
+        }
+        bitmap.metadata.extend_to(value.len() as u64);
+        self.put_bitmap_metadata(user_key, &bitmap.metadata)
+    }
+
+    /// Delete the value stored at `user_key` (of any type)
+    pub fn delete(&mut self, user_key: &BytesMut) -> Result<(), SableError> {
+        if let FindBitmapValueResult::Bitmap(bitmap) = self.find_value(user_key)? {
+            self.delete_chunks(&bitmap)?;
+            let bookkeeping_record = Bookkeeping::new(self.db_id, bitmap.slot())
+                .with_uid(bitmap.id())
+                .with_value_type(ValueType::Bitmap)
+                .to_bytes();
+            self.cache.delete(&bookkeeping_record)?;
+        }
+        let encoded_key = PrimaryKeyMetadata::new_primary_key(user_key, self.db_id);
+        self.cache.delete(&encoded_key)
+    }
+
+    /// Load the bitmap stored at `user_key`. Return `None` if the key exists but it is not
+    /// a bitmap
+    pub fn find_bitmap(&self, user_key: &BytesMut) -> Result<Option<Bitmap>, SableError> {
+        match self.find_value(user_key)? {
+            FindBitmapValueResult::Bitmap(bitmap) => Ok(Some(bitmap)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Apply cache changes to the disk
+    pub fn commit(&mut self) -> Result<(), SableError> {
+        self.cache.flush()
+    }
+
+    //=======================================================
+    // Internal API for this class
+    //=======================================================
+
+    fn bit_of(byte: u8, offset: u64) -> u8 {
+        (byte >> (7 - (offset % 8))) & 1
+    }
+
+    fn find_value(&self, user_key: &BytesMut) -> Result<FindBitmapValueResult, SableError> {
+        let encoded_key = PrimaryKeyMetadata::new_primary_key(user_key, self.db_id);
+        let Some(mut value) = self.cache.get(&encoded_key)? else {
+            return Ok(FindBitmapValueResult::NotFound);
+        };
+
+        let mut reader = U8ArrayReader::with_buffer(&value);
+        let common_md = CommonValueMetadata::from_bytes(&mut reader)?;
+        if common_md.expiration().is_expired()? {
+            return Ok(FindBitmapValueResult::NotFound);
+        }
+
+        reader.rewind();
+        if common_md.is_string() {
+            let md = StringValueMetadata::from_bytes(&mut reader)?;
+            let metadata_len = reader.consumed();
+            value.advance(metadata_len);
+            Ok(FindBitmapValueResult::String(value, md))
+        } else if common_md.is_bitmap() {
+            Ok(FindBitmapValueResult::Bitmap(Bitmap {
+                key: PrimaryKeyMetadata::new(user_key, self.db_id),
+                metadata: BitmapValueMetadata::from_bytes(&mut reader)?,
+            }))
+        } else {
+            Ok(FindBitmapValueResult::WrongType)
+        }
+    }
+
+    /// Load the bitmap for update. A new bitmap is created if the key does not exist and
+    /// a string value is converted into a bitmap. Return `None` if the key holds a value of
+    /// a different type
+    fn load_for_write(&mut self, user_key: &BytesMut) -> Result<Option<Bitmap>, SableError> {
+        match self.find_value(user_key)? {
+            FindBitmapValueResult::WrongType => Ok(None),
+            FindBitmapValueResult::NotFound => Ok(Some(self.create_bitmap(user_key)?)),
+            FindBitmapValueResult::String(value, md) => {
+                let mut bitmap = self.create_bitmap(user_key)?;
+                *bitmap.metadata.expiration_mut() = md.expiration().clone();
+                self.write_bitmap(&mut bitmap, 0, &value)?;
+                Ok(Some(bitmap))
+            }
+            FindBitmapValueResult::Bitmap(bitmap) => Ok(Some(bitmap)),
+        }
+    }
+
+    /// Create a new, empty, bitmap and its bookkeeping record
+    fn create_bitmap(&mut self, user_key: &BytesMut) -> Result<Bitmap, SableError> {
+        let bitmap = Bitmap {
+            key: PrimaryKeyMetadata::new(user_key, self.db_id),
+            metadata: BitmapValueMetadata::with_id(self.store.generate_id()),
+        };
+
+        let bookkeeping_record = Bookkeeping::new(self.db_id, bitmap.slot())
+            .with_uid(bitmap.id())
+            .with_value_type(ValueType::Bitmap)
+            .to_bytes();
+        self.cache.put(&bookkeeping_record, user_key.clone())?;
+        Ok(bitmap)
+    }
+
+    /// Put the bitmap metadata in the database
+    fn put_bitmap_metadata(
+        &mut self,
+        user_key: &BytesMut,
+        bitmap_md: &BitmapValueMetadata,
+    ) -> Result<(), SableError> {
+        let encoded_key = PrimaryKeyMetadata::new_primary_key(user_key, self.db_id);
+        let mut buffer = BytesMut::with_capacity(BitmapValueMetadata::SIZE);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        bitmap_md.to_bytes(&mut builder);
+        self.cache.put(&encoded_key, buffer)
+    }
+
+    /// Load the first `len` bytes of a chunk. Missing bytes are returned as zeros
+    fn read_chunk(
+        &self,
+        bitmap: &Bitmap,
+        chunk_index: u64,
+        len: u64,
+    ) -> Result<BytesMut, SableError> {
+        let mut chunk = self
+            .cache
+            .get(&bitmap.chunk_key(chunk_index))?
+            .unwrap_or_default();
+        chunk.resize(len as usize, 0);
+        Ok(chunk)
+    }
+
+    /// Read the bytes in the range `[start, end)` of `bitmap`, clipped to the bitmap length
+    fn read_bitmap(&self, bitmap: &Bitmap, start: u64, end: u64) -> Result<BytesMut, SableError> {
+        let end = end.min(bitmap.len());
+        let mut bytes = BytesMut::new();
+        let mut offset = start;
+        while offset < end {
+            let chunk_index = offset / BITMAP_CHUNK_SIZE;
+            let chunk_start = chunk_index * BITMAP_CHUNK_SIZE;
+            let chunk_end = (chunk_start + BITMAP_CHUNK_SIZE).min(end);
+            let chunk = self.read_chunk(bitmap, chunk_index, chunk_end - chunk_start)?;
+            bytes.extend_from_slice(&chunk[(offset - chunk_start) as usize..]);
+            offset = chunk_end;
+        }
+        Ok(bytes)
+    }
+
+    /// Write `bytes` into `bitmap` starting at `offset`, updating only the affected chunks
+    fn write_bitmap(
+        &mut self,
+        bitmap: &mut Bitmap,
+        offset: u64,
+        bytes: &[u8],
+    ) -> Result<(), SableError> {
+        let mut written = 0usize;
+        while written < bytes.len() {
+            let position = offset + written as u64;
+            let chunk_index = position / BITMAP_CHUNK_SIZE;
+            let chunk_offset = (position % BITMAP_CHUNK_SIZE) as usize;
+            let count = (BITMAP_CHUNK_SIZE as usize - chunk_offset).min(bytes.len() - written);
+
+            let chunk_key = bitmap.chunk_key(chunk_index);
+            let mut chunk = self.cache.get(&chunk_key)?.unwrap_or_default();
+            if chunk.len() < chunk_offset + count {
+                chunk.resize(chunk_offset + count, 0);
+            }
+            chunk[chunk_offset..chunk_offset + count]
+                .copy_from_slice(&bytes[written..written + count]);
+            self.cache.put(&chunk_key, chunk)?;
+            written += count;
+        }
+        bitmap.metadata.extend_to(offset + bytes.len() as u64);
+        Ok(())
+    }
+
+    /// Delete all the chunks owned by `bitmap`
+    fn delete_chunks(&mut self, bitmap: &Bitmap) -> Result<(), SableError> {
+        let prefix = bitmap.prefix();
+        let mut db_iter = self.store.create_iterator(&prefix)?;
+        while db_iter.valid() {
+            let Some(key) = db_iter.key() else {
+                break;
+            };
+
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            self.cache.delete(&BytesMut::from(key))?;
+            db_iter.next();
+        }
+        Ok(())
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{PutFlags, StringGetResult, StringsDb};
+
+    #[test]
+    fn test_bitmap_chunks() -> Result<(), SableError> {
+        let (_deleter, db) = crate::tests::open_store();
+        let mut bitmap_db = BitmapDb::with_storage(&db, 0);
+        let key = BytesMut::from("bitmap");
+
+        // set a bit in the second chunk
+        let offset = BITMAP_CHUNK_SIZE * 8 + 9;
+        assert_eq!(
+            bitmap_db.set_bit(&key, offset, true)?,
+            BitmapBitResult::Some(0)
+        );
+        bitmap_db.commit()?;
+
+        assert_eq!(
+            bitmap_db.len(&key)?,
+            BitmapLenResult::Some(BITMAP_CHUNK_SIZE + 2)
+        );
+        assert_eq!(bitmap_db.get_bit(&key, offset)?, BitmapBitResult::Some(1));
+        assert_eq!(bitmap_db.get_bit(&key, 9)?, BitmapBitResult::Some(0));
+
+        // only the chunk holding the bit was written
+        let bitmap = bitmap_db.find_bitmap(&key)?.unwrap();
+        assert!(db.get(&bitmap.chunk_key(0))?.is_none());
+        assert_eq!(db.get(&bitmap.chunk_key(1))?.unwrap().len(), 2);
+
+        // clearing the bit keeps the length
+        assert_eq!(
+            bitmap_db.set_bit(&key, offset, false)?,
+            BitmapBitResult::Some(1)
+        );
+        bitmap_db.commit()?;
+        assert_eq!(
+            bitmap_db.len(&key)?,
+            BitmapLenResult::Some(BITMAP_CHUNK_SIZE + 2)
+        );
+
+        // write across the chunks boundary
+        bitmap_db.write(&key, BITMAP_CHUNK_SIZE - 1, b"ab")?;
+        bitmap_db.commit()?;
+        assert_eq!(
+            bitmap_db.read(&key, BITMAP_CHUNK_SIZE - 2, u64::MAX)?,
+            BitmapReadResult::Some(BytesMut::from(&b"\x00ab\x00"[..]))
+        );
+
+        // delete removes the chunks and the bookkeeping record
+        bitmap_db.delete(&key)?;
+        bitmap_db.commit()?;
+        assert!(db.get(&bitmap.chunk_key(1))?.is_none());
+        assert_eq!(bitmap_db.len(&key)?, BitmapLenResult::NotFound);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitmap_string_conversion() -> Result<(), SableError> {
+        let (_deleter, db) = crate::tests::open_store();
+        let mut bitmap_db = BitmapDb::with_storage(&db, 0);
+        let mut strings_db = StringsDb::with_storage(&db, 0);
+        let key = BytesMut::from("key");
+
+        let string_md = StringValueMetadata::default();
+        strings_db.put(&key, &BytesMut::from("a"), &string_md, PutFlags::Override)?;
+
+        // read bits of a string value: 'a' is 0x61
+        assert_eq!(bitmap_db.get_bit(&key, 1)?, BitmapBitResult::Some(1));
+        assert_eq!(bitmap_db.get_bit(&key, 2)?, BitmapBitResult::Some(1));
+        assert_eq!(bitmap_db.get_bit(&key, 3)?, BitmapBitResult::Some(0));
+        assert!(bitmap_db.find_bitmap(&key)?.is_none());
+
+        // updating the string converts it into a bitmap: 'a' -> 'b'
+        bitmap_db.set_bit(&key, 6, true)?;
+        bitmap_db.set_bit(&key, 7, false)?;
+        bitmap_db.commit()?;
+        assert!(bitmap_db.find_bitmap(&key)?.is_some());
+
+        // the bitmap can still be read as a string
+        let StringGetResult::Some((value, md)) = strings_db.get(&key)? else {
+            panic!("expected a string value");
+        };
+        assert_eq!(value, BytesMut::from("b"));
+        assert!(md.common_metadata().is_string());
+
+        // other types can not be used as bitmaps
+        let mut hash_db = crate::storage::HashDb::with_storage(&db, 0);
+        let hash_key = BytesMut::from("hash");
+        hash_db.put_multi(&hash_key, &[(&key, &key)])?;
+        assert_eq!(bitmap_db.len(&hash_key)?, BitmapLenResult::WrongType);
+        assert_eq!(
+            bitmap_db.set_bit(&hash_key, 1, true)?,
+            BitmapBitResult::WrongType
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use crate::{
+    metadata::{FunctionKey, FunctionLibrary, FunctionRecordKind},
+    storage::DbWriteCache,
+    SableError, StorageAdapter,
+};
+use bytes::BytesMut;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FunctionLoadResult {
+    /// The library was stored
+    Ok,
+    /// A library with this name already exists and `replace` was not requested
+    LibraryExists,
+    /// The library registers a function that is owned by another library
+    FunctionExists(BytesMut),
+}
+
+/// Persists the libraries loaded by `FUNCTION LOAD`.
+///
+/// Each library is stored as a single record (its code and the functions it registered) and
+/// each function has an index record pointing to its library. Since the records are kept in the
+/// store, they survive restarts and are replicated like any other change
+pub struct FunctionDb<'a> {
+    store: &'a StorageAdapter,
+    cache: Box<DbWriteCache<'a>>,
+}
+
+impl<'a> FunctionDb<'a> {
+    pub fn with_storage(store: &'a StorageAdapter) -> Self {
+        let cache = Box::new(DbWriteCache::with_storage(store));
+        FunctionDb { store, cache }
+    }
+
+    /// Store `library`. If `replace` is `true`, an existing library with the same name is
+    /// replaced (including the functions it registered)
+    pub fn load(
+        &mut self,
+        library: &FunctionLibrary,
+        replace: bool,
+    ) -> Result<FunctionLoadResult, SableError> {
+        let old_library = self.library(&library.name)?;
+        if old_library.is_some() && !replace {
+            return Ok(FunctionLoadResult::LibraryExists);
+        }
+
+        // A function name is unique across all the libraries
+        for function in &library.functions {
+            if let Some(owner) = self.function_owner(&function.name)? {
+                if owner != library.name {
+                    return Ok(FunctionLoadResult::FunctionExists(function.name.clone()));
+                }
+            }
+        }
+
+        if let Some(old_library) = old_library {
+            self.delete_library_records(&old_library)?;
+        }
+
+        self.cache.put(
+            &FunctionKey::library(&library.name).to_bytes(),
+            library.to_bytes(),
+        )?;
+        for function in &library.functions {
+            self.cache.put(
+                &FunctionKey::function(&function.name).to_bytes(),
+                library.name.clone(),
+            )?;
+        }
+        Ok(FunctionLoadResult::Ok)
+    }
+
+    /// Delete the library `name` and its functions. Return `false` if no such library exists
+    pub fn delete(&mut self, name: &[u8]) -> Result<bool, SableError> {
+        let Some(library) = self.library(name)? else {
+            return Ok(false);
+        };
+        self.delete_library_records(&library)?;
+        Ok(true)
+    }
+
+    /// Delete all the libraries
+    pub fn delete_all(&mut self) -> Result<(), SableError> {
+        for library in self.libraries()? {
+            self.delete_library_records(&library)?;
+        }
+        Ok(())
+    }
+
+    /// Load the library `name`
+    pub fn library(&self, name: &[u8]) -> Result<Option<FunctionLibrary>, SableError> {
+        match self.cache.get(&FunctionKey::library(name).to_bytes())? {
+            Some(value) => Ok(Some(FunctionLibrary::from_bytes(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return the library that registered the function `name`
+    pub fn find_function(&self, name: &[u8]) -> Result<Option<FunctionLibrary>, SableError> {
+        match self.function_owner(name)? {
+            Some(library_name) => self.library(&library_name),
+            None => Ok(None),
+        }
+    }
+
+    /// Return all the libraries, sorted by name. Changes that were not committed yet are not
+    /// visible to this function
+    pub fn libraries(&self) -> Result<Vec<FunctionLibrary>, SableError> {
+        let prefix = FunctionKey::prefix(FunctionRecordKind::Library);
+        let mut libraries = Vec::<FunctionLibrary>::new();
+        let mut db_iter = self.store.create_iterator(&prefix)?;
+        while db_iter.valid() {
+            let Some((key, value)) = db_iter.key_value() else {
+                break;
+            };
+
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            libraries.push(FunctionLibrary::from_bytes(value)?);
+            db_iter.next();
+        }
+        Ok(libraries)
+    }
+
+    /// Apply the changes to the store
+    pub fn commit(&mut self) -> Result<(), SableError> {
+        self.cache.flush()
+    }
+
+    fn function_owner(&self, name: &[u8]) -> Result<Option<BytesMut>, SableError> {
+        self.cache.get(&FunctionKey::function(name).to_bytes())
+    }
+
+    fn delete_library_records(&mut self, library: &FunctionLibrary) -> Result<(), SableError> {
+        for function in &library.functions {
+            self.cache
+                .delete(&FunctionKey::function(&function.name).to_bytes())?;
+        }
+        self.cache
+            .delete(&FunctionKey::library(&library.name).to_bytes())
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::FunctionInfo;
+
+    fn library(name: &str, functions: &[&str]) -> FunctionLibrary {
+        FunctionLibrary {
+            name: BytesMut::from(name),
+            code: BytesMut::from(format!("#!lua name={}", name).as_str()),
+            functions: functions
+                .iter()
+                .map(|f| FunctionInfo {
+                    name: BytesMut::from(*f),
+                    flags: vec![],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_function_db() -> Result<(), SableError> {
+        let (_guard, store) = crate::tests::open_store();
+        let mut function_db = FunctionDb::with_storage(&store);
+
+        assert_eq!(
+            function_db.load(&library("lib1", &["f1", "f2"]), false)?,
+            FunctionLoadResult::Ok
+        );
+        function_db.commit()?;
+
+        let mut function_db = FunctionDb::with_storage(&store);
+        assert_eq!(
+            function_db.load(&library("lib1", &["f3"]), false)?,
+            FunctionLoadResult::LibraryExists
+        );
+        assert_eq!(
+            function_db.load(&library("lib2", &["f2"]), false)?,
+            FunctionLoadResult::FunctionExists(BytesMut::from("f2"))
+        );
+        assert_eq!(
+            function_db.find_function(b"f2")?.unwrap().name,
+            BytesMut::from("lib1")
+        );
+
+        // Replace: "f1" and "f2" are gone
+        assert_eq!(
+            function_db.load(&library("lib1", &["f3"]), true)?,
+            FunctionLoadResult::Ok
+        );
+        assert_eq!(
+            function_db.load(&library("lib2", &["f2"]), false)?,
+            FunctionLoadResult::Ok
+        );
+        function_db.commit()?;
+
+        let mut function_db = FunctionDb::with_storage(&store);
+        let names: Vec<BytesMut> = function_db
+            .libraries()?
+            .into_iter()
+            .map(|lib| lib.name)
+            .collect();
+        assert_eq!(names, vec![BytesMut::from("lib1"), BytesMut::from("lib2")]);
+        assert!(function_db.find_function(b"f1")?.is_none());
+
+        assert!(function_db.delete(b"lib1")?);
+        assert!(!function_db.delete(b"nosuchlib")?);
+        function_db.commit()?;
+
+        let mut function_db = FunctionDb::with_storage(&store);
+        assert!(function_db.find_function(b"f3")?.is_none());
+        assert_eq!(function_db.libraries()?.len(), 1);
+        function_db.delete_all()?;
+        function_db.commit()?;
+        assert!(FunctionDb::with_storage(&store).libraries()?.is_empty());
+        Ok(())
+    }
+}
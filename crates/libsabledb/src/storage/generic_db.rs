@@ -1,9 +1,28 @@
 /// A database accessor that does not really care about the value
 use crate::{
+    metadata::{
+        Bookkeeping, HashFieldKey, HashValueMetadata, KeyPrefix, SetMemberKey, StringValueMetadata,
+        ValueType, ZSetScoreItem,
+    },
+    storage::{
+        BitmapDb, BitmapReadResult, FindHashResult, FindSetResult, FindZSetResult, HashDb,
+        HashFieldExpiration, ListDb, ListFlags, ListRangeResult, PutFlags, SetDb, StreamDb,
+        StringGetResult, StringsDb, ZSetDb, ZWriteFlags,
+    },
+    utils::{calculate_slot, RdbValue, SLOT_SIZE},
     CommonValueMetadata, DbWriteCache, Expiration, PrimaryKeyMetadata, SableError, StorageAdapter,
-    U8ArrayBuilder, U8ArrayReader,
+    TimeUtils, ToU8Writer, TtlIndexKey, U8ArrayBuilder, U8ArrayReader,
 };
 use bytes::BytesMut;
+use rand::Rng;
+
+pub enum DumpValueResult {
+    NotFound,
+    /// The value type can not be serialized. Only locks, which are internal to SableDB and have
+    /// no RDB encoding, are not serializable
+    Unsupported(ValueType),
+    Some(RdbValue),
+}
 
 #[allow(dead_code)]
 /// General purpose database wrapper.
@@ -23,6 +42,14 @@ pub struct GenericDb<'a> {
 
 #[allow(dead_code)]
 impl<'a> GenericDb<'a> {
+    /// The resolution of the persisted last access time. A recorded access updates the stored
+    /// time only if it is newer by at least this, so idle keys are not rewritten
+    pub const ACCESS_TIME_RESOLUTION_MS: u64 = 1_000;
+
+    /// The number of random picks `random_key` makes before giving up on a database whose
+    /// sampled keys are all expired
+    const RANDOM_KEY_ATTEMPTS: usize = 16;
+
     pub fn with_storage(store: &'a StorageAdapter, db_id: u16) -> Self {
         let cache = Box::new(DbWriteCache::with_storage(store));
         GenericDb {
@@ -94,6 +121,61 @@ impl<'a> GenericDb<'a> {
         Ok(Some(common_md))
     }
 
+    /// Persist an access to `user_key` that was recorded in memory (see
+    /// `Telemetry::record_key_access`). Return `true` if the stored access time was updated
+    pub fn persist_last_accessed(
+        &mut self,
+        user_key: &BytesMut,
+        last_accessed: u64,
+    ) -> Result<bool, SableError> {
+        self.update_last_accessed(user_key, last_accessed, Self::ACCESS_TIME_RESOLUTION_MS)
+    }
+
+    /// Set the last access time of `user_key` (ms since UNIX_EPOCH). Return `false` if the key
+    /// does not exist
+    pub fn set_last_accessed(
+        &mut self,
+        user_key: &BytesMut,
+        last_accessed: u64,
+    ) -> Result<bool, SableError> {
+        self.update_last_accessed(user_key, last_accessed, 0)
+    }
+
+    /// Return the number of bytes used on disk by `user_key`: the sum of the key and value sizes
+    /// of its primary record and of all its sub items. Return `None` if the key does not exist
+    pub fn memory_usage(&mut self, user_key: &BytesMut) -> Result<Option<usize>, SableError> {
+        let Some((_, md)) = self.get_internal(user_key)? else {
+            return Ok(None);
+        };
+
+        let internal_key = PrimaryKeyMetadata::new_primary_key(user_key, self.db_id);
+        let record_len = self
+            .cache
+            .get(&internal_key)?
+            .map(|record| record.len())
+            .unwrap_or_default();
+        let mut usage = internal_key.len().saturating_add(record_len);
+
+        let slot = calculate_slot(user_key);
+        for key_type in md.value_type().sub_item_types() {
+            let prefix =
+                Self::sub_items_prefix(KeyPrefix::new(*key_type, self.db_id, slot), md.uid());
+            let mut db_iter = self.store.create_iterator(&prefix)?;
+            while db_iter.valid() {
+                let Some((key, value)) = db_iter.key_value() else {
+                    break;
+                };
+
+                if !key.starts_with(&prefix) {
+                    break;
+                }
+                usage = usage.saturating_add(key.len()).saturating_add(value.len());
+                db_iter.next();
+            }
+        }
+        Ok(Some(usage))
+    }
+
     /// Return the expiration properties of a `user_key`
     pub fn get_expiration(
         &mut self,
@@ -124,11 +206,307 @@ impl<'a> GenericDb<'a> {
         Ok(())
     }
 
+    /// Rename `user_key` to `new_user_key`, overwriting `new_user_key` if it exists. The sub items
+    /// of a complex type are kept in place when both keys share the same slot, otherwise they are
+    /// moved to the new slot. Return `false` if `user_key` does not exist
+    pub fn rename(
+        &mut self,
+        user_key: &BytesMut,
+        new_user_key: &BytesMut,
+    ) -> Result<bool, SableError> {
+        self.relocate(user_key, self.db_id, new_user_key, false)
+    }
+
+    /// Copy `user_key` into `target_user_key` of the database `target_db_id`, overwriting
+    /// `target_user_key` if it exists. A copy of a complex type gets a new UID. Return `false` if
+    /// `user_key` does not exist
+    pub fn copy(
+        &mut self,
+        user_key: &BytesMut,
+        target_db_id: u16,
+        target_user_key: &BytesMut,
+    ) -> Result<bool, SableError> {
+        self.relocate(user_key, target_db_id, target_user_key, true)
+    }
+
+    /// Move `user_key` to the database `target_db_id`, overwriting the key there if it exists.
+    /// Return `false` if `user_key` does not exist
+    pub fn move_to_db(
+        &mut self,
+        user_key: &BytesMut,
+        target_db_id: u16,
+    ) -> Result<bool, SableError> {
+        self.relocate(user_key, target_db_id, user_key, false)
+    }
+
+    /// Return a random key from the database, or `None` if the database is empty. Expired keys
+    /// are skipped: if `RANDOM_KEY_ATTEMPTS` picks in a row hit an expired key, `None` is returned
+    pub fn random_key(&self) -> Result<Option<BytesMut>, SableError> {
+        let prefix = PrimaryKeyMetadata::first_key_prefix(self.db_id);
+        let mut rng = rand::rng();
+
+        for _ in 0..Self::RANDOM_KEY_ATTEMPTS {
+            // Primary keys are sorted by their slot and then by the user key: seek to a random
+            // position inside a random slot and wrap around to the first slot if there are no
+            // keys after it
+            let mut start = prefix.clone();
+            let mut builder = U8ArrayBuilder::with_buffer(&mut start);
+            builder.write_u16(rng.random_range(0..SLOT_SIZE));
+            builder.write_u64(rng.random::<u64>());
+
+            for seek_key in [&start, &prefix] {
+                let db_iter = self.store.create_iterator(seek_key)?;
+                if !db_iter.valid() {
+                    continue;
+                }
+                let Some((key, value)) = db_iter.key_value() else {
+                    continue;
+                };
+                if !key.starts_with(&prefix) {
+                    continue;
+                }
+
+                let mut reader = U8ArrayReader::with_buffer(value);
+                let md = CommonValueMetadata::from_bytes(&mut reader)?;
+                if md.expiration().is_expired()? {
+                    // try another pick
+                    break;
+                }
+                return Ok(Some(PrimaryKeyMetadata::from_raw(key)?.user_key().clone()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the value stored at `user_key`, including all of its sub items, for serialization
+    /// (`DUMP`, `MIGRATE`)
+    pub fn dump_value(&mut self, user_key: &BytesMut) -> Result<DumpValueResult, SableError> {
+        let store = self.store;
+        let db_id = self.db_id;
+        let Some(common_md) = self.value_common_metadata(user_key)? else {
+            return Ok(DumpValueResult::NotFound);
+        };
+
+        let value = match common_md.value_type() {
+            ValueType::Str => match StringsDb::with_storage(store, db_id).get(user_key)? {
+                StringGetResult::Some((value, _)) => RdbValue::String(value),
+                _ => return Ok(DumpValueResult::NotFound),
+            },
+            // A bitmap is a string
+            ValueType::Bitmap => {
+                match BitmapDb::with_storage(store, db_id).read(user_key, 0, u64::MAX)? {
+                    BitmapReadResult::Some(value) => RdbValue::String(value),
+                    _ => return Ok(DumpValueResult::NotFound),
+                }
+            }
+            ValueType::List => match ListDb::with_storage(store, db_id).range(user_key, 0, -1)? {
+                ListRangeResult::Some(items) if !items.is_empty() => RdbValue::List(items),
+                _ => return Ok(DumpValueResult::NotFound),
+            },
+            ValueType::Hash => {
+                let FindHashResult::Some(hash) =
+                    HashDb::with_storage(store, db_id).find_hash(user_key)?
+                else {
+                    return Ok(DumpValueResult::NotFound);
+                };
+
+                let now = TimeUtils::epoch_ms()?;
+                let prefix = hash.item_prefix();
+                let mut fields =
+                    Vec::<(BytesMut, BytesMut, Option<u64>)>::with_capacity(hash.len() as usize);
+                let mut db_iter = store.create_iterator(&prefix)?;
+                while db_iter.valid() {
+                    let Some((key, value)) = db_iter.key_value() else {
+                        break;
+                    };
+                    if !key.starts_with(&prefix) {
+                        break;
+                    }
+                    let field_value = hash.value.decode_field_value(value)?;
+                    if !field_value.is_expired(now) {
+                        let field = HashFieldKey::from_bytes(key)?;
+                        let expire_timestamp_ms = field_value.expire_timestamp_ms();
+                        fields.push((
+                            BytesMut::from(field.user_key()),
+                            field_value.into_value(),
+                            expire_timestamp_ms,
+                        ));
+                    }
+                    db_iter.next();
+                }
+
+                if fields.is_empty() {
+                    return Ok(DumpValueResult::NotFound);
+                }
+
+                // Field expiration times need the newer (`HASH_METADATA`) encoding
+                if fields.iter().any(|(_, _, expire)| expire.is_some()) {
+                    RdbValue::HashWithExpire(fields)
+                } else {
+                    RdbValue::Hash(
+                        fields
+                            .into_iter()
+                            .map(|(field, value, _)| (field, value))
+                            .collect(),
+                    )
+                }
+            }
+            ValueType::Set => {
+                let FindSetResult::Some(set) =
+                    SetDb::with_storage(store, db_id).find_set(user_key)?
+                else {
+                    return Ok(DumpValueResult::NotFound);
+                };
+
+                let prefix = set.prefix();
+                let mut members = Vec::<BytesMut>::with_capacity(set.len() as usize);
+                let mut db_iter = store.create_iterator(&prefix)?;
+                while db_iter.valid() {
+                    let Some(key) = db_iter.key() else {
+                        break;
+                    };
+                    if !key.starts_with(&prefix) {
+                        break;
+                    }
+                    members.push(BytesMut::from(SetMemberKey::from_bytes(key)?.key()));
+                    db_iter.next();
+                }
+                RdbValue::Set(members)
+            }
+            ValueType::Zset => {
+                let FindZSetResult::Some(set) =
+                    ZSetDb::with_storage(store, db_id).find_set(user_key)?
+                else {
+                    return Ok(DumpValueResult::NotFound);
+                };
+
+                let prefix = set.prefix_by_score(None);
+                let mut members = Vec::<(BytesMut, f64)>::with_capacity(set.len() as usize);
+                let mut db_iter = store.create_iterator(&prefix)?;
+                while db_iter.valid() {
+                    let Some(key) = db_iter.key() else {
+                        break;
+                    };
+                    if !key.starts_with(&prefix) {
+                        break;
+                    }
+                    let item = ZSetScoreItem::from_bytes(key)?;
+                    members.push((BytesMut::from(item.member()), item.score()));
+                    db_iter.next();
+                }
+                RdbValue::Zset(members)
+            }
+            ValueType::Stream => match StreamDb::with_storage(store, db_id).dump(user_key)? {
+                Some(stream) => RdbValue::Stream(stream),
+                None => return Ok(DumpValueResult::NotFound),
+            },
+            value_type => return Ok(DumpValueResult::Unsupported(value_type)),
+        };
+        Ok(DumpValueResult::Some(value))
+    }
+
+    /// Replace the value stored at `user_key` (if any) with a deserialized value (`RESTORE`,
+    /// `MIGRATE`). `expire_timestamp_ms` is the expiration time of the new key, if any.
+    /// The old value is deleted and the new value, with all of its sub items, is written as a
+    /// single batch
+    pub fn restore_value(
+        &mut self,
+        user_key: &BytesMut,
+        value: RdbValue,
+        expire_timestamp_ms: Option<u64>,
+    ) -> Result<(), SableError> {
+        if self.store.is_transaction() {
+            // the writes are already buffered by the txn
+            return self.restore_value_internal(user_key, value, expire_timestamp_ms);
+        }
+
+        let txn = self.store.transaction();
+        GenericDb::with_storage(&txn, self.db_id).restore_value_internal(
+            user_key,
+            value,
+            expire_timestamp_ms,
+        )?;
+        self.store.apply_batch(&txn.take_transaction_batch()?)
+    }
+
     pub fn export_slot() {}
 
     // =========-------------------------------------------
     // Internal helpers
     // =========-------------------------------------------
+    fn restore_value_internal(
+        &mut self,
+        user_key: &BytesMut,
+        value: RdbValue,
+        expire_timestamp_ms: Option<u64>,
+    ) -> Result<(), SableError> {
+        if self.contains(user_key)? {
+            self.delete(user_key, true)?;
+        }
+
+        let store = self.store;
+        let db_id = self.db_id;
+        match value {
+            RdbValue::String(value) => {
+                StringsDb::with_storage(store, db_id).put(
+                    user_key,
+                    &value,
+                    &StringValueMetadata::new(),
+                    PutFlags::Override,
+                )?;
+            }
+            RdbValue::List(items) => {
+                let mut list_db = ListDb::with_storage(store, db_id);
+                let items: Vec<&BytesMut> = items.iter().collect();
+                list_db.push(user_key, &items, ListFlags::FromRight)?;
+                list_db.commit()?;
+            }
+            RdbValue::Hash(fields) => {
+                let fields: Vec<(&BytesMut, &BytesMut)> =
+                    fields.iter().map(|(field, value)| (field, value)).collect();
+                HashDb::with_storage(store, db_id).put_multi(user_key, &fields)?;
+            }
+            RdbValue::HashWithExpire(fields) => {
+                // Fields that already expired are dropped
+                let now = TimeUtils::epoch_ms()?;
+                let mut hash_db = HashDb::with_storage(store, db_id);
+                for (field, value, expire_timestamp_ms) in &fields {
+                    let expiration = match expire_timestamp_ms {
+                        Some(ts) if *ts <= now => continue,
+                        Some(ts) => HashFieldExpiration::At(*ts),
+                        None => HashFieldExpiration::Persist,
+                    };
+                    hash_db.put_multi_with_expiration(user_key, &[(field, value)], expiration)?;
+                }
+            }
+            RdbValue::Set(members) => {
+                let mut set_db = SetDb::with_storage(store, db_id);
+                let members: Vec<&BytesMut> = members.iter().collect();
+                set_db.put_multi_overwrite(user_key, &members)?;
+                set_db.commit()?;
+            }
+            RdbValue::Zset(members) => {
+                let mut zset_db = ZSetDb::with_storage(store, db_id);
+                for (member, score) in &members {
+                    zset_db.add(user_key, member, *score, &ZWriteFlags::None, false)?;
+                }
+                zset_db.commit()?;
+            }
+            RdbValue::Stream(stream) => {
+                let mut stream_db = StreamDb::with_storage(store, db_id);
+                stream_db.restore(user_key, stream)?;
+                stream_db.commit()?;
+            }
+        }
+
+        if let Some(expire_timestamp_ms) = expire_timestamp_ms {
+            let mut expiration = Expiration::default();
+            expiration.set_expire_timestamp_millis(expire_timestamp_ms)?;
+            self.put_expiration(user_key, &expiration, true)?;
+        }
+        Ok(())
+    }
+
     fn put_internal(
         &mut self,
         user_key: &BytesMut,
@@ -143,6 +521,180 @@ impl<'a> GenericDb<'a> {
         self.cache.put(&internal_key, joined_value)
     }
 
+    /// Rewrite the common metadata of `user_key` with `last_accessed`. With a non zero
+    /// `resolution_ms`, the stored access time is only moved forward, by at least `resolution_ms`.
+    /// Only the common metadata, which all the value types start with, is replaced: the rest of
+    /// the record is kept as is
+    fn update_last_accessed(
+        &mut self,
+        user_key: &BytesMut,
+        last_accessed: u64,
+        resolution_ms: u64,
+    ) -> Result<bool, SableError> {
+        let internal_key = PrimaryKeyMetadata::new_primary_key(user_key, self.db_id);
+        let Some(record) = self.store.get(&internal_key)? else {
+            return Ok(false);
+        };
+
+        let mut reader = U8ArrayReader::with_buffer(&record);
+        let mut md = CommonValueMetadata::from_bytes(&mut reader)?;
+        let metadata_len = reader.consumed();
+        if md.expiration().is_expired()?
+            || (resolution_ms > 0
+                && last_accessed < md.last_accessed().saturating_add(resolution_ms))
+        {
+            return Ok(false);
+        }
+
+        md.set_last_accessed(last_accessed);
+        let mut updated_record =
+            BytesMut::with_capacity(CommonValueMetadata::SIZE + record.len() - metadata_len);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut updated_record);
+        md.to_bytes(&mut builder);
+        builder.write_bytes(&record[metadata_len..]);
+        self.store
+            .put_no_watch_notify(&internal_key, &updated_record)?;
+        Ok(true)
+    }
+
+    /// Write `user_key` (with its common metadata) as `target_user_key` of database
+    /// `target_db_id`. Complex types sub items are moved (or copied, if `keep_source` is `true`)
+    /// along with their bookkeeping record. Sub items that do not change their location (same
+    /// database and slot) are not touched.
+    fn relocate(
+        &mut self,
+        user_key: &BytesMut,
+        target_db_id: u16,
+        target_user_key: &BytesMut,
+        keep_source: bool,
+    ) -> Result<bool, SableError> {
+        let Some((value, mut md)) = self.get_internal(user_key)? else {
+            return Ok(false);
+        };
+
+        let value_type = md.value_type();
+        let sub_items = value_type.sub_item_types();
+        if !sub_items.is_empty() {
+            let source_slot = calculate_slot(user_key);
+            let target_slot = calculate_slot(target_user_key);
+            let source_uid = md.uid();
+            let target_uid = if keep_source {
+                self.store.generate_id()
+            } else {
+                source_uid
+            };
+
+            if keep_source || self.db_id != target_db_id || source_slot != target_slot {
+                // Hash fields with an expiration time are indexed by their key
+                let hash_md = if value_type == ValueType::Hash {
+                    let mut buffer =
+                        BytesMut::with_capacity(CommonValueMetadata::SIZE + value.len());
+                    let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+                    md.to_bytes(&mut builder);
+                    builder.write_bytes(&value);
+                    let mut reader = U8ArrayReader::with_buffer(&buffer);
+                    Some(HashValueMetadata::from_bytes(&mut reader)?)
+                } else {
+                    None
+                };
+
+                for key_type in sub_items {
+                    let source_prefix = Self::sub_items_prefix(
+                        KeyPrefix::new(*key_type, self.db_id, source_slot),
+                        source_uid,
+                    );
+                    let target_prefix = Self::sub_items_prefix(
+                        KeyPrefix::new(*key_type, target_db_id, target_slot),
+                        target_uid,
+                    );
+                    self.copy_sub_items(
+                        &source_prefix,
+                        &target_prefix,
+                        keep_source,
+                        hash_md.as_ref(),
+                    )?;
+                }
+            }
+
+            if !keep_source {
+                let source_record = Bookkeeping::new(self.db_id, source_slot)
+                    .with_uid(source_uid)
+                    .with_value_type(value_type)
+                    .to_bytes();
+                self.cache.delete(&source_record)?;
+            }
+            let target_record = Bookkeeping::new(target_db_id, target_slot)
+                .with_uid(target_uid)
+                .with_value_type(value_type)
+                .to_bytes();
+            self.cache.put(&target_record, target_user_key.clone())?;
+            md.set_uid(target_uid);
+        }
+
+        if !keep_source {
+            let source_key = PrimaryKeyMetadata::new_primary_key(user_key, self.db_id);
+            self.cache.delete(&source_key)?;
+        }
+
+        let mut joined_value = BytesMut::with_capacity(value.len() + CommonValueMetadata::SIZE);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut joined_value);
+        md.to_bytes(&mut builder);
+        builder.write_bytes(&value);
+        let target_key = PrimaryKeyMetadata::new_primary_key(target_user_key, target_db_id);
+        self.cache.put(&target_key, joined_value)?;
+        self.cache.flush()?;
+        Ok(true)
+    }
+
+    /// Return the prefix shared by the sub items of a complex type: `<key prefix><uid>`
+    fn sub_items_prefix(key_prefix: KeyPrefix, uid: u64) -> BytesMut {
+        let mut prefix = BytesMut::with_capacity(KeyPrefix::SIZE + std::mem::size_of::<u64>());
+        let mut builder = U8ArrayBuilder::with_buffer(&mut prefix);
+        key_prefix.to_writer(&mut builder);
+        uid.to_writer(&mut builder);
+        prefix
+    }
+
+    /// Copy all the records starting with `source_prefix` to records starting with
+    /// `target_prefix`. If `delete_source` is `true`, the source records are deleted. `hash_md` is
+    /// used to index the expiration time of hash fields
+    fn copy_sub_items(
+        &mut self,
+        source_prefix: &BytesMut,
+        target_prefix: &BytesMut,
+        delete_source: bool,
+        hash_md: Option<&HashValueMetadata>,
+    ) -> Result<(), SableError> {
+        let mut db_iter = self.store.create_iterator(source_prefix)?;
+        while db_iter.valid() {
+            let Some((key, value)) = db_iter.key_value() else {
+                break;
+            };
+
+            if !key.starts_with(source_prefix) {
+                break;
+            }
+
+            let mut target_key = BytesMut::with_capacity(key.len());
+            target_key.extend_from_slice(target_prefix);
+            target_key.extend_from_slice(&key[source_prefix.len()..]);
+
+            if let Some(hash_md) = hash_md {
+                if let Some(timestamp) = hash_md.decode_field_value(value)?.expire_timestamp_ms() {
+                    let index_key = TtlIndexKey::new(timestamp, &target_key);
+                    self.cache.put(&index_key.to_bytes(), BytesMut::new())?;
+                }
+            }
+
+            self.cache.put(&target_key, BytesMut::from(value))?;
+            if delete_source {
+                self.cache.delete(&BytesMut::from(key))?;
+            }
+            db_iter.next();
+        }
+        Ok(())
+    }
+
     /// Get a string key from the underlying storage
     fn get_internal(
         &mut self,
@@ -154,12 +706,13 @@ impl<'a> GenericDb<'a> {
         if let Some(mut value) = raw_value {
             let mut reader = U8ArrayReader::with_buffer(&value);
             let md = CommonValueMetadata::from_bytes(&mut reader)?;
+            let metadata_len = reader.consumed();
 
             if md.expiration().is_expired()? {
                 self.cache.delete(&internal_key)?;
                 Ok(None)
             } else {
-                let _ = value.split_to(CommonValueMetadata::SIZE);
+                let _ = value.split_to(metadata_len);
                 Ok(Some((value, md)))
             }
         } else {
@@ -180,8 +733,8 @@ mod tests {
     use super::*;
     use crate::storage::PutFlags;
     use crate::{
-        metadata::ValueType,
-        storage::{StringGetResult, StringsDb},
+        metadata::KeyType,
+        storage::{HashDb, HashGetResult, StringGetResult, StringsDb},
         StorageOpenParams, StringValueMetadata,
     };
     use std::fs;
@@ -291,4 +844,200 @@ mod tests {
         assert_eq!(db_value, value);
         Ok(())
     }
+
+    #[test]
+    fn test_random_key() -> Result<(), SableError> {
+        let store = open_database("test_random_key");
+        let mut strings_db = StringsDb::with_storage(&store, 0);
+        let generic_db = GenericDb::with_storage(&store, 0);
+        assert_eq!(generic_db.random_key()?, None);
+
+        let mut md = StringValueMetadata::new();
+        md.expiration_mut().set_ttl_millis(1)?;
+        let expired = BytesMut::from("expired");
+        strings_db.put(&expired, &expired, &md, PutFlags::Override)?;
+        std::thread::sleep(std::time::Duration::from_millis(10));
+
+        // only expired keys
+        assert_eq!(generic_db.random_key()?, None);
+
+        let live_keys: Vec<BytesMut> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|key| BytesMut::from(*key))
+            .collect();
+        for key in &live_keys {
+            strings_db.put(key, key, &StringValueMetadata::new(), PutFlags::Override)?;
+        }
+
+        // every live key is picked, the expired key never is
+        let mut picked = std::collections::HashSet::<BytesMut>::new();
+        for _ in 0..500 {
+            let key = generic_db.random_key()?.unwrap();
+            assert_ne!(key, expired);
+            picked.insert(key);
+        }
+        assert_eq!(picked.len(), live_keys.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_value() -> Result<(), SableError> {
+        let store = open_database("test_restore_value");
+        let mut strings_db = StringsDb::with_storage(&store, 0);
+        let mut generic_db = GenericDb::with_storage(&store, 0);
+        let hash_db = HashDb::with_storage(&store, 0);
+
+        let key = BytesMut::from("key");
+        let field = BytesMut::from("field");
+        let value = BytesMut::from("value");
+        strings_db.put(
+            &key,
+            &value,
+            &StringValueMetadata::new(),
+            PutFlags::Override,
+        )?;
+
+        // replace a string with a hash
+        let hash = RdbValue::HashWithExpire(vec![(field.clone(), value.clone(), None)]);
+        generic_db.restore_value(&key, hash.clone(), None)?;
+        assert_eq!(
+            hash_db.get(&key, &field)?,
+            HashGetResult::Some(value.clone())
+        );
+
+        // within a txn, nothing is written before the txn is committed
+        let txn = store.transaction();
+        let copy = BytesMut::from("copy");
+        GenericDb::with_storage(&txn, 0).restore_value(&copy, hash, None)?;
+        assert!(!generic_db.contains(&copy)?);
+        txn.commit()?;
+        assert_eq!(hash_db.get(&copy, &field)?, HashGetResult::Some(value));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_and_copy() -> Result<(), SableError> {
+        let store = open_database("test_rename_and_copy");
+        let mut hash_db = HashDb::with_storage(&store, 0);
+        let mut generic_db = GenericDb::with_storage(&store, 0);
+
+        let field = BytesMut::from("field");
+        let value = BytesMut::from("value");
+        let source = BytesMut::from("source");
+        hash_db.put_multi(&source, &[(&field, &value)])?;
+        let uid = generic_db.get(&source)?.unwrap().1.uid();
+
+        let bookkeeping_record = |user_key: &BytesMut, uid: u64| {
+            Bookkeeping::new(0, calculate_slot(user_key))
+                .with_uid(uid)
+                .with_value_type(ValueType::Hash)
+                .to_bytes()
+        };
+
+        // rename to a different slot: the fields and the bookkeeping record are moved
+        let target = BytesMut::from("target");
+        assert_ne!(calculate_slot(&source), calculate_slot(&target));
+        assert!(generic_db.rename(&source, &target)?);
+        assert!(!generic_db.contains(&source)?);
+        assert_eq!(
+            hash_db.get(&target, &field)?,
+            HashGetResult::Some(value.clone())
+        );
+        assert!(store.get(&bookkeeping_record(&source, uid))?.is_none());
+        assert_eq!(
+            store.get(&bookkeeping_record(&target, uid))?,
+            Some(target.clone())
+        );
+        let source_fields = GenericDb::sub_items_prefix(
+            KeyPrefix::new(KeyType::HashItem, 0, calculate_slot(&source)),
+            uid,
+        );
+        assert!(!store
+            .create_iterator(&source_fields)?
+            .key()
+            .is_some_and(|key| key.starts_with(&source_fields)));
+
+        // copy: the copy gets a new uid
+        let copy = BytesMut::from("copy");
+        assert!(generic_db.copy(&target, 0, &copy)?);
+        let copy_uid = generic_db.get(&copy)?.unwrap().1.uid();
+        assert_ne!(copy_uid, uid);
+        assert_eq!(
+            store.get(&bookkeeping_record(&copy, copy_uid))?,
+            Some(copy.clone())
+        );
+        hash_db.delete(&target, &[&field])?;
+        assert_eq!(hash_db.get(&copy, &field)?, HashGetResult::Some(value));
+        assert!(!generic_db.rename(&target, &copy)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_usage() -> Result<(), SableError> {
+        let store = open_database("test_memory_usage");
+        let mut strings_db = StringsDb::with_storage(&store, 0);
+        let mut hash_db = HashDb::with_storage(&store, 0);
+        let mut generic_db = GenericDb::with_storage(&store, 0);
+
+        let key = BytesMut::from("key");
+        assert_eq!(generic_db.memory_usage(&key)?, None);
+
+        let value = BytesMut::from("value");
+        strings_db.put(
+            &key,
+            &value,
+            &StringValueMetadata::new(),
+            PutFlags::Override,
+        )?;
+        let primary_key_len = PrimaryKeyMetadata::new_primary_key(&key, 0).len();
+        assert_eq!(
+            generic_db.memory_usage(&key)?,
+            Some(primary_key_len + StringValueMetadata::SIZE + value.len())
+        );
+
+        // the hash fields are counted
+        let hash = BytesMut::from("hash");
+        let field1 = BytesMut::from("field1");
+        let field2 = BytesMut::from("field2");
+        hash_db.put_multi(&hash, &[(&field1, &value)])?;
+        let usage = generic_db.memory_usage(&hash)?.unwrap();
+        hash_db.put_multi(&hash, &[(&field2, &value)])?;
+        assert!(generic_db.memory_usage(&hash)?.unwrap() > usage + field2.len() + value.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_accessed() -> Result<(), SableError> {
+        let store = open_database("test_last_accessed");
+        let mut strings_db = StringsDb::with_storage(&store, 0);
+        let mut generic_db = GenericDb::with_storage(&store, 0);
+
+        let key = BytesMut::from("key");
+        strings_db.put(
+            &key,
+            &BytesMut::from("value"),
+            &StringValueMetadata::new(),
+            PutFlags::Override,
+        )?;
+        let last_accessed = generic_db.get(&key)?.unwrap().1.last_accessed();
+
+        // accesses within the resolution period, or older than the stored one, are not persisted
+        assert!(!generic_db.persist_last_accessed(&key, last_accessed + 10)?);
+        assert!(!generic_db.persist_last_accessed(&key, last_accessed - 10_000)?);
+        assert!(generic_db.set_last_accessed(&key, last_accessed - 10_000)?);
+        assert_eq!(
+            generic_db.get(&key)?.unwrap().1.last_accessed(),
+            last_accessed - 10_000
+        );
+        assert!(generic_db.persist_last_accessed(&key, last_accessed)?);
+        assert_eq!(
+            generic_db.get(&key)?.unwrap().1.last_accessed(),
+            last_accessed
+        );
+
+        // the value is kept as is
+        assert_eq!(generic_db.get(&key)?.unwrap().0, BytesMut::from("value"));
+        assert!(!generic_db.persist_last_accessed(&BytesMut::from("no_such_key"), last_accessed)?);
+        Ok(())
+    }
 }
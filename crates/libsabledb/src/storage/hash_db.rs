@@ -1,9 +1,9 @@
 /// A database accessor that does not really care about the value
 use crate::{
-    metadata::{Bookkeeping, HashFieldKey, HashValueMetadata, ValueType},
+    metadata::{Bookkeeping, HashFieldKey, HashFieldValue, HashValueMetadata, ValueType},
     storage::DbWriteCache,
-    CommonValueMetadata, KeyType, PrimaryKeyMetadata, SableError, StorageAdapter, ToU8Writer,
-    U8ArrayBuilder, U8ArrayReader,
+    CommonValueMetadata, KeyType, PrimaryKeyMetadata, SableError, StorageAdapter, TimeUtils,
+    ToU8Writer, TtlIndexKey, U8ArrayBuilder, U8ArrayReader,
 };
 use bytes::BytesMut;
 
@@ -16,9 +16,10 @@ pub struct Hash {
 impl Hash {
     /// Return a prefix suitable for iterating all items owned by this hash
     pub fn item_prefix(&self) -> BytesMut {
-        let mut buf = BytesMut::with_capacity(crate::metadata::KeyPrefix::SIZE);
+        let mut buf = BytesMut::with_capacity(HashFieldKey::SIZE);
         let mut builder = U8ArrayBuilder::with_buffer(&mut buf);
         self.key.common().to_writer(&mut builder);
+        builder.write_u64(self.id());
         buf
     }
 
@@ -100,11 +101,45 @@ pub enum HashExistsResult {
     NotExists,
 }
 
-enum PutFieldResult {
-    /// Ok...
-    Inserted,
-    /// Already exists in hash
-    Updated,
+/// `HashDb::set_fields_expiration` result
+#[derive(PartialEq, Eq, Debug)]
+pub enum HashExpireResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// Number of fields updated
+    Some(usize),
+}
+
+/// How the expiration time of hash fields is updated when the fields are written
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum HashFieldExpiration {
+    /// Remove the expiration time of the field
+    Persist,
+    /// Keep the current expiration time of the field
+    Keep,
+    /// Expire the field at the given timestamp (milliseconds since UNIX_EPOCH). A timestamp in the
+    /// past deletes the field
+    At(u64),
+}
+
+/// The expiration state of a hash field
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum HashFieldTtl {
+    /// The hash or the field do not exist
+    NoSuchField,
+    /// The field exists without an expiration time
+    Persistent,
+    /// The field expires at the given timestamp (milliseconds since UNIX_EPOCH)
+    ExpireAt(u64),
+}
+
+/// `HashDb::fields_ttl` result
+#[derive(PartialEq, Eq, Debug)]
+pub enum HashFieldsTtlResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// The expiration state of the fields, in the order they were requested
+    Some(Vec<HashFieldTtl>),
 }
 
 // Internal
@@ -140,11 +175,23 @@ impl<'a> HashDb<'a> {
         }
     }
 
-    /// Sets the specified fields to their respective values in the hash stored at `user_key`
+    /// Sets the specified fields to their respective values in the hash stored at `user_key`.
+    /// The expiration time of the fields is removed
     pub fn put_multi(
         &mut self,
         user_key: &BytesMut,
         field_vals: &[(&BytesMut, &BytesMut)],
+    ) -> Result<HashPutResult, SableError> {
+        self.put_multi_with_expiration(user_key, field_vals, HashFieldExpiration::Persist)
+    }
+
+    /// Sets the specified fields to their respective values in the hash stored at `user_key` and
+    /// update their expiration time according to `expiration`
+    pub fn put_multi_with_expiration(
+        &mut self,
+        user_key: &BytesMut,
+        field_vals: &[(&BytesMut, &BytesMut)],
+        expiration: HashFieldExpiration,
     ) -> Result<HashPutResult, SableError> {
         if field_vals.is_empty() {
             return Ok(HashPutResult::Some(0));
@@ -160,17 +207,30 @@ impl<'a> HashDb<'a> {
             FindHashResult::Some(hash) => hash,
         };
 
+        if let HashFieldExpiration::At(_) = expiration {
+            self.upgrade_field_values(&mut hash)?;
+        }
+
+        let now = TimeUtils::epoch_ms()?;
         let mut items_added = 0usize;
-        for (key, value) in field_vals {
+        for (field, value) in field_vals {
             // we overide the field's value
-            match self.put_hash_field_value(&hash, key, value)? {
-                PutFieldResult::Updated => {}
-                PutFieldResult::Inserted => items_added = items_added.saturating_add(1),
+            let current = self.get_hash_field(&hash, field)?;
+            let current_live = current.as_ref().filter(|current| !current.is_expired(now));
+            let expire_timestamp_ms = match expiration {
+                HashFieldExpiration::Persist => None,
+                HashFieldExpiration::Keep => current_live.and_then(|v| v.expire_timestamp_ms()),
+                HashFieldExpiration::At(timestamp) => Some(timestamp),
+            };
+            if current_live.is_none() {
+                items_added = items_added.saturating_add(1);
             }
+            let new_value =
+                HashFieldValue::new((*value).clone()).with_expire_timestamp_ms(expire_timestamp_ms);
+            self.replace_hash_field(&mut hash, field, current.as_ref(), Some(new_value), now)?;
         }
 
-        hash.value.incr_len_by(items_added as u64);
-        self.put_hash(user_key, &hash)?;
+        self.put_or_delete_hash(user_key, &hash)?;
 
         // flush the changes
         self.flush_cache()?;
@@ -199,9 +259,13 @@ impl<'a> HashDb<'a> {
             FindHashResult::Some(hash) => hash,
         };
 
+        let now = TimeUtils::epoch_ms()?;
         let mut values = Vec::<Option<BytesMut>>::with_capacity(fields.len());
         for field in fields {
-            values.push(self.get_hash_field_value(&hash, field)?);
+            values.push(
+                self.get_live_hash_field(&hash, field, now)?
+                    .map(|field_value| field_value.into_value()),
+            );
         }
 
         Ok(HashGetMultiResult::Some(values))
@@ -220,11 +284,12 @@ impl<'a> HashDb<'a> {
             FindHashResult::Some(hash) => hash,
         };
 
-        let Some(value) = self.get_hash_field_value(&hash, field)? else {
+        let now = TimeUtils::epoch_ms()?;
+        let Some(field_value) = self.get_live_hash_field(&hash, field, now)? else {
             return Ok(HashGetResult::FieldNotFound);
         };
 
-        Ok(HashGetResult::Some(value))
+        Ok(HashGetResult::Some(field_value.into_value()))
     }
 
     /// Removes the specified fields from the hash stored at `user_key`
@@ -244,21 +309,21 @@ impl<'a> HashDb<'a> {
             FindHashResult::Some(hash) => hash,
         };
 
+        let now = TimeUtils::epoch_ms()?;
         let mut items_deleted = 0usize;
         for field in fields {
-            if self.contains_hash_field(&hash, field)? {
-                self.delete_hash_field_key(&hash, field)?;
+            let Some(current) = self.get_hash_field(&hash, field)? else {
+                continue;
+            };
+            // expired fields are deleted, but not counted
+            if !current.is_expired(now) {
                 items_deleted = items_deleted.saturating_add(1);
             }
+            self.replace_hash_field(&mut hash, field, Some(&current), None, now)?;
         }
 
         // update the hash metadata
-        hash.value.decr_len_by(items_deleted as u64);
-        if hash.value.is_empty() {
-            self.delete_hash(user_key, &hash)?;
-        } else {
-            self.put_hash(user_key, &hash)?;
-        }
+        self.put_or_delete_hash(user_key, &hash)?;
 
         // flush the changes
         self.flush_cache()?;
@@ -277,7 +342,8 @@ impl<'a> HashDb<'a> {
             }
             FindHashResult::Some(hash) => hash,
         };
-        Ok(HashLenResult::Some(hash.len() as usize))
+        let now = TimeUtils::epoch_ms()?;
+        Ok(HashLenResult::Some(self.live_len(&hash, now)? as usize))
     }
 
     /// Check whether `user_field` exists in the hash `user_key`
@@ -297,13 +363,136 @@ impl<'a> HashDb<'a> {
             FindHashResult::Some(hash) => hash,
         };
 
-        if self.contains_hash_field(&hash, user_field)? {
+        let now = TimeUtils::epoch_ms()?;
+        if self.get_live_hash_field(&hash, user_field, now)?.is_some() {
             Ok(HashExistsResult::Exists)
         } else {
             Ok(HashExistsResult::NotExists)
         }
     }
 
+    /// Return the expiration state of the fields of the hash `user_key`
+    pub fn fields_ttl(
+        &self,
+        user_key: &BytesMut,
+        fields: &[&BytesMut],
+    ) -> Result<HashFieldsTtlResult, SableError> {
+        let hash = match self.find_hash(user_key)? {
+            FindHashResult::WrongType => {
+                return Ok(HashFieldsTtlResult::WrongType);
+            }
+            FindHashResult::NotFound => {
+                return Ok(HashFieldsTtlResult::Some(vec![
+                    HashFieldTtl::NoSuchField;
+                    fields.len()
+                ]));
+            }
+            FindHashResult::Some(hash) => hash,
+        };
+
+        let now = TimeUtils::epoch_ms()?;
+        let mut result = Vec::<HashFieldTtl>::with_capacity(fields.len());
+        for field in fields {
+            let ttl = match self.get_live_hash_field(&hash, field, now)? {
+                None => HashFieldTtl::NoSuchField,
+                Some(field_value) => match field_value.expire_timestamp_ms() {
+                    None => HashFieldTtl::Persistent,
+                    Some(timestamp) => HashFieldTtl::ExpireAt(timestamp),
+                },
+            };
+            result.push(ttl);
+        }
+        Ok(HashFieldsTtlResult::Some(result))
+    }
+
+    /// Update the expiration time of the existing `fields` of the hash `user_key`. Return the
+    /// number of fields updated
+    pub fn set_fields_expiration(
+        &mut self,
+        user_key: &BytesMut,
+        fields: &[&BytesMut],
+        expiration: HashFieldExpiration,
+    ) -> Result<HashExpireResult, SableError> {
+        let mut hash = match self.find_hash(user_key)? {
+            FindHashResult::WrongType => {
+                return Ok(HashExpireResult::WrongType);
+            }
+            FindHashResult::NotFound => {
+                return Ok(HashExpireResult::Some(0));
+            }
+            FindHashResult::Some(hash) => hash,
+        };
+
+        let expire_timestamp_ms = match expiration {
+            HashFieldExpiration::Keep => return Ok(HashExpireResult::Some(0)),
+            HashFieldExpiration::Persist => None,
+            HashFieldExpiration::At(timestamp) => {
+                self.upgrade_field_values(&mut hash)?;
+                Some(timestamp)
+            }
+        };
+
+        let now = TimeUtils::epoch_ms()?;
+        let mut items_updated = 0usize;
+        for field in fields {
+            let Some(current) = self.get_live_hash_field(&hash, field, now)? else {
+                continue;
+            };
+            let new_value = current
+                .clone()
+                .with_expire_timestamp_ms(expire_timestamp_ms);
+            self.replace_hash_field(&mut hash, field, Some(&current), Some(new_value), now)?;
+            items_updated = items_updated.saturating_add(1);
+        }
+
+        self.put_or_delete_hash(user_key, &hash)?;
+        self.flush_cache()?;
+        Ok(HashExpireResult::Some(items_updated))
+    }
+
+    /// Delete `user_field` from the hash `user_key` if it expired. `hash_id` is the ID of the hash
+    /// that owned the field when its expiration time was set: if the hash was replaced since then,
+    /// the field is kept. The expiration index entry `index_key` is deleted in the same batch,
+    /// whether the field was deleted or not. Return true if the field was deleted
+    pub fn delete_expired_field(
+        &mut self,
+        user_key: &BytesMut,
+        hash_id: u64,
+        user_field: &BytesMut,
+        index_key: &BytesMut,
+    ) -> Result<bool, SableError> {
+        self.cache.delete(index_key)?;
+        let deleted = self.delete_expired_field_internal(user_key, hash_id, user_field)?;
+        self.flush_cache()?;
+        Ok(deleted)
+    }
+
+    /// Return the number of fields in `hash` that did not expire at `now`
+    pub fn live_len(&self, hash: &Hash, now: u64) -> Result<u64, SableError> {
+        if hash.value.volatile_len() == 0 {
+            return Ok(hash.len());
+        }
+
+        let prefix = hash.item_prefix();
+        let mut expired = 0u64;
+        let mut db_iter = self.store.create_iterator(&prefix)?;
+        while db_iter.valid() {
+            let Some((key, value)) = db_iter.key_value() else {
+                break;
+            };
+
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            if hash.value.decode_field_value(value)?.is_expired(now) {
+                expired = expired.saturating_add(1);
+            }
+            db_iter.next();
+        }
+        Ok(hash.len().saturating_sub(expired))
+    }
+
     //=======================================================
     // Internal API for this class
     //=======================================================
@@ -329,6 +518,33 @@ impl<'a> HashDb<'a> {
         self.cache.flush()
     }
 
+    /// Delete `user_field` from the hash if it expired and the hash was not replaced since its
+    /// expiration time was set. The changes are left in the cache
+    fn delete_expired_field_internal(
+        &mut self,
+        user_key: &BytesMut,
+        hash_id: u64,
+        user_field: &BytesMut,
+    ) -> Result<bool, SableError> {
+        let mut hash = match self.find_hash(user_key)? {
+            FindHashResult::Some(hash) if hash.id() == hash_id => hash,
+            _ => return Ok(false),
+        };
+
+        let now = TimeUtils::epoch_ms()?;
+        let Some(current) = self.get_hash_field(&hash, user_field)? else {
+            return Ok(false);
+        };
+
+        if !current.is_expired(now) {
+            return Ok(false);
+        }
+
+        self.replace_hash_field(&mut hash, user_field, Some(&current), None, now)?;
+        self.put_or_delete_hash(user_key, &hash)?;
+        Ok(true)
+    }
+
     /// Put a hash entry in the database
     fn put_hash_metadata(
         &mut self,
@@ -358,6 +574,15 @@ impl<'a> HashDb<'a> {
         Ok(())
     }
 
+    /// Write the hash metadata, or delete the hash if it has no more fields
+    fn put_or_delete_hash(&mut self, user_key: &BytesMut, hash: &Hash) -> Result<(), SableError> {
+        if hash.is_empty() {
+            self.delete_hash(user_key, hash)
+        } else {
+            self.put_hash(user_key, hash)
+        }
+    }
+
     /// Delete the hash metadata
     fn delete_hash(&mut self, user_key: &BytesMut, hash: &Hash) -> Result<(), SableError> {
         let encoded_key = PrimaryKeyMetadata::new_primary_key(user_key, self.db_id);
@@ -406,48 +631,110 @@ impl<'a> HashDb<'a> {
         Ok(buffer)
     }
 
-    /// Delete hash field from the database
-    fn delete_hash_field_key(
-        &mut self,
+    /// Return the stored value of a hash field, including fields that expired
+    fn get_hash_field(
+        &self,
         hash: &Hash,
         user_field: &BytesMut,
-    ) -> Result<(), SableError> {
+    ) -> Result<Option<HashFieldValue>, SableError> {
         let key = self.encode_hash_field_key(hash, user_field)?;
-        self.cache.delete(&key)?;
-        Ok(())
+        let Some(value) = self.cache.get(&key)? else {
+            return Ok(None);
+        };
+        Ok(Some(hash.value.decode_field_value(&value)?))
     }
 
-    /// Return the value of hash field
-    fn get_hash_field_value(
+    /// Return the value of a hash field, unless it expired at `now`
+    fn get_live_hash_field(
         &self,
         hash: &Hash,
         user_field: &BytesMut,
-    ) -> Result<Option<BytesMut>, SableError> {
-        let key = self.encode_hash_field_key(hash, user_field)?;
-        self.cache.get(&key)
+        now: u64,
+    ) -> Result<Option<HashFieldValue>, SableError> {
+        Ok(self
+            .get_hash_field(hash, user_field)?
+            .filter(|field_value| !field_value.is_expired(now)))
     }
 
-    /// Return the value of hash field
-    fn contains_hash_field(&self, hash: &Hash, user_field: &BytesMut) -> Result<bool, SableError> {
-        let key = self.encode_hash_field_key(hash, user_field)?;
-        self.cache.contains(&key)
-    }
-
-    /// Put the value for a hash field
-    fn put_hash_field_value(
+    /// Replace the `current` value of a hash field with `new_value` (`None` deletes the field) and
+    /// update the hash counters. A `new_value` that expired at `now` deletes the field
+    fn replace_hash_field(
         &mut self,
-        hash: &Hash,
+        hash: &mut Hash,
         user_field: &BytesMut,
-        user_value: &BytesMut,
-    ) -> Result<PutFieldResult, SableError> {
+        current: Option<&HashFieldValue>,
+        new_value: Option<HashFieldValue>,
+        now: u64,
+    ) -> Result<(), SableError> {
         let key = self.encode_hash_field_key(hash, user_field)?;
-        let updating = self.cache.contains(&key)?;
-        self.cache.put(&key, user_value.clone())?;
-        Ok(if updating {
-            PutFieldResult::Updated
-        } else {
-            PutFieldResult::Inserted
-        })
+        if let Some(current) = current {
+            hash.value.decr_len_by(1);
+            if current.has_ttl() {
+                hash.value.decr_volatile_len_by(1);
+            }
+        }
+
+        match new_value.filter(|new_value| !new_value.is_expired(now)) {
+            Some(new_value) => {
+                hash.value.incr_len_by(1);
+                if let Some(expire_timestamp_ms) = new_value.expire_timestamp_ms() {
+                    hash.value.incr_volatile_len_by(1);
+                    // let the cron delete the field once it expires
+                    let index_key = TtlIndexKey::new(expire_timestamp_ms, &key);
+                    self.cache.put(&index_key.to_bytes(), BytesMut::new())?;
+                }
+                self.cache
+                    .put(&key, hash.value.encode_field_value(&new_value))?;
+            }
+            None => {
+                if current.is_some() {
+                    self.cache.delete(&key)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Hashes created before field expiration was supported store the raw field values. Rewrite
+    /// the fields of such a hash so they can hold an expiration time
+    fn upgrade_field_values(&mut self, hash: &mut Hash) -> Result<(), SableError> {
+        if hash.value.has_field_metadata() {
+            return Ok(());
+        }
+
+        hash.value.set_field_metadata();
+        let prefix = hash.item_prefix();
+
+        // Collect the field keys first: the iterator only sees committed data, so fields
+        // written by the current txn (e.g. HSET + HEXPIRE inside MULTI) are merged in here
+        let mut field_keys = std::collections::BTreeSet::<BytesMut>::new();
+        let mut db_iter = self.store.create_iterator(&prefix)?;
+        while db_iter.valid() {
+            let Some(key) = db_iter.key() else {
+                break;
+            };
+
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            field_keys.insert(BytesMut::from(key));
+            db_iter.next();
+        }
+        drop(db_iter);
+
+        field_keys.extend(self.store.txn_keys_with_prefix(&prefix));
+
+        for key in field_keys {
+            // read through the store so a value modified (or deleted) by the txn wins
+            let Some(value) = self.store.get(&key)? else {
+                continue;
+            };
+            let field_value = HashFieldValue::new(value);
+            self.cache
+                .put(&key, hash.value.encode_field_value(&field_value))?;
+        }
+        Ok(())
     }
 
     /// Given raw bytes (read from the db) return whether it represents a `HashValueMetadata`
@@ -546,6 +833,204 @@ mod tests {
         assert!(db.get(&bookkeeping_record_key).unwrap().is_none());
     }
 
+    #[test]
+    fn test_hash_field_expiration() -> Result<(), SableError> {
+        let (_deleter, db) = crate::tests::open_store();
+        let mut hash_db = HashDb::with_storage(&db, 0);
+
+        let hash_name = BytesMut::from("myhash");
+        let field1 = BytesMut::from("field1");
+        let field2 = BytesMut::from("field2");
+        let field3 = BytesMut::from("field3");
+        assert_eq!(
+            hash_db.put_multi(
+                &hash_name,
+                &[(&field1, &field1), (&field2, &field2), (&field3, &field3)]
+            )?,
+            HashPutResult::Some(3)
+        );
+
+        // new hashes store raw field values until a field expiration is set
+        let FindHashResult::Some(hash) = hash_db.find_hash(&hash_name)? else {
+            panic!("expected to find the hash");
+        };
+        assert!(!hash.value.has_field_metadata());
+
+        let expire_at = TimeUtils::epoch_ms()? + 50;
+        assert_eq!(
+            hash_db.set_fields_expiration(
+                &hash_name,
+                &[&field1, &field2],
+                HashFieldExpiration::At(expire_at)
+            )?,
+            HashExpireResult::Some(2)
+        );
+        let FindHashResult::Some(hash) = hash_db.find_hash(&hash_name)? else {
+            panic!("expected to find the hash");
+        };
+        assert!(hash.value.has_field_metadata());
+        assert_eq!(
+            hash_db.get(&hash_name, &field3)?,
+            HashGetResult::Some(field3.clone())
+        );
+        assert_eq!(hash_db.len(&hash_name)?, HashLenResult::Some(3));
+        assert_eq!(
+            hash_db.fields_ttl(&hash_name, &[&field1, &field3])?,
+            HashFieldsTtlResult::Some(vec![
+                HashFieldTtl::ExpireAt(expire_at),
+                HashFieldTtl::Persistent
+            ])
+        );
+
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        // expired fields are not visible, but they are still stored
+        assert_eq!(hash_db.len(&hash_name)?, HashLenResult::Some(1));
+        assert_eq!(
+            hash_db.get(&hash_name, &field1)?,
+            HashGetResult::FieldNotFound
+        );
+        assert_eq!(
+            hash_db.field_exists(&hash_name, &field2)?,
+            HashExistsResult::NotExists
+        );
+        let FindHashResult::Some(hash) = hash_db.find_hash(&hash_name)? else {
+            panic!("expected to find the hash");
+        };
+        assert_eq!(hash.len(), 3);
+        assert_eq!(hash.value.volatile_len(), 2);
+
+        // writing an expired field counts as a new field
+        assert_eq!(
+            hash_db.put_multi(&hash_name, &[(&field1, &field1)])?,
+            HashPutResult::Some(1)
+        );
+        // deleting an expired field does not count
+        assert_eq!(
+            hash_db.delete(&hash_name, &[&field2])?,
+            HashDeleteResult::Some(0)
+        );
+        let FindHashResult::Some(hash) = hash_db.find_hash(&hash_name)? else {
+            panic!("expected to find the hash");
+        };
+        assert_eq!(hash.len(), 2);
+        assert_eq!(hash.value.volatile_len(), 0);
+        assert_eq!(hash_db.len(&hash_name)?, HashLenResult::Some(2));
+
+        // an expiration time in the past deletes the field
+        assert_eq!(
+            hash_db.set_fields_expiration(&hash_name, &[&field1], HashFieldExpiration::At(1))?,
+            HashExpireResult::Some(1)
+        );
+        assert_eq!(
+            hash_db.fields_ttl(&hash_name, &[&field1, &field2, &field3])?,
+            HashFieldsTtlResult::Some(vec![
+                HashFieldTtl::NoSuchField,
+                HashFieldTtl::NoSuchField,
+                HashFieldTtl::Persistent
+            ])
+        );
+        assert_eq!(hash_db.len(&hash_name)?, HashLenResult::Some(1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_hash_fields() -> Result<(), SableError> {
+        let (_deleter, db) = crate::tests::open_store();
+        let hash_name = BytesMut::from("myhash");
+        let field = BytesMut::from("field");
+        let value = BytesMut::from("value");
+
+        // Write a hash the way it was written before field expiration was supported: no flags
+        // in the metadata and raw field values
+        let mut hash_md = HashValueMetadata::with_id(db.generate_id());
+        hash_md.incr_len_by(1);
+        let mut buffer = BytesMut::new();
+        hash_md.to_bytes(&mut U8ArrayBuilder::with_buffer(&mut buffer));
+        buffer.truncate(
+            HashValueMetadata::SIZE - std::mem::size_of::<u8>() - std::mem::size_of::<u64>(),
+        );
+        let primary_key = PrimaryKeyMetadata::new_primary_key(&hash_name, 0);
+        db.put(&primary_key, &buffer, crate::storage::PutFlags::Override)?;
+
+        let mut field_key = BytesMut::new();
+        HashFieldKey::with_user_key(
+            hash_md.id(),
+            0,
+            crate::utils::calculate_slot(&hash_name),
+            &field,
+        )
+        .to_bytes(&mut U8ArrayBuilder::with_buffer(&mut field_key));
+        db.put(&field_key, &value, crate::storage::PutFlags::Override)?;
+
+        let mut hash_db = HashDb::with_storage(&db, 0);
+        assert_eq!(
+            hash_db.get(&hash_name, &field)?,
+            HashGetResult::Some(value.clone())
+        );
+
+        // setting an expiration time converts the field values
+        let expire_at = TimeUtils::epoch_ms()? + 100_000;
+        assert_eq!(
+            hash_db.set_fields_expiration(
+                &hash_name,
+                &[&field],
+                HashFieldExpiration::At(expire_at)
+            )?,
+            HashExpireResult::Some(1)
+        );
+        let FindHashResult::Some(hash) = hash_db.find_hash(&hash_name)? else {
+            panic!("expected to find the hash");
+        };
+        assert!(hash.value.has_field_metadata());
+        assert_eq!(hash_db.get(&hash_name, &field)?, HashGetResult::Some(value));
+        assert_eq!(
+            hash_db.fields_ttl(&hash_name, &[&field])?,
+            HashFieldsTtlResult::Some(vec![HashFieldTtl::ExpireAt(expire_at)])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_field_expiration_in_txn() -> Result<(), SableError> {
+        let (_deleter, db) = crate::tests::open_store();
+        let hash_name = BytesMut::from("myhash");
+        let field1 = BytesMut::from("field1");
+        let field2 = BytesMut::from("field2");
+
+        // fields written by the txn are not visible to iterators, they must still be converted
+        let txn = db.transaction();
+        let mut hash_db = HashDb::with_storage(&txn, 0);
+        assert_eq!(
+            hash_db.put_multi(&hash_name, &[(&field1, &field1), (&field2, &field2)])?,
+            HashPutResult::Some(2)
+        );
+        let expire_at = TimeUtils::epoch_ms()? + 100_000;
+        assert_eq!(
+            hash_db.set_fields_expiration(
+                &hash_name,
+                &[&field1],
+                HashFieldExpiration::At(expire_at)
+            )?,
+            HashExpireResult::Some(1)
+        );
+        txn.commit()?;
+
+        let mut hash_db = HashDb::with_storage(&db, 0);
+        assert_eq!(
+            hash_db.get(&hash_name, &field2)?,
+            HashGetResult::Some(field2.clone())
+        );
+        assert_eq!(
+            hash_db.fields_ttl(&hash_name, &[&field1, &field2])?,
+            HashFieldsTtlResult::Some(vec![
+                HashFieldTtl::ExpireAt(expire_at),
+                HashFieldTtl::Persistent
+            ])
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_hash_db() -> Result<(), SableError> {
         let (_deleter, db) = crate::tests::open_store();
@@ -0,0 +1,92 @@
+use bytes::BytesMut;
+
+/// A key reported by the keyspace analysis
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyStats {
+    pub db_id: u16,
+    pub user_key: BytesMut,
+    /// The key size (number of items, or bytes for strings) or its estimated access count
+    pub value: u64,
+}
+
+/// Keep the `capacity` keys with the highest value, ordered by value (highest first)
+#[derive(Clone, Debug, Default)]
+pub struct TopKeys {
+    capacity: usize,
+    keys: Vec<KeyStats>,
+}
+
+impl TopKeys {
+    pub fn with_capacity(capacity: usize) -> Self {
+        TopKeys {
+            capacity,
+            keys: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Add `user_key` if its `value` is higher than the lowest value kept
+    pub fn add(&mut self, db_id: u16, user_key: &[u8], value: u64) {
+        if self.keys.len() == self.capacity
+            && !self.keys.last().is_some_and(|lowest| lowest.value < value)
+        {
+            return;
+        }
+
+        // keys with the same value are kept in insertion order
+        let pos = self.keys.partition_point(|key| key.value >= value);
+        self.keys.insert(
+            pos,
+            KeyStats {
+                db_id,
+                user_key: BytesMut::from(user_key),
+                value,
+            },
+        );
+        self.keys.truncate(self.capacity);
+    }
+
+    pub fn keys(&self) -> &[KeyStats] {
+        &self.keys
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_keys() {
+        let mut top_keys = TopKeys::with_capacity(3);
+        top_keys.add(0, b"a", 10);
+        top_keys.add(0, b"b", 30);
+        top_keys.add(1, b"c", 20);
+        top_keys.add(0, b"d", 5);
+        top_keys.add(0, b"e", 20);
+        top_keys.add(0, b"f", 40);
+
+        let keys: Vec<(u16, &[u8], u64)> = top_keys
+            .keys()
+            .iter()
+            .map(|key| (key.db_id, &key.user_key[..], key.value))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![(0, &b"f"[..], 40), (0, &b"b"[..], 30), (1, &b"c"[..], 20)]
+        );
+
+        let mut no_keys = TopKeys::with_capacity(0);
+        no_keys.add(0, b"a", 10);
+        assert!(no_keys.keys().is_empty());
+    }
+}
@@ -1,5 +1,8 @@
+mod bitmap_db;
+mod function_db;
 mod generic_db;
 mod hash_db;
+mod keyspace_analysis;
 mod limits;
 mod list_db;
 mod lock_db;
@@ -8,17 +11,22 @@ mod set_db;
 mod storage_adapter;
 mod storage_rocksdb;
 mod storage_trait;
+mod stream_db;
 mod string_db;
 mod write_cache;
 mod zset_db;
 
 pub use crate::replication::{StorageUpdates, StorageUpdatesRecord};
 pub use crate::storage::storage_adapter::*;
-pub use generic_db::GenericDb;
+pub use bitmap_db::*;
+pub use function_db::*;
+pub use generic_db::{DumpValueResult, GenericDb};
 pub use hash_db::{
-    FindHashResult, HashDb, HashDeleteResult, HashExistsResult, HashGetMultiResult, HashGetResult,
+    FindHashResult, HashDb, HashDeleteResult, HashExistsResult, HashExpireResult,
+    HashFieldExpiration, HashFieldTtl, HashFieldsTtlResult, HashGetMultiResult, HashGetResult,
     HashLenResult, HashPutResult,
 };
+pub use keyspace_analysis::{KeyStats, TopKeys};
 pub use limits::*;
 pub use list_db::*;
 pub use lock_db::*;
@@ -26,6 +34,7 @@ pub use scan_cursor::ScanCursor;
 pub use set_db::*;
 pub use storage_rocksdb::StorageRocksDb;
 pub use storage_trait::{IteratorAdapter, StorageIterator, StorageMetadata, StorageTrait};
+pub use stream_db::*;
 pub use string_db::*;
 pub use write_cache::{DbCacheEntry, DbWriteCache};
 pub use zset_db::*;
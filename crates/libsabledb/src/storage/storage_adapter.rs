@@ -1,5 +1,5 @@
 use crate::{
-    replication::StorageUpdates,
+    replication::{StorageUpdates, UpdatesNotifier},
     storage::StorageUpdatesRecord,
     storage::{storage_trait::IteratorAdapter, GetChangesLimits, StorageTrait},
     utils, StorageRocksDb,
@@ -344,6 +344,15 @@ impl TxnWriteCache {
         batch_update
     }
 
+    /// Return the keys written (put or deleted) by this txn that start with `prefix`
+    pub fn keys_with_prefix(&self, prefix: &BytesMut) -> Vec<BytesMut> {
+        self.changes
+            .iter()
+            .filter(|entry| entry.key().starts_with(prefix))
+            .map(|entry| entry.key().clone())
+            .collect()
+    }
+
     pub fn clear(&self) {
         self.changes.clear()
     }
@@ -374,6 +383,22 @@ impl StorageAdapter {
         cloned
     }
 
+    /// Return true if this adapter buffers its writes in a txn
+    pub fn is_transaction(&self) -> bool {
+        self.txn.is_some()
+    }
+
+    /// Remove the pending writes of the txn and return them as a single batch, to be applied
+    /// by the caller (e.g. into the parent adapter, so watchers are notified)
+    pub fn take_transaction_batch(&self) -> Result<BatchUpdate, SableError> {
+        let Some(txn) = &self.txn else {
+            return Err(SableError::NoActiveTransaction);
+        };
+        let updates = txn.to_write_batch();
+        txn.clear();
+        Ok(updates)
+    }
+
     /// Flush all dirty buffers to the disk
     pub fn flush(&self) -> Result<(), SableError> {
         let Some(db) = &self.store else {
@@ -434,6 +459,24 @@ impl StorageAdapter {
         } else {
             db.put(key, value, put_flags)?;
             WatchedKeys::notify(key, None);
+            UpdatesNotifier::notify();
+        }
+        Ok(())
+    }
+
+    /// Similar to `put`, but the watchers of `key` are not notified. Use this for updates that do
+    /// not change the value as seen by the user (e.g. its last access time), so they do not
+    /// abort transactions that are watching the key
+    pub fn put_no_watch_notify(&self, key: &BytesMut, value: &BytesMut) -> Result<(), SableError> {
+        let Some(db) = &self.store else {
+            return Err(SableError::OtherError("Database is not opened".to_string()));
+        };
+
+        if let Some(txn) = &self.txn {
+            txn.put_flags(key, value.clone(), PutFlags::Override)?;
+        } else {
+            db.put(key, value, PutFlags::Override)?;
+            UpdatesNotifier::notify();
         }
         Ok(())
     }
@@ -466,6 +509,7 @@ impl StorageAdapter {
         } else {
             db.delete(key)?;
             WatchedKeys::notify(key, None);
+            UpdatesNotifier::notify();
             Ok(())
         }
     }
@@ -488,6 +532,7 @@ impl StorageAdapter {
             db.apply_batch(update)?;
             let modified_keys = update.modified_keys();
             WatchedKeys::notify_multi(&modified_keys, None);
+            UpdatesNotifier::notify();
         }
         Ok(())
     }
@@ -582,6 +627,16 @@ impl StorageAdapter {
         db.create_iterator(prefix)
     }
 
+    /// Return the keys starting with `prefix` that were modified by the active txn and are not
+    /// committed yet. Iterators do not see these keys, so callers that must visit every key of
+    /// a prefix should merge them. Returns an empty list when not in a txn
+    pub fn txn_keys_with_prefix(&self, prefix: &BytesMut) -> Vec<BytesMut> {
+        match &self.txn {
+            Some(txn) => txn.keys_with_prefix(prefix),
+            None => Vec::default(),
+        }
+    }
+
     /// Create a reverse database iterator
     /// `upper_bound` should be the first prefix after the requested prefix.
     /// For example, if the caller wishes to iterate over all items starting with "1"
@@ -596,7 +651,8 @@ impl StorageAdapter {
         db.create_reverse_iterator(upper_bound)
     }
 
-    /// Commit the txn into the database as a single batch operation
+    /// Commit the txn into the database as a single batch operation. Once committed, the txn
+    /// is cleared so it can be re-used
     pub fn commit(&self) -> Result<(), SableError> {
         let Some(db) = &self.store else {
             return Err(SableError::OtherError("Database is not opened".to_string()));
@@ -607,7 +663,13 @@ impl StorageAdapter {
         };
 
         let updates = txn.to_write_batch();
-        db.apply_batch(&updates)
+        txn.clear();
+        if updates.is_empty() {
+            return Ok(());
+        }
+        db.apply_batch(&updates)?;
+        UpdatesNotifier::notify();
+        Ok(())
     }
 
     /// Delete keys ranging from `[start, end)` (including `start` excluding `end`) from the database.
@@ -634,7 +696,9 @@ impl StorageAdapter {
             ));
         };
 
-        db.delete_range(start, end)
+        db.delete_range(start, end)?;
+        UpdatesNotifier::notify();
+        Ok(())
     }
 
     /// Delete slot from the database
@@ -649,7 +713,9 @@ impl StorageAdapter {
                 "`delete_slot` can not be used within a txn".into(),
             ));
         };
-        db.delete_slot(db_id, slot)
+        db.delete_slot(db_id, slot)?;
+        UpdatesNotifier::notify();
+        Ok(())
     }
 
     /// Trigger a database vacuum
@@ -380,6 +380,7 @@ impl StorageTrait for StorageRocksDb {
                 Ok((seq, update)) => (seq, update),
             };
 
+            myiter.storage_updates.start_batch(seq);
             write_batch.iterate(&mut myiter);
 
             // update the counters
@@ -1,9 +1,10 @@
 use crate::{
-    storage::{BatchUpdate, GetChangesLimits, PutFlags, StorageUpdates},
+    storage::{BatchUpdate, GetChangesLimits, KeyStats, PutFlags, StorageUpdates, TopKeys},
     SableError,
 };
 use bytes::BytesMut;
 use nohash_hasher::IntMap;
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::rc::Rc;
 
@@ -66,9 +67,54 @@ impl IteratorAdapter<'_> {
 #[derive(Default, Clone, Debug)]
 pub struct StorageMetadata {
     db_hash_map: IntMap<u16, usize>,
+    /// The largest keys, per type name
+    big_keys: BTreeMap<&'static str, TopKeys>,
+    /// The most accessed keys, based on the sampled key accesses
+    hot_keys: TopKeys,
+    /// The number of keys kept per type by `big_keys` and by `hot_keys`
+    top_keys_count: usize,
 }
 
 impl StorageMetadata {
+    /// Keep the `count` largest keys of every type, and the `count` most accessed keys
+    pub fn with_top_keys(count: usize) -> Self {
+        StorageMetadata {
+            hot_keys: TopKeys::with_capacity(count),
+            top_keys_count: count,
+            ..Default::default()
+        }
+    }
+
+    /// Record the size of a key of type `type_name`
+    pub fn add_key_size(
+        &mut self,
+        type_name: &'static str,
+        db_id: u16,
+        user_key: &[u8],
+        size: u64,
+    ) {
+        let top_keys_count = self.top_keys_count;
+        self.big_keys
+            .entry(type_name)
+            .or_insert_with(|| TopKeys::with_capacity(top_keys_count))
+            .add(db_id, user_key, size);
+    }
+
+    /// Record the estimated access count of a key
+    pub fn add_key_accesses(&mut self, db_id: u16, user_key: &[u8], accesses: u64) {
+        self.hot_keys.add(db_id, user_key, accesses);
+    }
+
+    /// Return the largest keys, per type name
+    pub fn big_keys(&self) -> &BTreeMap<&'static str, TopKeys> {
+        &self.big_keys
+    }
+
+    /// Return the most accessed keys
+    pub fn hot_keys(&self) -> &[KeyStats] {
+        self.hot_keys.keys()
+    }
+
     /// Incremenet the number of keys for database `db_id`
     pub fn incr_keys(&mut self, db_id: u16) {
         self.db_hash_map
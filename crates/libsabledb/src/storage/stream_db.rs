@@ -0,0 +1,1708 @@
+use crate::{
+    metadata::{
+        Bookkeeping, StreamConsumerKey, StreamConsumerMetadata, StreamEntryKey, StreamGroupKey,
+        StreamGroupMetadata, StreamId, StreamPendingEntry, StreamPendingKey, StreamValueMetadata,
+        ValueType,
+    },
+    storage::DbWriteCache,
+    utils::{
+        current_time, CurrentTimeResolution, RdbStream, RdbStreamConsumer, RdbStreamEntry,
+        RdbStreamGroup, RdbStreamPending,
+    },
+    CommonValueMetadata, PrimaryKeyMetadata, SableError, StorageAdapter, U8ArrayBuilder,
+    U8ArrayReader,
+};
+use bytes::BytesMut;
+use std::collections::BTreeMap;
+
+/// `XAUTOCLAIM` examines up to `count` times this factor pending entries
+const STREAM_AUTOCLAIM_ATTEMPTS_FACTOR: usize = 10;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Stream {
+    pub key: PrimaryKeyMetadata,
+    pub metadata: StreamValueMetadata,
+}
+
+impl Stream {
+    pub fn slot(&self) -> u16 {
+        self.key.slot()
+    }
+
+    pub fn database_id(&self) -> u16 {
+        self.key.database_id()
+    }
+
+    pub fn id(&self) -> u64 {
+        self.metadata.id()
+    }
+
+    pub fn len(&self) -> u64 {
+        self.metadata.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_empty()
+    }
+
+    pub fn last_id(&self) -> &StreamId {
+        self.metadata.last_id()
+    }
+
+    pub fn prefix(&self) -> BytesMut {
+        StreamEntryKey::prefix(self.id(), self.database_id(), self.slot())
+    }
+
+    /// Encode the database key of the entry `entry_id`
+    pub fn entry_key(&self, entry_id: StreamId) -> BytesMut {
+        StreamEntryKey::new(self.id(), self.database_id(), self.slot(), entry_id).to_bytes()
+    }
+
+    /// Encode the database key of the consumer group `group_name`
+    pub fn group_key(&self, group_name: &[u8]) -> BytesMut {
+        StreamGroupKey::new(self.id(), self.database_id(), self.slot(), group_name).to_bytes()
+    }
+
+    pub fn pending_prefix(&self, group_id: u64) -> BytesMut {
+        StreamPendingKey::prefix(self.id(), group_id, self.database_id(), self.slot())
+    }
+
+    /// Encode the database key of the pending entry `entry_id` of the group `group_id`
+    pub fn pending_key(&self, group_id: u64, entry_id: StreamId) -> BytesMut {
+        StreamPendingKey::new(
+            self.id(),
+            group_id,
+            self.database_id(),
+            self.slot(),
+            entry_id,
+        )
+        .to_bytes()
+    }
+
+    pub fn consumer_prefix(&self, group_id: u64) -> BytesMut {
+        StreamConsumerKey::prefix(self.id(), group_id, self.database_id(), self.slot())
+    }
+
+    /// Encode the database key of the consumer `consumer` of the group `group_id`
+    pub fn consumer_key(&self, group_id: u64, consumer: &[u8]) -> BytesMut {
+        StreamConsumerKey::new(
+            self.id(),
+            group_id,
+            self.database_id(),
+            self.slot(),
+            consumer,
+        )
+        .to_bytes()
+    }
+}
+
+/// A single stream entry: an ID followed by a list of field-value pairs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamEntry {
+    pub id: StreamId,
+    pub fields: Vec<(BytesMut, BytesMut)>,
+}
+
+impl StreamEntry {
+    /// Serialise the entry fields. The entry ID is part of the key
+    pub fn fields_to_bytes(&self) -> BytesMut {
+        let mut buffer = BytesMut::default();
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        builder.write_usize(self.fields.len());
+        for (field, value) in &self.fields {
+            builder.write_message(field);
+            builder.write_message(value);
+        }
+        buffer
+    }
+
+    /// Construct an entry from its database key and value
+    pub fn from_bytes(key: &[u8], value: &[u8]) -> Result<Self, SableError> {
+        let entry_key = StreamEntryKey::from_bytes(key)?;
+        let mut reader = U8ArrayReader::with_buffer(value);
+        let count = reader.read_usize().ok_or(SableError::SerialisationError)?;
+        let mut fields = Vec::<(BytesMut, BytesMut)>::with_capacity(count);
+        for _ in 0..count {
+            let field = reader
+                .read_message()
+                .ok_or(SableError::SerialisationError)?;
+            let value = reader
+                .read_message()
+                .ok_or(SableError::SerialisationError)?;
+            fields.push((field, value));
+        }
+        Ok(StreamEntry {
+            id: *entry_key.entry_id(),
+            fields,
+        })
+    }
+}
+
+// Internal enum
+#[derive(Debug, PartialEq, Eq)]
+pub enum FindStreamResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// A match was found
+    Some(Stream),
+    /// No entry exist
+    NotFound,
+}
+
+// Internal enum
+#[derive(Debug, PartialEq, Eq)]
+enum FindStreamGroupResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// No such stream
+    NoStream,
+    /// The stream exists, but it has no such consumer group
+    NoGroup,
+    /// A match was found
+    Some(Stream, StreamGroupMetadata),
+}
+
+/// The ID requested by the caller of `StreamDb::add`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamIdSpec {
+    /// `*` - generate the ID from the current time
+    Auto,
+    /// `<ms>-*` - use the given milliseconds and generate the sequence part
+    AutoSeq(u64),
+    /// `<ms>-<seq>`
+    Explicit(StreamId),
+}
+
+/// Trimming strategy (`MAXLEN` or `MINID`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamTrimStrategy {
+    /// Keep at most `N` entries
+    MaxLen(u64),
+    /// Evict entries with ID lower than the given ID
+    MinId(StreamId),
+}
+
+/// Trimming options passed to `XADD` and `XTRIM`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamTrim {
+    pub strategy: StreamTrimStrategy,
+    /// Maximum number of entries to evict. `None` means no limit
+    pub limit: Option<u64>,
+}
+
+/// `StreamDb::add` result
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamAddResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// The stream does not exist and the caller asked not to create it
+    NotFound,
+    /// The requested ID is equal or smaller than the stream top item
+    IdTooSmall,
+    /// The requested ID is `0-0`
+    IdIsZero,
+    /// The stream reached the last possible ID
+    IdsExhausted,
+    /// The ID of the newly added entry
+    Some(StreamId),
+}
+
+/// `StreamDb::range` result
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamRangeResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// The entries found in the requested range
+    Some(Vec<StreamEntry>),
+}
+
+/// `StreamDb::delete`, `StreamDb::trim` and `StreamDb::len` result
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamCountResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// Number of entries deleted or the stream length
+    Some(usize),
+}
+
+/// `StreamDb::last_id` result
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamLastIdResult {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// The ID of the last entry added to the stream
+    Some(StreamId),
+    /// No such stream
+    NotFound,
+}
+
+/// Result of the consumer group API (`StreamDb::create_group`, `StreamDb::read_group` etc)
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamGroupResult<T> {
+    /// An entry exists in the db for the given key, but for a different type
+    WrongType,
+    /// No such stream
+    NoStream,
+    /// The stream exists, but it has no such consumer group
+    NoGroup,
+    /// The operation output
+    Some(T),
+}
+
+/// Options passed to `StreamDb::claim` (the `XCLAIM` command)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamClaimOptions {
+    /// Set the idle time (milliseconds) of the claimed entries
+    pub idle: Option<u64>,
+    /// Set the delivery time (milliseconds since UNIX EPOCH) of the claimed entries
+    pub time: Option<u64>,
+    /// Set the delivery count of the claimed entries
+    pub retry_count: Option<u64>,
+    /// Create a pending entry for IDs that are not in the PEL
+    pub force: bool,
+    /// Do not increment the delivery count of the claimed entries
+    pub justid: bool,
+    /// Advance the group last delivered ID to this ID
+    pub last_id: Option<StreamId>,
+}
+
+/// Summary of the pending entries of a group (`StreamDb::pending_summary`)
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StreamPendingSummary {
+    /// Number of pending entries
+    pub count: usize,
+    /// The smallest and the largest pending IDs
+    pub range: Option<(StreamId, StreamId)>,
+    /// Number of pending entries per consumer, sorted by the consumer name
+    pub consumers: Vec<(BytesMut, usize)>,
+}
+
+/// `StreamDb::autoclaim` output
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StreamAutoClaimResult {
+    /// The cursor for the next call. `0-0` when the entire PEL was scanned
+    pub next_id: StreamId,
+    /// The claimed entries
+    pub claimed: Vec<StreamEntry>,
+    /// Pending IDs that no longer exist in the stream. These IDs were removed from the PEL
+    pub deleted: Vec<StreamId>,
+}
+
+/// Stream DB wrapper. This class is specialized in reading/writing streams
+/// (commands from the `XADD`, `XRANGE` etc family)
+///
+/// Locking strategy: this class does not lock anything and relies on the caller
+/// to obtain the locks if needed
+pub struct StreamDb<'a> {
+    store: &'a StorageAdapter,
+    db_id: u16,
+    cache: Box<DbWriteCache<'a>>,
+}
+
+#[allow(dead_code)]
+impl<'a> StreamDb<'a> {
+    pub fn with_storage(store: &'a StorageAdapter, db_id: u16) -> Self {
+        let cache = Box::new(DbWriteCache::with_storage(store));
+        StreamDb {
+            store,
+            db_id,
+            cache,
+        }
+    }
+
+    /// Append a new entry to the stream `user_key`. If the stream does not exist, it is created
+    /// unless `nomkstream` is `true`. If `trim` is provided, the stream is trimmed after the
+    /// entry is added
+    pub fn add(
+        &mut self,
+        user_key: &BytesMut,
+        id_spec: StreamIdSpec,
+        fields: Vec<(BytesMut, BytesMut)>,
+        nomkstream: bool,
+        trim: Option<StreamTrim>,
+    ) -> Result<StreamAddResult, SableError> {
+        let (mut stream, is_new) = match self.find_stream(user_key)? {
+            FindStreamResult::WrongType => return Ok(StreamAddResult::WrongType),
+            FindStreamResult::NotFound if nomkstream => return Ok(StreamAddResult::NotFound),
+            FindStreamResult::NotFound => (self.new_stream(user_key), true),
+            FindStreamResult::Some(stream) => (stream, false),
+        };
+
+        let last_id = *stream.last_id();
+        let entry_id = match id_spec {
+            StreamIdSpec::Auto => {
+                let now = current_time(CurrentTimeResolution::Milliseconds);
+                if now > last_id.ms() {
+                    StreamId::new(now, 0)
+                } else {
+                    match last_id.next() {
+                        Some(id) => id,
+                        None => return Ok(StreamAddResult::IdsExhausted),
+                    }
+                }
+            }
+            StreamIdSpec::AutoSeq(ms) => {
+                if ms > last_id.ms() {
+                    StreamId::new(ms, 0)
+                } else if ms == last_id.ms() && last_id.seq() < u64::MAX {
+                    StreamId::new(ms, last_id.seq() + 1)
+                } else {
+                    return Ok(StreamAddResult::IdTooSmall);
+                }
+            }
+            StreamIdSpec::Explicit(id) => {
+                if id == StreamId::MIN {
+                    return Ok(StreamAddResult::IdIsZero);
+                }
+                if id <= last_id {
+                    return Ok(StreamAddResult::IdTooSmall);
+                }
+                id
+            }
+        };
+
+        if is_new {
+            self.put_bookkeeping_record(user_key, &stream.metadata)?;
+        }
+
+        let entry = StreamEntry {
+            id: entry_id,
+            fields,
+        };
+        self.cache
+            .put(&stream.entry_key(entry_id), entry.fields_to_bytes())?;
+        stream.metadata.incr_len_by(1);
+        stream.metadata.set_last_id(entry_id);
+        stream.metadata.incr_entries_added();
+
+        if let Some(trim) = trim {
+            self.trim_stream(&mut stream, &trim, Some(entry_id))?;
+        }
+
+        self.put_stream_metadata(user_key, &stream.metadata)?;
+        Ok(StreamAddResult::Some(entry_id))
+    }
+
+    /// Return up to `count` entries with IDs in the range `[start, end]` (inclusive)
+    pub fn range(
+        &self,
+        user_key: &BytesMut,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+    ) -> Result<StreamRangeResult, SableError> {
+        let stream = match self.find_stream(user_key)? {
+            FindStreamResult::WrongType => return Ok(StreamRangeResult::WrongType),
+            FindStreamResult::NotFound => return Ok(StreamRangeResult::Some(Vec::default())),
+            FindStreamResult::Some(stream) => stream,
+        };
+
+        let entries = self.stream_range(&stream, start, end, count.unwrap_or(usize::MAX))?;
+        Ok(StreamRangeResult::Some(entries))
+    }
+
+    /// Remove the entries identified by `ids` from the stream
+    pub fn delete(
+        &mut self,
+        user_key: &BytesMut,
+        ids: &[StreamId],
+    ) -> Result<StreamCountResult, SableError> {
+        let mut stream = match self.find_stream(user_key)? {
+            FindStreamResult::WrongType => return Ok(StreamCountResult::WrongType),
+            FindStreamResult::NotFound => return Ok(StreamCountResult::Some(0)),
+            FindStreamResult::Some(stream) => stream,
+        };
+
+        let mut items_deleted = 0usize;
+        for id in ids {
+            let entry_key = stream.entry_key(*id);
+            if self.cache.contains(&entry_key)? {
+                self.cache.delete(&entry_key)?;
+                stream.metadata.update_max_deleted_id(*id);
+                items_deleted = items_deleted.saturating_add(1);
+            }
+        }
+
+        if items_deleted > 0 {
+            stream.metadata.decr_len_by(items_deleted as u64);
+            self.put_stream_metadata(user_key, &stream.metadata)?;
+        }
+        Ok(StreamCountResult::Some(items_deleted))
+    }
+
+    /// Trim the stream according to `trim`. Return the number of entries evicted
+    pub fn trim(
+        &mut self,
+        user_key: &BytesMut,
+        trim: &StreamTrim,
+    ) -> Result<StreamCountResult, SableError> {
+        let mut stream = match self.find_stream(user_key)? {
+            FindStreamResult::WrongType => return Ok(StreamCountResult::WrongType),
+            FindStreamResult::NotFound => return Ok(StreamCountResult::Some(0)),
+            FindStreamResult::Some(stream) => stream,
+        };
+
+        let items_deleted = self.trim_stream(&mut stream, trim, None)?;
+        if items_deleted > 0 {
+            self.put_stream_metadata(user_key, &stream.metadata)?;
+        }
+        Ok(StreamCountResult::Some(items_deleted))
+    }
+
+    /// Return the number of entries in the stream
+    pub fn len(&self, user_key: &BytesMut) -> Result<StreamCountResult, SableError> {
+        match self.find_stream(user_key)? {
+            FindStreamResult::WrongType => Ok(StreamCountResult::WrongType),
+            FindStreamResult::NotFound => Ok(StreamCountResult::Some(0)),
+            FindStreamResult::Some(stream) => Ok(StreamCountResult::Some(stream.len() as usize)),
+        }
+    }
+
+    /// Return the ID of the last entry that was added to the stream
+    pub fn last_id(&self, user_key: &BytesMut) -> Result<StreamLastIdResult, SableError> {
+        match self.find_stream(user_key)? {
+            FindStreamResult::WrongType => Ok(StreamLastIdResult::WrongType),
+            FindStreamResult::NotFound => Ok(StreamLastIdResult::NotFound),
+            FindStreamResult::Some(stream) => Ok(StreamLastIdResult::Some(*stream.last_id())),
+        }
+    }
+
+    /// Create the consumer group `group`. `last_id` is the last ID delivered to the group,
+    /// `None` means the stream last ID (`$`). If the stream does not exist, it is created when
+    /// `mkstream` is `true`. Returns `Some(false)` if the group already exists
+    pub fn create_group(
+        &mut self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+        last_id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    ) -> Result<StreamGroupResult<bool>, SableError> {
+        let stream = match self.find_stream(user_key)? {
+            FindStreamResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamResult::NotFound if !mkstream => return Ok(StreamGroupResult::NoStream),
+            FindStreamResult::NotFound => {
+                let stream = self.new_stream(user_key);
+                self.put_bookkeeping_record(user_key, &stream.metadata)?;
+                self.put_stream_metadata(user_key, &stream.metadata)?;
+                stream
+            }
+            FindStreamResult::Some(stream) => stream,
+        };
+
+        if self.find_group(&stream, group)?.is_some() {
+            return Ok(StreamGroupResult::Some(false));
+        }
+
+        let entries_read = match (entries_read, last_id) {
+            (Some(entries_read), _) => entries_read,
+            (None, None) => stream.metadata.entries_added(),
+            (None, Some(_)) => 0,
+        };
+
+        let group_md = StreamGroupMetadata::new(
+            self.store.generate_id(),
+            last_id.unwrap_or(*stream.last_id()),
+            entries_read,
+        );
+        self.put_group(&stream, group, &group_md)?;
+        Ok(StreamGroupResult::Some(true))
+    }
+
+    /// Destroy the consumer group `group` together with its PEL and consumers.
+    /// Returns `Some(false)` if there is no such group
+    pub fn destroy_group(
+        &mut self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+    ) -> Result<StreamGroupResult<bool>, SableError> {
+        let (stream, group_md) = match self.find_stream_group(user_key, group)? {
+            FindStreamGroupResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamGroupResult::NoStream => return Ok(StreamGroupResult::NoStream),
+            FindStreamGroupResult::NoGroup => return Ok(StreamGroupResult::Some(false)),
+            FindStreamGroupResult::Some(stream, group_md) => (stream, group_md),
+        };
+
+        self.delete_by_prefix(&stream.pending_prefix(group_md.id()))?;
+        self.delete_by_prefix(&stream.consumer_prefix(group_md.id()))?;
+        self.cache.delete(&stream.group_key(group))?;
+        Ok(StreamGroupResult::Some(true))
+    }
+
+    /// Set the last delivered ID of the group. `None` means the stream last ID (`$`)
+    pub fn set_group_id(
+        &mut self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+        last_id: Option<StreamId>,
+        entries_read: Option<u64>,
+    ) -> Result<StreamGroupResult<()>, SableError> {
+        let (stream, mut group_md) = match self.find_stream_group(user_key, group)? {
+            FindStreamGroupResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamGroupResult::NoStream => return Ok(StreamGroupResult::NoStream),
+            FindStreamGroupResult::NoGroup => return Ok(StreamGroupResult::NoGroup),
+            FindStreamGroupResult::Some(stream, group_md) => (stream, group_md),
+        };
+
+        group_md.set_last_delivered_id(last_id.unwrap_or(*stream.last_id()));
+        if let Some(entries_read) = entries_read {
+            group_md.set_entries_read(entries_read);
+        }
+        self.put_group(&stream, group, &group_md)?;
+        Ok(StreamGroupResult::Some(()))
+    }
+
+    /// Create the consumer `consumer` in the group. Returns `Some(false)` if the consumer
+    /// already exists
+    pub fn create_consumer(
+        &mut self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+        consumer: &BytesMut,
+    ) -> Result<StreamGroupResult<bool>, SableError> {
+        let (stream, group_md) = match self.find_stream_group(user_key, group)? {
+            FindStreamGroupResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamGroupResult::NoStream => return Ok(StreamGroupResult::NoStream),
+            FindStreamGroupResult::NoGroup => return Ok(StreamGroupResult::NoGroup),
+            FindStreamGroupResult::Some(stream, group_md) => (stream, group_md),
+        };
+
+        if self
+            .cache
+            .contains(&stream.consumer_key(group_md.id(), consumer))?
+        {
+            return Ok(StreamGroupResult::Some(false));
+        }
+        self.touch_consumer(&stream, &group_md, consumer, false)?;
+        Ok(StreamGroupResult::Some(true))
+    }
+
+    /// Delete the consumer `consumer` from the group. The entries pending for this consumer are
+    /// removed from the PEL. Returns the number of pending entries the consumer had
+    pub fn delete_consumer(
+        &mut self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+        consumer: &BytesMut,
+    ) -> Result<StreamGroupResult<usize>, SableError> {
+        let (stream, group_md) = match self.find_stream_group(user_key, group)? {
+            FindStreamGroupResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamGroupResult::NoStream => return Ok(StreamGroupResult::NoStream),
+            FindStreamGroupResult::NoGroup => return Ok(StreamGroupResult::NoGroup),
+            FindStreamGroupResult::Some(stream, group_md) => (stream, group_md),
+        };
+
+        let mut pending_ids = Vec::<StreamId>::new();
+        self.for_each_pending(&stream, group_md.id(), StreamId::MIN, |id, pending| {
+            if pending.consumer().eq(consumer) {
+                pending_ids.push(id);
+            }
+            Ok(true)
+        })?;
+
+        for id in &pending_ids {
+            self.cache.delete(&stream.pending_key(group_md.id(), *id))?;
+        }
+        self.cache
+            .delete(&stream.consumer_key(group_md.id(), consumer))?;
+        Ok(StreamGroupResult::Some(pending_ids.len()))
+    }
+
+    /// Read entries on behalf of `consumer`. When `start` is `None` (`>`), deliver up to `count`
+    /// entries that were never delivered to the group and add them to the PEL (unless `noack`
+    /// is `true`). Otherwise, return the entries pending for `consumer` with ID greater than
+    /// `start`. A pending entry that was deleted from the stream is returned as `None`
+    pub fn read_group(
+        &mut self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+        consumer: &BytesMut,
+        start: Option<StreamId>,
+        count: Option<usize>,
+        noack: bool,
+    ) -> Result<StreamGroupResult<Vec<(StreamId, Option<StreamEntry>)>>, SableError> {
+        let (stream, mut group_md) = match self.find_stream_group(user_key, group)? {
+            FindStreamGroupResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamGroupResult::NoStream => return Ok(StreamGroupResult::NoStream),
+            FindStreamGroupResult::NoGroup => return Ok(StreamGroupResult::NoGroup),
+            FindStreamGroupResult::Some(stream, group_md) => (stream, group_md),
+        };
+
+        let count = count.unwrap_or(usize::MAX);
+        let entries: Vec<(StreamId, Option<StreamEntry>)> = match start {
+            None => {
+                let entries = match group_md.last_delivered_id().next() {
+                    Some(from) => self.stream_range(&stream, from, StreamId::MAX, count)?,
+                    None => Vec::default(),
+                };
+
+                if !noack {
+                    let now = current_time(CurrentTimeResolution::Milliseconds);
+                    for entry in &entries {
+                        // If the entry is already pending (e.g. after `XGROUP SETID`), it is
+                        // re-assigned to this consumer
+                        let pending = StreamPendingEntry::new(consumer, now, 1);
+                        self.cache.put(
+                            &stream.pending_key(group_md.id(), entry.id),
+                            pending.to_bytes(),
+                        )?;
+                    }
+                }
+
+                if let Some(last_entry) = entries.last() {
+                    group_md.set_last_delivered_id(last_entry.id);
+                    group_md.incr_entries_read_by(entries.len() as u64);
+                    self.put_group(&stream, group, &group_md)?;
+                }
+
+                entries
+                    .into_iter()
+                    .map(|entry| (entry.id, Some(entry)))
+                    .collect()
+            }
+            Some(start) => {
+                let mut pending_ids = Vec::<StreamId>::new();
+                if let Some(from) = start.next() {
+                    self.for_each_pending(&stream, group_md.id(), from, |id, pending| {
+                        if pending.consumer().eq(consumer) {
+                            pending_ids.push(id);
+                        }
+                        Ok(pending_ids.len() < count)
+                    })?;
+                }
+
+                let mut entries = Vec::with_capacity(pending_ids.len());
+                for id in pending_ids {
+                    entries.push((id, self.find_entry(&stream, id)?));
+                }
+                entries
+            }
+        };
+
+        // The consumer is active only when new entries are delivered to it
+        let active = start.is_none() && !entries.is_empty();
+        self.touch_consumer(&stream, &group_md, consumer, active)?;
+        Ok(StreamGroupResult::Some(entries))
+    }
+
+    /// Remove the entries identified by `ids` from the group PEL. Returns the number of
+    /// entries acknowledged
+    pub fn ack(
+        &mut self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+        ids: &[StreamId],
+    ) -> Result<StreamGroupResult<usize>, SableError> {
+        let (stream, group_md) = match self.find_stream_group(user_key, group)? {
+            FindStreamGroupResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamGroupResult::NoStream => return Ok(StreamGroupResult::NoStream),
+            FindStreamGroupResult::NoGroup => return Ok(StreamGroupResult::NoGroup),
+            FindStreamGroupResult::Some(stream, group_md) => (stream, group_md),
+        };
+
+        let mut acked = 0usize;
+        for id in ids {
+            let pending_key = stream.pending_key(group_md.id(), *id);
+            if self.cache.contains(&pending_key)? {
+                self.cache.delete(&pending_key)?;
+                acked = acked.saturating_add(1);
+            }
+        }
+        Ok(StreamGroupResult::Some(acked))
+    }
+
+    /// Return a summary of the group PEL
+    pub fn pending_summary(
+        &self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+    ) -> Result<StreamGroupResult<StreamPendingSummary>, SableError> {
+        let (stream, group_md) = match self.find_stream_group(user_key, group)? {
+            FindStreamGroupResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamGroupResult::NoStream => return Ok(StreamGroupResult::NoStream),
+            FindStreamGroupResult::NoGroup => return Ok(StreamGroupResult::NoGroup),
+            FindStreamGroupResult::Some(stream, group_md) => (stream, group_md),
+        };
+
+        let mut summary = StreamPendingSummary::default();
+        let mut consumers = BTreeMap::<BytesMut, usize>::new();
+        self.for_each_pending(&stream, group_md.id(), StreamId::MIN, |id, pending| {
+            summary.count = summary.count.saturating_add(1);
+            summary.range = match summary.range {
+                None => Some((id, id)),
+                Some((min_id, _)) => Some((min_id, id)),
+            };
+            let consumer_count = consumers.entry(pending.consumer().clone()).or_default();
+            *consumer_count = consumer_count.saturating_add(1);
+            Ok(true)
+        })?;
+        summary.consumers = consumers.into_iter().collect();
+        Ok(StreamGroupResult::Some(summary))
+    }
+
+    /// Return up to `count` pending entries with IDs in the range `[start, end]` (inclusive).
+    /// The output can be filtered by the minimum idle time and by the owning consumer
+    #[allow(clippy::too_many_arguments)]
+    pub fn pending_range(
+        &self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+        min_idle: Option<u64>,
+        consumer: Option<&BytesMut>,
+    ) -> Result<StreamGroupResult<Vec<(StreamId, StreamPendingEntry)>>, SableError> {
+        let (stream, group_md) = match self.find_stream_group(user_key, group)? {
+            FindStreamGroupResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamGroupResult::NoStream => return Ok(StreamGroupResult::NoStream),
+            FindStreamGroupResult::NoGroup => return Ok(StreamGroupResult::NoGroup),
+            FindStreamGroupResult::Some(stream, group_md) => (stream, group_md),
+        };
+
+        let mut items = Vec::<(StreamId, StreamPendingEntry)>::new();
+        if start > end || count == 0 {
+            return Ok(StreamGroupResult::Some(items));
+        }
+
+        let now = current_time(CurrentTimeResolution::Milliseconds);
+        self.for_each_pending(&stream, group_md.id(), start, |id, pending| {
+            if id > end {
+                return Ok(false);
+            }
+
+            let consumer_match = match consumer {
+                Some(consumer) => pending.consumer().eq(consumer),
+                None => true,
+            };
+            let idle_match = match min_idle {
+                Some(min_idle) => pending.idle(now) >= min_idle,
+                None => true,
+            };
+            if consumer_match && idle_match {
+                items.push((id, pending));
+            }
+            Ok(items.len() < count)
+        })?;
+        Ok(StreamGroupResult::Some(items))
+    }
+
+    /// Change the ownership of the pending entries `ids` to `consumer`. Only entries that are
+    /// idle for at least `min_idle` milliseconds are claimed. Pending entries that no longer
+    /// exist in the stream are removed from the PEL. Returns the claimed entries
+    pub fn claim(
+        &mut self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+        consumer: &BytesMut,
+        min_idle: u64,
+        ids: &[StreamId],
+        options: &StreamClaimOptions,
+    ) -> Result<StreamGroupResult<Vec<StreamEntry>>, SableError> {
+        let (stream, mut group_md) = match self.find_stream_group(user_key, group)? {
+            FindStreamGroupResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamGroupResult::NoStream => return Ok(StreamGroupResult::NoStream),
+            FindStreamGroupResult::NoGroup => return Ok(StreamGroupResult::NoGroup),
+            FindStreamGroupResult::Some(stream, group_md) => (stream, group_md),
+        };
+
+        if let Some(last_id) = options.last_id {
+            if last_id > *group_md.last_delivered_id() {
+                group_md.set_last_delivered_id(last_id);
+                self.put_group(&stream, group, &group_md)?;
+            }
+        }
+
+        let now = current_time(CurrentTimeResolution::Milliseconds);
+        let delivery_time = match (options.idle, options.time) {
+            (Some(idle), _) => now.saturating_sub(idle),
+            (None, Some(time)) => time,
+            (None, None) => now,
+        };
+
+        let mut claimed = Vec::<StreamEntry>::new();
+        for id in ids {
+            let pending_key = stream.pending_key(group_md.id(), *id);
+            let mut pending = match self.cache.get(&pending_key)? {
+                Some(value) => {
+                    let pending = StreamPendingEntry::from_bytes(&value)?;
+                    if pending.idle(now) < min_idle {
+                        continue;
+                    }
+                    pending
+                }
+                None if options.force => StreamPendingEntry::new(consumer, now, 0),
+                None => continue,
+            };
+
+            let Some(entry) = self.find_entry(&stream, *id)? else {
+                // The entry no longer exists in the stream
+                self.cache.delete(&pending_key)?;
+                continue;
+            };
+
+            pending.set_consumer(consumer);
+            pending.set_delivery_time(delivery_time);
+            match options.retry_count {
+                Some(retry_count) => pending.set_delivery_count(retry_count),
+                None if !options.justid => pending.incr_delivery_count(),
+                None => {}
+            }
+            self.cache.put(&pending_key, pending.to_bytes())?;
+            claimed.push(entry);
+        }
+
+        self.touch_consumer(&stream, &group_md, consumer, !claimed.is_empty())?;
+        Ok(StreamGroupResult::Some(claimed))
+    }
+
+    /// Scan the group PEL starting from `start` and claim up to `count` entries that are idle
+    /// for at least `min_idle` milliseconds. The scan examines at most
+    /// `count * STREAM_AUTOCLAIM_ATTEMPTS_FACTOR` pending entries
+    #[allow(clippy::too_many_arguments)]
+    pub fn autoclaim(
+        &mut self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+        consumer: &BytesMut,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        justid: bool,
+    ) -> Result<StreamGroupResult<StreamAutoClaimResult>, SableError> {
+        let (stream, group_md) = match self.find_stream_group(user_key, group)? {
+            FindStreamGroupResult::WrongType => return Ok(StreamGroupResult::WrongType),
+            FindStreamGroupResult::NoStream => return Ok(StreamGroupResult::NoStream),
+            FindStreamGroupResult::NoGroup => return Ok(StreamGroupResult::NoGroup),
+            FindStreamGroupResult::Some(stream, group_md) => (stream, group_md),
+        };
+
+        let now = current_time(CurrentTimeResolution::Milliseconds);
+        let mut attempts = count.saturating_mul(STREAM_AUTOCLAIM_ATTEMPTS_FACTOR);
+        let mut candidates = Vec::<(StreamId, StreamPendingEntry)>::new();
+        let mut result = StreamAutoClaimResult::default();
+        self.for_each_pending(&stream, group_md.id(), start, |id, pending| {
+            if attempts == 0 || candidates.len() == count {
+                // Stopped before the end of the PEL, the next call continues from here
+                result.next_id = id;
+                return Ok(false);
+            }
+            attempts = attempts.saturating_sub(1);
+            if pending.idle(now) >= min_idle {
+                candidates.push((id, pending));
+            }
+            Ok(true)
+        })?;
+
+        for (id, mut pending) in candidates {
+            let pending_key = stream.pending_key(group_md.id(), id);
+            let Some(entry) = self.find_entry(&stream, id)? else {
+                self.cache.delete(&pending_key)?;
+                result.deleted.push(id);
+                continue;
+            };
+
+            pending.set_consumer(consumer);
+            pending.set_delivery_time(now);
+            if !justid {
+                pending.incr_delivery_count();
+            }
+            self.cache.put(&pending_key, pending.to_bytes())?;
+            result.claimed.push(entry);
+        }
+
+        self.touch_consumer(&stream, &group_md, consumer, !result.claimed.is_empty())?;
+        Ok(StreamGroupResult::Some(result))
+    }
+
+    /// Read the stream `user_key`, including its consumer groups, for serialization (`DUMP`)
+    pub fn dump(&self, user_key: &BytesMut) -> Result<Option<RdbStream>, SableError> {
+        let FindStreamResult::Some(stream) = self.find_stream(user_key)? else {
+            return Ok(None);
+        };
+
+        let entries = self.stream_range(&stream, StreamId::MIN, StreamId::MAX, usize::MAX)?;
+        let mut groups = Vec::<RdbStreamGroup>::new();
+        let prefix = StreamGroupKey::prefix(stream.id(), stream.database_id(), stream.slot());
+        let mut db_iter = self.store.create_iterator(&prefix)?;
+        while db_iter.valid() {
+            let Some((key, value)) = db_iter.key_value() else {
+                break;
+            };
+
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let group_md = StreamGroupMetadata::from_bytes(value)?;
+            let mut pending = Vec::<RdbStreamPending>::new();
+            self.for_each_pending(&stream, group_md.id(), StreamId::MIN, |id, entry| {
+                pending.push(RdbStreamPending {
+                    id,
+                    consumer: entry.consumer().clone(),
+                    delivery_time: entry.delivery_time(),
+                    delivery_count: entry.delivery_count(),
+                });
+                Ok(true)
+            })?;
+
+            let mut consumers = Vec::<RdbStreamConsumer>::new();
+            let consumer_prefix = stream.consumer_prefix(group_md.id());
+            let mut consumers_iter = self.store.create_iterator(&consumer_prefix)?;
+            while consumers_iter.valid() {
+                let Some((key, value)) = consumers_iter.key_value() else {
+                    break;
+                };
+
+                if !key.starts_with(&consumer_prefix) {
+                    break;
+                }
+
+                let consumer_md = StreamConsumerMetadata::from_bytes(value)?;
+                consumers.push(RdbStreamConsumer {
+                    name: StreamConsumerKey::from_bytes(key)?.consumer_name().clone(),
+                    seen_time: consumer_md.seen_time(),
+                    active_time: consumer_md.active_time(),
+                });
+                consumers_iter.next();
+            }
+
+            groups.push(RdbStreamGroup {
+                name: StreamGroupKey::from_bytes(key)?.group_name().clone(),
+                last_delivered_id: *group_md.last_delivered_id(),
+                entries_read: group_md.entries_read(),
+                pending,
+                consumers,
+            });
+            db_iter.next();
+        }
+
+        Ok(Some(RdbStream {
+            entries: entries
+                .into_iter()
+                .map(|entry| RdbStreamEntry {
+                    id: entry.id,
+                    fields: entry.fields,
+                })
+                .collect(),
+            last_id: *stream.last_id(),
+            max_deleted_id: *stream.metadata.max_deleted_id(),
+            entries_added: stream.metadata.entries_added(),
+            groups,
+        }))
+    }
+
+    /// Create the stream `user_key` from a deserialized stream (`RESTORE`). The caller is
+    /// expected to delete any existing value first
+    pub fn restore(&mut self, user_key: &BytesMut, value: RdbStream) -> Result<(), SableError> {
+        let mut stream = self.new_stream(user_key);
+        stream.metadata.set_last_id(value.last_id);
+        stream.metadata.update_max_deleted_id(value.max_deleted_id);
+        stream.metadata.set_entries_added(value.entries_added);
+        stream.metadata.incr_len_by(value.entries.len() as u64);
+        self.put_bookkeeping_record(user_key, &stream.metadata)?;
+        self.put_stream_metadata(user_key, &stream.metadata)?;
+
+        for entry in value.entries {
+            let entry = StreamEntry {
+                id: entry.id,
+                fields: entry.fields,
+            };
+            self.cache
+                .put(&stream.entry_key(entry.id), entry.fields_to_bytes())?;
+        }
+
+        for group in value.groups {
+            let group_md = StreamGroupMetadata::new(
+                self.store.generate_id(),
+                group.last_delivered_id,
+                group.entries_read,
+            );
+            self.put_group(&stream, &group.name, &group_md)?;
+            for pending in group.pending {
+                let pending_entry = StreamPendingEntry::new(
+                    &pending.consumer,
+                    pending.delivery_time,
+                    pending.delivery_count,
+                );
+                self.cache.put(
+                    &stream.pending_key(group_md.id(), pending.id),
+                    pending_entry.to_bytes(),
+                )?;
+            }
+            for consumer in group.consumers {
+                self.cache.put(
+                    &stream.consumer_key(group_md.id(), &consumer.name),
+                    StreamConsumerMetadata::new(consumer.seen_time, consumer.active_time)
+                        .to_bytes(),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load stream value metadata from the store
+    pub fn find_stream(&self, user_key: &BytesMut) -> Result<FindStreamResult, SableError> {
+        let encoded_key = PrimaryKeyMetadata::new_primary_key(user_key, self.db_id);
+        let Some(value) = self.cache.get(&encoded_key)? else {
+            return Ok(FindStreamResult::NotFound);
+        };
+
+        match self.try_decode_stream_value_metadata(&value)? {
+            None => Ok(FindStreamResult::WrongType),
+            Some(md) => Ok(FindStreamResult::Some(Stream {
+                key: PrimaryKeyMetadata::new(user_key, self.db_id),
+                metadata: md,
+            })),
+        }
+    }
+
+    /// Apply cache changes to the disk
+    pub fn commit(&mut self) -> Result<(), SableError> {
+        self.flush_cache()
+    }
+
+    //=======================================================
+    // Internal API for this class
+    //=======================================================
+
+    /// Apply the changes to the store and clear the cache
+    fn flush_cache(&mut self) -> Result<(), SableError> {
+        self.cache.flush()
+    }
+
+    /// Construct a new, empty, stream
+    fn new_stream(&self, user_key: &BytesMut) -> Stream {
+        Stream {
+            key: PrimaryKeyMetadata::new(user_key, self.db_id),
+            metadata: StreamValueMetadata::with_id(self.store.generate_id()),
+        }
+    }
+
+    /// Return up to `count` entries with IDs in the range `[start, end]` (inclusive)
+    fn stream_range(
+        &self,
+        stream: &Stream,
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+    ) -> Result<Vec<StreamEntry>, SableError> {
+        let mut entries = Vec::<StreamEntry>::new();
+        if start > end || count == 0 {
+            return Ok(entries);
+        }
+
+        let prefix = stream.prefix();
+        let mut db_iter = self.store.create_iterator(&prefix)?;
+        db_iter.seek(&stream.entry_key(start));
+        while db_iter.valid() && entries.len() < count {
+            let Some((key, value)) = db_iter.key_value() else {
+                break;
+            };
+
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let entry = StreamEntry::from_bytes(key, value)?;
+            if entry.id > end {
+                break;
+            }
+            entries.push(entry);
+            db_iter.next();
+        }
+        Ok(entries)
+    }
+
+    /// Load a single entry from the stream
+    fn find_entry(&self, stream: &Stream, id: StreamId) -> Result<Option<StreamEntry>, SableError> {
+        let entry_key = stream.entry_key(id);
+        match self.cache.get(&entry_key)? {
+            Some(value) => Ok(Some(StreamEntry::from_bytes(&entry_key, &value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Load the stream and its consumer group `group`
+    fn find_stream_group(
+        &self,
+        user_key: &BytesMut,
+        group: &BytesMut,
+    ) -> Result<FindStreamGroupResult, SableError> {
+        let stream = match self.find_stream(user_key)? {
+            FindStreamResult::WrongType => return Ok(FindStreamGroupResult::WrongType),
+            FindStreamResult::NotFound => return Ok(FindStreamGroupResult::NoStream),
+            FindStreamResult::Some(stream) => stream,
+        };
+
+        match self.find_group(&stream, group)? {
+            Some(group_md) => Ok(FindStreamGroupResult::Some(stream, group_md)),
+            None => Ok(FindStreamGroupResult::NoGroup),
+        }
+    }
+
+    /// Load the consumer group metadata
+    fn find_group(
+        &self,
+        stream: &Stream,
+        group: &BytesMut,
+    ) -> Result<Option<StreamGroupMetadata>, SableError> {
+        match self.cache.get(&stream.group_key(group))? {
+            Some(value) => Ok(Some(StreamGroupMetadata::from_bytes(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Put the consumer group metadata in the database
+    fn put_group(
+        &mut self,
+        stream: &Stream,
+        group: &BytesMut,
+        group_md: &StreamGroupMetadata,
+    ) -> Result<(), SableError> {
+        self.cache
+            .put(&stream.group_key(group), group_md.to_bytes())
+    }
+
+    /// Update the seen time of the consumer (and its active time, if `active` is `true`).
+    /// The consumer is created if it does not exist
+    fn touch_consumer(
+        &mut self,
+        stream: &Stream,
+        group_md: &StreamGroupMetadata,
+        consumer: &BytesMut,
+        active: bool,
+    ) -> Result<(), SableError> {
+        let consumer_key = stream.consumer_key(group_md.id(), consumer);
+        let now = current_time(CurrentTimeResolution::Milliseconds);
+        let active_time = if active {
+            now
+        } else {
+            match self.cache.get(&consumer_key)? {
+                Some(value) => StreamConsumerMetadata::from_bytes(&value)?.active_time(),
+                None => 0,
+            }
+        };
+        self.cache.put(
+            &consumer_key,
+            StreamConsumerMetadata::new(now, active_time).to_bytes(),
+        )
+    }
+
+    /// Iterate over the group PEL, starting from `start`, and call `callback` for every pending
+    /// entry. The iteration stops when `callback` returns `false`
+    fn for_each_pending<F>(
+        &self,
+        stream: &Stream,
+        group_id: u64,
+        start: StreamId,
+        mut callback: F,
+    ) -> Result<(), SableError>
+    where
+        F: FnMut(StreamId, StreamPendingEntry) -> Result<bool, SableError>,
+    {
+        let prefix = stream.pending_prefix(group_id);
+        let mut db_iter = self.store.create_iterator(&prefix)?;
+        db_iter.seek(&stream.pending_key(group_id, start));
+        while db_iter.valid() {
+            let Some((key, value)) = db_iter.key_value() else {
+                break;
+            };
+
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let pending_key = StreamPendingKey::from_bytes(key)?;
+            if !callback(
+                *pending_key.entry_id(),
+                StreamPendingEntry::from_bytes(value)?,
+            )? {
+                break;
+            }
+            db_iter.next();
+        }
+        Ok(())
+    }
+
+    /// Delete all the records that start with `prefix`
+    fn delete_by_prefix(&mut self, prefix: &BytesMut) -> Result<(), SableError> {
+        let mut db_iter = self.store.create_iterator(prefix)?;
+        while db_iter.valid() {
+            let Some(key) = db_iter.key() else {
+                break;
+            };
+
+            if !key.starts_with(prefix) {
+                break;
+            }
+
+            self.cache.delete(&BytesMut::from(key))?;
+            db_iter.next();
+        }
+        Ok(())
+    }
+
+    /// Evict entries from the stream according to `trim`. `pending_id` is an entry that was added
+    /// to the cache (and therefore is not visible to the database iterator) and is always the
+    /// newest entry in the stream
+    fn trim_stream(
+        &mut self,
+        stream: &mut Stream,
+        trim: &StreamTrim,
+        pending_id: Option<StreamId>,
+    ) -> Result<usize, SableError> {
+        let limit = trim.limit.unwrap_or(u64::MAX);
+        let mut to_delete = match trim.strategy {
+            StreamTrimStrategy::MaxLen(maxlen) => stream.len().saturating_sub(maxlen),
+            StreamTrimStrategy::MinId(_) => u64::MAX,
+        }
+        .min(limit);
+
+        let is_trimmed = |id: &StreamId| match trim.strategy {
+            StreamTrimStrategy::MaxLen(_) => true,
+            StreamTrimStrategy::MinId(minid) => id < &minid,
+        };
+
+        let mut items_deleted = 0u64;
+        let prefix = stream.prefix();
+        let mut db_iter = self.store.create_iterator(&prefix)?;
+        while db_iter.valid() && to_delete > 0 {
+            let Some(key) = db_iter.key() else {
+                break;
+            };
+
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let entry_key = StreamEntryKey::from_bytes(key)?;
+            if !is_trimmed(entry_key.entry_id()) {
+                break;
+            }
+
+            self.cache.delete(&BytesMut::from(key))?;
+            items_deleted = items_deleted.saturating_add(1);
+            to_delete = to_delete.saturating_sub(1);
+            db_iter.next();
+        }
+
+        // The iterator does not see the pending entry
+        if let Some(pending_id) = pending_id {
+            if to_delete > 0 && is_trimmed(&pending_id) {
+                self.cache.delete(&stream.entry_key(pending_id))?;
+                items_deleted = items_deleted.saturating_add(1);
+            }
+        }
+
+        stream.metadata.decr_len_by(items_deleted);
+        Ok(items_deleted as usize)
+    }
+
+    /// Put a stream entry in the database
+    fn put_stream_metadata(
+        &mut self,
+        user_key: &BytesMut,
+        stream_md: &StreamValueMetadata,
+    ) -> Result<(), SableError> {
+        let encoded_key = PrimaryKeyMetadata::new_primary_key(user_key, self.db_id);
+
+        // serialise the stream value into bytes
+        let mut buffer = BytesMut::with_capacity(StreamValueMetadata::SIZE);
+        let mut builder = U8ArrayBuilder::with_buffer(&mut buffer);
+        stream_md.to_bytes(&mut builder);
+
+        self.cache.put(&encoded_key, buffer)?;
+        Ok(())
+    }
+
+    /// Add a bookkeeping record for the stream
+    fn put_bookkeeping_record(
+        &mut self,
+        user_key: &BytesMut,
+        stream_md: &StreamValueMetadata,
+    ) -> Result<(), SableError> {
+        let bookkeeping_record =
+            Bookkeeping::new(self.db_id, crate::utils::calculate_slot(user_key))
+                .with_uid(stream_md.id())
+                .with_value_type(ValueType::Stream)
+                .to_bytes();
+        self.cache.put(&bookkeeping_record, user_key.clone())?;
+        Ok(())
+    }
+
+    /// Given raw bytes (read from the db) return whether it represents a `StreamValueMetadata`
+    fn try_decode_stream_value_metadata(
+        &self,
+        value: &BytesMut,
+    ) -> Result<Option<StreamValueMetadata>, SableError> {
+        let mut reader = U8ArrayReader::with_buffer(value);
+        let common_md = CommonValueMetadata::from_bytes(&mut reader)?;
+        if !common_md.is_stream() {
+            return Ok(None);
+        }
+
+        reader.rewind();
+        let stream_md = StreamValueMetadata::from_bytes(&mut reader)?;
+        Ok(Some(stream_md))
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::PutFlags;
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(BytesMut, BytesMut)> {
+        pairs
+            .iter()
+            .map(|(f, v)| (BytesMut::from(*f), BytesMut::from(*v)))
+            .collect()
+    }
+
+    fn add(stream_db: &mut StreamDb, key: &BytesMut, ms: u64, seq: u64) -> StreamAddResult {
+        let res = stream_db
+            .add(
+                key,
+                StreamIdSpec::Explicit(StreamId::new(ms, seq)),
+                fields(&[("field", "value")]),
+                false,
+                None,
+            )
+            .unwrap();
+        stream_db.commit().unwrap();
+        res
+    }
+
+    fn range_ids(stream_db: &StreamDb, key: &BytesMut) -> Vec<StreamId> {
+        let StreamRangeResult::Some(entries) = stream_db
+            .range(key, StreamId::MIN, StreamId::MAX, None)
+            .unwrap()
+        else {
+            panic!("expected a stream");
+        };
+        entries.iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn test_stream_wrong_type() -> Result<(), SableError> {
+        let (_deleter, db) = crate::tests::open_store();
+        let mut stream_db = StreamDb::with_storage(&db, 0);
+        let mut strings_db = crate::storage::StringsDb::with_storage(&db, 0);
+
+        let string_md = crate::StringValueMetadata::default();
+        let key = BytesMut::from("key");
+        let value = BytesMut::from("value");
+        strings_db.put(&key, &value, &string_md, PutFlags::Override)?;
+
+        assert_eq!(stream_db.len(&key)?, StreamCountResult::WrongType);
+        assert_eq!(
+            stream_db.add(&key, StreamIdSpec::Auto, fields(&[("a", "b")]), false, None)?,
+            StreamAddResult::WrongType
+        );
+        assert_eq!(
+            stream_db.range(&key, StreamId::MIN, StreamId::MAX, None)?,
+            StreamRangeResult::WrongType
+        );
+        assert_eq!(
+            stream_db.delete(&key, &[StreamId::MIN])?,
+            StreamCountResult::WrongType
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_add_and_range() -> Result<(), SableError> {
+        let (_deleter, db) = crate::tests::open_store();
+        let mut stream_db = StreamDb::with_storage(&db, 0);
+        let key = BytesMut::from("mystream");
+
+        assert_eq!(
+            stream_db.add(&key, StreamIdSpec::Auto, fields(&[]), true, None)?,
+            StreamAddResult::NotFound
+        );
+        assert_eq!(add(&mut stream_db, &key, 0, 0), StreamAddResult::IdIsZero);
+        assert_eq!(
+            add(&mut stream_db, &key, 1, 1),
+            StreamAddResult::Some(StreamId::new(1, 1))
+        );
+        assert_eq!(add(&mut stream_db, &key, 1, 1), StreamAddResult::IdTooSmall);
+        assert_eq!(
+            stream_db.add(&key, StreamIdSpec::AutoSeq(1), fields(&[]), false, None)?,
+            StreamAddResult::Some(StreamId::new(1, 2))
+        );
+        stream_db.commit()?;
+        assert_eq!(
+            stream_db.add(&key, StreamIdSpec::AutoSeq(0), fields(&[]), false, None)?,
+            StreamAddResult::IdTooSmall
+        );
+        assert_eq!(
+            add(&mut stream_db, &key, 5, 0),
+            StreamAddResult::Some(StreamId::new(5, 0))
+        );
+        assert_eq!(stream_db.len(&key)?, StreamCountResult::Some(3));
+
+        // ranges are inclusive
+        let StreamRangeResult::Some(entries) =
+            stream_db.range(&key, StreamId::new(1, 2), StreamId::new(5, 0), Some(10))?
+        else {
+            panic!("expected a stream");
+        };
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, StreamId::new(1, 2));
+        assert_eq!(entries[1].id, StreamId::new(5, 0));
+        assert_eq!(entries[1].fields, fields(&[("field", "value")]));
+
+        let StreamRangeResult::Some(entries) =
+            stream_db.range(&key, StreamId::MIN, StreamId::MAX, Some(1))?
+        else {
+            panic!("expected a stream");
+        };
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, StreamId::new(1, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_delete_and_trim() -> Result<(), SableError> {
+        let (_deleter, db) = crate::tests::open_store();
+        let mut stream_db = StreamDb::with_storage(&db, 0);
+        let key = BytesMut::from("mystream");
+
+        for ms in 1..=10 {
+            add(&mut stream_db, &key, ms, 0);
+        }
+
+        assert_eq!(
+            stream_db.delete(&key, &[StreamId::new(3, 0), StreamId::new(30, 0)])?,
+            StreamCountResult::Some(1)
+        );
+        stream_db.commit()?;
+        assert_eq!(stream_db.len(&key)?, StreamCountResult::Some(9));
+
+        // MINID: evict 1-0 and 2-0
+        let trim = StreamTrim {
+            strategy: StreamTrimStrategy::MinId(StreamId::new(3, 0)),
+            limit: None,
+        };
+        assert_eq!(stream_db.trim(&key, &trim)?, StreamCountResult::Some(2));
+        stream_db.commit()?;
+
+        // MAXLEN with LIMIT
+        let trim = StreamTrim {
+            strategy: StreamTrimStrategy::MaxLen(2),
+            limit: Some(3),
+        };
+        assert_eq!(stream_db.trim(&key, &trim)?, StreamCountResult::Some(3));
+        stream_db.commit()?;
+        assert_eq!(
+            range_ids(&stream_db, &key),
+            vec![
+                StreamId::new(8, 0),
+                StreamId::new(9, 0),
+                StreamId::new(10, 0)
+            ]
+        );
+
+        // XADD with MAXLEN 2 keeps the new entry
+        let trim = StreamTrim {
+            strategy: StreamTrimStrategy::MaxLen(2),
+            limit: None,
+        };
+        stream_db.add(
+            &key,
+            StreamIdSpec::Explicit(StreamId::new(11, 0)),
+            fields(&[]),
+            false,
+            Some(trim),
+        )?;
+        stream_db.commit()?;
+        assert_eq!(
+            range_ids(&stream_db, &key),
+            vec![StreamId::new(10, 0), StreamId::new(11, 0)]
+        );
+
+        // XADD with MAXLEN 0 keeps nothing, but the last ID is updated
+        let trim = StreamTrim {
+            strategy: StreamTrimStrategy::MaxLen(0),
+            limit: None,
+        };
+        stream_db.add(
+            &key,
+            StreamIdSpec::Explicit(StreamId::new(12, 0)),
+            fields(&[]),
+            false,
+            Some(trim),
+        )?;
+        stream_db.commit()?;
+        assert!(range_ids(&stream_db, &key).is_empty());
+        assert_eq!(stream_db.len(&key)?, StreamCountResult::Some(0));
+        assert_eq!(
+            stream_db.last_id(&key)?,
+            StreamLastIdResult::Some(StreamId::new(12, 0))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_groups() -> Result<(), SableError> {
+        let (_deleter, db) = crate::tests::open_store();
+        let mut stream_db = StreamDb::with_storage(&db, 0);
+        let key = BytesMut::from("mystream");
+        let group = BytesMut::from("mygroup");
+        let alice = BytesMut::from("alice");
+        let bob = BytesMut::from("bob");
+
+        assert_eq!(
+            stream_db.create_group(&key, &group, None, false, None)?,
+            StreamGroupResult::NoStream
+        );
+        assert_eq!(
+            stream_db.create_group(&key, &group, Some(StreamId::MIN), true, None)?,
+            StreamGroupResult::Some(true)
+        );
+        stream_db.commit()?;
+        assert_eq!(
+            stream_db.create_group(&key, &group, None, false, None)?,
+            StreamGroupResult::Some(false)
+        );
+        assert_eq!(stream_db.len(&key)?, StreamCountResult::Some(0));
+
+        for ms in 1..=3 {
+            add(&mut stream_db, &key, ms, 0);
+        }
+
+        // `>` delivers new entries and adds them to the PEL
+        let StreamGroupResult::Some(entries) =
+            stream_db.read_group(&key, &group, &alice, None, Some(2), false)?
+        else {
+            panic!("expected entries");
+        };
+        stream_db.commit()?;
+        assert_eq!(
+            entries.iter().map(|(id, _)| *id).collect::<Vec<StreamId>>(),
+            vec![StreamId::new(1, 0), StreamId::new(2, 0)]
+        );
+
+        let StreamGroupResult::Some(entries) =
+            stream_db.read_group(&key, &group, &bob, None, None, false)?
+        else {
+            panic!("expected entries");
+        };
+        stream_db.commit()?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, StreamId::new(3, 0));
+
+        let StreamGroupResult::Some(summary) = stream_db.pending_summary(&key, &group)? else {
+            panic!("expected a summary");
+        };
+        assert_eq!(summary.count, 3);
+        assert_eq!(
+            summary.range,
+            Some((StreamId::new(1, 0), StreamId::new(3, 0)))
+        );
+        assert_eq!(
+            summary.consumers,
+            vec![(alice.clone(), 2), (bob.clone(), 1)]
+        );
+
+        // A history read returns only the entries pending for the consumer
+        assert_eq!(
+            stream_db.ack(&key, &group, &[StreamId::new(1, 0), StreamId::new(9, 0)])?,
+            StreamGroupResult::Some(1)
+        );
+        stream_db.commit()?;
+        let StreamGroupResult::Some(entries) =
+            stream_db.read_group(&key, &group, &alice, Some(StreamId::MIN), None, false)?
+        else {
+            panic!("expected entries");
+        };
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, StreamId::new(2, 0));
+
+        // Claim a deleted entry: it is removed from the PEL
+        stream_db.delete(&key, &[StreamId::new(3, 0)])?;
+        stream_db.commit()?;
+        let StreamGroupResult::Some(claimed) = stream_db.claim(
+            &key,
+            &group,
+            &bob,
+            0,
+            &[StreamId::new(2, 0), StreamId::new(3, 0)],
+            &StreamClaimOptions::default(),
+        )?
+        else {
+            panic!("expected claimed entries");
+        };
+        stream_db.commit()?;
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, StreamId::new(2, 0));
+
+        let StreamGroupResult::Some(items) =
+            stream_db.pending_range(&key, &group, StreamId::MIN, StreamId::MAX, 10, None, None)?
+        else {
+            panic!("expected pending entries");
+        };
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].0, StreamId::new(2, 0));
+        assert_eq!(items[0].1.consumer(), &bob);
+        assert_eq!(items[0].1.delivery_count(), 2);
+
+        // Auto claim everything back to alice
+        let StreamGroupResult::Some(result) =
+            stream_db.autoclaim(&key, &group, &alice, 0, StreamId::MIN, 10, false)?
+        else {
+            panic!("expected claimed entries");
+        };
+        stream_db.commit()?;
+        assert_eq!(result.next_id, StreamId::MIN);
+        assert_eq!(result.claimed.len(), 1);
+        assert!(result.deleted.is_empty());
+
+        assert_eq!(
+            stream_db.delete_consumer(&key, &group, &alice)?,
+            StreamGroupResult::Some(1)
+        );
+        stream_db.commit()?;
+        assert_eq!(
+            stream_db.destroy_group(&key, &group)?,
+            StreamGroupResult::Some(true)
+        );
+        stream_db.commit()?;
+        assert_eq!(
+            stream_db.read_group(&key, &group, &alice, None, None, false)?,
+            StreamGroupResult::NoGroup
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bookkeeping_record() {
+        let (_deleter, db) = crate::tests::open_store();
+        let mut stream_db = StreamDb::with_storage(&db, 0);
+        let key = BytesMut::from("mystream");
+        add(&mut stream_db, &key, 1, 0);
+
+        let stream_id = match stream_db.find_stream(&key).unwrap() {
+            FindStreamResult::Some(stream) => stream.id(),
+            _ => {
+                panic!("Expected to find the stream MD in the database");
+            }
+        };
+
+        let bookkeeping_record_key = Bookkeeping::new(0, crate::utils::calculate_slot(&key))
+            .with_uid(stream_id)
+            .with_value_type(ValueType::Stream)
+            .to_bytes();
+
+        let db_stream_name = db.get(&bookkeeping_record_key).unwrap().unwrap();
+        assert_eq!(db_stream_name, key);
+    }
+}
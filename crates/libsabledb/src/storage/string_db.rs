@@ -1,5 +1,5 @@
 use crate::{
-    storage::{DbWriteCache, PutFlags, StorageAdapter},
+    storage::{BitmapDb, BitmapReadResult, DbWriteCache, PutFlags, StorageAdapter},
     PrimaryKeyMetadata, SableError, StringValueMetadata, U8ArrayBuilder, U8ArrayReader,
 };
 use bytes::{Buf, BytesMut};
@@ -112,21 +112,41 @@ impl<'a> StringsDb<'a> {
         if let Some(mut value) = raw_value {
             let mut reader = U8ArrayReader::with_buffer(&value);
             let md = StringValueMetadata::from_bytes(&mut reader)?;
+            let metadata_len = reader.consumed();
 
             // Not a string
-            if !md.common_metadata().is_string() {
+            if !md.common_metadata().is_string() && !md.common_metadata().is_bitmap() {
                 return Ok(StringGetResult::WrongType);
             }
 
             if md.expiration().is_expired()? {
                 self.cache.delete(&internal_key)?;
                 Ok(StringGetResult::None)
+            } else if md.common_metadata().is_bitmap() {
+                self.get_bitmap_internal(user_key, &md)
             } else {
-                value.advance(StringValueMetadata::SIZE);
+                value.advance(metadata_len);
                 Ok(StringGetResult::Some((value, md)))
             }
         } else {
             Ok(StringGetResult::None)
         }
     }
+
+    /// Bitmaps are strings stored in chunks: read the entire bitmap and return it as a string
+    fn get_bitmap_internal(
+        &self,
+        user_key: &BytesMut,
+        md: &StringValueMetadata,
+    ) -> Result<StringGetResult, SableError> {
+        let bitmap_db = BitmapDb::with_storage(self.store, self.db_id);
+        let BitmapReadResult::Some(value) = bitmap_db.read(user_key, 0, u64::MAX)? else {
+            return Ok(StringGetResult::WrongType);
+        };
+
+        // Writing back the returned metadata stores the value as a plain string
+        let mut string_md = StringValueMetadata::new();
+        *string_md.expiration_mut() = md.expiration().clone();
+        Ok(StringGetResult::Some((value, string_md)))
+    }
 }
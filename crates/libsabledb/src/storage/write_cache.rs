@@ -1,4 +1,4 @@
-use crate::{storage::BatchUpdate, storage::PutFlags, SableError, StorageAdapter};
+use crate::{storage::BatchUpdate, storage::PutFlags, SableError, StorageAdapter, TtlIndexKey};
 use bytes::BytesMut;
 use std::collections::HashMap;
 use std::rc::Rc;
@@ -47,9 +47,7 @@ impl<'a> DbWriteCache<'a> {
     }
 
     pub fn put(&mut self, key: &BytesMut, value: BytesMut) -> Result<(), SableError> {
-        let entry = Rc::new(DbCacheEntry::new(value, PutFlags::Override));
-        let _ = self.changes.insert(key.clone(), Some(entry));
-        Ok(())
+        self.put_flags(key, value, PutFlags::Override)
     }
 
     pub fn put_flags(
@@ -58,11 +56,21 @@ impl<'a> DbWriteCache<'a> {
         value: BytesMut,
         flags: PutFlags,
     ) -> Result<(), SableError> {
+        self.index_expiration(key, &value);
         let entry = Rc::new(DbCacheEntry::new(value, flags));
         let _ = self.changes.insert(key.clone(), Some(entry));
         Ok(())
     }
 
+    /// If `key` is a primary key with a TTL, add its expiration index entry to the cache so both
+    /// records are written in the same batch
+    fn index_expiration(&mut self, key: &BytesMut, value: &BytesMut) {
+        if let Some(index_key) = TtlIndexKey::for_record(key, value) {
+            let entry = Rc::new(DbCacheEntry::new(BytesMut::new(), PutFlags::Override));
+            let _ = self.changes.insert(index_key.to_bytes(), Some(entry));
+        }
+    }
+
     /// Note that we do not remove the entry from the `changes` hash,
     /// instead we use a `None` marker to indicate that this entry
     /// should be converted into a `delete` operation
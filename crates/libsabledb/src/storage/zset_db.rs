@@ -375,6 +375,40 @@ impl<'a> ZSetDb<'a> {
         Ok(ZSetDeleteMemberResult::Ok)
     }
 
+    /// Return the members of `set` with a score in the range `[min, max)`, ordered by score.
+    /// Changes that were not committed yet are not visible to this function
+    pub fn members_by_score_range(
+        &self,
+        set: &SortedSet,
+        min: f64,
+        max: f64,
+    ) -> Result<Vec<(BytesMut, f64)>, SableError> {
+        let set_prefix = set.prefix_by_score(None);
+        let mut members = Vec::<(BytesMut, f64)>::new();
+
+        // place the iterator on the range start
+        let mut db_iter = self
+            .store
+            .create_iterator(&set.prefix_by_score(Some(min)))?;
+        while db_iter.valid() {
+            let Some((key, _)) = db_iter.key_value() else {
+                break;
+            };
+
+            if !key.starts_with(&set_prefix) {
+                break;
+            }
+
+            let item = ZSetScoreItem::from_bytes(key)?;
+            if item.score() >= max {
+                break;
+            }
+            members.push((BytesMut::from(item.member()), item.score()));
+            db_iter.next();
+        }
+        Ok(members)
+    }
+
     //=== ----------------------------
     // Private methods
     //=== ----------------------------
@@ -844,4 +878,33 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_members_by_score_range() -> Result<(), SableError> {
+        let (_deleter, db) = crate::tests::open_store();
+        let mut zset_db = ZSetDb::with_storage(&db, 0);
+        let set_name = BytesMut::from("myset");
+        for (member, score) in [("a", 1.0), ("b", 2.0), ("c", 3.0), ("d", 4.0)] {
+            zset_db.add(
+                &set_name,
+                &BytesMut::from(member),
+                score,
+                &ZWriteFlags::None,
+                false,
+            )?;
+        }
+        zset_db.commit()?;
+
+        let FindZSetResult::Some(set) = zset_db.find_set(&set_name)? else {
+            panic!("Expected to find the set MD in the database");
+        };
+
+        // the upper bound is excluded
+        assert_eq!(
+            zset_db.members_by_score_range(&set, 2.0, 4.0)?,
+            vec![(BytesMut::from("b"), 2.0), (BytesMut::from("c"), 3.0)]
+        );
+        assert!(zset_db.members_by_score_range(&set, 5.0, 10.0)?.is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,495 @@
+//! Geohash encoding, as used by the `GEO*` commands.
+//!
+//! A location is stored as a member of a sorted set, its score is the 52 bit interleaved geohash
+//! of its coordinates (26 bits for the longitude and 26 bits for the latitude). The encoding is
+//! identical to the one used by Valkey, so the scores are interchangeable
+
+/// Number of bits per coordinate
+pub const GEO_STEP_MAX: u8 = 26;
+pub const GEO_LAT_MIN: f64 = -85.05112878;
+pub const GEO_LAT_MAX: f64 = 85.05112878;
+pub const GEO_LONG_MIN: f64 = -180.0;
+pub const GEO_LONG_MAX: f64 = 180.0;
+
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+const MERCATOR_MAX: f64 = 20037726.37;
+const GEOHASH_ALPHABET: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GeoHashBits {
+    pub bits: u64,
+    pub step: u8,
+}
+
+impl GeoHashBits {
+    /// An empty cell, used to mark a neighbour that should not be searched
+    pub fn is_zero(&self) -> bool {
+        self.bits == 0 && self.step == 0
+    }
+
+    /// Return the range of scores `[min, max)` covered by this cell
+    pub fn score_range(&self) -> (u64, u64) {
+        let shift = (GEO_STEP_MAX as u32 - self.step as u32) * 2;
+        (self.bits << shift, (self.bits + 1) << shift)
+    }
+
+    fn move_x(&mut self, d: i8) {
+        if d == 0 {
+            return;
+        }
+        let mut x = self.bits & 0xaaaaaaaaaaaaaaaa;
+        let y = self.bits & 0x5555555555555555;
+        let zz = 0x5555555555555555u64 >> (64 - self.step as u32 * 2);
+        if d > 0 {
+            x = x.wrapping_add(zz + 1);
+        } else {
+            x |= zz;
+            x = x.wrapping_sub(zz + 1);
+        }
+        x &= 0xaaaaaaaaaaaaaaaau64 >> (64 - self.step as u32 * 2);
+        self.bits = x | y;
+    }
+
+    fn move_y(&mut self, d: i8) {
+        if d == 0 {
+            return;
+        }
+        let x = self.bits & 0xaaaaaaaaaaaaaaaa;
+        let mut y = self.bits & 0x5555555555555555;
+        let zz = 0xaaaaaaaaaaaaaaaau64 >> (64 - self.step as u32 * 2);
+        if d > 0 {
+            y = y.wrapping_add(zz + 1);
+        } else {
+            y |= zz;
+            y = y.wrapping_sub(zz + 1);
+        }
+        y &= 0x5555555555555555u64 >> (64 - self.step as u32 * 2);
+        self.bits = x | y;
+    }
+
+    fn moved(&self, dx: i8, dy: i8) -> Self {
+        let mut cell = *self;
+        cell.move_x(dx);
+        cell.move_y(dy);
+        cell
+    }
+}
+
+/// The area covered by a geohash cell
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GeoHashArea {
+    pub longitude: (f64, f64),
+    pub latitude: (f64, f64),
+}
+
+/// The shape used by `GEOSEARCH`. All the sizes are in meters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+pub struct GeoHash {}
+
+impl GeoHash {
+    /// Return `true` if the coordinates can be indexed
+    pub fn is_valid(longitude: f64, latitude: f64) -> bool {
+        (GEO_LONG_MIN..=GEO_LONG_MAX).contains(&longitude)
+            && (GEO_LAT_MIN..=GEO_LAT_MAX).contains(&latitude)
+    }
+
+    /// Encode the coordinates into a geohash with `step` bits per coordinate
+    pub fn encode(longitude: f64, latitude: f64, step: u8) -> Option<GeoHashBits> {
+        Self::encode_with_range(
+            (GEO_LONG_MIN, GEO_LONG_MAX),
+            (GEO_LAT_MIN, GEO_LAT_MAX),
+            longitude,
+            latitude,
+            step,
+        )
+    }
+
+    /// Encode the coordinates into the score stored in the sorted set
+    pub fn encode_score(longitude: f64, latitude: f64) -> Option<f64> {
+        Self::encode(longitude, latitude, GEO_STEP_MAX).map(|hash| hash.bits as f64)
+    }
+
+    /// Decode a sorted set score into `(longitude, latitude)`
+    pub fn decode_score(score: f64) -> (f64, f64) {
+        Self::decode_center(&GeoHashBits {
+            bits: score as u64,
+            step: GEO_STEP_MAX,
+        })
+    }
+
+    /// Return the area covered by `hash`
+    pub fn decode(hash: &GeoHashBits) -> GeoHashArea {
+        Self::decode_with_range(
+            (GEO_LONG_MIN, GEO_LONG_MAX),
+            (GEO_LAT_MIN, GEO_LAT_MAX),
+            hash,
+        )
+    }
+
+    /// Return the standard 11 characters geohash string of a sorted set score. Unlike the
+    /// score, the string uses the standard latitude range of `[-90, 90]`
+    pub fn to_geohash_string(score: f64) -> String {
+        let (longitude, latitude) = Self::decode_score(score);
+        let hash = Self::encode_with_range(
+            (-180.0, 180.0),
+            (-90.0, 90.0),
+            longitude,
+            latitude,
+            GEO_STEP_MAX,
+        )
+        .unwrap_or_default();
+
+        (0..11)
+            .map(|i| {
+                let idx = if i == 10 {
+                    // Only 52 bits are available, the last character is padding
+                    0
+                } else {
+                    (hash.bits >> (52 - ((i + 1) * 5))) & 0x1f
+                };
+                GEOHASH_ALPHABET[idx as usize] as char
+            })
+            .collect()
+    }
+
+    /// Distance in meters between two points
+    pub fn distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+        let lat1r = lat1.to_radians();
+        let lon1r = lon1.to_radians();
+        let lat2r = lat2.to_radians();
+        let lon2r = lon2.to_radians();
+        let v = ((lon2r - lon1r) / 2.0).sin();
+        if v == 0.0 {
+            return Self::latitude_distance(lat1, lat2);
+        }
+        let u = ((lat2r - lat1r) / 2.0).sin();
+        let a = u * u + lat1r.cos() * lat2r.cos() * v * v;
+        2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+    }
+
+    /// If the point `(longitude, latitude)` is inside `shape` centred at
+    /// `(center_lon, center_lat)`, return its distance from the center (in meters)
+    pub fn distance_if_in_shape(
+        shape: &GeoShape,
+        center_lon: f64,
+        center_lat: f64,
+        longitude: f64,
+        latitude: f64,
+    ) -> Option<f64> {
+        match shape {
+            GeoShape::Radius(radius) => {
+                let distance = Self::distance(center_lon, center_lat, longitude, latitude);
+                (distance <= *radius).then_some(distance)
+            }
+            GeoShape::Box { width, height } => {
+                if Self::latitude_distance(latitude, center_lat) > height / 2.0 {
+                    return None;
+                }
+                if Self::distance(longitude, latitude, center_lon, latitude) > width / 2.0 {
+                    return None;
+                }
+                Some(Self::distance(center_lon, center_lat, longitude, latitude))
+            }
+        }
+    }
+
+    /// Return the geohash cells that must be scanned in order to find all the points within
+    /// `shape`: the cell containing the center and its 8 neighbours (cells that can not
+    /// contain matching points are omitted)
+    pub fn search_areas(shape: &GeoShape, longitude: f64, latitude: f64) -> Vec<GeoHashBits> {
+        let radius = match shape {
+            GeoShape::Radius(radius) => *radius,
+            GeoShape::Box { width, height } => {
+                ((width / 2.0) * (width / 2.0) + (height / 2.0) * (height / 2.0)).sqrt()
+            }
+        };
+
+        let (min_lon, min_lat, max_lon, max_lat) = Self::bounding_box(shape, longitude, latitude);
+        let mut steps = Self::estimate_steps_by_radius(radius, latitude);
+        let Some(mut hash) = Self::encode(longitude, latitude, steps) else {
+            return Vec::default();
+        };
+        let mut neighbours = Self::neighbours(&hash);
+        let mut area = Self::decode(&hash);
+
+        // Check whether the neighbours cover the entire bounding box, if not, use larger cells
+        let north = Self::decode(&neighbours[0]);
+        let south = Self::decode(&neighbours[1]);
+        let east = Self::decode(&neighbours[2]);
+        let west = Self::decode(&neighbours[3]);
+        let decrease_step = north.latitude.1 < max_lat
+            || south.latitude.0 > min_lat
+            || east.longitude.1 < max_lon
+            || west.longitude.0 > min_lon;
+
+        if steps > 1 && decrease_step {
+            steps -= 1;
+            let Some(larger_hash) = Self::encode(longitude, latitude, steps) else {
+                return Vec::default();
+            };
+            hash = larger_hash;
+            neighbours = Self::neighbours(&hash);
+            area = Self::decode(&hash);
+        }
+
+        // Exclude the neighbours that are outside of the bounding box
+        // neighbours: [north, south, east, west, north_east, north_west, south_east, south_west]
+        if steps >= 2 {
+            let mut exclude = |indices: &[usize]| {
+                for idx in indices {
+                    neighbours[*idx] = GeoHashBits::default();
+                }
+            };
+            if area.latitude.0 < min_lat {
+                exclude(&[1, 6, 7]);
+            }
+            if area.latitude.1 > max_lat {
+                exclude(&[0, 4, 5]);
+            }
+            if area.longitude.0 < min_lon {
+                exclude(&[3, 5, 7]);
+            }
+            if area.longitude.1 > max_lon {
+                exclude(&[2, 4, 6]);
+            }
+        }
+
+        let mut areas = vec![hash];
+        for neighbour in neighbours {
+            if !neighbour.is_zero() && !areas.contains(&neighbour) {
+                areas.push(neighbour);
+            }
+        }
+        areas
+    }
+
+    fn latitude_distance(lat1: f64, lat2: f64) -> f64 {
+        EARTH_RADIUS_IN_METERS * (lat2.to_radians() - lat1.to_radians()).abs()
+    }
+
+    /// Return the neighbours of `hash`:
+    /// `[north, south, east, west, north_east, north_west, south_east, south_west]`
+    fn neighbours(hash: &GeoHashBits) -> [GeoHashBits; 8] {
+        [
+            hash.moved(0, 1),
+            hash.moved(0, -1),
+            hash.moved(1, 0),
+            hash.moved(-1, 0),
+            hash.moved(1, 1),
+            hash.moved(-1, 1),
+            hash.moved(1, -1),
+            hash.moved(-1, -1),
+        ]
+    }
+
+    /// Return the bounding box of `shape`: `(min_lon, min_lat, max_lon, max_lat)`
+    fn bounding_box(shape: &GeoShape, longitude: f64, latitude: f64) -> (f64, f64, f64, f64) {
+        let (width, height) = match shape {
+            GeoShape::Radius(radius) => (*radius, *radius),
+            GeoShape::Box { width, height } => (width / 2.0, height / 2.0),
+        };
+
+        let lat_delta = (height / EARTH_RADIUS_IN_METERS).to_degrees();
+        let long_delta_top =
+            (width / EARTH_RADIUS_IN_METERS / (latitude + lat_delta).to_radians().cos())
+                .to_degrees();
+        let long_delta_bottom =
+            (width / EARTH_RADIUS_IN_METERS / (latitude - lat_delta).to_radians().cos())
+                .to_degrees();
+
+        // The box is wider at the side closer to the equator
+        let long_delta = if latitude < 0.0 {
+            long_delta_bottom
+        } else {
+            long_delta_top
+        };
+        (
+            longitude - long_delta,
+            latitude - lat_delta,
+            longitude + long_delta,
+            latitude + lat_delta,
+        )
+    }
+
+    /// Return the number of bits per coordinate needed to cover `range_meters`
+    fn estimate_steps_by_radius(range_meters: f64, latitude: f64) -> u8 {
+        if range_meters == 0.0 {
+            return GEO_STEP_MAX;
+        }
+
+        let mut range_meters = range_meters;
+        let mut step = 1i32;
+        while range_meters < MERCATOR_MAX {
+            range_meters *= 2.0;
+            step += 1;
+        }
+        // Make sure the range is included in most of the base cases
+        step -= 2;
+
+        // Wider range towards the poles
+        if !(-66.0..=66.0).contains(&latitude) {
+            step -= 1;
+            if !(-80.0..=80.0).contains(&latitude) {
+                step -= 1;
+            }
+        }
+        step.clamp(1, GEO_STEP_MAX as i32) as u8
+    }
+
+    fn encode_with_range(
+        long_range: (f64, f64),
+        lat_range: (f64, f64),
+        longitude: f64,
+        latitude: f64,
+        step: u8,
+    ) -> Option<GeoHashBits> {
+        if step > 32
+            || step == 0
+            || !(long_range.0..=long_range.1).contains(&longitude)
+            || !(lat_range.0..=lat_range.1).contains(&latitude)
+        {
+            return None;
+        }
+
+        let scale = (1u64 << step) as f64;
+        let lat_offset = (latitude - lat_range.0) / (lat_range.1 - lat_range.0) * scale;
+        let long_offset = (longitude - long_range.0) / (long_range.1 - long_range.0) * scale;
+        Some(GeoHashBits {
+            bits: Self::interleave(lat_offset as u32, long_offset as u32),
+            step,
+        })
+    }
+
+    fn decode_with_range(
+        long_range: (f64, f64),
+        lat_range: (f64, f64),
+        hash: &GeoHashBits,
+    ) -> GeoHashArea {
+        let separated = Self::deinterleave(hash.bits);
+        let lat_offset = (separated & 0xffffffff) as f64;
+        let long_offset = (separated >> 32) as f64;
+        let scale = (1u64 << hash.step) as f64;
+        let lat_scale = lat_range.1 - lat_range.0;
+        let long_scale = long_range.1 - long_range.0;
+        GeoHashArea {
+            latitude: (
+                lat_range.0 + (lat_offset / scale) * lat_scale,
+                lat_range.0 + ((lat_offset + 1.0) / scale) * lat_scale,
+            ),
+            longitude: (
+                long_range.0 + (long_offset / scale) * long_scale,
+                long_range.0 + ((long_offset + 1.0) / scale) * long_scale,
+            ),
+        }
+    }
+
+    /// Return the center of the cell `hash` as `(longitude, latitude)`
+    fn decode_center(hash: &GeoHashBits) -> (f64, f64) {
+        let area = Self::decode(hash);
+        let longitude =
+            ((area.longitude.0 + area.longitude.1) / 2.0).clamp(GEO_LONG_MIN, GEO_LONG_MAX);
+        let latitude = ((area.latitude.0 + area.latitude.1) / 2.0).clamp(GEO_LAT_MIN, GEO_LAT_MAX);
+        (longitude, latitude)
+    }
+
+    /// Interleave the bits of `x` (even positions) and `y` (odd positions)
+    fn interleave(x: u32, y: u32) -> u64 {
+        Self::spread(x) | (Self::spread(y) << 1)
+    }
+
+    /// Reverse `interleave`: the even bits are placed in the lower 32 bits and the odd bits in
+    /// the upper 32 bits
+    fn deinterleave(interleaved: u64) -> u64 {
+        Self::squash(interleaved) | (Self::squash(interleaved >> 1) << 32)
+    }
+
+    fn spread(value: u32) -> u64 {
+        let mut x = value as u64;
+        x = (x | (x << 16)) & 0x0000ffff0000ffff;
+        x = (x | (x << 8)) & 0x00ff00ff00ff00ff;
+        x = (x | (x << 4)) & 0x0f0f0f0f0f0f0f0f;
+        x = (x | (x << 2)) & 0x3333333333333333;
+        (x | (x << 1)) & 0x5555555555555555
+    }
+
+    fn squash(value: u64) -> u64 {
+        let mut x = value & 0x5555555555555555;
+        x = (x | (x >> 1)) & 0x3333333333333333;
+        x = (x | (x >> 2)) & 0x0f0f0f0f0f0f0f0f;
+        x = (x | (x >> 4)) & 0x00ff00ff00ff00ff;
+        x = (x | (x >> 8)) & 0x0000ffff0000ffff;
+        (x | (x >> 16)) & 0x00000000ffffffff
+    }
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geohash_encoding() {
+        // Palermo and Catania, as used in the Valkey documentation
+        let palermo = GeoHash::encode_score(13.361389, 38.115556).unwrap();
+        let catania = GeoHash::encode_score(15.087269, 37.502669).unwrap();
+        assert_eq!(palermo, 3479099956230698.0);
+        assert_eq!(catania, 3479447370796909.0);
+        assert_eq!(GeoHash::to_geohash_string(palermo), "sqc8b49rny0");
+        assert_eq!(GeoHash::to_geohash_string(catania), "sqdtr74hyu0");
+
+        let (lon, lat) = GeoHash::decode_score(palermo);
+        assert!((lon - 13.361389).abs() < 0.00001);
+        assert!((lat - 38.115556).abs() < 0.00001);
+
+        let (lon2, lat2) = GeoHash::decode_score(catania);
+        let distance = GeoHash::distance(lon, lat, lon2, lat2);
+        assert_eq!(format!("{:.4}", distance), "166274.1516");
+
+        assert!(GeoHash::encode_score(181.0, 0.0).is_none());
+        assert!(GeoHash::encode_score(0.0, 86.0).is_none());
+    }
+
+    #[test]
+    fn test_geohash_search_areas() {
+        let shape = GeoShape::Radius(200_000.0);
+        let areas = GeoHash::search_areas(&shape, 15.0, 37.0);
+        assert!(!areas.is_empty());
+
+        // Both cities must be inside one of the scanned cells
+        for score in [3479099956230698u64, 3479447370796909u64] {
+            assert!(areas.iter().any(|area| {
+                let (min, max) = area.score_range();
+                score >= min && score < max
+            }));
+        }
+
+        let (lon, lat) = GeoHash::decode_score(3479099956230698.0);
+        assert!(GeoHash::distance_if_in_shape(&shape, 15.0, 37.0, lon, lat).is_some());
+        assert!(
+            GeoHash::distance_if_in_shape(&GeoShape::Radius(100_000.0), 15.0, 37.0, lon, lat)
+                .is_none()
+        );
+        assert!(GeoHash::distance_if_in_shape(
+            &GeoShape::Box {
+                width: 400_000.0,
+                height: 400_000.0
+            },
+            15.0,
+            37.0,
+            lon,
+            lat
+        )
+        .is_some());
+    }
+}
@@ -0,0 +1,420 @@
+//! HyperLogLog cardinality estimation, as used by the `PF*` commands.
+//!
+//! The value is stored as a plain string using the same layout as Valkey: a 16 bytes header
+//! (`HYLL` magic, encoding byte, 3 unused bytes and the cached cardinality) followed by either
+//! the sparse (run length) or the dense (6 bits per register) encoding of the registers. This
+//! allows values to be moved between SableDB and Valkey as-is
+
+use bytes::BytesMut;
+
+/// Number of bits of the hash used to select the register
+const HLL_P: u32 = 14;
+/// Number of bits of the hash used to count the leading zeros
+const HLL_Q: u32 = 64 - HLL_P;
+pub const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_P_MASK: u64 = HLL_REGISTERS as u64 - 1;
+const HLL_BITS: usize = 6;
+const HLL_REGISTER_MAX: u8 = (1 << HLL_BITS) - 1;
+pub const HLL_HDR_SIZE: usize = 16;
+const HLL_DENSE_SIZE: usize = HLL_HDR_SIZE + (HLL_REGISTERS * HLL_BITS).div_ceil(8);
+const HLL_MAGIC: &[u8] = b"HYLL";
+const HLL_DENSE: u8 = 0;
+const HLL_SPARSE: u8 = 1;
+const HLL_ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+const HLL_HASH_SEED: u64 = 0xadc83b19;
+
+/// Sparse opcodes
+const HLL_SPARSE_XZERO_BIT: u8 = 0x40;
+const HLL_SPARSE_VAL_BIT: u8 = 0x80;
+const HLL_SPARSE_VAL_MAX_VALUE: u8 = 32;
+const HLL_SPARSE_VAL_MAX_LEN: usize = 4;
+const HLL_SPARSE_ZERO_MAX_LEN: usize = 64;
+const HLL_SPARSE_XZERO_MAX_LEN: usize = 16384;
+
+/// Sparse values larger than this are converted into the dense encoding
+pub const HLL_SPARSE_MAX_BYTES: usize = 3000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HyperLogLogError {
+    /// The string does not hold a HyperLogLog value
+    NotHyperLogLog,
+    /// The value has a valid header but its content can not be decoded
+    Corrupted,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+    dense: bool,
+    cached_cardinality: Option<u64>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog {
+            registers: vec![0u8; HLL_REGISTERS],
+            dense: false,
+            cached_cardinality: Some(0),
+        }
+    }
+}
+
+impl HyperLogLog {
+    /// Decode a HyperLogLog string value
+    pub fn from_bytes(value: &[u8]) -> Result<Self, HyperLogLogError> {
+        if value.len() < HLL_HDR_SIZE || !value.starts_with(HLL_MAGIC) {
+            return Err(HyperLogLogError::NotHyperLogLog);
+        }
+
+        let mut card = [0u8; 8];
+        card.copy_from_slice(&value[8..HLL_HDR_SIZE]);
+        // The most significant bit of the cached cardinality marks it as invalid
+        let cached_cardinality = if card[7] & 0x80 != 0 {
+            None
+        } else {
+            Some(u64::from_le_bytes(card))
+        };
+
+        let content = &value[HLL_HDR_SIZE..];
+        let mut registers = vec![0u8; HLL_REGISTERS];
+        let dense = match value[4] {
+            HLL_DENSE => {
+                if value.len() != HLL_DENSE_SIZE {
+                    return Err(HyperLogLogError::NotHyperLogLog);
+                }
+                for (index, register) in registers.iter_mut().enumerate() {
+                    *register = Self::dense_get(content, index);
+                }
+                true
+            }
+            HLL_SPARSE => {
+                Self::sparse_decode(content, &mut registers)?;
+                false
+            }
+            _ => return Err(HyperLogLogError::NotHyperLogLog),
+        };
+
+        Ok(HyperLogLog {
+            registers,
+            dense,
+            cached_cardinality,
+        })
+    }
+
+    /// Encode this HyperLogLog. The sparse encoding is kept for as long as the registers can be
+    /// represented by it and the value is smaller than `HLL_SPARSE_MAX_BYTES`
+    pub fn to_bytes(&self) -> BytesMut {
+        if !self.dense {
+            if let Some(sparse) = self.sparse_encode() {
+                if HLL_HDR_SIZE + sparse.len() <= HLL_SPARSE_MAX_BYTES {
+                    let mut buffer = self.header(HLL_SPARSE, HLL_HDR_SIZE + sparse.len());
+                    buffer.extend_from_slice(&sparse);
+                    return buffer;
+                }
+            }
+        }
+
+        let mut buffer = self.header(HLL_DENSE, HLL_DENSE_SIZE);
+        buffer.resize(HLL_DENSE_SIZE, 0);
+        for (index, register) in self.registers.iter().enumerate() {
+            Self::dense_set(&mut buffer[HLL_HDR_SIZE..], index, *register);
+        }
+        buffer
+    }
+
+    /// Add `element` to the set. Return `true` if a register was updated (i.e. the estimated
+    /// cardinality might have changed)
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let (index, count) = Self::pattern_len(element);
+        if self.registers[index] >= count {
+            return false;
+        }
+        self.registers[index] = count;
+        self.cached_cardinality = None;
+        true
+    }
+
+    /// Merge `other` into this HyperLogLog. The result uses the dense encoding if any of the
+    /// inputs does
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (register, other_register) in self.registers.iter_mut().zip(other.registers.iter()) {
+            *register = (*register).max(*other_register);
+        }
+        self.dense = self.dense || other.dense;
+        self.cached_cardinality = None;
+    }
+
+    /// Return the cached cardinality, if valid
+    pub fn cached_cardinality(&self) -> Option<u64> {
+        self.cached_cardinality
+    }
+
+    /// Return the estimated cardinality
+    pub fn count(&self) -> u64 {
+        if let Some(cardinality) = self.cached_cardinality {
+            return cardinality;
+        }
+
+        let m = HLL_REGISTERS as f64;
+        let mut histogram = [0usize; HLL_Q as usize + 2];
+        for register in &self.registers {
+            histogram[*register as usize] += 1;
+        }
+
+        let mut z = m * Self::tau((m - histogram[HLL_Q as usize + 1] as f64) / m);
+        for count in histogram[1..=HLL_Q as usize].iter().rev() {
+            z += *count as f64;
+            z *= 0.5;
+        }
+        z += m * Self::sigma(histogram[0] as f64 / m);
+        (HLL_ALPHA_INF * m * m / z).round() as u64
+    }
+
+    /// Return `true` if this HyperLogLog uses the dense encoding
+    pub fn is_dense(&self) -> bool {
+        self.dense
+    }
+
+    // Internal helpers
+
+    fn header(&self, encoding: u8, capacity: usize) -> BytesMut {
+        let mut buffer = BytesMut::with_capacity(capacity);
+        buffer.extend_from_slice(HLL_MAGIC);
+        buffer.extend_from_slice(&[encoding, 0, 0, 0]);
+        match self.cached_cardinality {
+            Some(cardinality) => buffer.extend_from_slice(&cardinality.to_le_bytes()),
+            None => buffer.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0x80]),
+        }
+        buffer
+    }
+
+    /// Return the register index and the number of leading zeros (+1) for `element`
+    fn pattern_len(element: &[u8]) -> (usize, u8) {
+        let hash = murmurhash64a(element, HLL_HASH_SEED);
+        let index = (hash & HLL_P_MASK) as usize;
+        // Add a sentinel bit so the count is bounded by `HLL_Q + 1`
+        let hash = (hash >> HLL_P) | (1u64 << HLL_Q);
+        (index, hash.trailing_zeros() as u8 + 1)
+    }
+
+    fn dense_get(content: &[u8], index: usize) -> u8 {
+        let byte = index * HLL_BITS / 8;
+        let shift = index * HLL_BITS % 8;
+        let b0 = content[byte] as u16;
+        let b1 = content.get(byte + 1).copied().unwrap_or_default() as u16;
+        (((b0 >> shift) | (b1 << (8 - shift))) & HLL_REGISTER_MAX as u16) as u8
+    }
+
+    fn dense_set(content: &mut [u8], index: usize, value: u8) {
+        let byte = index * HLL_BITS / 8;
+        let shift = index * HLL_BITS % 8;
+        let value = value as u16;
+        let max = HLL_REGISTER_MAX as u16;
+        content[byte] &= !((max << shift) as u8);
+        content[byte] |= (value << shift) as u8;
+        if let Some(next) = content.get_mut(byte + 1) {
+            *next &= !((max >> (8 - shift)) as u8);
+            *next |= (value >> (8 - shift)) as u8;
+        }
+    }
+
+    fn sparse_decode(content: &[u8], registers: &mut [u8]) -> Result<(), HyperLogLogError> {
+        let mut index = 0usize;
+        let mut pos = 0usize;
+        while pos < content.len() {
+            let opcode = content[pos];
+            if opcode & HLL_SPARSE_VAL_BIT != 0 {
+                let value = ((opcode >> 2) & 0x1f) + 1;
+                let run_len = (opcode & 0x3) as usize + 1;
+                let run = registers
+                    .get_mut(index..index + run_len)
+                    .ok_or(HyperLogLogError::Corrupted)?;
+                run.fill(value);
+                index += run_len;
+                pos += 1;
+            } else if opcode & HLL_SPARSE_XZERO_BIT != 0 {
+                let low = *content.get(pos + 1).ok_or(HyperLogLogError::Corrupted)?;
+                index += ((((opcode & 0x3f) as usize) << 8) | low as usize) + 1;
+                pos += 2;
+            } else {
+                index += (opcode & 0x3f) as usize + 1;
+                pos += 1;
+            }
+        }
+
+        if index != HLL_REGISTERS {
+            return Err(HyperLogLogError::Corrupted);
+        }
+        Ok(())
+    }
+
+    /// Encode the registers using the sparse encoding. Return `None` if a register value is
+    /// too large for it
+    fn sparse_encode(&self) -> Option<Vec<u8>> {
+        let mut content = Vec::<u8>::new();
+        let mut index = 0usize;
+        while index < HLL_REGISTERS {
+            let value = self.registers[index];
+            if value > HLL_SPARSE_VAL_MAX_VALUE {
+                return None;
+            }
+            let mut run_len = self.registers[index..]
+                .iter()
+                .take_while(|register| **register == value)
+                .count();
+            index += run_len;
+
+            while run_len > 0 {
+                if value != 0 {
+                    let len = run_len.min(HLL_SPARSE_VAL_MAX_LEN);
+                    content.push(HLL_SPARSE_VAL_BIT | ((value - 1) << 2) | (len - 1) as u8);
+                    run_len -= len;
+                } else if run_len > HLL_SPARSE_ZERO_MAX_LEN {
+                    let len = run_len.min(HLL_SPARSE_XZERO_MAX_LEN) - 1;
+                    content.push(HLL_SPARSE_XZERO_BIT | (len >> 8) as u8);
+                    content.push((len & 0xff) as u8);
+                    run_len -= len + 1;
+                } else {
+                    content.push((run_len - 1) as u8);
+                    run_len = 0;
+                }
+            }
+        }
+        Some(content)
+    }
+
+    fn sigma(mut x: f64) -> f64 {
+        if x == 1.0 {
+            return f64::INFINITY;
+        }
+        let mut y = 1.0;
+        let mut z = x;
+        loop {
+            x *= x;
+            let z_prime = z;
+            z += x * y;
+            y += y;
+            if z_prime == z {
+                return z;
+            }
+        }
+    }
+
+    fn tau(mut x: f64) -> f64 {
+        if x == 0.0 || x == 1.0 {
+            return 0.0;
+        }
+        let mut y = 1.0;
+        let mut z = 1.0 - x;
+        loop {
+            x = x.sqrt();
+            let z_prime = z;
+            y *= 0.5;
+            z -= (1.0 - x).powi(2) * y;
+            if z_prime == z {
+                return z / 3.0;
+            }
+        }
+    }
+}
+
+/// MurmurHash2, 64-bit version, by Austin Appleby. The input is read as little endian so the
+/// hash is identical on all platforms
+fn murmurhash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4a7935bd1e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap_or_default());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (index, byte) in tail.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * index);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_hyperloglog_encoding() {
+        // The value created by Valkey for an empty HyperLogLog
+        let expected = b"HYLL\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x7f\xff";
+        let hll = HyperLogLog::default();
+        assert_eq!(hll.to_bytes().as_ref(), expected);
+        assert_eq!(HyperLogLog::from_bytes(expected).unwrap(), hll);
+        assert_eq!(hll.count(), 0);
+    }
+
+    #[test]
+    fn test_hyperloglog_estimation() {
+        let mut hll = HyperLogLog::default();
+        for i in 0..1000 {
+            hll.add(format!("element:{}", i).as_bytes());
+        }
+        assert!(!hll.add(b"element:0"));
+        assert_eq!(hll.cached_cardinality(), None);
+
+        // Still small enough for the sparse encoding
+        let bytes = hll.to_bytes();
+        assert_eq!(bytes[4], HLL_SPARSE);
+        let decoded = HyperLogLog::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, hll);
+        let estimate = decoded.count() as f64;
+        assert!((estimate - 1000.0).abs() / 1000.0 < 0.02, "{}", estimate);
+
+        for i in 1000..50000 {
+            hll.add(format!("element:{}", i).as_bytes());
+        }
+        let bytes = hll.to_bytes();
+        assert_eq!(bytes[4], HLL_DENSE);
+        assert_eq!(bytes.len(), HLL_DENSE_SIZE);
+        let decoded = HyperLogLog::from_bytes(&bytes).unwrap();
+        assert!(decoded.is_dense());
+        assert_eq!(decoded.registers, hll.registers);
+        let estimate = decoded.count() as f64;
+        assert!((estimate - 50000.0).abs() / 50000.0 < 0.02, "{}", estimate);
+    }
+
+    #[test]
+    fn test_hyperloglog_invalid_values() {
+        assert_eq!(
+            HyperLogLog::from_bytes(b"hello world"),
+            Err(HyperLogLogError::NotHyperLogLog)
+        );
+        // Dense value with the wrong length
+        assert_eq!(
+            HyperLogLog::from_bytes(b"HYLL\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00"),
+            Err(HyperLogLogError::NotHyperLogLog)
+        );
+        // Sparse value that does not cover all the registers
+        assert_eq!(
+            HyperLogLog::from_bytes(b"HYLL\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x7f"),
+            Err(HyperLogLogError::Corrupted)
+        );
+    }
+}
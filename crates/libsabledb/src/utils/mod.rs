@@ -1,5 +1,8 @@
 pub mod file_utils;
+pub mod geohash;
+pub mod hyperloglog;
 pub mod pattern_matcher;
+pub mod rdb;
 pub mod request_parser;
 pub mod resp_builder_v2;
 pub mod resp_response_parser_v2;
@@ -18,7 +21,10 @@ pub use crate::{
     metadata::KeyType,
     server::{ParserError, SableError},
 };
+pub use geohash::*;
+pub use hyperloglog::*;
 pub use pattern_matcher::*;
+pub use rdb::*;
 pub use request_parser::*;
 pub use resp_builder_v2::RespBuilderV2;
 pub use resp_response_parser_v2::{RespResponseParserV2, ResponseParseResult, ValkeyObject};
@@ -0,0 +1,1341 @@
+//! Serialization of values, as used by the `DUMP` and `RESTORE` commands.
+//!
+//! The payload uses the RDB object encoding: `<object type><encoded value><RDB version (2 bytes,
+//! little endian)><CRC64 of everything before it (8 bytes, little endian)>`. Values are always
+//! written using the plain encodings (raw strings and plain list / set / zset / hash objects),
+//! which any RDB reader understands. Hashes with field expiration times are written as
+//! `RDB_TYPE_HASH_METADATA` and streams as `RDB_TYPE_STREAM_LISTPACKS_3`, the only encodings that
+//! can hold them. When restoring, the compact encodings (ziplist, listpack, intset and quicklist)
+//! and LZF compressed strings are accepted as well, so keys dumped by Valkey or Redis can be
+//! restored as-is
+
+use crate::metadata::StreamId;
+use bytes::{BufMut, BytesMut};
+use std::collections::HashMap;
+
+/// The RDB version written to the payload
+const RDB_VERSION: u16 = 9;
+/// The RDB version that introduced `RDB_TYPE_STREAM_LISTPACKS_3`
+const RDB_VERSION_STREAM: u16 = 11;
+/// The RDB version that introduced `RDB_TYPE_HASH_METADATA`
+const RDB_VERSION_HASH_METADATA: u16 = 12;
+/// Payloads with a higher RDB version are rejected
+const RDB_VERSION_MAX: u16 = 12;
+/// Version (2 bytes) + CRC64 (8 bytes)
+const RDB_FOOTER_SIZE: usize = 10;
+
+const RDB_TYPE_STRING: u8 = 0;
+const RDB_TYPE_LIST: u8 = 1;
+const RDB_TYPE_SET: u8 = 2;
+const RDB_TYPE_ZSET: u8 = 3;
+const RDB_TYPE_HASH: u8 = 4;
+const RDB_TYPE_ZSET_2: u8 = 5;
+const RDB_TYPE_LIST_ZIPLIST: u8 = 10;
+const RDB_TYPE_SET_INTSET: u8 = 11;
+const RDB_TYPE_ZSET_ZIPLIST: u8 = 12;
+const RDB_TYPE_HASH_ZIPLIST: u8 = 13;
+const RDB_TYPE_LIST_QUICKLIST: u8 = 14;
+const RDB_TYPE_STREAM_LISTPACKS: u8 = 15;
+const RDB_TYPE_HASH_LISTPACK: u8 = 16;
+const RDB_TYPE_ZSET_LISTPACK: u8 = 17;
+const RDB_TYPE_LIST_QUICKLIST_2: u8 = 18;
+const RDB_TYPE_STREAM_LISTPACKS_2: u8 = 19;
+const RDB_TYPE_SET_LISTPACK: u8 = 20;
+const RDB_TYPE_STREAM_LISTPACKS_3: u8 = 21;
+const RDB_TYPE_HASH_METADATA: u8 = 24;
+
+/// Special string encodings (the 2 most significant bits of the length are `11`)
+const RDB_ENC_INT8: u8 = 0;
+const RDB_ENC_INT16: u8 = 1;
+const RDB_ENC_INT32: u8 = 2;
+const RDB_ENC_LZF: u8 = 3;
+
+/// `RDB_TYPE_LIST_QUICKLIST_2` node containers
+const QUICKLIST_NODE_PLAIN: usize = 1;
+const QUICKLIST_NODE_PACKED: usize = 2;
+
+/// Flags of a stream entry, stored in the stream listpack nodes
+const STREAM_ITEM_FLAG_DELETED: i64 = 1;
+const STREAM_ITEM_FLAG_SAMEFIELDS: i64 = 2;
+/// The maximum number of entries written to a single stream listpack node
+const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+/// CRC64 (Jones polynomial, reflected), as used by the RDB format
+const CRC64_POLY: u64 = 0x95ac_9329_ac4b_c9b5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RdbError {
+    /// The payload is too short, has an unsupported RDB version or a wrong checksum
+    VersionOrChecksum,
+    /// The payload could not be decoded
+    BadFormat,
+    /// The payload holds an object type that SableDB does not support (e.g. a module value)
+    UnsupportedType(u8),
+}
+
+/// A value that can be serialized with `DUMP` and restored with `RESTORE`
+#[derive(Clone, Debug, PartialEq)]
+pub enum RdbValue {
+    String(BytesMut),
+    List(Vec<BytesMut>),
+    Set(Vec<BytesMut>),
+    Zset(Vec<(BytesMut, f64)>),
+    Hash(Vec<(BytesMut, BytesMut)>),
+    /// A hash with fields that have an expiration time (milliseconds since UNIX EPOCH)
+    HashWithExpire(Vec<(BytesMut, BytesMut, Option<u64>)>),
+    Stream(RdbStream),
+}
+
+/// A stream, including its consumer groups
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RdbStream {
+    pub entries: Vec<RdbStreamEntry>,
+    pub last_id: StreamId,
+    pub max_deleted_id: StreamId,
+    /// Number of entries ever added to the stream
+    pub entries_added: u64,
+    pub groups: Vec<RdbStreamGroup>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RdbStreamEntry {
+    pub id: StreamId,
+    pub fields: Vec<(BytesMut, BytesMut)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RdbStreamGroup {
+    pub name: BytesMut,
+    pub last_delivered_id: StreamId,
+    pub entries_read: u64,
+    /// The group PEL, sorted by entry ID
+    pub pending: Vec<RdbStreamPending>,
+    pub consumers: Vec<RdbStreamConsumer>,
+}
+
+/// An entry that was delivered to `consumer` but not acknowledged yet
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RdbStreamPending {
+    pub id: StreamId,
+    pub consumer: BytesMut,
+    /// Milliseconds since UNIX EPOCH
+    pub delivery_time: u64,
+    pub delivery_count: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RdbStreamConsumer {
+    pub name: BytesMut,
+    /// Milliseconds since UNIX EPOCH
+    pub seen_time: u64,
+    /// Milliseconds since UNIX EPOCH
+    pub active_time: u64,
+}
+
+impl RdbValue {
+    /// Serialize the value into a `DUMP` payload
+    pub fn to_dump_payload(&self) -> BytesMut {
+        let mut buffer = BytesMut::new();
+        match self {
+            Self::String(value) => {
+                buffer.put_u8(RDB_TYPE_STRING);
+                write_string(&mut buffer, value);
+            }
+            Self::List(items) => {
+                buffer.put_u8(RDB_TYPE_LIST);
+                write_length(&mut buffer, items.len());
+                for item in items {
+                    write_string(&mut buffer, item);
+                }
+            }
+            Self::Set(members) => {
+                buffer.put_u8(RDB_TYPE_SET);
+                write_length(&mut buffer, members.len());
+                for member in members {
+                    write_string(&mut buffer, member);
+                }
+            }
+            Self::Zset(members) => {
+                buffer.put_u8(RDB_TYPE_ZSET_2);
+                write_length(&mut buffer, members.len());
+                for (member, score) in members {
+                    write_string(&mut buffer, member);
+                    buffer.put_f64_le(*score);
+                }
+            }
+            Self::Hash(fields) => {
+                buffer.put_u8(RDB_TYPE_HASH);
+                write_length(&mut buffer, fields.len());
+                for (field, value) in fields {
+                    write_string(&mut buffer, field);
+                    write_string(&mut buffer, value);
+                }
+            }
+            Self::HashWithExpire(fields) => {
+                // `<min expire><count>` followed by `<ttl><field><value>` tuples. The TTL of a
+                // field is stored relative to the minimum expiration time, 0 means "no TTL"
+                let min_expire = fields
+                    .iter()
+                    .filter_map(|(_, _, expire)| *expire)
+                    .min()
+                    .unwrap_or_default();
+                buffer.put_u8(RDB_TYPE_HASH_METADATA);
+                buffer.put_u64_le(min_expire);
+                write_length(&mut buffer, fields.len());
+                for (field, value, expire) in fields {
+                    let ttl = expire.map(|expire| expire - min_expire + 1).unwrap_or(0);
+                    write_length(&mut buffer, ttl as usize);
+                    write_string(&mut buffer, field);
+                    write_string(&mut buffer, value);
+                }
+            }
+            Self::Stream(stream) => {
+                buffer.put_u8(RDB_TYPE_STREAM_LISTPACKS_3);
+                write_stream(&mut buffer, stream);
+            }
+        }
+        buffer.put_u16_le(self.rdb_version());
+        let crc = crc64(0, &buffer);
+        buffer.put_u64_le(crc);
+        buffer
+    }
+
+    /// The oldest RDB version that can hold this value
+    fn rdb_version(&self) -> u16 {
+        match self {
+            Self::HashWithExpire(_) => RDB_VERSION_HASH_METADATA,
+            Self::Stream(_) => RDB_VERSION_STREAM,
+            _ => RDB_VERSION,
+        }
+    }
+
+    /// Decode a `DUMP` payload
+    pub fn from_dump_payload(payload: &[u8]) -> Result<Self, RdbError> {
+        if payload.len() < RDB_FOOTER_SIZE + 1 {
+            return Err(RdbError::VersionOrChecksum);
+        }
+
+        let (body, crc) = payload.split_at(payload.len() - 8);
+        let crc = u64::from_le_bytes(crc.try_into().map_err(|_| RdbError::VersionOrChecksum)?);
+        // A zero checksum means that the writer did not compute it
+        if crc != 0 && crc != crc64(0, body) {
+            return Err(RdbError::VersionOrChecksum);
+        }
+
+        let (object, version) = body.split_at(body.len() - 2);
+        let version = u16::from_le_bytes([version[0], version[1]]);
+        if version > RDB_VERSION_MAX {
+            return Err(RdbError::VersionOrChecksum);
+        }
+
+        let mut reader = RdbReader::new(object);
+        let value = reader.read_object()?;
+        if !reader.is_empty() {
+            return Err(RdbError::BadFormat);
+        }
+        Ok(value)
+    }
+}
+
+/// Compute the CRC64 of `buffer`, starting from `crc`
+pub fn crc64(crc: u64, buffer: &[u8]) -> u64 {
+    let mut crc = crc;
+    for byte in buffer {
+        crc ^= *byte as u64;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ CRC64_POLY
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn write_length(buffer: &mut BytesMut, len: usize) {
+    if len < 1 << 6 {
+        buffer.put_u8(len as u8);
+    } else if len < 1 << 14 {
+        buffer.put_u8(0x40 | (len >> 8) as u8);
+        buffer.put_u8(len as u8);
+    } else if let Ok(len) = u32::try_from(len) {
+        buffer.put_u8(0x80);
+        buffer.put_u32(len);
+    } else {
+        buffer.put_u8(0x81);
+        buffer.put_u64(len as u64);
+    }
+}
+
+fn write_string(buffer: &mut BytesMut, value: &[u8]) {
+    write_length(buffer, value.len());
+    buffer.put_slice(value);
+}
+
+/// Write a stream: the entries (grouped into listpack nodes), the stream metadata and the consumer
+/// groups
+fn write_stream(buffer: &mut BytesMut, stream: &RdbStream) {
+    let nodes = stream.entries.chunks(STREAM_NODE_MAX_ENTRIES);
+    write_length(buffer, nodes.len());
+    for node in nodes {
+        // the node key is the ID of its first entry (the "master ID"), big endian
+        write_string(buffer, &stream_id_to_bytes(&node[0].id));
+        write_string(buffer, &stream_node_listpack(node));
+    }
+
+    let first_id = stream
+        .entries
+        .first()
+        .map(|entry| entry.id)
+        .unwrap_or_default();
+    write_length(buffer, stream.entries.len());
+    write_stream_id(buffer, &stream.last_id);
+    write_stream_id(buffer, &first_id);
+    write_stream_id(buffer, &stream.max_deleted_id);
+    write_length(buffer, stream.entries_added as usize);
+
+    write_length(buffer, stream.groups.len());
+    for group in &stream.groups {
+        write_string(buffer, &group.name);
+        write_stream_id(buffer, &group.last_delivered_id);
+        write_length(buffer, group.entries_read as usize);
+
+        write_length(buffer, group.pending.len());
+        for pending in &group.pending {
+            buffer.put_slice(&stream_id_to_bytes(&pending.id));
+            buffer.put_u64_le(pending.delivery_time);
+            write_length(buffer, pending.delivery_count as usize);
+        }
+
+        // every consumer lists the IDs of the pending entries it owns
+        write_length(buffer, group.consumers.len());
+        for consumer in &group.consumers {
+            write_string(buffer, &consumer.name);
+            buffer.put_u64_le(consumer.seen_time);
+            buffer.put_u64_le(consumer.active_time);
+            let owned: Vec<&RdbStreamPending> = group
+                .pending
+                .iter()
+                .filter(|pending| pending.consumer == consumer.name)
+                .collect();
+            write_length(buffer, owned.len());
+            for pending in owned {
+                buffer.put_slice(&stream_id_to_bytes(&pending.id));
+            }
+        }
+    }
+}
+
+fn write_stream_id(buffer: &mut BytesMut, id: &StreamId) {
+    write_length(buffer, id.ms() as usize);
+    write_length(buffer, id.seq() as usize);
+}
+
+/// Encode a stream ID as 16 bytes, big endian
+fn stream_id_to_bytes(id: &StreamId) -> [u8; 16] {
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&id.ms().to_be_bytes());
+    bytes[8..].copy_from_slice(&id.seq().to_be_bytes());
+    bytes
+}
+
+fn stream_id_from_bytes(bytes: &[u8]) -> Result<StreamId, RdbError> {
+    let bytes: [u8; 16] = bytes.try_into().map_err(|_| RdbError::BadFormat)?;
+    let ms = u64::from_be_bytes(bytes[..8].try_into().map_err(|_| RdbError::BadFormat)?);
+    let seq = u64::from_be_bytes(bytes[8..].try_into().map_err(|_| RdbError::BadFormat)?);
+    Ok(StreamId::new(ms, seq))
+}
+
+/// Build the listpack of a stream node. The node starts with a "master entry" (the number of valid
+/// and deleted entries and the fields of the first entry), followed by the entries. Entry IDs are
+/// stored relative to the ID of the first entry, and entries that have the same fields as the
+/// master entry only store their values
+fn stream_node_listpack(entries: &[RdbStreamEntry]) -> BytesMut {
+    let master_id = entries[0].id;
+    let master_fields: Vec<&BytesMut> = entries[0].fields.iter().map(|(field, _)| field).collect();
+
+    let mut listpack = ListpackWriter::default();
+    listpack.push_int(entries.len() as i64);
+    listpack.push_int(0);
+    listpack.push_int(master_fields.len() as i64);
+    for field in &master_fields {
+        listpack.push_string(field);
+    }
+    listpack.push_int(0);
+
+    for entry in entries {
+        let same_fields = entry.fields.len() == master_fields.len()
+            && entry
+                .fields
+                .iter()
+                .zip(&master_fields)
+                .all(|((field, _), master_field)| field == *master_field);
+        listpack.push_int(if same_fields {
+            STREAM_ITEM_FLAG_SAMEFIELDS
+        } else {
+            0
+        });
+        listpack.push_int(entry.id.ms().wrapping_sub(master_id.ms()) as i64);
+        listpack.push_int(entry.id.seq().wrapping_sub(master_id.seq()) as i64);
+        if same_fields {
+            for (_, value) in &entry.fields {
+                listpack.push_string(value);
+            }
+        } else {
+            listpack.push_int(entry.fields.len() as i64);
+            for (field, value) in &entry.fields {
+                listpack.push_string(field);
+                listpack.push_string(value);
+            }
+        }
+        // the number of listpack elements of the entry (excluding this one)
+        let lp_count = if same_fields {
+            entry.fields.len() + 3
+        } else {
+            2 * entry.fields.len() + 4
+        };
+        listpack.push_int(lp_count as i64);
+    }
+    listpack.finish()
+}
+
+/// Decode the listpack of a stream node whose first entry ID is `master_id`
+fn stream_node_entries(master_id: &StreamId, blob: &[u8]) -> Result<Vec<RdbStreamEntry>, RdbError> {
+    let mut elements = listpack_entries(blob)?.into_iter();
+    let mut next_element = || elements.next().ok_or(RdbError::BadFormat);
+
+    let count = parse_int(&next_element()?)?;
+    let deleted = parse_int(&next_element()?)?;
+    let master_fields_count = parse_int(&next_element()?)?;
+    let mut master_fields = Vec::new();
+    for _ in 0..master_fields_count {
+        master_fields.push(next_element()?);
+    }
+    // the master entry terminator
+    next_element()?;
+
+    let mut entries = Vec::new();
+    for _ in 0..count.saturating_add(deleted) {
+        let flags = parse_int(&next_element()?)?;
+        let ms = master_id
+            .ms()
+            .wrapping_add(parse_int(&next_element()?)? as u64);
+        let seq = master_id
+            .seq()
+            .wrapping_add(parse_int(&next_element()?)? as u64);
+        let mut fields = Vec::new();
+        if flags & STREAM_ITEM_FLAG_SAMEFIELDS != 0 {
+            for field in &master_fields {
+                fields.push((field.clone(), next_element()?));
+            }
+        } else {
+            for _ in 0..parse_int(&next_element()?)? {
+                fields.push((next_element()?, next_element()?));
+            }
+        }
+        // lp-count
+        next_element()?;
+        if flags & STREAM_ITEM_FLAG_DELETED == 0 {
+            entries.push(RdbStreamEntry {
+                id: StreamId::new(ms, seq),
+                fields,
+            });
+        }
+    }
+
+    if elements.next().is_some() {
+        return Err(RdbError::BadFormat);
+    }
+    Ok(entries)
+}
+
+fn int_to_bytes(value: i64) -> BytesMut {
+    BytesMut::from(value.to_string().as_bytes())
+}
+
+/// Parse a listpack element that holds an integer
+fn parse_int(value: &[u8]) -> Result<i64, RdbError> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or(RdbError::BadFormat)
+}
+
+fn parse_score(value: &[u8]) -> Result<f64, RdbError> {
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .ok_or(RdbError::BadFormat)
+}
+
+/// Return `len` bytes of `buffer`, starting at `start`
+fn slice_at(buffer: &[u8], start: usize, len: usize) -> Result<&[u8], RdbError> {
+    let end = start.checked_add(len).ok_or(RdbError::BadFormat)?;
+    buffer.get(start..end).ok_or(RdbError::BadFormat)
+}
+
+/// Read a little endian, two's complement, integer of `len` bytes starting at `start`
+fn read_int_le(buffer: &[u8], start: usize, len: usize) -> Result<i64, RdbError> {
+    let bytes = slice_at(buffer, start, len)?;
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate() {
+        value |= (*byte as u64) << (8 * i);
+    }
+    // sign extend
+    let unused_bits = 64 - 8 * len as u32;
+    Ok(((value << unused_bits) as i64) >> unused_bits)
+}
+
+/// A length, or the special encoding of a string
+enum RdbLength {
+    Len(usize),
+    Encoded(u8),
+}
+
+struct RdbReader<'a> {
+    buffer: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> RdbReader<'a> {
+    fn new(buffer: &'a [u8]) -> Self {
+        RdbReader { buffer, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.buffer.len()
+    }
+
+    fn read_u8(&mut self) -> Result<u8, RdbError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], RdbError> {
+        let bytes = slice_at(self.buffer, self.pos, len)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read_length_or_encoding(&mut self) -> Result<RdbLength, RdbError> {
+        let first = self.read_u8()?;
+        Ok(match first >> 6 {
+            0 => RdbLength::Len((first & 0x3f) as usize),
+            1 => RdbLength::Len((((first & 0x3f) as usize) << 8) | self.read_u8()? as usize),
+            3 => RdbLength::Encoded(first & 0x3f),
+            _ => {
+                let len = match first {
+                    0x80 => u32::from_be_bytes(self.read_bytes(4)?.try_into().unwrap_or_default())
+                        as u64,
+                    0x81 => u64::from_be_bytes(self.read_bytes(8)?.try_into().unwrap_or_default()),
+                    _ => return Err(RdbError::BadFormat),
+                };
+                RdbLength::Len(usize::try_from(len).map_err(|_| RdbError::BadFormat)?)
+            }
+        })
+    }
+
+    fn read_length(&mut self) -> Result<usize, RdbError> {
+        match self.read_length_or_encoding()? {
+            RdbLength::Len(len) => Ok(len),
+            RdbLength::Encoded(_) => Err(RdbError::BadFormat),
+        }
+    }
+
+    /// Read the number of items of a collection. Empty collections are not valid
+    fn read_items_count(&mut self) -> Result<usize, RdbError> {
+        match self.read_length()? {
+            0 => Err(RdbError::BadFormat),
+            // do not trust the length for pre-allocating memory
+            len if len > self.buffer.len() => Err(RdbError::BadFormat),
+            len => Ok(len),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<BytesMut, RdbError> {
+        match self.read_length_or_encoding()? {
+            RdbLength::Len(len) => Ok(BytesMut::from(self.read_bytes(len)?)),
+            RdbLength::Encoded(encoding @ (RDB_ENC_INT8 | RDB_ENC_INT16 | RDB_ENC_INT32)) => {
+                let int_len = 1usize << encoding;
+                Ok(int_to_bytes(read_int_le(
+                    self.read_bytes(int_len)?,
+                    0,
+                    int_len,
+                )?))
+            }
+            RdbLength::Encoded(RDB_ENC_LZF) => {
+                let compressed_len = self.read_length()?;
+                let len = self.read_length()?;
+                lzf_decompress(self.read_bytes(compressed_len)?, len)
+            }
+            RdbLength::Encoded(_) => Err(RdbError::BadFormat),
+        }
+    }
+
+    /// Read a score encoded as a string (`RDB_TYPE_ZSET`)
+    fn read_string_double(&mut self) -> Result<f64, RdbError> {
+        match self.read_u8()? {
+            253 => Ok(f64::NAN),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            len => parse_score(self.read_bytes(len as usize)?),
+        }
+    }
+
+    fn read_binary_double(&mut self) -> Result<f64, RdbError> {
+        let bytes = self.read_bytes(8)?;
+        Ok(f64::from_le_bytes(
+            bytes.try_into().map_err(|_| RdbError::BadFormat)?,
+        ))
+    }
+
+    fn read_u64_le(&mut self) -> Result<u64, RdbError> {
+        let bytes = self.read_bytes(8)?;
+        Ok(u64::from_le_bytes(
+            bytes.try_into().map_err(|_| RdbError::BadFormat)?,
+        ))
+    }
+
+    fn read_stream_id(&mut self) -> Result<StreamId, RdbError> {
+        let ms = self.read_length()? as u64;
+        let seq = self.read_length()? as u64;
+        Ok(StreamId::new(ms, seq))
+    }
+
+    /// Read a stream. Older stream encodings do not store the deletion bookkeeping, the entries
+    /// read counter of the groups and the active time of the consumers
+    fn read_stream(&mut self, object_type: u8) -> Result<RdbStream, RdbError> {
+        let mut stream = RdbStream::default();
+        for _ in 0..self.read_length()? {
+            let master_id = stream_id_from_bytes(&self.read_string()?)?;
+            stream
+                .entries
+                .extend(stream_node_entries(&master_id, &self.read_string()?)?);
+        }
+
+        if self.read_length()? != stream.entries.len() {
+            return Err(RdbError::BadFormat);
+        }
+        stream.last_id = self.read_stream_id()?;
+        if object_type == RDB_TYPE_STREAM_LISTPACKS {
+            stream.entries_added = stream.entries.len() as u64;
+        } else {
+            // first ID
+            self.read_stream_id()?;
+            stream.max_deleted_id = self.read_stream_id()?;
+            stream.entries_added = self.read_length()? as u64;
+        }
+
+        for _ in 0..self.read_length()? {
+            let mut group = RdbStreamGroup {
+                name: self.read_string()?,
+                last_delivered_id: self.read_stream_id()?,
+                ..Default::default()
+            };
+            if object_type != RDB_TYPE_STREAM_LISTPACKS {
+                group.entries_read = self.read_length()? as u64;
+            }
+
+            let mut pending = Vec::new();
+            for _ in 0..self.read_length()? {
+                let id = stream_id_from_bytes(self.read_bytes(16)?)?;
+                let delivery_time = self.read_u64_le()?;
+                let delivery_count = self.read_length()? as u64;
+                pending.push((id, delivery_time, delivery_count));
+            }
+
+            // every consumer lists the IDs of the pending entries it owns
+            let mut owners = HashMap::<StreamId, BytesMut>::new();
+            for _ in 0..self.read_length()? {
+                let name = self.read_string()?;
+                let seen_time = self.read_u64_le()?;
+                let active_time = if object_type == RDB_TYPE_STREAM_LISTPACKS_3 {
+                    self.read_u64_le()?
+                } else {
+                    seen_time
+                };
+                for _ in 0..self.read_length()? {
+                    owners.insert(stream_id_from_bytes(self.read_bytes(16)?)?, name.clone());
+                }
+                group.consumers.push(RdbStreamConsumer {
+                    name,
+                    seen_time,
+                    active_time,
+                });
+            }
+
+            for (id, delivery_time, delivery_count) in pending {
+                // a pending entry must be owned by a consumer
+                let consumer = owners.remove(&id).ok_or(RdbError::BadFormat)?;
+                group.pending.push(RdbStreamPending {
+                    id,
+                    consumer,
+                    delivery_time,
+                    delivery_count,
+                });
+            }
+            if !owners.is_empty() {
+                return Err(RdbError::BadFormat);
+            }
+            stream.groups.push(group);
+        }
+        Ok(stream)
+    }
+
+    fn read_object(&mut self) -> Result<RdbValue, RdbError> {
+        let object_type = self.read_u8()?;
+        let value = match object_type {
+            RDB_TYPE_STRING => RdbValue::String(self.read_string()?),
+            RDB_TYPE_LIST => {
+                let count = self.read_items_count()?;
+                let mut items = Vec::with_capacity(count);
+                for _ in 0..count {
+                    items.push(self.read_string()?);
+                }
+                RdbValue::List(items)
+            }
+            RDB_TYPE_SET => {
+                let count = self.read_items_count()?;
+                let mut members = Vec::with_capacity(count);
+                for _ in 0..count {
+                    members.push(self.read_string()?);
+                }
+                RdbValue::Set(members)
+            }
+            RDB_TYPE_ZSET | RDB_TYPE_ZSET_2 => {
+                let count = self.read_items_count()?;
+                let mut members = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let member = self.read_string()?;
+                    let score = if object_type == RDB_TYPE_ZSET {
+                        self.read_string_double()?
+                    } else {
+                        self.read_binary_double()?
+                    };
+                    members.push((member, score));
+                }
+                RdbValue::Zset(members)
+            }
+            RDB_TYPE_HASH => {
+                let count = self.read_items_count()?;
+                let mut fields = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let field = self.read_string()?;
+                    let value = self.read_string()?;
+                    fields.push((field, value));
+                }
+                RdbValue::Hash(fields)
+            }
+            RDB_TYPE_HASH_METADATA => {
+                let min_expire = self.read_u64_le()?;
+                let count = self.read_items_count()?;
+                let mut fields = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let expire = match self.read_length()? as u64 {
+                        0 => None,
+                        ttl => Some(min_expire.saturating_add(ttl - 1)),
+                    };
+                    let field = self.read_string()?;
+                    let value = self.read_string()?;
+                    fields.push((field, value, expire));
+                }
+                RdbValue::HashWithExpire(fields)
+            }
+            RDB_TYPE_STREAM_LISTPACKS
+            | RDB_TYPE_STREAM_LISTPACKS_2
+            | RDB_TYPE_STREAM_LISTPACKS_3 => RdbValue::Stream(self.read_stream(object_type)?),
+            RDB_TYPE_LIST_ZIPLIST => RdbValue::List(ziplist_entries(&self.read_string()?)?),
+            RDB_TYPE_SET_INTSET => RdbValue::Set(intset_entries(&self.read_string()?)?),
+            RDB_TYPE_SET_LISTPACK => RdbValue::Set(listpack_entries(&self.read_string()?)?),
+            RDB_TYPE_ZSET_ZIPLIST | RDB_TYPE_ZSET_LISTPACK => {
+                let blob = self.read_string()?;
+                let entries = if object_type == RDB_TYPE_ZSET_ZIPLIST {
+                    ziplist_entries(&blob)?
+                } else {
+                    listpack_entries(&blob)?
+                };
+                let mut members = Vec::with_capacity(entries.len() / 2);
+                for pair in pairs(entries)? {
+                    let score = parse_score(&pair.1)?;
+                    members.push((pair.0, score));
+                }
+                RdbValue::Zset(members)
+            }
+            RDB_TYPE_HASH_ZIPLIST | RDB_TYPE_HASH_LISTPACK => {
+                let blob = self.read_string()?;
+                let entries = if object_type == RDB_TYPE_HASH_ZIPLIST {
+                    ziplist_entries(&blob)?
+                } else {
+                    listpack_entries(&blob)?
+                };
+                RdbValue::Hash(pairs(entries)?)
+            }
+            RDB_TYPE_LIST_QUICKLIST | RDB_TYPE_LIST_QUICKLIST_2 => {
+                let nodes = self.read_items_count()?;
+                let mut items = Vec::new();
+                for _ in 0..nodes {
+                    if object_type == RDB_TYPE_LIST_QUICKLIST {
+                        items.extend(ziplist_entries(&self.read_string()?)?);
+                        continue;
+                    }
+                    match self.read_length()? {
+                        QUICKLIST_NODE_PLAIN => items.push(self.read_string()?),
+                        QUICKLIST_NODE_PACKED => {
+                            items.extend(listpack_entries(&self.read_string()?)?)
+                        }
+                        _ => return Err(RdbError::BadFormat),
+                    }
+                }
+                RdbValue::List(items)
+            }
+            other => return Err(RdbError::UnsupportedType(other)),
+        };
+
+        let is_empty = match &value {
+            RdbValue::String(_) => false,
+            RdbValue::List(items) | RdbValue::Set(items) => items.is_empty(),
+            RdbValue::Zset(members) => members.is_empty(),
+            RdbValue::Hash(fields) => fields.is_empty(),
+            RdbValue::HashWithExpire(fields) => fields.is_empty(),
+            // a stream with no entries is valid (e.g. all its entries were deleted)
+            RdbValue::Stream(_) => false,
+        };
+        if is_empty {
+            return Err(RdbError::BadFormat);
+        }
+        Ok(value)
+    }
+}
+
+/// Group the entries of a ziplist / listpack into pairs (e.g. hash field and value)
+fn pairs(entries: Vec<BytesMut>) -> Result<Vec<(BytesMut, BytesMut)>, RdbError> {
+    if entries.len() % 2 == 1 {
+        return Err(RdbError::BadFormat);
+    }
+    let mut result = Vec::with_capacity(entries.len() / 2);
+    let mut entries = entries.into_iter();
+    while let (Some(first), Some(second)) = (entries.next(), entries.next()) {
+        result.push((first, second));
+    }
+    Ok(result)
+}
+
+/// Decode an intset: `<encoding (u32)><length (u32)><integers>`, all little endian
+fn intset_entries(blob: &[u8]) -> Result<Vec<BytesMut>, RdbError> {
+    let encoding = read_int_le(blob, 0, 4)? as usize;
+    let len = read_int_le(blob, 4, 4)? as usize;
+    if !matches!(encoding, 2 | 4 | 8) || blob.len() != 8 + encoding * len {
+        return Err(RdbError::BadFormat);
+    }
+    (0..len)
+        .map(|i| Ok(int_to_bytes(read_int_le(blob, 8 + i * encoding, encoding)?)))
+        .collect()
+}
+
+/// Decode a ziplist: `<total bytes (u32)><tail offset (u32)><length (u16)><entries><0xFF>`
+fn ziplist_entries(blob: &[u8]) -> Result<Vec<BytesMut>, RdbError> {
+    let mut entries = Vec::new();
+    let mut pos = 10usize;
+    loop {
+        // previous entry length: 1 byte, or 0xFE followed by 4 bytes
+        match *blob.get(pos).ok_or(RdbError::BadFormat)? {
+            0xFF => break,
+            0xFE => pos += 5,
+            _ => pos += 1,
+        }
+
+        let encoding = *blob.get(pos).ok_or(RdbError::BadFormat)?;
+        let (entry, entry_len) = match encoding >> 6 {
+            0 => {
+                let len = (encoding & 0x3f) as usize;
+                (BytesMut::from(slice_at(blob, pos + 1, len)?), 1 + len)
+            }
+            1 => {
+                let len = (((encoding & 0x3f) as usize) << 8)
+                    | *blob.get(pos + 1).ok_or(RdbError::BadFormat)? as usize;
+                (BytesMut::from(slice_at(blob, pos + 2, len)?), 2 + len)
+            }
+            2 => {
+                let len = u32::from_be_bytes(
+                    slice_at(blob, pos + 1, 4)?
+                        .try_into()
+                        .map_err(|_| RdbError::BadFormat)?,
+                ) as usize;
+                (BytesMut::from(slice_at(blob, pos + 5, len)?), 5 + len)
+            }
+            _ => {
+                let int_len = match encoding {
+                    0xC0 => 2,
+                    0xD0 => 4,
+                    0xE0 => 8,
+                    0xF0 => 3,
+                    0xFE => 1,
+                    0xF1..=0xFD => 0,
+                    _ => return Err(RdbError::BadFormat),
+                };
+                let value = if int_len == 0 {
+                    // 4 bits immediate value, between 0 and 12
+                    (encoding & 0x0f) as i64 - 1
+                } else {
+                    read_int_le(blob, pos + 1, int_len)?
+                };
+                (int_to_bytes(value), 1 + int_len)
+            }
+        };
+        entries.push(entry);
+        pos += entry_len;
+    }
+    Ok(entries)
+}
+
+/// Decode a listpack: `<total bytes (u32)><length (u16)><entries><0xFF>`. Every entry is followed
+/// by its length (encoding + data), encoded using 1 to 5 bytes
+fn listpack_entries(blob: &[u8]) -> Result<Vec<BytesMut>, RdbError> {
+    let mut entries = Vec::new();
+    let mut pos = 6usize;
+    loop {
+        let encoding = *blob.get(pos).ok_or(RdbError::BadFormat)?;
+        if encoding == 0xFF {
+            break;
+        }
+
+        let (entry, entry_len) = if encoding & 0x80 == 0 {
+            // 7 bits unsigned integer
+            (int_to_bytes((encoding & 0x7f) as i64), 1)
+        } else if encoding & 0xC0 == 0x80 {
+            // 6 bits string length
+            let len = (encoding & 0x3f) as usize;
+            (BytesMut::from(slice_at(blob, pos + 1, len)?), 1 + len)
+        } else if encoding & 0xE0 == 0xC0 {
+            // 13 bits signed integer
+            let value = (((encoding & 0x1f) as i64) << 8)
+                | *blob.get(pos + 1).ok_or(RdbError::BadFormat)? as i64;
+            let value = if value >= 1 << 12 {
+                value - (1 << 13)
+            } else {
+                value
+            };
+            (int_to_bytes(value), 2)
+        } else if encoding & 0xF0 == 0xE0 {
+            // 12 bits string length
+            let len = (((encoding & 0x0f) as usize) << 8)
+                | *blob.get(pos + 1).ok_or(RdbError::BadFormat)? as usize;
+            (BytesMut::from(slice_at(blob, pos + 2, len)?), 2 + len)
+        } else {
+            match encoding {
+                0xF0 => {
+                    let len = read_int_le(blob, pos + 1, 4)? as u32 as usize;
+                    (BytesMut::from(slice_at(blob, pos + 5, len)?), 5 + len)
+                }
+                0xF1..=0xF4 => {
+                    let int_len = match encoding {
+                        0xF1 => 2,
+                        0xF2 => 3,
+                        0xF3 => 4,
+                        _ => 8,
+                    };
+                    (
+                        int_to_bytes(read_int_le(blob, pos + 1, int_len)?),
+                        1 + int_len,
+                    )
+                }
+                _ => return Err(RdbError::BadFormat),
+            }
+        };
+
+        entries.push(entry);
+        pos += entry_len + backlen_size(entry_len);
+    }
+    Ok(entries)
+}
+
+/// The number of bytes used to encode the length of a listpack entry
+fn backlen_size(entry_len: usize) -> usize {
+    match entry_len {
+        0..=127 => 1,
+        128..=16382 => 2,
+        16383..=2097150 => 3,
+        2097151..=268435454 => 4,
+        _ => 5,
+    }
+}
+
+/// Build a listpack. Integers use the smallest integer encoding that can hold them
+#[derive(Default)]
+struct ListpackWriter {
+    elements: BytesMut,
+    count: usize,
+}
+
+impl ListpackWriter {
+    fn push_int(&mut self, value: i64) {
+        let mut element = Vec::with_capacity(9);
+        if (0..=127).contains(&value) {
+            element.push(value as u8);
+        } else if (-4096..=4095).contains(&value) {
+            let value = (value as u16) & 0x1fff;
+            element.push(0xC0 | (value >> 8) as u8);
+            element.push(value as u8);
+        } else if let Ok(value) = i16::try_from(value) {
+            element.push(0xF1);
+            element.extend_from_slice(&value.to_le_bytes());
+        } else if let Ok(value) = i32::try_from(value) {
+            element.push(0xF3);
+            element.extend_from_slice(&value.to_le_bytes());
+        } else {
+            element.push(0xF4);
+            element.extend_from_slice(&value.to_le_bytes());
+        }
+        self.push_element(&element);
+    }
+
+    fn push_string(&mut self, value: &[u8]) {
+        let mut element = Vec::with_capacity(value.len() + 5);
+        if value.len() < 1 << 6 {
+            element.push(0x80 | value.len() as u8);
+        } else if value.len() < 1 << 12 {
+            element.push(0xE0 | (value.len() >> 8) as u8);
+            element.push(value.len() as u8);
+        } else {
+            element.push(0xF0);
+            element.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        }
+        element.extend_from_slice(value);
+        self.push_element(&element);
+    }
+
+    /// Append the element followed by its length (7 bits per byte, most significant first, all
+    /// the bytes but the first have their high bit set)
+    fn push_element(&mut self, element: &[u8]) {
+        self.elements.put_slice(element);
+        let len = element.len();
+        let size = backlen_size(len);
+        for i in (0..size).rev() {
+            let byte = ((len >> (7 * i)) & 0x7f) as u8;
+            self.elements
+                .put_u8(if i == size - 1 { byte } else { byte | 0x80 });
+        }
+        self.count += 1;
+    }
+
+    /// Return the listpack: `<total bytes (u32)><length (u16)><entries><0xFF>`
+    fn finish(self) -> BytesMut {
+        let mut listpack = BytesMut::with_capacity(self.elements.len() + 7);
+        listpack.put_u32_le((self.elements.len() + 7) as u32);
+        // the length saturates, readers count the entries instead
+        listpack.put_u16_le(self.count.min(u16::MAX as usize) as u16);
+        listpack.put_slice(&self.elements);
+        listpack.put_u8(0xFF);
+        listpack
+    }
+}
+
+/// Decompress LZF `data` into a buffer of `len` bytes
+fn lzf_decompress(data: &[u8], len: usize) -> Result<BytesMut, RdbError> {
+    let mut output = Vec::<u8>::with_capacity(len.min(data.len().saturating_mul(256)));
+    let mut pos = 0usize;
+    while pos < data.len() {
+        let ctrl = data[pos] as usize;
+        pos += 1;
+        if ctrl < 1 << 5 {
+            // literal run of ctrl + 1 bytes
+            output.extend_from_slice(slice_at(data, pos, ctrl + 1)?);
+            pos += ctrl + 1;
+        } else {
+            // back reference
+            let mut ref_len = ctrl >> 5;
+            if ref_len == 7 {
+                ref_len += *data.get(pos).ok_or(RdbError::BadFormat)? as usize;
+                pos += 1;
+            }
+            let offset = ((ctrl & 0x1f) << 8) + *data.get(pos).ok_or(RdbError::BadFormat)? as usize;
+            pos += 1;
+            let start = output
+                .len()
+                .checked_sub(offset + 1)
+                .ok_or(RdbError::BadFormat)?;
+            // the reference may overlap with the bytes being written
+            for i in start..start + ref_len + 2 {
+                output.push(output[i]);
+            }
+        }
+
+        if output.len() > len {
+            return Err(RdbError::BadFormat);
+        }
+    }
+
+    if output.len() != len {
+        return Err(RdbError::BadFormat);
+    }
+    Ok(BytesMut::from(output.as_slice()))
+}
+
+//  _    _ _   _ _____ _______      _______ ______  _____ _______ _____ _   _  _____
+// | |  | | \ | |_   _|__   __|    |__   __|  ____|/ ____|__   __|_   _| \ | |/ ____|
+// | |  | |  \| | | |    | |    _     | |  | |__  | (___    | |    | | |  \| | |  __|
+// | |  | | . ` | | |    | |   / \    | |  |  __|  \___ \   | |    | | | . ` | | |_ |
+// | |__| | |\  |_| |_   | |   \_/    | |  | |____ ____) |  | |   _| |_| |\  | |__| |
+//  \____/|_| \_|_____|  |_|          |_|  |______|_____/   |_|  |_____|_| \_|\_____|
+//
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a payload from an encoded object, as written by Valkey
+    fn payload_of(object: &[u8]) -> BytesMut {
+        let mut payload = BytesMut::from(object);
+        payload.put_u16_le(11);
+        let crc = crc64(0, &payload);
+        payload.put_u64_le(crc);
+        payload
+    }
+
+    fn test_stream() -> RdbStream {
+        let mut entries = Vec::new();
+        for i in 0..250u64 {
+            let mut fields = vec![(BytesMut::from("name"), int_to_bytes(i as i64 - 100))];
+            if i % 7 == 0 {
+                fields.push((
+                    BytesMut::from("extra"),
+                    BytesMut::from(vec![b'x'; 5000].as_slice()),
+                ));
+            }
+            entries.push(RdbStreamEntry {
+                id: StreamId::new(1_700_000_000_000 + i / 3, i % 3),
+                fields,
+            });
+        }
+        RdbStream {
+            last_id: StreamId::new(1_800_000_000_000, 5),
+            max_deleted_id: StreamId::new(1_800_000_000_000, 5),
+            entries_added: 300,
+            groups: vec![
+                RdbStreamGroup {
+                    name: BytesMut::from("g1"),
+                    last_delivered_id: entries[10].id,
+                    entries_read: 11,
+                    pending: vec![
+                        RdbStreamPending {
+                            id: entries[3].id,
+                            consumer: BytesMut::from("alice"),
+                            delivery_time: 1_700_000_000_123,
+                            delivery_count: 2,
+                        },
+                        RdbStreamPending {
+                            id: entries[4].id,
+                            consumer: BytesMut::from("bob"),
+                            delivery_time: 1_700_000_000_456,
+                            delivery_count: 1,
+                        },
+                    ],
+                    consumers: vec![
+                        RdbStreamConsumer {
+                            name: BytesMut::from("alice"),
+                            seen_time: 1_700_000_000_200,
+                            active_time: 1_700_000_000_123,
+                        },
+                        RdbStreamConsumer {
+                            name: BytesMut::from("bob"),
+                            seen_time: 1_700_000_000_456,
+                            active_time: 1_700_000_000_456,
+                        },
+                    ],
+                },
+                RdbStreamGroup {
+                    name: BytesMut::from("g2"),
+                    ..Default::default()
+                },
+            ],
+            entries,
+        }
+    }
+
+    #[test]
+    fn test_crc64() {
+        assert_eq!(crc64(0, b"123456789"), 0xe9c6_d914_c4b8_d9ca);
+    }
+
+    #[test]
+    fn test_dump_payload_round_trip() {
+        let values = vec![
+            RdbValue::String(BytesMut::from("value")),
+            RdbValue::String(BytesMut::from(vec![b'x'; 20_000].as_slice())),
+            RdbValue::List(vec![BytesMut::from("a"), BytesMut::from("b")]),
+            RdbValue::Set(vec![BytesMut::from("a")]),
+            RdbValue::Zset(vec![
+                (BytesMut::from("a"), 1.5),
+                (BytesMut::from("b"), f64::INFINITY),
+            ]),
+            RdbValue::Hash(vec![(BytesMut::from("field"), BytesMut::from("value"))]),
+            RdbValue::HashWithExpire(vec![
+                (BytesMut::from("a"), BytesMut::from("1"), None),
+                (
+                    BytesMut::from("b"),
+                    BytesMut::from("2"),
+                    Some(1_700_000_000_000),
+                ),
+                (
+                    BytesMut::from("c"),
+                    BytesMut::from("3"),
+                    Some(1_700_000_050_000),
+                ),
+            ]),
+            RdbValue::Stream(RdbStream::default()),
+            RdbValue::Stream(test_stream()),
+        ];
+        for value in values {
+            let payload = value.to_dump_payload();
+            assert_eq!(RdbValue::from_dump_payload(&payload), Ok(value));
+        }
+    }
+
+    #[test]
+    fn test_valkey_payloads() {
+        // `SET mykey 10` + `DUMP mykey`
+        let payload = b"\x00\xc0\x0a\x09\x00\xbe\x6d\x06\x89\x5a\x28\x00\x0a";
+        assert_eq!(
+            RdbValue::from_dump_payload(payload),
+            Ok(RdbValue::String(BytesMut::from("10")))
+        );
+
+        // LZF compressed string: a literal run ("abc") followed by a back reference (6 bytes)
+        let payload = payload_of(b"\x00\xc3\x06\x09\x02abc\x80\x02");
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload),
+            Ok(RdbValue::String(BytesMut::from("abcabcabc")))
+        );
+
+        // Hash listpack: {"f": "v", "n": -2}
+        let listpack = b"\x11\x00\x00\x00\x04\x00\x81f\x02\x81v\x02\x81n\x02\xdf\xfe\x02\xff";
+        let mut object = vec![RDB_TYPE_HASH_LISTPACK, listpack.len() as u8];
+        object.extend_from_slice(listpack);
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload_of(&object)),
+            Ok(RdbValue::Hash(vec![
+                (BytesMut::from("f"), BytesMut::from("v")),
+                (BytesMut::from("n"), BytesMut::from("-2")),
+            ]))
+        );
+
+        // Set intset (16 bits integers): {1, 300}
+        let intset = b"\x02\x00\x00\x00\x02\x00\x00\x00\x01\x00\x2c\x01";
+        let mut object = vec![RDB_TYPE_SET_INTSET, intset.len() as u8];
+        object.extend_from_slice(intset);
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload_of(&object)),
+            Ok(RdbValue::Set(vec![
+                BytesMut::from("1"),
+                BytesMut::from("300")
+            ]))
+        );
+
+        // Zset ziplist: {"a": 1, "b": 2.5}
+        let ziplist =
+            b"\x00\x00\x00\x00\x00\x00\x00\x00\x04\x00\x00\x01a\x03\xf2\x02\x01b\x03\x032.5\xff";
+        let mut object = vec![RDB_TYPE_ZSET_ZIPLIST, ziplist.len() as u8];
+        object.extend_from_slice(ziplist);
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload_of(&object)),
+            Ok(RdbValue::Zset(vec![
+                (BytesMut::from("a"), 1.0),
+                (BytesMut::from("b"), 2.5)
+            ]))
+        );
+
+        // Quicklist 2: a packed node (listpack ["x", 7]) and a plain node ("y")
+        let listpack = b"\x0c\x00\x00\x00\x02\x00\x81x\x02\x07\x01\xff";
+        let mut object = vec![RDB_TYPE_LIST_QUICKLIST_2, 2, 2, listpack.len() as u8];
+        object.extend_from_slice(listpack);
+        object.extend_from_slice(b"\x01\x01y");
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload_of(&object)),
+            Ok(RdbValue::List(vec![
+                BytesMut::from("x"),
+                BytesMut::from("7"),
+                BytesMut::from("y")
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_listpack_writer() {
+        let mut listpack = ListpackWriter::default();
+        let ints = [
+            0i64,
+            127,
+            128,
+            -1,
+            -4096,
+            4095,
+            4096,
+            -32768,
+            40_000,
+            i64::MIN,
+            i64::MAX,
+        ];
+        for value in ints {
+            listpack.push_int(value);
+        }
+        let strings = [0usize, 63, 64, 4095, 4096, 20_000].map(|len| vec![b's'; len]);
+        for value in &strings {
+            listpack.push_string(value);
+        }
+
+        let mut expected: Vec<BytesMut> = ints.iter().map(|value| int_to_bytes(*value)).collect();
+        expected.extend(strings.iter().map(|value| BytesMut::from(value.as_slice())));
+        let listpack = listpack.finish();
+        assert_eq!(read_int_le(&listpack, 0, 4), Ok(listpack.len() as i64));
+        assert_eq!(listpack_entries(&listpack), Ok(expected));
+    }
+
+    #[test]
+    fn test_invalid_payloads() {
+        let mut payload = RdbValue::String(BytesMut::from("value")).to_dump_payload();
+        let last = payload.len() - 1;
+        payload[last] ^= 0xff;
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload),
+            Err(RdbError::VersionOrChecksum)
+        );
+        assert_eq!(
+            RdbValue::from_dump_payload(b"\x00"),
+            Err(RdbError::VersionOrChecksum)
+        );
+
+        // version too high
+        let mut payload = BytesMut::from(&b"\x00\x01a"[..]);
+        payload.put_u16_le(RDB_VERSION_MAX + 1);
+        let crc = crc64(0, &payload);
+        payload.put_u64_le(crc);
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload),
+            Err(RdbError::VersionOrChecksum)
+        );
+
+        // module values are not supported
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload_of(b"\x07\x00")),
+            Err(RdbError::UnsupportedType(7))
+        );
+        // a pending entry that is not owned by any consumer
+        let mut object = vec![RDB_TYPE_STREAM_LISTPACKS_3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        object.extend_from_slice(b"\x01g\x00\x00\x00\x01");
+        object.extend_from_slice(&[0u8; 16]);
+        object.extend_from_slice(&[0u8; 8]);
+        object.extend_from_slice(b"\x01\x00");
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload_of(&object)),
+            Err(RdbError::BadFormat)
+        );
+        // empty collections and trailing bytes
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload_of(b"\x01\x00")),
+            Err(RdbError::BadFormat)
+        );
+        assert_eq!(
+            RdbValue::from_dump_payload(&payload_of(b"\x00\x01ab")),
+            Err(RdbError::BadFormat)
+        );
+    }
+}
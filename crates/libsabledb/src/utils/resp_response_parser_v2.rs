@@ -16,11 +16,11 @@ pub enum ValkeyObject {
     Array(Vec<ValkeyObject>),
     NullArray,
     NullString,
-    Integer(u64),
+    Integer(i64),
 }
 
 impl ValkeyObject {
-    pub fn integer(&self) -> Result<u64, SableError> {
+    pub fn integer(&self) -> Result<i64, SableError> {
         match self {
             Self::Integer(num) => Ok(*num),
             other => Err(SableError::OtherError(
@@ -122,7 +122,7 @@ impl RespResponseParserV2 {
                 };
                 consume = consume.saturating_add(crlf_pos + 2);
                 let num = BytesMut::from(&buffer[..crlf_pos]);
-                let Some(num) = BytesMutUtils::parse::<u64>(&num) else {
+                let Some(num) = BytesMutUtils::parse::<i64>(&num) else {
                     return Err(SableError::OtherError(format!(
                         "failed to parse number: `{:?}`",
                         num
@@ -151,9 +151,19 @@ impl RespResponseParserV2 {
                     )));
                 };
 
-                if strlen <= 0 {
-                    // Null or empty string
+                if strlen < 0 {
+                    // Null string
                     Ok(ResponseParseResult::Ok((consume, ValkeyObject::NullString)))
+                } else if strlen == 0 {
+                    // Empty string, skip the terminator
+                    if buffer.len() < crlf_pos + 4 {
+                        return Ok(ResponseParseResult::NeedMoreData);
+                    }
+                    consume = consume.saturating_add(2);
+                    Ok(ResponseParseResult::Ok((
+                        consume,
+                        ValkeyObject::Str(BytesMut::new()),
+                    )))
                 } else {
                     let strlen = strlen as usize;
                     // read the string content
@@ -247,6 +257,8 @@ mod tests {
         ValkeyObject::Error(BytesMut::from("ERR bad thing happened"))
     ; "parse err message")]
     #[test_case(b":42\r\n", ValkeyObject::Integer(42); "parse integer")]
+    #[test_case(b":-2\r\n", ValkeyObject::Integer(-2); "parse negative integer")]
+    #[test_case(b"$0\r\n\r\n", ValkeyObject::Str(BytesMut::new()); "parse empty string")]
     fn test_happy_response_parser(
         buffer: &[u8],
         expected_response: ValkeyObject,
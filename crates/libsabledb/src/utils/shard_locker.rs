@@ -137,6 +137,14 @@ impl LockManager {
         Self::lock_internal_key_shared_unconditionally(user_key).await
     }
 
+    /// Obtain exclusive lock on a user key, without conditions (see
+    /// `lock_user_key_shared_unconditionally` above)
+    pub async fn lock_user_key_exclusive_unconditionally<'a>(
+        user_key: &BytesMut,
+    ) -> Result<ShardLockGuard<'a>, SableError> {
+        Self::lock_multi_slots_exclusive_unconditionally(vec![calculate_slot(user_key)]).await
+    }
+
     /// Lock the entire storage
     pub async fn lock_all_keys_shared<'a>() -> Result<ShardLockGuard<'a>, SableError> {
         let mut read_locks =
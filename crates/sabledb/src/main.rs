@@ -102,6 +102,9 @@ fn main() -> Result<(), SableError> {
         .persistent_state()
         .initialise(options.clone());
 
+    // load the ACL users
+    server_state_clone.acl().initialise(options.clone());
+
     // Now that we loaded the configuration file "NODE", apply any changes coming from the command line
     server_state_clone
         .persistent_state()
@@ -91,7 +91,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let client = redis::Client::open(connection_string.as_str())?;
     let mut conn = client.get_connection()?;
 
-    if let Some(input_file) = args.file {
+    if args.bigkeys || args.hotkeys {
+        keyspace_report(&mut conn, args.bigkeys, args.hotkeys)?;
+    } else if let Some(input_file) = args.file {
         tracing::debug!("Loading file: {input_file}");
         // read the file's content. Each line in the file is considered as a command
         let file_content = fs::read_to_string(input_file)?;
@@ -167,6 +169,116 @@ fn batch_execute(
     Ok(())
 }
 
+/// A key reported by the `keyspace-analysis` section of the `INFO` command
+struct ReportedKey {
+    db: String,
+    value: String,
+    key: String,
+}
+
+impl ReportedKey {
+    /// Parse `db=<db>,<size|freq>=<value>,key=<key>`
+    fn parse(fields: &str) -> Option<Self> {
+        let mut fields = fields.splitn(3, ',');
+        let db = fields.next()?.strip_prefix("db=")?;
+        let (_, value) = fields.next()?.split_once('=')?;
+        let key = fields.next()?.strip_prefix("key=")?;
+        Some(ReportedKey {
+            db: db.to_string(),
+            value: value.to_string(),
+            key: key.to_string(),
+        })
+    }
+}
+
+/// Print the big keys and / or the hot keys found by the server's keyspace analysis
+fn keyspace_report(
+    conn: &mut redis::Connection,
+    bigkeys: bool,
+    hotkeys: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let info: String = redis::cmd("INFO").arg("keyspace-analysis").query(conn)?;
+
+    let mut keys_count = "0";
+    let mut sample_rate = "0";
+    // type name -> the largest keys of that type, largest first
+    let mut big_keys = std::collections::BTreeMap::<&str, Vec<ReportedKey>>::new();
+    let mut hot_keys = Vec::<ReportedKey>::new();
+    for line in info.lines() {
+        let Some((name, fields)) = line.split_once(':') else {
+            continue;
+        };
+        match name {
+            "keys" => keys_count = fields,
+            "key_access_sample_rate" => sample_rate = fields,
+            _ => {
+                if let Some(type_and_rank) = name.strip_prefix("bigkey_") {
+                    let (Some((type_name, _)), Some(key)) =
+                        (type_and_rank.rsplit_once('_'), ReportedKey::parse(fields))
+                    else {
+                        continue;
+                    };
+                    big_keys.entry(type_name).or_default().push(key);
+                } else if name.starts_with("hotkey_") {
+                    if let Some(key) = ReportedKey::parse(fields) {
+                        hot_keys.push(key);
+                    }
+                }
+            }
+        }
+    }
+
+    if bigkeys {
+        println!("# Biggest keys ({} keys scanned)", keys_count);
+        if big_keys.is_empty() {
+            println!("(none)");
+        }
+        for (type_name, keys) in &big_keys {
+            let unit = match *type_name {
+                "string" => "bytes",
+                "hash" => "fields",
+                "set" | "zset" => "members",
+                "stream" => "entries",
+                _ => "items",
+            };
+            println!("{}:", type_name);
+            for (rank, key) in keys.iter().enumerate() {
+                println!(
+                    "{: >4}) \"{}\" (db {}) has {} {}",
+                    rank + 1,
+                    key.key,
+                    key.db,
+                    key.value,
+                    unit
+                );
+            }
+        }
+    }
+
+    if hotkeys {
+        if bigkeys {
+            println!();
+        }
+        println!(
+            "# Most accessed keys (estimated accesses since the previous scan, 1 in {} commands sampled)",
+            sample_rate
+        );
+        if hot_keys.is_empty() {
+            println!("(none)");
+        }
+        for (rank, key) in hot_keys.iter().enumerate() {
+            println!(
+                "{: >4}) \"{}\" (db {}) accessed ~{} times",
+                rank + 1,
+                key.key,
+                key.db,
+                key.value
+            );
+        }
+    }
+    Ok(())
+}
+
 fn print_response_pretty(value: &Value, indent: usize, seq: Option<usize>) {
     match value {
         Value::Nil => {
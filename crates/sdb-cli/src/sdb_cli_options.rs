@@ -18,6 +18,14 @@ pub struct Options {
     #[arg(long, short)]
     pub file: Option<String>,
 
+    /// Print the largest keys of every type, as found by the last keyspace scan of the server
+    #[arg(long, default_value = "false")]
+    pub bigkeys: bool,
+
+    /// Print the most accessed keys, as sampled by the server since its previous keyspace scan
+    #[arg(long, default_value = "false")]
+    pub hotkeys: bool,
+
     #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
     pub parameters: Vec<String>,
 }